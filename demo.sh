@@ -17,8 +17,6 @@ cargo build
 
 echo "================== Compiling stdlib ======================"
 
-# Set Dllimport attribute in xtd source code with value "libxtd.so" or xix cannot find libxtd.so
-
 ./target/debug/xic std/lib.xi -vv -O0 -o target/std/
 
 echo "================== Compiling examples ======================"
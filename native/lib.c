@@ -1,5 +1,6 @@
 ﻿// entrypoint for dll
 
+#include <inttypes.h>
 #include <stdint.h>
 #include <stdio.h>
 #include <string.h>
@@ -58,6 +59,50 @@ _declspec(dllexport)
         int32_t v0;
         NEXT_ARG(int32_t, v0); 
         fprintf(stdout, "%d", v0);
+    } else if (strcmp(fname, "puti8") == 0) {
+        int8_t v0;
+        NEXT_ARG(int8_t, v0);
+        fprintf(stdout, "%" PRId8, v0);
+    } else if (strcmp(fname, "putu8") == 0) {
+        uint8_t v0;
+        NEXT_ARG(uint8_t, v0);
+        fprintf(stdout, "%" PRIu8, v0);
+    } else if (strcmp(fname, "puti16") == 0) {
+        int16_t v0;
+        NEXT_ARG(int16_t, v0);
+        fprintf(stdout, "%" PRId16, v0);
+    } else if (strcmp(fname, "putu16") == 0) {
+        uint16_t v0;
+        NEXT_ARG(uint16_t, v0);
+        fprintf(stdout, "%" PRIu16, v0);
+    } else if (strcmp(fname, "putu32") == 0) {
+        uint32_t v0;
+        NEXT_ARG(uint32_t, v0);
+        fprintf(stdout, "%" PRIu32, v0);
+    } else if (strcmp(fname, "puti64") == 0) {
+        int64_t v0;
+        NEXT_ARG(int64_t, v0);
+        fprintf(stdout, "%" PRId64, v0);
+    } else if (strcmp(fname, "putu64") == 0) {
+        uint64_t v0;
+        NEXT_ARG(uint64_t, v0);
+        fprintf(stdout, "%" PRIu64, v0);
+    } else if (strcmp(fname, "putisize") == 0) {
+        intptr_t v0;
+        NEXT_ARG(intptr_t, v0);
+        fprintf(stdout, "%" PRIdPTR, v0);
+    } else if (strcmp(fname, "putusize") == 0) {
+        uintptr_t v0;
+        NEXT_ARG(uintptr_t, v0);
+        fprintf(stdout, "%" PRIuPTR, v0);
+    } else if (strcmp(fname, "putf32") == 0) {
+        float v0;
+        NEXT_ARG(float, v0);
+        fprintf(stdout, "%g", v0);
+    } else if (strcmp(fname, "putf64") == 0) {
+        double v0;
+        NEXT_ARG(double, v0);
+        fprintf(stdout, "%g", v0);
     } else {
         return NoFunc;
     }
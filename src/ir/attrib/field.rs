@@ -4,11 +4,14 @@ use std::fmt;
 const FIELD_ATTRIB_ACC_MASK: u16 = 0x0007;
 
 const FIELD_ATTRIB_PRIV_FLAG: u16 = 0x0001;
+const FIELD_ATTRIB_CRATE_FLAG: u16 = 0x0003;
 const FIELD_ATTRIB_PUB_FLAG: u16 = 0x0006;
 const FIELD_ATTRIB_STATIC_FLAG: u16 = 0x0010;
 
 pub enum FieldAttribFlag {
     Priv,
+    /// accessible within the declaring crate
+    Crate,
     Pub,
     Static,
 }
@@ -19,6 +22,7 @@ impl TryFrom<u16> for FieldAttribFlag {
     fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
             FIELD_ATTRIB_PRIV_FLAG => Ok(Self::Priv),
+            FIELD_ATTRIB_CRATE_FLAG => Ok(Self::Crate),
             FIELD_ATTRIB_PUB_FLAG => Ok(Self::Pub),
             FIELD_ATTRIB_STATIC_FLAG => Ok(Self::Static),
             _ => Err("Invalid value for MethodFlagTag"),
@@ -30,6 +34,7 @@ impl From<FieldAttribFlag> for u16 {
     fn from(value: FieldAttribFlag) -> Self {
         match value {
             FieldAttribFlag::Priv => FIELD_ATTRIB_PRIV_FLAG,
+            FieldAttribFlag::Crate => FIELD_ATTRIB_CRATE_FLAG,
             FieldAttribFlag::Pub => FIELD_ATTRIB_PUB_FLAG,
             FieldAttribFlag::Static => FIELD_ATTRIB_STATIC_FLAG,
         }
@@ -48,7 +53,7 @@ impl FieldAttrib {
 
     pub fn set(&mut self, flag: FieldAttribFlag) {
         match flag {
-            FieldAttribFlag::Pub | FieldAttribFlag::Priv => {
+            FieldAttribFlag::Pub | FieldAttribFlag::Crate | FieldAttribFlag::Priv => {
                 self.attrib = (self.attrib & !FIELD_ATTRIB_ACC_MASK) | u16::from(flag);
             }
             _ => self.attrib |= u16::from(flag),
@@ -56,7 +61,12 @@ impl FieldAttrib {
     }
 
     pub fn is(&self, flag: FieldAttribFlag) -> bool {
-        self.attrib & u16::from(flag) != 0
+        match flag {
+            FieldAttribFlag::Pub | FieldAttribFlag::Crate | FieldAttribFlag::Priv => {
+                (self.attrib & FIELD_ATTRIB_ACC_MASK) == u16::from(flag)
+            }
+            _ => (self.attrib & u16::from(flag)) != 0,
+        }
     }
 }
 
@@ -64,6 +74,7 @@ impl fmt::Display for FieldAttrib {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.attrib & FIELD_ATTRIB_ACC_MASK {
             FIELD_ATTRIB_PRIV_FLAG => write!(f, "priv")?,
+            FIELD_ATTRIB_CRATE_FLAG => write!(f, "crate")?,
             FIELD_ATTRIB_PUB_FLAG => write!(f, "pub")?,
             _ => unreachable!(),
         }
@@ -4,21 +4,27 @@ use std::fmt;
 const METHOD_ATTRIB_ACC_MASK: u16 = 0x0007;
 
 const METHOD_ATTRIB_PRIV_FLAG: u16 = 0x0001;
+const METHOD_ATTRIB_CRATE_FLAG: u16 = 0x0003;
 const METHOD_ATTRIB_PUB_FLAG: u16 = 0x0006;
 const METHOD_ATTRIB_STATIC_FLAG: u16 = 0x0010;
 const METHOD_ATTRIB_VIRTUAL_FLAG: u16 = 0x0040;
 const METHOD_ATTRIB_NEWSLOT_FLAG: u16 = 0x0100;
+const METHOD_ATTRIB_ABSTRACT_FLAG: u16 = 0x0400;
 const METHOD_ATTRIB_SPECIAL_NAME_FLAG: u16 = 0x0800;
 const METHOD_ATTRIB_PINVOKEIMPL_FLAG: u16 = 0x2000;
 const METHOD_ATTRIB_RT_SPECIAL_NAME_FLAG: u16 = 0x1000;
 
 pub enum MethodAttribFlag {
     Priv,
+    /// accessible within the declaring crate
+    Crate,
     Pub,
     Static,
     Virtual,
     /// for ReuseSlot, just unset NewSlot
     NewSlot,
+    /// method has no implementation, e.g. method of interface
+    Abstract,
     SpecialName,
     PInvokeImpl,
     RTSpecialName,
@@ -30,6 +36,7 @@ impl TryFrom<u16> for MethodAttribFlag {
     fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
             METHOD_ATTRIB_PRIV_FLAG => Ok(Self::Priv),
+            METHOD_ATTRIB_CRATE_FLAG => Ok(Self::Crate),
             METHOD_ATTRIB_PUB_FLAG => Ok(Self::Pub),
             METHOD_ATTRIB_STATIC_FLAG => Ok(Self::Static),
             METHOD_ATTRIB_PINVOKEIMPL_FLAG => Ok(Self::PInvokeImpl),
@@ -37,6 +44,7 @@ impl TryFrom<u16> for MethodAttribFlag {
             METHOD_ATTRIB_VIRTUAL_FLAG => Ok(Self::Virtual),
             METHOD_ATTRIB_SPECIAL_NAME_FLAG => Ok(Self::SpecialName),
             METHOD_ATTRIB_NEWSLOT_FLAG => Ok(Self::NewSlot),
+            METHOD_ATTRIB_ABSTRACT_FLAG => Ok(Self::Abstract),
             _ => Err("Invalid value for MethodFlagTag"),
         }
     }
@@ -46,6 +54,7 @@ impl From<MethodAttribFlag> for u16 {
     fn from(value: MethodAttribFlag) -> Self {
         match value {
             MethodAttribFlag::Priv => METHOD_ATTRIB_PRIV_FLAG,
+            MethodAttribFlag::Crate => METHOD_ATTRIB_CRATE_FLAG,
             MethodAttribFlag::Pub => METHOD_ATTRIB_PUB_FLAG,
             MethodAttribFlag::Static => METHOD_ATTRIB_STATIC_FLAG,
             MethodAttribFlag::PInvokeImpl => METHOD_ATTRIB_PINVOKEIMPL_FLAG,
@@ -53,6 +62,7 @@ impl From<MethodAttribFlag> for u16 {
             MethodAttribFlag::Virtual => METHOD_ATTRIB_VIRTUAL_FLAG,
             MethodAttribFlag::SpecialName => METHOD_ATTRIB_SPECIAL_NAME_FLAG,
             MethodAttribFlag::NewSlot => METHOD_ATTRIB_NEWSLOT_FLAG,
+            MethodAttribFlag::Abstract => METHOD_ATTRIB_ABSTRACT_FLAG,
         }
     }
 }
@@ -69,7 +79,7 @@ impl MethodAttrib {
 
     pub fn set(&mut self, flag: MethodAttribFlag) {
         match flag {
-            MethodAttribFlag::Pub | MethodAttribFlag::Priv => {
+            MethodAttribFlag::Pub | MethodAttribFlag::Crate | MethodAttribFlag::Priv => {
                 self.attrib = (self.attrib & !METHOD_ATTRIB_ACC_MASK) | u16::from(flag);
             }
             _ => self.attrib |= u16::from(flag),
@@ -78,7 +88,7 @@ impl MethodAttrib {
 
     pub fn is(&self, flag: MethodAttribFlag) -> bool {
         match flag {
-            MethodAttribFlag::Pub | MethodAttribFlag::Priv => {
+            MethodAttribFlag::Pub | MethodAttribFlag::Crate | MethodAttribFlag::Priv => {
                 (self.attrib & METHOD_ATTRIB_ACC_MASK) == u16::from(flag)
             }
             _ => (self.attrib & u16::from(flag)) != 0,
@@ -90,6 +100,7 @@ impl fmt::Display for MethodAttrib {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.attrib & METHOD_ATTRIB_ACC_MASK {
             METHOD_ATTRIB_PRIV_FLAG => write!(f, "priv")?,
+            METHOD_ATTRIB_CRATE_FLAG => write!(f, "crate")?,
             METHOD_ATTRIB_PUB_FLAG => write!(f, "pub")?,
             _ => unreachable!(),
         }
@@ -106,6 +117,10 @@ impl fmt::Display for MethodAttrib {
             write!(f, " newslot")?;
         }
 
+        if self.is(MethodAttribFlag::Abstract) {
+            write!(f, " abstract")?;
+        }
+
         if self.is(MethodAttribFlag::SpecialName) {
             write!(f, " specialname")?;
         }
@@ -274,6 +289,12 @@ impl fmt::Display for MethodImplAttrib {
             MethodImplAttribCodeTypeFlag::IL => write!(f, "cil"),
             MethodImplAttribCodeTypeFlag::Native => write!(f, "native"),
             MethodImplAttribCodeTypeFlag::Runtime => write!(f, "runtime"),
+        }?;
+
+        if self.is_impl_info(MethodImplInfoFlag::InternalCall) {
+            write!(f, " internalcall")?;
         }
+
+        Ok(())
     }
 }
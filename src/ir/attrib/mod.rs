@@ -7,6 +7,7 @@ mod pinvoke;
 mod ty;
 
 pub use field::{FieldAttrib, FieldAttribFlag};
+pub use generic::{GenericParamAttrib, GenericParamVariance};
 pub use local::LocalAttrib;
 pub use method::{
     MethodAttrib, MethodAttribFlag, MethodImplAttrib, MethodImplAttribCodeTypeFlag,
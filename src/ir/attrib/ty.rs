@@ -4,6 +4,7 @@ use std::fmt;
 const TYPE_ATTRIB_VIS_MASK: u32 = 0x00000007;
 const TYPE_ATTRIB_PRIV_FLAG: u32 = 0x000000000;
 const TYPE_ATTRIB_PUB_FLAG: u32 = 0x000000001;
+const TYPE_ATTRIB_CRATE_FLAG: u32 = 0x000000002;
 
 const TYPE_ATTRIB_SEM_MASK: u32 = 0x00000020;
 const TYPE_ATTRIB_CLASS_FLAG: u32 = 0x00000000;
@@ -11,10 +12,15 @@ const TYPE_ATTRIB_INTERFACE_FLAG: u32 = 0x00000020;
 
 const TYPE_ATTRIB_ABSTRACT_FLAG: u32 = 0x00000080;
 const TYPE_ATTRIB_SEALED_FLAG: u32 = 0x00000100;
+const TYPE_ATTRIB_ENUM_FLAG: u32 = 0x00000200;
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum TypeAttribVisFlag {
+    /// visible within the declaring module and its sub-modules
     Priv,
     Pub,
+    /// visible within the declaring crate
+    Crate,
 }
 
 impl TryFrom<u32> for TypeAttribVisFlag {
@@ -24,6 +30,7 @@ impl TryFrom<u32> for TypeAttribVisFlag {
         match value {
             TYPE_ATTRIB_PRIV_FLAG => Ok(Self::Priv),
             TYPE_ATTRIB_PUB_FLAG => Ok(Self::Pub),
+            TYPE_ATTRIB_CRATE_FLAG => Ok(Self::Crate),
             _ => Err("Invalid value for TypeAttribVisFlag"),
         }
     }
@@ -34,6 +41,17 @@ impl From<TypeAttribVisFlag> for u32 {
         match value {
             TypeAttribVisFlag::Priv => TYPE_ATTRIB_PRIV_FLAG,
             TypeAttribVisFlag::Pub => TYPE_ATTRIB_PUB_FLAG,
+            TypeAttribVisFlag::Crate => TYPE_ATTRIB_CRATE_FLAG,
+        }
+    }
+}
+
+impl fmt::Display for TypeAttribVisFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Priv => write!(f, "priv"),
+            Self::Pub => write!(f, "pub"),
+            Self::Crate => write!(f, "crate"),
         }
     }
 }
@@ -67,6 +85,8 @@ impl From<TypeAttribSemFlag> for u32 {
 pub enum TypeAttribFlag {
     Abstract,
     Sealed,
+    /// A value type with a discriminant and the payloads of its variants
+    Enum,
 }
 
 impl TryFrom<u32> for TypeAttribFlag {
@@ -76,6 +96,7 @@ impl TryFrom<u32> for TypeAttribFlag {
         match value {
             TYPE_ATTRIB_ABSTRACT_FLAG => Ok(Self::Abstract),
             TYPE_ATTRIB_SEALED_FLAG => Ok(Self::Sealed),
+            TYPE_ATTRIB_ENUM_FLAG => Ok(Self::Enum),
             _ => Err("Invalid value for TypeAttribFlag"),
         }
     }
@@ -86,6 +107,7 @@ impl From<TypeAttribFlag> for u32 {
         match value {
             TypeAttribFlag::Abstract => TYPE_ATTRIB_ABSTRACT_FLAG,
             TypeAttribFlag::Sealed => TYPE_ATTRIB_SEALED_FLAG,
+            TypeAttribFlag::Enum => TYPE_ATTRIB_ENUM_FLAG,
         }
     }
 }
@@ -118,16 +140,20 @@ impl TypeAttrib {
         self.attrib = (self.attrib & !TYPE_ATTRIB_VIS_MASK) | u32::from(flag);
     }
 
+    pub fn vis(&self) -> TypeAttribVisFlag {
+        TypeAttribVisFlag::try_from(self.attrib & TYPE_ATTRIB_VIS_MASK).unwrap()
+    }
+
     pub fn set_sem(&mut self, flag: TypeAttribSemFlag) {
         self.attrib = (self.attrib & !TYPE_ATTRIB_SEM_MASK) | u32::from(flag);
     }
 
     pub fn is_vis(&self, flag: TypeAttribVisFlag) -> bool {
-        self.attrib & u32::from(flag) != 0
+        (self.attrib & TYPE_ATTRIB_VIS_MASK) == u32::from(flag)
     }
 
     pub fn is_sem(&self, flag: TypeAttribSemFlag) -> bool {
-        self.attrib & u32::from(flag) != 0
+        (self.attrib & TYPE_ATTRIB_SEM_MASK) == u32::from(flag)
     }
 }
 
@@ -136,6 +162,7 @@ impl fmt::Display for TypeAttrib {
         match self.attrib & TYPE_ATTRIB_VIS_MASK {
             TYPE_ATTRIB_PRIV_FLAG => write!(f, "priv")?,
             TYPE_ATTRIB_PUB_FLAG => write!(f, "pub")?,
+            TYPE_ATTRIB_CRATE_FLAG => write!(f, "crate")?,
             _ => unreachable!(),
         }
 
@@ -145,6 +172,10 @@ impl fmt::Display for TypeAttrib {
             _ => unreachable!(),
         }
 
+        if (self.attrib & TYPE_ATTRIB_ENUM_FLAG) != 0 {
+            write!(f, " enum")?;
+        }
+
         if (self.attrib & TYPE_ATTRIB_SEALED_FLAG) != 0 {
             write!(f, " sealed")?;
         }
@@ -2,15 +2,16 @@ use std::io::Read;
 use std::iter::Peekable;
 use std::mem::transmute;
 
-use super::code::CorILMethod;
+use super::code::{CorILMethod, ExceptionClause};
+use super::debug::{Document, LocalScope, LocalVarInfo, MethodDebugInfo, SequencePoint};
 use super::file::{IrFile, MAJOR_VERSION, MINOR_VERSION};
-use super::generic::{GenericParam, GenericParamConstraint};
+use super::generic::{GenericParam, GenericParamConstraint, MethodSpec};
 use super::member::{Field, ImplMap, MemberRef, MethodDef};
 use super::module::{Mod, ModRef};
 use super::param::Param;
 use super::sig::IrSig;
 use super::stand_alone_sig::IrStandAloneSig;
-use super::ty::{TypeDef, TypeRef, TypeSpec};
+use super::ty::{InterfaceImpl, TypeDef, TypeRef, TypeSpec};
 
 pub trait IDeserializer {
     fn peek_byte(&mut self) -> u8;
@@ -86,6 +87,7 @@ impl IrFile {
         self.typedef_tbl.serialize(&mut buf);
         self.typeref_tbl.serialize(&mut buf);
         self.typespec_tbl.serialize(&mut buf);
+        self.interfaceimpl_tbl.serialize(&mut buf);
 
         self.field_tbl.serialize(&mut buf);
         self.method_tbl.serialize(&mut buf);
@@ -97,6 +99,7 @@ impl IrFile {
         self.param_tbl.serialize(&mut buf);
         self.generic_param_tbl.serialize(&mut buf);
         self.generic_param_constraint_tbl.serialize(&mut buf);
+        self.methodspec_tbl.serialize(&mut buf);
 
         self.stand_alone_sig_tbl.serialize(&mut buf);
 
@@ -106,6 +109,9 @@ impl IrFile {
 
         self.codes.serialize(&mut buf);
 
+        self.document_tbl.serialize(&mut buf);
+        self.method_debug_tbl.serialize(&mut buf);
+
         buf
     }
 
@@ -128,6 +134,7 @@ impl IrFile {
         let typedef_tbl = Vec::deserialize(&mut buf);
         let typeref_tbl = Vec::deserialize(&mut buf);
         let typespec_tbl = Vec::deserialize(&mut buf);
+        let interfaceimpl_tbl = Vec::deserialize(&mut buf);
 
         let field_tbl = Vec::deserialize(&mut buf);
         let method_tbl = Vec::deserialize(&mut buf);
@@ -138,6 +145,7 @@ impl IrFile {
         let param_tbl = Vec::deserialize(&mut buf);
         let generic_param_tbl = Vec::deserialize(&mut buf);
         let generic_param_constraint_tbl = Vec::deserialize(&mut buf);
+        let methodspec_tbl = Vec::deserialize(&mut buf);
 
         let stand_alone_sig_tbl = Vec::deserialize(&mut buf);
 
@@ -147,6 +155,9 @@ impl IrFile {
 
         let codes = Vec::deserialize(&mut buf);
 
+        let document_tbl = Vec::deserialize(&mut buf);
+        let method_debug_tbl = Vec::deserialize(&mut buf);
+
         IrFile {
             minor_version,
             major_version,
@@ -157,6 +168,7 @@ impl IrFile {
             typedef_tbl,
             typeref_tbl,
             typespec_tbl,
+            interfaceimpl_tbl,
 
             field_tbl,
             method_tbl,
@@ -167,6 +179,7 @@ impl IrFile {
             param_tbl,
             generic_param_tbl,
             generic_param_constraint_tbl,
+            methodspec_tbl,
 
             stand_alone_sig_tbl,
 
@@ -175,6 +188,9 @@ impl IrFile {
             blob_heap,
 
             codes,
+
+            document_tbl,
+            method_debug_tbl,
         }
     }
 }
@@ -239,6 +255,41 @@ impl ISerializable for i32 {
     }
 }
 
+impl ISerializable for i64 {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        let bytes = self.to_be_bytes();
+        for b in bytes.iter() {
+            buf.push(*b);
+        }
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> i64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf.take_bytes(8));
+        i64::from_be_bytes(bytes)
+    }
+}
+
+impl ISerializable for f32 {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.to_bits().serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> f32 {
+        f32::from_bits(u32::deserialize(buf))
+    }
+}
+
+impl ISerializable for f64 {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        (self.to_bits() as i64).serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> f64 {
+        f64::from_bits(i64::deserialize(buf) as u64)
+    }
+}
+
 impl ISerializable for String {
     fn serialize(&self, buf: &mut Vec<u8>) {
         (self.len() as u16).serialize(buf);
@@ -293,6 +344,7 @@ impl_vec_serde!(ModRef);
 impl_vec_serde!(TypeDef);
 impl_vec_serde!(TypeRef);
 impl_vec_serde!(TypeSpec);
+impl_vec_serde!(InterfaceImpl);
 impl_vec_serde!(Field);
 impl_vec_serde!(MethodDef);
 impl_vec_serde!(MemberRef);
@@ -300,6 +352,13 @@ impl_vec_serde!(ImplMap);
 impl_vec_serde!(Param);
 impl_vec_serde!(GenericParam);
 impl_vec_serde!(GenericParamConstraint);
+impl_vec_serde!(MethodSpec);
 impl_vec_serde!(IrStandAloneSig);
 impl_vec_serde!(CorILMethod);
+impl_vec_serde!(ExceptionClause);
 impl_vec_serde!(IrSig);
+impl_vec_serde!(Document);
+impl_vec_serde!(MethodDebugInfo);
+impl_vec_serde!(SequencePoint);
+impl_vec_serde!(LocalScope);
+impl_vec_serde!(LocalVarInfo);
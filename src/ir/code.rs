@@ -11,6 +11,8 @@ pub struct CorILMethod {
     /// local sig, index into StandAloneSig table, or 0 if no local var is presented
     pub locals: u32,
     pub insts: Vec<u8>,
+    /// exception clauses, inner clauses are before outer clauses
+    pub excepts: Vec<ExceptionClause>,
 }
 
 impl ISerializable for CorILMethod {
@@ -18,16 +20,86 @@ impl ISerializable for CorILMethod {
         self.max_stack.serialize(buf);
         self.locals.serialize(buf);
         self.insts.serialize(buf);
+        self.excepts.serialize(buf);
     }
 
     fn deserialize(buf: &mut dyn IDeserializer) -> Self {
         let max_stack = u16::deserialize(buf);
         let local = u32::deserialize(buf);
         let insts = Vec::deserialize(buf);
+        let excepts = Vec::deserialize(buf);
         CorILMethod {
             max_stack,
             locals: local,
             insts,
+            excepts,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionClauseKind {
+    /// 0x0000, catch exceptions of class_tok
+    Catch,
+    /// 0x0002, handler is executed when leaving the try block, normally or by exception
+    Finally,
+}
+
+/// II.25.4.6
+///
+/// Offsets are byte offsets into insts of CorILMethod, lengths are in bytes
+pub struct ExceptionClause {
+    pub kind: ExceptionClauseKind,
+    pub try_offset: u32,
+    pub try_length: u32,
+    pub handler_offset: u32,
+    pub handler_length: u32,
+    /// TypeDefOrRef or TypeSpec token of the exception class if Catch, 0 if Finally
+    pub class_tok: u32,
+}
+
+impl ExceptionClause {
+    pub fn try_contains(&self, offset: u32) -> bool {
+        offset >= self.try_offset && offset < self.try_offset + self.try_length
+    }
+
+    pub fn handler_contains(&self, offset: u32) -> bool {
+        offset >= self.handler_offset && offset < self.handler_offset + self.handler_length
+    }
+}
+
+impl ISerializable for ExceptionClause {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        match self.kind {
+            ExceptionClauseKind::Catch => 0x0u16,
+            ExceptionClauseKind::Finally => 0x2u16,
+        }
+        .serialize(buf);
+        self.try_offset.serialize(buf);
+        self.try_length.serialize(buf);
+        self.handler_offset.serialize(buf);
+        self.handler_length.serialize(buf);
+        self.class_tok.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let kind = match u16::deserialize(buf) {
+            0x0 => ExceptionClauseKind::Catch,
+            0x2 => ExceptionClauseKind::Finally,
+            flag => panic!("Unknown exception clause flag 0x{:X}", flag),
+        };
+        let try_offset = u32::deserialize(buf);
+        let try_length = u32::deserialize(buf);
+        let handler_offset = u32::deserialize(buf);
+        let handler_length = u32::deserialize(buf);
+        let class_tok = u32::deserialize(buf);
+        ExceptionClause {
+            kind,
+            try_offset,
+            try_length,
+            handler_offset,
+            handler_length,
+            class_tok,
         }
     }
 }
@@ -81,7 +153,12 @@ impl<'i> IDeserializer for InstDeserializer<'i> {
 }
 
 impl CorILMethod {
-    pub fn new(max_stack: u16, locals_sig: u32, insts: Vec<Inst>) -> CorILMethod {
+    pub fn new(
+        max_stack: u16,
+        locals_sig: u32,
+        insts: Vec<Inst>,
+        excepts: Vec<ExceptionClause>,
+    ) -> CorILMethod {
         let mut code = vec![];
         for inst in insts.iter() {
             inst.serialize(&mut code);
@@ -90,6 +167,7 @@ impl CorILMethod {
             max_stack,
             locals: locals_sig,
             insts: code,
+            excepts,
         }
     }
 
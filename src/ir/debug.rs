@@ -0,0 +1,141 @@
+use super::bc_serde::{IDeserializer, ISerializable};
+
+/// Source file of a module, similar to Document table of portable PDB
+pub struct Document {
+    /// index into str heap, path of the source file
+    pub name: u32,
+}
+
+/// Debug info of a method with IL body, generated by `xic -g`
+pub struct MethodDebugInfo {
+    /// index into method tbl
+    pub method: u32,
+    /// index into document tbl
+    pub document: u32,
+    /// ordered by offset
+    pub seq_points: Vec<SequencePoint>,
+    /// outer scopes are before inner scopes
+    pub scopes: Vec<LocalScope>,
+}
+
+/// IL at offset is generated from the statement that starts at line:col of the source file
+pub struct SequencePoint {
+    pub offset: u32,
+    /// starts from 1
+    pub line: u32,
+    /// starts from 1, in chars
+    pub col: u32,
+}
+
+/// Local variables that are visible in IL range [start, start + length)
+pub struct LocalScope {
+    pub start: u32,
+    pub length: u32,
+    pub vars: Vec<LocalVarInfo>,
+}
+
+pub struct LocalVarInfo {
+    /// index into locals of the method
+    pub idx: u16,
+    /// index into str heap
+    pub name: u32,
+}
+
+impl MethodDebugInfo {
+    /// Sequence point of the statement that IL at offset belongs to
+    pub fn seq_point_at(&self, offset: u32) -> Option<&SequencePoint> {
+        self.seq_points.iter().rev().find(|sp| sp.offset <= offset)
+    }
+
+    /// Name of local var idx if it is visible at offset
+    pub fn local_name_at(&self, idx: u16, offset: u32) -> Option<u32> {
+        // inner scopes are searched first since they might shadow outer ones
+        self.scopes
+            .iter()
+            .rev()
+            .filter(|s| offset >= s.start && offset < s.start + s.length)
+            .find_map(|s| s.vars.iter().find(|v| v.idx == idx))
+            .map(|v| v.name)
+    }
+}
+
+impl ISerializable for Document {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.name.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        Document {
+            name: u32::deserialize(buf),
+        }
+    }
+}
+
+impl ISerializable for MethodDebugInfo {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.method.serialize(buf);
+        self.document.serialize(buf);
+        self.seq_points.serialize(buf);
+        self.scopes.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let method = u32::deserialize(buf);
+        let document = u32::deserialize(buf);
+        let seq_points = Vec::deserialize(buf);
+        let scopes = Vec::deserialize(buf);
+        MethodDebugInfo {
+            method,
+            document,
+            seq_points,
+            scopes,
+        }
+    }
+}
+
+impl ISerializable for SequencePoint {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.offset.serialize(buf);
+        self.line.serialize(buf);
+        self.col.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let offset = u32::deserialize(buf);
+        let line = u32::deserialize(buf);
+        let col = u32::deserialize(buf);
+        SequencePoint { offset, line, col }
+    }
+}
+
+impl ISerializable for LocalScope {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.start.serialize(buf);
+        self.length.serialize(buf);
+        self.vars.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let start = u32::deserialize(buf);
+        let length = u32::deserialize(buf);
+        let vars = Vec::deserialize(buf);
+        LocalScope {
+            start,
+            length,
+            vars,
+        }
+    }
+}
+
+impl ISerializable for LocalVarInfo {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.idx.serialize(buf);
+        self.name.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let idx = u16::deserialize(buf);
+        let name = u32::deserialize(buf);
+        LocalVarInfo { idx, name }
+    }
+}
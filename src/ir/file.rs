@@ -1,14 +1,15 @@
 use super::code::CorILMethod;
-use super::generic::{GenericParam, GenericParamConstraint};
+use super::debug::{Document, MethodDebugInfo};
+use super::generic::{GenericParam, GenericParamConstraint, MethodSpec};
 use super::member::{Field, ImplMap, MemberRef, MethodDef};
 use super::module::{Mod, ModRef};
 use super::param::Param;
 use super::sig::IrSig;
 use super::stand_alone_sig::IrStandAloneSig;
-use super::ty::{TypeDef, TypeRef, TypeSpec};
+use super::ty::{InterfaceImpl, TypeDef, TypeRef, TypeSpec};
 
 pub const MAJOR_VERSION: u16 = 0;
-pub const MINOR_VERSION: u16 = 4;
+pub const MINOR_VERSION: u16 = 5;
 
 pub struct IrFile {
     pub major_version: u16,
@@ -23,6 +24,7 @@ pub struct IrFile {
     /// type ref tbl in CLR
     pub typeref_tbl: Vec<TypeRef>,
     pub typespec_tbl: Vec<TypeSpec>,
+    pub interfaceimpl_tbl: Vec<InterfaceImpl>,
 
     pub field_tbl: Vec<Field>,
     pub method_tbl: Vec<MethodDef>,
@@ -34,6 +36,7 @@ pub struct IrFile {
     pub param_tbl: Vec<Param>,
     pub generic_param_tbl: Vec<GenericParam>,
     pub generic_param_constraint_tbl: Vec<GenericParamConstraint>,
+    pub methodspec_tbl: Vec<MethodSpec>,
 
     pub stand_alone_sig_tbl: Vec<IrStandAloneSig>,
 
@@ -45,6 +48,10 @@ pub struct IrFile {
 
     /// None CLR standard, index starts from 1
     pub codes: Vec<CorILMethod>,
+
+    /// Debug section, empty if not compiled with -g
+    pub document_tbl: Vec<Document>,
+    pub method_debug_tbl: Vec<MethodDebugInfo>,
 }
 
 impl IrFile {
@@ -59,6 +66,7 @@ impl IrFile {
             typedef_tbl: vec![],
             typeref_tbl: vec![],
             typespec_tbl: vec![],
+            interfaceimpl_tbl: vec![],
 
             field_tbl: vec![],
             method_tbl: vec![],
@@ -69,6 +77,7 @@ impl IrFile {
             param_tbl: vec![],
             generic_param_tbl: vec![],
             generic_param_constraint_tbl: vec![],
+            methodspec_tbl: vec![],
 
             stand_alone_sig_tbl: vec![],
 
@@ -76,6 +85,9 @@ impl IrFile {
             usr_str_heap: vec![],
             blob_heap: vec![],
             codes: vec![],
+
+            document_tbl: vec![],
+            method_debug_tbl: vec![],
         }
     }
 
@@ -86,4 +98,11 @@ impl IrFile {
     pub fn get_str(&self, idx: u32) -> &str {
         &self.str_heap[idx as usize]
     }
+
+    /// method_idx: index into method tbl
+    pub fn method_debug_info(&self, method_idx: u32) -> Option<&MethodDebugInfo> {
+        self.method_debug_tbl
+            .iter()
+            .find(|m| m.method == method_idx)
+    }
 }
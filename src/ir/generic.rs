@@ -1,4 +1,9 @@
 use super::bc_serde::{IDeserializer, ISerializable};
+use super::file::IrFile;
+use super::text_serde::IrFmt;
+use super::tok::{fmt_tok, to_tok, TokTag};
+
+use std::fmt;
 
 /// II.22.20 0x2A
 pub struct GenericParam {
@@ -34,6 +39,35 @@ impl ISerializable for GenericParam {
     }
 }
 
+impl GenericParam {
+    pub fn get_owner(&self) -> (TypeOrMethodDef, u32) {
+        let tag = self.owner & TYPE_OR_METHOD_DEF_TAG_MASK;
+        let idx = self.owner >> TYPE_OR_METHOD_DEF_TAG_SIZE;
+        (
+            match tag {
+                0 => TypeOrMethodDef::TypeDef,
+                1 => TypeOrMethodDef::MethodDef,
+                _ => unreachable!(),
+            },
+            idx,
+        )
+    }
+}
+
+const TYPE_OR_METHOD_DEF_TAG_SIZE: u32 = 1;
+const TYPE_OR_METHOD_DEF_TAG_MASK: u32 = (0x1 << TYPE_OR_METHOD_DEF_TAG_SIZE) - 1; // 0x1
+
+/// 1 bits tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeOrMethodDef {
+    TypeDef = 0,
+    MethodDef = 1,
+}
+
+pub fn to_type_or_methoddef(raw_idx: u32, tag: TypeOrMethodDef) -> u32 {
+    (raw_idx << TYPE_OR_METHOD_DEF_TAG_SIZE) | (tag as u32)
+}
+
 /// II.22.21 0x2C
 pub struct GenericParamConstraint {
     /// index into GenericParam table
@@ -54,3 +88,74 @@ impl ISerializable for GenericParamConstraint {
         Self { owner, constraint }
     }
 }
+
+/// II.22.29 0x2B
+pub struct MethodSpec {
+    /// MethodDefOrRef
+    pub method: u32,
+    /// index into blob heap
+    pub instantiation: u32,
+}
+
+impl MethodSpec {
+    pub fn get_method(&self) -> (MethodDefOrRef, u32) {
+        let tag = self.method & METHOD_DEF_OR_REF_TAG_MASK;
+        let idx = self.method >> METHOD_DEF_OR_REF_TAG_SIZE;
+        (
+            match tag {
+                0 => MethodDefOrRef::MethodDef,
+                1 => MethodDefOrRef::MemberRef,
+                _ => unreachable!(),
+            },
+            idx,
+        )
+    }
+}
+
+impl IrFmt for MethodSpec {
+    fn fmt(&self, f: &mut fmt::Formatter, ctx: &IrFile) -> fmt::Result {
+        let (tag, idx) = self.get_method();
+        fmt_tok(to_tok(idx, tag.to_tok_tag()), f, ctx)?;
+        write!(f, " ")?;
+        ctx.blob_heap[self.instantiation as usize].fmt(f, ctx)
+    }
+}
+
+const METHOD_DEF_OR_REF_TAG_SIZE: u32 = 1;
+const METHOD_DEF_OR_REF_TAG_MASK: u32 = (0x1 << METHOD_DEF_OR_REF_TAG_SIZE) - 1; // 0x1
+
+/// 1 bits tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodDefOrRef {
+    MethodDef = 0,
+    MemberRef = 1,
+}
+
+impl MethodDefOrRef {
+    pub fn to_tok_tag(&self) -> TokTag {
+        match self {
+            MethodDefOrRef::MethodDef => TokTag::MethodDef,
+            MethodDefOrRef::MemberRef => TokTag::MemberRef,
+        }
+    }
+}
+
+pub fn to_methoddeforref(raw_idx: u32, tag: MethodDefOrRef) -> u32 {
+    (raw_idx << METHOD_DEF_OR_REF_TAG_SIZE) | (tag as u32)
+}
+
+impl ISerializable for MethodSpec {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.method.serialize(buf);
+        self.instantiation.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let method = u32::deserialize(buf);
+        let instantiation = u32::deserialize(buf);
+        Self {
+            method,
+            instantiation,
+        }
+    }
+}
@@ -47,6 +47,10 @@ impl Inst {
             Inst::LdC8 => write!(f, "ldc.i4.8"),
             Inst::LdCI4S(num) => write!(f, "ldc.i4.s {}", num),
             Inst::LdCI4(num) => write!(f, "ldc.i4 {}", num),
+            Inst::LdCI8(num) => write!(f, "ldc.i8 {}", num),
+            // debug format keeps the decimal point and exponent so that it can be parsed back
+            Inst::LdCR4(num) => write!(f, "ldc.r4 {:?}", num),
+            Inst::LdCR8(num) => write!(f, "ldc.r8 {:?}", num),
 
             Inst::Dup => write!(f, "dup"),
             Inst::Pop => write!(f, "pop"),
@@ -69,18 +73,87 @@ impl Inst {
             Inst::BGt(offset) => write!(f, "bgt IL_{:0>4X}", (i + self.size()) as i32 + offset),
             Inst::BLe(offset) => write!(f, "ble IL_{:0>4X}", (i + self.size()) as i32 + offset),
             Inst::BLt(offset) => write!(f, "blt IL_{:0>4X}", (i + self.size()) as i32 + offset),
+            Inst::BNe(offset) => {
+                write!(f, "bne.un IL_{:0>4X}", (i + self.size()) as i32 + offset)
+            }
+            Inst::Leave(offset) => {
+                write!(f, "leave IL_{:0>4X}", (i + self.size()) as i32 + offset)
+            }
 
             Inst::CEq => write!(f, "ceq"),
             Inst::CGt => write!(f, "cgt"),
+            Inst::CGtUn => write!(f, "cgt.un"),
             Inst::CLt => write!(f, "clt"),
+            Inst::CLtUn => write!(f, "clt.un"),
+
+            Inst::LdIndI1 => write!(f, "ldind.i1"),
+            Inst::LdIndU1 => write!(f, "ldind.u1"),
+            Inst::LdIndI2 => write!(f, "ldind.i2"),
+            Inst::LdIndU2 => write!(f, "ldind.u2"),
+            Inst::LdIndI4 => write!(f, "ldind.i4"),
+            Inst::LdIndU4 => write!(f, "ldind.u4"),
+            Inst::LdIndI8 => write!(f, "ldind.i8"),
+            Inst::LdIndI => write!(f, "ldind.i"),
+            Inst::LdIndR4 => write!(f, "ldind.r4"),
+            Inst::LdIndR8 => write!(f, "ldind.r8"),
+            Inst::StIndI1 => write!(f, "stind.i1"),
+            Inst::StIndI2 => write!(f, "stind.i2"),
+            Inst::StIndI4 => write!(f, "stind.i4"),
+            Inst::StIndI8 => write!(f, "stind.i8"),
+            Inst::StIndR4 => write!(f, "stind.r4"),
+            Inst::StIndR8 => write!(f, "stind.r8"),
+            Inst::StIndI => write!(f, "stind.i"),
 
             Inst::Add => write!(f, "add"),
             Inst::Sub => write!(f, "sub"),
             Inst::Mul => write!(f, "mul"),
             Inst::Div => write!(f, "div"),
+            Inst::DivUn => write!(f, "div.un"),
             Inst::Rem => write!(f, "rem"),
+            Inst::RemUn => write!(f, "rem.un"),
+            Inst::And => write!(f, "and"),
+            Inst::Or => write!(f, "or"),
+            Inst::Xor => write!(f, "xor"),
+            Inst::Shl => write!(f, "shl"),
+            Inst::Shr => write!(f, "shr"),
+            Inst::ShrUn => write!(f, "shr.un"),
 
             Inst::Neg => write!(f, "neg"),
+            Inst::Not => write!(f, "not"),
+
+            Inst::ConvI1 => write!(f, "conv.i1"),
+            Inst::ConvI2 => write!(f, "conv.i2"),
+            Inst::ConvI4 => write!(f, "conv.i4"),
+            Inst::ConvI8 => write!(f, "conv.i8"),
+            Inst::ConvR4 => write!(f, "conv.r4"),
+            Inst::ConvR8 => write!(f, "conv.r8"),
+            Inst::ConvU4 => write!(f, "conv.u4"),
+            Inst::ConvU8 => write!(f, "conv.u8"),
+            Inst::ConvRUn => write!(f, "conv.r.un"),
+            Inst::ConvU2 => write!(f, "conv.u2"),
+            Inst::ConvU1 => write!(f, "conv.u1"),
+            Inst::ConvI => write!(f, "conv.i"),
+            Inst::ConvU => write!(f, "conv.u"),
+            Inst::ConvOvfI1 => write!(f, "conv.ovf.i1"),
+            Inst::ConvOvfU1 => write!(f, "conv.ovf.u1"),
+            Inst::ConvOvfI2 => write!(f, "conv.ovf.i2"),
+            Inst::ConvOvfU2 => write!(f, "conv.ovf.u2"),
+            Inst::ConvOvfI4 => write!(f, "conv.ovf.i4"),
+            Inst::ConvOvfU4 => write!(f, "conv.ovf.u4"),
+            Inst::ConvOvfI8 => write!(f, "conv.ovf.i8"),
+            Inst::ConvOvfU8 => write!(f, "conv.ovf.u8"),
+            Inst::ConvOvfI => write!(f, "conv.ovf.i"),
+            Inst::ConvOvfU => write!(f, "conv.ovf.u"),
+            Inst::ConvOvfI1Un => write!(f, "conv.ovf.i1.un"),
+            Inst::ConvOvfI2Un => write!(f, "conv.ovf.i2.un"),
+            Inst::ConvOvfI4Un => write!(f, "conv.ovf.i4.un"),
+            Inst::ConvOvfI8Un => write!(f, "conv.ovf.i8.un"),
+            Inst::ConvOvfU1Un => write!(f, "conv.ovf.u1.un"),
+            Inst::ConvOvfU2Un => write!(f, "conv.ovf.u2.un"),
+            Inst::ConvOvfU4Un => write!(f, "conv.ovf.u4.un"),
+            Inst::ConvOvfU8Un => write!(f, "conv.ovf.u8.un"),
+            Inst::ConvOvfIUn => write!(f, "conv.ovf.i.un"),
+            Inst::ConvOvfUUn => write!(f, "conv.ovf.u.un"),
 
             Inst::CallVirt(tok) => {
                 write!(f, "callvirt ")?;
@@ -94,6 +167,15 @@ impl Inst {
                 write!(f, "newobj ")?;
                 fmt_tok(*tok, f, ctx)
             }
+            Inst::CastClass(tok) => {
+                write!(f, "castclass ")?;
+                fmt_tok(*tok, f, ctx)
+            }
+            Inst::IsInst(tok) => {
+                write!(f, "isinst ")?;
+                fmt_tok(*tok, f, ctx)
+            }
+            Inst::Throw => write!(f, "throw"),
             Inst::LdFld(tok) => {
                 write!(f, "ldfld ")?;
                 fmt_tok(*tok, f, ctx)
@@ -119,7 +201,7 @@ impl Inst {
                 fmt_tok(*tok, f, ctx)
             }
 
-            Inst::LdStr(s) => write!(f, "ldstr \"{}\"", ctx.usr_str_heap[*s as usize]),
+            Inst::LdStr(s) => write!(f, "ldstr {:?}", ctx.usr_str_heap[*s as usize]),
 
             Inst::NewArr(tok) => {
                 write!(f, "newarr ")?;
@@ -129,9 +211,24 @@ impl Inst {
 
             Inst::LdLen => write!(f, "ldlen"),
 
+            Inst::LdElemI1 => write!(f, "ldelem.i1"),
+            Inst::LdElemU1 => write!(f, "ldelem.u1"),
+            Inst::LdElemI2 => write!(f, "ldelem.i2"),
+            Inst::LdElemU2 => write!(f, "ldelem.u2"),
             Inst::LdElemI4 => write!(f, "ldelem.i4"),
+            Inst::LdElemU4 => write!(f, "ldelem.u4"),
+            Inst::LdElemI8 => write!(f, "ldelem.i8"),
+            Inst::LdElemI => write!(f, "ldelem.i"),
+            Inst::LdElemR4 => write!(f, "ldelem.r4"),
+            Inst::LdElemR8 => write!(f, "ldelem.r8"),
             Inst::LdElemRef => write!(f, "ldelem.ref"),
+            Inst::StElemI => write!(f, "stelem.i"),
+            Inst::StElemI1 => write!(f, "stelem.i1"),
+            Inst::StElemI2 => write!(f, "stelem.i2"),
             Inst::StElemI4 => write!(f, "stelem.i4"),
+            Inst::StElemI8 => write!(f, "stelem.i8"),
+            Inst::StElemR4 => write!(f, "stelem.r4"),
+            Inst::StElemR8 => write!(f, "stelem.r8"),
             Inst::StElemRef => write!(f, "stelem.ref"),
 
             Inst::LdElem(tok) => {
@@ -146,6 +243,8 @@ impl Inst {
                 write!(f, "stelem ")?;
                 fmt_tok(*tok, f, ctx)
             }
+
+            Inst::EndFinally => write!(f, "endfinally"),
         }
     }
 }
@@ -1,6 +1,10 @@
 mod fmt;
 mod serde;
 
+use super::file::IrFile;
+use super::sig::{InnerRetType, IrSig, MethodSigFlagTag};
+use super::tok::{get_tok_tag, to_tok, TokTag};
+
 use std::mem;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -108,6 +112,16 @@ pub enum Inst {
     LdCI4S(i8),
     /// 0x20, ldc.i4 num
     LdCI4(i32),
+    /// 0x21, ldc.i8 num
+    ///
+    /// Push num onto the stack as i64
+    LdCI8(i64),
+    /// 0x22, ldc.r4 num
+    ///
+    /// Push num onto the stack as F
+    LdCR4(f32),
+    /// 0x23, ldc.r8 num
+    LdCR8(f64),
 
     /// 0x25, dup
     ///
@@ -160,15 +174,69 @@ pub enum Inst {
     /// 0x3F, blt
     BLt(i32),
 
+    /// 0x40, bne.un
+    BNe(i32),
+
     /// 0xFE01, ceq
     CEq,
 
     /// 0xFE02 cgt
     CGt,
 
+    /// 0xFE03 cgt.un
+    ///
+    /// Compare integers as unsigned, or floats with unordered (NaN) considered as true
+    CGtUn,
+
     /// 0xFE04 clt
     CLt,
 
+    /// 0xFE05 clt.un
+    CLtUn,
+
+    /// 0x46, ldind.i1
+    ///
+    /// Load value of the type indirectly from **addr**
+    ///
+    /// `..., addr -> ..., val`
+    LdIndI1,
+    /// 0x47, ldind.u1
+    LdIndU1,
+    /// 0x48, ldind.i2
+    LdIndI2,
+    /// 0x49, ldind.u2
+    LdIndU2,
+    /// 0x4A, ldind.i4
+    LdIndI4,
+    /// 0x4B, ldind.u4
+    LdIndU4,
+    /// 0x4C, ldind.i8
+    LdIndI8,
+    /// 0x4D, ldind.i
+    LdIndI,
+    /// 0x4E, ldind.r4
+    LdIndR4,
+    /// 0x4F, ldind.r8
+    LdIndR8,
+    /// 0x52, stind.i1
+    ///
+    /// Store value of the type indirectly to **addr**
+    ///
+    /// `..., addr, val -> ...`
+    StIndI1,
+    /// 0x53, stind.i2
+    StIndI2,
+    /// 0x54, stind.i4
+    StIndI4,
+    /// 0x55, stind.i8
+    StIndI8,
+    /// 0x56, stind.r4
+    StIndR4,
+    /// 0x57, stind.r8
+    StIndR8,
+    /// 0xDF, stind.i
+    StIndI,
+
     /// 0x58, add
     ///
     /// Add two numeric values without overflow check
@@ -181,17 +249,130 @@ pub enum Inst {
     Mul,
     /// 0x5B, div
     Div,
+    /// 0x5C, div.un
+    ///
+    /// Divide two integers as unsigned
+    DivUn,
     /// 0x5D, rem
     ///
     /// a % b
     ///
     /// `..., val1, val2 -> ..., res`
     Rem,
+    /// 0x5E, rem.un
+    RemUn,
+    /// 0x5F, and
+    ///
+    /// Bitwise and of two integers
+    ///
+    /// `..., val1, val2 -> ..., res`
+    And,
+    /// 0x60, or
+    Or,
+    /// 0x61, xor
+    Xor,
+    /// 0x62, shl
+    ///
+    /// Shift an integer left by **amount**: i32 or inative
+    ///
+    /// `..., val, amount -> ..., res`
+    Shl,
+    /// 0x63, shr
+    ///
+    /// Shift an integer right with sign extension
+    Shr,
+    /// 0x64, shr.un
+    ///
+    /// Shift an integer right with zero extension
+    ShrUn,
 
     /// 0x65, neg
     ///
     /// neg int or float
     Neg,
+    /// 0x66, not
+    ///
+    /// Bitwise complement of an integer
+    Not,
+
+    /// 0x67, conv.i1
+    ///
+    /// Convert to i8, then extend to i32. Overflow is not checked
+    ///
+    /// `..., val -> ..., res`
+    ConvI1,
+    /// 0x68, conv.i2
+    ConvI2,
+    /// 0x69, conv.i4
+    ConvI4,
+    /// 0x6A, conv.i8
+    ConvI8,
+    /// 0x6B, conv.r4
+    ConvR4,
+    /// 0x6C, conv.r8
+    ConvR8,
+    /// 0x6D, conv.u4
+    ConvU4,
+    /// 0x6E, conv.u8
+    ConvU8,
+    /// 0x76, conv.r.un
+    ///
+    /// Convert unsigned integer to float
+    ConvRUn,
+    /// 0xD1, conv.u2
+    ConvU2,
+    /// 0xD2, conv.u1
+    ConvU1,
+    /// 0xD3, conv.i
+    ConvI,
+    /// 0xE0, conv.u
+    ConvU,
+    /// 0xB3, conv.ovf.i1
+    ///
+    /// Convert to i8, then extend to i32. Throw std::OverflowException if the value cannot be represented
+    ///
+    /// `..., val -> ..., res`
+    ConvOvfI1,
+    /// 0xB4, conv.ovf.u1
+    ConvOvfU1,
+    /// 0xB5, conv.ovf.i2
+    ConvOvfI2,
+    /// 0xB6, conv.ovf.u2
+    ConvOvfU2,
+    /// 0xB7, conv.ovf.i4
+    ConvOvfI4,
+    /// 0xB8, conv.ovf.u4
+    ConvOvfU4,
+    /// 0xB9, conv.ovf.i8
+    ConvOvfI8,
+    /// 0xBA, conv.ovf.u8
+    ConvOvfU8,
+    /// 0xD4, conv.ovf.i
+    ConvOvfI,
+    /// 0xD5, conv.ovf.u
+    ConvOvfU,
+    /// 0x82, conv.ovf.i1.un
+    ///
+    /// Same as conv.ovf.i1 but integer operand is treated as unsigned
+    ConvOvfI1Un,
+    /// 0x83, conv.ovf.i2.un
+    ConvOvfI2Un,
+    /// 0x84, conv.ovf.i4.un
+    ConvOvfI4Un,
+    /// 0x85, conv.ovf.i8.un
+    ConvOvfI8Un,
+    /// 0x86, conv.ovf.u1.un
+    ConvOvfU1Un,
+    /// 0x87, conv.ovf.u2.un
+    ConvOvfU2Un,
+    /// 0x88, conv.ovf.u4.un
+    ConvOvfU4Un,
+    /// 0x89, conv.ovf.u8.un
+    ConvOvfU8Un,
+    /// 0x8A, conv.ovf.i.un
+    ConvOvfIUn,
+    /// 0x8B, conv.ovf.u.un
+    ConvOvfUUn,
 
     /// 0x6F, callvirt method
     ///
@@ -213,6 +394,26 @@ pub enum Inst {
     ///
     /// `..., arg0, ..., argN -> ..., obj`
     NewObj(u32),
+    /// 0x74, castclass ty
+    ///
+    /// Cast **obj** to **ty** (typedef|typeref|typespec).
+    /// Throw std::InvalidCastException if obj is not an instance of ty. null is always castable
+    ///
+    /// `..., obj -> ..., obj`
+    CastClass(u32),
+    /// 0x75, isinst ty
+    ///
+    /// Test if **obj** is an instance of **ty**, push obj if it is, otherwise null
+    ///
+    /// `..., obj -> ..., obj or null`
+    IsInst(u32),
+
+    /// 0x7A, throw
+    ///
+    /// Throw an exception **obj**, unwind until a matching catch handler is found
+    ///
+    /// `..., obj -> ...`
+    Throw,
 
     /// 0x7B, ldfld field
     ///
@@ -265,12 +466,46 @@ pub enum Inst {
     ///
     /// ..., arr -> len
     LdLen,
+    /// 0x90, ldelem.i1
+    LdElemI1,
+    /// 0x91, ldelem.u1
+    LdElemU1,
+    /// 0x92, ldelem.i2
+    LdElemI2,
+    /// 0x93, ldelem.u2
+    LdElemU2,
     /// 0x94, ldelem.i4
     LdElemI4,
+    /// 0x95, ldelem.u4
+    LdElemU4,
+    /// 0x96, ldelem.i8
+    ///
+    /// Also used for u64 elements
+    LdElemI8,
+    /// 0x97, ldelem.i
+    LdElemI,
+    /// 0x98, ldelem.r4
+    LdElemR4,
+    /// 0x99, ldelem.r8
+    LdElemR8,
     /// 0x9A, ldelem.ref
     LdElemRef,
+    /// 0x9B, stelem.i
+    StElemI,
+    /// 0x9C, stelem.i1
+    ///
+    /// Also used for u8 and bool elements
+    StElemI1,
+    /// 0x9D, stelem.i2
+    StElemI2,
     /// 0x9E, stelem.i4
     StElemI4,
+    /// 0x9F, stelem.i8
+    StElemI8,
+    /// 0xA0, stelem.r4
+    StElemR4,
+    /// 0xA1, stelem.r8
+    StElemR8,
     /// 0xA2, stelem.ref
     StElemRef,
     /// 0xA3, ldelem ty
@@ -284,6 +519,15 @@ pub enum Inst {
     /// ..., arr, idx, val -> ...
     StElem(u32),
 
+    /// 0xDC, endfinally
+    ///
+    /// End of a finally handler, continue the leave or the unwinding that runs this handler
+    EndFinally,
+    /// 0xDD, leave
+    ///
+    /// Exit a protected region, run finally handlers on the way and empty the eval stack
+    Leave(i32),
+
     /// 0xFE15, initobj ty
     ///
     /// init value at addr **dset**: unmanaged ptr (native int) or managed ptr (&).
@@ -328,6 +572,9 @@ impl Inst {
             | Inst::LdC8 => INST_SIZE,
             Inst::LdCI4S(_) => INST_SIZE + mem::size_of::<u8>(),
             Inst::LdCI4(_) => INST_SIZE + mem::size_of::<i32>(),
+            Inst::LdCI8(_) => INST_SIZE + mem::size_of::<i64>(),
+            Inst::LdCR4(_) => INST_SIZE + mem::size_of::<f32>(),
+            Inst::LdCR8(_) => INST_SIZE + mem::size_of::<f64>(),
 
             Inst::Dup => INST_SIZE,
             Inst::Pop => INST_SIZE,
@@ -342,13 +589,81 @@ impl Inst {
             | Inst::BGe(_)
             | Inst::BGt(_)
             | Inst::BLe(_)
-            | Inst::BLt(_) => INST_SIZE + mem::size_of::<i32>(),
-
-            Inst::CEq | Inst::CGt | Inst::CLt => FAT_INST_SIZE,
-
-            Inst::Add | Inst::Sub | Inst::Mul | Inst::Div | Inst::Rem => INST_SIZE,
-
-            Inst::Neg => INST_SIZE,
+            | Inst::BLt(_)
+            | Inst::BNe(_)
+            | Inst::Leave(_) => INST_SIZE + mem::size_of::<i32>(),
+
+            Inst::Throw | Inst::EndFinally => INST_SIZE,
+
+            Inst::CEq | Inst::CGt | Inst::CGtUn | Inst::CLt | Inst::CLtUn => FAT_INST_SIZE,
+
+            Inst::Add
+            | Inst::Sub
+            | Inst::Mul
+            | Inst::Div
+            | Inst::DivUn
+            | Inst::Rem
+            | Inst::RemUn
+            | Inst::And
+            | Inst::Or
+            | Inst::Xor
+            | Inst::Shl
+            | Inst::Shr
+            | Inst::ShrUn => INST_SIZE,
+
+            Inst::Neg | Inst::Not => INST_SIZE,
+
+            Inst::LdIndI1
+            | Inst::LdIndU1
+            | Inst::LdIndI2
+            | Inst::LdIndU2
+            | Inst::LdIndI4
+            | Inst::LdIndU4
+            | Inst::LdIndI8
+            | Inst::LdIndI
+            | Inst::LdIndR4
+            | Inst::LdIndR8
+            | Inst::StIndI1
+            | Inst::StIndI2
+            | Inst::StIndI4
+            | Inst::StIndI8
+            | Inst::StIndR4
+            | Inst::StIndR8
+            | Inst::StIndI => INST_SIZE,
+
+            Inst::ConvI1
+            | Inst::ConvI2
+            | Inst::ConvI4
+            | Inst::ConvI8
+            | Inst::ConvR4
+            | Inst::ConvR8
+            | Inst::ConvU4
+            | Inst::ConvU8
+            | Inst::ConvRUn
+            | Inst::ConvU2
+            | Inst::ConvU1
+            | Inst::ConvI
+            | Inst::ConvU
+            | Inst::ConvOvfI1
+            | Inst::ConvOvfU1
+            | Inst::ConvOvfI2
+            | Inst::ConvOvfU2
+            | Inst::ConvOvfI4
+            | Inst::ConvOvfU4
+            | Inst::ConvOvfI8
+            | Inst::ConvOvfU8
+            | Inst::ConvOvfI
+            | Inst::ConvOvfU
+            | Inst::ConvOvfI1Un
+            | Inst::ConvOvfI2Un
+            | Inst::ConvOvfI4Un
+            | Inst::ConvOvfI8Un
+            | Inst::ConvOvfU1Un
+            | Inst::ConvOvfU2Un
+            | Inst::ConvOvfU4Un
+            | Inst::ConvOvfU8Un
+            | Inst::ConvOvfIUn
+            | Inst::ConvOvfUUn => INST_SIZE,
 
             Inst::CallVirt(_)
             | Inst::NewObj(_)
@@ -357,7 +672,9 @@ impl Inst {
             | Inst::StFld(_)
             | Inst::LdSFld(_)
             | Inst::LdSFldA(_)
-            | Inst::StSFld(_) => INST_SIZE + mem::size_of::<u32>(),
+            | Inst::StSFld(_)
+            | Inst::CastClass(_)
+            | Inst::IsInst(_) => INST_SIZE + mem::size_of::<u32>(),
 
             Inst::InitObj(_) => FAT_INST_SIZE + mem::size_of::<i32>(),
 
@@ -367,11 +684,255 @@ impl Inst {
 
             Inst::LdLen => INST_SIZE,
 
-            Inst::LdElemI4 | Inst::LdElemRef | Inst::StElemI4 | Inst::StElemRef => INST_SIZE,
+            Inst::LdElemI1
+            | Inst::LdElemU1
+            | Inst::LdElemI2
+            | Inst::LdElemU2
+            | Inst::LdElemI4
+            | Inst::LdElemU4
+            | Inst::LdElemI8
+            | Inst::LdElemI
+            | Inst::LdElemR4
+            | Inst::LdElemR8
+            | Inst::LdElemRef
+            | Inst::StElemI
+            | Inst::StElemI1
+            | Inst::StElemI2
+            | Inst::StElemI4
+            | Inst::StElemI8
+            | Inst::StElemR4
+            | Inst::StElemR8
+            | Inst::StElemRef => INST_SIZE,
 
             Inst::LdElem(_) | Inst::LdElemA(_) | Inst::StElem(_) => {
                 INST_SIZE + mem::size_of::<u32>()
             }
         }
     }
+
+    /// metadata token operand of this inst
+    pub fn tok(&self) -> Option<u32> {
+        match self {
+            Inst::Call(tok)
+            | Inst::CallVirt(tok)
+            | Inst::NewObj(tok)
+            | Inst::InitObj(tok)
+            | Inst::LdFld(tok)
+            | Inst::LdFldA(tok)
+            | Inst::StFld(tok)
+            | Inst::LdSFld(tok)
+            | Inst::LdSFldA(tok)
+            | Inst::StSFld(tok)
+            | Inst::CastClass(tok)
+            | Inst::IsInst(tok)
+            | Inst::NewArr(tok)
+            | Inst::LdElem(tok)
+            | Inst::LdElemA(tok)
+            | Inst::StElem(tok) => Some(*tok),
+            _ => None,
+        }
+    }
+
+    /// (number of slots popped, number of slots pushed) when executing this inst
+    ///
+    /// Signatures of call insts are looked up in file
+    pub fn stack_transition(&self, file: &IrFile) -> (usize, usize) {
+        match self {
+            Inst::Nop | Inst::Br(_) | Inst::Leave(_) | Inst::EndFinally => (0, 0),
+
+            Inst::LdArg0
+            | Inst::LdArg1
+            | Inst::LdArg2
+            | Inst::LdArg3
+            | Inst::LdArgS(_)
+            | Inst::LdArgAS(_)
+            | Inst::LdLoc0
+            | Inst::LdLoc1
+            | Inst::LdLoc2
+            | Inst::LdLoc3
+            | Inst::LdLocS(_)
+            | Inst::LdLocAS(_)
+            | Inst::LdLoc(_)
+            | Inst::LdLocA(_)
+            | Inst::LdNull
+            | Inst::LdCM1
+            | Inst::LdC0
+            | Inst::LdC1
+            | Inst::LdC2
+            | Inst::LdC3
+            | Inst::LdC4
+            | Inst::LdC5
+            | Inst::LdC6
+            | Inst::LdC7
+            | Inst::LdC8
+            | Inst::LdCI4S(_)
+            | Inst::LdCI4(_)
+            | Inst::LdCI8(_)
+            | Inst::LdCR4(_)
+            | Inst::LdCR8(_)
+            | Inst::LdSFld(_)
+            | Inst::LdSFldA(_)
+            | Inst::LdStr(_) => (0, 1),
+
+            Inst::StArgS(_)
+            | Inst::StLoc0
+            | Inst::StLoc1
+            | Inst::StLoc2
+            | Inst::StLoc3
+            | Inst::StLocS(_)
+            | Inst::StLoc(_)
+            | Inst::Pop
+            | Inst::BrFalse(_)
+            | Inst::BrTrue(_)
+            | Inst::Throw
+            | Inst::StSFld(_)
+            | Inst::InitObj(_) => (1, 0),
+
+            Inst::Dup => (1, 2),
+
+            Inst::Neg
+            | Inst::Not
+            | Inst::ConvI1
+            | Inst::ConvI2
+            | Inst::ConvI4
+            | Inst::ConvI8
+            | Inst::ConvR4
+            | Inst::ConvR8
+            | Inst::ConvU4
+            | Inst::ConvU8
+            | Inst::ConvRUn
+            | Inst::ConvU2
+            | Inst::ConvU1
+            | Inst::ConvI
+            | Inst::ConvU
+            | Inst::ConvOvfI1
+            | Inst::ConvOvfU1
+            | Inst::ConvOvfI2
+            | Inst::ConvOvfU2
+            | Inst::ConvOvfI4
+            | Inst::ConvOvfU4
+            | Inst::ConvOvfI8
+            | Inst::ConvOvfU8
+            | Inst::ConvOvfI
+            | Inst::ConvOvfU
+            | Inst::ConvOvfI1Un
+            | Inst::ConvOvfI2Un
+            | Inst::ConvOvfI4Un
+            | Inst::ConvOvfI8Un
+            | Inst::ConvOvfU1Un
+            | Inst::ConvOvfU2Un
+            | Inst::ConvOvfU4Un
+            | Inst::ConvOvfU8Un
+            | Inst::ConvOvfIUn
+            | Inst::ConvOvfUUn
+            | Inst::CastClass(_)
+            | Inst::IsInst(_)
+            | Inst::LdFld(_)
+            | Inst::LdFldA(_)
+            | Inst::NewArr(_)
+            | Inst::LdLen
+            | Inst::LdIndI1
+            | Inst::LdIndU1
+            | Inst::LdIndI2
+            | Inst::LdIndU2
+            | Inst::LdIndI4
+            | Inst::LdIndU4
+            | Inst::LdIndI8
+            | Inst::LdIndI
+            | Inst::LdIndR4
+            | Inst::LdIndR8 => (1, 1),
+
+            Inst::BEq(_)
+            | Inst::BGe(_)
+            | Inst::BGt(_)
+            | Inst::BLe(_)
+            | Inst::BLt(_)
+            | Inst::BNe(_)
+            | Inst::StFld(_)
+            | Inst::StIndI1
+            | Inst::StIndI2
+            | Inst::StIndI4
+            | Inst::StIndI8
+            | Inst::StIndR4
+            | Inst::StIndR8
+            | Inst::StIndI => (2, 0),
+
+            Inst::CEq
+            | Inst::CGt
+            | Inst::CGtUn
+            | Inst::CLt
+            | Inst::CLtUn
+            | Inst::Add
+            | Inst::Sub
+            | Inst::Mul
+            | Inst::Div
+            | Inst::DivUn
+            | Inst::Rem
+            | Inst::RemUn
+            | Inst::And
+            | Inst::Or
+            | Inst::Xor
+            | Inst::Shl
+            | Inst::Shr
+            | Inst::ShrUn
+            | Inst::LdElemI1
+            | Inst::LdElemU1
+            | Inst::LdElemI2
+            | Inst::LdElemU2
+            | Inst::LdElemI4
+            | Inst::LdElemU4
+            | Inst::LdElemI8
+            | Inst::LdElemI
+            | Inst::LdElemR4
+            | Inst::LdElemR8
+            | Inst::LdElemRef
+            | Inst::LdElem(_)
+            | Inst::LdElemA(_) => (2, 1),
+
+            Inst::StElemI
+            | Inst::StElemI1
+            | Inst::StElemI2
+            | Inst::StElemI4
+            | Inst::StElemI8
+            | Inst::StElemR4
+            | Inst::StElemR8
+            | Inst::StElemRef
+            | Inst::StElem(_) => (3, 0),
+
+            // ret pops the return value if there is one, but stack is discarded anyway
+            Inst::Ret => (0, 0),
+
+            Inst::Call(tok) | Inst::CallVirt(tok) | Inst::NewObj(tok) => {
+                let (has_this, n_ps, has_ret) = method_tok_sig(*tok, file);
+                if let Inst::NewObj(_) = self {
+                    (n_ps, 1)
+                } else {
+                    (n_ps + has_this as usize, has_ret as usize)
+                }
+            }
+        }
+    }
+}
+
+/// (has this, number of params, has return value) of a method token
+fn method_tok_sig(tok: u32, file: &IrFile) -> (bool, usize, bool) {
+    let (tag, idx) = get_tok_tag(tok);
+    let sig = match tag {
+        TokTag::MethodDef => file.method_tbl[idx as usize - 1].sig,
+        TokTag::MemberRef => file.memberref_tbl[idx as usize - 1].sig,
+        TokTag::MethodSpec => {
+            let (method_tag, method_idx) = file.methodspec_tbl[idx as usize - 1].get_method();
+            return method_tok_sig(to_tok(method_idx, method_tag.to_tok_tag()), file);
+        }
+        _ => panic!("Invalid method token 0x{:08X}", tok),
+    };
+    if let IrSig::Method(flag, ps, ret) = &file.blob_heap[sig as usize] {
+        (
+            flag.has_flag(MethodSigFlagTag::HasThis),
+            ps.len(),
+            !matches!(ret.ty, InnerRetType::Void),
+        )
+    } else {
+        panic!("Invalid method token 0x{:08X}", tok);
+    }
 }
@@ -76,6 +76,18 @@ impl ISerializable for Inst {
                 0x20u8.serialize(buf);
                 num.serialize(buf);
             }
+            Inst::LdCI8(num) => {
+                0x21u8.serialize(buf);
+                num.serialize(buf);
+            }
+            Inst::LdCR4(num) => {
+                0x22u8.serialize(buf);
+                num.serialize(buf);
+            }
+            Inst::LdCR8(num) => {
+                0x23u8.serialize(buf);
+                num.serialize(buf);
+            }
 
             Inst::Dup => 0x25u8.serialize(buf),
             Inst::Pop => 0x26u8.serialize(buf),
@@ -118,18 +130,85 @@ impl ISerializable for Inst {
                 0x3Fu8.serialize(buf);
                 offset.serialize(buf);
             }
+            Inst::BNe(offset) => {
+                0x40u8.serialize(buf);
+                offset.serialize(buf);
+            }
 
             Inst::CEq => 0xFE01u16.serialize(buf),
             Inst::CGt => 0xFE02u16.serialize(buf),
+            Inst::CGtUn => 0xFE03u16.serialize(buf),
             Inst::CLt => 0xFE04u16.serialize(buf),
+            Inst::CLtUn => 0xFE05u16.serialize(buf),
+
+            Inst::LdIndI1 => 0x46u8.serialize(buf),
+            Inst::LdIndU1 => 0x47u8.serialize(buf),
+            Inst::LdIndI2 => 0x48u8.serialize(buf),
+            Inst::LdIndU2 => 0x49u8.serialize(buf),
+            Inst::LdIndI4 => 0x4Au8.serialize(buf),
+            Inst::LdIndU4 => 0x4Bu8.serialize(buf),
+            Inst::LdIndI8 => 0x4Cu8.serialize(buf),
+            Inst::LdIndI => 0x4Du8.serialize(buf),
+            Inst::LdIndR4 => 0x4Eu8.serialize(buf),
+            Inst::LdIndR8 => 0x4Fu8.serialize(buf),
+            Inst::StIndI1 => 0x52u8.serialize(buf),
+            Inst::StIndI2 => 0x53u8.serialize(buf),
+            Inst::StIndI4 => 0x54u8.serialize(buf),
+            Inst::StIndI8 => 0x55u8.serialize(buf),
+            Inst::StIndR4 => 0x56u8.serialize(buf),
+            Inst::StIndR8 => 0x57u8.serialize(buf),
+            Inst::StIndI => 0xDFu8.serialize(buf),
 
             Inst::Add => 0x58u8.serialize(buf),
             Inst::Sub => 0x59u8.serialize(buf),
             Inst::Mul => 0x5Au8.serialize(buf),
             Inst::Div => 0x5Bu8.serialize(buf),
+            Inst::DivUn => 0x5Cu8.serialize(buf),
             Inst::Rem => 0x5Du8.serialize(buf),
+            Inst::RemUn => 0x5Eu8.serialize(buf),
+            Inst::And => 0x5Fu8.serialize(buf),
+            Inst::Or => 0x60u8.serialize(buf),
+            Inst::Xor => 0x61u8.serialize(buf),
+            Inst::Shl => 0x62u8.serialize(buf),
+            Inst::Shr => 0x63u8.serialize(buf),
+            Inst::ShrUn => 0x64u8.serialize(buf),
 
             Inst::Neg => 0x65u8.serialize(buf),
+            Inst::Not => 0x66u8.serialize(buf),
+
+            Inst::ConvI1 => 0x67u8.serialize(buf),
+            Inst::ConvI2 => 0x68u8.serialize(buf),
+            Inst::ConvI4 => 0x69u8.serialize(buf),
+            Inst::ConvI8 => 0x6Au8.serialize(buf),
+            Inst::ConvR4 => 0x6Bu8.serialize(buf),
+            Inst::ConvR8 => 0x6Cu8.serialize(buf),
+            Inst::ConvU4 => 0x6Du8.serialize(buf),
+            Inst::ConvU8 => 0x6Eu8.serialize(buf),
+            Inst::ConvRUn => 0x76u8.serialize(buf),
+            Inst::ConvU2 => 0xD1u8.serialize(buf),
+            Inst::ConvU1 => 0xD2u8.serialize(buf),
+            Inst::ConvI => 0xD3u8.serialize(buf),
+            Inst::ConvU => 0xE0u8.serialize(buf),
+            Inst::ConvOvfI1 => 0xB3u8.serialize(buf),
+            Inst::ConvOvfU1 => 0xB4u8.serialize(buf),
+            Inst::ConvOvfI2 => 0xB5u8.serialize(buf),
+            Inst::ConvOvfU2 => 0xB6u8.serialize(buf),
+            Inst::ConvOvfI4 => 0xB7u8.serialize(buf),
+            Inst::ConvOvfU4 => 0xB8u8.serialize(buf),
+            Inst::ConvOvfI8 => 0xB9u8.serialize(buf),
+            Inst::ConvOvfU8 => 0xBAu8.serialize(buf),
+            Inst::ConvOvfI => 0xD4u8.serialize(buf),
+            Inst::ConvOvfU => 0xD5u8.serialize(buf),
+            Inst::ConvOvfI1Un => 0x82u8.serialize(buf),
+            Inst::ConvOvfI2Un => 0x83u8.serialize(buf),
+            Inst::ConvOvfI4Un => 0x84u8.serialize(buf),
+            Inst::ConvOvfI8Un => 0x85u8.serialize(buf),
+            Inst::ConvOvfU1Un => 0x86u8.serialize(buf),
+            Inst::ConvOvfU2Un => 0x87u8.serialize(buf),
+            Inst::ConvOvfU4Un => 0x88u8.serialize(buf),
+            Inst::ConvOvfU8Un => 0x89u8.serialize(buf),
+            Inst::ConvOvfIUn => 0x8Au8.serialize(buf),
+            Inst::ConvOvfUUn => 0x8Bu8.serialize(buf),
 
             Inst::CallVirt(idx) => {
                 0x6Fu8.serialize(buf);
@@ -143,6 +222,15 @@ impl ISerializable for Inst {
                 0x73u8.serialize(buf);
                 tok.serialize(buf);
             }
+            Inst::CastClass(tok) => {
+                0x74u8.serialize(buf);
+                tok.serialize(buf);
+            }
+            Inst::IsInst(tok) => {
+                0x75u8.serialize(buf);
+                tok.serialize(buf);
+            }
+            Inst::Throw => 0x7Au8.serialize(buf),
             Inst::LdFld(tok) => {
                 0x7Bu8.serialize(buf);
                 tok.serialize(buf);
@@ -175,9 +263,24 @@ impl ISerializable for Inst {
 
             Inst::LdLen => 0x8Eu8.serialize(buf),
 
+            Inst::LdElemI1 => 0x90u8.serialize(buf),
+            Inst::LdElemU1 => 0x91u8.serialize(buf),
+            Inst::LdElemI2 => 0x92u8.serialize(buf),
+            Inst::LdElemU2 => 0x93u8.serialize(buf),
             Inst::LdElemI4 => 0x94u8.serialize(buf),
+            Inst::LdElemU4 => 0x95u8.serialize(buf),
+            Inst::LdElemI8 => 0x96u8.serialize(buf),
+            Inst::LdElemI => 0x97u8.serialize(buf),
+            Inst::LdElemR4 => 0x98u8.serialize(buf),
+            Inst::LdElemR8 => 0x99u8.serialize(buf),
             Inst::LdElemRef => 0x9Au8.serialize(buf),
+            Inst::StElemI => 0x9Bu8.serialize(buf),
+            Inst::StElemI1 => 0x9Cu8.serialize(buf),
+            Inst::StElemI2 => 0x9Du8.serialize(buf),
             Inst::StElemI4 => 0x9Eu8.serialize(buf),
+            Inst::StElemI8 => 0x9Fu8.serialize(buf),
+            Inst::StElemR4 => 0xA0u8.serialize(buf),
+            Inst::StElemR8 => 0xA1u8.serialize(buf),
             Inst::StElemRef => 0xA2u8.serialize(buf),
 
             Inst::LdElem(tok) => {
@@ -193,6 +296,12 @@ impl ISerializable for Inst {
                 tok.serialize(buf);
             }
 
+            Inst::EndFinally => 0xDCu8.serialize(buf),
+            Inst::Leave(offset) => {
+                0xDDu8.serialize(buf);
+                offset.serialize(buf);
+            }
+
             Inst::InitObj(tok) => {
                 0xFE15u16.serialize(buf);
                 tok.serialize(buf);
@@ -201,8 +310,16 @@ impl ISerializable for Inst {
     }
 
     fn deserialize(buf: &mut dyn IDeserializer) -> Inst {
+        let code = buf.peek_byte();
+        Inst::try_deserialize(buf).unwrap_or_else(|| panic!("Unknown inst: 0x{:X}", code))
+    }
+}
+
+impl Inst {
+    /// Deserialize an inst, return None if opcode is unknown
+    pub fn try_deserialize(buf: &mut dyn IDeserializer) -> Option<Inst> {
         let code = u8::deserialize(buf);
-        match code {
+        Some(match code {
             0x00 => Inst::Nop,
 
             0x02 => Inst::LdArg0,
@@ -239,6 +356,9 @@ impl ISerializable for Inst {
             0x1E => Inst::LdC8,
             0x1F => Inst::LdCI4S(i8::deserialize(buf)),
             0x20 => Inst::LdCI4(i32::deserialize(buf)),
+            0x21 => Inst::LdCI8(i64::deserialize(buf)),
+            0x22 => Inst::LdCR4(f32::deserialize(buf)),
+            0x23 => Inst::LdCR8(f64::deserialize(buf)),
 
             0x25 => Inst::Dup,
             0x26 => Inst::Pop,
@@ -255,49 +375,133 @@ impl ISerializable for Inst {
             0x3D => Inst::BGt(i32::deserialize(buf)),
             0x3E => Inst::BLe(i32::deserialize(buf)),
             0x3F => Inst::BLt(i32::deserialize(buf)),
+            0x40 => Inst::BNe(i32::deserialize(buf)),
+
+            0x46 => Inst::LdIndI1,
+            0x47 => Inst::LdIndU1,
+            0x48 => Inst::LdIndI2,
+            0x49 => Inst::LdIndU2,
+            0x4A => Inst::LdIndI4,
+            0x4B => Inst::LdIndU4,
+            0x4C => Inst::LdIndI8,
+            0x4D => Inst::LdIndI,
+            0x4E => Inst::LdIndR4,
+            0x4F => Inst::LdIndR8,
+            0x52 => Inst::StIndI1,
+            0x53 => Inst::StIndI2,
+            0x54 => Inst::StIndI4,
+            0x55 => Inst::StIndI8,
+            0x56 => Inst::StIndR4,
+            0x57 => Inst::StIndR8,
 
             0x58 => Inst::Add,
             0x59 => Inst::Sub,
             0x5A => Inst::Mul,
             0x5B => Inst::Div,
+            0x5C => Inst::DivUn,
             0x5D => Inst::Rem,
+            0x5E => Inst::RemUn,
+            0x5F => Inst::And,
+            0x60 => Inst::Or,
+            0x61 => Inst::Xor,
+            0x62 => Inst::Shl,
+            0x63 => Inst::Shr,
+            0x64 => Inst::ShrUn,
 
             0x65 => Inst::Neg,
+            0x66 => Inst::Not,
+
+            0x67 => Inst::ConvI1,
+            0x68 => Inst::ConvI2,
+            0x69 => Inst::ConvI4,
+            0x6A => Inst::ConvI8,
+            0x6B => Inst::ConvR4,
+            0x6C => Inst::ConvR8,
+            0x6D => Inst::ConvU4,
+            0x6E => Inst::ConvU8,
 
             0x6F => Inst::CallVirt(u32::deserialize(buf)),
             0x72 => Inst::LdStr(u32::deserialize(buf)),
             0x73 => Inst::NewObj(u32::deserialize(buf)),
+            0x74 => Inst::CastClass(u32::deserialize(buf)),
+            0x75 => Inst::IsInst(u32::deserialize(buf)),
+            0x76 => Inst::ConvRUn,
+            0x7A => Inst::Throw,
             0x7B => Inst::LdFld(u32::deserialize(buf)),
             0x7C => Inst::LdFldA(u32::deserialize(buf)),
             0x7D => Inst::StFld(u32::deserialize(buf)),
             0x7E => Inst::LdSFld(u32::deserialize(buf)),
             0x7F => Inst::LdSFldA(u32::deserialize(buf)),
             0x80 => Inst::StSFld(u32::deserialize(buf)),
+            0x82 => Inst::ConvOvfI1Un,
+            0x83 => Inst::ConvOvfI2Un,
+            0x84 => Inst::ConvOvfI4Un,
+            0x85 => Inst::ConvOvfI8Un,
+            0x86 => Inst::ConvOvfU1Un,
+            0x87 => Inst::ConvOvfU2Un,
+            0x88 => Inst::ConvOvfU4Un,
+            0x89 => Inst::ConvOvfU8Un,
+            0x8A => Inst::ConvOvfIUn,
+            0x8B => Inst::ConvOvfUUn,
 
             0x8D => Inst::NewArr(u32::deserialize(buf)),
             0x8E => Inst::LdLen,
             0x8F => Inst::LdElemA(u32::deserialize(buf)),
+            0x90 => Inst::LdElemI1,
+            0x91 => Inst::LdElemU1,
+            0x92 => Inst::LdElemI2,
+            0x93 => Inst::LdElemU2,
             0x94 => Inst::LdElemI4,
+            0x95 => Inst::LdElemU4,
+            0x96 => Inst::LdElemI8,
+            0x97 => Inst::LdElemI,
+            0x98 => Inst::LdElemR4,
+            0x99 => Inst::LdElemR8,
             0x9A => Inst::LdElemRef,
+            0x9B => Inst::StElemI,
+            0x9C => Inst::StElemI1,
+            0x9D => Inst::StElemI2,
             0x9E => Inst::StElemI4,
+            0x9F => Inst::StElemI8,
+            0xA0 => Inst::StElemR4,
+            0xA1 => Inst::StElemR8,
             0xA2 => Inst::StElemRef,
             0xA3 => Inst::LdElem(u32::deserialize(buf)),
             0xA4 => Inst::StElem(u32::deserialize(buf)),
+            0xB3 => Inst::ConvOvfI1,
+            0xB4 => Inst::ConvOvfU1,
+            0xB5 => Inst::ConvOvfI2,
+            0xB6 => Inst::ConvOvfU2,
+            0xB7 => Inst::ConvOvfI4,
+            0xB8 => Inst::ConvOvfU4,
+            0xB9 => Inst::ConvOvfI8,
+            0xBA => Inst::ConvOvfU8,
+            0xD1 => Inst::ConvU2,
+            0xD2 => Inst::ConvU1,
+            0xD3 => Inst::ConvI,
+            0xD4 => Inst::ConvOvfI,
+            0xD5 => Inst::ConvOvfU,
+            0xDC => Inst::EndFinally,
+            0xDD => Inst::Leave(i32::deserialize(buf)),
+            0xDF => Inst::StIndI,
+            0xE0 => Inst::ConvU,
 
             0xFE => {
                 let inner_code = u8::deserialize(buf);
                 match inner_code {
                     0x01 => Inst::CEq,
                     0x02 => Inst::CGt,
+                    0x03 => Inst::CGtUn,
                     0x04 => Inst::CLt,
+                    0x05 => Inst::CLtUn,
                     0x0C => Inst::LdLoc(u16::deserialize(buf)),
                     0x0D => Inst::LdLocA(u16::deserialize(buf)),
                     0x0E => Inst::StLoc(u16::deserialize(buf)),
                     0x15 => Inst::InitObj(u32::deserialize(buf)),
-                    _ => panic!("Unknown inst 0xFE{:X}", inner_code),
+                    _ => return None,
                 }
             }
-            _ => panic!("Unknown inst: 0x{:X}", code),
-        }
+            _ => return None,
+        })
     }
 }
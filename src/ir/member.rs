@@ -21,6 +21,7 @@ impl IrFmt for MemberRef {
             MemberRefParent::TypeRef => ctx.typeref_tbl[idx as usize - 1].fullname(f, ctx)?,
             MemberRefParent::ModRef => ctx.modref_tbl[idx as usize - 1].fmt(f, ctx)?,
             MemberRefParent::MethodDef => ctx.method_tbl[idx as usize - 1].fmt(f, ctx)?,
+            MemberRefParent::TypeSpec => ctx.typespec_tbl[idx as usize - 1].fmt(f, ctx)?,
         };
         write!(f, "::{}: ", ctx.get_str(self.name))?;
         ctx.blob_heap[self.sig as usize].fmt(f, ctx)
@@ -41,7 +42,7 @@ impl MemberRef {
                 1 => MemberRefParent::TypeRef,
                 2 => MemberRefParent::ModRef,
                 3 => MemberRefParent::MethodDef,
-                4 => unreachable!(),
+                4 => MemberRefParent::TypeSpec,
                 _ => unreachable!(),
             },
             index,
@@ -58,7 +59,7 @@ pub enum MemberRefParent {
     TypeRef = 1,
     ModRef = 2,
     MethodDef = 3,
-    // TypeSpec = 4
+    TypeSpec = 4,
 }
 
 pub fn to_memberref_parent(raw_idx: u32, tag: MemberRefParent) -> u32 {
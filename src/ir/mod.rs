@@ -1,6 +1,6 @@
 #[macro_use]
 mod bc_serde;
-mod text_serde;
+pub mod text_serde;
 
 pub mod attrib;
 pub mod sig;
@@ -8,11 +8,14 @@ pub mod tok;
 pub mod util;
 
 pub mod code;
+pub mod debug;
 pub mod file;
 pub mod generic;
 pub mod inst;
 pub mod member;
 pub mod module;
 pub mod param;
+pub mod parser;
 pub mod stand_alone_sig;
 pub mod ty;
+pub mod verifier;
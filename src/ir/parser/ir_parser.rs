@@ -1,28 +1,1347 @@
-use std::fs;
-use std::path::Path;
+// pest errors are large, but boxing them makes the parser verbose
+#![allow(clippy::result_large_err)]
 
-use super::super::blob::*;
-use super::super::ir_file::*;
+use super::super::attrib::*;
+use super::super::code::{CorILMethod, ExceptionClause, ExceptionClauseKind};
+use super::super::debug::{Document, LocalScope, LocalVarInfo, MethodDebugInfo, SequencePoint};
+use super::super::file::IrFile;
+use super::super::generic::{
+    to_methoddeforref, to_type_or_methoddef, GenericParam, GenericParamConstraint, MethodDefOrRef,
+    MethodSpec, TypeOrMethodDef,
+};
+use super::super::inst::Inst;
+use super::super::member::{
+    to_implmap_member, to_memberref_parent, Field, ImplMap, MemberForwarded, MemberRef,
+    MemberRefParent, MethodDef,
+};
+use super::super::module::{Mod, ModRef};
+use super::super::param::Param;
+use super::super::sig::{
+    InnerLocalVarType, InnerParamType, InnerRetType, IrSig, MethodSigFlag, MethodSigFlagTag,
+    ParamType, RetType, TypeSig, TypeSpecSig,
+};
+use super::super::stand_alone_sig::IrStandAloneSig;
+use super::super::tok::{get_tok_tag, to_tok, TokTag};
+use super::super::ty::{
+    get_typeref_parent, to_typedeforref, InterfaceImpl, ResolutionScope, TypeDef, TypeDefOrRef,
+    TypeRef, TypeSpec,
+};
 
-use pest::error::Error;
+use pest::error::{Error, ErrorVariant};
 use pest::iterators::Pair;
-use pest::Parser;
+use pest::{Parser, Span};
+use pest_derive::Parser;
+
+use std::char;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 
 #[derive(Parser)]
-#[grammar = "ir/text_serde/ir.pest"]
-struct IRParser;
+#[grammar = "ir/parser/ir.pest"]
+struct IrParser;
 
+type ParseResult<T> = Result<T, Error<Rule>>;
+
+/// Parse a .xir file
 pub fn parse(path: &Path) -> Result<IrFile, Error<Rule>> {
-    let code = fs::read_to_string(path).unwrap();
+    let code = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Cannot read {}: {}", path.display(), e));
+    parse_str(&code).map_err(|e| e.with_path(&path.to_string_lossy()))
+}
+
+/// Parse IR in text format, which is the same as the output of `IrFile::fmt`
+pub fn parse_str(code: &str) -> Result<IrFile, Error<Rule>> {
+    let file = IrParser::parse(Rule::File, code)?.next().unwrap();
+    let mut asm = Assembler::new();
+    asm.build(file)?;
+    Ok(asm.file)
+}
+
+fn error<T>(span: Span, msg: String) -> ParseResult<T> {
+    Err(Error::new_from_span(
+        ErrorVariant::CustomError { message: msg },
+        span,
+    ))
+}
+
+/// Descriptor of types and sigs, e.g. "instance(i4,std/String)->void"
+fn descriptor(p: &Pair<Rule>) -> String {
+    p.as_str().chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn parse_int<T: FromStr>(p: &Pair<Rule>) -> ParseResult<T> {
+    match p.as_str().parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(_) => error(p.as_span(), format!("Invalid integer {}", p.as_str())),
+    }
+}
+
+/// Float or Int operand as float
+fn parse_float<T: FromStr>(p: &Pair<Rule>) -> ParseResult<T> {
+    match p.as_rule() {
+        Rule::Float | Rule::Int => match p.as_str().parse::<T>() {
+            Ok(v) => Ok(v),
+            Err(_) => error(p.as_span(), format!("Invalid float {}", p.as_str())),
+        },
+        _ => error(p.as_span(), String::from("Expect float literal")),
+    }
+}
+
+/// Content of String rule with escape sequences unescaped
+fn parse_str_lit(p: Pair<Rule>) -> ParseResult<String> {
+    let span = p.as_span();
+    let mut ret = String::new();
+    let mut chars = p.into_inner().next().unwrap().as_str().chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next().unwrap() {
+            'n' => ret.push('\n'),
+            't' => ret.push('\t'),
+            'r' => ret.push('\r'),
+            '0' => ret.push('\0'),
+            '\\' => ret.push('\\'),
+            '\'' => ret.push('\''),
+            '"' => ret.push('"'),
+            'u' => {
+                // \u{XXXX}
+                if chars.next() != Some('{') {
+                    return error(span, String::from("Expect { after \\u"));
+                }
+                let code: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    Some(c) => ret.push(c),
+                    None => return error(span, format!("Invalid unicode escape \\u{{{}}}", code)),
+                }
+            }
+            c => return error(span, format!("Unknown escape sequence \\{}", c)),
+        }
+    }
+    Ok(ret)
+}
+
+/// split `name: ty` of NamedParam or NamedRet
+fn split_named<'i>(p: Pair<'i, Rule>) -> (Option<&'i str>, Pair<'i, Rule>) {
+    let mut iter = p.into_inner();
+    let first = iter.next().unwrap();
+    if let Rule::Id = first.as_rule() {
+        (Some(first.as_str()), iter.next().unwrap())
+    } else {
+        (None, first)
+    }
+}
+
+/// Labels are resolved after all insts of the method are parsed.
+/// Undefined labels like IL_001A are treated as offsets,
+/// which is necessary for the end of protected regions or handlers
+fn resolve_offset(labels: &HashMap<&str, usize>, label: &Pair<Rule>) -> ParseResult<u32> {
+    let name = label.as_str();
+    if let Some(offset) = labels.get(name) {
+        Ok(*offset as u32)
+    } else if let Some(offset) = name
+        .strip_prefix("IL_")
+        .and_then(|offset| u32::from_str_radix(offset, 16).ok())
+    {
+        Ok(offset)
+    } else {
+        error(label.as_span(), format!("Undefined label {}", name))
+    }
+}
+
+/// (parent typedef or 0 if has no parent, name, sig descriptor)
+type MemberKey = (u32, String, String);
+
+/// Similar to lang::gen::Builder, but it builds IrFile from text
+struct Assembler {
+    mod_name: String,
+
+    /// Name -> TblIdx
+    modref_map: HashMap<String, u32>,
+
+    /// Name -> TblIdx, all classes are registered before parsing members
+    type_map: HashMap<String, u32>,
+    /// TypeRef -> TblIdx
+    typeref_map: HashMap<TypeRef, u32>,
+    /// descriptor -> TblIdx
+    typespec_map: HashMap<String, u32>,
+
+    /// MemberKey -> IdxIntoFieldTbl
+    field_map: HashMap<MemberKey, u32>,
+    method_map: HashMap<MemberKey, u32>,
+    /// MemberRef -> TblIdx
+    memberref_map: HashMap<MemberRef, u32>,
+    /// (MethodDefOrRef, Instantiation) -> TblIdx
+    methodspec_map: HashMap<(u32, u32), u32>,
+
+    /// str -> StrHeapIdx
+    str_map: HashMap<String, u32>,
+    /// str -> UsrStrHeapIdx
+    usr_str_map: HashMap<String, u32>,
+
+    /// descriptor -> blob head index
+    member_sig_map: HashMap<String, u32>,
+
+    file: IrFile,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            mod_name: String::new(),
+
+            modref_map: HashMap::new(),
+
+            type_map: HashMap::new(),
+            typeref_map: HashMap::new(),
+            typespec_map: HashMap::new(),
+
+            field_map: HashMap::new(),
+            method_map: HashMap::new(),
+            memberref_map: HashMap::new(),
+            methodspec_map: HashMap::new(),
+
+            str_map: HashMap::new(),
+            usr_str_map: HashMap::new(),
+
+            member_sig_map: HashMap::new(),
+
+            file: IrFile::new(),
+        }
+    }
+
+    fn build(&mut self, file: Pair<Rule>) -> ParseResult<()> {
+        let items: Vec<Pair<Rule>> = file.into_inner().collect();
+
+        // 1. register classes so that they can be referenced before declaration
+        for item in items.iter() {
+            if let Rule::Class = item.as_rule() {
+                let name = item
+                    .clone()
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::Id)
+                    .unwrap();
+                let idx = self.type_map.len() as u32 + 1;
+                if self
+                    .type_map
+                    .insert(name.as_str().to_owned(), idx)
+                    .is_some()
+                {
+                    return error(
+                        name.as_span(),
+                        format!("Duplicated class {}", name.as_str()),
+                    );
+                }
+            }
+        }
+
+        // 2. declare fields and methods
+        let mut bodies = Vec::new();
+        for item in items.into_iter() {
+            match item.as_rule() {
+                Rule::Version => {
+                    let mut iter = item.into_inner();
+                    self.file.major_version = parse_int(&iter.next().unwrap())?;
+                    self.file.minor_version = parse_int(&iter.next().unwrap())?;
+                }
+                Rule::ModDecl => {
+                    self.mod_name = item.into_inner().next().unwrap().as_str().to_owned();
+                    let name = self.add_const_str(&self.mod_name.clone());
+                    self.file.mod_tbl.push(Mod {
+                        name,
+                        entrypoint: 0,
+                    });
+                }
+                Rule::Extern => {
+                    let name = parse_str_lit(item.into_inner().next().unwrap())?;
+                    self.add_const_mod(&name);
+                }
+                Rule::Document => {
+                    let name = parse_str_lit(item.into_inner().next().unwrap())?;
+                    let name = self.add_const_str(&name);
+                    self.file.document_tbl.push(Document { name });
+                }
+                Rule::Field => self.build_field(item)?,
+                Rule::Method => self.build_method(item, &mut bodies)?,
+                Rule::Class => self.build_class(item, &mut bodies)?,
+                Rule::EOI => {}
+                _ => unreachable!(),
+            }
+        }
+
+        // 3. method bodies, all members are declared now
+        for (method_idx, body) in bodies.into_iter() {
+            self.build_body(method_idx, body)?;
+        }
+
+        Ok(())
+    }
+
+    fn build_class<'i>(
+        &mut self,
+        class: Pair<'i, Rule>,
+        bodies: &mut Vec<(u32, Pair<'i, Rule>)>,
+    ) -> ParseResult<()> {
+        let mut flag = TypeAttrib::from(0);
+        let mut class_idx = 0;
+        for p in class.into_inner() {
+            match p.as_rule() {
+                Rule::TypeFlag => match p.as_str() {
+                    "pub" => flag.set_vis(TypeAttribVisFlag::Pub),
+                    "crate" => flag.set_vis(TypeAttribVisFlag::Crate),
+                    "priv" => flag.set_vis(TypeAttribVisFlag::Priv),
+                    "interface" => flag.set_sem(TypeAttribSemFlag::Interface),
+                    "sealed" => flag.set(TypeAttribFlag::Sealed),
+                    "abstract" => flag.set(TypeAttribFlag::Abstract),
+                    "enum" => flag.set(TypeAttribFlag::Enum),
+                    _ => unreachable!(),
+                },
+                Rule::Id => {
+                    let name = self.add_const_str(p.as_str());
+                    self.file.typedef_tbl.push(TypeDef {
+                        flag: flag.attrib,
+                        name,
+                        extends: 0,
+                        fields: self.file.field_tbl.len() as u32 + 1,
+                        methods: self.file.method_tbl.len() as u32 + 1,
+                    });
+                    class_idx = self.file.typedef_tbl.len() as u32;
+                }
+                Rule::GenericParams => self.build_generic_params(
+                    to_type_or_methoddef(class_idx, TypeOrMethodDef::TypeDef),
+                    p,
+                )?,
+                Rule::Extends => {
+                    let (idx, tag) = self.build_typedeforref(p.into_inner().next().unwrap())?;
+                    self.file.typedef_tbl[class_idx as usize - 1].set_extends(idx, tag);
+                }
+                Rule::Implements => {
+                    for ty in p.into_inner() {
+                        let (idx, tag) = self.build_typedeforref(ty)?;
+                        self.file.interfaceimpl_tbl.push(InterfaceImpl {
+                            class: class_idx,
+                            interface: to_typedeforref(idx, tag),
+                        });
+                    }
+                }
+                Rule::Field => self.build_field(p)?,
+                Rule::Method => self.build_method(p, bodies)?,
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    fn build_generic_params(&mut self, owner: u32, params: Pair<Rule>) -> ParseResult<()> {
+        for (i, p) in params.into_inner().enumerate() {
+            let mut iter = p.into_inner();
+            let name = self.add_const_str(iter.next().unwrap().as_str());
+            self.file.generic_param_tbl.push(GenericParam {
+                number: i as u16,
+                flag: GenericParamAttrib::default().attirb,
+                owner,
+                name,
+            });
+            let param_idx = self.file.generic_param_tbl.len() as u32;
+            for constraint in iter {
+                let (idx, tag) = self.build_typedeforref(constraint)?;
+                self.file
+                    .generic_param_constraint_tbl
+                    .push(GenericParamConstraint {
+                        owner: param_idx,
+                        constraint: to_typedeforref(idx, tag),
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    /// Field parent is the newly added class or none if no class has been added
+    fn build_field(&mut self, field: Pair<Rule>) -> ParseResult<()> {
+        let mut flag = FieldAttrib::from(0);
+        flag.set(FieldAttribFlag::Priv);
+        let mut iter = field.into_inner();
+        let mut p = iter.next().unwrap();
+        while let Rule::FieldFlag = p.as_rule() {
+            flag.set(match p.as_str() {
+                "pub" => FieldAttribFlag::Pub,
+                "crate" => FieldAttribFlag::Crate,
+                "priv" => FieldAttribFlag::Priv,
+                "static" => FieldAttribFlag::Static,
+                _ => unreachable!(),
+            });
+            p = iter.next().unwrap();
+        }
+        let id = p;
+        let ty = iter.next().unwrap();
+
+        let desc = descriptor(&ty);
+        let sig = IrSig::Field(self.build_type_sig(ty)?);
+        let sig = self.add_member_sig(desc.clone(), sig);
+        let name = self.add_const_str(id.as_str());
+        self.file.field_tbl.push(Field {
+            name,
+            sig,
+            flag: flag.attrib,
+        });
+
+        let key = (
+            self.file.typedef_tbl.len() as u32,
+            id.as_str().to_owned(),
+            desc,
+        );
+        if self
+            .field_map
+            .insert(key, self.file.field_tbl.len() as u32)
+            .is_some()
+        {
+            return error(id.as_span(), format!("Duplicated field {}", id.as_str()));
+        }
+        Ok(())
+    }
+
+    /// Method parent is the newly added class or none if no class has been added
+    ///
+    /// Method body is pushed to bodies and will be built after all members are declared
+    fn build_method<'i>(
+        &mut self,
+        method: Pair<'i, Rule>,
+        bodies: &mut Vec<(u32, Pair<'i, Rule>)>,
+    ) -> ParseResult<()> {
+        let mut flag = MethodAttrib::from(0);
+        flag.set(MethodAttribFlag::Priv);
+        let mut impl_flag = MethodImplAttrib::new(
+            MethodImplAttribCodeTypeFlag::IL,
+            MethodImplAttribManagedFlag::Managed,
+        );
+        let mut pinvoke = None;
+        let mut id = None;
+        let mut generic_params = None;
+        let mut is_instance = false;
+        let mut ps = Vec::new();
+        let mut ret = None;
+        let mut body = None;
+        for p in method.into_inner() {
+            match p.as_rule() {
+                Rule::MethodFlag => flag.set(match p.as_str() {
+                    "pub" => MethodAttribFlag::Pub,
+                    "crate" => MethodAttribFlag::Crate,
+                    "priv" => MethodAttribFlag::Priv,
+                    "static" => MethodAttribFlag::Static,
+                    "virtual" => MethodAttribFlag::Virtual,
+                    "newslot" => MethodAttribFlag::NewSlot,
+                    "abstract" => MethodAttribFlag::Abstract,
+                    "specialname" => MethodAttribFlag::SpecialName,
+                    "rtspecialname" => MethodAttribFlag::RTSpecialName,
+                    _ => unreachable!(),
+                }),
+                Rule::PInvokeImpl => pinvoke = Some(p),
+                Rule::Id => id = Some(p),
+                Rule::GenericParams => generic_params = Some(p),
+                Rule::Instance => is_instance = true,
+                Rule::NamedParam => ps.push(split_named(p)),
+                Rule::NamedRet => ret = Some(split_named(p)),
+                Rule::ImplFlag => match p.as_str() {
+                    "managed" => impl_flag.set_managed(MethodImplAttribManagedFlag::Managed),
+                    "unmanaged" => impl_flag.set_managed(MethodImplAttribManagedFlag::Unmanaged),
+                    "cil" => impl_flag.set_code_ty(MethodImplAttribCodeTypeFlag::IL),
+                    "native" => impl_flag.set_code_ty(MethodImplAttribCodeTypeFlag::Native),
+                    "runtime" => impl_flag.set_code_ty(MethodImplAttribCodeTypeFlag::Runtime),
+                    "internalcall" => impl_flag.set_impl_info(MethodImplInfoFlag::InternalCall),
+                    _ => unreachable!(),
+                },
+                Rule::MethodBody => body = Some(p),
+                _ => unreachable!(),
+            }
+        }
+        let id = id.unwrap();
+        let (ret_name, ret) = ret.unwrap();
+
+        // sig
+        let mut ps_desc = Vec::new();
+        let mut ps_ty = Vec::new();
+        for (_, p) in ps.iter() {
+            ps_desc.push(descriptor(p));
+            ps_ty.push(self.build_param_type(p.clone())?);
+        }
+        let desc = format!(
+            "{}({})->{}",
+            if is_instance { "instance" } else { "" },
+            ps_desc.join(","),
+            descriptor(&ret)
+        );
+        let sig_flag = if is_instance {
+            MethodSigFlag::new(MethodSigFlagTag::HasThis)
+        } else {
+            MethodSigFlag::new(MethodSigFlagTag::Default)
+        };
+        let ret = self.build_ret_type(ret)?;
+        let sig = self.add_member_sig(desc.clone(), IrSig::Method(sig_flag, ps_ty, ret));
+
+        let name = self.add_const_str(id.as_str());
+        self.file.method_tbl.push(MethodDef {
+            name,
+            body: 0,
+            sig,
+            flag: flag.attrib,
+            impl_flag: impl_flag.attrib,
+            param_list: self.file.param_tbl.len() as u32 + 1,
+        });
+        let method_idx = self.file.method_tbl.len() as u32;
+
+        // params
+        if let Some(ret_name) = ret_name {
+            let name = self.add_const_str(ret_name);
+            self.file.param_tbl.push(Param {
+                flag: ParamAttrib::default().attrib,
+                sequence: 0,
+                name,
+            });
+        }
+        for (i, (p_name, _)) in ps.iter().enumerate() {
+            if let Some(p_name) = p_name {
+                let name = self.add_const_str(p_name);
+                self.file.param_tbl.push(Param {
+                    flag: ParamAttrib::default().attrib,
+                    sequence: i as u16 + 1,
+                    name,
+                });
+            }
+        }
+
+        let key = (
+            self.file.typedef_tbl.len() as u32,
+            id.as_str().to_owned(),
+            desc,
+        );
+        if self.method_map.insert(key, method_idx).is_some() {
+            return error(id.as_span(), format!("Duplicated method {}", id.as_str()));
+        }
+
+        if let Some(generic_params) = generic_params {
+            self.build_generic_params(
+                to_type_or_methoddef(method_idx, TypeOrMethodDef::MethodDef),
+                generic_params,
+            )?;
+        }
+
+        if let Some(pinvoke) = pinvoke {
+            let span = pinvoke.as_span();
+            let mut iter = pinvoke.into_inner();
+            let scope = parse_str_lit(iter.next().unwrap())?;
+            let flags: Vec<&str> = iter.map(|p| p.as_str()).collect();
+            if !flags.contains(&"ansi") || !flags.contains(&"cdecl") {
+                return error(
+                    span,
+                    String::from("Only ansi cdecl pinvokeimpl is supported"),
+                );
+            }
+            let (scope, scope_tag) = self.add_const_mod(&scope);
+            if scope_tag != TokTag::ModRef {
+                return error(span, String::from("Cannot pinvoke method of this module"));
+            }
+            self.file.implmap_tbl.push(ImplMap {
+                member: to_implmap_member(method_idx, MemberForwarded::MethodDef),
+                name,
+                scope,
+                flag: PInvokeAttrib::new(
+                    PInvokeAttribCharsetFlag::Ansi,
+                    PInvokeAttribCallConvFlag::CDecl,
+                )
+                .attrib,
+            });
+        }
+
+        let body = body.unwrap();
+        if body.clone().into_inner().next().is_some() {
+            bodies.push((method_idx, body));
+        }
+
+        Ok(())
+    }
+
+    fn build_body(&mut self, method_idx: u32, body: Pair<Rule>) -> ParseResult<()> {
+        let body_span = body.as_span();
+        let mut max_stack = 0;
+        let mut locals = 0;
+        // (inst, offset, operand)
+        let mut insts: Vec<(Inst, usize, Option<Pair<Rule>>)> = Vec::new();
+        let mut labels = HashMap::new();
+        let mut clauses = Vec::new();
+        let mut scopes = Vec::new();
+        let mut seq_points = Vec::new();
+        let mut offset = 0;
+        for p in body.into_inner() {
+            match p.as_rule() {
+                Rule::MaxStack => max_stack = parse_int(&p.into_inner().next().unwrap())?,
+                Rule::Locals => locals = self.build_locals(p)?,
+                Rule::EntryPoint => self.file.mod_tbl[0].entrypoint = method_idx,
+                Rule::Scope => scopes.push(p),
+                Rule::Line => {
+                    let mut iter = p.into_inner();
+                    seq_points.push(SequencePoint {
+                        offset: offset as u32,
+                        line: parse_int(&iter.next().unwrap())?,
+                        col: parse_int(&iter.next().unwrap())?,
+                    });
+                }
+                Rule::Inst => {
+                    let mut iter = p.into_inner();
+                    let mut op = iter.next().unwrap();
+                    if let Rule::LabelDef = op.as_rule() {
+                        let label = op.into_inner().next().unwrap();
+                        if labels.insert(label.as_str(), offset).is_some() {
+                            return error(
+                                label.as_span(),
+                                format!("Duplicated label {}", label.as_str()),
+                            );
+                        }
+                        op = iter.next().unwrap();
+                    }
+                    let operand = iter.next().map(|p| p.into_inner().next().unwrap());
+                    let inst = self.build_inst(op, operand.clone())?;
+                    let size = inst.size();
+                    insts.push((inst, offset, operand));
+                    offset += size;
+                }
+                Rule::Try => clauses.push(p),
+                _ => unreachable!(),
+            }
+        }
+
+        // fill jump instructions
+        for (inst, offset, operand) in insts.iter_mut() {
+            let next = (*offset + inst.size()) as i32;
+            match inst {
+                Inst::Br(rel)
+                | Inst::BrFalse(rel)
+                | Inst::BrTrue(rel)
+                | Inst::BEq(rel)
+                | Inst::BGe(rel)
+                | Inst::BGt(rel)
+                | Inst::BLe(rel)
+                | Inst::BLt(rel)
+                | Inst::BNe(rel)
+                | Inst::Leave(rel) => {
+                    // label is parsed as Tok, whose span may include trailing whitespaces
+                    let label = operand.take().unwrap();
+                    let label = label.clone().into_inner().next().unwrap_or(label);
+                    if let Some(target) = labels.get(label.as_str()) {
+                        *rel = *target as i32 - next;
+                    } else {
+                        return error(
+                            label.as_span(),
+                            format!("Undefined label {}", label.as_str()),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut excepts = Vec::new();
+        for clause in clauses.into_iter() {
+            let span = clause.as_span();
+            let mut iter = clause.into_inner();
+            let try_start = resolve_offset(&labels, &iter.next().unwrap())?;
+            let try_end = resolve_offset(&labels, &iter.next().unwrap())?;
+            let kind = iter.next().unwrap();
+            let handler_start = resolve_offset(&labels, &iter.next().unwrap())?;
+            let handler_end = resolve_offset(&labels, &iter.next().unwrap())?;
+            if try_end < try_start || handler_end < handler_start {
+                return error(span, String::from("End of region is before its start"));
+            }
+            let (kind, class_tok) = match kind.as_rule() {
+                Rule::Catch => (
+                    ExceptionClauseKind::Catch,
+                    self.build_type_tok(kind.into_inner().next().unwrap())?,
+                ),
+                Rule::Finally => (ExceptionClauseKind::Finally, 0),
+                _ => unreachable!(),
+            };
+            excepts.push(ExceptionClause {
+                kind,
+                try_offset: try_start,
+                try_length: try_end - try_start,
+                handler_offset: handler_start,
+                handler_length: handler_end - handler_start,
+                class_tok,
+            });
+        }
+
+        // like xic -g, all methods with body have debug info if the module has a document
+        if !self.file.document_tbl.is_empty() {
+            let mut local_scopes = Vec::new();
+            for scope in scopes.into_iter() {
+                let span = scope.as_span();
+                let mut iter = scope.into_inner();
+                let start = resolve_offset(&labels, &iter.next().unwrap())?;
+                let end = resolve_offset(&labels, &iter.next().unwrap())?;
+                if end < start {
+                    return error(span, String::from("End of region is before its start"));
+                }
+                let mut vars = Vec::new();
+                for var in iter {
+                    let mut var_iter = var.into_inner();
+                    let idx = parse_int(&var_iter.next().unwrap())?;
+                    let name = self.add_const_str(var_iter.next().unwrap().as_str());
+                    vars.push(LocalVarInfo { idx, name });
+                }
+                local_scopes.push(LocalScope {
+                    start,
+                    length: end - start,
+                    vars,
+                });
+            }
+            self.file.method_debug_tbl.push(MethodDebugInfo {
+                method: method_idx,
+                document: 1,
+                seq_points,
+                scopes: local_scopes,
+            });
+        } else if !scopes.is_empty() || !seq_points.is_empty() {
+            return error(body_span, String::from("Debug info without .document"));
+        }
+
+        self.file.codes.push(CorILMethod::new(
+            max_stack,
+            locals,
+            insts.into_iter().map(|(inst, _, _)| inst).collect(),
+            excepts,
+        ));
+        self.file.method_tbl[method_idx as usize - 1].body = self.file.codes.len() as u32;
+        Ok(())
+    }
+
+    /// return index into StandAloneSig tbl, or 0 if there is no local
+    fn build_locals(&mut self, locals: Pair<Rule>) -> ParseResult<u32> {
+        let mut vars = Vec::new();
+        for local in locals.into_inner() {
+            let ty = self.build_param_type(local.into_inner().next().unwrap())?;
+            vars.push(match ty.ty {
+                InnerParamType::Default(ty) => InnerLocalVarType::Default(ty),
+                InnerParamType::ByRef(ty) => InnerLocalVarType::ByRef(ty),
+            });
+        }
+        if vars.is_empty() {
+            return Ok(0);
+        }
+        self.file.blob_heap.push(IrSig::LocalVar(vars));
+        self.file.stand_alone_sig_tbl.push(IrStandAloneSig {
+            sig: self.file.blob_heap.len() as u32 - 1,
+        });
+        Ok(self.file.stand_alone_sig_tbl.len() as u32)
+    }
+
+    /// Branch insts are built with offset 0, they are filled after all labels are known
+    fn build_inst(&mut self, op: Pair<Rule>, operand: Option<Pair<Rule>>) -> ParseResult<Inst> {
+        let span = op.as_span();
+        let op = op.as_str();
+        let operand = if let Some(operand) = operand {
+            operand
+        } else {
+            return Ok(match op {
+                "nop" => Inst::Nop,
 
-    let file = IRParser::parse(Rule::File, &code)?.next().unwrap();
+                "ldarg.0" => Inst::LdArg0,
+                "ldarg.1" => Inst::LdArg1,
+                "ldarg.2" => Inst::LdArg2,
+                "ldarg.3" => Inst::LdArg3,
 
-    for sub in file.into_inner() {
-        match sub.as_rule() {
-            Rule::EOI => break,
+                "ldloc.0" => Inst::LdLoc0,
+                "ldloc.1" => Inst::LdLoc1,
+                "ldloc.2" => Inst::LdLoc2,
+                "ldloc.3" => Inst::LdLoc3,
+                "stloc.0" => Inst::StLoc0,
+                "stloc.1" => Inst::StLoc1,
+                "stloc.2" => Inst::StLoc2,
+                "stloc.3" => Inst::StLoc3,
+
+                "ldnull" => Inst::LdNull,
+                "ldc.i4.m1" => Inst::LdCM1,
+                "ldc.i4.0" => Inst::LdC0,
+                "ldc.i4.1" => Inst::LdC1,
+                "ldc.i4.2" => Inst::LdC2,
+                "ldc.i4.3" => Inst::LdC3,
+                "ldc.i4.4" => Inst::LdC4,
+                "ldc.i4.5" => Inst::LdC5,
+                "ldc.i4.6" => Inst::LdC6,
+                "ldc.i4.7" => Inst::LdC7,
+                "ldc.i4.8" => Inst::LdC8,
+
+                "dup" => Inst::Dup,
+                "pop" => Inst::Pop,
+                "ret" => Inst::Ret,
+
+                "ceq" => Inst::CEq,
+                "cgt" => Inst::CGt,
+                "cgt.un" => Inst::CGtUn,
+                "clt" => Inst::CLt,
+                "clt.un" => Inst::CLtUn,
+
+                "ldind.i1" => Inst::LdIndI1,
+                "ldind.u1" => Inst::LdIndU1,
+                "ldind.i2" => Inst::LdIndI2,
+                "ldind.u2" => Inst::LdIndU2,
+                "ldind.i4" => Inst::LdIndI4,
+                "ldind.u4" => Inst::LdIndU4,
+                "ldind.i8" => Inst::LdIndI8,
+                "ldind.i" => Inst::LdIndI,
+                "ldind.r4" => Inst::LdIndR4,
+                "ldind.r8" => Inst::LdIndR8,
+                "stind.i1" => Inst::StIndI1,
+                "stind.i2" => Inst::StIndI2,
+                "stind.i4" => Inst::StIndI4,
+                "stind.i8" => Inst::StIndI8,
+                "stind.r4" => Inst::StIndR4,
+                "stind.r8" => Inst::StIndR8,
+                "stind.i" => Inst::StIndI,
+
+                "add" => Inst::Add,
+                "sub" => Inst::Sub,
+                "mul" => Inst::Mul,
+                "div" => Inst::Div,
+                "div.un" => Inst::DivUn,
+                "rem" => Inst::Rem,
+                "rem.un" => Inst::RemUn,
+                "and" => Inst::And,
+                "or" => Inst::Or,
+                "xor" => Inst::Xor,
+                "shl" => Inst::Shl,
+                "shr" => Inst::Shr,
+                "shr.un" => Inst::ShrUn,
+                "neg" => Inst::Neg,
+                "not" => Inst::Not,
+
+                "conv.i1" => Inst::ConvI1,
+                "conv.i2" => Inst::ConvI2,
+                "conv.i4" => Inst::ConvI4,
+                "conv.i8" => Inst::ConvI8,
+                "conv.r4" => Inst::ConvR4,
+                "conv.r8" => Inst::ConvR8,
+                "conv.u4" => Inst::ConvU4,
+                "conv.u8" => Inst::ConvU8,
+                "conv.r.un" => Inst::ConvRUn,
+                "conv.u2" => Inst::ConvU2,
+                "conv.u1" => Inst::ConvU1,
+                "conv.i" => Inst::ConvI,
+                "conv.u" => Inst::ConvU,
+                "conv.ovf.i1" => Inst::ConvOvfI1,
+                "conv.ovf.u1" => Inst::ConvOvfU1,
+                "conv.ovf.i2" => Inst::ConvOvfI2,
+                "conv.ovf.u2" => Inst::ConvOvfU2,
+                "conv.ovf.i4" => Inst::ConvOvfI4,
+                "conv.ovf.u4" => Inst::ConvOvfU4,
+                "conv.ovf.i8" => Inst::ConvOvfI8,
+                "conv.ovf.u8" => Inst::ConvOvfU8,
+                "conv.ovf.i" => Inst::ConvOvfI,
+                "conv.ovf.u" => Inst::ConvOvfU,
+                "conv.ovf.i1.un" => Inst::ConvOvfI1Un,
+                "conv.ovf.i2.un" => Inst::ConvOvfI2Un,
+                "conv.ovf.i4.un" => Inst::ConvOvfI4Un,
+                "conv.ovf.i8.un" => Inst::ConvOvfI8Un,
+                "conv.ovf.u1.un" => Inst::ConvOvfU1Un,
+                "conv.ovf.u2.un" => Inst::ConvOvfU2Un,
+                "conv.ovf.u4.un" => Inst::ConvOvfU4Un,
+                "conv.ovf.u8.un" => Inst::ConvOvfU8Un,
+                "conv.ovf.i.un" => Inst::ConvOvfIUn,
+                "conv.ovf.u.un" => Inst::ConvOvfUUn,
+
+                "throw" => Inst::Throw,
+
+                "ldlen" => Inst::LdLen,
+                "ldelem.i1" => Inst::LdElemI1,
+                "ldelem.u1" => Inst::LdElemU1,
+                "ldelem.i2" => Inst::LdElemI2,
+                "ldelem.u2" => Inst::LdElemU2,
+                "ldelem.i4" => Inst::LdElemI4,
+                "ldelem.u4" => Inst::LdElemU4,
+                "ldelem.i8" => Inst::LdElemI8,
+                "ldelem.i" => Inst::LdElemI,
+                "ldelem.r4" => Inst::LdElemR4,
+                "ldelem.r8" => Inst::LdElemR8,
+                "ldelem.ref" => Inst::LdElemRef,
+                "stelem.i" => Inst::StElemI,
+                "stelem.i1" => Inst::StElemI1,
+                "stelem.i2" => Inst::StElemI2,
+                "stelem.i4" => Inst::StElemI4,
+                "stelem.i8" => Inst::StElemI8,
+                "stelem.r4" => Inst::StElemR4,
+                "stelem.r8" => Inst::StElemR8,
+                "stelem.ref" => Inst::StElemRef,
+
+                "endfinally" => Inst::EndFinally,
+
+                _ => return error(span, format!("Unknown inst {} without operand", op)),
+            });
+        };
+
+        Ok(match op {
+            "ldarg.s" => Inst::LdArgS(parse_int(&operand)?),
+            "ldarga.s" => Inst::LdArgAS(parse_int(&operand)?),
+            "starg.s" => Inst::StArgS(parse_int(&operand)?),
+
+            "ldloc.s" => Inst::LdLocS(parse_int(&operand)?),
+            "ldloca.s" => Inst::LdLocAS(parse_int(&operand)?),
+            "ldloc" => Inst::LdLoc(parse_int(&operand)?),
+            "ldloca" => Inst::LdLocA(parse_int(&operand)?),
+            "stloc.s" => Inst::StLocS(parse_int(&operand)?),
+            "stloc" => Inst::StLoc(parse_int(&operand)?),
+
+            "ldc.i4.s" => Inst::LdCI4S(parse_int(&operand)?),
+            "ldc.i4" => Inst::LdCI4(parse_int(&operand)?),
+            "ldc.i8" => Inst::LdCI8(parse_int(&operand)?),
+            "ldc.r4" => Inst::LdCR4(parse_float(&operand)?),
+            "ldc.r8" => Inst::LdCR8(parse_float(&operand)?),
+
+            "call" => Inst::Call(self.build_tok_operand(operand)?),
+            "callvirt" => Inst::CallVirt(self.build_tok_operand(operand)?),
+            "newobj" => Inst::NewObj(self.build_tok_operand(operand)?),
+            "initobj" => Inst::InitObj(self.build_tok_operand(operand)?),
+            "castclass" => Inst::CastClass(self.build_tok_operand(operand)?),
+            "isinst" => Inst::IsInst(self.build_tok_operand(operand)?),
+
+            "br" => Inst::Br(0),
+            "brfalse" => Inst::BrFalse(0),
+            "brtrue" => Inst::BrTrue(0),
+            "beq" => Inst::BEq(0),
+            "bge" => Inst::BGe(0),
+            "bgt" => Inst::BGt(0),
+            "ble" => Inst::BLe(0),
+            "blt" => Inst::BLt(0),
+            "bne.un" => Inst::BNe(0),
+            "leave" => Inst::Leave(0),
+
+            "ldfld" => Inst::LdFld(self.build_tok_operand(operand)?),
+            "ldflda" => Inst::LdFldA(self.build_tok_operand(operand)?),
+            "stfld" => Inst::StFld(self.build_tok_operand(operand)?),
+            "ldsfld" => Inst::LdSFld(self.build_tok_operand(operand)?),
+            "ldsflda" => Inst::LdSFldA(self.build_tok_operand(operand)?),
+            "stsfld" => Inst::StSFld(self.build_tok_operand(operand)?),
+
+            "ldstr" => {
+                if let Rule::String = operand.as_rule() {
+                    let s = parse_str_lit(operand)?;
+                    Inst::LdStr(self.add_const_usr_str(&s))
+                } else {
+                    return error(operand.as_span(), String::from("Expect string literal"));
+                }
+            }
+
+            "newarr" => Inst::NewArr(self.build_arr_ele_tok(operand)?),
+
+            "ldelem" => Inst::LdElem(self.build_tok_operand(operand)?),
+            "ldelema" => Inst::LdElemA(self.build_tok_operand(operand)?),
+            "stelem" => Inst::StElem(self.build_tok_operand(operand)?),
+
+            _ => return error(span, format!("Unknown inst {} with operand", op)),
+        })
+    }
+}
+
+// Types, sigs and toks
+impl Assembler {
+    fn build_tok_operand(&mut self, operand: Pair<Rule>) -> ParseResult<u32> {
+        if let Rule::Tok = operand.as_rule() {
+            self.build_tok(operand)
+        } else {
+            error(operand.as_span(), String::from("Expect token"))
+        }
+    }
+
+    /// operand of newarr is the arr type, e.g. std/Int32[], but the tok is its elem type
+    fn build_arr_ele_tok(&mut self, operand: Pair<Rule>) -> ParseResult<u32> {
+        let span = operand.as_span();
+        if let Rule::Tok = operand.as_rule() {
+            let mut iter = operand.into_inner();
+            let ty = iter.next().unwrap();
+            if iter.next().is_none() {
+                let mut desc = descriptor(&ty);
+                if let TypeSig::SZArray(ele_ty) = self.build_type_sig(ty)? {
+                    // remove "[]"
+                    desc.truncate(desc.len() - 2);
+                    return self.type_sig_to_tok(*ele_ty, desc, span);
+                }
+            }
+        }
+        error(span, String::from("Expect array type"))
+    }
+
+    /// tok of type, field, method or methodspec
+    fn build_tok(&mut self, tok: Pair<Rule>) -> ParseResult<u32> {
+        let span = tok.as_span();
+        let mut iter = tok.into_inner();
+        let ty = iter.next().unwrap();
+        let mut member = None;
+        let mut sig = None;
+        let mut generic_args = None;
+        for p in iter {
+            match p.as_rule() {
+                Rule::Id => member = Some(p),
+                Rule::Sig => sig = Some(p),
+                Rule::GenericArgs => generic_args = Some(p),
+                _ => unreachable!(),
+            }
+        }
+
+        let member = if let Some(member) = member {
+            member
+        } else if generic_args.is_some() {
+            return error(
+                span,
+                String::from("Generic args of methodspec without method"),
+            );
+        } else {
+            return self.build_type_tok(ty);
+        };
+
+        let sig = sig.unwrap();
+        let sig_desc = descriptor(&sig);
+        let sig = self.build_sig(sig)?;
+        let member_tok = if ty.as_str() == self.mod_name {
+            // member without parent
+            self.find_member(0, member, sig_desc)?
+        } else {
+            let (tag, idx) = get_tok_tag(self.build_type_tok(ty)?);
+            let parent = match tag {
+                TokTag::TypeDef => None,
+                TokTag::TypeRef => Some(to_memberref_parent(idx, MemberRefParent::TypeRef)),
+                TokTag::TypeSpec => Some(to_memberref_parent(idx, MemberRefParent::TypeSpec)),
+                _ => unreachable!(),
+            };
+            if let Some(parent) = parent {
+                let name = self.add_const_str(member.as_str());
+                to_tok(
+                    self.add_const_memberref(MemberRef { parent, name, sig }),
+                    TokTag::MemberRef,
+                )
+            } else {
+                self.find_member(idx, member, sig_desc)?
+            }
+        };
+
+        if let Some(generic_args) = generic_args {
+            let method = match get_tok_tag(member_tok) {
+                (TokTag::MethodDef, idx) => to_methoddeforref(idx, MethodDefOrRef::MethodDef),
+                (TokTag::MemberRef, idx) => to_methoddeforref(idx, MethodDefOrRef::MemberRef),
+                _ => return error(span, String::from("Methodspec of field")),
+            };
+            let desc = descriptor(&generic_args);
+            let args = self.build_generic_args(generic_args)?;
+            let instantiation = self.add_member_sig(desc, IrSig::MethodSpec(args));
+            Ok(to_tok(
+                self.add_const_methodspec(method, instantiation),
+                TokTag::MethodSpec,
+            ))
+        } else {
+            Ok(member_tok)
+        }
+    }
+
+    /// field or method defined in this module
+    fn find_member(&self, parent: u32, member: Pair<Rule>, sig_desc: String) -> ParseResult<u32> {
+        let key = (parent, member.as_str().to_owned(), sig_desc);
+        if let Some(field) = self.field_map.get(&key) {
+            Ok(to_tok(*field, TokTag::Field))
+        } else if let Some(method) = self.method_map.get(&key) {
+            Ok(to_tok(*method, TokTag::MethodDef))
+        } else {
+            error(
+                member.as_span(),
+                format!("Member {}: {} not found", key.1, key.2),
+            )
+        }
+    }
+
+    /// return index into blob heap
+    fn build_sig(&mut self, sig: Pair<Rule>) -> ParseResult<u32> {
+        let desc = descriptor(&sig);
+        let sig = sig.into_inner().next().unwrap();
+        let sig = match sig.as_rule() {
+            Rule::MethodSig => {
+                let mut flag = MethodSigFlag::new(MethodSigFlagTag::Default);
+                let mut ps = Vec::new();
+                let mut ret = None;
+                for p in sig.into_inner() {
+                    match p.as_rule() {
+                        Rule::Instance => flag = MethodSigFlag::new(MethodSigFlagTag::HasThis),
+                        Rule::ParamType => ps.push(self.build_param_type(p)?),
+                        Rule::RetType => ret = Some(self.build_ret_type(p)?),
+                        _ => unreachable!(),
+                    }
+                }
+                IrSig::Method(flag, ps, ret.unwrap())
+            }
+            Rule::Type => IrSig::Field(self.build_type_sig(sig)?),
+            _ => unreachable!(),
+        };
+        Ok(self.add_member_sig(desc, sig))
+    }
+
+    fn build_param_type(&mut self, p: Pair<Rule>) -> ParseResult<ParamType> {
+        let mut iter = p.into_inner();
+        let first = iter.next().unwrap();
+        Ok(ParamType {
+            ty: if let Rule::ByRef = first.as_rule() {
+                InnerParamType::ByRef(self.build_type_sig(iter.next().unwrap())?)
+            } else {
+                InnerParamType::Default(self.build_type_sig(first)?)
+            },
+        })
+    }
+
+    fn build_ret_type(&mut self, p: Pair<Rule>) -> ParseResult<RetType> {
+        let p = p.into_inner().next().unwrap();
+        Ok(RetType {
+            ty: if let Rule::Void = p.as_rule() {
+                InnerRetType::Void
+            } else {
+                match self.build_param_type(p)?.ty {
+                    InnerParamType::Default(ty) => InnerRetType::Default(ty),
+                    InnerParamType::ByRef(ty) => InnerRetType::ByRef(ty),
+                }
+            },
+        })
+    }
+
+    fn build_generic_args(&mut self, args: Pair<Rule>) -> ParseResult<Vec<TypeSig>> {
+        args.into_inner()
+            .map(|arg| self.build_type_sig(arg))
+            .collect()
+    }
+
+    fn build_type_sig(&mut self, ty: Pair<Rule>) -> ParseResult<TypeSig> {
+        let ty = ty.into_inner().next().unwrap();
+        match ty.as_rule() {
+            // skip ValueTypeKw
+            Rule::ValueType => self.build_type_core(ty.into_inner().nth(1).unwrap(), false),
+            Rule::TypeCore => self.build_type_core(ty, true),
+            _ => unreachable!(),
+        }
+    }
+
+    fn build_type_core(&mut self, ty: Pair<Rule>, is_class: bool) -> ParseResult<TypeSig> {
+        let span = ty.as_span();
+        let mut iter = ty.into_inner();
+        let ele_ty = iter.next().unwrap();
+        let mut sig = match ele_ty.as_rule() {
+            Rule::ClassType => {
+                let mut class_iter = ele_ty.into_inner();
+                let tok = self.build_class_tok(class_iter.next().unwrap())?;
+                if let Some(args) = class_iter.next() {
+                    TypeSig::GenericInst(is_class, tok, self.build_generic_args(args)?)
+                } else if is_class {
+                    TypeSig::Class(tok)
+                } else {
+                    TypeSig::ValueType(tok)
+                }
+            }
+            _ if !is_class => {
+                return error(
+                    span,
+                    String::from("valuetype should be followed by a class"),
+                );
+            }
+            Rule::PrimType => match ele_ty.as_str() {
+                "bool" => TypeSig::Boolean,
+                "char" => TypeSig::Char,
+                "i1" => TypeSig::I1,
+                "u1" => TypeSig::U1,
+                "i2" => TypeSig::I2,
+                "u2" => TypeSig::U2,
+                "i4" => TypeSig::I4,
+                "u4" => TypeSig::U4,
+                "i8" => TypeSig::I8,
+                "u8" => TypeSig::U8,
+                "r4" => TypeSig::R4,
+                "r8" => TypeSig::R8,
+                "i" => TypeSig::I,
+                "u" => TypeSig::U,
+                "string" => TypeSig::String,
+                _ => unreachable!(),
+            },
+            Rule::Var => TypeSig::Var(parse_int(&ele_ty.into_inner().next().unwrap())?),
+            Rule::MVar => TypeSig::MVar(parse_int(&ele_ty.into_inner().next().unwrap())?),
             _ => unreachable!(),
         };
+        for _ in iter {
+            sig = TypeSig::SZArray(Box::new(sig));
+        }
+        Ok(sig)
+    }
+
+    /// tok of typedef or typeref
+    fn build_class_tok(&mut self, path: Pair<Rule>) -> ParseResult<u32> {
+        let fullname = path.as_str();
+        let (mod_name, name) = if let Some(i) = fullname.rfind('/') {
+            (&fullname[..i], &fullname[i + 1..])
+        } else {
+            return error(
+                path.as_span(),
+                format!("{} is not a class, expect full name", fullname),
+            );
+        };
+
+        if mod_name == self.mod_name {
+            if let Some(idx) = self.type_map.get(name) {
+                Ok(to_tok(*idx, TokTag::TypeDef))
+            } else {
+                error(path.as_span(), format!("Class {} not found", fullname))
+            }
+        } else {
+            let (idx, tag) = self.add_const_class(mod_name, name);
+            Ok(to_tok(idx, tag.to_tok_tag()))
+        }
+    }
+
+    /// tok of typedef, typeref or typespec
+    fn build_type_tok(&mut self, ty: Pair<Rule>) -> ParseResult<u32> {
+        let span = ty.as_span();
+        let desc = descriptor(&ty);
+        let sig = self.build_type_sig(ty)?;
+        self.type_sig_to_tok(sig, desc, span)
+    }
+
+    fn type_sig_to_tok(&mut self, sig: TypeSig, desc: String, span: Span) -> ParseResult<u32> {
+        let spec = match sig {
+            TypeSig::Class(tok) | TypeSig::ValueType(tok) => return Ok(tok),
+            TypeSig::GenericInst(is_class, tok, args) => {
+                TypeSpecSig::GenericInst(is_class, tok, args)
+            }
+            TypeSig::SZArray(ele_ty) => TypeSpecSig::SZArray(*ele_ty),
+            TypeSig::Var(i) => TypeSpecSig::Var(i),
+            TypeSig::MVar(i) => TypeSpecSig::MVar(i),
+            _ => return error(span, format!("{} cannot be a typespec", desc)),
+        };
+        Ok(to_tok(
+            self.add_const_typespec(desc, spec),
+            TokTag::TypeSpec,
+        ))
     }
 
-    unimplemented!();
+    /// TypeDefOrRef coded index of extends, implements and constraints
+    fn build_typedeforref(&mut self, ty: Pair<Rule>) -> ParseResult<(u32, TypeDefOrRef)> {
+        let (tag, idx) = get_tok_tag(self.build_type_tok(ty)?);
+        Ok((
+            idx,
+            match tag {
+                TokTag::TypeDef => TypeDefOrRef::TypeDef,
+                TokTag::TypeRef => TypeDefOrRef::TypeRef,
+                TokTag::TypeSpec => TypeDefOrRef::TypeSpec,
+                _ => unreachable!(),
+            },
+        ))
+    }
+}
+
+// Const values
+impl Assembler {
+    fn add_const_str(&mut self, v: &str) -> u32 {
+        if let Some(ret) = self.str_map.get(v) {
+            *ret
+        } else {
+            let ret = self.file.str_heap.len() as u32;
+            self.file.str_heap.push(v.to_owned());
+            self.str_map.insert(v.to_owned(), ret);
+            ret
+        }
+    }
+
+    fn add_const_usr_str(&mut self, v: &str) -> u32 {
+        if let Some(ret) = self.usr_str_map.get(v) {
+            *ret
+        } else {
+            let ret = self.file.usr_str_heap.len() as u32;
+            self.file.usr_str_heap.push(v.to_owned());
+            self.usr_str_map.insert(v.to_owned(), ret);
+            ret
+        }
+    }
+
+    /// sig of field, method or methodspec
+    fn add_member_sig(&mut self, desc: String, sig: IrSig) -> u32 {
+        if let Some(ret) = self.member_sig_map.get(&desc) {
+            *ret
+        } else {
+            let ret = self.file.blob_heap.len() as u32;
+            self.file.blob_heap.push(sig);
+            self.member_sig_map.insert(desc, ret);
+            ret
+        }
+    }
+
+    fn add_const_typespec(&mut self, desc: String, spec: TypeSpecSig) -> u32 {
+        if let Some(ret) = self.typespec_map.get(&desc) {
+            *ret
+        } else {
+            self.file.blob_heap.push(IrSig::TypeSpec(spec));
+            self.file.typespec_tbl.push(TypeSpec {
+                sig: self.file.blob_heap.len() as u32 - 1,
+            });
+            let ret = self.file.typespec_tbl.len() as u32;
+            self.typespec_map.insert(desc, ret);
+            ret
+        }
+    }
+
+    fn add_const_methodspec(&mut self, method: u32, instantiation: u32) -> u32 {
+        if let Some(ret) = self.methodspec_map.get(&(method, instantiation)) {
+            *ret
+        } else {
+            self.file.methodspec_tbl.push(MethodSpec {
+                method,
+                instantiation,
+            });
+            let ret = self.file.methodspec_tbl.len() as u32;
+            self.methodspec_map.insert((method, instantiation), ret);
+            ret
+        }
+    }
+
+    fn add_const_memberref(&mut self, memberref: MemberRef) -> u32 {
+        if let Some(ret) = self.memberref_map.get(&memberref) {
+            *ret
+        } else {
+            self.file.memberref_tbl.push(memberref.clone());
+            let ret = self.file.memberref_tbl.len() as u32;
+            self.memberref_map.insert(memberref, ret);
+            ret
+        }
+    }
+
+    fn add_const_mod(&mut self, name: &str) -> (u32, TokTag) {
+        if name == self.mod_name {
+            // this module
+            (1, TokTag::Mod)
+        } else if let Some(ret) = self.modref_map.get(name) {
+            (*ret, TokTag::ModRef)
+        } else {
+            let name_idx = self.add_const_str(name);
+            self.file.modref_tbl.push(ModRef { name: name_idx });
+            let ret = self.file.modref_tbl.len() as u32;
+            self.modref_map.insert(name.to_owned(), ret);
+            (ret, TokTag::ModRef)
+        }
+    }
+
+    /// class of other modules
+    fn add_const_class(&mut self, mod_name: &str, name: &str) -> (u32, TypeDefOrRef) {
+        let (parent_idx, parent_tag) = self.add_const_mod(mod_name);
+        assert_eq!(parent_tag, TokTag::ModRef);
+        let typeref = TypeRef {
+            parent: get_typeref_parent(parent_idx, ResolutionScope::ModRef),
+            name: self.add_const_str(name),
+        };
+        if let Some(ret) = self.typeref_map.get(&typeref) {
+            (*ret, TypeDefOrRef::TypeRef)
+        } else {
+            self.file.typeref_tbl.push(typeref.clone());
+            let ret = self.file.typeref_tbl.len() as u32;
+            self.typeref_map.insert(typeref, ret);
+            (ret, TypeDefOrRef::TypeRef)
+        }
+    }
 }
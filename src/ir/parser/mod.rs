@@ -0,0 +1,3 @@
+mod ir_parser;
+
+pub use ir_parser::{parse, parse_str, Rule};
@@ -19,6 +19,8 @@ enum EleType {
     Char,
     I1,
     U1,
+    I2,
+    U2,
     I4,
     U4,
     I8,
@@ -29,8 +31,10 @@ enum EleType {
     ByRef,
     ValueType,
     Class,
+    Var,
     GenericInst,
     SZArray,
+    MVar,
     I,
     U,
     Object,
@@ -46,6 +50,8 @@ impl TryFrom<u8> for EleType {
             ELEMENT_TYPE_CHAR => Self::Char,
             ELEMENT_TYPE_I1 => Self::I1,
             ELEMENT_TYPE_U1 => Self::U1,
+            ELEMENT_TYPE_I2 => Self::I2,
+            ELEMENT_TYPE_U2 => Self::U2,
             ELEMENT_TYPE_I4 => Self::I4,
             ELEMENT_TYPE_U4 => Self::U4,
             ELEMENT_TYPE_I8 => Self::I8,
@@ -56,8 +62,10 @@ impl TryFrom<u8> for EleType {
             ELEMENT_TYPE_BYREF => Self::ByRef,
             ELEMENT_TYPE_VALUETYPE => Self::ValueType,
             ELEMENT_TYPE_CLASS => Self::Class,
+            ELEMENT_TYPE_VAR => Self::Var,
             ELEMENT_TYPE_GENERICINST => Self::GenericInst,
             ELEMENT_TYPE_SZARRAY => Self::SZArray,
+            ELEMENT_TYPE_MVAR => Self::MVar,
             ELEMENT_TYPE_I => Self::I,
             ELEMENT_TYPE_U => Self::U,
             ELEMENT_TYPE_OBJECT => Self::Object,
@@ -71,6 +79,8 @@ const ELEMENT_TYPE_BOOLEAN: u8 = 0x02;
 const ELEMENT_TYPE_CHAR: u8 = 0x03;
 const ELEMENT_TYPE_I1: u8 = 0x04;
 const ELEMENT_TYPE_U1: u8 = 0x05;
+const ELEMENT_TYPE_I2: u8 = 0x06;
+const ELEMENT_TYPE_U2: u8 = 0x07;
 const ELEMENT_TYPE_I4: u8 = 0x08;
 const ELEMENT_TYPE_U4: u8 = 0x09;
 const ELEMENT_TYPE_I8: u8 = 0x0A;
@@ -81,11 +91,16 @@ const ELEMENT_TYPE_STRING: u8 = 0x0E;
 const ELEMENT_TYPE_BYREF: u8 = 0x10;
 const ELEMENT_TYPE_VALUETYPE: u8 = 0x11;
 const ELEMENT_TYPE_CLASS: u8 = 0x12;
+const ELEMENT_TYPE_VAR: u8 = 0x13;
 const ELEMENT_TYPE_GENERICINST: u8 = 0x15;
 const ELEMENT_TYPE_I: u8 = 0x18;
 const ELEMENT_TYPE_U: u8 = 0x19;
 const ELEMENT_TYPE_OBJECT: u8 = 0x1C;
 const ELEMENT_TYPE_SZARRAY: u8 = 0x1D;
+const ELEMENT_TYPE_MVAR: u8 = 0x1E;
+
+/// II.23.2.15 prefix of MethodSpec blob
+const METHOD_SPEC_SIG: u8 = 0x0A;
 
 impl_vec_serde!(TypeSig);
 
@@ -95,6 +110,8 @@ pub enum TypeSig {
     Char,
     I1,
     U1,
+    I2,
+    U2,
     I4,
     U4,
     I8,
@@ -115,6 +132,10 @@ pub enum TypeSig {
     /// .1: typedef or typeref or typespec
     /// .2: GenArgs
     GenericInst(bool, u32, Vec<TypeSig>),
+    /// Generic parameter of a generic type definition, by index
+    Var(u32),
+    /// Generic parameter of a generic method definition, by index
+    MVar(u32),
     String,
 }
 
@@ -137,6 +158,8 @@ pub struct ArrayShapeSig {
 pub enum TypeSpecSig {
     SZArray(TypeSig),
     GenericInst(bool, u32, Vec<TypeSig>),
+    Var(u32),
+    MVar(u32),
 }
 
 pub enum InnerLocalVarType {
@@ -154,6 +177,8 @@ pub enum IrSig {
     LocalVar(Vec<InnerLocalVarType>),
     /// II.23.2.14
     TypeSpec(TypeSpecSig),
+    /// II.23.2.15 generic args of a generic method instantiation
+    MethodSpec(Vec<TypeSig>),
 }
 
 impl IrFmt for TypeSig {
@@ -163,6 +188,8 @@ impl IrFmt for TypeSig {
             TypeSig::Char => write!(f, "char"),
             TypeSig::I1 => write!(f, "i1"),
             TypeSig::U1 => write!(f, "u1"),
+            TypeSig::I2 => write!(f, "i2"),
+            TypeSig::U2 => write!(f, "u2"),
             TypeSig::I4 => write!(f, "i4"),
             TypeSig::U4 => write!(f, "u4"),
             TypeSig::I8 => write!(f, "i8"),
@@ -194,6 +221,8 @@ impl IrFmt for TypeSig {
                 ty.fmt(f, ctx)?;
                 write!(f, "[]")
             }
+            TypeSig::Var(i) => write!(f, "!{}", i),
+            TypeSig::MVar(i) => write!(f, "!!{}", i),
             TypeSig::String => write!(f, "string"),
         }
     }
@@ -206,6 +235,8 @@ impl ISerializable for TypeSig {
             TypeSig::Char => ELEMENT_TYPE_CHAR.serialize(buf),
             TypeSig::I1 => ELEMENT_TYPE_I1.serialize(buf),
             TypeSig::U1 => ELEMENT_TYPE_U1.serialize(buf),
+            TypeSig::I2 => ELEMENT_TYPE_I2.serialize(buf),
+            TypeSig::U2 => ELEMENT_TYPE_U2.serialize(buf),
             TypeSig::I4 => ELEMENT_TYPE_I4.serialize(buf),
             TypeSig::U4 => ELEMENT_TYPE_U4.serialize(buf),
             TypeSig::I8 => ELEMENT_TYPE_I8.serialize(buf),
@@ -237,6 +268,14 @@ impl ISerializable for TypeSig {
                 ELEMENT_TYPE_SZARRAY.serialize(buf);
                 ty.serialize(buf);
             }
+            TypeSig::Var(i) => {
+                ELEMENT_TYPE_VAR.serialize(buf);
+                i.serialize(buf);
+            }
+            TypeSig::MVar(i) => {
+                ELEMENT_TYPE_MVAR.serialize(buf);
+                i.serialize(buf);
+            }
             TypeSig::String => ELEMENT_TYPE_STRING.serialize(buf),
         }
     }
@@ -248,6 +287,8 @@ impl ISerializable for TypeSig {
             ELEMENT_TYPE_CHAR => TypeSig::Char,
             ELEMENT_TYPE_I1 => TypeSig::I1,
             ELEMENT_TYPE_U1 => TypeSig::U1,
+            ELEMENT_TYPE_I2 => TypeSig::I2,
+            ELEMENT_TYPE_U2 => TypeSig::U2,
             ELEMENT_TYPE_I4 => TypeSig::I4,
             ELEMENT_TYPE_U4 => TypeSig::U4,
             ELEMENT_TYPE_I8 => TypeSig::I8,
@@ -270,6 +311,8 @@ impl ISerializable for TypeSig {
                 TypeSig::GenericInst(is_class, ty, args)
             }
             ELEMENT_TYPE_SZARRAY => TypeSig::SZArray(Box::new(TypeSig::deserialize(buf))),
+            ELEMENT_TYPE_VAR => TypeSig::Var(u32::deserialize(buf)),
+            ELEMENT_TYPE_MVAR => TypeSig::MVar(u32::deserialize(buf)),
             ELEMENT_TYPE_STRING => TypeSig::String,
             _ => panic!("Cannot recognize TypeSig with code {:0X}", code),
         }
@@ -393,7 +436,19 @@ impl IrFmt for IrSig {
                     }
                     write!(f, ">")
                 }
+                TypeSpecSig::Var(i) => write!(f, "!{}", i),
+                TypeSpecSig::MVar(i) => write!(f, "!!{}", i),
             },
+            Self::MethodSpec(args) => {
+                write!(f, "<")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    arg.fmt(f, ctx)?;
+                }
+                write!(f, ">")
+            }
         }
     }
 }
@@ -434,7 +489,19 @@ impl ISerializable for IrSig {
                     tok.serialize(buf);
                     args.serialize(buf);
                 }
+                TypeSpecSig::Var(i) => {
+                    ELEMENT_TYPE_VAR.serialize(buf);
+                    i.serialize(buf);
+                }
+                TypeSpecSig::MVar(i) => {
+                    ELEMENT_TYPE_MVAR.serialize(buf);
+                    i.serialize(buf);
+                }
             },
+            IrSig::MethodSpec(args) => {
+                METHOD_SPEC_SIG.serialize(buf);
+                args.serialize(buf);
+            }
         }
     }
 
@@ -443,27 +510,23 @@ impl ISerializable for IrSig {
         match code {
             0x06u8 => IrSig::Field(TypeSig::deserialize(buf)),
             0x07u8 => IrSig::LocalVar(Vec::deserialize(buf)),
+            METHOD_SPEC_SIG => IrSig::MethodSpec(Vec::deserialize(buf)),
+            ELEMENT_TYPE_SZARRAY => {
+                IrSig::TypeSpec(TypeSpecSig::SZArray(TypeSig::deserialize(buf)))
+            }
+            ELEMENT_TYPE_GENERICINST => {
+                let is_class = match EleType::try_from(u8::deserialize(buf)).unwrap() {
+                    EleType::ValueType => false,
+                    EleType::Class => true,
+                    _ => unreachable!(),
+                };
+                let tok = u32::deserialize(buf);
+                let args = Vec::deserialize(buf);
+                IrSig::TypeSpec(TypeSpecSig::GenericInst(is_class, tok, args))
+            }
+            ELEMENT_TYPE_VAR => IrSig::TypeSpec(TypeSpecSig::Var(u32::deserialize(buf))),
+            ELEMENT_TYPE_MVAR => IrSig::TypeSpec(TypeSpecSig::MVar(u32::deserialize(buf))),
             _ => {
-                if let Ok(ele_ty) = EleType::try_from(code) {
-                    match ele_ty {
-                        EleType::SZArray => {
-                            return IrSig::TypeSpec(TypeSpecSig::SZArray(TypeSig::deserialize(
-                                buf,
-                            )));
-                        }
-                        EleType::GenericInst => {
-                            let is_class = match EleType::try_from(u8::deserialize(buf)).unwrap() {
-                                EleType::ValueType => false,
-                                EleType::Class => true,
-                                _ => unreachable!(),
-                            };
-                            let tok = u32::deserialize(buf);
-                            let args = Vec::deserialize(buf);
-                            return IrSig::TypeSpec(TypeSpecSig::GenericInst(is_class, tok, args));
-                        }
-                        _ => unimplemented!(),
-                    }
-                }
                 // TODO: check flag validity
                 let method_sig_flag = MethodSigFlag::from(code);
 
@@ -1,10 +1,13 @@
 use super::attrib::*;
+use super::code::ExceptionClauseKind;
 use super::file::IrFile;
+use super::generic::{to_type_or_methoddef, TypeOrMethodDef};
 use super::inst::Inst;
-use super::member::MemberForwarded;
+use super::member::{MemberForwarded, MemberRefParent};
 use super::param::Param;
 use super::sig::{IrSig, MethodSigFlagTag, ParamType, RetType};
-use super::ty::TypeDefOrRef;
+use super::tok::{fmt_tok, to_tok, TokTag};
+use super::ty::{get_typedeforref, ResolutionScope, TypeDefOrRef};
 
 use std::fmt;
 
@@ -79,12 +82,46 @@ impl IrFile {
         ty.fmt(f, self)
     }
 
+    /// write `<T, U: Constraint>` if owner has generic params
+    fn write_generic_params(&self, f: &mut fmt::Formatter<'_>, owner: u32) -> fmt::Result {
+        let mut first = true;
+        for (param_i, param) in self.generic_param_tbl.iter().enumerate() {
+            if param.owner != owner {
+                continue;
+            }
+            write!(
+                f,
+                "{}{}",
+                if first { "<" } else { ", " },
+                self.get_str(param.name)
+            )?;
+            first = false;
+
+            let mut first_constraint = true;
+            for constraint in self
+                .generic_param_constraint_tbl
+                .iter()
+                .filter(|c| c.owner as usize == param_i + 1)
+            {
+                write!(f, "{}", if first_constraint { ": " } else { " + " })?;
+                first_constraint = false;
+                let (tag, idx) = get_typedeforref(constraint.constraint);
+                fmt_tok(to_tok(idx, tag.to_tok_tag()), f, self)?;
+            }
+        }
+        if !first {
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+
     pub fn write_method(
         &self,
         f: &mut fmt::Formatter<'_>,
         indent: usize,
         method_i: usize,
         is_entrypoint: bool,
+        show_tok: bool,
     ) -> fmt::Result {
         let method = &self.method_tbl[method_i];
         let param = if method_i == self.method_tbl.len() - 1 {
@@ -122,7 +159,12 @@ impl IrFile {
             }
         }
 
-        write!(f, "{} ", self.get_str(method.name))?;
+        write!(f, "{}", self.get_str(method.name))?;
+        self.write_generic_params(
+            f,
+            to_type_or_methoddef(method_i as u32 + 1, TypeOrMethodDef::MethodDef),
+        )?;
+        write!(f, " ")?;
         let mut param_iter = param.iter().peekable();
         if let Some(&p) = param_iter.peek() {
             if p.sequence == 0 {
@@ -189,12 +231,77 @@ impl IrFile {
 
             let code: Vec<Inst> = body.to_insts();
 
+            // debug info refers to the only document of the module, see IrFile::write_ir
+            let debug = self.method_debug_info(method_i as u32 + 1);
+            if let Some(debug) = debug {
+                for scope in debug.scopes.iter() {
+                    write!(
+                        f,
+                        "\n{}.scope IL_{:0>4X} to IL_{:0>4X}",
+                        " ".repeat(indent * 8),
+                        scope.start,
+                        scope.start + scope.length
+                    )?;
+                    for var in scope.vars.iter() {
+                        write!(f, " {}: {}", var.idx, self.get_str(var.name))?;
+                    }
+                }
+            }
+            let mut seq_points = debug.map(|d| d.seq_points.iter().peekable());
+
             let mut offset = 0;
             for inst in code.iter() {
+                // .line applies to the inst that follows it
+                if let Some(seq_points) = &mut seq_points {
+                    while let Some(sp) = seq_points.next_if(|sp| sp.offset as usize <= offset) {
+                        write!(
+                            f,
+                            "\n{}.line {}:{}",
+                            " ".repeat(indent * 8),
+                            sp.line,
+                            sp.col
+                        )?;
+                    }
+                }
                 write!(f, "\n{}", " ".repeat(indent * 8))?;
                 inst.fmt(f, self, offset)?;
+                if let (true, Some(tok)) = (show_tok, inst.tok()) {
+                    write!(f, " // 0x{:0>8X}", tok)?;
+                }
                 offset += inst.size();
             }
+            // sequence points at the end of code
+            for sp in seq_points.into_iter().flatten() {
+                write!(
+                    f,
+                    "\n{}.line {}:{}",
+                    " ".repeat(indent * 8),
+                    sp.line,
+                    sp.col
+                )?;
+            }
+            for clause in body.excepts.iter() {
+                write!(
+                    f,
+                    "\n{}.try IL_{:0>4X} to IL_{:0>4X} ",
+                    " ".repeat(indent * 8),
+                    clause.try_offset,
+                    clause.try_offset + clause.try_length
+                )?;
+                match clause.kind {
+                    ExceptionClauseKind::Catch => {
+                        write!(f, "catch ")?;
+                        fmt_tok(clause.class_tok, f, self)?;
+                    }
+                    ExceptionClauseKind::Finally => write!(f, "finally")?,
+                }
+                write!(
+                    f,
+                    " handler IL_{:0>4X} to IL_{:0>4X}",
+                    clause.handler_offset,
+                    clause.handler_offset + clause.handler_length
+                )?;
+            }
             write!(f, "\n{}}}\n", " ".repeat(indent * 4))
         } else {
             write!(f, " {{}}\n")
@@ -202,8 +309,67 @@ impl IrFile {
     }
 }
 
+/// Display IrFile with options, see IrFile::display
+pub struct IrDisplay<'f> {
+    file: &'f IrFile,
+    /// only display the class with this name or fullname
+    pub class: Option<String>,
+    /// only display methods with this name
+    pub method: Option<String>,
+    /// annotate tokens in IL with their raw values
+    pub show_tok: bool,
+    /// display raw metadata tables instead of IR
+    pub tables: bool,
+}
+
+impl IrDisplay<'_> {
+    fn class_shown(&self, typedef_i: usize) -> bool {
+        if let Some(class) = &self.class {
+            let name = self.file.get_str(self.file.typedef_tbl[typedef_i].name);
+            class == name || *class == format!("{}/{}", self.file.mod_name(), name)
+        } else {
+            true
+        }
+    }
+
+    fn method_shown(&self, method_i: usize) -> bool {
+        if let Some(method) = &self.method {
+            method == self.file.get_str(self.file.method_tbl[method_i].name)
+        } else {
+            true
+        }
+    }
+}
+
+impl fmt::Display for IrDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tables {
+            self.file.write_tables(f)
+        } else {
+            self.file.write_ir(f, self)
+        }
+    }
+}
+
 impl fmt::Display for IrFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display().fmt(f)
+    }
+}
+
+impl IrFile {
+    /// Display with default options, which are changed by setting fields of the returned value
+    pub fn display(&self) -> IrDisplay<'_> {
+        IrDisplay {
+            file: self,
+            class: None,
+            method: None,
+            show_tok: false,
+            tables: false,
+        }
+    }
+
+    fn write_ir(&self, f: &mut fmt::Formatter<'_>, opt: &IrDisplay) -> fmt::Result {
         write!(
             f,
             ".version {}.{}\n.mod {}",
@@ -212,6 +378,15 @@ impl fmt::Display for IrFile {
             self.get_str(self.mod_tbl[0].name)
         )?;
 
+        for modref in self.modref_tbl.iter() {
+            write!(f, "\n.extern \"{}\"", self.get_str(modref.name))?;
+        }
+
+        // xic -g generates one document for each module, all methods with body have debug info
+        for doc in self.document_tbl.iter() {
+            write!(f, "\n.document {:?}", self.get_str(doc.name))?;
+        }
+
         let entrypoint = self.mod_tbl[0].entrypoint;
 
         let (mut field_i, mut method_i) = if let Some(c0) = self.typedef_tbl.first() {
@@ -220,12 +395,19 @@ impl fmt::Display for IrFile {
             (self.field_tbl.len(), self.method_tbl.len())
         };
 
-        for i in (0..field_i).into_iter() {
-            self.write_field(f, 0, i)?;
-        }
+        if opt.class.is_none() {
+            // module-level members are not in any class
+            if opt.method.is_none() {
+                for i in (0..field_i).into_iter() {
+                    self.write_field(f, 0, i)?;
+                }
+            }
 
-        for i in (0..method_i).into_iter() {
-            self.write_method(f, 0, i, i as u32 + 1 == entrypoint)?;
+            for i in (0..method_i).into_iter() {
+                if opt.method_shown(i) {
+                    self.write_method(f, 0, i, i as u32 + 1 == entrypoint, opt.show_tok)?;
+                }
+            }
         }
 
         for (typedef_i, typedef) in self.typedef_tbl.iter().enumerate() {
@@ -240,8 +422,20 @@ impl fmt::Display for IrFile {
                 )
             };
 
+            if !opt.class_shown(typedef_i)
+                || (opt.method.is_some() && !(method_i..method_lim).any(|i| opt.method_shown(i)))
+            {
+                field_i = field_lim;
+                method_i = method_lim;
+                continue;
+            }
+
             let flag = TypeAttrib::from(typedef.flag);
             write!(f, "\n\n\n.class {} {}", flag, self.get_str(typedef.name))?;
+            self.write_generic_params(
+                f,
+                to_type_or_methoddef(typedef_i as u32 + 1, TypeOrMethodDef::TypeDef),
+            )?;
 
             if let Some((extends_idx_tag, extends_idx)) = typedef.get_extends() {
                 write!(f, " extends ")?;
@@ -252,18 +446,42 @@ impl fmt::Display for IrFile {
                     TypeDefOrRef::TypeRef => {
                         self.typeref_tbl[extends_idx].fullname(f, self)?;
                     }
-                    TypeDefOrRef::TypeSpec => unimplemented!(),
+                    TypeDefOrRef::TypeSpec => {
+                        self.typespec_tbl[extends_idx].fmt(f, self)?;
+                    }
                 }
             }
+
+            let mut first_impl = true;
+            for interface_impl in self
+                .interfaceimpl_tbl
+                .iter()
+                .filter(|i| i.class as usize == typedef_i + 1)
+            {
+                write!(f, "{}", if first_impl { " implements " } else { ", " })?;
+                first_impl = false;
+                let (tag, idx) = interface_impl.get_interface();
+                fmt_tok(to_tok(idx, tag.to_tok_tag()), f, self)?;
+            }
             write!(f, " {{ ")?;
 
             while field_i < field_lim {
-                self.write_field(f, 1, field_i)?;
+                if opt.method.is_none() {
+                    self.write_field(f, 1, field_i)?;
+                }
                 field_i += 1;
             }
 
             while method_i < method_lim {
-                self.write_method(f, 1, method_i, method_i as u32 + 1 == entrypoint)?;
+                if opt.method_shown(method_i) {
+                    self.write_method(
+                        f,
+                        1,
+                        method_i,
+                        method_i as u32 + 1 == entrypoint,
+                        opt.show_tok,
+                    )?;
+                }
                 method_i += 1;
             }
             write!(f, "}}")?;
@@ -272,3 +490,289 @@ impl fmt::Display for IrFile {
         Ok(())
     }
 }
+
+impl IrFile {
+    /// write `#idx "str"` of an index into str heap
+    fn write_str_idx(&self, f: &mut fmt::Formatter<'_>, idx: u32) -> fmt::Result {
+        write!(f, "#{} {:?}", idx, self.get_str(idx))
+    }
+
+    /// write `#idx sig` of an index into blob heap
+    fn write_blob_idx(&self, f: &mut fmt::Formatter<'_>, idx: u32) -> fmt::Result {
+        write!(f, "#{} ", idx)?;
+        self.blob_heap[idx as usize].fmt(f, self)
+    }
+
+    /// write raw coded index followed by the name of what it refers to
+    fn write_coded_idx(&self, f: &mut fmt::Formatter<'_>, coded: u32, tok: u32) -> fmt::Result {
+        write!(f, "0x{:X} ", coded)?;
+        fmt_tok(tok, f, self)
+    }
+
+    /// Write all metadata tables and heaps with indices of entries.
+    ///
+    /// Table indices start from 1, heap indices start from 0
+    fn write_tables(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, ".version {}.{}", self.major_version, self.minor_version)?;
+
+        writeln!(f, "\n.mod_tbl")?;
+        for (i, m) in self.mod_tbl.iter().enumerate() {
+            write!(f, "    #{} name: ", i + 1)?;
+            self.write_str_idx(f, m.name)?;
+            write!(f, ", entrypoint: #{}", m.entrypoint)?;
+            if m.entrypoint != 0 {
+                write!(f, " ")?;
+                fmt_tok(to_tok(m.entrypoint, TokTag::MethodDef), f, self)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.modref_tbl")?;
+        for (i, modref) in self.modref_tbl.iter().enumerate() {
+            write!(f, "    #{} name: ", i + 1)?;
+            self.write_str_idx(f, modref.name)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.typedef_tbl")?;
+        for (i, typedef) in self.typedef_tbl.iter().enumerate() {
+            write!(f, "    #{} flag: 0x{:X}, name: ", i + 1, typedef.flag)?;
+            self.write_str_idx(f, typedef.name)?;
+            write!(f, ", extends: ")?;
+            if let Some((tag, idx)) = typedef.get_extends() {
+                self.write_coded_idx(f, typedef.extends, to_tok(idx as u32 + 1, tag.to_tok_tag()))?;
+            } else {
+                write!(f, "0x0")?;
+            }
+            writeln!(
+                f,
+                ", fields: #{}, methods: #{}",
+                typedef.fields, typedef.methods
+            )?;
+        }
+
+        writeln!(f, "\n.typeref_tbl")?;
+        for (i, typeref) in self.typeref_tbl.iter().enumerate() {
+            write!(f, "    #{} parent: ", i + 1)?;
+            let (tag, idx) = typeref.get_parent();
+            let tag = match tag {
+                ResolutionScope::Mod => TokTag::Mod,
+                ResolutionScope::ModRef => TokTag::ModRef,
+                ResolutionScope::TypeRef => TokTag::TypeRef,
+            };
+            self.write_coded_idx(f, typeref.parent, to_tok(idx as u32 + 1, tag))?;
+            write!(f, ", name: ")?;
+            self.write_str_idx(f, typeref.name)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.typespec_tbl")?;
+        for (i, typespec) in self.typespec_tbl.iter().enumerate() {
+            write!(f, "    #{} sig: ", i + 1)?;
+            self.write_blob_idx(f, typespec.sig)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.interfaceimpl_tbl")?;
+        for (i, interface_impl) in self.interfaceimpl_tbl.iter().enumerate() {
+            write!(f, "    #{} class: #{} ", i + 1, interface_impl.class)?;
+            fmt_tok(to_tok(interface_impl.class, TokTag::TypeDef), f, self)?;
+            write!(f, ", interface: ")?;
+            let (tag, idx) = interface_impl.get_interface();
+            self.write_coded_idx(f, interface_impl.interface, to_tok(idx, tag.to_tok_tag()))?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.field_tbl")?;
+        for (i, field) in self.field_tbl.iter().enumerate() {
+            write!(f, "    #{} flag: 0x{:X}, name: ", i + 1, field.flag)?;
+            self.write_str_idx(f, field.name)?;
+            write!(f, ", sig: ")?;
+            self.write_blob_idx(f, field.sig)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.method_tbl")?;
+        for (i, method) in self.method_tbl.iter().enumerate() {
+            write!(
+                f,
+                "    #{} body: #{}, impl_flag: 0x{:X}, flag: 0x{:X}, name: ",
+                i + 1,
+                method.body,
+                method.impl_flag,
+                method.flag
+            )?;
+            self.write_str_idx(f, method.name)?;
+            write!(f, ", sig: ")?;
+            self.write_blob_idx(f, method.sig)?;
+            writeln!(f, ", param_list: #{}", method.param_list)?;
+        }
+
+        writeln!(f, "\n.param_tbl")?;
+        for (i, param) in self.param_tbl.iter().enumerate() {
+            write!(
+                f,
+                "    #{} flag: 0x{:X}, sequence: {}, name: ",
+                i + 1,
+                param.flag,
+                param.sequence
+            )?;
+            self.write_str_idx(f, param.name)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.memberref_tbl")?;
+        for (i, memberref) in self.memberref_tbl.iter().enumerate() {
+            write!(f, "    #{} parent: ", i + 1)?;
+            let (tag, idx) = memberref.get_parent();
+            let tag = match tag {
+                MemberRefParent::TypeDef => TokTag::TypeDef,
+                MemberRefParent::TypeRef => TokTag::TypeRef,
+                MemberRefParent::ModRef => TokTag::ModRef,
+                MemberRefParent::MethodDef => TokTag::MethodDef,
+                MemberRefParent::TypeSpec => TokTag::TypeSpec,
+            };
+            self.write_coded_idx(f, memberref.parent, to_tok(idx, tag))?;
+            write!(f, ", name: ")?;
+            self.write_str_idx(f, memberref.name)?;
+            write!(f, ", sig: ")?;
+            self.write_blob_idx(f, memberref.sig)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.implmap_tbl")?;
+        for (i, implmap) in self.implmap_tbl.iter().enumerate() {
+            write!(f, "    #{} member: ", i + 1)?;
+            let (tag, idx) = implmap.get_member();
+            let tag = match tag {
+                MemberForwarded::Field => TokTag::Field,
+                MemberForwarded::MethodDef => TokTag::MethodDef,
+            };
+            self.write_coded_idx(f, implmap.member, to_tok(idx, tag))?;
+            write!(f, ", name: ")?;
+            self.write_str_idx(f, implmap.name)?;
+            write!(f, ", scope: #{} ", implmap.scope)?;
+            fmt_tok(to_tok(implmap.scope, TokTag::ModRef), f, self)?;
+            writeln!(f, ", flag: 0x{:X}", implmap.flag)?;
+        }
+
+        writeln!(f, "\n.generic_param_tbl")?;
+        for (i, param) in self.generic_param_tbl.iter().enumerate() {
+            write!(
+                f,
+                "    #{} number: {}, flag: 0x{:X}, owner: ",
+                i + 1,
+                param.number,
+                param.flag
+            )?;
+            let (tag, idx) = param.get_owner();
+            let tag = match tag {
+                TypeOrMethodDef::TypeDef => TokTag::TypeDef,
+                TypeOrMethodDef::MethodDef => TokTag::MethodDef,
+            };
+            self.write_coded_idx(f, param.owner, to_tok(idx, tag))?;
+            write!(f, ", name: ")?;
+            self.write_str_idx(f, param.name)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.generic_param_constraint_tbl")?;
+        for (i, constraint) in self.generic_param_constraint_tbl.iter().enumerate() {
+            write!(
+                f,
+                "    #{} owner: #{} {}, constraint: ",
+                i + 1,
+                constraint.owner,
+                self.get_str(self.generic_param_tbl[constraint.owner as usize - 1].name)
+            )?;
+            let (tag, idx) = get_typedeforref(constraint.constraint);
+            self.write_coded_idx(f, constraint.constraint, to_tok(idx, tag.to_tok_tag()))?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.methodspec_tbl")?;
+        for (i, methodspec) in self.methodspec_tbl.iter().enumerate() {
+            write!(f, "    #{} method: ", i + 1)?;
+            let (tag, idx) = methodspec.get_method();
+            self.write_coded_idx(f, methodspec.method, to_tok(idx, tag.to_tok_tag()))?;
+            write!(f, ", instantiation: ")?;
+            self.write_blob_idx(f, methodspec.instantiation)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.stand_alone_sig_tbl")?;
+        for (i, sig) in self.stand_alone_sig_tbl.iter().enumerate() {
+            write!(f, "    #{} sig: ", i + 1)?;
+            self.write_blob_idx(f, sig.sig)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.codes")?;
+        for (i, code) in self.codes.iter().enumerate() {
+            writeln!(
+                f,
+                "    #{} max_stack: {}, locals: #{}, insts: {} bytes, excepts: {}",
+                i + 1,
+                code.max_stack,
+                code.locals,
+                code.insts.len(),
+                code.excepts.len()
+            )?;
+        }
+
+        writeln!(f, "\n.document_tbl")?;
+        for (i, doc) in self.document_tbl.iter().enumerate() {
+            write!(f, "    #{} name: ", i + 1)?;
+            self.write_str_idx(f, doc.name)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\n.method_debug_tbl")?;
+        for (i, debug) in self.method_debug_tbl.iter().enumerate() {
+            write!(f, "    #{} method: #{} ", i + 1, debug.method)?;
+            fmt_tok(to_tok(debug.method, TokTag::MethodDef), f, self)?;
+            writeln!(
+                f,
+                ", document: #{}, seq_points: {}, scopes: {}",
+                debug.document,
+                debug.seq_points.len(),
+                debug.scopes.len()
+            )?;
+            for sp in debug.seq_points.iter() {
+                writeln!(f, "        IL_{:0>4X} {}:{}", sp.offset, sp.line, sp.col)?;
+            }
+            for scope in debug.scopes.iter() {
+                write!(
+                    f,
+                    "        scope IL_{:0>4X} to IL_{:0>4X}",
+                    scope.start,
+                    scope.start + scope.length
+                )?;
+                for var in scope.vars.iter() {
+                    write!(f, ", {}: ", var.idx)?;
+                    self.write_str_idx(f, var.name)?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f, "\n.str_heap")?;
+        for (i, s) in self.str_heap.iter().enumerate() {
+            writeln!(f, "    #{} {:?}", i, s)?;
+        }
+
+        writeln!(f, "\n.usr_str_heap")?;
+        for (i, s) in self.usr_str_heap.iter().enumerate() {
+            writeln!(f, "    #{} {:?}", i, s)?;
+        }
+
+        writeln!(f, "\n.blob_heap")?;
+        for (i, blob) in self.blob_heap.iter().enumerate() {
+            write!(f, "    #{} ", i)?;
+            blob.fmt(f, self)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
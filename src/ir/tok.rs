@@ -16,6 +16,7 @@ const TOK_STANDALONESIG_TAG: u32 = 0x11;
 const TOK_MEMBERREF_TAG: u32 = 0x0A;
 const TOK_TYPESPEC_TAG: u32 = 0x1B;
 const TOK_IMPLMAP_TAG: u32 = 0x1C;
+const TOK_METHODSPEC_TAG: u32 = 0x2B;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum TokTag {
@@ -30,6 +31,7 @@ pub enum TokTag {
     Param,
     StandAloneSig,
     ImplMap,
+    MethodSpec,
 }
 
 impl From<TokTag> for u32 {
@@ -46,18 +48,24 @@ impl From<TokTag> for u32 {
             TokTag::Param => TOK_PARAM_TAG,
             TokTag::StandAloneSig => TOK_STANDALONESIG_TAG,
             TokTag::ImplMap => TOK_IMPLMAP_TAG,
+            TokTag::MethodSpec => TOK_METHODSPEC_TAG,
         }
     }
 }
 
 pub fn get_tok_tag(tok: u32) -> (TokTag, u32) {
+    try_get_tok_tag(tok).unwrap_or_else(|| panic!("Invalid token 0x{:08X}", tok))
+}
+
+/// Same as get_tok_tag but return None if tok is invalid
+pub fn try_get_tok_tag(tok: u32) -> Option<(TokTag, u32)> {
     let tag = tok & TOK_TAG_MASK;
     let idx = tok >> TOK_TAG_MASK_SIZE;
     if idx == 0 {
-        panic!();
+        return None;
     }
 
-    (
+    Some((
         match tag {
             TOK_MOD_TAG => TokTag::Mod,
             TOK_MODREF_TAG => TokTag::ModRef,
@@ -70,10 +78,11 @@ pub fn get_tok_tag(tok: u32) -> (TokTag, u32) {
             TOK_PARAM_TAG => TokTag::Param,
             TOK_STANDALONESIG_TAG => TokTag::StandAloneSig,
             TOK_IMPLMAP_TAG => TokTag::ImplMap,
-            _ => unreachable!(),
+            TOK_METHODSPEC_TAG => TokTag::MethodSpec,
+            _ => return None,
         },
         idx,
-    )
+    ))
 }
 
 pub fn to_tok(raw_idx: u32, tag: TokTag) -> u32 {
@@ -131,5 +140,6 @@ pub fn fmt_tok(tok: u32, f: &mut fmt::Formatter<'_>, ctx: &IrFile) -> fmt::Resul
         TokTag::Param => unimplemented!(),
         TokTag::StandAloneSig => unimplemented!(),
         TokTag::ImplMap => unimplemented!(),
+        TokTag::MethodSpec => ctx.methodspec_tbl[idx].fmt(f, ctx),
     }
 }
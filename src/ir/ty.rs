@@ -34,7 +34,7 @@ impl TypeDef {
                 match self.extends & TYPEDEFORREF_TAG_MASK {
                     0 => TypeDefOrRef::TypeDef,
                     1 => TypeDefOrRef::TypeRef,
-                    3 => TypeDefOrRef::TypeSpec,
+                    2 => TypeDefOrRef::TypeSpec,
                     _ => unreachable!(),
                 },
                 raw_idx as usize - 1,
@@ -48,7 +48,7 @@ impl TypeDef {
         } else {
             None
         };
-        self.extends = (raw_idx << RESOLUTION_SCOPE_TAG_SIZE) | (idx_tag as u32);
+        self.extends = (raw_idx << TYPEDEFORREF_TAG_SIZE) | (idx_tag as u32);
         old_idx
     }
 }
@@ -166,6 +166,22 @@ pub enum TypeDefOrRef {
     TypeSpec = 2,
 }
 
+pub fn to_typedeforref(raw_idx: u32, tag: TypeDefOrRef) -> u32 {
+    (raw_idx << TYPEDEFORREF_TAG_SIZE) | (tag as u32)
+}
+
+pub fn get_typedeforref(coded: u32) -> (TypeDefOrRef, u32) {
+    (
+        match coded & TYPEDEFORREF_TAG_MASK {
+            0 => TypeDefOrRef::TypeDef,
+            1 => TypeDefOrRef::TypeRef,
+            2 => TypeDefOrRef::TypeSpec,
+            _ => unreachable!(),
+        },
+        coded >> TYPEDEFORREF_TAG_SIZE,
+    )
+}
+
 impl TypeDefOrRef {
     pub fn to_tok_tag(&self) -> TokTag {
         match self {
@@ -176,6 +192,33 @@ impl TypeDefOrRef {
     }
 }
 
+/// II.22.23 0x09
+pub struct InterfaceImpl {
+    /// index into typedef tbl
+    pub class: u32,
+    /// TypeDefOrRef
+    pub interface: u32,
+}
+
+impl InterfaceImpl {
+    pub fn get_interface(&self) -> (TypeDefOrRef, u32) {
+        get_typedeforref(self.interface)
+    }
+}
+
+impl ISerializable for InterfaceImpl {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.class.serialize(buf);
+        self.interface.serialize(buf);
+    }
+
+    fn deserialize(buf: &mut dyn IDeserializer) -> Self {
+        let class = u32::deserialize(buf);
+        let interface = u32::deserialize(buf);
+        InterfaceImpl { class, interface }
+    }
+}
+
 pub struct TypeSpec {
     /// index into blob heap
     pub sig: u32,
@@ -0,0 +1,1614 @@
+use super::bc_serde::Deserializer;
+use super::code::ExceptionClauseKind;
+use super::file::IrFile;
+use super::inst::Inst;
+use super::member::MemberRef;
+use super::member::MemberRefParent;
+use super::sig::{
+    InnerLocalVarType, InnerParamType, InnerRetType, IrSig, MethodSigFlag, MethodSigFlagTag,
+    ParamType, RetType, TypeSig, TypeSpecSig,
+};
+use super::tok::{fmt_tok, to_tok, try_get_tok_tag, TokTag};
+use super::ty::{ResolutionScope, TypeDefOrRef};
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// Verification type of a slot on eval stack, see III.1.8.1.1
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum VerType {
+    I4,
+    I8,
+    I,
+    F,
+    /// object reference
+    Ref(RefType),
+    /// managed pointer
+    ByRef,
+    /// instance of value type
+    Value,
+    /// type of generic param, which is unknown before instantiation
+    Generic,
+}
+
+/// Type of the object an object reference points to
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RefType {
+    /// null, which is assignable to any object reference
+    Null,
+    /// std::Object, any object reference is assignable to it
+    Object,
+    /// std::String
+    String,
+    /// single dimensional array of element type
+    Array(Box<VerType>),
+    /// instance of class or interface, typedef or typeref token
+    Class(u32),
+    /// instance of a class that is derived from all of the classes or interfaces,
+    /// which is the result of merging unrelated classes
+    Merged(Vec<u32>),
+}
+
+impl RefType {
+    /// classes or interfaces that the instance is known to be derived from
+    fn classes(&self) -> Option<&[u32]> {
+        match self {
+            RefType::Class(tok) => Some(std::slice::from_ref(tok)),
+            RefType::Merged(toks) => Some(toks),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerType::I4 => write!(f, "i32"),
+            VerType::I8 => write!(f, "i64"),
+            VerType::I => write!(f, "inative"),
+            VerType::F => write!(f, "F"),
+            VerType::Ref(ty) => write!(f, "{}", ty),
+            VerType::ByRef => write!(f, "&"),
+            VerType::Value => write!(f, "val"),
+            VerType::Generic => write!(f, "generic"),
+        }
+    }
+}
+
+impl fmt::Display for RefType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefType::Null => write!(f, "null"),
+            RefType::Object => write!(f, "O"),
+            RefType::String => write!(f, "string"),
+            RefType::Array(ele) => write!(f, "{}[]", ele),
+            RefType::Class(tok) => write!(f, "O(0x{:08X})", tok),
+            RefType::Merged(toks) => {
+                for (i, tok) in toks.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " & ")?;
+                    }
+                    write!(f, "O(0x{:08X})", tok)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl VerType {
+    /// whether a value of self can be used where expected is required.
+    /// int32 and native int are implicitly converted (III.1.6).
+    ///
+    /// Object references are checked by Verifier::is_assignable
+    fn is_assignable_to(&self, expected: &VerType) -> bool {
+        self == expected
+            || *self == VerType::Generic
+            || *expected == VerType::Generic
+            || matches!(
+                (self, expected),
+                (VerType::I, VerType::I4) | (VerType::I4, VerType::I)
+            )
+    }
+}
+
+pub struct VerifyError {
+    /// method that fails the verification
+    pub method: String,
+    /// offset of the inst that fails the verification
+    pub offset: Option<usize>,
+    pub msg: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, "{} IL_{:04X}: {}", self.method, offset, self.msg)
+        } else {
+            write!(f, "{}: {}", self.method, self.msg)
+        }
+    }
+}
+
+/// Verify all method bodies in file.
+///
+/// Branch targets, stack balance at merge points, stack depth against max stack,
+/// slot types against signatures and validity of tokens are checked.
+/// Object references are checked against the class hierarchy of this file
+pub fn verify(file: &IrFile) -> Result<(), VerifyError> {
+    let verifier = Verifier::new(file);
+    for method_i in 0..file.method_tbl.len() {
+        verifier.verify_method(method_i)?;
+    }
+    Ok(())
+}
+
+/// fmt_tok as Display
+struct TokDisplay<'f>(u32, &'f IrFile);
+
+impl fmt::Display for TokDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_tok(self.0, f, self.1)
+    }
+}
+
+/// Signature of a method token in the generic context of its instantiation
+struct MethodRef<'f> {
+    flag: &'f MethodSigFlag,
+    ps: &'f [ParamType],
+    ret: &'f RetType,
+    /// type token of parent, None if method has no parent type
+    parent: Option<u32>,
+    class_args: &'f [TypeSig],
+    method_args: &'f [TypeSig],
+}
+
+/// Verification context of a method
+struct MethodCtx {
+    args: Vec<VerType>,
+    locals: Vec<VerType>,
+    /// None if return void
+    ret: Option<VerType>,
+}
+
+struct Verifier<'f> {
+    file: &'f IrFile,
+    /// typerefs that are known to be value types from signatures in this file.
+    ///
+    /// A typeref does not tell whether it is a value type or not
+    value_typerefs: HashSet<u32>,
+}
+
+impl<'f> Verifier<'f> {
+    fn new(file: &'f IrFile) -> Verifier<'f> {
+        let mut verifier = Verifier {
+            file,
+            value_typerefs: HashSet::new(),
+        };
+        for sig in file.blob_heap.iter() {
+            match sig {
+                IrSig::Method(_, ps, ret) => {
+                    for p in ps.iter() {
+                        match &p.ty {
+                            InnerParamType::Default(ty) | InnerParamType::ByRef(ty) => {
+                                verifier.collect_value_typerefs(ty)
+                            }
+                        }
+                    }
+                    match &ret.ty {
+                        InnerRetType::Default(ty) | InnerRetType::ByRef(ty) => {
+                            verifier.collect_value_typerefs(ty)
+                        }
+                        InnerRetType::Void => {}
+                    }
+                }
+                IrSig::Field(ty) => verifier.collect_value_typerefs(ty),
+                IrSig::LocalVar(vars) => {
+                    for var in vars.iter() {
+                        match var {
+                            InnerLocalVarType::Default(ty) | InnerLocalVarType::ByRef(ty) => {
+                                verifier.collect_value_typerefs(ty)
+                            }
+                        }
+                    }
+                }
+                IrSig::TypeSpec(TypeSpecSig::SZArray(ty)) => verifier.collect_value_typerefs(ty),
+                IrSig::TypeSpec(TypeSpecSig::GenericInst(is_class, tok, args)) => {
+                    if !*is_class {
+                        verifier.value_typerefs.insert(*tok);
+                    }
+                    for arg in args.iter() {
+                        verifier.collect_value_typerefs(arg);
+                    }
+                }
+                IrSig::TypeSpec(TypeSpecSig::Var(_)) | IrSig::TypeSpec(TypeSpecSig::MVar(_)) => {}
+                IrSig::MethodSpec(args) => {
+                    for arg in args.iter() {
+                        verifier.collect_value_typerefs(arg);
+                    }
+                }
+            }
+        }
+        // Int32 is a special value type
+        for (typeref_i, typeref) in file.typeref_tbl.iter().enumerate() {
+            if let (ResolutionScope::ModRef, parent) = typeref.get_parent() {
+                if file.get_str(file.modref_tbl[parent].name) == "std"
+                    && file.get_str(typeref.name) == "Int32"
+                {
+                    verifier
+                        .value_typerefs
+                        .insert(to_tok(typeref_i as u32 + 1, TokTag::TypeRef));
+                }
+            }
+        }
+        verifier
+    }
+
+    fn collect_value_typerefs(&mut self, ty: &TypeSig) {
+        match ty {
+            TypeSig::ValueType(tok) => {
+                self.value_typerefs.insert(*tok);
+            }
+            TypeSig::GenericInst(is_class, tok, args) => {
+                if !*is_class {
+                    self.value_typerefs.insert(*tok);
+                }
+                for arg in args.iter() {
+                    self.collect_value_typerefs(arg);
+                }
+            }
+            TypeSig::SZArray(ty) => self.collect_value_typerefs(ty),
+            _ => {}
+        }
+    }
+
+    /// whether typedef (index starts from 1) is derived from std::ValueType
+    fn is_value_typedef(&self, idx: u32) -> bool {
+        let mut typedef = &self.file.typedef_tbl[idx as usize - 1];
+        // at most typedef_tbl.len() typedefs in the chain, unless there is a cycle
+        for _ in 0..self.file.typedef_tbl.len() {
+            match typedef.get_extends() {
+                Some((TypeDefOrRef::TypeDef, base)) => {
+                    typedef = &self.file.typedef_tbl[base];
+                    if self.file.mod_name() == "std"
+                        && self.file.get_str(typedef.name) == "ValueType"
+                    {
+                        return true;
+                    }
+                }
+                Some((TypeDefOrRef::TypeRef, base)) => {
+                    let typeref = &self.file.typeref_tbl[base];
+                    return match typeref.get_parent() {
+                        (ResolutionScope::ModRef, parent) => {
+                            self.file.get_str(self.file.modref_tbl[parent].name) == "std"
+                                && self.file.get_str(typeref.name) == "ValueType"
+                        }
+                        _ => false,
+                    };
+                }
+                Some((TypeDefOrRef::TypeSpec, _)) | None => return false,
+            }
+        }
+        false
+    }
+
+    /// index of parent typedef of method (index starts from 1), None if method has no parent
+    fn method_parent(&self, idx: u32) -> Option<u32> {
+        self.file
+            .typedef_tbl
+            .iter()
+            .rposition(|typedef| typedef.methods <= idx)
+            .map(|typedef_i| typedef_i as u32 + 1)
+    }
+
+    /// index of parent typedef of field (index starts from 1), None if field has no parent
+    fn field_parent(&self, idx: u32) -> Option<u32> {
+        self.file
+            .typedef_tbl
+            .iter()
+            .rposition(|typedef| typedef.fields <= idx)
+            .map(|typedef_i| typedef_i as u32 + 1)
+    }
+
+    /// name of a typedef or typeref token if it is a class of std
+    fn std_class_name(&self, tok: u32) -> Option<&'f str> {
+        match try_get_tok_tag(tok) {
+            Some((TokTag::TypeDef, idx)) if self.file.mod_name() == "std" => self
+                .file
+                .typedef_tbl
+                .get((idx as usize).wrapping_sub(1))
+                .map(|typedef| self.file.get_str(typedef.name)),
+            Some((TokTag::TypeRef, idx)) => {
+                let typeref = self.file.typeref_tbl.get((idx as usize).wrapping_sub(1))?;
+                match typeref.get_parent() {
+                    (ResolutionScope::ModRef, parent)
+                        if self.file.get_str(self.file.modref_tbl[parent].name) == "std" =>
+                    {
+                        Some(self.file.get_str(typeref.name))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// type of a std struct that wraps a primitive type
+    fn prim_struct_type(&self, tok: u32) -> Option<VerType> {
+        match self.std_class_name(tok)? {
+            "Boolean" | "Char" | "Int8" | "UInt8" | "Int16" | "UInt16" | "Int32" | "UInt32" => {
+                Some(VerType::I4)
+            }
+            "Int64" | "UInt64" => Some(VerType::I8),
+            "ISize" | "USize" => Some(VerType::I),
+            "Float32" | "Float64" => Some(VerType::F),
+            _ => None,
+        }
+    }
+
+    /// object reference to instance of a typedef or typeref token
+    fn class_ref(&self, tok: u32) -> VerType {
+        VerType::Ref(match self.std_class_name(tok) {
+            Some("Object") => RefType::Object,
+            Some("String") => RefType::String,
+            _ => RefType::Class(tok),
+        })
+    }
+
+    /// typedef or typeref token of a type token, generic args of typespec are dropped.
+    /// None if the type is not a class
+    fn class_tok(&self, tok: u32) -> Option<u32> {
+        match try_get_tok_tag(tok)? {
+            (TokTag::TypeDef, _) | (TokTag::TypeRef, _) => Some(tok),
+            (TokTag::TypeSpec, idx) => match self.file.blob_heap.get(
+                self.file
+                    .typespec_tbl
+                    .get((idx as usize).wrapping_sub(1))?
+                    .sig as usize,
+            ) {
+                Some(IrSig::TypeSpec(TypeSpecSig::GenericInst(_, base, _))) => Some(*base),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// whether two typedef or typeref tokens are the same class
+    fn is_same_class(&self, lhs: u32, rhs: u32) -> bool {
+        if lhs == rhs {
+            return true;
+        }
+        match (try_get_tok_tag(lhs), try_get_tok_tag(rhs)) {
+            (Some((TokTag::TypeRef, lhs)), Some((TokTag::TypeRef, rhs))) => {
+                match (
+                    self.file.typeref_tbl.get((lhs as usize).wrapping_sub(1)),
+                    self.file.typeref_tbl.get((rhs as usize).wrapping_sub(1)),
+                ) {
+                    (Some(lhs), Some(rhs)) => lhs.name == rhs.name && lhs.parent == rhs.parent,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// direct base class and interfaces of a typedef, None if tok is not a typedef
+    fn supertypes(&self, tok: u32) -> Option<Vec<u32>> {
+        let (idx, typedef) = match try_get_tok_tag(tok) {
+            Some((TokTag::TypeDef, idx)) => (
+                idx,
+                self.file.typedef_tbl.get((idx as usize).wrapping_sub(1))?,
+            ),
+            _ => return None,
+        };
+        let mut supertypes = Vec::new();
+        if let Some((tag, base)) = typedef.get_extends() {
+            if let Some(base) = self.class_tok(to_tok(base as u32 + 1, tag.to_tok_tag())) {
+                supertypes.push(base);
+            }
+        }
+        for interface_impl in self.file.interfaceimpl_tbl.iter() {
+            if interface_impl.class == idx {
+                let (tag, interface) = interface_impl.get_interface();
+                if let Some(interface) = self.class_tok(to_tok(interface, tag.to_tok_tag())) {
+                    supertypes.push(interface);
+                }
+            }
+        }
+        Some(supertypes)
+    }
+
+    /// whether class tok is derived from or implements expected.
+    ///
+    /// Supertypes of classes in other modules are unknown to the verifier.
+    /// Such a class is assumed to be derived from any class that is not defined in this module
+    fn is_subclass(&self, tok: u32, expected: u32) -> bool {
+        let expected_is_local = matches!(try_get_tok_tag(expected), Some((TokTag::TypeDef, _)));
+        let mut visited: HashSet<u32> = HashSet::new();
+        let mut worklist = vec![tok];
+        while let Some(tok) = worklist.pop() {
+            if self.is_same_class(tok, expected) {
+                return true;
+            }
+            if !visited.insert(tok) {
+                continue;
+            }
+            match self.supertypes(tok) {
+                Some(supertypes) => worklist.extend(supertypes),
+                None if matches!(self.std_class_name(tok), Some("Object") | Some("ValueType")) => {}
+                None if !expected_is_local => return true,
+                None => {}
+            }
+        }
+        false
+    }
+
+    /// whether a value of ty can be used where expected is required
+    fn is_assignable(&self, ty: &VerType, expected: &VerType) -> bool {
+        match (ty, expected) {
+            (VerType::Ref(ty), VerType::Ref(expected)) => self.is_ref_assignable(ty, expected),
+            _ => ty.is_assignable_to(expected),
+        }
+    }
+
+    fn is_ref_assignable(&self, ty: &RefType, expected: &RefType) -> bool {
+        match (ty, expected) {
+            (RefType::Null, _) | (_, RefType::Object) => true,
+            (RefType::String, RefType::String) => true,
+            (RefType::Array(ele), RefType::Array(expected)) => {
+                match (ele.as_ref(), expected.as_ref()) {
+                    // arrays of object references are covariant
+                    (VerType::Ref(ele), VerType::Ref(expected)) => {
+                        self.is_ref_assignable(ele, expected)
+                    }
+                    (ele, expected) => {
+                        ele == expected || *ele == VerType::Generic || *expected == VerType::Generic
+                    }
+                }
+            }
+            (_, RefType::Merged(expected)) => expected
+                .iter()
+                .all(|expected| self.is_ref_assignable(ty, &RefType::Class(*expected))),
+            (_, RefType::Class(expected)) => ty
+                .classes()
+                .is_some_and(|toks| toks.iter().any(|tok| self.is_subclass(*tok, *expected))),
+            _ => false,
+        }
+    }
+
+    /// Type of a slot at merge point, None if the two types cannot be merged
+    fn merge(&self, lhs: &VerType, rhs: &VerType) -> Option<VerType> {
+        match (lhs, rhs) {
+            _ if lhs == rhs => Some(lhs.clone()),
+            (VerType::Generic, _) | (_, VerType::Generic) => Some(VerType::Generic),
+            (VerType::Ref(lhs), VerType::Ref(rhs)) => Some(VerType::Ref(self.merge_ref(lhs, rhs))),
+            _ => None,
+        }
+    }
+
+    /// the closest common supertype of two object references
+    fn merge_ref(&self, lhs: &RefType, rhs: &RefType) -> RefType {
+        if self.is_ref_assignable(rhs, lhs) {
+            return lhs.clone();
+        }
+        if self.is_ref_assignable(lhs, rhs) {
+            return rhs.clone();
+        }
+        match (lhs, rhs) {
+            (RefType::Array(lhs_ele), RefType::Array(rhs_ele)) => {
+                match (lhs_ele.as_ref(), rhs_ele.as_ref()) {
+                    (VerType::Ref(lhs_ele), VerType::Ref(rhs_ele)) => {
+                        RefType::Array(Box::new(VerType::Ref(self.merge_ref(lhs_ele, rhs_ele))))
+                    }
+                    _ => RefType::Object,
+                }
+            }
+            _ => match (lhs.classes(), rhs.classes()) {
+                (Some(lhs), Some(rhs)) => self.merge_classes(lhs, rhs),
+                _ => RefType::Object,
+            },
+        }
+    }
+
+    /// the closest common supertypes of two sets of classes
+    fn merge_classes(&self, lhs: &[u32], rhs: &[u32]) -> RefType {
+        let is_common = |tok: u32| rhs.iter().any(|rhs| self.is_subclass(*rhs, tok));
+        let mut visited: HashSet<u32> = HashSet::new();
+        let mut worklist: Vec<u32> = lhs.to_vec();
+        let mut common: Vec<u32> = Vec::new();
+        while let Some(tok) = worklist.pop() {
+            if !visited.insert(tok) {
+                continue;
+            }
+            if is_common(tok) {
+                // supertypes of a common supertype are not closer
+                common.push(tok);
+            } else if let Some(supertypes) = self.supertypes(tok) {
+                worklist.extend(supertypes);
+            }
+        }
+        // keep the most derived ones
+        let mut closest: Vec<u32> = common
+            .iter()
+            .cloned()
+            .filter(|&tok| {
+                self.std_class_name(tok) != Some("Object")
+                    && !common.iter().any(|&other| {
+                        !self.is_same_class(other, tok)
+                            && self.is_subclass(other, tok)
+                            && !self.is_subclass(tok, other)
+                    })
+            })
+            .collect();
+        closest.sort_unstable();
+        closest.dedup();
+        match closest.len() {
+            0 => RefType::Object,
+            1 => RefType::Class(closest[0]),
+            _ => RefType::Merged(closest),
+        }
+    }
+
+    fn pop_expect(&self, stack: &mut Vec<VerType>, expected: &VerType) -> Result<(), String> {
+        let ty = pop(stack)?;
+        if self.is_assignable(&ty, expected) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Expect {} on stack but found {}",
+                self.type_desc(expected),
+                self.type_desc(&ty)
+            ))
+        }
+    }
+
+    /// pop object reference or pointer for field access of class parent
+    fn pop_instance(&self, stack: &mut Vec<VerType>, parent: Option<u32>) -> Result<(), String> {
+        match pop(stack)? {
+            VerType::Ref(ty) => match parent {
+                Some(parent) if !matches!(self.tok_type(parent), Ok(VerType::Value)) => {
+                    let ty = VerType::Ref(ty);
+                    let expected = self.class_ref(parent);
+                    if self.is_assignable(&ty, &expected) {
+                        Ok(())
+                    } else {
+                        Err(format!(
+                            "Cannot access field of {} on {}",
+                            self.type_desc(&expected),
+                            self.type_desc(&ty)
+                        ))
+                    }
+                }
+                _ => Ok(()),
+            },
+            VerType::ByRef | VerType::I | VerType::Generic => Ok(()),
+            ty => Err(format!("Cannot access field of {}", ty)),
+        }
+    }
+
+    /// description of a verification type with class names
+    fn type_desc(&self, ty: &VerType) -> String {
+        match ty {
+            VerType::Ref(RefType::Class(tok)) => TokDisplay(*tok, self.file).to_string(),
+            VerType::Ref(RefType::Merged(toks)) => toks
+                .iter()
+                .map(|tok| TokDisplay(*tok, self.file).to_string())
+                .collect::<Vec<String>>()
+                .join(" & "),
+            VerType::Ref(RefType::Array(ele)) => format!("{}[]", self.type_desc(ele)),
+            _ => ty.to_string(),
+        }
+    }
+
+    fn sig_type(&self, ty: &TypeSig, class_args: &[TypeSig], method_args: &[TypeSig]) -> VerType {
+        match ty {
+            TypeSig::Boolean
+            | TypeSig::Char
+            | TypeSig::I1
+            | TypeSig::U1
+            | TypeSig::I2
+            | TypeSig::U2
+            | TypeSig::I4
+            | TypeSig::U4 => VerType::I4,
+            TypeSig::I8 | TypeSig::U8 => VerType::I8,
+            TypeSig::R4 | TypeSig::R8 => VerType::F,
+            TypeSig::I | TypeSig::U => VerType::I,
+            TypeSig::String => VerType::Ref(RefType::String),
+            TypeSig::SZArray(ele) => VerType::Ref(RefType::Array(Box::new(self.sig_type(
+                ele,
+                class_args,
+                method_args,
+            )))),
+            TypeSig::Class(tok) => self.class_ref(*tok),
+            TypeSig::ValueType(_) => VerType::Value,
+            TypeSig::GenericInst(is_class, tok, _) => {
+                if *is_class {
+                    self.class_ref(*tok)
+                } else {
+                    VerType::Value
+                }
+            }
+            // generic args are in the generic context of the caller
+            TypeSig::Var(i) => class_args
+                .get(*i as usize)
+                .map_or(VerType::Generic, |arg| self.sig_type(arg, &[], &[])),
+            TypeSig::MVar(i) => method_args
+                .get(*i as usize)
+                .map_or(VerType::Generic, |arg| self.sig_type(arg, &[], &[])),
+        }
+    }
+
+    fn param_type(
+        &self,
+        p: &ParamType,
+        class_args: &[TypeSig],
+        method_args: &[TypeSig],
+    ) -> VerType {
+        match &p.ty {
+            InnerParamType::Default(ty) => self.sig_type(ty, class_args, method_args),
+            InnerParamType::ByRef(_) => VerType::ByRef,
+        }
+    }
+
+    fn ret_type(
+        &self,
+        ret: &RetType,
+        class_args: &[TypeSig],
+        method_args: &[TypeSig],
+    ) -> Option<VerType> {
+        match &ret.ty {
+            InnerRetType::Default(ty) => Some(self.sig_type(ty, class_args, method_args)),
+            InnerRetType::ByRef(_) => Some(VerType::ByRef),
+            InnerRetType::Void => None,
+        }
+    }
+
+    /// type of the instance of a typedef, typeref or typespec token
+    fn tok_type(&self, tok: u32) -> Result<VerType, String> {
+        if let Some(ty) = self.prim_struct_type(tok) {
+            return Ok(ty);
+        }
+        match try_get_tok_tag(tok) {
+            Some((TokTag::TypeDef, idx)) if idx as usize <= self.file.typedef_tbl.len() => {
+                Ok(if self.is_value_typedef(idx) {
+                    VerType::Value
+                } else {
+                    self.class_ref(tok)
+                })
+            }
+            Some((TokTag::TypeRef, idx)) if idx as usize <= self.file.typeref_tbl.len() => {
+                Ok(if self.value_typerefs.contains(&tok) {
+                    VerType::Value
+                } else {
+                    self.class_ref(tok)
+                })
+            }
+            Some((TokTag::TypeSpec, idx)) if idx as usize <= self.file.typespec_tbl.len() => {
+                match self
+                    .file
+                    .blob_heap
+                    .get(self.file.typespec_tbl[idx as usize - 1].sig as usize)
+                {
+                    Some(IrSig::TypeSpec(TypeSpecSig::SZArray(ele))) => Ok(VerType::Ref(
+                        RefType::Array(Box::new(self.sig_type(ele, &[], &[]))),
+                    )),
+                    Some(IrSig::TypeSpec(TypeSpecSig::GenericInst(is_class, base, _))) => {
+                        Ok(if *is_class {
+                            self.class_ref(*base)
+                        } else {
+                            VerType::Value
+                        })
+                    }
+                    Some(IrSig::TypeSpec(TypeSpecSig::Var(_)))
+                    | Some(IrSig::TypeSpec(TypeSpecSig::MVar(_))) => Ok(VerType::Generic),
+                    _ => Err(format!("Typespec #{} has an invalid signature", idx)),
+                }
+            }
+            _ => Err(format!("Invalid type token 0x{:08X}", tok)),
+        }
+    }
+
+    fn method_sig(
+        &self,
+        sig: u32,
+    ) -> Result<(&'f MethodSigFlag, &'f [ParamType], &'f RetType), String> {
+        if let Some(IrSig::Method(flag, ps, ret)) = self.file.blob_heap.get(sig as usize) {
+            Ok((flag, ps, ret))
+        } else {
+            Err(format!("Blob #{} is not a method signature", sig))
+        }
+    }
+
+    /// return type token and generic args of memberref parent
+    fn memberref_parent(
+        &self,
+        memberref: &MemberRef,
+    ) -> Result<(Option<u32>, &'f [TypeSig]), String> {
+        let (tag, idx) = memberref.get_parent();
+        let tbl_len = match tag {
+            MemberRefParent::TypeDef => self.file.typedef_tbl.len(),
+            MemberRefParent::TypeRef => self.file.typeref_tbl.len(),
+            MemberRefParent::ModRef => self.file.modref_tbl.len(),
+            MemberRefParent::MethodDef => self.file.method_tbl.len(),
+            MemberRefParent::TypeSpec => self.file.typespec_tbl.len(),
+        };
+        if idx as usize > tbl_len {
+            return Err(format!("Invalid memberref parent 0x{:X}", memberref.parent));
+        }
+        Ok(match tag {
+            MemberRefParent::TypeDef => (Some(to_tok(idx, TokTag::TypeDef)), &[]),
+            MemberRefParent::TypeRef => (Some(to_tok(idx, TokTag::TypeRef)), &[]),
+            MemberRefParent::TypeSpec => (
+                Some(to_tok(idx, TokTag::TypeSpec)),
+                match self
+                    .file
+                    .blob_heap
+                    .get(self.file.typespec_tbl[idx as usize - 1].sig as usize)
+                {
+                    Some(IrSig::TypeSpec(TypeSpecSig::GenericInst(_, _, args))) => args,
+                    _ => &[],
+                },
+            ),
+            MemberRefParent::ModRef | MemberRefParent::MethodDef => (None, &[]),
+        })
+    }
+
+    fn resolve_method(&self, tok: u32) -> Result<MethodRef<'f>, String> {
+        match try_get_tok_tag(tok) {
+            Some((TokTag::MethodDef, idx)) if idx as usize <= self.file.method_tbl.len() => {
+                let (flag, ps, ret) =
+                    self.method_sig(self.file.method_tbl[idx as usize - 1].sig)?;
+                Ok(MethodRef {
+                    flag,
+                    ps,
+                    ret,
+                    parent: self
+                        .method_parent(idx)
+                        .map(|parent| to_tok(parent, TokTag::TypeDef)),
+                    class_args: &[],
+                    method_args: &[],
+                })
+            }
+            Some((TokTag::MemberRef, idx)) if idx as usize <= self.file.memberref_tbl.len() => {
+                let memberref = &self.file.memberref_tbl[idx as usize - 1];
+                let (flag, ps, ret) = self.method_sig(memberref.sig)?;
+                let (parent, class_args) = self.memberref_parent(memberref)?;
+                Ok(MethodRef {
+                    flag,
+                    ps,
+                    ret,
+                    parent,
+                    class_args,
+                    method_args: &[],
+                })
+            }
+            Some((TokTag::MethodSpec, idx)) if idx as usize <= self.file.methodspec_tbl.len() => {
+                let methodspec = &self.file.methodspec_tbl[idx as usize - 1];
+                let (tag, method_idx) = methodspec.get_method();
+                let mut method = self.resolve_method(to_tok(method_idx, tag.to_tok_tag()))?;
+                if let Some(IrSig::MethodSpec(args)) =
+                    self.file.blob_heap.get(methodspec.instantiation as usize)
+                {
+                    method.method_args = args;
+                    Ok(method)
+                } else {
+                    Err(format!("Methodspec #{} has an invalid instantiation", idx))
+                }
+            }
+            _ => Err(format!("Invalid method token 0x{:08X}", tok)),
+        }
+    }
+
+    /// return field type and class token of its parent
+    fn resolve_field(&self, tok: u32) -> Result<(VerType, Option<u32>), String> {
+        match try_get_tok_tag(tok) {
+            Some((TokTag::Field, idx)) if idx as usize <= self.file.field_tbl.len() => {
+                let sig = self.file.field_tbl[idx as usize - 1].sig;
+                if let Some(IrSig::Field(ty)) = self.file.blob_heap.get(sig as usize) {
+                    Ok((
+                        self.sig_type(ty, &[], &[]),
+                        self.field_parent(idx)
+                            .map(|parent| to_tok(parent, TokTag::TypeDef)),
+                    ))
+                } else {
+                    Err(format!("Blob #{} is not a field signature", sig))
+                }
+            }
+            Some((TokTag::MemberRef, idx)) if idx as usize <= self.file.memberref_tbl.len() => {
+                let memberref = &self.file.memberref_tbl[idx as usize - 1];
+                if let Some(IrSig::Field(ty)) = self.file.blob_heap.get(memberref.sig as usize) {
+                    let (parent, class_args) = self.memberref_parent(memberref)?;
+                    Ok((
+                        self.sig_type(ty, class_args, &[]),
+                        parent.and_then(|parent| self.class_tok(parent)),
+                    ))
+                } else {
+                    Err(format!("Blob #{} is not a field signature", memberref.sig))
+                }
+            }
+            _ => Err(format!("Invalid field token 0x{:08X}", tok)),
+        }
+    }
+
+    fn method_desc(&self, method_i: usize) -> String {
+        let method = &self.file.method_tbl[method_i];
+        if let Some(IrSig::Method(_, _, _)) = self.file.blob_heap.get(method.sig as usize) {
+            TokDisplay(to_tok(method_i as u32 + 1, TokTag::MethodDef), self.file).to_string()
+        } else {
+            // signature is broken
+            format!(
+                "{}::{}",
+                self.file.mod_name(),
+                self.file.get_str(method.name)
+            )
+        }
+    }
+
+    fn verify_method(&self, method_i: usize) -> Result<(), VerifyError> {
+        let method = &self.file.method_tbl[method_i];
+        if method.body == 0 {
+            // abstract or external method
+            return Ok(());
+        }
+        let error = |offset: Option<usize>, msg: String| VerifyError {
+            method: self.method_desc(method_i),
+            offset,
+            msg,
+        };
+
+        let code = self
+            .file
+            .codes
+            .get(method.body as usize - 1)
+            .ok_or_else(|| error(None, format!("Method body #{} does not exist", method.body)))?;
+        let (flag, ps, ret) = self
+            .method_sig(method.sig)
+            .map_err(|msg| error(None, msg))?;
+        let mut args = Vec::new();
+        if flag.has_flag(MethodSigFlagTag::HasThis) {
+            args.push(match self.method_parent(method_i as u32 + 1) {
+                Some(parent) if self.is_value_typedef(parent) => VerType::ByRef,
+                Some(parent) => self.class_ref(to_tok(parent, TokTag::TypeDef)),
+                None => VerType::Ref(RefType::Object),
+            });
+        }
+        args.extend(ps.iter().map(|p| self.param_type(p, &[], &[])));
+        let locals = if code.locals == 0 {
+            vec![]
+        } else {
+            match self
+                .file
+                .stand_alone_sig_tbl
+                .get(code.locals as usize - 1)
+                .and_then(|sig| self.file.blob_heap.get(sig.sig as usize))
+            {
+                Some(IrSig::LocalVar(vars)) => vars
+                    .iter()
+                    .map(|var| match var {
+                        InnerLocalVarType::Default(ty) => self.sig_type(ty, &[], &[]),
+                        InnerLocalVarType::ByRef(_) => VerType::ByRef,
+                    })
+                    .collect(),
+                _ => {
+                    return Err(error(
+                        None,
+                        format!("Invalid local var signature #{}", code.locals),
+                    ))
+                }
+            }
+        };
+        let ctx = MethodCtx {
+            args,
+            locals,
+            ret: self.ret_type(ret, &[], &[]),
+        };
+
+        // decode insts
+        let mut offsets: Vec<usize> = Vec::new();
+        let mut insts: Vec<Inst> = Vec::new();
+        let mut offset = 0;
+        while offset < code.insts.len() {
+            // pad zeros so that a truncated inst can be detected after it is decoded
+            let mut buf = Deserializer::new(
+                code.insts[offset..]
+                    .iter()
+                    .cloned()
+                    .chain([0; 8].iter().cloned())
+                    .peekable(),
+            );
+            let inst = Inst::try_deserialize(&mut buf).ok_or_else(|| {
+                error(
+                    Some(offset),
+                    format!("Unknown opcode 0x{:02X}", code.insts[offset]),
+                )
+            })?;
+            if offset + inst.size() > code.insts.len() {
+                return Err(error(Some(offset), String::from("Truncated inst")));
+            }
+            offsets.push(offset);
+            offset += inst.size();
+            insts.push(inst);
+        }
+        if insts.is_empty() {
+            return Err(error(None, String::from("Method body is empty")));
+        }
+        let inst_at = |offset: i64| -> Option<usize> {
+            if offset < 0 {
+                None
+            } else {
+                offsets.binary_search(&(offset as usize)).ok()
+            }
+        };
+        // offset of the end of a region can be the end of insts
+        let is_boundary = |offset: u32| -> bool {
+            offset as usize == code.insts.len() || inst_at(offset as i64).is_some()
+        };
+
+        // eval stack state at the beginning of each inst
+        let mut states: Vec<Option<Vec<VerType>>> = vec![None; insts.len()];
+        let mut worklist: Vec<usize> = Vec::new();
+        states[0] = Some(vec![]);
+        worklist.push(0);
+        for (clause_i, clause) in code.excepts.iter().enumerate() {
+            let handler_i = match inst_at(clause.handler_offset as i64) {
+                Some(handler_i)
+                    if is_boundary(clause.try_offset)
+                        && is_boundary(clause.try_offset + clause.try_length)
+                        && is_boundary(clause.handler_offset + clause.handler_length) =>
+                {
+                    handler_i
+                }
+                _ => {
+                    return Err(error(
+                        None,
+                        format!(
+                            "Exception clause {} has a region that does not start or end at inst boundaries",
+                            clause_i
+                        ),
+                    ))
+                }
+            };
+            states[handler_i] = Some(match clause.kind {
+                ExceptionClauseKind::Catch => {
+                    vec![self.tok_type(clause.class_tok).map_err(|msg| {
+                        error(None, format!("Exception clause {}: {}", clause_i, msg))
+                    })?]
+                }
+                ExceptionClauseKind::Finally => vec![],
+            });
+            worklist.push(handler_i);
+        }
+
+        while let Some(inst_i) = worklist.pop() {
+            let offset = offsets[inst_i];
+            let inst = &insts[inst_i];
+            let mut stack = states[inst_i].clone().unwrap();
+            let falls_through = self
+                .exec(&ctx, inst, &mut stack)
+                .map_err(|msg| error(Some(offset), msg))?;
+            if stack.len() > code.max_stack as usize {
+                return Err(error(
+                    Some(offset),
+                    format!("Stack depth exceeds max stack {}", code.max_stack),
+                ));
+            }
+
+            let mut succs: Vec<usize> = Vec::new();
+            if falls_through {
+                if inst_i + 1 >= insts.len() {
+                    return Err(error(
+                        Some(offset),
+                        String::from("Control falls through the end of method"),
+                    ));
+                }
+                succs.push(inst_i + 1);
+            }
+            if let Some(delta) = branch_offset(inst) {
+                let target = (offset + inst.size()) as i64 + delta as i64;
+                succs.push(inst_at(target).ok_or_else(|| {
+                    error(
+                        Some(offset),
+                        format!("Branch target IL_{:04X} is not at an inst boundary", target),
+                    )
+                })?);
+            }
+
+            for succ in succs.into_iter() {
+                let merged = if let Some(old) = &states[succ] {
+                    if old.len() != stack.len() {
+                        return Err(error(
+                            Some(offset),
+                            format!(
+                                "Stack depth mismatch at merge point IL_{:04X}: {} and {}",
+                                offsets[succ],
+                                old.len(),
+                                stack.len()
+                            ),
+                        ));
+                    }
+                    let mut merged = Vec::with_capacity(old.len());
+                    for (old_ty, new_ty) in old.iter().zip(stack.iter()) {
+                        merged.push(self.merge(old_ty, new_ty).ok_or_else(|| {
+                            error(
+                                Some(offset),
+                                format!(
+                                    "Stack type mismatch at merge point IL_{:04X}: {} and {}",
+                                    offsets[succ],
+                                    self.type_desc(old_ty),
+                                    self.type_desc(new_ty)
+                                ),
+                            )
+                        })?);
+                    }
+                    if &merged == old {
+                        continue;
+                    }
+                    merged
+                } else {
+                    stack.clone()
+                };
+                states[succ] = Some(merged);
+                worklist.push(succ);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Simulate inst on stack, return whether control falls through to the next inst
+    fn exec(&self, ctx: &MethodCtx, inst: &Inst, stack: &mut Vec<VerType>) -> Result<bool, String> {
+        match inst {
+            Inst::Nop => {}
+
+            Inst::LdArg0 => stack.push(load_var(&ctx.args, 0, "Arg")?),
+            Inst::LdArg1 => stack.push(load_var(&ctx.args, 1, "Arg")?),
+            Inst::LdArg2 => stack.push(load_var(&ctx.args, 2, "Arg")?),
+            Inst::LdArg3 => stack.push(load_var(&ctx.args, 3, "Arg")?),
+            Inst::LdArgS(idx) => stack.push(load_var(&ctx.args, *idx as usize, "Arg")?),
+            Inst::LdArgAS(idx) => {
+                load_var(&ctx.args, *idx as usize, "Arg")?;
+                stack.push(VerType::ByRef);
+            }
+            Inst::StArgS(idx) => {
+                self.pop_expect(stack, &load_var(&ctx.args, *idx as usize, "Arg")?)?
+            }
+
+            Inst::LdLoc0 => stack.push(load_var(&ctx.locals, 0, "Local")?),
+            Inst::LdLoc1 => stack.push(load_var(&ctx.locals, 1, "Local")?),
+            Inst::LdLoc2 => stack.push(load_var(&ctx.locals, 2, "Local")?),
+            Inst::LdLoc3 => stack.push(load_var(&ctx.locals, 3, "Local")?),
+            Inst::LdLocS(idx) => stack.push(load_var(&ctx.locals, *idx as usize, "Local")?),
+            Inst::LdLoc(idx) => stack.push(load_var(&ctx.locals, *idx as usize, "Local")?),
+            Inst::LdLocAS(idx) => {
+                load_var(&ctx.locals, *idx as usize, "Local")?;
+                stack.push(VerType::ByRef);
+            }
+            Inst::LdLocA(idx) => {
+                load_var(&ctx.locals, *idx as usize, "Local")?;
+                stack.push(VerType::ByRef);
+            }
+            Inst::StLoc0 => self.pop_expect(stack, &load_var(&ctx.locals, 0, "Local")?)?,
+            Inst::StLoc1 => self.pop_expect(stack, &load_var(&ctx.locals, 1, "Local")?)?,
+            Inst::StLoc2 => self.pop_expect(stack, &load_var(&ctx.locals, 2, "Local")?)?,
+            Inst::StLoc3 => self.pop_expect(stack, &load_var(&ctx.locals, 3, "Local")?)?,
+            Inst::StLocS(idx) => {
+                self.pop_expect(stack, &load_var(&ctx.locals, *idx as usize, "Local")?)?
+            }
+            Inst::StLoc(idx) => {
+                self.pop_expect(stack, &load_var(&ctx.locals, *idx as usize, "Local")?)?
+            }
+
+            Inst::LdNull => stack.push(VerType::Ref(RefType::Null)),
+            Inst::LdCM1
+            | Inst::LdC0
+            | Inst::LdC1
+            | Inst::LdC2
+            | Inst::LdC3
+            | Inst::LdC4
+            | Inst::LdC5
+            | Inst::LdC6
+            | Inst::LdC7
+            | Inst::LdC8
+            | Inst::LdCI4S(_)
+            | Inst::LdCI4(_) => stack.push(VerType::I4),
+            Inst::LdCI8(_) => stack.push(VerType::I8),
+            Inst::LdCR4(_) | Inst::LdCR8(_) => stack.push(VerType::F),
+
+            Inst::Dup => {
+                let ty = pop(stack)?;
+                stack.push(ty.clone());
+                stack.push(ty);
+            }
+            Inst::Pop => {
+                pop(stack)?;
+            }
+
+            Inst::Call(tok) | Inst::CallVirt(tok) | Inst::NewObj(tok) => {
+                let method = self.resolve_method(*tok)?;
+                for p in method.ps.iter().rev() {
+                    self.pop_expect(
+                        stack,
+                        &self.param_type(p, method.class_args, method.method_args),
+                    )?;
+                }
+                let has_this = method.flag.has_flag(MethodSigFlagTag::HasThis);
+                if let Inst::NewObj(_) = inst {
+                    if !has_this {
+                        return Err(String::from(".ctor should be an instance method"));
+                    }
+                    let parent = method
+                        .parent
+                        .ok_or_else(|| String::from(".ctor must be class member"))?;
+                    stack.push(self.tok_type(parent)?);
+                } else {
+                    if has_this {
+                        let this = pop(stack)?;
+                        let is_valid_this = match &this {
+                            VerType::Ref(_) => {
+                                match method.parent.and_then(|p| self.class_tok(p)) {
+                                    Some(parent) => {
+                                        self.is_assignable(&this, &self.class_ref(parent))
+                                    }
+                                    None => true,
+                                }
+                            }
+                            // callvirt is not used for methods of value types
+                            VerType::ByRef => !matches!(inst, Inst::CallVirt(_)),
+                            VerType::Generic => true,
+                            _ => false,
+                        };
+                        if !is_valid_this {
+                            return Err(format!(
+                                "Invalid this pointer of type {}",
+                                self.type_desc(&this)
+                            ));
+                        }
+                    }
+                    if let Some(ret) =
+                        self.ret_type(method.ret, method.class_args, method.method_args)
+                    {
+                        stack.push(ret);
+                    }
+                }
+            }
+            Inst::Ret => {
+                if let Some(ret) = &ctx.ret {
+                    self.pop_expect(stack, ret)?;
+                }
+                if !stack.is_empty() {
+                    return Err(format!(
+                        "{} slots are left on stack when return",
+                        stack.len()
+                    ));
+                }
+                return Ok(false);
+            }
+
+            Inst::Br(_) | Inst::Leave(_) => {
+                if let Inst::Leave(_) = inst {
+                    // leave empties the eval stack
+                    stack.clear();
+                }
+                return Ok(false);
+            }
+            Inst::BrFalse(_) | Inst::BrTrue(_) => self.pop_expect(stack, &VerType::I4)?,
+            Inst::BEq(_)
+            | Inst::BGe(_)
+            | Inst::BGt(_)
+            | Inst::BLe(_)
+            | Inst::BLt(_)
+            | Inst::BNe(_) => {
+                let rhs = pop(stack)?;
+                let lhs = pop(stack)?;
+                check_cmp(&lhs, &rhs, matches!(inst, Inst::BEq(_) | Inst::BNe(_)))?;
+            }
+
+            Inst::Throw => {
+                pop_ref(stack)?;
+                return Ok(false);
+            }
+            Inst::EndFinally => {
+                stack.clear();
+                return Ok(false);
+            }
+
+            Inst::CEq | Inst::CGt | Inst::CGtUn | Inst::CLt | Inst::CLtUn => {
+                let rhs = pop(stack)?;
+                let lhs = pop(stack)?;
+                // cgt.un on refs is used to test if a ref is not null
+                check_cmp(&lhs, &rhs, *inst == Inst::CEq || *inst == Inst::CGtUn)?;
+                stack.push(VerType::I4);
+            }
+
+            Inst::Add | Inst::Sub | Inst::Mul | Inst::Div | Inst::Rem => {
+                let rhs = pop(stack)?;
+                let lhs = pop(stack)?;
+                stack.push(binary_numeric_type(&lhs, &rhs)?);
+            }
+            Inst::DivUn | Inst::RemUn | Inst::And | Inst::Or | Inst::Xor => {
+                let rhs = pop(stack)?;
+                let lhs = pop(stack)?;
+                let ty = binary_numeric_type(&lhs, &rhs)?;
+                if ty == VerType::F {
+                    return Err(String::from("Int op cannot be applied to F"));
+                }
+                stack.push(ty);
+            }
+            Inst::Shl | Inst::Shr | Inst::ShrUn => {
+                let amount = pop(stack)?;
+                let ty = pop(stack)?;
+                match amount {
+                    VerType::I4 | VerType::I | VerType::Generic => {}
+                    _ => {
+                        return Err(format!(
+                            "Shift amount should be I4 or I but found {}",
+                            amount
+                        ))
+                    }
+                }
+                match ty {
+                    VerType::I4 | VerType::I8 | VerType::I | VerType::Generic => stack.push(ty),
+                    _ => return Err(format!("Cannot shift {}", ty)),
+                }
+            }
+            Inst::Neg => {
+                let ty = pop(stack)?;
+                match ty {
+                    VerType::I4 | VerType::I8 | VerType::I | VerType::F | VerType::Generic => {
+                        stack.push(ty)
+                    }
+                    _ => return Err(format!("Cannot neg {}", ty)),
+                }
+            }
+            Inst::Not => {
+                let ty = pop(stack)?;
+                match ty {
+                    VerType::I4 | VerType::I8 | VerType::I | VerType::Generic => stack.push(ty),
+                    _ => return Err(format!("Cannot not {}", ty)),
+                }
+            }
+            Inst::ConvI1
+            | Inst::ConvI2
+            | Inst::ConvI4
+            | Inst::ConvI8
+            | Inst::ConvR4
+            | Inst::ConvR8
+            | Inst::ConvU4
+            | Inst::ConvU8
+            | Inst::ConvRUn
+            | Inst::ConvU2
+            | Inst::ConvU1
+            | Inst::ConvI
+            | Inst::ConvU
+            | Inst::ConvOvfI1
+            | Inst::ConvOvfU1
+            | Inst::ConvOvfI2
+            | Inst::ConvOvfU2
+            | Inst::ConvOvfI4
+            | Inst::ConvOvfU4
+            | Inst::ConvOvfI8
+            | Inst::ConvOvfU8
+            | Inst::ConvOvfI
+            | Inst::ConvOvfU
+            | Inst::ConvOvfI1Un
+            | Inst::ConvOvfI2Un
+            | Inst::ConvOvfI4Un
+            | Inst::ConvOvfI8Un
+            | Inst::ConvOvfU1Un
+            | Inst::ConvOvfU2Un
+            | Inst::ConvOvfU4Un
+            | Inst::ConvOvfU8Un
+            | Inst::ConvOvfIUn
+            | Inst::ConvOvfUUn => {
+                let ty = pop(stack)?;
+                match ty {
+                    VerType::F if *inst == Inst::ConvRUn => {
+                        return Err(String::from("conv.r.un cannot be applied to F"))
+                    }
+                    VerType::I4 | VerType::I8 | VerType::I | VerType::F | VerType::Generic => {}
+                    _ => return Err(format!("Cannot convert {}", ty)),
+                }
+                stack.push(conv_type(inst));
+            }
+
+            Inst::LdFld(tok) | Inst::LdFldA(tok) => {
+                let (ty, parent) = self.resolve_field(*tok)?;
+                self.pop_instance(stack, parent)?;
+                stack.push(if let Inst::LdFld(_) = inst {
+                    ty
+                } else {
+                    VerType::ByRef
+                });
+            }
+            Inst::StFld(tok) => {
+                let (ty, parent) = self.resolve_field(*tok)?;
+                self.pop_expect(stack, &ty)?;
+                self.pop_instance(stack, parent)?;
+            }
+            Inst::LdSFld(tok) => stack.push(self.resolve_field(*tok)?.0),
+            Inst::LdSFldA(tok) => {
+                self.resolve_field(*tok)?;
+                stack.push(VerType::ByRef);
+            }
+            Inst::StSFld(tok) => self.pop_expect(stack, &self.resolve_field(*tok)?.0)?,
+
+            Inst::InitObj(tok) => {
+                self.tok_type(*tok)?;
+                self.pop_expect(stack, &VerType::ByRef)?;
+            }
+            Inst::CastClass(tok) | Inst::IsInst(tok) => {
+                let ty = self.tok_type(*tok)?;
+                // generic args are erased, so the target type cannot be checked at runtime
+                if !matches!(ty, VerType::Ref(_)) {
+                    return Err(format!("Cannot cast object reference to {}", ty));
+                }
+                pop_ref(stack)?;
+                stack.push(ty);
+            }
+
+            Inst::LdStr(idx) => {
+                if *idx as usize >= self.file.usr_str_heap.len() {
+                    return Err(format!("Invalid user string index {}", idx));
+                }
+                stack.push(VerType::Ref(RefType::String));
+            }
+
+            Inst::NewArr(tok) => {
+                let ele = self.tok_type(*tok)?;
+                pop_arr_size(stack)?;
+                stack.push(VerType::Ref(RefType::Array(Box::new(ele))));
+            }
+            Inst::LdLen => {
+                pop_arr(stack)?;
+                stack.push(VerType::I);
+            }
+            Inst::LdElemI1
+            | Inst::LdElemU1
+            | Inst::LdElemI2
+            | Inst::LdElemU2
+            | Inst::LdElemI4
+            | Inst::LdElemU4
+            | Inst::LdElemI8
+            | Inst::LdElemI
+            | Inst::LdElemR4
+            | Inst::LdElemR8 => {
+                pop_arr_size(stack)?;
+                pop_arr(stack)?;
+                stack.push(elem_type(inst));
+            }
+            Inst::LdElemRef => {
+                pop_arr_size(stack)?;
+                let ele = pop_arr(stack)?;
+                match ele {
+                    VerType::Ref(_) | VerType::Generic => stack.push(ele),
+                    _ => return Err(format!("ldelem.ref cannot load element of type {}", ele)),
+                }
+            }
+            Inst::LdElem(tok) | Inst::LdElemA(tok) => {
+                let ty = self.tok_type(*tok)?;
+                pop_arr_size(stack)?;
+                pop_arr(stack)?;
+                stack.push(if let Inst::LdElem(_) = inst {
+                    ty
+                } else {
+                    VerType::ByRef
+                });
+            }
+            Inst::StElemI
+            | Inst::StElemI1
+            | Inst::StElemI2
+            | Inst::StElemI4
+            | Inst::StElemI8
+            | Inst::StElemR4
+            | Inst::StElemR8
+            | Inst::StElem(_) => {
+                self.pop_expect(
+                    stack,
+                    &if let Inst::StElem(tok) = inst {
+                        self.tok_type(*tok)?
+                    } else {
+                        elem_type(inst)
+                    },
+                )?;
+                pop_arr_size(stack)?;
+                pop_arr(stack)?;
+            }
+            Inst::StElemRef => {
+                let value = pop(stack)?;
+                pop_arr_size(stack)?;
+                let ele = pop_arr(stack)?;
+                if !matches!(ele, VerType::Ref(_) | VerType::Generic) {
+                    return Err(format!("stelem.ref cannot store element of type {}", ele));
+                }
+                if !self.is_assignable(&value, &ele) {
+                    return Err(format!(
+                        "Cannot store {} into {} array",
+                        self.type_desc(&value),
+                        self.type_desc(&ele)
+                    ));
+                }
+            }
+            Inst::LdIndI1
+            | Inst::LdIndU1
+            | Inst::LdIndI2
+            | Inst::LdIndU2
+            | Inst::LdIndI4
+            | Inst::LdIndU4
+            | Inst::LdIndI8
+            | Inst::LdIndI
+            | Inst::LdIndR4
+            | Inst::LdIndR8 => {
+                self.pop_expect(stack, &VerType::ByRef)?;
+                stack.push(elem_type(inst));
+            }
+            Inst::StIndI1
+            | Inst::StIndI2
+            | Inst::StIndI4
+            | Inst::StIndI8
+            | Inst::StIndR4
+            | Inst::StIndR8
+            | Inst::StIndI => {
+                self.pop_expect(stack, &elem_type(inst))?;
+                self.pop_expect(stack, &VerType::ByRef)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// offset of branch inst
+fn branch_offset(inst: &Inst) -> Option<i32> {
+    match inst {
+        Inst::Br(offset)
+        | Inst::BrFalse(offset)
+        | Inst::BrTrue(offset)
+        | Inst::BEq(offset)
+        | Inst::BGe(offset)
+        | Inst::BGt(offset)
+        | Inst::BLe(offset)
+        | Inst::BLt(offset)
+        | Inst::BNe(offset)
+        | Inst::Leave(offset) => Some(*offset),
+        _ => None,
+    }
+}
+
+/// Type on stack after conv inst
+fn conv_type(inst: &Inst) -> VerType {
+    match inst {
+        Inst::ConvI1
+        | Inst::ConvI2
+        | Inst::ConvI4
+        | Inst::ConvU1
+        | Inst::ConvU2
+        | Inst::ConvU4
+        | Inst::ConvOvfI1
+        | Inst::ConvOvfU1
+        | Inst::ConvOvfI2
+        | Inst::ConvOvfU2
+        | Inst::ConvOvfI4
+        | Inst::ConvOvfU4
+        | Inst::ConvOvfI1Un
+        | Inst::ConvOvfI2Un
+        | Inst::ConvOvfI4Un
+        | Inst::ConvOvfU1Un
+        | Inst::ConvOvfU2Un
+        | Inst::ConvOvfU4Un => VerType::I4,
+        Inst::ConvI8
+        | Inst::ConvU8
+        | Inst::ConvOvfI8
+        | Inst::ConvOvfU8
+        | Inst::ConvOvfI8Un
+        | Inst::ConvOvfU8Un => VerType::I8,
+        Inst::ConvR4 | Inst::ConvR8 | Inst::ConvRUn => VerType::F,
+        Inst::ConvI
+        | Inst::ConvU
+        | Inst::ConvOvfI
+        | Inst::ConvOvfU
+        | Inst::ConvOvfIUn
+        | Inst::ConvOvfUUn => VerType::I,
+        _ => unreachable!(),
+    }
+}
+
+/// Type of element on stack of ldelem.*, stelem.*, ldind.* and stind.*
+fn elem_type(inst: &Inst) -> VerType {
+    match inst {
+        Inst::LdElemI1
+        | Inst::LdElemU1
+        | Inst::LdElemI2
+        | Inst::LdElemU2
+        | Inst::LdElemI4
+        | Inst::LdElemU4
+        | Inst::StElemI1
+        | Inst::StElemI2
+        | Inst::StElemI4
+        | Inst::LdIndI1
+        | Inst::LdIndU1
+        | Inst::LdIndI2
+        | Inst::LdIndU2
+        | Inst::LdIndI4
+        | Inst::LdIndU4
+        | Inst::StIndI1
+        | Inst::StIndI2
+        | Inst::StIndI4 => VerType::I4,
+        Inst::LdElemI8 | Inst::StElemI8 | Inst::LdIndI8 | Inst::StIndI8 => VerType::I8,
+        Inst::LdElemI | Inst::StElemI | Inst::LdIndI | Inst::StIndI => VerType::I,
+        Inst::LdElemR4
+        | Inst::LdElemR8
+        | Inst::StElemR4
+        | Inst::StElemR8
+        | Inst::LdIndR4
+        | Inst::LdIndR8
+        | Inst::StIndR4
+        | Inst::StIndR8 => VerType::F,
+        _ => unreachable!(),
+    }
+}
+
+fn load_var(vars: &[VerType], idx: usize, kind: &str) -> Result<VerType, String> {
+    vars.get(idx)
+        .cloned()
+        .ok_or_else(|| format!("{} index {} out of range", kind, idx))
+}
+
+fn pop(stack: &mut Vec<VerType>) -> Result<VerType, String> {
+    stack.pop().ok_or_else(|| String::from("Stack underflow"))
+}
+
+/// pop object reference of any type
+fn pop_ref(stack: &mut Vec<VerType>) -> Result<(), String> {
+    match pop(stack)? {
+        VerType::Ref(_) | VerType::Generic => Ok(()),
+        ty => Err(format!("Expect object reference on stack but found {}", ty)),
+    }
+}
+
+/// pop array, return element type
+fn pop_arr(stack: &mut Vec<VerType>) -> Result<VerType, String> {
+    match pop(stack)? {
+        VerType::Ref(RefType::Array(ele)) => Ok(*ele),
+        // null array
+        VerType::Ref(RefType::Null) => Ok(VerType::Ref(RefType::Null)),
+        VerType::Generic => Ok(VerType::Generic),
+        ty => Err(format!("Expect array on stack but found {}", ty)),
+    }
+}
+
+fn pop_arr_size(stack: &mut Vec<VerType>) -> Result<(), String> {
+    match pop(stack)? {
+        VerType::I4 | VerType::I | VerType::Generic => Ok(()),
+        ty => Err(format!(
+            "Array size or index should be i32 or inative but found {}",
+            ty
+        )),
+    }
+}
+
+/// Table III.2
+fn binary_numeric_type(lhs: &VerType, rhs: &VerType) -> Result<VerType, String> {
+    match (lhs, rhs) {
+        (VerType::I4, VerType::I4) => Ok(VerType::I4),
+        (VerType::I4, VerType::I) | (VerType::I, VerType::I4) | (VerType::I, VerType::I) => {
+            Ok(VerType::I)
+        }
+        (VerType::I8, VerType::I8) => Ok(VerType::I8),
+        (VerType::F, VerType::F) => Ok(VerType::F),
+        (VerType::Generic, _) | (_, VerType::Generic) => Ok(VerType::Generic),
+        _ => Err(format!(
+            "Invalid operands of numeric op: {} and {}",
+            lhs, rhs
+        )),
+    }
+}
+
+/// Table III.4, refs can only be compared by equality
+fn check_cmp(lhs: &VerType, rhs: &VerType, is_eq: bool) -> Result<(), String> {
+    match (lhs, rhs) {
+        (VerType::Ref(_), VerType::Ref(_)) | (VerType::ByRef, VerType::ByRef) if is_eq => Ok(()),
+        (VerType::Ref(_), _) | (_, VerType::Ref(_)) | (VerType::ByRef, _) | (_, VerType::ByRef) => {
+            Err(format!("Cannot compare {} and {}", lhs, rhs))
+        }
+        _ => binary_numeric_type(lhs, rhs).map(|_| ()),
+    }
+}
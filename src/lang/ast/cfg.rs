@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// Predicate in #[cfg(...)]
+pub enum ASTCfgPred {
+    /// name, e.g. debug
+    Flag(String),
+    /// name, value, e.g. feature = "x"
+    KeyValue(String, String),
+    All(Vec<ASTCfgPred>),
+    Any(Vec<ASTCfgPred>),
+    Not(Box<ASTCfgPred>),
+}
+
+impl fmt::Display for ASTCfgPred {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ASTCfgPred::Flag(name) => write!(f, "{}", name),
+            ASTCfgPred::KeyValue(name, val) => write!(f, "{} = {:?}", name, val),
+            ASTCfgPred::All(preds) | ASTCfgPred::Any(preds) => {
+                write!(
+                    f,
+                    "{}(",
+                    if let ASTCfgPred::All(_) = self {
+                        "all"
+                    } else {
+                        "any"
+                    }
+                )?;
+                for (i, pred) in preds.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", pred)?;
+                }
+                write!(f, ")")
+            }
+            ASTCfgPred::Not(pred) => write!(f, "not({})", pred),
+        }
+    }
+}
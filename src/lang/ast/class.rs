@@ -14,7 +14,9 @@ pub struct ASTClass {
     pub custom_attribs: Vec<Box<AST>>,
     pub generic_params: Vec<ASTGenericParamDecl>,
     pub extends_or_impls: Vec<ItemPathBuf>,
+    /// AST::Field or AST::Const, or AST::Variant if this is an enum
     pub fields: Vec<Box<AST>>,
+    /// AST::Method, or AST::Error for class items with syntax errors
     pub methods: Vec<Box<AST>>,
     pub cctor: Box<AST>,
     /// AST::Ctor
@@ -22,13 +24,9 @@ pub struct ASTClass {
 }
 
 impl ASTClass {
-    pub fn ast_fmt(&self, f: &mut fmt::Formatter<'_>, is_struct: bool) -> fmt::Result {
-        write!(
-            f,
-            "{{\"name\":\"({}){}",
-            if is_struct { "struct" } else { "class" },
-            self.name,
-        )?;
+    /// sem: class, struct, interface or enum
+    pub fn ast_fmt(&self, f: &mut fmt::Formatter<'_>, sem: &str) -> fmt::Result {
+        write!(f, "{{\"name\":\"({}){}", sem, self.name)?;
         if !self.generic_params.is_empty() {
             write!(f, "<")?;
             for (i, generic_p) in self.generic_params.iter().enumerate() {
@@ -25,13 +25,13 @@ fn restore_escape(s: &str) -> String {
 impl fmt::Display for AST {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // write!(f, "({}, {})", self.x, self.y)
-        match self {
-            Self::File(mods, ext, uses, children) => {
+        match &self.kind {
+            ASTKind::File(mods, ext, uses, children) => {
                 write!(
                     f,
                     "{{\"name\":\"(file)\",\"mods\":[{}],\"extern\":[{}],\"uses\":{},\"classes\":{}}}",
                     mods.iter()
-                        .map(|m| format!("\"{}\"", m))
+                        .map(|(m, vis, _, _)| format!("\"{} {}\"", vis, m))
                         .collect::<Vec<String>>()
                         .join(","),
                     ext.iter()
@@ -42,23 +42,30 @@ impl fmt::Display for AST {
                     BoxASTVecWrapper(children)
                 )
             }
-            Self::Use(path, as_id) => write!(
+            ASTKind::Use(path, as_id) => write!(
                 f,
                 "{{\"name\":\"(use)\",\"path\":\"{}\",\"as\":\"{}\"}}",
                 path.as_str(),
                 if let Some(as_id) = as_id { as_id } else { "" }
             ),
-            Self::CustomAttrib(id, args) => write!(
+            ASTKind::CustomAttrib(id, args) => write!(
                 f,
                 "{{\"name\":\"(Attr){}\",\"args\":{}}}",
                 id,
                 BoxASTVecWrapper(args)
             ),
-            Self::Class(class) => class.ast_fmt(f, false),
-            Self::Struct(class) => class.ast_fmt(f, true),
-            Self::Ctor(ctor) => ctor.fmt(f),
-            Self::Method(method) => method.fmt(f),
-            Self::Field(id, flag, attr, ty) => write!(
+            ASTKind::Cfg(pred) => write!(
+                f,
+                "{{\"name\":\"(Attr)cfg\",\"pred\":\"{}\"}}",
+                restore_escape(&pred.to_string())
+            ),
+            ASTKind::Class(class) => class.ast_fmt(f, "class"),
+            ASTKind::Struct(class) => class.ast_fmt(f, "struct"),
+            ASTKind::Interface(class) => class.ast_fmt(f, "interface"),
+            ASTKind::Enum(class) => class.ast_fmt(f, "enum"),
+            ASTKind::Ctor(ctor) => ctor.fmt(f),
+            ASTKind::Method(method) => method.fmt(f),
+            ASTKind::Field(id, flag, attr, ty) => write!(
                 f,
                 "{{\"name\":\"(field){}\",\"flag\":\"{}\",\"attr\":{},\"type\":\"{}\"}}",
                 id,
@@ -66,101 +73,196 @@ impl fmt::Display for AST {
                 BoxASTVecWrapper(attr),
                 ty
             ),
-            Self::Param(id, flag, ty) => write!(
+            ASTKind::Const(id, attr, ty, val) => write!(
+                f,
+                "{{\"name\":\"(const){}\",\"attr\":{},\"type\":\"{}\",\"val\":{}}}",
+                id,
+                BoxASTVecWrapper(attr),
+                ty,
+                val
+            ),
+            ASTKind::Variant(id, tys, discriminant) => write!(
+                f,
+                "{{\"name\":\"(variant){}\",\"types\":[{}],\"discriminant\":{}}}",
+                id,
+                tys.iter()
+                    .map(|ty| format!("\"{}\"", ty))
+                    .collect::<Vec<String>>()
+                    .join(","),
+                discriminant
+            ),
+            ASTKind::Param(id, flag, ty) => write!(
                 f,
                 "{{\"name\":\"(param){}\",\"flag\":\"{}\",\"type\":\"{}\"}}",
                 id, flag, ty
             ),
-            Self::Let(pattern, flag, ty, init) => write!(
+            ASTKind::Let(pattern, flag, ty, init) => write!(
                 f,
                 "{{\"name\":\"(let)\",\"id\":{},\"flag\":\"{}\",\"type\":\"{}\",\"init\":{}}}",
                 pattern, flag, ty, init
             ),
-            Self::ExprStmt(stmt) => write!(f, "{{\"name\":\"(ExprStmt)\",\"stmt\":{}}}", stmt),
-            Self::Block(children) => write!(
+            ASTKind::ExprStmt(stmt) => write!(f, "{{\"name\":\"(ExprStmt)\",\"stmt\":{}}}", stmt),
+            ASTKind::Block(children) => write!(
                 f,
                 "{{\"name\":\"(block)\",\"children\":{}}}",
                 BoxASTVecWrapper(children)
             ),
-            Self::If(cond, then, els) => write!(
+            ASTKind::If(cond, then, els) => write!(
                 f,
                 "{{\"name\":\"(if)\",\"cond\":{},\"then\":{},\"els\":{}}}",
                 cond, then, els
             ),
-            Self::Continue => write!(f, "{{\"name\":\"continue\"}}"),
-            Self::Break(val) => write!(f, "{{\"name\":\"break\",\"val\":{}}}", val),
-            Self::Return(val) => write!(f, "{{\"name\":\"return\",\"val\":{}}}", val),
-            Self::Loop(body) => write!(f, "{{\"name\":\"(loop)\",\"body\":{}}}", body),
-            Self::OpPos(o) => write!(f, "{{\"name\":\"+\",\"lhs\":{}}}", o),
-            Self::OpNeg(o) => write!(f, "{{\"name\":\"-\",\"lhs\":{}}}", o),
-            Self::OpAdd(o1, o2) => write!(f, "{{\"name\":\"+\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpSub(o1, o2) => write!(f, "{{\"name\":\"-\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpMul(o1, o2) => write!(f, "{{\"name\":\"*\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpDiv(o1, o2) => write!(f, "{{\"name\":\"/\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpMod(o1, o2) => write!(f, "{{\"name\":\"%\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpLogNot(o1) => write!(f, "{{\"name\":\"!\",\"lhs\":{}}}", o1),
-            Self::OpLogAnd(o1, o2) => {
+            ASTKind::Continue => write!(f, "{{\"name\":\"continue\"}}"),
+            ASTKind::Break(val) => write!(f, "{{\"name\":\"break\",\"val\":{}}}", val),
+            ASTKind::Return(val) => write!(f, "{{\"name\":\"return\",\"val\":{}}}", val),
+            ASTKind::Loop(body) => write!(f, "{{\"name\":\"(loop)\",\"body\":{}}}", body),
+            ASTKind::While(cond, body) => write!(
+                f,
+                "{{\"name\":\"(while)\",\"cond\":{},\"body\":{}}}",
+                cond, body
+            ),
+            ASTKind::For(id, iter, body) => write!(
+                f,
+                "{{\"name\":\"(for)\",\"id\":\"{}\",\"iter\":{},\"body\":{}}}",
+                id, iter, body
+            ),
+            ASTKind::Range(start, end) => {
+                write!(f, "{{\"name\":\"..\",\"start\":{},\"end\":{}}}", start, end)
+            }
+            ASTKind::Try(body, catches, finally) => write!(
+                f,
+                "{{\"name\":\"(try)\",\"body\":{},\"catches\":{},\"finally\":{}}}",
+                body,
+                BoxASTVecWrapper(catches),
+                finally
+            ),
+            ASTKind::Catch(id, ty, body) => write!(
+                f,
+                "{{\"name\":\"(catch)\",\"id\":\"{}\",\"type\":\"{}\",\"body\":{}}}",
+                id, ty, body
+            ),
+            ASTKind::Match(val, arms) => write!(
+                f,
+                "{{\"name\":\"(match)\",\"val\":{},\"arms\":{}}}",
+                val,
+                BoxASTVecWrapper(arms)
+            ),
+            ASTKind::MatchArm(pattern, body) => write!(
+                f,
+                "{{\"name\":\"(arm)\",\"pattern\":{},\"body\":{}}}",
+                pattern, body
+            ),
+            ASTKind::Throw(val) => write!(f, "{{\"name\":\"throw\",\"val\":{}}}", val),
+            ASTKind::OpPos(o) => write!(f, "{{\"name\":\"+\",\"lhs\":{}}}", o),
+            ASTKind::OpNeg(o) => write!(f, "{{\"name\":\"-\",\"lhs\":{}}}", o),
+            ASTKind::OpAdd(o1, o2) => write!(f, "{{\"name\":\"+\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpSub(o1, o2) => write!(f, "{{\"name\":\"-\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpMul(o1, o2) => write!(f, "{{\"name\":\"*\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpDiv(o1, o2) => write!(f, "{{\"name\":\"/\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpMod(o1, o2) => write!(f, "{{\"name\":\"%\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpLogNot(o1) => write!(f, "{{\"name\":\"!\",\"lhs\":{}}}", o1),
+            ASTKind::OpLogAnd(o1, o2) => {
                 write!(f, "{{\"name\":\"&&\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
             }
-            Self::OpLogOr(o1, o2) => write!(f, "{{\"name\":\"||\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpEq(o1, o2) => write!(f, "{{\"name\":\"==\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpNe(o1, o2) => write!(f, "{{\"name\":\"!=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpGe(o1, o2) => write!(f, "{{\"name\":\">=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpGt(o1, o2) => write!(f, "{{\"name\":\">\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpLe(o1, o2) => write!(f, "{{\"name\":\"<=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpLt(o1, o2) => write!(f, "{{\"name\":\"<\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpAssign(o1, o2) => write!(f, "{{\"name\":\"=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
-            Self::OpStaticAccess(o1, o2) => {
+            ASTKind::OpLogOr(o1, o2) => {
+                write!(f, "{{\"name\":\"||\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpBitNot(o1) => write!(f, "{{\"name\":\"~\",\"lhs\":{}}}", o1),
+            ASTKind::OpBitAnd(o1, o2) => {
+                write!(f, "{{\"name\":\"&\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpBitOr(o1, o2) => {
+                write!(f, "{{\"name\":\"|\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpBitXor(o1, o2) => {
+                write!(f, "{{\"name\":\"^\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpShl(o1, o2) => {
+                write!(f, "{{\"name\":\"<<\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpShr(o1, o2) => {
+                write!(f, "{{\"name\":\">>\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpEq(o1, o2) => write!(f, "{{\"name\":\"==\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpNe(o1, o2) => write!(f, "{{\"name\":\"!=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpGe(o1, o2) => write!(f, "{{\"name\":\">=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpGt(o1, o2) => write!(f, "{{\"name\":\">\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpLe(o1, o2) => write!(f, "{{\"name\":\"<=\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpLt(o1, o2) => write!(f, "{{\"name\":\"<\",\"lhs\":{},\"rhs\":{}}}", o1, o2),
+            ASTKind::OpAssign(o1, o2) => {
+                write!(f, "{{\"name\":\"=\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
+            }
+            ASTKind::OpCompoundAssign(op) => {
+                write!(f, "{{\"name\":\"(compound assign)\",\"op\":{}}}", op)
+            }
+            ASTKind::OpStaticAccess(o1, o2) => {
                 write!(f, "{{\"name\":\"::\",\"lhs\":{},\"rhs\":\"{}\"}}", o1, o2)
             }
-            Self::OpObjAccess(o1, o2) => {
+            ASTKind::OpObjAccess(o1, o2) => {
                 write!(f, "{{\"name\":\".\",\"lhs\":{},\"rhs\":\"{}\"}}", o1, o2)
             }
-            Self::OpArrayAccess(o1, o2) => {
+            ASTKind::OpArrayAccess(o1, o2) => {
                 write!(f, "{{\"name\":\"[]\",\"lhs\":{},\"rhs\":{}}}", o1, o2)
             }
-            Self::OpCast(ty, expr) => {
+            ASTKind::OpCast(ty, expr, is_checked) => {
                 write!(
                     f,
-                    "{{\"name\":\"(cast)\",\"ty\":\"{}\",\"val\":{}}}",
-                    ty, expr
+                    "{{\"name\":\"{}\",\"ty\":\"{}\",\"val\":{}}}",
+                    if *is_checked { "(checked cast)" } else { "(cast)" },
+                    ty,
+                    expr
                 )
             }
-            Self::OpCall(func, ps) => write!(
+            ASTKind::OpIs(ty, expr) => {
+                write!(f, "{{\"name\":\"is\",\"ty\":\"{}\",\"val\":{}}}", ty, expr)
+            }
+            ASTKind::OpCall(func, ps) => write!(
                 f,
                 "{{\"name\":\"(call)\",\"func\":{},\"args\":{}}}",
                 func,
                 BoxASTVecWrapper(ps)
             ),
-            Self::OpNew(ty, args) => write!(
+            ASTKind::OpNew(ty, args) => write!(
                 f,
                 "{{\"name\":\"new\",\"type\":\"{}\",\"args\":{}}}",
                 ty,
                 BoxASTVecWrapper(args)
             ),
-            Self::OpNewArr(ty, dim) => write!(
+            ASTKind::OpNewArr(ty, dim) => write!(
                 f,
                 "{{\"name\":\"newarr\",\"type\":\"{}\",\"dim\":{}}}",
                 ty, dim,
             ),
-            Self::Id(id) => write!(f, "{{\"name\":\"(id){}\"}}", id),
-            Self::IdWithGenericParams(id_with_generic_ps) => {
+            ASTKind::Tuple(elems) => write!(
+                f,
+                "{{\"name\":\"tuple\",\"children\":{}}}",
+                BoxASTVecWrapper(elems)
+            ),
+            ASTKind::Id(id) => write!(f, "{{\"name\":\"(id){}\"}}", id),
+            ASTKind::IdWithGenericParams(id_with_generic_ps) => {
                 write!(f, "{{\"name\":\"(id){}\"}}", id_with_generic_ps)
             }
-            Self::TuplePattern(p) => write!(
+            ASTKind::TuplePattern(p) => write!(
                 f,
                 "{{\"name\":\"(TuplePattern)\",\"children\":{}}}",
                 BoxASTVecWrapper(p)
             ),
-            Self::Type(ty) => write!(f, "{{\"name\":\"{}\"}}", ty),
-            Self::Null => write!(f, "{{\"name\":\"null\"}}"),
-            Self::Bool(val) => write!(f, "{{\"name\":\"(bool){}\"}}", val),
-            Self::Int(val) => write!(f, "{{\"name\":\"(int){}\"}}", val),
-            Self::Float(val) => write!(f, "{{\"name\":\"(float){}\"}}", val),
+            ASTKind::VariantPattern(path, p) => write!(
+                f,
+                "{{\"name\":\"(VariantPattern){}\",\"children\":{}}}",
+                path.as_str(),
+                BoxASTVecWrapper(p)
+            ),
+            ASTKind::Type(ty) => write!(f, "{{\"name\":\"{}\"}}", ty),
+            ASTKind::Null => write!(f, "{{\"name\":\"null\"}}"),
+            ASTKind::Bool(val) => write!(f, "{{\"name\":\"(bool){}\"}}", val),
+            ASTKind::Int(val, ty) => write!(f, "{{\"name\":\"{}{}\"}}", ty, val),
+            ASTKind::Float(val, ty) => write!(f, "{{\"name\":\"{}{}\"}}", ty, val),
             // TODO: escape chars
-            Self::String(val) => write!(f, "{{\"name\":\"(string){}\"}}", restore_escape(val)),
-            Self::Char(val) => write!(f, "{{\"name\":\"(char){}\"}}", val),
-            Self::None => write!(f, "{{}}"),
+            ASTKind::String(val) => write!(f, "{{\"name\":\"(string){}\"}}", restore_escape(val)),
+            ASTKind::Char(val) => write!(f, "{{\"name\":\"(char){}\"}}", val),
+            ASTKind::None => write!(f, "{{}}"),
+            ASTKind::Error => write!(f, "{{\"name\":\"error\"}}"),
         }
     }
 }
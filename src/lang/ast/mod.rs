@@ -1,3 +1,4 @@
+mod cfg;
 mod class;
 mod disp;
 mod generic;
@@ -6,29 +7,55 @@ mod ty;
 
 use xir::attrib::*;
 
-use super::util::ItemPathBuf;
+use super::util::{ItemPathBuf, Span};
+pub use cfg::ASTCfgPred;
 pub use class::ASTClass;
 pub use generic::{ASTGenericParamDecl, ASTIdWithGenericParam};
 pub use method::{ASTCtor, ASTMethod, ASTMethodAttrib, ASTMethodAttribFlag};
 pub use ty::ASTType;
 
-pub enum AST {
-    /// mods, ext_mods, uses, classes: Vec<AST>
-    File(Vec<String>, Vec<String>, Vec<Box<AST>>, Vec<Box<AST>>),
+/// All AST nodes are boxed and carry the span of their source text
+pub struct AST {
+    pub span: Span,
+    pub kind: ASTKind,
+}
+
+impl AST {
+    pub fn new(span: Span, kind: ASTKind) -> Box<AST> {
+        Box::new(AST { span, kind })
+    }
+}
+
+pub enum ASTKind {
+    /// mods with their visibility, custom attribs and span of name, ext_mods, uses, classes: Vec<AST>
+    File(
+        Vec<(String, TypeAttribVisFlag, Vec<Box<AST>>, Span)>,
+        Vec<String>,
+        Vec<Box<AST>>,
+        Vec<Box<AST>>,
+    ),
 
     /// path, as
     Use(ItemPathBuf, Option<String>),
 
     /// attrib name, args
     CustomAttrib(String, Vec<Box<AST>>),
+    /// #[cfg(pred)], consumed when the module is loaded
+    Cfg(ASTCfgPred),
 
     Class(ASTClass),
     Struct(ASTClass),
+    Interface(ASTClass),
+    Enum(ASTClass),
     Ctor(ASTCtor),
     Method(ASTMethod),
 
     /// id, attrib, custom-attrib, ty
     Field(String, FieldAttrib, Vec<Box<AST>>, Box<ASTType>),
+    /// id, custom-attrib, ty, val: Box<Expr>
+    Const(String, Vec<Box<AST>>, Box<ASTType>, Box<AST>),
+    /// id, payload tys, discriminant: Box<Expr> or None
+    Variant(String, Vec<Box<ASTType>>, Box<AST>),
     Param(String, ParamAttrib, Box<ASTType>),
     /// pattern, attrib, ty, init: Box<AST>
     Let(Box<AST>, LocalAttrib, Box<ASTType>, Box<AST>),
@@ -40,12 +67,28 @@ pub enum AST {
     /// cond: Box<Expr>, then: Box<Block>, els: Box<Stmt>
     If(Box<AST>, Box<AST>, Box<AST>),
     Loop(Box<AST>),
+    /// cond: Box<Expr>, body: Box<Block>
+    While(Box<AST>, Box<AST>),
+    /// id, iter: Box<Expr> or Range, body: Box<Block>
+    For(String, Box<AST>, Box<AST>),
+    /// start: Box<Expr>, end: Box<Expr>. Only used as iter of for
+    Range(Box<AST>, Box<AST>),
+    /// body: Box<Block>, catches: Vec<Catch>, finally: Box<Block>
+    Try(Box<AST>, Vec<Box<AST>>, Box<AST>),
+    /// id, ty, body: Box<Block>
+    Catch(String, Box<ASTType>, Box<AST>),
+    /// val: Box<Expr>, arms: Vec<MatchArm>
+    Match(Box<AST>, Vec<Box<AST>>),
+    /// pattern, body: Box<Expr>
+    MatchArm(Box<AST>, Box<AST>),
 
     /// ret_val: Box<Expr>
     Return(Box<AST>),
     Continue,
     /// break_val: Box<Expr>
     Break(Box<AST>),
+    /// exception: Box<Expr>
+    Throw(Box<AST>),
 
     OpPos(Box<AST>),
     OpNeg(Box<AST>),
@@ -57,6 +100,12 @@ pub enum AST {
     OpLogNot(Box<AST>),
     OpLogAnd(Box<AST>, Box<AST>),
     OpLogOr(Box<AST>, Box<AST>),
+    OpBitNot(Box<AST>),
+    OpBitAnd(Box<AST>, Box<AST>),
+    OpBitOr(Box<AST>, Box<AST>),
+    OpBitXor(Box<AST>, Box<AST>),
+    OpShl(Box<AST>, Box<AST>),
+    OpShr(Box<AST>, Box<AST>),
     OpEq(Box<AST>, Box<AST>),
     OpNe(Box<AST>, Box<AST>),
     OpGe(Box<AST>, Box<AST>),
@@ -64,32 +113,49 @@ pub enum AST {
     OpLe(Box<AST>, Box<AST>),
     OpLt(Box<AST>, Box<AST>),
     OpAssign(Box<AST>, Box<AST>),
+    /// op: Box<AST> of a numeric or bitwise binary op, lhs of which is also the assignee.
+    ///
+    /// `a += b` is `OpCompoundAssign(OpAdd(a, b))`
+    OpCompoundAssign(Box<AST>),
     OpStaticAccess(Box<AST>, ASTIdWithGenericParam),
     OpObjAccess(Box<AST>, ASTIdWithGenericParam),
     OpArrayAccess(Box<AST>, Box<AST>),
+    /// ty, val, is_checked
+    ///
+    /// `val as! ty` is checked and throws std::OverflowException if numeric value is out of range
+    OpCast(Box<ASTType>, Box<AST>, bool),
     /// ty, val
-    OpCast(Box<ASTType>, Box<AST>),
+    OpIs(Box<ASTType>, Box<AST>),
     /// f: Box<Expr>, ps: Vec<Expr>
     OpCall(Box<AST>, Vec<Box<AST>>),
     /// ty, ps: Vec<Expr>
     OpNew(Box<ASTType>, Vec<Box<AST>>),
     /// elem_ty, dim: Expr
     OpNewArr(Box<ASTType>, Box<AST>),
+    /// elements: Vec<Expr>
+    Tuple(Vec<Box<AST>>),
 
     Id(String),
     IdWithGenericParams(ASTIdWithGenericParam),
     TuplePattern(Vec<Box<AST>>),
+    /// Enum::Variant, sub-patterns. Literals, Id and "_" are also patterns
+    VariantPattern(ItemPathBuf, Vec<Box<AST>>),
 
     Type(Box<ASTType>),
 
     /// Literal
     Null,
     Bool(bool),
-    Int(i32),
-    Float(f64),
+    /// value and type, type is i32 if there is no suffix
+    Int(i128, ASTType),
+    /// value and type, type is f64 if there is no suffix
+    Float(f64, ASTType),
     String(String),
     Char(u32),
 
     /// Option<AST>::None
     None,
+
+    /// Source text that cannot be parsed. Only exists in ASTs with syntax errors
+    Error,
 }
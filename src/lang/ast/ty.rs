@@ -6,7 +6,17 @@ use std::fmt;
 pub enum ASTType {
     Bool,
     Char,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
     I32,
+    U64,
+    I64,
+    USize,
+    ISize,
+    F32,
     F64,
     String,
     Tuple(Vec<Box<ASTType>>),
@@ -22,10 +32,27 @@ impl fmt::Display for ASTType {
         match self {
             ASTType::Bool => write!(f, "(type)bool"),
             ASTType::Char => write!(f, "(type)char"),
+            ASTType::U8 => write!(f, "(type)u8"),
+            ASTType::I8 => write!(f, "(type)i8"),
+            ASTType::U16 => write!(f, "(type)u16"),
+            ASTType::I16 => write!(f, "(type)i16"),
+            ASTType::U32 => write!(f, "(type)u32"),
             ASTType::I32 => write!(f, "(type)i32"),
+            ASTType::U64 => write!(f, "(type)u64"),
+            ASTType::I64 => write!(f, "(type)i64"),
+            ASTType::USize => write!(f, "(type)usize"),
+            ASTType::ISize => write!(f, "(type)isize"),
+            ASTType::F32 => write!(f, "(type)f32"),
             ASTType::F64 => write!(f, "(type)f64"),
             ASTType::String => write!(f, "(type)string"),
-            ASTType::Tuple(_) => unimplemented!(),
+            ASTType::Tuple(tys) => write!(
+                f,
+                "(type)({})",
+                tys.iter()
+                    .map(|ty| ty.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
             ASTType::Arr(dtype) => write!(f, "(type){}[]", dtype),
             ASTType::UsrType(names) => write!(f, "(type){}", names.as_str()),
             ASTType::None => write!(f, "(type)none"),
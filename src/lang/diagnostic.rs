@@ -0,0 +1,182 @@
+use super::util::Span;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Error codes, displayed as E0001, E0002 ...
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ErrCode {
+    /// Source file does not match the grammar
+    Syntax = 1,
+    /// Malformed int, char or string literal
+    InvalidLiteral,
+    /// Item (class, method, field, module, use ...) is declared more than once
+    Duplicated,
+    /// Duplicated or conflicting method modifiers
+    InvalidModifier,
+    /// Misuse of self or Self
+    InvalidSelf,
+    /// Path, type, member or variable cannot be found
+    Unresolved,
+    /// Type of value does not match the expected type
+    TypeMismatch,
+    /// Sealed base, multiple inheritance or nothing to override
+    InvalidInheritance,
+    /// Unknown custom attribute or invalid attribute args
+    InvalidAttribute,
+    /// Expression is used in a way that is not allowed (e.g. calling a field)
+    InvalidExpr,
+    /// Module file cannot be located or module name is ambiguous
+    InvalidModule,
+    /// Wrong number of generic args, unsatisfied constraint or unsupported generic usage
+    InvalidGeneric,
+    /// Interface has fields, ctors or method bodies, or interface method is not implemented
+    InvalidInterface,
+    /// Integer division or remainder by a constant zero
+    DivideByZero,
+    /// Match arms do not cover all possible values
+    NonExhaustive,
+    /// Item is priv or pub(crate) and cannot be used here
+    Inaccessible,
+}
+
+impl fmt::Display for ErrCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{:04}", *self as u32)
+    }
+}
+
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrCode>,
+    pub msg: String,
+    /// source file and location in that file
+    pub loc: Option<(PathBuf, Span)>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(code: ErrCode, msg: S) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: Some(code),
+            msg: msg.into(),
+            loc: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning<S: Into<String>>(msg: S) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: None,
+            msg: msg.into(),
+            loc: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_loc(mut self, path: &Path, span: Span) -> Diagnostic {
+        self.loc = Some((path.to_owned(), span));
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Print to stderr
+    pub fn emit(&self) {
+        eprintln!("{}", self);
+    }
+
+    /// Report an unrecoverable error and terminate compilation
+    pub fn abort(self) -> ! {
+        self.emit();
+        eprintln!("error: aborting due to previous error");
+        process::exit(1);
+    }
+}
+
+/// Emit all diagnostics and terminate compilation if there is any error
+pub fn emit_all(diags: &[Diagnostic]) {
+    for diag in diags.iter() {
+        diag.emit();
+    }
+    let err_count = diags.iter().filter(|d| d.is_error()).count();
+    if err_count == 1 {
+        eprintln!("error: aborting due to previous error");
+        process::exit(1);
+    } else if err_count > 1 {
+        eprintln!("error: aborting due to {} previous errors", err_count);
+        process::exit(1);
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        writeln!(f, ": {}", self.msg)?;
+
+        // gutter is as wide as the line number
+        let mut gutter = String::from(" ");
+        if let Some((path, span)) = &self.loc {
+            if let Ok(src) = fs::read_to_string(path) {
+                let start = span.start.min(src.len());
+                let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+                let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+                let line = src[line_start..line_end].trim_end_matches('\r');
+                let line_no = src[..start].matches('\n').count() + 1;
+                let col = src[line_start..start].chars().count() + 1;
+
+                let line_no_str = line_no.to_string();
+                gutter = " ".repeat(line_no_str.len());
+                writeln!(f, "{}--> {}:{}:{}", gutter, path.display(), line_no, col)?;
+                writeln!(f, "{} |", gutter)?;
+                writeln!(f, "{} | {}", line_no_str, line)?;
+
+                // keep tabs so that the underline is aligned with the source line
+                let indent: String = src[line_start..start]
+                    .chars()
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                // multi-line span is only underlined to the end of the first line
+                let end = span.end.min(line_start + line.len()).max(start);
+                let underline_len = src[start..end].chars().count().max(1);
+                writeln!(f, "{} | {}{}", gutter, indent, "^".repeat(underline_len))?;
+            } else {
+                writeln!(f, " --> {}", path.display())?;
+            }
+        }
+
+        for note in self.notes.iter() {
+            writeln!(f, "{} = note: {}", gutter, note)?;
+        }
+        Ok(())
+    }
+}
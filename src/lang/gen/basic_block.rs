@@ -4,12 +4,29 @@ use std::ptr::null_mut;
 
 use xir::inst::Inst;
 
+use super::super::util::Span;
+
+/// Debug info bound to a position in a bb, offsets are calculated in Builder::done
+pub enum DebugMarker {
+    /// a statement starts here
+    SeqPoint(Span),
+    /// index into Locals::scopes
+    ScopeStart(usize),
+    ScopeEnd(usize),
+}
+
 pub struct BasicBlock {
     pub insts: Vec<Inst>,
     pub offset: i32,
     pub size: usize,
     /// Branch target of the last inst,
     pub target: Option<LLCursor<BasicBlock>>,
+    /// Whether floats are compared in this bb.
+    ///
+    /// Comparisons on NaN are always false, so they cannot be negated as the inverse comparison
+    pub has_float_cmp: bool,
+    /// (index into insts, marker), ordered by index. Markers are before insts[index]
+    pub markers: Vec<(usize, DebugMarker)>,
 }
 
 impl BasicBlock {
@@ -19,6 +36,8 @@ impl BasicBlock {
             offset: 0,
             size: 0,
             target: None,
+            has_float_cmp: false,
+            markers: Vec::new(),
         }
     }
 
@@ -109,6 +128,9 @@ impl<T> LinkedList<T> {
             let ret = LLCursor {
                 node: node.as_mut() as *mut Node<T>,
             };
+            if self.tail == cursor.node {
+                self.tail = ret.node;
+            }
             cur_ptr.next = Some(node);
             ret
         }
@@ -120,9 +142,11 @@ impl<T> LinkedList<T> {
             self.tail = node.as_mut() as *mut Node<T>;
             self.head = Some(node);
         } else {
+            let tail = node.as_mut() as *mut Node<T>;
             unsafe {
                 (*self.tail).next = Some(node);
             }
+            self.tail = tail;
         }
     }
 
@@ -1,20 +1,33 @@
 use xir::attrib::*;
-use xir::code::CorILMethod;
+use xir::code::{CorILMethod, ExceptionClause, ExceptionClauseKind};
+use xir::debug::{Document, LocalScope, LocalVarInfo, MethodDebugInfo, SequencePoint};
 use xir::file::IrFile;
+use xir::generic::{
+    to_methoddeforref, to_type_or_methoddef, GenericParamConstraint, MethodDefOrRef, MethodSpec,
+    TypeOrMethodDef,
+};
 use xir::inst::Inst;
 use xir::member::{
     to_implmap_member, to_memberref_parent, Field, ImplMap, MemberForwarded, MemberRef,
     MemberRefParent, MethodDef,
 };
 use xir::module::{Mod, ModRef};
-use xir::sig::{self, IrSig, MethodSigFlag, MethodSigFlagTag, TypeSig};
+use xir::sig::{self, IrSig, MethodSigFlag, MethodSigFlagTag, TypeSig, TypeSpecSig};
 use xir::stand_alone_sig::IrStandAloneSig;
-use xir::tok::{to_tok, TokTag};
-use xir::ty::{get_typeref_parent, ResolutionScope, TypeDef, TypeDefOrRef, TypeRef};
+use xir::tok::{get_tok_tag, to_tok, TokTag};
+use xir::ty::{
+    get_typeref_parent, to_typedeforref, InterfaceImpl, ResolutionScope, TypeDef, TypeDefOrRef,
+    TypeRef, TypeSpec,
+};
 
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::ptr::NonNull;
 
-use super::super::mod_mgr::{Param, Var};
+use super::super::mod_mgr::{GenericParam, Param, Type, Var};
+use super::super::util::Span;
+use super::basic_block::{BasicBlock, DebugMarker};
 use super::{MethodBuilder, RValType};
 
 #[derive(Clone, Hash, PartialEq, Eq)]
@@ -35,6 +48,27 @@ struct ImplMapInfo {
     name: u32,
 }
 
+/// Source file of the module, kept if debug info is generated
+struct DebugSource {
+    /// index into document tbl
+    document: u32,
+    src: String,
+    /// byte offset of the start of each line
+    line_starts: Vec<usize>,
+}
+
+impl DebugSource {
+    /// (line, col) of the start of span, both start from 1
+    fn line_col(&self, span: Span) -> (u32, u32) {
+        let line = match self.line_starts.binary_search(&span.start) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let col = self.src[self.line_starts[line]..span.start].chars().count();
+        (line as u32 + 1, col as u32 + 1)
+    }
+}
+
 pub struct Builder {
     // use const map to avoid redeclaration
     mod_name_idx: u32,
@@ -47,12 +81,16 @@ pub struct Builder {
     type_map: HashMap<u32, u32>,
     /// TypeRef -> TblIdx
     typeref_map: HashMap<TypeRef, u32>,
+    /// descriptor -> TblIdx
+    typespec_map: HashMap<String, u32>,
 
     /// FieldOrMethod -> IdxIntoFieldTbl
     field_map: HashMap<FieldOrMethod, u32>,
     method_map: HashMap<FieldOrMethod, u32>,
     /// MemberRef -> TblIdx
     memberref_map: HashMap<MemberRef, u32>,
+    /// (MethodDefOrRef, Instantiation) -> TblIdx
+    methodspec_map: HashMap<(u32, u32), u32>,
 
     /// ImplMap -> TblIdx
     implmap_map: HashMap<ImplMapInfo, u32>,
@@ -65,6 +103,8 @@ pub struct Builder {
     /// descriptor -> blob head index
     member_sig_map: HashMap<String, u32>,
 
+    debug_src: Option<DebugSource>,
+
     pub file: IrFile,
 }
 
@@ -77,10 +117,12 @@ impl Builder {
 
             type_map: HashMap::new(),
             typeref_map: HashMap::new(),
+            typespec_map: HashMap::new(),
 
             field_map: HashMap::new(),
             method_map: HashMap::new(),
             memberref_map: HashMap::new(),
+            methodspec_map: HashMap::new(),
 
             implmap_map: HashMap::new(),
 
@@ -89,6 +131,8 @@ impl Builder {
 
             member_sig_map: HashMap::new(),
 
+            debug_src: None,
+
             file: IrFile::new(),
         };
         let name = builder.add_const_str(name);
@@ -101,6 +145,20 @@ impl Builder {
         builder
     }
 
+    /// Generate debug info of methods in fpath, which is the source file of this module
+    pub fn enable_debug_info(&mut self, fpath: &Path) {
+        let src = fs::read_to_string(fpath).unwrap();
+        let mut line_starts = vec![0];
+        line_starts.extend(src.match_indices('\n').map(|(i, _)| i + 1));
+        let name = self.add_const_str(&fpath.display().to_string());
+        self.file.document_tbl.push(Document { name });
+        self.debug_src = Some(DebugSource {
+            document: self.file.document_tbl.len() as u32,
+            src,
+            line_starts,
+        });
+    }
+
     pub fn add_class(&mut self, name: &str, flag: &TypeAttrib) -> u32 {
         let name = self.add_const_str(name);
         self.file.typedef_tbl.push(TypeDef {
@@ -127,6 +185,19 @@ impl Builder {
         assert_eq!(old_extends, None, "Overriding old extends");
     }
 
+    /// interface_idx and interface_idx_tag may be acquired from Builder.add_const_class
+    pub fn add_class_impl(
+        &mut self,
+        class_idx: u32,
+        interface_idx: u32,
+        interface_idx_tag: TypeDefOrRef,
+    ) {
+        self.file.interfaceimpl_tbl.push(InterfaceImpl {
+            class: class_idx,
+            interface: to_typedeforref(interface_idx, interface_idx_tag),
+        });
+    }
+
     /// Add a field of this class
     ///
     /// Field parent is the newly added class or none if no class has been added
@@ -190,16 +261,64 @@ impl Builder {
         ret
     }
 
+    /// Add generic params of the newly added class or method
+    ///
+    /// owner_method: index into methoddef tbl, None if owner is the newly added class
+    pub fn add_generic_params(&mut self, owner_method: Option<u32>, params: &[GenericParam]) {
+        let owner = match owner_method {
+            Some(method_idx) => to_type_or_methoddef(method_idx, TypeOrMethodDef::MethodDef),
+            None => {
+                to_type_or_methoddef(self.file.typedef_tbl.len() as u32, TypeOrMethodDef::TypeDef)
+            }
+        };
+        for (i, p) in params.iter().enumerate() {
+            let name = self.add_const_str(&p.name);
+            self.file
+                .generic_param_tbl
+                .push(xir::generic::GenericParam {
+                    number: i as u16,
+                    flag: GenericParamAttrib::default().attirb,
+                    owner,
+                    name,
+                });
+            let param_idx = self.file.generic_param_tbl.len() as u32;
+            for constraint in p.constraints.iter() {
+                let constraint = match constraint {
+                    RValType::Class(ty) | RValType::Value(ty) => {
+                        let ty_ref = unsafe { ty.as_ref() };
+                        let (idx, tag) = self.add_const_class(ty_ref.modname(), &ty_ref.name);
+                        to_typedeforref(idx, tag)
+                    }
+                    RValType::String => {
+                        let (idx, tag) = self.add_const_class("std", "String");
+                        to_typedeforref(idx, tag)
+                    }
+                    _ => {
+                        to_typedeforref(self.add_const_typespec(constraint), TypeDefOrRef::TypeSpec)
+                    }
+                };
+                self.file
+                    .generic_param_constraint_tbl
+                    .push(GenericParamConstraint {
+                        owner: param_idx,
+                        constraint,
+                    });
+            }
+        }
+    }
+
     /// Post-Process
     ///
-    /// Fill all jump instructions, concat all basic blocks
+    /// Optimize basic blocks if optim is set, fill all jump instructions, concat all basic blocks
     ///
+    /// scopes: named locals declared in each scope, see Locals::scopes
     pub fn done(
         &mut self,
         m: &mut MethodBuilder,
         method_idx: u32,
         locals: &Vec<Var>,
-        fold_br: bool,
+        scopes: &[Vec<u16>],
+        optim: bool,
     ) {
         // store local var info
         let locals_sig = if locals.len() == 0 {
@@ -218,11 +337,8 @@ impl Builder {
             self.file.stand_alone_sig_tbl.len() as u32
         };
 
-        let ir_method = &mut self.file.method_tbl[method_idx as usize - 1];
-
-        if fold_br {
-            unimplemented!("Fold branch operation is not implemented");
-            // ceq, brfalse -> bne
+        if optim {
+            m.optimize();
         }
 
         let mut offset = 0;
@@ -244,19 +360,178 @@ impl Builder {
                     Inst::BGt(offset_) => *offset_ = offset,
                     Inst::BLe(offset_) => *offset_ = offset,
                     Inst::BLt(offset_) => *offset_ = offset,
+                    Inst::BNe(offset_) => *offset_ = offset,
+                    Inst::Leave(offset_) => *offset_ = offset,
                     _ => {}
                 }
             }
         }
 
+        // fill exception clauses
+        let excepts: Vec<ExceptionClause> = m
+            .excepts
+            .iter()
+            .map(|e| {
+                let try_offset = e.try_start.as_ref().unwrap().offset as u32;
+                let handler_offset = e.handler_start.as_ref().unwrap().offset as u32;
+                ExceptionClause {
+                    kind: e.kind,
+                    try_offset,
+                    try_length: e.try_end.as_ref().unwrap().offset as u32 - try_offset,
+                    handler_offset,
+                    handler_length: e.handler_end.as_ref().unwrap().offset as u32 - handler_offset,
+                    class_tok: e.class_tok,
+                }
+            })
+            .collect();
+
+        let max_stack = self.calc_max_stack(m, &excepts);
+
+        if self.debug_src.is_some() {
+            self.add_method_debug_info(m, method_idx, offset as u32, locals, scopes);
+        }
+
         // concat basic blocks
         let mut code: Vec<Inst> = Vec::new();
         for bb in m.bb.iter_mut() {
             code.append(&mut bb.insts);
         }
 
-        self.file.codes.push(CorILMethod::new(0, locals_sig, code));
-        ir_method.body = self.file.codes.len() as u32;
+        self.file
+            .codes
+            .push(CorILMethod::new(max_stack, locals_sig, code, excepts));
+        self.file.method_tbl[method_idx as usize - 1].body = self.file.codes.len() as u32;
+    }
+
+    /// Resolve debug markers in bbs whose offsets have been calculated
+    fn add_method_debug_info(
+        &mut self,
+        m: &mut MethodBuilder,
+        method_idx: u32,
+        code_size: u32,
+        locals: &Vec<Var>,
+        scopes: &[Vec<u16>],
+    ) {
+        let debug_src = self.debug_src.as_ref().unwrap();
+        let mut seq_points: Vec<SequencePoint> = Vec::new();
+        // (start, end) of each scope, None if its bb is removed by optimization
+        let mut scope_ranges: Vec<(Option<u32>, Option<u32>)> = vec![(None, None); scopes.len()];
+        for bb in m.bb.iter_mut() {
+            for (idx, marker) in bb.markers.iter() {
+                let offset = bb.offset as u32
+                    + bb.insts[..(*idx).min(bb.insts.len())]
+                        .iter()
+                        .map(|inst| inst.size() as u32)
+                        .sum::<u32>();
+                match marker {
+                    DebugMarker::SeqPoint(span) => {
+                        let (line, col) = debug_src.line_col(*span);
+                        if let Some(last) = seq_points.last() {
+                            if last.offset == offset {
+                                // the previous statement generates no code
+                                seq_points.pop();
+                            }
+                        }
+                        seq_points.push(SequencePoint { offset, line, col });
+                    }
+                    DebugMarker::ScopeStart(scope) => scope_ranges[*scope].0 = Some(offset),
+                    DebugMarker::ScopeEnd(scope) => scope_ranges[*scope].1 = Some(offset),
+                }
+            }
+        }
+
+        let document = debug_src.document;
+        let mut debug_scopes = Vec::new();
+        for (vars, (start, end)) in scopes.iter().zip(scope_ranges.into_iter()) {
+            if vars.is_empty() {
+                continue;
+            }
+            // scope is unreachable if its start is removed
+            if let Some(start) = start {
+                let end = end.unwrap_or(code_size);
+                let vars = vars
+                    .iter()
+                    .map(|idx| LocalVarInfo {
+                        idx: *idx,
+                        name: self.add_const_str(&locals[*idx as usize].id),
+                    })
+                    .collect();
+                debug_scopes.push(LocalScope {
+                    start,
+                    length: end - start,
+                    vars,
+                });
+            }
+        }
+
+        self.file.method_debug_tbl.push(MethodDebugInfo {
+            method: method_idx,
+            document,
+            seq_points,
+            scopes: debug_scopes,
+        });
+    }
+
+    /// Max eval stack depth of a method whose jump instructions have been filled
+    ///
+    /// Stack depth at the entry of each basic block is propagated along the control flow
+    fn calc_max_stack(&self, m: &mut MethodBuilder, excepts: &[ExceptionClause]) -> u16 {
+        let bbs: Vec<&BasicBlock> = m.bb.iter_mut().map(|bb| &*bb).collect();
+        // empty bbs share offset with the next bb, and they fall through to it
+        let bb_at = |offset: i32| bbs.iter().position(|bb| bb.offset == offset).unwrap();
+
+        // (bb idx, stack depth at entry)
+        let mut worklist: Vec<(usize, usize)> = vec![(0, 0)];
+        for e in excepts.iter() {
+            worklist.push((
+                bb_at(e.handler_offset as i32),
+                match e.kind {
+                    // exception object is on the stack
+                    ExceptionClauseKind::Catch => 1,
+                    ExceptionClauseKind::Finally => 0,
+                },
+            ));
+        }
+
+        let mut visited = vec![false; bbs.len()];
+        let mut max_stack = 0;
+        while let Some((bb_idx, mut depth)) = worklist.pop() {
+            // stack depth at a bb is the same along all paths
+            if visited[bb_idx] {
+                continue;
+            }
+            visited[bb_idx] = true;
+
+            let bb = bbs[bb_idx];
+            for inst in bb.insts.iter() {
+                let (pop, push) = inst.stack_transition(&self.file);
+                depth = depth - pop + push;
+                if depth > max_stack {
+                    max_stack = depth;
+                }
+            }
+
+            let target = bb
+                .target
+                .as_ref()
+                .map(|t| bb_at(t.as_ref().unwrap().offset));
+            match bb.insts.last() {
+                Some(Inst::Br(_)) => worklist.push((target.unwrap(), depth)),
+                // leave empties the stack
+                Some(Inst::Leave(_)) => worklist.push((target.unwrap(), 0)),
+                Some(Inst::Ret) | Some(Inst::Throw) | Some(Inst::EndFinally) => {}
+                _ => {
+                    if let Some(target) = target {
+                        worklist.push((target, depth));
+                    }
+                    if bb_idx + 1 < bbs.len() {
+                        worklist.push((bb_idx + 1, depth));
+                    }
+                }
+            }
+        }
+
+        max_stack as u16
     }
 }
 
@@ -309,7 +584,16 @@ impl Builder {
             RValType::Bool => TypeSig::Boolean,
             RValType::U8 => TypeSig::U1,
             RValType::Char => TypeSig::Char,
+            RValType::I8 => TypeSig::I1,
+            RValType::U16 => TypeSig::U2,
+            RValType::I16 => TypeSig::I2,
+            RValType::U32 => TypeSig::U4,
             RValType::I32 => TypeSig::I4,
+            RValType::U64 => TypeSig::U8,
+            RValType::I64 => TypeSig::I8,
+            RValType::USize => TypeSig::U,
+            RValType::ISize => TypeSig::I,
+            RValType::F32 => TypeSig::R4,
             RValType::F64 => TypeSig::R8,
             RValType::Never => unreachable!(),
             RValType::String => TypeSig::String,
@@ -332,6 +616,8 @@ impl Builder {
                     args.iter().map(|arg| self.to_sig_ty(arg)).collect(),
                 )
             }
+            RValType::Var(i) => TypeSig::Var(*i),
+            RValType::MVar(i) => TypeSig::MVar(*i),
             RValType::ByRef(_) => unimplemented!(),
             RValType::Array(ele_ty) => TypeSig::SZArray(Box::new(self.to_sig_ty(ele_ty))),
             RValType::Void => unreachable!(),
@@ -355,8 +641,8 @@ impl Builder {
         let desc = format!(
             "{}({}){}",
             if is_instance { "instance " } else { "" },
-            ps.iter().map(|t| format!("{}", t.ty)).collect::<String>(),
-            ret_ty
+            ps.iter().map(|t| t.ty.descriptor()).collect::<String>(),
+            ret_ty.descriptor()
         );
         if let Some(ret) = self.member_sig_map.get(&desc) {
             *ret
@@ -375,6 +661,72 @@ impl Builder {
         }
     }
 
+    /// return index into typespec tbl
+    pub fn add_const_typespec(&mut self, ty: &RValType) -> u32 {
+        let desc = ty.descriptor();
+        if let Some(ret) = self.typespec_map.get(&desc) {
+            *ret
+        } else {
+            let spec = match self.to_sig_ty(ty) {
+                TypeSig::GenericInst(is_class, tok, args) => {
+                    TypeSpecSig::GenericInst(is_class, tok, args)
+                }
+                TypeSig::SZArray(ele_ty) => TypeSpecSig::SZArray(*ele_ty),
+                TypeSig::Var(i) => TypeSpecSig::Var(i),
+                TypeSig::MVar(i) => TypeSpecSig::MVar(i),
+                _ => unreachable!("{} cannot be a typespec", ty),
+            };
+            self.file.blob_heap.push(IrSig::TypeSpec(spec));
+            self.file.typespec_tbl.push(TypeSpec {
+                sig: self.file.blob_heap.len() as u32 - 1,
+            });
+            let ret = self.file.typespec_tbl.len() as u32;
+            self.typespec_map.insert(desc, ret);
+            ret
+        }
+    }
+
+    /// method: tok of methoddef or memberref
+    ///
+    /// return: index into methodspec tbl
+    pub fn add_const_methodspec(&mut self, method: u32, generic_args: &[RValType]) -> u32 {
+        let (tag, idx) = get_tok_tag(method);
+        let method = match tag {
+            TokTag::MethodDef => to_methoddeforref(idx, MethodDefOrRef::MethodDef),
+            TokTag::MemberRef => to_methoddeforref(idx, MethodDefOrRef::MemberRef),
+            _ => unreachable!(),
+        };
+
+        let desc = format!(
+            "<{}>",
+            generic_args
+                .iter()
+                .map(|t| t.descriptor())
+                .collect::<String>()
+        );
+        let instantiation = if let Some(ret) = self.member_sig_map.get(&desc) {
+            *ret
+        } else {
+            let args = generic_args.iter().map(|t| self.to_sig_ty(t)).collect();
+            let ret = self.file.blob_heap.len() as u32;
+            self.file.blob_heap.push(IrSig::MethodSpec(args));
+            self.member_sig_map.insert(desc, ret);
+            ret
+        };
+
+        if let Some(ret) = self.methodspec_map.get(&(method, instantiation)) {
+            *ret
+        } else {
+            self.file.methodspec_tbl.push(MethodSpec {
+                method,
+                instantiation,
+            });
+            let ret = self.file.methodspec_tbl.len() as u32;
+            self.methodspec_map.insert((method, instantiation), ret);
+            ret
+        }
+    }
+
     pub fn add_const_mod(&mut self, name: &str) -> (u32, TokTag) {
         let name = self.add_const_str(name);
         if name == self.mod_name_idx {
@@ -462,6 +814,42 @@ impl Builder {
         }
     }
 
+    /// Same as add_const_member, but members of a generic class are referenced
+    /// through the instantiated class, i.e. a memberref whose parent is a typespec.
+    ///
+    /// class_args: generic args of the accessed class, ignored if class is not generic
+    /// (e.g. class is a non-generic base class)
+    pub fn add_const_inst_member(
+        &mut self,
+        class: &Type,
+        class_args: &[RValType],
+        member_name: &str,
+        sig: u32,
+    ) -> (u32, TokTag) {
+        if class.generic_params.is_empty() {
+            return self.add_const_member(class.modname(), &class.name, member_name, sig);
+        }
+
+        let parent = RValType::from_class(
+            NonNull::new(class as *const Type as *mut Type).unwrap(),
+            class_args.to_vec(),
+        );
+        let parent = self.add_const_typespec(&parent);
+        let memberref = MemberRef {
+            parent: to_memberref_parent(parent, MemberRefParent::TypeSpec),
+            name: self.add_const_str(member_name),
+            sig,
+        };
+        if let Some(ret) = self.memberref_map.get(&memberref) {
+            (*ret, TokTag::MemberRef)
+        } else {
+            self.file.memberref_tbl.push(memberref.clone());
+            let ret = self.file.memberref_tbl.len() as u32;
+            self.memberref_map.insert(memberref, ret);
+            (ret, TokTag::MemberRef)
+        }
+    }
+
     /// mod_name: external module name
     ///
     /// name: external function name
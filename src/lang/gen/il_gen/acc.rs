@@ -1,11 +1,10 @@
-use core::panic;
-
 use super::super::super::ast::{ASTIdWithGenericParam, AST};
-use super::super::super::util::IItemPath;
+use super::super::super::diagnostic::ErrCode;
+use super::super::super::util::{IItemPath, Span};
 use super::super::{
     CodeGenCtx, Field, Method, Module, RValType, SymType, Type, ValExpectation, ValType,
 };
-use super::gen;
+use super::{gen, literal, match_expr};
 
 use xir::attrib::{FieldAttribFlag, MethodAttribFlag};
 use xir::inst::Inst;
@@ -15,10 +14,22 @@ use std::ptr::NonNull;
 
 fn gen_instance_obj_acc(
     ctx: &CodeGenCtx,
+    span: Span,
     lhs: &Type,
+    class_args: &[RValType],
     rhs: &ASTIdWithGenericParam,
     expectation: ValExpectation,
 ) -> ValType {
+    if !rhs.generic_params.is_empty() {
+        match expectation {
+            ValExpectation::None | ValExpectation::Callable => {}
+            _ => ctx.error(
+                ErrCode::InvalidGeneric,
+                span,
+                format!("Field {} cannot have generic args", rhs.id),
+            ),
+        }
+    }
     match expectation {
         ValExpectation::None | ValExpectation::Callable => {
             let ms = lhs.query_method(&rhs.id);
@@ -28,19 +39,29 @@ fn gen_instance_obj_acc(
                 .map(|m| NonNull::new(m as *const Method as *mut Method).unwrap())
                 .collect();
             if ms.is_empty() {
-                panic!("No instance method {} found in type {}", rhs, lhs);
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("No instance method {} found in type {}", rhs, lhs),
+                );
             }
-            ValType::Sym(SymType::Method(ms))
+            let method_args = rhs
+                .generic_params
+                .iter()
+                .map(|arg| ctx.get_ty(arg, span))
+                .collect();
+            ValType::Sym(SymType::Method(class_args.to_vec(), ms, method_args))
         }
         ValExpectation::RVal | ValExpectation::Instance => {
             // unlike ValExpectation::Callable,
             // xivm can handle instance field acc of value type correctly, as specified in CLI III.4.10
             if let Some(f) = lhs.query_field(&rhs.id) {
-                let field_ty = f.ty.clone();
-                let sig = ctx.module.builder.borrow_mut().add_field_sig(&field_ty);
-                let (field_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_member(
-                    unsafe { f.parent.as_ref().modname() },
-                    unsafe { &f.parent.as_ref().name },
+                ctx.check_field_access(span, f);
+                let field_ty = f.ty.instantiate(class_args, &[]);
+                let sig = ctx.module.builder.borrow_mut().add_field_sig(&f.ty);
+                let (field_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_inst_member(
+                    unsafe { f.parent.as_ref() },
+                    class_args,
                     &rhs.id,
                     sig,
                 );
@@ -48,12 +69,11 @@ fn gen_instance_obj_acc(
                 let loada = match expectation {
                     ValExpectation::RVal => false,
                     ValExpectation::Instance => {
-                        if let RValType::Value(_ty) = field_ty {
-                            // load addr if field is a value type
-                            true
-                        } else {
-                            false
-                        }
+                        // load addr if field is a value type
+                        matches!(
+                            field_ty,
+                            RValType::Value(_) | RValType::GenericInst(false, _, _)
+                        )
                     }
                     _ => unreachable!(),
                 };
@@ -70,19 +90,31 @@ fn gen_instance_obj_acc(
                     field_ty
                 })
             } else {
-                panic!("no field \"{}\" in {}", rhs, lhs);
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("no field \"{}\" in {}", rhs, lhs),
+                );
             }
         }
-        ValExpectation::Static => {
-            panic!("Type instance member cannot be static accessed")
-        }
+        ValExpectation::Static => ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            "Type instance member cannot be static accessed",
+        ),
         ValExpectation::Assignable => {
             if let Some(f) = lhs.query_field(&rhs.id) {
+                ctx.check_field_access(span, f);
                 ValType::Sym(SymType::Field(
+                    class_args.to_vec(),
                     NonNull::new(f as *const Field as *mut Field).unwrap(),
                 ))
             } else {
-                panic!("no field \"{}\" in {}", rhs, lhs);
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("no field \"{}\" in {}", rhs, lhs),
+                );
             }
         }
     }
@@ -90,6 +122,7 @@ fn gen_instance_obj_acc(
 
 pub fn gen_instance_acc(
     ctx: &CodeGenCtx,
+    span: Span,
     lhs: &AST,
     rhs: &ASTIdWithGenericParam,
     expectation: ValExpectation,
@@ -100,7 +133,7 @@ pub fn gen_instance_acc(
         | RValType::Class(_)
         | RValType::Value(_)
         | RValType::GenericInst(_, _, _) => {
-            let (ty, is_value) = match &lhs_ty {
+            let (ty, is_value, class_args) = match &lhs_ty {
                 RValType::String => (
                     NonNull::new(
                         ctx.mgr
@@ -114,10 +147,13 @@ pub fn gen_instance_acc(
                     )
                     .unwrap(),
                     false,
+                    vec![],
                 ),
-                RValType::Value(ty) => (ty.clone(), true),
-                RValType::Class(ty) => (ty.clone(), false),
-                RValType::GenericInst(_, _, _) => todo!(),
+                RValType::Value(ty) => (*ty, true, vec![]),
+                RValType::Class(ty) => (*ty, false, vec![]),
+                RValType::GenericInst(is_class, ty, class_args) => {
+                    (*ty, !is_class, class_args.clone())
+                }
                 _ => unreachable!(),
             };
 
@@ -138,45 +174,78 @@ pub fn gen_instance_acc(
                 }
                 _ => {}
             }
-            gen_instance_obj_acc(ctx, unsafe { ty.as_ref() }, rhs, expectation)
+            gen_instance_obj_acc(
+                ctx,
+                span,
+                unsafe { ty.as_ref() },
+                &class_args,
+                rhs,
+                expectation,
+            )
         }
         RValType::Array(_) => {
             if rhs.id == "len" {
                 match expectation {
                     ValExpectation::Callable => {
-                        panic!("arr.len is not callable");
+                        ctx.error(ErrCode::InvalidExpr, span, "arr.len is not callable");
                     }
                     ValExpectation::RVal | ValExpectation::Instance => {
                         ctx.method_builder.borrow_mut().add_inst(Inst::LdLen);
                         ValType::RVal(RValType::I32)
                     }
                     ValExpectation::Static => {
-                        panic!("arr.len has no static member");
+                        ctx.error(ErrCode::InvalidExpr, span, "arr.len has no static member");
                     }
                     ValExpectation::Assignable => {
-                        panic!("arr.len is not assignable");
+                        ctx.error(ErrCode::InvalidExpr, span, "arr.len is not assignable");
                     }
                     ValExpectation::None => {
-                        panic!("Expect None value but found arr.len");
+                        ctx.error(
+                            ErrCode::InvalidExpr,
+                            span,
+                            "Expect None value but found arr.len",
+                        );
                     }
                 }
             } else {
-                panic!("no field \"{}\" in {}", rhs, lhs_ty);
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("no field \"{}\" in {}", rhs, ctx.ty_name(&lhs_ty)),
+                );
             }
         }
         RValType::ByRef(ty) => match ty.as_ref() {
             RValType::Value(_ty) => {
                 let _ty_ref = unsafe { _ty.as_ref() };
-                gen_instance_obj_acc(ctx, _ty_ref, rhs, expectation)
+                gen_instance_obj_acc(ctx, span, _ty_ref, &[], rhs, expectation)
+            }
+            RValType::GenericInst(false, _ty, class_args) => {
+                let _ty_ref = unsafe { _ty.as_ref() };
+                gen_instance_obj_acc(ctx, span, _ty_ref, class_args, rhs, expectation)
             }
             _ => unimplemented!(),
         },
-        _ => panic!("no field \"{}\" in {}", rhs, lhs_ty),
+        RValType::Var(_) | RValType::MVar(_) => ctx.error(
+            ErrCode::InvalidGeneric,
+            span,
+            format!(
+                "Cannot access member \"{}\" of generic param {}",
+                rhs,
+                ctx.ty_name(&lhs_ty)
+            ),
+        ),
+        _ => ctx.error(
+            ErrCode::Unresolved,
+            span,
+            format!("no field \"{}\" in {}", rhs, ctx.ty_name(&lhs_ty)),
+        ),
     }
 }
 
 pub fn gen_static_acc(
     ctx: &CodeGenCtx,
+    span: Span,
     lhs: &AST,
     rhs: &ASTIdWithGenericParam,
     expectation: ValExpectation,
@@ -185,44 +254,103 @@ pub fn gen_static_acc(
     match &lhs_ty {
         SymType::Module(m) => match expectation {
             ValExpectation::Callable => {
-                panic!("Module member is not callable");
+                ctx.error(ErrCode::InvalidExpr, span, "Module member is not callable");
             }
             ValExpectation::RVal => {
-                panic!("Module member cannot be loaded as rval");
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Module member cannot be loaded as rval",
+                );
             }
             ValExpectation::Instance => {
-                panic!("Module member cannot be instance accessed");
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Module member cannot be instance accessed",
+                );
             }
             ValExpectation::Static => {
                 // Access a class or sub-module in module
                 let m = unsafe { m.as_ref() };
+                let this_mod = ctx.module.get_module();
                 if let Some(c) = m.classes.get(&rhs.id) {
+                    if !c.is_visible_from(this_mod, ctx.mgr) {
+                        ctx.error(
+                            ErrCode::Inaccessible,
+                            span,
+                            format!("{} is not visible in mod {}", c, this_mod),
+                        );
+                    }
                     ValType::Sym(SymType::Class(
                         NonNull::new(c.as_ref() as *const Type as *mut Type).unwrap(),
+                        ctx.get_class_generic_args(span, c, &rhs.generic_params),
                     ))
                 } else if m.sub_mods.contains(&rhs.id) {
+                    if !rhs.generic_params.is_empty() {
+                        ctx.error(
+                            ErrCode::InvalidGeneric,
+                            span,
+                            format!("Module {} cannot have generic args", rhs.id),
+                        );
+                    }
                     let mut path = m.mod_path.clone();
                     path.push(&rhs.id);
+                    let sub_mod = ctx.mgr.mod_tbl.get(path.as_str()).unwrap();
+                    if !sub_mod.is_visible_from(this_mod, ctx.mgr) {
+                        ctx.error(
+                            ErrCode::Inaccessible,
+                            span,
+                            format!("Module {} is not visible in mod {}", sub_mod, this_mod),
+                        );
+                    }
                     ValType::Sym(SymType::Module(
-                        NonNull::new(ctx.mgr.mod_tbl.get(path.as_str()).unwrap().as_ref()
-                            as *const Module as *mut Module)
-                        .unwrap(),
+                        NonNull::new(sub_mod.as_ref() as *const Module as *mut Module).unwrap(),
                     ))
                 } else {
-                    panic!("No item {} in module {}", rhs, m);
+                    ctx.error(
+                        ErrCode::Unresolved,
+                        span,
+                        format!("No item {} in module {}", rhs, m),
+                    );
                 }
             }
             ValExpectation::Assignable => {
-                panic!("Module member cannot be assigned");
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Module member cannot be assigned",
+                );
             }
             ValExpectation::None => {
-                panic!("Expect None type but found module member");
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Expect None type but found module member",
+                );
             }
         },
-        SymType::Class(c) => {
+        SymType::Class(c, class_args) => {
             let c = unsafe { c.as_ref() };
+            if !rhs.generic_params.is_empty() {
+                match expectation {
+                    ValExpectation::None | ValExpectation::Callable => {}
+                    _ => ctx.error(
+                        ErrCode::InvalidGeneric,
+                        span,
+                        format!("Field {} cannot have generic args", rhs.id),
+                    ),
+                }
+            }
             match expectation {
                 ValExpectation::None | ValExpectation::Callable => {
+                    if let Some(idx) = c.variants.iter().position(|v| v.name == rhs.id) {
+                        // variant with payloads is created like a call
+                        return ValType::Sym(SymType::Variant(
+                            NonNull::new(c as *const Type as *mut Type).unwrap(),
+                            idx,
+                        ));
+                    }
                     let ms = c.query_method(&rhs.id);
                     let ms: Vec<NonNull<Method>> = ms
                         .into_iter()
@@ -230,35 +358,59 @@ pub fn gen_static_acc(
                         .map(|m| NonNull::new(m as *const Method as *mut Method).unwrap())
                         .collect();
                     if ms.is_empty() {
-                        panic!("No static method {} found in class {}", rhs, c);
+                        ctx.error(
+                            ErrCode::Unresolved,
+                            span,
+                            format!("No static method {} found in class {}", rhs, c),
+                        );
                     }
-                    ValType::Sym(SymType::Method(ms))
+                    let method_args = rhs
+                        .generic_params
+                        .iter()
+                        .map(|arg| ctx.get_ty(arg, span))
+                        .collect();
+                    ValType::Sym(SymType::Method(class_args.clone(), ms, method_args))
                 }
                 ValExpectation::RVal | ValExpectation::Instance => {
-                    if let Some(f) = c.query_field(&rhs.id) {
+                    if let Some(v) = c.consts.get(&rhs.id) {
+                        // const is inlined as a literal
+                        literal::gen_const(ctx, v)
+                    } else if let Some(idx) = c.variants.iter().position(|v| v.name == rhs.id) {
+                        ValType::RVal(match_expr::gen_new_variant(
+                            ctx,
+                            span,
+                            NonNull::new(c as *const Type as *mut Type).unwrap(),
+                            idx,
+                            &[],
+                        ))
+                    } else if let Some(f) = c.query_field(&rhs.id) {
                         if !f.attrib.is(FieldAttribFlag::Static) {
-                            panic!("Field {} in {} is not static", rhs, lhs_ty);
+                            ctx.error(
+                                ErrCode::InvalidExpr,
+                                span,
+                                format!("Field {} in {} is not static", rhs, c),
+                            );
                         }
-                        let field_ty = f.ty.clone();
+                        ctx.check_field_access(span, f);
+                        let field_ty = f.ty.instantiate(class_args, &[]);
 
                         let loada = match expectation {
                             ValExpectation::RVal => false,
                             ValExpectation::Instance => {
-                                if let RValType::Value(_) = field_ty {
-                                    // load addr if field is a value type
-                                    true
-                                } else {
-                                    false
-                                }
+                                // load addr if field is a value type
+                                matches!(
+                                    field_ty,
+                                    RValType::Value(_) | RValType::GenericInst(false, _, _)
+                                )
                             }
                             _ => unreachable!(),
                         };
 
-                        let sig = ctx.module.builder.borrow_mut().add_field_sig(&field_ty);
+                        let sig = ctx.module.builder.borrow_mut().add_field_sig(&f.ty);
                         let (field_idx, tok_tag) =
-                            ctx.module.builder.borrow_mut().add_const_member(
-                                unsafe { f.parent.as_ref().modname() },
-                                unsafe { &f.parent.as_ref().name },
+                            ctx.module.builder.borrow_mut().add_const_inst_member(
+                                unsafe { f.parent.as_ref() },
+                                class_args,
                                 &rhs.id,
                                 sig,
                             );
@@ -277,44 +429,61 @@ pub fn gen_static_acc(
                             field_ty
                         })
                     } else {
-                        panic!("No field {} in {}", rhs, lhs_ty);
+                        ctx.error(
+                            ErrCode::Unresolved,
+                            span,
+                            format!("No field {} in {}", rhs, c),
+                        );
                     }
                 }
                 ValExpectation::Assignable => {
+                    if c.consts.contains_key(&rhs.id) {
+                        ctx.error(
+                            ErrCode::InvalidExpr,
+                            span,
+                            format!("Cannot assign to const {} in {}", rhs, c),
+                        );
+                    }
                     if let Some(f) = c.query_field(&rhs.id) {
                         if !f.attrib.is(FieldAttribFlag::Static) {
-                            panic!("Field {} in {} is not static", rhs, lhs_ty);
+                            ctx.error(
+                                ErrCode::InvalidExpr,
+                                span,
+                                format!("Field {} in {} is not static", rhs, c),
+                            );
                         }
+                        ctx.check_field_access(span, f);
                         ValType::Sym(SymType::Field(
+                            class_args.clone(),
                             NonNull::new(f as *const Field as *mut Field).unwrap(),
                         ))
                     } else {
-                        panic!("No field {} in {}", rhs, lhs_ty);
+                        ctx.error(
+                            ErrCode::Unresolved,
+                            span,
+                            format!("No field {} in {}", rhs, c),
+                        );
                     }
                 }
                 ValExpectation::Static => {
-                    panic!("Type static member cannot be static accessed");
+                    ctx.error(
+                        ErrCode::InvalidExpr,
+                        span,
+                        "Type static member cannot be static accessed",
+                    );
                 }
             }
         }
-        SymType::Field(f) => {
-            let (mod_name, class_name, field_name, field_ty) = unsafe {
-                let f_ref = f.as_ref();
-                let class_ref = f_ref.parent.as_ref();
-                let module_ref = class_ref.parent.as_ref();
-                (
-                    module_ref.fullname(),
-                    &class_ref.name,
-                    &f_ref.name,
-                    f_ref.ty.clone(),
-                )
-            };
-            let sig = ctx.module.builder.borrow_mut().add_field_sig(&field_ty);
-            let (field_idx, tok_tag) = ctx
-                .module
-                .builder
-                .borrow_mut()
-                .add_const_member(mod_name, class_name, field_name, sig);
+        SymType::Field(class_args, f) => {
+            let f_ref = unsafe { f.as_ref() };
+            let field_ty = f_ref.ty.instantiate(class_args, &[]);
+            let sig = ctx.module.builder.borrow_mut().add_field_sig(&f_ref.ty);
+            let (field_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_inst_member(
+                unsafe { f_ref.parent.as_ref() },
+                class_args,
+                &f_ref.name,
+                sig,
+            );
             ctx.method_builder
                 .borrow_mut()
                 .add_inst(Inst::LdSFld(to_tok(field_idx, tok_tag)));
@@ -325,11 +494,17 @@ pub fn gen_static_acc(
     }
 }
 
-pub fn gen_arr_acc(ctx: &CodeGenCtx, lhs: &AST, rhs: &AST, expectation: ValExpectation) -> ValType {
+pub fn gen_arr_acc(
+    ctx: &CodeGenCtx,
+    span: Span,
+    lhs: &AST,
+    rhs: &AST,
+    expectation: ValExpectation,
+) -> ValType {
     let lhs_ty = gen(ctx, lhs, ValExpectation::RVal).expect_rval();
     if let RValType::Array(ele_ty) = lhs_ty {
         let rhs_val = gen(ctx, rhs, ValExpectation::RVal);
-        if let ValType::RVal(RValType::I32) = rhs_val {
+        if let ValType::RVal(RValType::I32 | RValType::ISize | RValType::USize) = rhs_val {
             match expectation {
                 ValExpectation::RVal => {
                     ctx.method_builder
@@ -338,12 +513,11 @@ pub fn gen_arr_acc(ctx: &CodeGenCtx, lhs: &AST, rhs: &AST, expectation: ValExpec
                     ValType::RVal(ele_ty.as_ref().clone())
                 }
                 ValExpectation::Instance => {
-                    let loada = if let RValType::Value(_) = ele_ty.as_ref() {
-                        // load addr if element is a value type
-                        true
-                    } else {
-                        false
-                    };
+                    // load addr if element is a value type
+                    let loada = matches!(
+                        ele_ty.as_ref(),
+                        RValType::Value(_) | RValType::GenericInst(false, _, _)
+                    );
                     if loada {
                         ctx.method_builder
                             .borrow_mut()
@@ -359,14 +533,37 @@ pub fn gen_arr_acc(ctx: &CodeGenCtx, lhs: &AST, rhs: &AST, expectation: ValExpec
                 ValExpectation::Assignable => {
                     ValType::Sym(SymType::ArrAcc(ele_ty.as_ref().clone()))
                 }
-                ValExpectation::Callable => panic!("Array element cannot be directly called"),
-                ValExpectation::Static => panic!("Cannot static access array element"),
-                ValExpectation::None => panic!("Expect None value but found array element"),
+                ValExpectation::Callable => ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Array element cannot be directly called",
+                ),
+                ValExpectation::Static => ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Cannot static access array element",
+                ),
+                ValExpectation::None => ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    "Expect None value but found array element",
+                ),
             }
         } else {
-            panic!("Array index cannot be {}", rhs_val);
+            ctx.error(
+                ErrCode::TypeMismatch,
+                rhs.span,
+                format!(
+                    "Array index cannot be {}",
+                    ctx.ty_name(rhs_val.expect_rval_ref())
+                ),
+            );
         }
     } else {
-        panic!("Cannot array access {}", lhs);
+        ctx.error(
+            ErrCode::InvalidExpr,
+            lhs.span,
+            format!("Cannot array access {}", ctx.ty_name(&lhs_ty)),
+        );
     }
 }
@@ -1,4 +1,5 @@
 use super::super::super::ast::AST;
+use super::super::super::diagnostic::ErrCode;
 use super::super::{CodeGenCtx, RValType, ValExpectation, ValType};
 use super::gen;
 
@@ -22,7 +23,11 @@ pub fn gen_if(
     let cond_ty = gen(ctx, cond, ValExpectation::RVal);
     match cond_ty.expect_rval() {
         RValType::Bool => {}
-        _ => panic!("Cond not return bool"),
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            cond.span,
+            format!("Cond should be bool but found {}", ctx.ty_name(&ty)),
+        ),
     }
 
     {
@@ -52,12 +57,31 @@ pub fn gen_if(
     let ret = if let ValType::RVal(then_v) = then_v {
         if let ValType::RVal(els_v) = els_v {
             if then_v != els_v {
-                panic!("Mismatch then type ({}) and else type ({})", then_v, els_v);
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    then.span.merge(&els.span),
+                    format!(
+                        "Mismatch then type ({}) and else type ({})",
+                        ctx.ty_name(&then_v),
+                        ctx.ty_name(&els_v)
+                    ),
+                );
             } else {
                 then_v
             }
         } else {
-            panic!("Mismatch then type ({}) and else type ({})", then_v, els_v);
+            ctx.error(
+                ErrCode::TypeMismatch,
+                then.span.merge(&els.span),
+                format!(
+                    "Mismatch then type ({}) and else type ({})",
+                    ctx.ty_name(&then_v),
+                    match &els_v {
+                        ValType::Ret(_) => String::from("return"),
+                        _ => els_v.to_string(),
+                    }
+                ),
+            );
         }
     } else {
         RValType::Void
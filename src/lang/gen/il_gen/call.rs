@@ -1,30 +1,37 @@
 use super::super::super::ast::{ASTType, AST};
-use super::super::super::mod_mgr::Method;
+use super::super::super::diagnostic::{Diagnostic, ErrCode};
+use super::super::super::mod_mgr::{GenericParam, Method};
+use super::super::super::util::Span;
 use super::super::{CodeGenCtx, RValType, SymType, ValExpectation};
-use super::gen;
+use super::cast::is_castable;
+use super::{gen, match_expr};
 
 use xir::attrib::MethodAttribFlag;
-use xir::tok::to_tok;
+use xir::tok::{to_tok, TokTag};
 use xir::{Inst, CTOR_NAME};
 
 use std::ptr::{self, NonNull};
 
 pub fn pick_method_from_ptrs(
     candidates: &Vec<NonNull<Method>>,
-    args_ty: &Vec<RValType>,
+    class_args: &[RValType],
+    method_args: &[RValType],
+    args_ty: &[RValType],
 ) -> *const Method {
     for candidate in candidates.iter() {
         let candidate_ref = unsafe { candidate.as_ref() };
-        if candidate_ref.ps.len() == args_ty.len() {
+        if candidate_ref.generic_params.len() == method_args.len()
+            && candidate_ref.ps.len() == args_ty.len()
+        {
             let mut is_match = true;
             for (param, arg_ty) in candidate_ref.ps.iter().zip(args_ty.iter()) {
-                if &param.ty != arg_ty {
+                if &param.ty.instantiate(class_args, method_args) != arg_ty {
                     is_match = false;
                     break;
                 }
             }
             if is_match {
-                return candidate.clone().as_ptr();
+                return candidate.as_ptr();
             }
         }
     }
@@ -33,13 +40,14 @@ pub fn pick_method_from_ptrs(
 
 pub fn pick_method_from_refs<'m>(
     candidates: &'m Vec<Box<Method>>,
-    args_ty: &Vec<RValType>,
+    class_args: &[RValType],
+    args_ty: &[RValType],
 ) -> Option<&'m Method> {
     for candidate in candidates.iter() {
         if candidate.ps.len() == args_ty.len() {
             let mut is_match = true;
             for (param, arg_ty) in candidate.ps.iter().zip(args_ty.iter()) {
-                if &param.ty != arg_ty {
+                if &param.ty.instantiate(class_args, &[]) != arg_ty {
                     is_match = false;
                     break;
                 }
@@ -52,43 +60,83 @@ pub fn pick_method_from_refs<'m>(
     None
 }
 
-pub fn gen_call(ctx: &CodeGenCtx, f: &Box<AST>, args: &Vec<Box<AST>>) -> RValType {
+/// Check whether generic args satisfy constraints of generic params
+///
+/// class_args and method_args are used to instantiate constraints
+fn check_generic_args(
+    ctx: &CodeGenCtx,
+    span: Span,
+    params: &[GenericParam],
+    args: &[RValType],
+    class_args: &[RValType],
+    method_args: &[RValType],
+) {
+    for (param, arg) in params.iter().zip(args.iter()) {
+        for constraint in param.constraints.iter() {
+            let constraint = constraint.instantiate(class_args, method_args);
+            if !is_castable(ctx, arg, &constraint) {
+                ctx.error(
+                    ErrCode::InvalidGeneric,
+                    span,
+                    format!(
+                        "Generic arg {} does not satisfy constraint {}: {}",
+                        ctx.ty_name(arg),
+                        param.name,
+                        ctx.ty_name(&constraint)
+                    ),
+                );
+            }
+        }
+    }
+}
+
+pub fn gen_call(ctx: &CodeGenCtx, span: Span, f: &Box<AST>, args: &Vec<Box<AST>>) -> RValType {
     let lval = gen(ctx, f, ValExpectation::Callable).expect_sym();
     let (inst, ret) = match &lval {
-        SymType::Method(candidates) => {
+        SymType::Method(class_args, candidates, method_args) => {
             // build args
             let args_ty: Vec<RValType> = args
                 .iter()
                 .map(|arg| gen(ctx, arg, ValExpectation::RVal).expect_rval())
                 .collect();
 
-            // only type is checked in gen_call
-            // accessibility should be checked in class.query_method
+            // only type and accessibility are checked in gen_call
             // static/instance should be checked in gen_val
 
             // Pick method
-            let m_ref = pick_method_from_ptrs(candidates, &args_ty);
-            let (mod_name, class_name, m_ref) = if let Some(m_ref) = unsafe { m_ref.as_ref() } {
-                unsafe {
-                    let class_ref = m_ref.parent.as_ref();
-                    let module_ref = class_ref.parent.as_ref();
-                    (module_ref.fullname(), &class_ref.name, m_ref)
-                }
+            let m_ref = pick_method_from_ptrs(candidates, class_args, method_args, &args_ty);
+            let m_ref = if let Some(m_ref) = unsafe { m_ref.as_ref() } {
+                m_ref
             } else {
-                panic!(
-                    "No matched method with param ({}), candidates are: {}",
-                    args_ty
-                        .iter()
-                        .map(|t| t.to_string())
-                        .collect::<Vec<String>>()
-                        .join(", "),
-                    candidates
-                        .iter()
-                        .map(|m| unsafe { m.as_ref().to_string() })
-                        .collect::<Vec<String>>()
-                        .join(", ")
+                let mut diag = Diagnostic::error(
+                    ErrCode::TypeMismatch,
+                    format!(
+                        "No matched method with param ({})",
+                        args_ty
+                            .iter()
+                            .map(|t| ctx.ty_name(t).to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ),
                 )
+                .with_loc(&ctx.module.fpath, span);
+                for candidate in candidates.iter() {
+                    diag = diag.with_note(format!(
+                        "candidate: {}",
+                        unsafe { candidate.as_ref() }.sig_name()
+                    ));
+                }
+                diag.abort();
             };
+            ctx.check_method_access(span, m_ref);
+            check_generic_args(
+                ctx,
+                span,
+                &m_ref.generic_params,
+                method_args,
+                class_args,
+                method_args,
+            );
 
             // Add to class file
             let sig = ctx.module.builder.borrow_mut().add_method_sig(
@@ -96,12 +144,21 @@ pub fn gen_call(ctx: &CodeGenCtx, f: &Box<AST>, args: &Vec<Box<AST>>) -> RValTyp
                 &m_ref.ps,
                 &m_ref.ret,
             );
-            let (m_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_member(
-                mod_name,
-                class_name,
+            let (m_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_inst_member(
+                unsafe { m_ref.parent.as_ref() },
+                class_args,
                 &m_ref.name,
                 sig,
             );
+            let mut tok = to_tok(m_idx, tok_tag);
+            if !method_args.is_empty() {
+                let spec_idx = ctx
+                    .module
+                    .builder
+                    .borrow_mut()
+                    .add_const_methodspec(tok, method_args);
+                tok = to_tok(spec_idx, TokTag::MethodSpec);
+            }
 
             let mut callvirt = !m_ref.attrib.is(MethodAttribFlag::Static);
             if callvirt {
@@ -114,15 +171,21 @@ pub fn gen_call(ctx: &CodeGenCtx, f: &Box<AST>, args: &Vec<Box<AST>>) -> RValTyp
 
             (
                 if callvirt {
-                    Inst::CallVirt(to_tok(m_idx, tok_tag))
+                    Inst::CallVirt(tok)
                 } else {
-                    Inst::Call(to_tok(m_idx, tok_tag))
+                    Inst::Call(tok)
                 },
-                m_ref.ret.clone(),
+                m_ref.ret.instantiate(class_args, method_args),
             )
         }
-        SymType::Module(_) => panic!(),
-        SymType::Class(_) => panic!(),
+        SymType::Variant(ty, idx) => {
+            return match_expr::gen_new_variant(ctx, span, *ty, *idx, args);
+        }
+        SymType::Module(_) | SymType::Class(_, _) => ctx.error(
+            ErrCode::InvalidExpr,
+            f.span,
+            "Module or class is not callable",
+        ),
         _ => unreachable!(),
     };
 
@@ -131,13 +194,14 @@ pub fn gen_call(ctx: &CodeGenCtx, f: &Box<AST>, args: &Vec<Box<AST>>) -> RValTyp
     ret
 }
 
-pub fn gen_new(ctx: &CodeGenCtx, ty: &ASTType, args: &Vec<Box<AST>>) -> RValType {
-    let ret = ctx.get_ty(ty);
-    let ty = match &ret {
-        RValType::Value(ty) => ty,
-        RValType::Class(ty) => ty,
+pub fn gen_new(ctx: &CodeGenCtx, span: Span, ty: &ASTType, args: &Vec<Box<AST>>) -> RValType {
+    let ret = ctx.get_ty(ty, span);
+    let (ty, class_args) = match &ret {
+        RValType::Value(ty) => (ty, vec![]),
+        RValType::Class(ty) => (ty, vec![]),
+        RValType::GenericInst(_, ty, class_args) => (ty, class_args.clone()),
         RValType::String => unimplemented!("new string is not implemented"),
-        _ => panic!("Cannot new {}", ret),
+        _ => ctx.error(ErrCode::InvalidExpr, span, format!("Cannot new {}", ret)),
     };
 
     let args_ty: Vec<RValType> = args
@@ -146,20 +210,51 @@ pub fn gen_new(ctx: &CodeGenCtx, ty: &ASTType, args: &Vec<Box<AST>>) -> RValType
         .collect();
 
     let type_ref = unsafe { ty.as_ref() };
+    if type_ref.is_enum() {
+        ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            format!(
+                "Enum {} cannot be created by new, use its variants",
+                type_ref
+            ),
+        );
+    }
+    check_generic_args(
+        ctx,
+        span,
+        &type_ref.generic_params,
+        &class_args,
+        &class_args,
+        &[],
+    );
 
     let ctors = type_ref.methods.get(CTOR_NAME).unwrap();
 
-    let ctor = pick_method_from_refs(ctors, &args_ty);
+    let ctor = pick_method_from_refs(ctors, &class_args, &args_ty);
     let ctor = if let Some(ctor) = ctor {
         ctor
     } else {
-        panic!("Cannot find ctor");
+        ctx.error(
+            ErrCode::Unresolved,
+            span,
+            format!(
+                "Cannot find ctor of {} with param ({})",
+                type_ref,
+                args_ty
+                    .iter()
+                    .map(|t| ctx.ty_name(t).to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        );
     };
+    ctx.check_method_access(span, ctor);
 
     let mut builder = ctx.module.builder.borrow_mut();
     let ctor_sig = builder.add_method_sig(true, &ctor.ps, &RValType::Void);
     let (ctor_idx, tok_tag) =
-        builder.add_const_member(type_ref.modname(), &type_ref.name, CTOR_NAME, ctor_sig);
+        builder.add_const_inst_member(type_ref, &class_args, CTOR_NAME, ctor_sig);
 
     ctx.method_builder
         .borrow_mut()
@@ -168,35 +263,52 @@ pub fn gen_new(ctx: &CodeGenCtx, ty: &ASTType, args: &Vec<Box<AST>>) -> RValType
     ret
 }
 
-pub fn gen_new_arr(ctx: &CodeGenCtx, ty: &ASTType, dim: &AST) -> RValType {
+pub fn gen_new_arr(ctx: &CodeGenCtx, span: Span, ty: &ASTType, dim: &AST) -> RValType {
     let dim_ty = gen(ctx, dim, ValExpectation::RVal);
-    // only i32 or isize if allowed
+    // only i32 or native int if allowed
     match dim_ty.expect_rval_ref() {
-        RValType::I32 => {}
-        _ => panic!(
-            "Array size only support i32 or isize val, but found {}",
-            dim_ty
+        RValType::I32 | RValType::ISize | RValType::USize => {}
+        _ => ctx.error(
+            ErrCode::TypeMismatch,
+            dim.span,
+            format!(
+                "Array size only support i32 or isize val, but found {}",
+                ctx.ty_name(dim_ty.expect_rval_ref())
+            ),
         ),
     }
 
-    let ele_ty = ctx.get_ty(ty);
+    let ele_ty = ctx.get_ty(ty, span);
 
     let ty_tok = match &ele_ty {
-        RValType::Bool | RValType::U8 | RValType::Char | RValType::F64 => unimplemented!(),
-        RValType::I32 => {
-            let i32_ty = ctx
+        RValType::Bool
+        | RValType::Char
+        | RValType::U8
+        | RValType::I8
+        | RValType::U16
+        | RValType::I16
+        | RValType::U32
+        | RValType::I32
+        | RValType::U64
+        | RValType::I64
+        | RValType::USize
+        | RValType::ISize
+        | RValType::F32
+        | RValType::F64 => {
+            // element of a primitive array is the std struct that wraps the primitive
+            let prim_ty = ctx
                 .mgr
                 .mod_tbl
                 .get("std")
                 .unwrap()
                 .classes
-                .get("Int32")
+                .get(ele_ty.prim_struct_name())
                 .unwrap();
             let (idx, tag) = ctx
                 .module
                 .builder
                 .borrow_mut()
-                .add_const_class(i32_ty.modname(), &i32_ty.name);
+                .add_const_class(prim_ty.modname(), &prim_ty.name);
             to_tok(idx, tag.to_tok_tag())
         }
         RValType::String => {
@@ -224,6 +336,10 @@ pub fn gen_new_arr(ctx: &CodeGenCtx, ty: &ASTType, dim: &AST) -> RValType {
                 .add_const_class(ty_ref.modname(), &ty_ref.name);
             to_tok(idx, tag.to_tok_tag())
         }
+        RValType::GenericInst(_, _, _) | RValType::Var(_) | RValType::MVar(_) => {
+            let idx = ctx.module.builder.borrow_mut().add_const_typespec(&ele_ty);
+            to_tok(idx, TokTag::TypeSpec)
+        }
         RValType::Array(elem_ty) => {
             todo!()
         }
@@ -1,68 +1,430 @@
 use super::super::super::ast::{ASTType, AST};
-use super::super::super::mod_mgr::Type;
-use super::super::{CodeGenCtx, RValType, ValExpectation, ValType};
-use super::gen;
+use super::super::super::diagnostic::ErrCode;
+use super::super::super::util::Span;
+use super::super::{CodeGenCtx, RValType, Type, ValExpectation, ValType};
+use super::{gen, match_expr};
 
-pub fn gen_cast(ctx: &CodeGenCtx, ty: &ASTType, val: &AST) -> ValType {
+use xir::attrib::LocalAttrib;
+use xir::inst::Inst;
+use xir::tok::{to_tok, TokTag};
+
+use std::ptr::{self, NonNull};
+
+/// `val as ty` or `val as! ty` if is_checked
+pub fn gen_cast(
+    ctx: &CodeGenCtx,
+    span: Span,
+    ty: &ASTType,
+    val: &AST,
+    is_checked: bool,
+) -> ValType {
     let lhs_ty = gen(ctx, val, ValExpectation::RVal);
     let lhs_rval_ty = lhs_ty.expect_rval_ref();
 
-    let to_type = ctx.get_ty(ty);
+    let to_type = ctx.get_ty(ty, span);
 
     match lhs_rval_ty {
-        RValType::Bool => unimplemented!(),
-        RValType::U8 => unimplemented!(),
-        RValType::Char => unimplemented!(),
-        RValType::I32 => unimplemented!(),
-        RValType::F64 => unimplemented!(),
-        RValType::Void => panic!("Cannot cast void type"),
-        RValType::Never => panic!("Cannot cast never type"),
-        RValType::Value(_) => unimplemented!(),
-        RValType::GenericInst(_, _, _) => unimplemented!(),
-        RValType::String => unimplemented!(),
-        RValType::Class(ty) => {
-            let lhs_ty = unsafe { ty.as_ref() };
-            match &to_type {
-                RValType::Bool
-                | RValType::U8
-                | RValType::Char
-                | RValType::I32
-                | RValType::F64
-                | RValType::Void
-                | RValType::Never
-                | RValType::ByRef(_)
-                | RValType::Array(_) => {
-                    panic!("cast from {} to {} is not allowed", lhs_rval_ty, to_type)
+        RValType::Void => ctx.error(ErrCode::InvalidExpr, val.span, "Cannot cast void type"),
+        RValType::Never => ctx.error(ErrCode::InvalidExpr, val.span, "Cannot cast never type"),
+        RValType::Bool => match &to_type {
+            RValType::Bool => {}
+            _ => ctx.error(
+                ErrCode::InvalidExpr,
+                span,
+                format!(
+                    "cast from {} to {} is not allowed",
+                    ctx.ty_name(lhs_rval_ty),
+                    ctx.ty_name(&to_type)
+                ),
+            ),
+        },
+        RValType::Char
+        | RValType::U8
+        | RValType::I8
+        | RValType::U16
+        | RValType::I16
+        | RValType::U32
+        | RValType::I32
+        | RValType::U64
+        | RValType::I64
+        | RValType::USize
+        | RValType::ISize
+        | RValType::F32
+        | RValType::F64 => {
+            if is_numeric(&to_type) {
+                gen_conv(ctx, lhs_rval_ty, &to_type, is_checked);
+            } else {
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    format!(
+                        "cast from {} to {} is not allowed",
+                        ctx.ty_name(lhs_rval_ty),
+                        ctx.ty_name(&to_type)
+                    ),
+                );
+            }
+        }
+        RValType::Value(ty) if unsafe { ty.as_ref() }.is_enum() => match &to_type {
+            RValType::I32 => {
+                // enum is casted to its discriminant
+                let tmp = ctx.locals.borrow_mut().add_tmp(
+                    lhs_rval_ty.clone(),
+                    LocalAttrib::default(),
+                    true,
+                );
+                ctx.method_builder.borrow_mut().add_inst_stloc(tmp);
+                match_expr::gen_ld_tag(ctx, unsafe { ty.as_ref() }, tmp);
+            }
+            _ => ctx.error(
+                ErrCode::InvalidExpr,
+                span,
+                format!(
+                    "cast from {} to {} is not allowed",
+                    ctx.ty_name(lhs_rval_ty),
+                    ctx.ty_name(&to_type)
+                ),
+            ),
+        },
+        RValType::Value(_) | RValType::GenericInst(false, _, _) | RValType::ByRef(_) => {
+            if lhs_rval_ty != &to_type {
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    format!(
+                        "cast from {} to {} is not allowed",
+                        ctx.ty_name(lhs_rval_ty),
+                        ctx.ty_name(&to_type)
+                    ),
+                );
+            }
+        }
+        RValType::Class(_)
+        | RValType::GenericInst(true, _, _)
+        | RValType::Var(_)
+        | RValType::MVar(_)
+        | RValType::String
+        | RValType::Array(_) => {
+            if is_ref(&to_type) {
+                let from = as_class(ctx, lhs_rval_ty);
+                let to = as_class(ctx, &to_type);
+                if is_castable(ctx, &from, &to) {
+                    // upcast is a no-op, generic args are erased in IL
+                } else if is_generic_param(&to_type) {
+                    // generic args are erased, a downcast to them cannot be checked at runtime
+                    ctx.error(
+                        ErrCode::InvalidExpr,
+                        span,
+                        format!(
+                            "cast from {} to {} is not allowed",
+                            ctx.ty_name(lhs_rval_ty),
+                            ctx.ty_name(&to_type)
+                        ),
+                    );
+                } else if is_castable(ctx, &to, &from) || is_interface(&from) || is_interface(&to) {
+                    // downcast is checked at runtime.
+                    // A class may be casted to any interface since its subclasses may implement it
+                    let tok = ty_tok(ctx, &to_type);
+                    ctx.method_builder
+                        .borrow_mut()
+                        .add_inst(Inst::CastClass(tok));
+                } else {
+                    ctx.error(
+                        ErrCode::InvalidExpr,
+                        span,
+                        format!(
+                            "cast from {} to {} is not allowed",
+                            ctx.ty_name(lhs_rval_ty),
+                            ctx.ty_name(&to_type)
+                        ),
+                    );
                 }
-                RValType::Value(_) => unimplemented!(),
-                RValType::GenericInst(_, _, _) => unimplemented!(),
-                RValType::String => {
-                    unimplemented!()
+            } else {
+                ctx.error(
+                    ErrCode::InvalidExpr,
+                    span,
+                    format!(
+                        "cast from {} to {} is not allowed",
+                        ctx.ty_name(lhs_rval_ty),
+                        ctx.ty_name(&to_type)
+                    ),
+                );
+            }
+        }
+    }
+
+    ValType::RVal(to_type)
+}
+
+/// `val is ty`, test if val is an instance of ty at runtime
+pub fn gen_is(ctx: &CodeGenCtx, span: Span, ty: &ASTType, val: &AST) -> ValType {
+    let lhs_ty = gen(ctx, val, ValExpectation::RVal);
+    let lhs_rval_ty = lhs_ty.expect_rval_ref();
+
+    let to_type = ctx.get_ty(ty, span);
+
+    if !is_ref(lhs_rval_ty) {
+        ctx.error(
+            ErrCode::InvalidExpr,
+            val.span,
+            format!("is cannot be applied on {}", ctx.ty_name(lhs_rval_ty)),
+        );
+    } else if !is_ref(&to_type) || is_generic_param(&to_type) {
+        ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            format!("{} cannot be tested by is", ctx.ty_name(&to_type)),
+        );
+    } else {
+        let tok = ty_tok(ctx, &to_type);
+        // isinst pushes null if val is not an instance of ty
+        ctx.method_builder
+            .borrow_mut()
+            .add_inst(Inst::IsInst(tok))
+            .add_inst(Inst::LdNull)
+            .add_inst(Inst::CGtUn);
+    }
+
+    ValType::RVal(RValType::Bool)
+}
+
+fn is_numeric(ty: &RValType) -> bool {
+    ty.is_int() || ty.is_float() || *ty == RValType::Char
+}
+
+/// Whether ty is a reference type that can be casted by castclass
+fn is_ref(ty: &RValType) -> bool {
+    matches!(
+        ty,
+        RValType::Class(_)
+            | RValType::GenericInst(true, _, _)
+            | RValType::Var(_)
+            | RValType::MVar(_)
+            | RValType::String
+            | RValType::Array(_)
+    )
+}
+
+fn is_generic_param(ty: &RValType) -> bool {
+    matches!(ty, RValType::Var(_) | RValType::MVar(_))
+}
+
+fn is_interface(ty: &RValType) -> bool {
+    match ty {
+        RValType::Class(ty) | RValType::GenericInst(true, ty, _) => {
+            unsafe { ty.as_ref() }.is_interface()
+        }
+        _ => false,
+    }
+}
+
+/// string and arrays are viewed as std::String and std::Array when checking inheritance
+fn as_class(ctx: &CodeGenCtx, ty: &RValType) -> RValType {
+    let name = match ty {
+        RValType::String => "String",
+        RValType::Array(_) => "Array",
+        _ => return ty.clone(),
+    };
+    let class = ctx
+        .mgr
+        .mod_tbl
+        .get("std")
+        .unwrap()
+        .classes
+        .get(name)
+        .unwrap()
+        .as_ref();
+    RValType::Class(NonNull::new(class as *const Type as *mut Type).unwrap())
+}
+
+/// token of a reference type
+fn ty_tok(ctx: &CodeGenCtx, ty: &RValType) -> u32 {
+    match ty {
+        RValType::Class(class) => {
+            let class = unsafe { class.as_ref() };
+            let (idx, tag) = ctx
+                .module
+                .builder
+                .borrow_mut()
+                .add_const_class(class.modname(), &class.name);
+            to_tok(idx, tag.to_tok_tag())
+        }
+        RValType::String => ty_tok(ctx, &as_class(ctx, ty)),
+        RValType::GenericInst(_, _, _)
+        | RValType::Var(_)
+        | RValType::MVar(_)
+        | RValType::Array(_) => {
+            let idx = ctx.module.builder.borrow_mut().add_const_typespec(ty);
+            to_tok(idx, TokTag::TypeSpec)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// (min bits, max bits, is signed) of int type. char is viewed as u16
+fn int_info(ty: &RValType) -> (u8, u8, bool) {
+    match ty {
+        RValType::U8 => (8, 8, false),
+        RValType::I8 => (8, 8, true),
+        RValType::U16 | RValType::Char => (16, 16, false),
+        RValType::I16 => (16, 16, true),
+        RValType::U32 => (32, 32, false),
+        RValType::I32 => (32, 32, true),
+        RValType::U64 => (64, 64, false),
+        RValType::I64 => (64, 64, true),
+        // size of native int depends on platform
+        RValType::USize => (32, 64, false),
+        RValType::ISize => (32, 64, true),
+        _ => unreachable!(),
+    }
+}
+
+/// Whether every value of int type from can be represented by int type to
+fn is_widening(from: &RValType, to: &RValType) -> bool {
+    let (_, from_max_bits, from_signed) = int_info(from);
+    let (to_min_bits, _, to_signed) = int_info(to);
+    match (from_signed, to_signed) {
+        (true, true) | (false, false) => from_max_bits <= to_min_bits,
+        (false, true) => from_max_bits < to_min_bits,
+        (true, false) => false,
+    }
+}
+
+/// conv.ovf.* that converts to int type to
+fn conv_ovf_inst(to: &RValType, is_un: bool) -> Inst {
+    match (to, is_un) {
+        (RValType::I8, false) => Inst::ConvOvfI1,
+        (RValType::I8, true) => Inst::ConvOvfI1Un,
+        (RValType::U8, false) => Inst::ConvOvfU1,
+        (RValType::U8, true) => Inst::ConvOvfU1Un,
+        (RValType::I16, false) => Inst::ConvOvfI2,
+        (RValType::I16, true) => Inst::ConvOvfI2Un,
+        (RValType::U16, false) | (RValType::Char, false) => Inst::ConvOvfU2,
+        (RValType::U16, true) | (RValType::Char, true) => Inst::ConvOvfU2Un,
+        (RValType::I32, false) => Inst::ConvOvfI4,
+        (RValType::I32, true) => Inst::ConvOvfI4Un,
+        (RValType::U32, false) => Inst::ConvOvfU4,
+        (RValType::U32, true) => Inst::ConvOvfU4Un,
+        (RValType::I64, false) => Inst::ConvOvfI8,
+        (RValType::I64, true) => Inst::ConvOvfI8Un,
+        (RValType::U64, false) => Inst::ConvOvfU8,
+        (RValType::U64, true) => Inst::ConvOvfU8Un,
+        (RValType::ISize, false) => Inst::ConvOvfI,
+        (RValType::ISize, true) => Inst::ConvOvfIUn,
+        (RValType::USize, false) => Inst::ConvOvfU,
+        (RValType::USize, true) => Inst::ConvOvfUUn,
+        _ => unreachable!(),
+    }
+}
+
+/// conv.* that converts to int type to, truncating or wrapping around on overflow
+fn conv_inst(to: &RValType) -> Inst {
+    match to {
+        RValType::I8 => Inst::ConvI1,
+        RValType::U8 => Inst::ConvU1,
+        RValType::I16 => Inst::ConvI2,
+        RValType::U16 | RValType::Char => Inst::ConvU2,
+        RValType::I32 => Inst::ConvI4,
+        RValType::U32 => Inst::ConvU4,
+        RValType::I64 => Inst::ConvI8,
+        RValType::U64 => Inst::ConvU8,
+        RValType::ISize => Inst::ConvI,
+        RValType::USize => Inst::ConvU,
+        _ => unreachable!(),
+    }
+}
+
+/// Convert between numeric types.
+///
+/// If is_checked, conversions that may lose information throw std::OverflowException,
+/// except conversions to float. Otherwise ints are truncated and floats are rounded toward zero
+fn gen_conv(ctx: &CodeGenCtx, from: &RValType, to: &RValType, is_checked: bool) {
+    let mut builder = ctx.method_builder.borrow_mut();
+    if from == to {
+        return;
+    }
+    match to {
+        RValType::F32 | RValType::F64 => {
+            if !from.is_float() && (from.is_unsigned() || *from == RValType::Char) {
+                builder.add_inst(Inst::ConvRUn);
+                if *to == RValType::F64 {
+                    return;
                 }
-                RValType::Class(ty) => {
-                    let rhs_ty = unsafe { ty.as_ref() };
-
-                    if lhs_ty as *const Type != rhs_ty {
-                        let mut base = lhs_ty.extends;
-                        let mut castable = false;
-                        while let Some(base_ref) = unsafe { base.as_ref() } {
-                            if base == rhs_ty {
-                                castable = true;
-                                break;
-                            }
-                            base = base_ref;
-                        }
-
-                        if !castable {
-                            panic!("cast from {} to {} is not allowed", lhs_rval_ty, to_type);
-                        }
-                    }
+            }
+            builder.add_inst(if *to == RValType::F32 {
+                Inst::ConvR4
+            } else {
+                Inst::ConvR8
+            });
+        }
+        _ if from.is_float() => {
+            builder.add_inst(if is_checked {
+                conv_ovf_inst(to, false)
+            } else {
+                conv_inst(to)
+            });
+        }
+        _ if is_widening(from, to) || (!is_checked && int_info(to).0 > 32) => {
+            // small ints are already extended to i32 on stack.
+            // Extension to 64-bit int depends on the signedness of from, like widening
+            let is_signed = int_info(from).2;
+            match to {
+                RValType::I64 | RValType::U64 => {
+                    builder.add_inst(if is_signed {
+                        Inst::ConvI8
+                    } else {
+                        Inst::ConvU8
+                    });
+                }
+                RValType::ISize | RValType::USize => {
+                    builder.add_inst(if is_signed { Inst::ConvI } else { Inst::ConvU });
                 }
+                _ => {}
             }
         }
-        RValType::ByRef(_) => unimplemented!(),
-        RValType::Array(_) => unimplemented!(),
+        _ if is_checked => {
+            let is_un = !int_info(from).2;
+            builder.add_inst(conv_ovf_inst(to, is_un));
+        }
+        _ => {
+            builder.add_inst(conv_inst(to));
+        }
     }
+}
 
-    ValType::RVal(to_type)
+/// Whether from can be implicitly viewed as to
+///
+/// Generic params are castable to their constraints
+pub fn is_castable(ctx: &CodeGenCtx, from: &RValType, to: &RValType) -> bool {
+    if from == to {
+        return true;
+    }
+    match from {
+        RValType::Class(ty) | RValType::GenericInst(true, ty, _) => {
+            if let RValType::Class(to_ty) = to {
+                if unsafe { ty.as_ref() }.implements(to_ty.as_ptr()) {
+                    return true;
+                }
+            }
+            let mut base = unsafe { ty.as_ref().extends };
+            while let Some(base_ref) = unsafe { base.as_ref() } {
+                if let RValType::Class(to_ty) = to {
+                    if ptr::eq(base, to_ty.as_ptr()) {
+                        return true;
+                    }
+                }
+                base = base_ref.extends;
+            }
+            false
+        }
+        RValType::Var(idx) => ctx.class.generic_params[*idx as usize]
+            .constraints
+            .iter()
+            .any(|constraint| is_castable(ctx, constraint, to)),
+        RValType::MVar(idx) => ctx.method.generic_params[*idx as usize]
+            .constraints
+            .iter()
+            .any(|constraint| is_castable(ctx, constraint, to)),
+        _ => false,
+    }
 }
@@ -1,3 +1,4 @@
+use super::super::interpreter::ConstVal;
 use super::super::{CodeGenCtx, RValType, ValType};
 
 use xir::Inst;
@@ -9,9 +10,47 @@ pub fn gen_bool(ctx: &CodeGenCtx, val: bool) -> ValType {
     ValType::RVal(RValType::Bool)
 }
 
-pub fn gen_int(ctx: &CodeGenCtx, val: i32) -> ValType {
-    ctx.method_builder.borrow_mut().add_inst_ldc(val);
-    ValType::RVal(RValType::I32)
+pub fn gen_int(ctx: &CodeGenCtx, val: i128, ty: RValType) -> ValType {
+    let mut builder = ctx.method_builder.borrow_mut();
+    match ty {
+        RValType::I64 | RValType::U64 => {
+            builder.add_inst(Inst::LdCI8(val as i64));
+        }
+        RValType::ISize | RValType::USize => {
+            // there is no ldc for native int
+            if val >= i32::MIN as i128 && val <= i32::MAX as i128 {
+                builder.add_inst_ldc(val as i32);
+            } else {
+                builder.add_inst(Inst::LdCI8(val as i64));
+            }
+            builder.add_inst(if let RValType::ISize = ty {
+                Inst::ConvI
+            } else {
+                Inst::ConvU
+            });
+        }
+        _ => {
+            // u32 is loaded as the i32 with the same bits
+            builder.add_inst_ldc(val as i32);
+        }
+    }
+    ValType::RVal(ty)
+}
+
+pub fn gen_float(ctx: &CodeGenCtx, val: f64, ty: RValType) -> ValType {
+    ctx.method_builder
+        .borrow_mut()
+        .add_inst(if let RValType::F32 = ty {
+            Inst::LdCR4(val as f32)
+        } else {
+            Inst::LdCR8(val)
+        });
+    ValType::RVal(ty)
+}
+
+pub fn gen_char(ctx: &CodeGenCtx, val: u32) -> ValType {
+    ctx.method_builder.borrow_mut().add_inst_ldc(val as i32);
+    ValType::RVal(RValType::Char)
 }
 
 pub fn gen_string(ctx: &CodeGenCtx, val: &str) -> ValType {
@@ -25,3 +64,13 @@ pub fn gen_string(ctx: &CodeGenCtx, val: &str) -> ValType {
 pub fn gen_none() -> ValType {
     ValType::RVal(RValType::Void)
 }
+
+pub fn gen_const(ctx: &CodeGenCtx, val: &ConstVal) -> ValType {
+    match val {
+        ConstVal::Bool(val) => gen_bool(ctx, *val),
+        ConstVal::Int(val, ty) => gen_int(ctx, *val, ty.clone()),
+        ConstVal::Float(val, ty) => gen_float(ctx, *val, ty.clone()),
+        ConstVal::Char(val) => gen_char(ctx, *val),
+        ConstVal::String(val) => gen_string(ctx, val),
+    }
+}
@@ -1,37 +1,78 @@
-use super::super::super::ast::AST;
-use super::super::{CodeGenCtx, LoopCtx, LoopType, RValType, ValExpectation, ValType};
+use super::super::super::ast::{ASTKind, AST};
+use super::super::super::diagnostic::ErrCode;
+use super::super::super::util::Span;
+use super::super::{
+    BasicBlock, CodeGenCtx, LLCursor, LoopCtx, LoopType, RValType, TryBlockType, ValExpectation,
+    ValType,
+};
 use super::gen;
 
-pub fn gen_continue(ctx: &CodeGenCtx) -> ValType {
+use xir::attrib::LocalAttrib;
+use xir::Inst;
+
+/// br to target, or leave if it jumps out of try blocks or catch handlers
+fn gen_jump_out(
+    ctx: &CodeGenCtx,
+    span: Span,
+    target: LLCursor<BasicBlock>,
+    try_depth: usize,
+    with_value: bool,
+) {
+    let try_ctx = ctx.try_ctx.borrow();
+    if try_ctx.len() > try_depth {
+        if try_ctx[try_depth..].contains(&TryBlockType::Finally) {
+            ctx.error(
+                ErrCode::InvalidExpr,
+                span,
+                "Cannot jump out of finally block",
+            );
+        }
+        if with_value {
+            // leave empties the eval stack
+            ctx.error(
+                ErrCode::InvalidExpr,
+                span,
+                "Cannot break with value out of try block or catch handler",
+            );
+        }
+        ctx.method_builder.borrow_mut().add_leave(target);
+    } else {
+        ctx.method_builder.borrow_mut().add_br(target);
+    }
+}
+
+pub fn gen_continue(ctx: &CodeGenCtx, span: Span) -> ValType {
     if let Some(l) = ctx.loop_ctx.borrow_mut().last_mut() {
-        ctx.method_builder
-            .borrow_mut()
-            .add_br(l.break_target.clone());
+        gen_jump_out(ctx, span, l.continue_target.clone(), l.try_depth, false);
     } else {
-        panic!("Break not in a loop expr");
+        ctx.error(ErrCode::InvalidExpr, span, "Continue not in a loop expr");
     }
     ValType::RVal(RValType::Void)
 }
 
-pub fn gen_break(ctx: &CodeGenCtx, v: &AST) -> ValType {
-    if let AST::None = v {
+pub fn gen_break(ctx: &CodeGenCtx, span: Span, v: &AST) -> ValType {
+    if let ASTKind::None = v.kind {
         if let Some(l) = ctx.loop_ctx.borrow_mut().last_mut() {
             if let LoopType::Loop(ty) = &mut l.ty {
                 match ty {
-                            RValType::Void => {}
-                            RValType::Never => {
-                                l.ty = LoopType::Loop(RValType::Void);
-                            },
-                            _ => panic!("Loop return type mismatch. Previously break with {} but later break with {}", ty, RValType::Void),
-                        };
-            } else {
-                unimplemented!();
+                    RValType::Void => {}
+                    RValType::Never => {
+                        l.ty = LoopType::Loop(RValType::Void);
+                    }
+                    _ => ctx.error(
+                        ErrCode::TypeMismatch,
+                        span,
+                        format!(
+                            "Loop return type mismatch. Previously break with {} but later break with {}",
+                            ctx.ty_name(ty),
+                            ctx.ty_name(&RValType::Void)
+                        ),
+                    ),
+                };
             }
-            ctx.method_builder
-                .borrow_mut()
-                .add_br(l.break_target.clone());
+            gen_jump_out(ctx, span, l.break_target.clone(), l.try_depth, false);
         } else {
-            panic!("Break not in a loop expr");
+            ctx.error(ErrCode::InvalidExpr, span, "Break not in a loop expr");
         }
 
         ValType::RVal(RValType::Void)
@@ -47,21 +88,31 @@ pub fn gen_break(ctx: &CodeGenCtx, v: &AST) -> ValType {
                         }
                         _ => {
                             if v_ty_ != ty {
-                                panic!("Loop return type mismatch. Previously break with {} but later break with {}", ty, v_ty_);
+                                ctx.error(
+                                    ErrCode::TypeMismatch,
+                                    span,
+                                    format!(
+                                        "Loop return type mismatch. Previously break with {} but later break with {}",
+                                        ctx.ty_name(ty),
+                                        ctx.ty_name(v_ty_)
+                                    ),
+                                );
                             }
                         }
                     };
                 } else {
-                    panic!("break with expr is only allowed in loop");
+                    ctx.error(
+                        ErrCode::InvalidExpr,
+                        span,
+                        "break with expr is only allowed in loop",
+                    );
                 }
-                ctx.method_builder
-                    .borrow_mut()
-                    .add_br(l.break_target.clone());
+                gen_jump_out(ctx, span, l.break_target.clone(), l.try_depth, true);
             } else {
-                panic!("Break not in a loop expr");
+                ctx.error(ErrCode::InvalidExpr, span, "Break not in a loop expr");
             }
         } else {
-            panic!();
+            ctx.error(ErrCode::InvalidExpr, v.span, "Break value must be an rval");
         }
 
         v_ty
@@ -78,6 +129,7 @@ pub fn gen_loop(ctx: &CodeGenCtx, body: &AST, expectation: ValExpectation) -> RV
             ty: LoopType::Loop(RValType::Never),
             continue_target: body_bb,
             break_target: after_bb,
+            try_depth: ctx.try_ctx.borrow().len(),
         });
     }
 
@@ -88,6 +140,7 @@ pub fn gen_loop(ctx: &CodeGenCtx, body: &AST, expectation: ValExpectation) -> RV
             ty,
             continue_target: body_bb,
             break_target: after_bb,
+            ..
         } = ctx.loop_ctx.borrow_mut().pop().unwrap();
         let mut builder = ctx.method_builder.borrow_mut();
 
@@ -105,3 +158,218 @@ pub fn gen_loop(ctx: &CodeGenCtx, body: &AST, expectation: ValExpectation) -> RV
         }
     }
 }
+
+/// Gen body of while or for loop, the next iteration starts at continue_target
+fn gen_loop_body(
+    ctx: &CodeGenCtx,
+    body: &AST,
+    continue_target: LLCursor<BasicBlock>,
+    break_target: LLCursor<BasicBlock>,
+) {
+    ctx.loop_ctx.borrow_mut().push(LoopCtx {
+        ty: LoopType::For,
+        continue_target,
+        break_target,
+        try_depth: ctx.try_ctx.borrow().len(),
+    });
+
+    let body_ty = gen(ctx, body, ValExpectation::RVal);
+
+    let LoopCtx {
+        continue_target,
+        break_target: after_bb,
+        ..
+    } = ctx.loop_ctx.borrow_mut().pop().unwrap();
+    let mut builder = ctx.method_builder.borrow_mut();
+
+    if !builder.cur_bb_last_is_branch() {
+        if !matches!(
+            body_ty,
+            ValType::RVal(RValType::Void) | ValType::RVal(RValType::Never)
+        ) {
+            // value of body is dropped
+            builder.add_inst(Inst::Pop);
+        }
+        builder.add_br(continue_target);
+    }
+
+    builder.set_cur_bb(after_bb);
+}
+
+fn gen_cond(ctx: &CodeGenCtx, cond: &AST) {
+    let cond_ty = gen(ctx, cond, ValExpectation::RVal);
+    match cond_ty.expect_rval() {
+        RValType::Bool => {}
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            cond.span,
+            format!("Cond should be bool but found {}", ctx.ty_name(&ty)),
+        ),
+    }
+}
+
+pub fn gen_while(ctx: &CodeGenCtx, cond: &AST, body: &AST) -> RValType {
+    let cond_bb;
+    let body_bb;
+    let after_bb;
+    {
+        let mut builder = ctx.method_builder.borrow_mut();
+        after_bb = builder.insert_after_cur();
+        body_bb = builder.insert_after_cur();
+        cond_bb = builder.insert_after_cur();
+        builder.set_cur_bb(cond_bb.clone());
+    }
+
+    gen_cond(ctx, cond);
+
+    ctx.method_builder
+        .borrow_mut()
+        .add_brfalse(after_bb.clone())
+        .set_cur_bb(body_bb);
+
+    gen_loop_body(ctx, body, cond_bb, after_bb);
+
+    RValType::Void
+}
+
+pub fn gen_for(ctx: &CodeGenCtx, id: &str, iter: &AST, body: &AST) -> RValType {
+    // scope of loop var and hidden locals
+    ctx.push_scope();
+
+    let cond_bb;
+    let body_bb;
+    let next_bb;
+    let after_bb;
+
+    if let ASTKind::Range(start, end) = &iter.kind {
+        // for i in start..end, bounds can be any integer type but they must be the same
+        let ty = gen(ctx, start, ValExpectation::RVal).expect_rval();
+        if !ty.is_int() {
+            ctx.error(
+                ErrCode::TypeMismatch,
+                start.span,
+                format!(
+                    "Range bound should be an integer but found {}",
+                    ctx.ty_name(&ty)
+                ),
+            );
+        }
+        let end_ty = gen(ctx, end, ValExpectation::RVal).expect_rval();
+        if end_ty != ty {
+            ctx.error(
+                ErrCode::TypeMismatch,
+                end.span,
+                format!(
+                    "Range end should be {} as the start but found {}",
+                    ctx.ty_name(&ty),
+                    ctx.ty_name(&end_ty)
+                ),
+            );
+        }
+
+        let mut locals = ctx.locals.borrow_mut();
+        let end_idx = locals.add_tmp(ty.clone(), LocalAttrib::default(), true);
+        let i_idx = locals.add(id, ty.clone(), LocalAttrib::default(), true);
+        drop(locals);
+
+        let mut builder = ctx.method_builder.borrow_mut();
+        builder.add_inst_stloc(end_idx).add_inst_stloc(i_idx);
+
+        after_bb = builder.insert_after_cur();
+        next_bb = builder.insert_after_cur();
+        body_bb = builder.insert_after_cur();
+        cond_bb = builder.insert_after_cur();
+
+        // i < end
+        builder.set_cur_bb(cond_bb.clone());
+        builder
+            .add_inst_ldloc(i_idx)
+            .add_inst_ldloc(end_idx)
+            .add_inst(if ty.is_unsigned() {
+                Inst::CLtUn
+            } else {
+                Inst::CLt
+            })
+            .add_brfalse(after_bb.clone());
+
+        // i = i + 1, it never overflows since i < end
+        // i8, u8, i16 and u16 are i32 on eval stack and are truncated by stloc
+        builder.set_cur_bb(next_bb.clone());
+        builder.add_inst_ldloc(i_idx);
+        match ty {
+            RValType::I64 | RValType::U64 => builder.add_inst(Inst::LdCI8(1)),
+            RValType::ISize => builder.add_inst_ldc(1).add_inst(Inst::ConvI),
+            RValType::USize => builder.add_inst_ldc(1).add_inst(Inst::ConvU),
+            _ => builder.add_inst_ldc(1),
+        };
+        builder
+            .add_inst(Inst::Add)
+            .add_inst_stloc(i_idx)
+            .add_br(cond_bb);
+
+        builder.set_cur_bb(body_bb);
+    } else {
+        // for x in arr
+        let arr_ty = gen(ctx, iter, ValExpectation::RVal).expect_rval();
+        let ele_ty = if let RValType::Array(ele_ty) = &arr_ty {
+            ele_ty.as_ref().clone()
+        } else {
+            ctx.error(
+                ErrCode::TypeMismatch,
+                iter.span,
+                format!(
+                    "Cannot iterate over {}, only array is iterable",
+                    ctx.ty_name(&arr_ty)
+                ),
+            );
+        };
+
+        let mut locals = ctx.locals.borrow_mut();
+        let arr_idx = locals.add_tmp(arr_ty, LocalAttrib::default(), true);
+        let idx_idx = locals.add_tmp(RValType::I32, LocalAttrib::default(), true);
+        let x_idx = locals.add(id, ele_ty.clone(), LocalAttrib::default(), true);
+        drop(locals);
+
+        let mut builder = ctx.method_builder.borrow_mut();
+        builder
+            .add_inst_stloc(arr_idx)
+            .add_inst_ldc(0)
+            .add_inst_stloc(idx_idx);
+
+        after_bb = builder.insert_after_cur();
+        next_bb = builder.insert_after_cur();
+        body_bb = builder.insert_after_cur();
+        cond_bb = builder.insert_after_cur();
+
+        // idx < arr.len
+        builder.set_cur_bb(cond_bb.clone());
+        builder
+            .add_inst_ldloc(idx_idx)
+            .add_inst_ldloc(arr_idx)
+            .add_inst(Inst::LdLen)
+            .add_inst(Inst::CLt)
+            .add_brfalse(after_bb.clone());
+
+        // idx = idx + 1
+        builder.set_cur_bb(next_bb.clone());
+        builder
+            .add_inst_ldloc(idx_idx)
+            .add_inst_ldc(1)
+            .add_inst(Inst::Add)
+            .add_inst_stloc(idx_idx)
+            .add_br(cond_bb);
+
+        // x = arr[idx]
+        builder.set_cur_bb(body_bb);
+        builder
+            .add_inst_ldloc(arr_idx)
+            .add_inst_ldloc(idx_idx)
+            .add_ldelem(&ele_ty, &ctx.module.builder)
+            .add_inst_stloc(x_idx);
+    }
+
+    gen_loop_body(ctx, body, next_bb, after_bb);
+
+    ctx.pop_scope();
+    RValType::Void
+}
@@ -0,0 +1,511 @@
+use super::super::super::ast::{ASTKind, ASTType, AST};
+use super::super::super::diagnostic::{Diagnostic, ErrCode};
+use super::super::super::mod_mgr::{Type, ENUM_TAG_FIELD};
+use super::super::super::util::{IItemPath, ItemPathBuf, Span};
+use super::super::{
+    BasicBlock, CodeGenCtx, LLCursor, MethodBuilder, RValType, ValExpectation, ValType,
+};
+use super::gen;
+
+use xir::attrib::LocalAttrib;
+use xir::tok::to_tok;
+use xir::Inst;
+
+use std::ptr::NonNull;
+
+/// Token and type of an instance field of enum
+fn enum_field(ctx: &CodeGenCtx, ty: &Type, name: &str) -> (u32, RValType) {
+    let f = ty.fields.get(name).unwrap();
+    let mut builder = ctx.module.builder.borrow_mut();
+    let sig = builder.add_field_sig(&f.ty);
+    let (f_idx, tok_tag) = builder.add_const_inst_member(ty, &[], name, sig);
+    (to_tok(f_idx, tok_tag), f.ty.clone())
+}
+
+/// Load the discriminant of enum stored in local val_idx
+pub fn gen_ld_tag(ctx: &CodeGenCtx, ty: &Type, val_idx: u16) {
+    let (tag_tok, _) = enum_field(ctx, ty, ENUM_TAG_FIELD);
+    let mut method_builder = ctx.method_builder.borrow_mut();
+    method_builder.add_inst_ldloca(val_idx);
+    method_builder.add_inst(Inst::LdFld(tag_tok));
+}
+
+/// Create a variant of enum ty, the result is left on eval stack
+pub fn gen_new_variant(
+    ctx: &CodeGenCtx,
+    span: Span,
+    ty: NonNull<Type>,
+    variant_idx: usize,
+    args: &[Box<AST>],
+) -> RValType {
+    let ty_ref = unsafe { ty.as_ref() };
+    let variant = &ty_ref.variants[variant_idx];
+    if args.len() != variant.fields.len() {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            span,
+            format!(
+                "Variant {}::{} expects {} args but {} are given",
+                ty_ref,
+                variant.name,
+                variant.fields.len(),
+                args.len()
+            ),
+        );
+    }
+
+    let ret = RValType::Value(ty);
+    let tmp = ctx
+        .locals
+        .borrow_mut()
+        .add_tmp(ret.clone(), LocalAttrib::default(), true);
+
+    let (ty_idx, ty_tag) = ctx
+        .module
+        .builder
+        .borrow_mut()
+        .add_const_class(ty_ref.modname(), &ty_ref.name);
+    let (tag_tok, _) = enum_field(ctx, ty_ref, ENUM_TAG_FIELD);
+    {
+        let mut method_builder = ctx.method_builder.borrow_mut();
+        method_builder.add_inst_ldloca(tmp);
+        method_builder.add_inst(Inst::InitObj(to_tok(ty_idx, ty_tag.to_tok_tag())));
+        method_builder.add_inst_ldloca(tmp);
+        method_builder.add_inst_ldc(variant.discriminant);
+        method_builder.add_inst(Inst::StFld(tag_tok));
+    }
+
+    for (arg, field) in args.iter().zip(variant.fields.iter()) {
+        let (f_tok, f_ty) = enum_field(ctx, ty_ref, field);
+        ctx.method_builder.borrow_mut().add_inst_ldloca(tmp);
+        let arg_ty = gen(ctx, arg, ValExpectation::RVal).expect_rval();
+        if arg_ty != f_ty {
+            ctx.error(
+                ErrCode::TypeMismatch,
+                arg.span,
+                format!(
+                    "Cannot use {} as payload {} of {}::{}",
+                    ctx.ty_name(&arg_ty),
+                    ctx.ty_name(&f_ty),
+                    ty_ref,
+                    variant.name
+                ),
+            );
+        }
+        ctx.method_builder.borrow_mut().add_inst(Inst::StFld(f_tok));
+    }
+
+    ctx.method_builder.borrow_mut().add_inst_ldloc(tmp);
+    ret
+}
+
+/// Enum and variant index referred by the path of a variant pattern
+fn resolve_variant(ctx: &CodeGenCtx, span: Span, path: &ItemPathBuf) -> (NonNull<Type>, usize) {
+    if path.len() < 2 {
+        ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            format!("Expect a variant like Enum::{} in pattern", path),
+        );
+    }
+    let enum_path = path.range(0, path.len() - 1).to_owned();
+    let ty = match ctx.get_ty(&ASTType::UsrType(enum_path), span) {
+        RValType::Value(ty) if unsafe { ty.as_ref() }.is_enum() => ty,
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            span,
+            format!("{} is not an enum", ctx.ty_name(&ty)),
+        ),
+    };
+    let (id, _) = path.get_self().unwrap();
+    let ty_ref = unsafe { ty.as_ref() };
+    if let Some(idx) = ty_ref.variants.iter().position(|v| v.name == id) {
+        (ty, idx)
+    } else {
+        ctx.error(
+            ErrCode::Unresolved,
+            span,
+            format!("No variant {} in enum {}", id, ty_ref),
+        );
+    }
+}
+
+fn check_variant_arity(
+    ctx: &CodeGenCtx,
+    span: Span,
+    ty: &Type,
+    idx: usize,
+    sub_patterns: &[Box<AST>],
+) {
+    let variant = &ty.variants[idx];
+    if sub_patterns.len() != variant.fields.len() {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            span,
+            format!(
+                "Variant {}::{} has {} fields but pattern has {}",
+                ty,
+                variant.name,
+                variant.fields.len(),
+                sub_patterns.len()
+            ),
+        );
+    }
+}
+
+/// Whether pattern matches any value of type ty
+fn is_irrefutable(ctx: &CodeGenCtx, pattern: &AST, ty: &RValType) -> bool {
+    match &pattern.kind {
+        ASTKind::Id(_) => true,
+        ASTKind::VariantPattern(path, sub_patterns) => {
+            let (enum_ty, idx) = resolve_variant(ctx, pattern.span, path);
+            let enum_ref = unsafe { enum_ty.as_ref() };
+            &RValType::Value(enum_ty) == ty
+                && enum_ref.variants.len() == 1
+                && covers_variant(ctx, enum_ref, idx, sub_patterns)
+        }
+        _ => false,
+    }
+}
+
+/// Whether sub patterns match all payloads of a variant
+fn covers_variant(ctx: &CodeGenCtx, ty: &Type, idx: usize, sub_patterns: &[Box<AST>]) -> bool {
+    let variant = &ty.variants[idx];
+    sub_patterns.len() == variant.fields.len()
+        && sub_patterns
+            .iter()
+            .zip(variant.fields.iter())
+            .all(|(p, f)| is_irrefutable(ctx, p, &ty.fields.get(f).unwrap().ty))
+}
+
+/// Rows of a pattern matrix, None is a wildcard
+type PatternRow<'a> = Vec<Option<&'a AST>>;
+
+/// First pattern of row, bindings are treated as wildcards
+fn row_head<'a>(row: &PatternRow<'a>) -> Option<&'a AST> {
+    match row[0] {
+        Some(AST {
+            kind: ASTKind::Id(_),
+            ..
+        }) => None,
+        head => head,
+    }
+}
+
+/// Search a value that is not matched by any row.
+///
+/// Return the patterns of such value (one for each column) or None if rows are exhaustive
+fn find_missing<'a>(
+    ctx: &CodeGenCtx,
+    rows: &[PatternRow<'a>],
+    tys: &[RValType],
+) -> Option<Vec<String>> {
+    let (ty, rest_tys) = if let Some(split) = tys.split_first() {
+        split
+    } else {
+        // no column left, exhaustive if any row is left
+        return if rows.is_empty() { Some(vec![]) } else { None };
+    };
+
+    match ty {
+        RValType::Bool => {
+            for b in [true, false].iter() {
+                let spec: Vec<PatternRow<'a>> = rows
+                    .iter()
+                    .filter(|row| match row_head(row) {
+                        None => true,
+                        Some(p) => matches!(&p.kind, ASTKind::Bool(v) if v == b),
+                    })
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                if let Some(mut missing) = find_missing(ctx, &spec, rest_tys) {
+                    missing.insert(0, b.to_string());
+                    return Some(missing);
+                }
+            }
+            None
+        }
+        RValType::Value(enum_ty) if unsafe { enum_ty.as_ref() }.is_enum() => {
+            let enum_ref = unsafe { enum_ty.as_ref() };
+            for (idx, variant) in enum_ref.variants.iter().enumerate() {
+                let mut spec: Vec<PatternRow<'a>> = vec![];
+                for row in rows.iter() {
+                    let mut spec_row: PatternRow<'a> = match row_head(row) {
+                        None => vec![None; variant.fields.len()],
+                        Some(p) => match &p.kind {
+                            ASTKind::VariantPattern(path, sub_patterns) => {
+                                if resolve_variant(ctx, p.span, path) != (*enum_ty, idx) {
+                                    continue;
+                                }
+                                check_variant_arity(ctx, p.span, enum_ref, idx, sub_patterns);
+                                sub_patterns.iter().map(|sub| Some(sub.as_ref())).collect()
+                            }
+                            _ => continue,
+                        },
+                    };
+                    spec_row.extend_from_slice(&row[1..]);
+                    spec.push(spec_row);
+                }
+                let mut spec_tys: Vec<RValType> = variant
+                    .fields
+                    .iter()
+                    .map(|f| enum_ref.fields.get(f).unwrap().ty.clone())
+                    .collect();
+                spec_tys.extend_from_slice(rest_tys);
+
+                if let Some(mut missing) = find_missing(ctx, &spec, &spec_tys) {
+                    let subs: Vec<String> = missing.drain(..variant.fields.len()).collect();
+                    missing.insert(
+                        0,
+                        if subs.is_empty() {
+                            format!("{}::{}", enum_ref.name, variant.name)
+                        } else {
+                            format!("{}::{}({})", enum_ref.name, variant.name, subs.join(", "))
+                        },
+                    );
+                    return Some(missing);
+                }
+            }
+            None
+        }
+        _ => {
+            // infinite values, only wildcards can cover all of them
+            let default: Vec<PatternRow<'a>> = rows
+                .iter()
+                .filter(|row| row_head(row).is_none())
+                .map(|row| row[1..].to_vec())
+                .collect();
+            find_missing(ctx, &default, rest_tys).map(|mut missing| {
+                missing.insert(0, String::from("_"));
+                missing
+            })
+        }
+    }
+}
+
+/// Report values of ty that are not matched by any pattern
+fn check_exhaustive(ctx: &CodeGenCtx, span: Span, ty: &RValType, patterns: &[&AST]) {
+    let rows: Vec<PatternRow> = patterns.iter().map(|p| vec![Some(*p)]).collect();
+    if let Some(missing) = find_missing(ctx, &rows, std::slice::from_ref(ty)) {
+        Diagnostic::error(
+            ErrCode::NonExhaustive,
+            format!("Non-exhaustive patterns: {} not covered", missing[0]),
+        )
+        .with_loc(&ctx.module.fpath, span)
+        .with_note("add arms for the missing values or a wildcard arm `_ =>`")
+        .abort();
+    }
+}
+
+/// Test pattern against value in local val_idx and bind variables in pattern.
+///
+/// Branch to fail_bb if pattern is not matched.
+/// fail_bb is None if pattern must be matched, i.e. the last arm of an exhaustive match
+fn gen_pattern(
+    ctx: &CodeGenCtx,
+    pattern: &AST,
+    val_idx: u16,
+    val_ty: &RValType,
+    fail_bb: &Option<LLCursor<BasicBlock>>,
+    bound: &mut Vec<String>,
+) {
+    match &pattern.kind {
+        ASTKind::Id(id) => {
+            if id == "_" {
+                return;
+            }
+            if bound.contains(id) {
+                ctx.error(
+                    ErrCode::Duplicated,
+                    pattern.span,
+                    format!(
+                        "Identifier {} is bound more than once in the same pattern",
+                        id
+                    ),
+                );
+            }
+            bound.push(id.to_owned());
+            let idx = ctx
+                .locals
+                .borrow_mut()
+                .add(id, val_ty.clone(), LocalAttrib::default(), true);
+            let mut method_builder = ctx.method_builder.borrow_mut();
+            method_builder.add_inst_ldloc(val_idx);
+            method_builder.add_inst_stloc(idx);
+        }
+        ASTKind::Bool(_) | ASTKind::Int(_, _) | ASTKind::Char(_) => {
+            let lit_ty = match &pattern.kind {
+                ASTKind::Bool(_) => RValType::Bool,
+                ASTKind::Int(_, ty) => RValType::from_lit(ty),
+                _ => RValType::Char,
+            };
+            if &lit_ty != val_ty {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    pattern.span,
+                    format!(
+                        "Cannot match {} with {} pattern",
+                        ctx.ty_name(val_ty),
+                        ctx.ty_name(&lit_ty)
+                    ),
+                );
+            }
+            if let Some(fail_bb) = fail_bb {
+                ctx.method_builder.borrow_mut().add_inst_ldloc(val_idx);
+                gen(ctx, pattern, ValExpectation::RVal);
+                gen_test_branch(&mut ctx.method_builder.borrow_mut(), fail_bb);
+            }
+        }
+        ASTKind::VariantPattern(path, sub_patterns) => {
+            let (enum_ty, idx) = resolve_variant(ctx, pattern.span, path);
+            let enum_ref = unsafe { enum_ty.as_ref() };
+            if &RValType::Value(enum_ty) != val_ty {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    pattern.span,
+                    format!(
+                        "Cannot match {} with {} pattern",
+                        ctx.ty_name(val_ty),
+                        enum_ref
+                    ),
+                );
+            }
+            check_variant_arity(ctx, pattern.span, enum_ref, idx, sub_patterns);
+            let variant = &enum_ref.variants[idx];
+
+            if let Some(fail_bb) = fail_bb {
+                gen_ld_tag(ctx, enum_ref, val_idx);
+                let mut method_builder = ctx.method_builder.borrow_mut();
+                method_builder.add_inst_ldc(variant.discriminant);
+                gen_test_branch(&mut method_builder, fail_bb);
+            }
+
+            for (sub, field) in sub_patterns.iter().zip(variant.fields.iter()) {
+                if let ASTKind::Id(id) = &sub.kind {
+                    if id == "_" {
+                        continue;
+                    }
+                }
+                let (f_tok, f_ty) = enum_field(ctx, enum_ref, field);
+                let sub_idx =
+                    ctx.locals
+                        .borrow_mut()
+                        .add_tmp(f_ty.clone(), LocalAttrib::default(), true);
+                {
+                    let mut method_builder = ctx.method_builder.borrow_mut();
+                    method_builder.add_inst_ldloca(val_idx);
+                    method_builder.add_inst(Inst::LdFld(f_tok));
+                    method_builder.add_inst_stloc(sub_idx);
+                }
+                gen_pattern(ctx, sub, sub_idx, &f_ty, fail_bb, bound);
+            }
+        }
+        ASTKind::String(_) => ctx.error(
+            ErrCode::InvalidExpr,
+            pattern.span,
+            "String literal cannot be used as a pattern",
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// Compare the two values on eval stack, branch to fail_bb if not equal
+/// and continue in a new bb otherwise
+fn gen_test_branch(method_builder: &mut MethodBuilder, fail_bb: &LLCursor<BasicBlock>) {
+    method_builder.add_inst(Inst::CEq);
+    method_builder.add_brfalse(fail_bb.clone());
+    let next_bb = method_builder.insert_after_cur();
+    method_builder.set_cur_bb(next_bb);
+}
+
+pub fn gen_match(
+    ctx: &CodeGenCtx,
+    span: Span,
+    val: &AST,
+    arms: &[Box<AST>],
+    expectation: ValExpectation,
+) -> RValType {
+    let val_ty = gen(ctx, val, ValExpectation::RVal).expect_rval();
+    ctx.push_scope();
+    let val_idx = ctx
+        .locals
+        .borrow_mut()
+        .add_tmp(val_ty.clone(), LocalAttrib::default(), true);
+    ctx.method_builder.borrow_mut().add_inst_stloc(val_idx);
+
+    let arms: Vec<(&AST, &AST)> = arms
+        .iter()
+        .map(|arm| {
+            if let ASTKind::MatchArm(pattern, body) = &arm.kind {
+                (pattern.as_ref(), body.as_ref())
+            } else {
+                unreachable!()
+            }
+        })
+        .collect();
+    let patterns: Vec<&AST> = arms.iter().map(|(p, _)| *p).collect();
+    check_exhaustive(ctx, span, &val_ty, &patterns);
+
+    // arms after an irrefutable arm are never reached
+    let arm_num = if let Some(i) = patterns
+        .iter()
+        .position(|p| is_irrefutable(ctx, p, &val_ty))
+    {
+        for (pattern, _) in arms[i + 1..].iter() {
+            ctx.module.warning(pattern.span, "Unreachable match arm");
+        }
+        i + 1
+    } else {
+        arms.len()
+    };
+
+    let after_bb = ctx.method_builder.borrow_mut().insert_after_cur();
+    let mut ret: Option<RValType> = None;
+    for (i, (pattern, body)) in arms[..arm_num].iter().enumerate() {
+        ctx.push_scope();
+        // the last arm must match since patterns are exhaustive
+        let next_bb = if i + 1 == arm_num {
+            None
+        } else {
+            Some(ctx.method_builder.borrow_mut().insert_after_cur())
+        };
+
+        gen_pattern(ctx, pattern, val_idx, &val_ty, &next_bb, &mut vec![]);
+        let body_v = gen(ctx, body, expectation);
+
+        let mut method_builder = ctx.method_builder.borrow_mut();
+        if !method_builder.cur_bb_last_is_branch() {
+            if let ValType::RVal(body_ty) = body_v {
+                if !matches!(body_ty, RValType::Never) {
+                    if let Some(ret) = &ret {
+                        if ret != &body_ty {
+                            ctx.error(
+                                ErrCode::TypeMismatch,
+                                body.span,
+                                format!(
+                                    "Mismatch arm type ({}) and previous arm type ({})",
+                                    ctx.ty_name(&body_ty),
+                                    ctx.ty_name(ret)
+                                ),
+                            );
+                        }
+                    } else {
+                        ret = Some(body_ty);
+                    }
+                }
+            }
+            method_builder.add_br(after_bb.clone());
+        }
+        drop(method_builder);
+
+        ctx.pop_scope();
+        if let Some(next_bb) = next_bb {
+            ctx.method_builder.borrow_mut().set_cur_bb(next_bb);
+        }
+    }
+
+    ctx.method_builder.borrow_mut().set_cur_bb(after_bb);
+    ctx.pop_scope();
+    // all arms diverge
+    ret.unwrap_or(RValType::Never)
+}
@@ -4,13 +4,16 @@ mod call;
 mod cast;
 mod literal;
 mod loop_expr;
+mod match_expr;
 mod op;
+mod try_expr;
+mod tuple_expr;
 
-use super::super::ast::{ASTType, AST};
-use super::super::util::IItemPath;
-use op::BinOp;
-// use super::interpreter::constant_folding;
+use super::super::ast::{ASTKind, ASTType, AST};
+use super::super::diagnostic::ErrCode;
+use super::super::util::{IItemPath, Span};
 use super::{CodeGenCtx, Field, Method, Module, RValType, SymType, Type, ValExpectation, ValType};
+use op::BinOp;
 
 use xir::attrib::*;
 use xir::inst::Inst;
@@ -19,22 +22,37 @@ use xir::CTOR_NAME;
 
 use std::ptr::NonNull;
 
-pub fn gen_base_ctor(ctx: &CodeGenCtx, args: &Vec<Box<AST>>) {
+/// span is the location of the ctor
+pub fn gen_base_ctor(ctx: &CodeGenCtx, span: Span, args: &Vec<Box<AST>>) {
     let base = unsafe { ctx.class.extends.as_ref().unwrap() };
 
     // similar to gen_new
     let ctors = base.methods.get(CTOR_NAME).unwrap();
+    ctx.method_builder.borrow_mut().add_inst(Inst::LdArg0); // load self
     let args_ty: Vec<RValType> = args
         .iter()
         .map(|arg| gen(ctx, arg, ValExpectation::RVal).expect_rval())
         .collect();
 
-    let ctor = call::pick_method_from_refs(ctors, &args_ty);
+    let ctor = call::pick_method_from_refs(ctors, &[], &args_ty);
     let ctor = if let Some(ctor) = ctor {
         ctor
     } else {
-        panic!("Cannot find ctor");
+        ctx.error(
+            ErrCode::Unresolved,
+            span,
+            format!(
+                "Cannot find ctor of {} with param ({})",
+                base,
+                args_ty
+                    .iter()
+                    .map(|t| ctx.ty_name(t).to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        );
     };
+    ctx.check_method_access(span, ctor);
 
     let mut builder = ctx.module.builder.borrow_mut();
     let ctor_sig = builder.add_method_sig(true, &ctor.ps, &RValType::Void);
@@ -45,90 +63,147 @@ pub fn gen_base_ctor(ctx: &CodeGenCtx, args: &Vec<Box<AST>>) {
         ctor_sig,
     );
 
-    ctx.method_builder.borrow_mut().add_inst(Inst::LdArg0); // load self
     ctx.method_builder
         .borrow_mut()
         .add_inst(Inst::Call(to_tok(ctor_idx, tok_tag)));
 }
 
 pub fn gen(ctx: &CodeGenCtx, ast: &AST, expectation: ValExpectation) -> ValType {
-    /*
-    if ctx.mgr.cfg.optim >= 1 && ast.is_constant() {
-        return gen(ctx, &Box::new(constant_folding(ast)));
+    let span = ast.span;
+    match &ast.kind {
+        ASTKind::OpPos(_)
+        | ASTKind::OpNeg(_)
+        | ASTKind::OpLogNot(_)
+        | ASTKind::OpLogAnd(_, _)
+        | ASTKind::OpLogOr(_, _)
+        | ASTKind::OpBitNot(_)
+        | ASTKind::OpBitAnd(_, _)
+        | ASTKind::OpBitOr(_, _)
+        | ASTKind::OpBitXor(_, _)
+        | ASTKind::OpShl(_, _)
+        | ASTKind::OpShr(_, _)
+        | ASTKind::OpAdd(_, _)
+        | ASTKind::OpSub(_, _)
+        | ASTKind::OpMul(_, _)
+        | ASTKind::OpDiv(_, _)
+        | ASTKind::OpMod(_, _)
+        | ASTKind::OpEq(_, _)
+        | ASTKind::OpNe(_, _)
+        | ASTKind::OpGe(_, _)
+        | ASTKind::OpGt(_, _)
+        | ASTKind::OpLe(_, _)
+        | ASTKind::OpLt(_, _) => {
+            if let Some(ret) = op::gen_folded(ctx, ast) {
+                return ret;
+            }
+        }
+        _ => {}
     }
-    */
 
-    match ast {
-        AST::Block(children) => gen_block(ctx, children, expectation),
-        AST::ExprStmt(stmt) => gen_expr_stmt(ctx, stmt),
-        AST::If(cond, then, els) => {
+    match &ast.kind {
+        ASTKind::Block(children) => gen_block(ctx, children, expectation),
+        ASTKind::ExprStmt(stmt) => gen_expr_stmt(ctx, stmt),
+        ASTKind::If(cond, then, els) => {
             ValType::RVal(branch_expr::gen_if(ctx, cond, then, els, expectation))
         }
-        AST::Let(pattern, flag, ty, init) => {
-            gen_let(ctx, pattern, flag, ty, init);
+        ASTKind::Let(pattern, flag, ty, init) => {
+            gen_let(ctx, span, pattern, flag, ty, init);
             ValType::RVal(RValType::Void)
         }
-        AST::Return(v) => {
+        ASTKind::Return(v) => {
             let ret = gen(ctx, v, ValExpectation::RVal).expect_rval();
-            ctx.method_builder.borrow_mut().add_inst(Inst::Ret);
+            if ctx.try_ctx.borrow().is_empty() {
+                ctx.method_builder.borrow_mut().add_inst(Inst::Ret);
+            } else {
+                try_expr::gen_leave_ret(ctx, span);
+            }
             ValType::Ret(ret)
         }
-        AST::Loop(body) => ValType::RVal(loop_expr::gen_loop(ctx, body, expectation)),
-        AST::Break(v) => loop_expr::gen_break(ctx, v),
-        AST::Continue => loop_expr::gen_continue(ctx),
-        AST::OpCast(ty, val) => cast::gen_cast(ctx, ty, val),
-        AST::OpNew(ty, fields) => ValType::RVal(call::gen_new(ctx, ty, fields)),
-        AST::OpNewArr(ty, dim) => ValType::RVal(call::gen_new_arr(ctx, ty, dim)),
-        AST::OpCall(f, args) => ValType::RVal(call::gen_call(ctx, f, args)),
-        AST::OpAssign(lhs, rhs) => ValType::RVal(gen_assign(ctx, lhs, rhs)),
-        AST::OpNeg(lhs) => op::gen_neg(ctx, lhs),
-        AST::OpLogNot(lhs) => op::gen_log_not(ctx, lhs),
-        AST::OpLogAnd(lhs, rhs) => ValType::RVal(op::gen_and(ctx, lhs, rhs)),
-        AST::OpLogOr(lhs, rhs) => ValType::RVal(op::gen_or(ctx, lhs, rhs)),
-        AST::OpAdd(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Add, lhs, rhs)),
-        AST::OpSub(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Sub, lhs, rhs)),
-        AST::OpMul(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Mul, lhs, rhs)),
-        AST::OpDiv(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Div, lhs, rhs)),
-        AST::OpMod(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Mod, lhs, rhs)),
-        AST::OpNe(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Ne, lhs, rhs)),
-        AST::OpEq(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Eq, lhs, rhs)),
-        AST::OpGe(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Ge, lhs, rhs)),
-        AST::OpGt(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Gt, lhs, rhs)),
-        AST::OpLe(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Le, lhs, rhs)),
-        AST::OpLt(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Lt, lhs, rhs)),
-        AST::OpObjAccess(lhs, rhs) => acc::gen_instance_acc(ctx, lhs, rhs, expectation),
-        AST::OpStaticAccess(lhs, rhs) => acc::gen_static_acc(ctx, lhs, rhs, expectation),
-        AST::OpArrayAccess(lhs, rhs) => acc::gen_arr_acc(ctx, lhs, rhs, expectation),
-        AST::Id(id) => gen_id(ctx, id, expectation),
-        AST::IdWithGenericParams(id_with_generic_ps) => {
-            assert!(id_with_generic_ps.generic_params.is_empty());
-            gen_id(ctx, &id_with_generic_ps.id, expectation)
+        ASTKind::Loop(body) => ValType::RVal(loop_expr::gen_loop(ctx, body, expectation)),
+        ASTKind::While(cond, body) => ValType::RVal(loop_expr::gen_while(ctx, cond, body)),
+        ASTKind::For(id, iter, body) => ValType::RVal(loop_expr::gen_for(ctx, id, iter, body)),
+        ASTKind::Match(val, arms) => {
+            ValType::RVal(match_expr::gen_match(ctx, span, val, arms, expectation))
+        }
+        ASTKind::Try(body, catches, finally) => {
+            ValType::RVal(try_expr::gen_try(ctx, body, catches, finally))
         }
-        AST::Type(ty) => match expectation {
+        ASTKind::Throw(v) => try_expr::gen_throw(ctx, span, v),
+        ASTKind::Break(v) => loop_expr::gen_break(ctx, span, v),
+        ASTKind::Continue => loop_expr::gen_continue(ctx, span),
+        ASTKind::OpCast(ty, val, is_checked) => {
+            cast::gen_cast(ctx, span, ty, val, *is_checked)
+        }
+        ASTKind::OpIs(ty, val) => cast::gen_is(ctx, span, ty, val),
+        ASTKind::OpNew(ty, fields) => ValType::RVal(call::gen_new(ctx, span, ty, fields)),
+        ASTKind::Tuple(elems) => ValType::RVal(tuple_expr::gen_tuple(ctx, span, elems)),
+        ASTKind::OpNewArr(ty, dim) => ValType::RVal(call::gen_new_arr(ctx, span, ty, dim)),
+        ASTKind::OpCall(f, args) => ValType::RVal(call::gen_call(ctx, span, f, args)),
+        ASTKind::OpAssign(lhs, rhs) => ValType::RVal(gen_assign(ctx, lhs, rhs)),
+        ASTKind::OpCompoundAssign(op) => ValType::RVal(gen_compound_assign(ctx, op)),
+        ASTKind::OpPos(lhs) => op::gen_pos(ctx, lhs),
+        ASTKind::OpNeg(lhs) => op::gen_neg(ctx, lhs),
+        ASTKind::OpLogNot(lhs) => op::gen_log_not(ctx, lhs),
+        ASTKind::OpLogAnd(lhs, rhs) => ValType::RVal(op::gen_and(ctx, lhs, rhs)),
+        ASTKind::OpLogOr(lhs, rhs) => ValType::RVal(op::gen_or(ctx, lhs, rhs)),
+        ASTKind::OpBitNot(lhs) => op::gen_bit_not(ctx, lhs),
+        ASTKind::OpBitAnd(lhs, rhs) => ValType::RVal(op::gen_bitwise(ctx, BinOp::BitAnd, lhs, rhs)),
+        ASTKind::OpBitOr(lhs, rhs) => ValType::RVal(op::gen_bitwise(ctx, BinOp::BitOr, lhs, rhs)),
+        ASTKind::OpBitXor(lhs, rhs) => ValType::RVal(op::gen_bitwise(ctx, BinOp::BitXor, lhs, rhs)),
+        ASTKind::OpShl(lhs, rhs) => ValType::RVal(op::gen_shift(ctx, BinOp::Shl, lhs, rhs)),
+        ASTKind::OpShr(lhs, rhs) => ValType::RVal(op::gen_shift(ctx, BinOp::Shr, lhs, rhs)),
+        ASTKind::OpAdd(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Add, lhs, rhs)),
+        ASTKind::OpSub(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Sub, lhs, rhs)),
+        ASTKind::OpMul(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Mul, lhs, rhs)),
+        ASTKind::OpDiv(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Div, lhs, rhs)),
+        ASTKind::OpMod(lhs, rhs) => ValType::RVal(op::gen_numeric(ctx, BinOp::Mod, lhs, rhs)),
+        ASTKind::OpNe(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Ne, lhs, rhs)),
+        ASTKind::OpEq(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Eq, lhs, rhs)),
+        ASTKind::OpGe(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Ge, lhs, rhs)),
+        ASTKind::OpGt(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Gt, lhs, rhs)),
+        ASTKind::OpLe(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Le, lhs, rhs)),
+        ASTKind::OpLt(lhs, rhs) => ValType::RVal(op::gen_cmp(ctx, BinOp::Lt, lhs, rhs)),
+        ASTKind::OpObjAccess(lhs, rhs) => acc::gen_instance_acc(ctx, span, lhs, rhs, expectation),
+        ASTKind::OpStaticAccess(lhs, rhs) => acc::gen_static_acc(ctx, span, lhs, rhs, expectation),
+        ASTKind::OpArrayAccess(lhs, rhs) => acc::gen_arr_acc(ctx, span, lhs, rhs, expectation),
+        ASTKind::Id(id) => gen_id(ctx, span, id, &[], expectation),
+        ASTKind::IdWithGenericParams(id_with_generic_ps) => gen_id(
+            ctx,
+            span,
+            &id_with_generic_ps.id,
+            &id_with_generic_ps.generic_params,
+            expectation,
+        ),
+        ASTKind::Type(ty) => match expectation {
             ValExpectation::Callable
             | ValExpectation::RVal
             | ValExpectation::Instance
             | ValExpectation::Assignable
-            | ValExpectation::None => {
-                panic!()
-            }
-            ValExpectation::Static => ValType::Sym(gen_type(ctx, ty)),
+            | ValExpectation::None => ctx.error(
+                ErrCode::InvalidExpr,
+                span,
+                format!("Type {} is not a value", ty),
+            ),
+            ValExpectation::Static => ValType::Sym(gen_type(ctx, span, ty)),
         },
-        AST::Bool(val) => literal::gen_bool(ctx, *val),
-        AST::Int(val) => literal::gen_int(ctx, *val),
-        AST::String(val) => literal::gen_string(ctx, val),
-        AST::None => literal::gen_none(),
+        ASTKind::Bool(val) => literal::gen_bool(ctx, *val),
+        ASTKind::Int(val, ty) => literal::gen_int(ctx, *val, RValType::from_lit(ty)),
+        ASTKind::Float(val, ty) => literal::gen_float(ctx, *val, RValType::from_lit(ty)),
+        ASTKind::Char(val) => literal::gen_char(ctx, *val),
+        ASTKind::String(val) => literal::gen_string(ctx, val),
+        ASTKind::None => literal::gen_none(),
         _ => unimplemented!("{}", ast),
     }
 }
 
 fn gen_block(ctx: &CodeGenCtx, children: &Vec<Box<AST>>, expectation: ValExpectation) -> ValType {
     // Push Symbol table
-    ctx.locals.borrow_mut().push();
+    ctx.push_scope();
 
     let mut ret = ValType::RVal(RValType::Void);
     let mut child_iter = children.iter().peekable();
     while let Some(stmt) = child_iter.next() {
+        ctx.add_seq_point(stmt.span);
         if child_iter.peek().is_some() {
             // not last
             gen(ctx, stmt, ValExpectation::None);
@@ -144,7 +219,7 @@ fn gen_block(ctx: &CodeGenCtx, children: &Vec<Box<AST>>, expectation: ValExpecta
     }
 
     // Pop Symbol table
-    ctx.locals.borrow_mut().pop();
+    ctx.pop_scope();
     ret
 }
 
@@ -166,17 +241,28 @@ fn gen_expr_stmt(ctx: &CodeGenCtx, stmt: &Box<AST>) -> ValType {
     }
 }
 
-fn gen_let(ctx: &CodeGenCtx, pattern: &Box<AST>, flag: &LocalAttrib, ty: &ASTType, init: &AST) {
-    match pattern.as_ref() {
-        AST::Id(id) => {
-            if let AST::None = init {
+fn gen_let(
+    ctx: &CodeGenCtx,
+    span: Span,
+    pattern: &Box<AST>,
+    flag: &LocalAttrib,
+    ty: &ASTType,
+    init: &AST,
+) {
+    match &pattern.kind {
+        ASTKind::Id(id) => {
+            if let ASTKind::None = init.kind {
                 // no initialization
                 if let ASTType::None = ty {
                     // invalid let stmt
-                    panic!("Specify type or use initialization");
+                    ctx.error(
+                        ErrCode::TypeMismatch,
+                        span,
+                        "Specify type or use initialization",
+                    );
                 } else {
                     // this variable is declared but not initialized
-                    let ty = ctx.get_ty(ty);
+                    let ty = ctx.get_ty(ty, span);
                     ctx.locals.borrow_mut().add(id, ty.clone(), *flag, false);
                 }
             } else {
@@ -192,21 +278,29 @@ fn gen_let(ctx: &CodeGenCtx, pattern: &Box<AST>, flag: &LocalAttrib, ty: &ASTTyp
                     // no type, induce type from return value of init
                 } else {
                     // check type match
-                    let ty = ctx.get_ty(ty);
+                    let ty = ctx.get_ty(ty, span);
                     if ty != init_ty {
-                        panic!("Cannot assign {} to local var {}: {}", init_ty, id, ty);
+                        ctx.error(
+                            ErrCode::TypeMismatch,
+                            init.span,
+                            format!(
+                                "Cannot assign {} to local var {}: {}",
+                                ctx.ty_name(&init_ty),
+                                id,
+                                ctx.ty_name(&ty)
+                            ),
+                        );
                     }
                 }
             }
         }
-        AST::TuplePattern(_) => {
-            unimplemented!()
-        }
+        ASTKind::TuplePattern(_) => tuple_expr::gen_let_tuple(ctx, span, pattern, flag, ty, init),
         _ => unreachable!(),
     };
 }
 
 fn gen_assign(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
+    let span = lhs.span.merge(&rhs.span);
     // filter rval value type by expect_sym
     let lval = gen(ctx, lhs, ValExpectation::Assignable).expect_sym();
     let v_ty = gen(ctx, rhs, ValExpectation::RVal).expect_rval();
@@ -218,7 +312,16 @@ fn gen_assign(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
             let local_ty = local.ty.clone();
 
             if local_ty != v_ty {
-                panic!("Cannot assign {} to local {}: {}", v_ty, local.id, local_ty);
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    span,
+                    format!(
+                        "Cannot assign {} to local {}: {}",
+                        ctx.ty_name(&v_ty),
+                        local.id,
+                        ctx.ty_name(&local_ty)
+                    ),
+                );
             }
 
             ctx.method_builder.borrow_mut().add_inst_stloc(local.idx);
@@ -226,13 +329,22 @@ fn gen_assign(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
         SymType::KwLSelf => {
             // lval guarentee that we are in instance method
             // ctx.method_builder.borrow_mut().add_inst_starg(0);
-            panic!("Cannot assign self");
+            ctx.error(ErrCode::InvalidSelf, span, "Cannot assign self");
         }
         SymType::Arg(idx) => {
             let arg = &ctx.method.ps[*idx];
 
             if arg.ty != v_ty {
-                panic!("Cannot assign {} to arg {}: {}", v_ty, arg.id, arg.ty);
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    span,
+                    format!(
+                        "Cannot assign {} to arg {}: {}",
+                        ctx.ty_name(&v_ty),
+                        arg.id,
+                        ctx.ty_name(&arg.ty)
+                    ),
+                );
             }
 
             ctx.method_builder.borrow_mut().add_inst_starg(if ctx
@@ -245,23 +357,22 @@ fn gen_assign(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
                 *idx + 1
             } as u16);
         }
-        SymType::Field(f) => {
+        SymType::Field(class_args, f) => {
             // TODO private and public
-            let (mod_name, class_name, f_ref) = unsafe {
-                let f_ref = f.as_ref();
-                let class_ref = f_ref.parent.as_ref();
-                let module_ref = class_ref.parent.as_ref();
-                (module_ref.fullname(), &class_ref.name, f_ref)
-            };
+            let f_ref = unsafe { f.as_ref() };
 
-            if f_ref.ty != v_ty {
-                panic!("Cannot assign {} value to {}", v_ty, f_ref);
+            if f_ref.ty.instantiate(class_args, &[]) != v_ty {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    span,
+                    format!("Cannot assign {} value to {}", ctx.ty_name(&v_ty), f_ref),
+                );
             }
 
             let sig = ctx.module.builder.borrow_mut().add_field_sig(&f_ref.ty);
-            let (f_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_member(
-                mod_name,
-                class_name,
+            let (f_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_inst_member(
+                unsafe { f_ref.parent.as_ref() },
+                class_args,
                 &f_ref.name,
                 sig,
             );
@@ -275,14 +386,125 @@ fn gen_assign(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
         }
         SymType::ArrAcc(ele_ty) => {
             if ele_ty != &v_ty {
-                panic!("Cannot store {} into {} array", v_ty, ele_ty);
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    span,
+                    format!(
+                        "Cannot store {} into {} array",
+                        ctx.ty_name(&v_ty),
+                        ctx.ty_name(ele_ty)
+                    ),
+                );
             }
             ctx.method_builder
                 .borrow_mut()
                 .add_stelem(&ele_ty, &ctx.module.builder);
         }
-        SymType::Module(_) => panic!(),
-        SymType::Class(_) => panic!(),
+        SymType::Module(_) | SymType::Class(_, _) => ctx.error(
+            ErrCode::InvalidExpr,
+            lhs.span,
+            "Cannot assign to a module or a class",
+        ),
+        _ => unreachable!(),
+    }
+
+    // assign op has no value left on evaluation stack
+    RValType::Void
+}
+
+/// `lhs op= rhs`. Object, array and index in lhs are evaluated only once
+fn gen_compound_assign(ctx: &CodeGenCtx, op: &AST) -> RValType {
+    let (bin_op, lhs, rhs) = match &op.kind {
+        ASTKind::OpAdd(lhs, rhs) => (BinOp::Add, lhs, rhs),
+        ASTKind::OpSub(lhs, rhs) => (BinOp::Sub, lhs, rhs),
+        ASTKind::OpMul(lhs, rhs) => (BinOp::Mul, lhs, rhs),
+        ASTKind::OpDiv(lhs, rhs) => (BinOp::Div, lhs, rhs),
+        ASTKind::OpMod(lhs, rhs) => (BinOp::Mod, lhs, rhs),
+        ASTKind::OpBitAnd(lhs, rhs) => (BinOp::BitAnd, lhs, rhs),
+        ASTKind::OpBitOr(lhs, rhs) => (BinOp::BitOr, lhs, rhs),
+        ASTKind::OpBitXor(lhs, rhs) => (BinOp::BitXor, lhs, rhs),
+        ASTKind::OpShl(lhs, rhs) => (BinOp::Shl, lhs, rhs),
+        ASTKind::OpShr(lhs, rhs) => (BinOp::Shr, lhs, rhs),
+        _ => unreachable!(),
+    };
+
+    let lval = gen(ctx, lhs, ValExpectation::Assignable).expect_sym();
+    match &lval {
+        SymType::Local(idx) => {
+            let (local_idx, local_ty) = {
+                let locals = ctx.locals.borrow();
+                let local = &locals.locals[*idx];
+                (local.idx, local.ty.clone())
+            };
+            ctx.method_builder.borrow_mut().add_inst_ldloc(local_idx);
+            op::add_bin_op(ctx, bin_op, lhs.span, local_ty, rhs);
+            ctx.method_builder.borrow_mut().add_inst_stloc(local_idx);
+        }
+        SymType::KwLSelf => {
+            ctx.error(ErrCode::InvalidSelf, op.span, "Cannot assign self");
+        }
+        SymType::Arg(idx) => {
+            let arg_ty = ctx.method.ps[*idx].ty.clone();
+            let arg_idx = if ctx.method.attrib.is(MethodAttribFlag::Static) {
+                *idx
+            } else {
+                *idx + 1
+            } as u16;
+            ctx.method_builder.borrow_mut().add_inst_ldarg(arg_idx);
+            op::add_bin_op(ctx, bin_op, lhs.span, arg_ty, rhs);
+            ctx.method_builder.borrow_mut().add_inst_starg(arg_idx);
+        }
+        SymType::Field(class_args, f) => {
+            let f_ref = unsafe { f.as_ref() };
+            let f_ty = f_ref.ty.instantiate(class_args, &[]);
+
+            let sig = ctx.module.builder.borrow_mut().add_field_sig(&f_ref.ty);
+            let (f_idx, tok_tag) = ctx.module.builder.borrow_mut().add_const_inst_member(
+                unsafe { f_ref.parent.as_ref() },
+                class_args,
+                &f_ref.name,
+                sig,
+            );
+            let tok = to_tok(f_idx, tok_tag);
+            if f_ref.attrib.is(FieldAttribFlag::Static) {
+                ctx.method_builder.borrow_mut().add_inst(Inst::LdSFld(tok));
+                op::add_bin_op(ctx, bin_op, lhs.span, f_ty, rhs);
+                ctx.method_builder.borrow_mut().add_inst(Inst::StSFld(tok));
+            } else {
+                // obj is already on the stack
+                ctx.method_builder
+                    .borrow_mut()
+                    .add_inst(Inst::Dup)
+                    .add_inst(Inst::LdFld(tok));
+                op::add_bin_op(ctx, bin_op, lhs.span, f_ty, rhs);
+                ctx.method_builder.borrow_mut().add_inst(Inst::StFld(tok));
+            }
+        }
+        SymType::ArrAcc(ele_ty) => {
+            if !ele_ty.is_int() && !ele_ty.is_float() {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    lhs.span,
+                    format!(
+                        "Compound assignment cannot be applied on {} element",
+                        ctx.ty_name(ele_ty)
+                    ),
+                );
+            }
+            // arr and idx are already on the stack, load and store through the element addr
+            ctx.method_builder
+                .borrow_mut()
+                .add_ldelema(ele_ty, &ctx.module.builder)
+                .add_inst(Inst::Dup)
+                .add_ldind(ele_ty);
+            op::add_bin_op(ctx, bin_op, lhs.span, ele_ty.clone(), rhs);
+            ctx.method_builder.borrow_mut().add_stind(ele_ty);
+        }
+        SymType::Module(_) | SymType::Class(_, _) => ctx.error(
+            ErrCode::InvalidExpr,
+            lhs.span,
+            "Cannot assign to a module or a class",
+        ),
         _ => unreachable!(),
     }
 
@@ -290,24 +512,52 @@ fn gen_assign(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     RValType::Void
 }
 
-fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
+fn gen_id(
+    ctx: &CodeGenCtx,
+    span: Span,
+    id: &str,
+    generic_args: &[Box<ASTType>],
+    expectation: ValExpectation,
+) -> ValType {
+    match expectation {
+        ValExpectation::RVal | ValExpectation::Instance | ValExpectation::Assignable
+            if !generic_args.is_empty() =>
+        {
+            ctx.error(
+                ErrCode::InvalidGeneric,
+                span,
+                format!("Value {} cannot have generic args", id),
+            );
+        }
+        _ => {}
+    }
+
     // try search locals
     match expectation {
         ValExpectation::None | ValExpectation::Callable => {
             // currently only method is callable
             let ms = ctx.class.query_method(id);
             if ms.is_empty() {
-                panic!(
-                    "No method {} in class {}/{}",
-                    id,
-                    ctx.module.get_module().fullname(),
-                    ctx.class.name
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!(
+                        "No method {} in class {}/{}",
+                        id,
+                        ctx.module.get_module().fullname(),
+                        ctx.class.name
+                    ),
                 );
             }
             ValType::Sym(SymType::Method(
+                ctx.class_generic_args(),
                 ms.into_iter()
                     .map(|m| NonNull::new(m as *const Method as *mut Method).unwrap())
                     .collect(),
+                generic_args
+                    .iter()
+                    .map(|arg| ctx.get_ty(arg, span))
+                    .collect(),
             ))
         }
         ValExpectation::RVal | ValExpectation::Instance => {
@@ -318,13 +568,22 @@ fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
                 if is_instance_method {
                     // first argument
                     ctx.method_builder.borrow_mut().add_inst_ldarg(0);
-                    RValType::Class(NonNull::new(ctx.class as *const Type as *mut Type).unwrap())
+                    let class = NonNull::new(ctx.class as *const Type as *mut Type).unwrap();
+                    if ctx.class.generic_params.is_empty() {
+                        RValType::Class(class)
+                    } else {
+                        RValType::GenericInst(true, class, ctx.class_generic_args())
+                    }
                 } else {
-                    panic!("Invalid self keyword in static method");
+                    ctx.error(
+                        ErrCode::InvalidSelf,
+                        span,
+                        "Invalid self keyword in static method",
+                    );
                 }
             } else if let Some(local_var) = locals.get(id) {
                 if let ValExpectation::Instance = expectation {
-                    if let RValType::Value(_) = local_var.ty {
+                    if let RValType::Value(_) | RValType::GenericInst(false, _, _) = local_var.ty {
                         loada = true;
                     }
                 }
@@ -343,7 +602,7 @@ fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
             } else if let Some(arg_idx) = ctx.ps_map.get(id) {
                 let arg = &ctx.method.ps[*arg_idx];
                 if let ValExpectation::Instance = expectation {
-                    if let RValType::Value(_) = arg.ty {
+                    if let RValType::Value(_) | RValType::GenericInst(false, _, _) = arg.ty {
                         loada = true;
                     }
                 }
@@ -367,12 +626,20 @@ fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
                     arg.ty.clone()
                 }
             } else {
-                panic!();
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("Cannot find value {} in this scope", id),
+                );
             })
         }
         ValExpectation::Static => {
-            let id = if id == "Self" { &ctx.class.name } else { id };
-            ValType::Sym(if let Some(path) = ctx.module.use_map.get(id) {
+            let (id, is_self) = if id == "Self" {
+                (ctx.class.name.as_str(), true)
+            } else {
+                (id, false)
+            };
+            let sym = if let Some((path, _)) = ctx.module.use_map.get(id) {
                 // item in sub module or any using module
                 SymType::Module(
                     NonNull::new(ctx.mgr.mod_tbl.get(path.as_str()).unwrap().as_ref()
@@ -390,13 +657,35 @@ fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
                 )
             } else if let Some(c) = ctx.module.get_module().classes.get(id) {
                 // class within the same module
-                SymType::Class(NonNull::new(c.as_ref() as *const Type as *mut Type).unwrap())
+                SymType::Class(
+                    NonNull::new(c.as_ref() as *const Type as *mut Type).unwrap(),
+                    if is_self && generic_args.is_empty() {
+                        // Self is instantiated with generic params of this class
+                        ctx.class_generic_args()
+                    } else {
+                        ctx.get_class_generic_args(span, c, generic_args)
+                    },
+                )
             } else if let Some(m) = ctx.mgr.mod_tbl.get(id) {
                 // this crate can be referenced in this case (allow or not?)
                 SymType::Module(NonNull::new(m.as_ref() as *const Module as *mut Module).unwrap())
             } else {
-                panic!();
-            })
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("Cannot find module or class {}", id),
+                );
+            };
+            if let SymType::Module(_) = sym {
+                if !generic_args.is_empty() {
+                    ctx.error(
+                        ErrCode::InvalidGeneric,
+                        span,
+                        format!("Module {} cannot have generic args", id),
+                    );
+                }
+            }
+            ValType::Sym(sym)
         }
         ValExpectation::Assignable => {
             let is_instance_method = !ctx.method.attrib.is(MethodAttribFlag::Static);
@@ -404,7 +693,11 @@ fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
                 if is_instance_method {
                     SymType::KwLSelf
                 } else {
-                    panic!("invalid keyword self in static method");
+                    ctx.error(
+                        ErrCode::InvalidSelf,
+                        span,
+                        "Invalid self keyword in static method",
+                    );
                 }
             } else if let Some(var) = ctx.locals.borrow().get(id) {
                 // query local var
@@ -415,23 +708,47 @@ fn gen_id(ctx: &CodeGenCtx, id: &str, expectation: ValExpectation) -> ValType {
             } else if let Some(f) = ctx.class.query_field(id) {
                 // query field in this class
                 // either static or non-static is ok
-                SymType::Field(NonNull::new(f as *const Field as *mut Field).unwrap())
+                SymType::Field(
+                    ctx.class_generic_args(),
+                    NonNull::new(f as *const Field as *mut Field).unwrap(),
+                )
             } else {
-                panic!("Cannot found item with id: {}", id);
+                ctx.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("Cannot found item with id: {}", id),
+                );
             })
         }
     }
 }
 
-fn gen_type(ctx: &CodeGenCtx, ty: &ASTType) -> SymType {
+fn gen_type(ctx: &CodeGenCtx, span: Span, ty: &ASTType) -> SymType {
     match ty {
-        ASTType::Bool => unimplemented!(),
-        ASTType::Char => unimplemented!(),
-        ASTType::I32 => unimplemented!(),
-        ASTType::F64 => unimplemented!(),
-        ASTType::String => unimplemented!(),
-        ASTType::Tuple(_) => unimplemented!(),
-        ASTType::Arr(_) => unimplemented!(),
+        ASTType::Bool
+        | ASTType::Char
+        | ASTType::U8
+        | ASTType::I8
+        | ASTType::U16
+        | ASTType::I16
+        | ASTType::U32
+        | ASTType::I32
+        | ASTType::U64
+        | ASTType::I64
+        | ASTType::USize
+        | ASTType::ISize
+        | ASTType::F32
+        | ASTType::F64
+        | ASTType::String
+        | ASTType::Tuple(_)
+        | ASTType::Arr(_) => ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            format!(
+                "Type {} has no static members",
+                ctx.ty_name(&ctx.get_ty(ty, span))
+            ),
+        ),
         ASTType::UsrType(path) => {
             assert!(path.len() == 1, "invalid path in lval gen \"{}\"", path);
             match path.as_str() {
@@ -445,6 +762,7 @@ fn gen_type(ctx: &CodeGenCtx, ty: &ASTType) -> SymType {
                             .as_ref() as *const Type as *mut Type,
                     )
                     .unwrap(),
+                    ctx.class_generic_args(),
                 ),
                 _ => unreachable!(),
             }
@@ -1,5 +1,9 @@
 use super::super::super::ast::AST;
-use super::super::{gen, CodeGenCtx, RValType, ValExpectation, ValType};
+use super::super::super::diagnostic::ErrCode;
+use super::super::super::util::Span;
+use super::super::interpreter::{eval, ConstVal, EvalErr};
+use super::super::{gen, CodeGenCtx, MethodBuilder, RValType, ValExpectation, ValType};
+use super::literal;
 
 use xir::Inst;
 
@@ -15,17 +19,52 @@ pub enum BinOp {
     Gt,
     Le,
     Lt,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+/// Fold constant op into a literal, None if ast is not constant
+pub fn gen_folded(ctx: &CodeGenCtx, ast: &AST) -> Option<ValType> {
+    match eval(ast) {
+        Ok(val) => Some(literal::gen_const(ctx, &val)),
+        Err(EvalErr::NotConst(_)) => None,
+        Err(EvalErr::Invalid(code, span, msg)) => ctx.error(code, span, msg),
+    }
+}
+
+pub fn gen_pos(ctx: &CodeGenCtx, lhs: &AST) -> ValType {
+    let v_ty = gen(ctx, lhs, ValExpectation::RVal);
+
+    let ty = v_ty.expect_rval_ref();
+    if !ty.is_int() && !ty.is_float() {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span,
+            "pos op is only available for numeric operand",
+        );
+    }
+
+    v_ty
 }
 
 pub fn gen_neg(ctx: &CodeGenCtx, lhs: &Box<AST>) -> ValType {
     let v_ty = gen(ctx, lhs, ValExpectation::RVal);
 
-    match v_ty.expect_rval_ref() {
-        RValType::I32 | RValType::F64 => {
-            ctx.method_builder.borrow_mut().add_inst(Inst::Neg);
-        }
-        _ => panic!("neg op is only available for i32 or f64 operand"),
-    };
+    let ty = v_ty.expect_rval_ref();
+    if (ty.is_int() && !ty.is_unsigned()) || ty.is_float() {
+        let mut builder = ctx.method_builder.borrow_mut();
+        builder.add_inst(Inst::Neg);
+        add_truncate(&mut builder, ty);
+    } else {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span,
+            "neg op is only available for signed int or float operand",
+        );
+    }
 
     v_ty
 }
@@ -40,12 +79,35 @@ pub fn gen_log_not(ctx: &CodeGenCtx, lhs: &Box<AST>) -> ValType {
                 .add_inst_ldc(0)
                 .add_inst(Inst::CEq);
         }
-        _ => panic!("not op is only available for bool operand"),
+        _ => ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span,
+            "not op is only available for bool operand",
+        ),
     };
 
     v_ty
 }
 
+pub fn gen_bit_not(ctx: &CodeGenCtx, lhs: &Box<AST>) -> ValType {
+    let v_ty = gen(ctx, lhs, ValExpectation::RVal);
+
+    let ty = v_ty.expect_rval_ref();
+    if ty.is_int() {
+        let mut builder = ctx.method_builder.borrow_mut();
+        builder.add_inst(Inst::Not);
+        add_truncate(&mut builder, ty);
+    } else {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span,
+            "bitwise not op is only available for int operand",
+        );
+    }
+
+    v_ty
+}
+
 pub fn gen_and(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     let rhs_bb;
     let false_bb;
@@ -60,7 +122,11 @@ pub fn gen_and(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     let lhs_ty = gen(ctx, lhs, ValExpectation::RVal);
     match lhs_ty.expect_rval() {
         RValType::Bool => {}
-        _ => panic!("Cond not return bool"),
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span,
+            format!("Cond should be bool but found {}", ctx.ty_name(&ty)),
+        ),
     }
 
     ctx.method_builder
@@ -71,7 +137,11 @@ pub fn gen_and(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     let rhs_ty = gen(ctx, rhs, ValExpectation::RVal);
     match rhs_ty.expect_rval() {
         RValType::Bool => {}
-        _ => panic!("Cond not return bool"),
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            rhs.span,
+            format!("Cond should be bool but found {}", ctx.ty_name(&ty)),
+        ),
     }
 
     let mut builder = ctx.method_builder.borrow_mut();
@@ -99,7 +169,11 @@ pub fn gen_or(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     let lhs_ty = gen(ctx, lhs, ValExpectation::RVal);
     match lhs_ty.expect_rval() {
         RValType::Bool => {}
-        _ => panic!("Cond not return bool"),
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span,
+            format!("Cond should be bool but found {}", ctx.ty_name(&ty)),
+        ),
     }
 
     ctx.method_builder
@@ -110,7 +184,11 @@ pub fn gen_or(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     let rhs_ty = gen(ctx, rhs, ValExpectation::RVal);
     match rhs_ty.expect_rval() {
         RValType::Bool => {}
-        _ => panic!("Cond not return bool"),
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            rhs.span,
+            format!("Cond should be bool but found {}", ctx.ty_name(&ty)),
+        ),
     }
 
     let mut builder = ctx.method_builder.borrow_mut();
@@ -126,22 +204,158 @@ pub fn gen_or(ctx: &CodeGenCtx, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
 
 pub fn gen_numeric(ctx: &CodeGenCtx, op: BinOp, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
     let lty = gen(ctx, lhs, ValExpectation::RVal).expect_rval();
+    add_numeric(ctx, op, lhs.span, lty, rhs)
+}
+
+/// & | ^ on ints or bools (without short circuit)
+pub fn gen_bitwise(ctx: &CodeGenCtx, op: BinOp, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
+    let lty = gen(ctx, lhs, ValExpectation::RVal).expect_rval();
+    add_bitwise(ctx, op, lhs.span, lty, rhs)
+}
+
+/// << and >> on ints. Shift amount can be any int type.
+///
+/// Like shl and shr in vm, amount is masked by the bit width of lhs on eval stack,
+/// which is 32 for ints smaller than i32
+pub fn gen_shift(ctx: &CodeGenCtx, op: BinOp, lhs: &Box<AST>, rhs: &Box<AST>) -> RValType {
+    let lty = gen(ctx, lhs, ValExpectation::RVal).expect_rval();
+    add_shift(ctx, op, lhs.span, lty, rhs)
+}
+
+/// Gen rhs and apply numeric, bitwise or shift op on it and lhs of lty,
+/// which is already on eval stack. Used by compound assignment
+pub fn add_bin_op(
+    ctx: &CodeGenCtx,
+    op: BinOp,
+    lhs_span: Span,
+    lty: RValType,
+    rhs: &AST,
+) -> RValType {
+    match op {
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+            add_numeric(ctx, op, lhs_span, lty, rhs)
+        }
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => add_bitwise(ctx, op, lhs_span, lty, rhs),
+        BinOp::Shl | BinOp::Shr => add_shift(ctx, op, lhs_span, lty, rhs),
+        _ => unreachable!(),
+    }
+}
+
+fn add_numeric(ctx: &CodeGenCtx, op: BinOp, lhs_span: Span, lty: RValType, rhs: &AST) -> RValType {
     let rty = gen(ctx, rhs, ValExpectation::RVal).expect_rval();
 
     if lty != rty {
-        panic!("Numeric op cannot be applied between {} and {}", lty, rty);
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs_span.merge(&rhs.span),
+            format!(
+                "Numeric op cannot be applied between {} and {}",
+                ctx.ty_name(&lty),
+                ctx.ty_name(&rty)
+            ),
+        );
     }
 
-    // TODO: check lty
+    if !lty.is_int() && !lty.is_float() {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs_span,
+            format!("Numeric op cannot be applied on {}", ctx.ty_name(&lty)),
+        );
+    }
 
-    ctx.method_builder.borrow_mut().add_inst(match op {
+    if let BinOp::Div | BinOp::Mod = op {
+        if let Ok(ConstVal::Int(0, _)) = eval(rhs) {
+            ctx.error(ErrCode::DivideByZero, rhs.span, "Divide by constant zero");
+        }
+    }
+
+    let mut builder = ctx.method_builder.borrow_mut();
+    builder.add_inst(match op {
         BinOp::Add => Inst::Add,
         BinOp::Sub => Inst::Sub,
         BinOp::Mul => Inst::Mul,
+        BinOp::Div if lty.is_unsigned() => Inst::DivUn,
         BinOp::Div => Inst::Div,
+        BinOp::Mod if lty.is_unsigned() => Inst::RemUn,
         BinOp::Mod => Inst::Rem,
         _ => unreachable!(),
     });
+    add_truncate(&mut builder, &lty);
+    lty
+}
+
+fn add_bitwise(ctx: &CodeGenCtx, op: BinOp, lhs_span: Span, lty: RValType, rhs: &AST) -> RValType {
+    let rty = gen(ctx, rhs, ValExpectation::RVal).expect_rval();
+
+    if lty != rty {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs_span.merge(&rhs.span),
+            format!(
+                "Bitwise op cannot be applied between {} and {}",
+                ctx.ty_name(&lty),
+                ctx.ty_name(&rty)
+            ),
+        );
+    }
+
+    if !lty.is_int() && lty != RValType::Bool {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs_span,
+            format!("Bitwise op cannot be applied on {}", ctx.ty_name(&lty)),
+        );
+    }
+
+    // operands are already in the range of lty, so are the results. No truncation needed
+    ctx.method_builder.borrow_mut().add_inst(match op {
+        BinOp::BitAnd => Inst::And,
+        BinOp::BitOr => Inst::Or,
+        BinOp::BitXor => Inst::Xor,
+        _ => unreachable!(),
+    });
+    lty
+}
+
+fn add_shift(ctx: &CodeGenCtx, op: BinOp, lhs_span: Span, lty: RValType, rhs: &AST) -> RValType {
+    if !lty.is_int() {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs_span,
+            format!("Shift op cannot be applied on {}", ctx.ty_name(&lty)),
+        );
+    }
+
+    let rty = gen(ctx, rhs, ValExpectation::RVal).expect_rval();
+    let mut builder = ctx.method_builder.borrow_mut();
+    match rty {
+        // shift amount should be i32 or native int
+        RValType::U64 | RValType::I64 => {
+            builder.add_inst(Inst::ConvI4);
+        }
+        ty if ty.is_int() => {}
+        ty => ctx.error(
+            ErrCode::TypeMismatch,
+            rhs.span,
+            format!("Shift amount should be int but found {}", ctx.ty_name(&ty)),
+        ),
+    }
+
+    match op {
+        BinOp::Shl => {
+            builder.add_inst(Inst::Shl);
+            add_truncate(&mut builder, &lty);
+        }
+        BinOp::Shr => {
+            builder.add_inst(if lty.is_unsigned() {
+                Inst::ShrUn
+            } else {
+                Inst::Shr
+            });
+        }
+        _ => unreachable!(),
+    }
     lty
 }
 
@@ -150,12 +364,26 @@ pub fn gen_cmp(ctx: &CodeGenCtx, op: BinOp, lhs: &Box<AST>, rhs: &Box<AST>) -> R
     let rty = gen(ctx, rhs, ValExpectation::RVal).expect_rval();
 
     if lty != rty {
-        panic!("Cmp op cannot be applied between {} and {}", lty, rty);
+        ctx.error(
+            ErrCode::TypeMismatch,
+            lhs.span.merge(&rhs.span),
+            format!(
+                "Cmp op cannot be applied between {} and {}",
+                ctx.ty_name(&lty),
+                ctx.ty_name(&rty)
+            ),
+        );
     }
 
-    // TODO: check lty
+    // unsigned ints are compared with .un insts.
+    // Unordered floats (NaN) are compared with .un insts in <= and >= so that the result is false
+    let is_float = lty.is_float();
+    let is_un = lty.is_unsigned();
 
     let mut builder = ctx.method_builder.borrow_mut();
+    if is_float {
+        builder.mark_float_cmp();
+    }
 
     match op {
         BinOp::Eq => {
@@ -168,20 +396,28 @@ pub fn gen_cmp(ctx: &CodeGenCtx, op: BinOp, lhs: &Box<AST>, rhs: &Box<AST>) -> R
                 .add_inst(Inst::CEq);
         }
         BinOp::Gt => {
-            builder.add_inst(Inst::CGt);
+            builder.add_inst(if is_un { Inst::CGtUn } else { Inst::CGt });
         }
         BinOp::Le => {
             builder
-                .add_inst(Inst::CGt)
+                .add_inst(if is_un || is_float {
+                    Inst::CGtUn
+                } else {
+                    Inst::CGt
+                })
                 .add_inst(Inst::LdC0)
                 .add_inst(Inst::CEq);
         }
         BinOp::Lt => {
-            builder.add_inst(Inst::CLt);
+            builder.add_inst(if is_un { Inst::CLtUn } else { Inst::CLt });
         }
         BinOp::Ge => {
             builder
-                .add_inst(Inst::CLt)
+                .add_inst(if is_un || is_float {
+                    Inst::CLtUn
+                } else {
+                    Inst::CLt
+                })
                 .add_inst(Inst::LdC0)
                 .add_inst(Inst::CEq);
         }
@@ -190,3 +426,22 @@ pub fn gen_cmp(ctx: &CodeGenCtx, op: BinOp, lhs: &Box<AST>, rhs: &Box<AST>) -> R
 
     RValType::Bool
 }
+
+/// ints smaller than i32 are i32 on eval stack, truncate the result of an op to ty
+fn add_truncate(builder: &mut MethodBuilder, ty: &RValType) {
+    match ty {
+        RValType::U8 => {
+            builder.add_inst(Inst::ConvU1);
+        }
+        RValType::I8 => {
+            builder.add_inst(Inst::ConvI1);
+        }
+        RValType::U16 => {
+            builder.add_inst(Inst::ConvU2);
+        }
+        RValType::I16 => {
+            builder.add_inst(Inst::ConvI2);
+        }
+        _ => {}
+    }
+}
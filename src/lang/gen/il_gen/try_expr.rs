@@ -0,0 +1,211 @@
+use super::super::super::ast::{ASTKind, AST};
+use super::super::super::diagnostic::ErrCode;
+use super::super::super::util::Span;
+use super::super::{
+    CodeGenCtx, ExceptionClauseBB, RValType, TryBlockType, Type, ValExpectation, ValType,
+};
+use super::cast::is_castable;
+use super::gen;
+
+use xir::attrib::LocalAttrib;
+use xir::code::ExceptionClauseKind;
+use xir::inst::Inst;
+use xir::tok::to_tok;
+
+use std::ptr::NonNull;
+
+/// check that ty is std::Exception or its subclass
+fn check_exception_ty(ctx: &CodeGenCtx, span: Span, ty: &RValType) {
+    let exception = ctx
+        .mgr
+        .mod_tbl
+        .get("std")
+        .unwrap()
+        .classes
+        .get("Exception")
+        .unwrap()
+        .as_ref();
+    let exception = RValType::Class(NonNull::new(exception as *const Type as *mut Type).unwrap());
+    if !is_castable(ctx, ty, &exception) {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            span,
+            format!(
+                "{} is not a subclass of {}",
+                ctx.ty_name(ty),
+                ctx.ty_name(&exception)
+            ),
+        );
+    }
+}
+
+/// gen block of try, catch or finally, the value of the block is discarded
+fn gen_protected_block(ctx: &CodeGenCtx, block: &AST, block_ty: TryBlockType) {
+    ctx.try_ctx.borrow_mut().push(block_ty);
+    if let ValType::RVal(ty) = gen(ctx, block, ValExpectation::RVal) {
+        match ty {
+            RValType::Void | RValType::Never => {}
+            _ => {
+                ctx.method_builder.borrow_mut().add_inst(Inst::Pop);
+            }
+        }
+    }
+    ctx.try_ctx.borrow_mut().pop();
+}
+
+pub fn gen_throw(ctx: &CodeGenCtx, span: Span, v: &AST) -> ValType {
+    let ty = gen(ctx, v, ValExpectation::RVal).expect_rval();
+    check_exception_ty(ctx, span, &ty);
+    ctx.method_builder.borrow_mut().add_inst(Inst::Throw);
+    // like return, code after throw is unreachable
+    ValType::Ret(ctx.method.ret.clone())
+}
+
+/// return inside try blocks or catch handlers, whose value is on the stack.
+///
+/// The value is stored to a local and control leaves to the epilogue of method,
+/// so that enclosing finally handlers are executed
+pub fn gen_leave_ret(ctx: &CodeGenCtx, span: Span) {
+    if ctx.try_ctx.borrow().contains(&TryBlockType::Finally) {
+        ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            "Cannot return inside finally block",
+        );
+    }
+
+    let mut ret_epilogue = ctx.ret_epilogue.borrow_mut();
+    let (epilogue, ret_local) = ret_epilogue.get_or_insert_with(|| {
+        let ret_local = match &ctx.method.ret {
+            RValType::Void | RValType::Never => None,
+            ty => Some(ctx.locals.borrow_mut().add_tmp(
+                ty.clone(),
+                LocalAttrib::default(),
+                true,
+            )),
+        };
+        // epilogue is after all protected regions
+        (ctx.method_builder.borrow_mut().push_back_bb(), ret_local)
+    });
+    let mut builder = ctx.method_builder.borrow_mut();
+    if let Some(ret_local) = ret_local {
+        builder.add_inst_stloc(*ret_local);
+    }
+    builder.add_leave(epilogue.clone());
+}
+
+pub fn gen_try(ctx: &CodeGenCtx, body: &AST, catches: &[Box<AST>], finally: &AST) -> RValType {
+    let try_bb;
+    let mut catch_bbs;
+    let finally_bb;
+    let after_bb;
+    {
+        let mut builder = ctx.method_builder.borrow_mut();
+        after_bb = builder.insert_after_cur();
+        // finally_bb is empty if there is no finally
+        finally_bb = builder.insert_after_cur();
+        catch_bbs = catches
+            .iter()
+            .map(|_| builder.insert_after_cur())
+            .collect::<Vec<_>>();
+        catch_bbs.reverse();
+        try_bb = builder.insert_after_cur();
+        builder.set_cur_bb(try_bb.clone());
+    }
+
+    gen_protected_block(ctx, body, TryBlockType::Try);
+    {
+        let mut builder = ctx.method_builder.borrow_mut();
+        if !builder.cur_bb_last_is_branch() {
+            builder.add_leave(after_bb.clone());
+        }
+    }
+
+    let mut excepts = vec![];
+    for (i, catch) in catches.iter().enumerate() {
+        ctx.method_builder
+            .borrow_mut()
+            .set_cur_bb(catch_bbs[i].clone());
+
+        if let ASTKind::Catch(id, ty, catch_body) = &catch.kind {
+            let ty = ctx.get_ty(ty, catch.span);
+            check_exception_ty(ctx, catch.span, &ty);
+            let class_tok = if let RValType::Class(class) = &ty {
+                let class = unsafe { class.as_ref() };
+                let (idx, tag) = ctx
+                    .module
+                    .builder
+                    .borrow_mut()
+                    .add_const_class(class.modname(), &class.name);
+                to_tok(idx, tag.to_tok_tag())
+            } else {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    catch.span,
+                    format!("Cannot catch generic exception {}", ctx.ty_name(&ty)),
+                );
+            };
+
+            // exception object is on the stack when entering the handler
+            ctx.push_scope();
+            let local_idx = ctx
+                .locals
+                .borrow_mut()
+                .add(id, ty, LocalAttrib::default(), true);
+            ctx.method_builder.borrow_mut().add_inst_stloc(local_idx);
+            gen_protected_block(ctx, catch_body, TryBlockType::Catch);
+            ctx.pop_scope();
+
+            let mut builder = ctx.method_builder.borrow_mut();
+            if !builder.cur_bb_last_is_branch() {
+                builder.add_leave(after_bb.clone());
+            }
+
+            excepts.push(ExceptionClauseBB {
+                kind: ExceptionClauseKind::Catch,
+                try_start: try_bb.clone(),
+                try_end: catch_bbs[0].clone(),
+                handler_start: catch_bbs[i].clone(),
+                handler_end: if i + 1 < catch_bbs.len() {
+                    catch_bbs[i + 1].clone()
+                } else {
+                    finally_bb.clone()
+                },
+                class_tok,
+            });
+        } else {
+            unreachable!("Parser error");
+        }
+    }
+
+    if !matches!(finally.kind, ASTKind::None) {
+        ctx.method_builder
+            .borrow_mut()
+            .set_cur_bb(finally_bb.clone());
+        gen_protected_block(ctx, finally, TryBlockType::Finally);
+
+        let mut builder = ctx.method_builder.borrow_mut();
+        if !builder.cur_bb_last_is_branch() {
+            builder.add_inst(Inst::EndFinally);
+        }
+
+        // try block together with catch handlers are protected by finally
+        excepts.push(ExceptionClauseBB {
+            kind: ExceptionClauseKind::Finally,
+            try_start: try_bb,
+            try_end: finally_bb.clone(),
+            handler_start: finally_bb,
+            handler_end: after_bb.clone(),
+            class_tok: 0,
+        });
+    }
+
+    {
+        let mut builder = ctx.method_builder.borrow_mut();
+        // clauses of nested try are already pushed, so inner clauses are before outer ones
+        builder.excepts.append(&mut excepts);
+        builder.set_cur_bb(after_bb);
+    }
+
+    RValType::Void
+}
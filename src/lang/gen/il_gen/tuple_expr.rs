@@ -0,0 +1,217 @@
+use super::super::super::ast::{ASTKind, ASTType, AST};
+use super::super::super::diagnostic::ErrCode;
+use super::super::super::mod_mgr::{
+    get_tuple_type, tuple_arity, tuple_field_name, Type, TUPLE_MAX_ARITY, TUPLE_MIN_ARITY,
+};
+use super::super::super::util::Span;
+use super::super::{CodeGenCtx, RValType, ValExpectation};
+use super::gen;
+
+use xir::attrib::LocalAttrib;
+use xir::tok::to_tok;
+use xir::Inst;
+
+use std::ptr::NonNull;
+
+/// Tuple type and its element types if ty is a tuple
+fn as_tuple(ty: &RValType) -> Option<(&Type, &[RValType])> {
+    if let RValType::GenericInst(false, tuple_ty, elem_tys) = ty {
+        let tuple_ty = unsafe { tuple_ty.as_ref() };
+        if tuple_arity(tuple_ty).is_some() {
+            return Some((tuple_ty, elem_tys));
+        }
+    }
+    None
+}
+
+/// Token of the i-th field of tuple
+fn tuple_field(ctx: &CodeGenCtx, tuple_ty: &Type, elem_tys: &[RValType], i: usize) -> u32 {
+    let name = tuple_field_name(i);
+    let f = tuple_ty.fields.get(&name).unwrap();
+    let mut builder = ctx.module.builder.borrow_mut();
+    let sig = builder.add_field_sig(&f.ty);
+    let (f_idx, tok_tag) = builder.add_const_inst_member(tuple_ty, elem_tys, &name, sig);
+    to_tok(f_idx, tok_tag)
+}
+
+pub fn gen_tuple(ctx: &CodeGenCtx, span: Span, elems: &[Box<AST>]) -> RValType {
+    let tuple_ty = if let Some(tuple_ty) = get_tuple_type(ctx.mgr, elems.len()) {
+        tuple_ty
+    } else {
+        ctx.error(
+            ErrCode::TypeMismatch,
+            span,
+            format!(
+                "Tuple should have {} to {} elements but found {}",
+                TUPLE_MIN_ARITY,
+                TUPLE_MAX_ARITY,
+                elems.len()
+            ),
+        );
+    };
+
+    // elements are evaluated in order before the tuple is created
+    let mut elem_tys: Vec<RValType> = Vec::new();
+    let mut elem_locals: Vec<u16> = Vec::new();
+    for elem in elems.iter() {
+        let elem_ty = gen(ctx, elem, ValExpectation::RVal).expect_rval();
+        if let RValType::Void | RValType::Never = elem_ty {
+            ctx.error(
+                ErrCode::TypeMismatch,
+                elem.span,
+                format!("Tuple element cannot be {}", ctx.ty_name(&elem_ty)),
+            );
+        }
+        let idx = ctx
+            .locals
+            .borrow_mut()
+            .add_tmp(elem_ty.clone(), LocalAttrib::default(), true);
+        ctx.method_builder.borrow_mut().add_inst_stloc(idx);
+        elem_tys.push(elem_ty);
+        elem_locals.push(idx);
+    }
+
+    let ret = RValType::GenericInst(
+        false,
+        NonNull::new(tuple_ty as *const Type as *mut Type).unwrap(),
+        elem_tys,
+    );
+    let tmp = ctx
+        .locals
+        .borrow_mut()
+        .add_tmp(ret.clone(), LocalAttrib::default(), true);
+    if let RValType::GenericInst(_, _, elem_tys) = &ret {
+        for (i, elem_idx) in elem_locals.into_iter().enumerate() {
+            let f_tok = tuple_field(ctx, tuple_ty, elem_tys, i);
+            let mut method_builder = ctx.method_builder.borrow_mut();
+            method_builder.add_inst_ldloca(tmp);
+            method_builder.add_inst_ldloc(elem_idx);
+            method_builder.add_inst(Inst::StFld(f_tok));
+        }
+    }
+    ctx.method_builder.borrow_mut().add_inst_ldloc(tmp);
+    ret
+}
+
+/// let (x, (y, _)) = init;
+pub fn gen_let_tuple(
+    ctx: &CodeGenCtx,
+    span: Span,
+    pattern: &AST,
+    flag: &LocalAttrib,
+    ty: &ASTType,
+    init: &AST,
+) {
+    if let ASTKind::None = init.kind {
+        ctx.error(
+            ErrCode::InvalidExpr,
+            span,
+            "Tuple pattern should be initialized",
+        );
+    }
+
+    let init_ty = gen(ctx, init, ValExpectation::RVal).expect_rval();
+    if let ASTType::None = ty {
+        // no type, induce type from return value of init
+    } else {
+        let ty = ctx.get_ty(ty, span);
+        if ty != init_ty {
+            ctx.error(
+                ErrCode::TypeMismatch,
+                init.span,
+                format!(
+                    "Cannot assign {} to tuple pattern of {}",
+                    ctx.ty_name(&init_ty),
+                    ctx.ty_name(&ty)
+                ),
+            );
+        }
+    }
+
+    let tmp = ctx
+        .locals
+        .borrow_mut()
+        .add_tmp(init_ty.clone(), LocalAttrib::default(), true);
+    ctx.method_builder.borrow_mut().add_inst_stloc(tmp);
+    gen_destructure(ctx, pattern, tmp, &init_ty, flag, &mut vec![]);
+}
+
+/// Bind ids in pattern to value in local val_idx
+fn gen_destructure(
+    ctx: &CodeGenCtx,
+    pattern: &AST,
+    val_idx: u16,
+    val_ty: &RValType,
+    flag: &LocalAttrib,
+    bound: &mut Vec<String>,
+) {
+    match &pattern.kind {
+        ASTKind::Id(id) => {
+            if id == "_" {
+                return;
+            }
+            if bound.contains(id) {
+                ctx.error(
+                    ErrCode::Duplicated,
+                    pattern.span,
+                    format!(
+                        "Identifier {} is bound more than once in the same pattern",
+                        id
+                    ),
+                );
+            }
+            bound.push(id.to_owned());
+            let idx = ctx.locals.borrow_mut().add(id, val_ty.clone(), *flag, true);
+            let mut method_builder = ctx.method_builder.borrow_mut();
+            method_builder.add_inst_ldloc(val_idx);
+            method_builder.add_inst_stloc(idx);
+        }
+        ASTKind::TuplePattern(sub_patterns) => {
+            let (tuple_ty, elem_tys) = if let Some(tuple) = as_tuple(val_ty) {
+                tuple
+            } else {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    pattern.span,
+                    format!(
+                        "Cannot destructure {} with tuple pattern",
+                        ctx.ty_name(val_ty)
+                    ),
+                );
+            };
+            if sub_patterns.len() != elem_tys.len() {
+                ctx.error(
+                    ErrCode::TypeMismatch,
+                    pattern.span,
+                    format!(
+                        "Tuple {} has {} elements but pattern has {}",
+                        ctx.ty_name(val_ty),
+                        elem_tys.len(),
+                        sub_patterns.len()
+                    ),
+                );
+            }
+
+            for (i, (sub, elem_ty)) in sub_patterns.iter().zip(elem_tys.iter()).enumerate() {
+                if let ASTKind::Id(id) = &sub.kind {
+                    if id == "_" {
+                        continue;
+                    }
+                }
+                let f_tok = tuple_field(ctx, tuple_ty, elem_tys, i);
+                let elem_idx =
+                    ctx.locals
+                        .borrow_mut()
+                        .add_tmp(elem_ty.clone(), LocalAttrib::default(), true);
+                {
+                    let mut method_builder = ctx.method_builder.borrow_mut();
+                    method_builder.add_inst_ldloca(val_idx);
+                    method_builder.add_inst(Inst::LdFld(f_tok));
+                    method_builder.add_inst_stloc(elem_idx);
+                }
+                gen_destructure(ctx, sub, elem_idx, elem_ty, flag, bound);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
@@ -1,134 +1,310 @@
-use super::super::ast::AST;
+use super::super::ast::{ASTKind, AST};
+use super::super::diagnostic::ErrCode;
+use super::super::util::Span;
+use super::RValType;
 
-impl AST {
-    pub fn is_constant(&self) -> bool {
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Value of an expression evaluated at compile time
+#[derive(Clone, PartialEq)]
+pub enum ConstVal {
+    Bool(bool),
+    /// value and type, value is always in the range of the type
+    Int(i128, RValType),
+    /// value and type, value is rounded to f32 if type is f32
+    Float(f64, RValType),
+    Char(u32),
+    String(String),
+}
+
+impl ConstVal {
+    pub fn ty(&self) -> RValType {
         match self {
-            Self::File(_, _, _, _)
-            | Self::Use(_, _)
-            | Self::CustomAttr(_, _)
-            | Self::Class(_, _, _, _, _, _, _)
-            | Self::Method(_, _, _, _, _, _)
-            | Self::Field(_, _, _, _)
-            | Self::Param(_, _, _)
-            | Self::Let(_, _, _, _)
-            | Self::Loop(_)
-            | Self::Return(_)
-            | Self::Continue
-            | Self::Break(_)
-            | Self::OpCall(_, _)
-            | Self::Id(_)
-            | Self::TuplePattern(_)
-            | Self::TypeBool
-            | Self::TypeChar
-            | Self::TypeI32
-            | Self::TypeF64
-            | Self::TypeTuple(_)
-            | Self::Path(_)
-            | Self::TypeArr(_, _) => false,
-            Self::ExprStmt(stmt) => stmt.is_constant(),
-            Self::Block(children) => {
-                children.len() == 0 || (children.len() == 1 && children[0].is_constant())
-            }
-            Self::If(cond, then, els) => {
-                cond.is_constant() && then.is_constant() && els.is_constant()
-            }
-            Self::OpPos(o) => o.is_constant(),
-            Self::OpNeg(o) => o.is_constant(),
-            Self::OpAdd(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpSub(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpMul(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpDiv(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpMod(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpLogNot(o1) => o1.is_constant(),
-            Self::OpLogAnd(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpLogOr(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpEq(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpNe(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpGe(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpGt(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpLe(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpLt(o1, o2) => o1.is_constant() && o2.is_constant(),
-            Self::OpCast(_, v) => v.is_constant(),
-            Self::StructExprField(_, _) => false,
-            Self::OpAssign(_, _)
-            | Self::OpNew(_, _)
-            | Self::OpStaticAccess(_, _)
-            | Self::OpObjAccess(_, _)
-            | Self::OpArrayAccess(_, _) => false,
-            Self::Null
-            | Self::Bool(_)
-            | Self::Int(_)
-            | Self::Float(_)
-            | Self::Char(_)
-            | Self::String(_)
-            | Self::None => true,
+            Self::Bool(_) => RValType::Bool,
+            Self::Int(_, ty) | Self::Float(_, ty) => ty.clone(),
+            Self::Char(_) => RValType::Char,
+            Self::String(_) => RValType::String,
         }
     }
 }
 
-pub fn literal_type(ast: &Box<AST>) -> AST {
-    match ast.as_ref() {
-        AST::Bool(_) => AST::TypeBool,
-        AST::Int(_) => AST::TypeI32,
-        AST::Float(_) => AST::TypeF64,
-        AST::String(_) => unimplemented!(),
-        AST::Char(_) => AST::TypeChar,
-        AST::Null => panic!("Type of null is unknown"),
-        AST::None => AST::None,
-        _ => unreachable!(),
+impl fmt::Display for ConstVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(v) => write!(f, "{}", v),
+            Self::Int(v, _) => write!(f, "{}", v),
+            Self::Float(v, _) => write!(f, "{}", v),
+            Self::Char(v) => write!(f, "'{}'", std::char::from_u32(*v).unwrap()),
+            Self::String(v) => write!(f, "\"{}\"", v.escape_default()),
+        }
     }
 }
 
-pub fn constant_folding(ast: &Box<AST>) -> AST {
-    match ast.as_ref() {
-        AST::Block(stmts) => {
-            if stmts.len() == 0 {
-                AST::None
-            } else if stmts.len() == 1 {
-                constant_folding(&stmts[0])
-            } else {
-                unreachable!();
-            }
-        }
-        AST::If(cond, then, els) => {
-            let cond = constant_folding(cond);
-            match cond {
-                AST::Bool(true) => constant_folding(then),
-                AST::Bool(false) => constant_folding(els),
-                _ => panic!("Invalid condition in if statement, neither true nor false"),
-            }
+pub enum EvalErr {
+    /// Expression cannot be evaluated at compile time
+    NotConst(Span),
+    /// Expression is constant but cannot be evaluated, e.g. divided by zero
+    Invalid(ErrCode, Span, String),
+}
+
+/// Evaluate a constant expression
+///
+/// Literals and arithmetic, bitwise, comparison and logical ops on them are constant.
+/// Integer ops wrap on overflow like they do in vm.
+pub fn eval(ast: &AST) -> Result<ConstVal, EvalErr> {
+    let span = ast.span;
+    match &ast.kind {
+        ASTKind::Bool(v) => Ok(ConstVal::Bool(*v)),
+        ASTKind::Int(v, ty) => Ok(ConstVal::Int(*v, RValType::from_lit(ty))),
+        ASTKind::Float(v, ty) => {
+            let ty = RValType::from_lit(ty);
+            Ok(ConstVal::Float(round_float(*v, &ty), ty))
         }
-        AST::OpCast(ty, val) => match constant_folding(val) {
-            AST::Bool(v) => match ty.as_ref() {
-                AST::TypeBool => AST::Bool(v),
-                _ => panic!("Invalid cast. Bool value cannot be cast to other type"),
-            },
-            AST::Int(v) => match ty.as_ref() {
-                AST::TypeI32 => AST::Int(v),
-                AST::TypeF64 => AST::Float(v as f64),
-                AST::TypeChar => AST::Char(v as u32),
-                _ => panic!("Invalid cast. I32 value can only be cast to i32/f64/char"),
-            },
-            AST::Float(v) => match ty.as_ref() {
-                AST::TypeI32 => AST::Int(v as i32),
-                AST::TypeF64 => AST::Float(v),
-                _ => panic!("Invalid cast. F64 value can only be cast to i32/f64"),
-            },
-            AST::Char(v) => match ty.as_ref() {
-                AST::TypeChar => AST::Char(v),
-                AST::TypeI32 => AST::Int(v as i32),
-                _ => panic!("Invalid cast. Char value can only be cast to i32/char"),
-            },
-            AST::String(_) => panic!("Cannot cast string literal"),
-            _ => unimplemented!(),
+        ASTKind::Char(v) => Ok(ConstVal::Char(*v)),
+        ASTKind::String(v) => Ok(ConstVal::String(v.clone())),
+        ASTKind::OpPos(v) => match eval(v)? {
+            val @ ConstVal::Int(_, _) | val @ ConstVal::Float(_, _) => Ok(val),
+            _ => Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                v.span,
+                "pos op is only available for numeric operand".to_owned(),
+            )),
+        },
+        ASTKind::OpNeg(v) => match eval(v)? {
+            ConstVal::Int(v, ty) if !ty.is_unsigned() => Ok(ConstVal::Int(wrap_int(-v, &ty), ty)),
+            ConstVal::Float(v, ty) => Ok(ConstVal::Float(-v, ty)),
+            _ => Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                v.span,
+                "neg op is only available for signed int or float operand".to_owned(),
+            )),
+        },
+        ASTKind::OpLogNot(v) => match eval(v)? {
+            ConstVal::Bool(v) => Ok(ConstVal::Bool(!v)),
+            _ => Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                v.span,
+                "not op is only available for bool operand".to_owned(),
+            )),
         },
-        AST::Null => AST::Null,
-        AST::Bool(v) => AST::Bool(*v),
-        AST::Int(v) => AST::Int(*v),
-        AST::Float(v) => AST::Float(*v),
-        AST::Char(v) => AST::Char(*v),
-        AST::String(v) => AST::String(v.clone()),
-        AST::None => AST::None,
-        _ => unimplemented!(),
+        ASTKind::OpBitNot(v) => match eval(v)? {
+            ConstVal::Int(v, ty) => Ok(ConstVal::Int(wrap_int(!v, &ty), ty)),
+            _ => Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                v.span,
+                "bitwise not op is only available for int operand".to_owned(),
+            )),
+        },
+        ASTKind::OpLogAnd(lhs, rhs) => Ok(ConstVal::Bool(eval_cond(lhs)? && eval_cond(rhs)?)),
+        ASTKind::OpLogOr(lhs, rhs) => Ok(ConstVal::Bool(eval_cond(lhs)? || eval_cond(rhs)?)),
+        ASTKind::OpAdd(lhs, rhs)
+        | ASTKind::OpSub(lhs, rhs)
+        | ASTKind::OpMul(lhs, rhs)
+        | ASTKind::OpDiv(lhs, rhs)
+        | ASTKind::OpMod(lhs, rhs) => eval_numeric(ast, lhs, rhs),
+        ASTKind::OpBitAnd(lhs, rhs) | ASTKind::OpBitOr(lhs, rhs) | ASTKind::OpBitXor(lhs, rhs) => {
+            eval_bitwise(ast, lhs, rhs)
+        }
+        ASTKind::OpShl(lhs, rhs) | ASTKind::OpShr(lhs, rhs) => eval_shift(ast, lhs, rhs),
+        ASTKind::OpEq(lhs, rhs)
+        | ASTKind::OpNe(lhs, rhs)
+        | ASTKind::OpGe(lhs, rhs)
+        | ASTKind::OpGt(lhs, rhs)
+        | ASTKind::OpLe(lhs, rhs)
+        | ASTKind::OpLt(lhs, rhs) => eval_cmp(ast, lhs, rhs),
+        _ => Err(EvalErr::NotConst(span)),
+    }
+}
+
+fn eval_cond(ast: &AST) -> Result<bool, EvalErr> {
+    match eval(ast)? {
+        ConstVal::Bool(v) => Ok(v),
+        v => Err(EvalErr::Invalid(
+            ErrCode::TypeMismatch,
+            ast.span,
+            format!("Cond should be bool but found {}", v.ty()),
+        )),
+    }
+}
+
+fn eval_numeric(ast: &AST, lhs: &AST, rhs: &AST) -> Result<ConstVal, EvalErr> {
+    let l = eval(lhs)?;
+    let r = eval(rhs)?;
+    Ok(match (l, r) {
+        (ConstVal::Int(l, lty), ConstVal::Int(r, rty)) if lty == rty => {
+            // results are computed in i128 then truncated, which is the same as wrapping ops
+            let v = match &ast.kind {
+                ASTKind::OpAdd(_, _) => l.wrapping_add(r),
+                ASTKind::OpSub(_, _) => l.wrapping_sub(r),
+                ASTKind::OpMul(_, _) => l.wrapping_mul(r),
+                ASTKind::OpDiv(_, _) | ASTKind::OpMod(_, _) if r == 0 => {
+                    return Err(EvalErr::Invalid(
+                        ErrCode::DivideByZero,
+                        rhs.span,
+                        "Divide by constant zero".to_owned(),
+                    ));
+                }
+                ASTKind::OpDiv(_, _) => l / r,
+                ASTKind::OpMod(_, _) => l % r,
+                _ => unreachable!(),
+            };
+            ConstVal::Int(wrap_int(v, &lty), lty)
+        }
+        (ConstVal::Float(l, lty), ConstVal::Float(r, rty)) if lty == rty => {
+            let v = match &ast.kind {
+                ASTKind::OpAdd(_, _) => l + r,
+                ASTKind::OpSub(_, _) => l - r,
+                ASTKind::OpMul(_, _) => l * r,
+                ASTKind::OpDiv(_, _) => l / r,
+                ASTKind::OpMod(_, _) => l % r,
+                _ => unreachable!(),
+            };
+            ConstVal::Float(round_float(v, &lty), lty)
+        }
+        (l, r) => {
+            return Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                ast.span,
+                format!(
+                    "Numeric op cannot be applied between {} and {}",
+                    l.ty(),
+                    r.ty()
+                ),
+            ));
+        }
+    })
+}
+
+fn eval_bitwise(ast: &AST, lhs: &AST, rhs: &AST) -> Result<ConstVal, EvalErr> {
+    let l = eval(lhs)?;
+    let r = eval(rhs)?;
+    Ok(match (l, r) {
+        // two's complement of i128 agrees with the truncated ints
+        (ConstVal::Int(l, lty), ConstVal::Int(r, rty)) if lty == rty => {
+            let v = match &ast.kind {
+                ASTKind::OpBitAnd(_, _) => l & r,
+                ASTKind::OpBitOr(_, _) => l | r,
+                ASTKind::OpBitXor(_, _) => l ^ r,
+                _ => unreachable!(),
+            };
+            ConstVal::Int(wrap_int(v, &lty), lty)
+        }
+        (ConstVal::Bool(l), ConstVal::Bool(r)) => ConstVal::Bool(match &ast.kind {
+            ASTKind::OpBitAnd(_, _) => l & r,
+            ASTKind::OpBitOr(_, _) => l | r,
+            ASTKind::OpBitXor(_, _) => l ^ r,
+            _ => unreachable!(),
+        }),
+        (l, r) => {
+            return Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                ast.span,
+                format!(
+                    "Bitwise op cannot be applied between {} and {}",
+                    l.ty(),
+                    r.ty()
+                ),
+            ));
+        }
+    })
+}
+
+fn eval_shift(ast: &AST, lhs: &AST, rhs: &AST) -> Result<ConstVal, EvalErr> {
+    let (l, lty) = match eval(lhs)? {
+        ConstVal::Int(l, lty) => (l, lty),
+        l => {
+            return Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                lhs.span,
+                format!("Shift op cannot be applied on {}", l.ty()),
+            ));
+        }
+    };
+    let r = match eval(rhs)? {
+        ConstVal::Int(r, _) => r,
+        r => {
+            return Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                rhs.span,
+                format!("Shift amount should be int but found {}", r.ty()),
+            ));
+        }
+    };
+
+    // same as shl and shr in vm, amount is masked by the bit width of lhs on eval stack
+    let width = match lty {
+        RValType::U64 | RValType::I64 => 64,
+        RValType::USize | RValType::ISize => usize::BITS,
+        _ => 32,
+    };
+    let amount = (r as u32) & (width - 1);
+    // unsigned values are never negative, so >> is shr.un for them
+    let v = match &ast.kind {
+        ASTKind::OpShl(_, _) => l << amount,
+        ASTKind::OpShr(_, _) => l >> amount,
+        _ => unreachable!(),
+    };
+    Ok(ConstVal::Int(wrap_int(v, &lty), lty))
+}
+
+fn eval_cmp(ast: &AST, lhs: &AST, rhs: &AST) -> Result<ConstVal, EvalErr> {
+    let l = eval(lhs)?;
+    let r = eval(rhs)?;
+    let is_eq = matches!(ast.kind, ASTKind::OpEq(_, _) | ASTKind::OpNe(_, _));
+    let ord = match (&l, &r) {
+        (ConstVal::Int(l, lty), ConstVal::Int(r, rty)) if lty == rty => l.partial_cmp(r),
+        (ConstVal::Float(l, lty), ConstVal::Float(r, rty)) if lty == rty => l.partial_cmp(r),
+        (ConstVal::Char(l), ConstVal::Char(r)) => l.partial_cmp(r),
+        (ConstVal::Bool(l), ConstVal::Bool(r)) if is_eq => l.partial_cmp(r),
+        // strings are compared by reference in vm
+        (ConstVal::String(_), ConstVal::String(_)) => return Err(EvalErr::NotConst(ast.span)),
+        _ => {
+            return Err(EvalErr::Invalid(
+                ErrCode::TypeMismatch,
+                ast.span,
+                format!("Cmp op cannot be applied between {} and {}", l.ty(), r.ty()),
+            ));
+        }
+    };
+
+    // same as the insts generated by il_gen::op::gen_cmp,
+    // ord is None if any operand is NaN, then only != is true
+    let gt = ord == Some(Ordering::Greater);
+    let lt = ord == Some(Ordering::Less);
+    let eq = ord == Some(Ordering::Equal);
+    Ok(ConstVal::Bool(match &ast.kind {
+        ASTKind::OpEq(_, _) => eq,
+        ASTKind::OpNe(_, _) => !eq,
+        ASTKind::OpGe(_, _) => gt || eq,
+        ASTKind::OpGt(_, _) => gt,
+        ASTKind::OpLe(_, _) => lt || eq,
+        ASTKind::OpLt(_, _) => lt,
+        _ => unreachable!(),
+    }))
+}
+
+/// Truncate v to the range of int type ty, same as conv.* in vm
+fn wrap_int(v: i128, ty: &RValType) -> i128 {
+    match ty {
+        RValType::U8 => v as u8 as i128,
+        RValType::I8 => v as i8 as i128,
+        RValType::U16 => v as u16 as i128,
+        RValType::I16 => v as i16 as i128,
+        RValType::U32 => v as u32 as i128,
+        RValType::I32 => v as i32 as i128,
+        RValType::U64 => v as u64 as i128,
+        RValType::I64 => v as i64 as i128,
+        RValType::USize => v as usize as i128,
+        RValType::ISize => v as isize as i128,
+        _ => unreachable!(),
+    }
+}
+
+fn round_float(v: f64, ty: &RValType) -> f64 {
+    if let RValType::F32 = ty {
+        v as f32 as f64
+    } else {
+        v
     }
 }
@@ -1,15 +1,33 @@
+use xir::code::ExceptionClauseKind;
 use xir::inst::Inst;
-use xir::tok::to_tok;
+use xir::tok::{to_tok, TokTag};
 
 use std::cell::RefCell;
 use std::mem;
 
-use super::basic_block::{BasicBlock, LLCursor, LinkedList};
+use super::basic_block::{BasicBlock, DebugMarker, LLCursor, LinkedList};
 use super::{Builder, RValType};
 
+/// Exception clause bounded by basic blocks, offsets are calculated in Builder::done
+pub struct ExceptionClauseBB {
+    pub kind: ExceptionClauseKind,
+    /// first bb of the try block
+    pub try_start: LLCursor<BasicBlock>,
+    /// first bb after the try block
+    pub try_end: LLCursor<BasicBlock>,
+    /// first bb of the handler
+    pub handler_start: LLCursor<BasicBlock>,
+    /// first bb after the handler
+    pub handler_end: LLCursor<BasicBlock>,
+    /// exception class token if kind is Catch
+    pub class_tok: u32,
+}
+
 pub struct MethodBuilder {
     pub bb: LinkedList<BasicBlock>,
     cur_bb: LLCursor<BasicBlock>,
+    /// inner clauses are pushed before outer clauses
+    pub excepts: Vec<ExceptionClauseBB>,
 }
 
 impl MethodBuilder {
@@ -18,7 +36,11 @@ impl MethodBuilder {
         bb.push_back(BasicBlock::new());
         let cur_bb = bb.cursor_back_mut();
 
-        MethodBuilder { bb, cur_bb }
+        MethodBuilder {
+            bb,
+            cur_bb,
+            excepts: vec![],
+        }
     }
 
     pub fn insert_after_cur(&mut self) -> LLCursor<BasicBlock> {
@@ -26,6 +48,12 @@ impl MethodBuilder {
             .insert_after_cursor(&mut self.cur_bb, BasicBlock::new())
     }
 
+    /// append a basic block to the end of method
+    pub fn push_back_bb(&mut self) -> LLCursor<BasicBlock> {
+        self.bb.push_back(BasicBlock::new());
+        self.bb.cursor_back_mut()
+    }
+
     pub fn set_cur_bb(&mut self, cur_bb: LLCursor<BasicBlock>) -> LLCursor<BasicBlock> {
         let mut cur_bb = cur_bb;
         mem::swap(&mut cur_bb, &mut self.cur_bb);
@@ -40,9 +68,13 @@ impl MethodBuilder {
                 | Inst::BGt(_)
                 | Inst::BLe(_)
                 | Inst::BLt(_)
+                | Inst::BNe(_)
                 | Inst::Br(_)
                 | Inst::BrFalse(_)
                 | Inst::BrTrue(_)
+                | Inst::Leave(_)
+                | Inst::Throw
+                | Inst::EndFinally
                 | Inst::Ret => true,
                 _ => false,
             }
@@ -58,6 +90,18 @@ impl MethodBuilder {
         self
     }
 
+    /// Marker is before the next inst added to the current bb
+    pub fn add_marker(&mut self, marker: DebugMarker) -> &mut Self {
+        let cur_bb = self.cur_bb.as_mut().unwrap();
+        cur_bb.markers.push((cur_bb.insts.len(), marker));
+        self
+    }
+
+    pub fn mark_float_cmp(&mut self) -> &mut Self {
+        self.cur_bb.as_mut().unwrap().has_float_cmp = true;
+        self
+    }
+
     pub fn add_brfalse(&mut self, target: LLCursor<BasicBlock>) -> &mut Self {
         let cur_bb = self.cur_bb.as_mut().unwrap();
         cur_bb.push(Inst::BrFalse(0));
@@ -91,6 +135,17 @@ impl MethodBuilder {
         self
     }
 
+    pub fn add_leave(&mut self, target: LLCursor<BasicBlock>) -> &mut Self {
+        let cur_bb = self.cur_bb.as_mut().unwrap();
+        cur_bb.push(Inst::Leave(0));
+        if let Some(_) = cur_bb.target {
+            unreachable!();
+        } else {
+            cur_bb.target = Some(target);
+        }
+        self
+    }
+
     pub fn add_inst_stloc(&mut self, local_idx: u16) -> &mut Self {
         self.add_inst(match local_idx {
             0 => Inst::StLoc0,
@@ -190,7 +245,13 @@ impl MethodBuilder {
 
     pub fn add_stelem(&mut self, ele_ty: &RValType, builder: &RefCell<Builder>) -> &mut Self {
         match ele_ty {
-            RValType::I32 => self.add_inst(Inst::StElemI4),
+            RValType::Bool | RValType::U8 | RValType::I8 => self.add_inst(Inst::StElemI1),
+            RValType::Char | RValType::U16 | RValType::I16 => self.add_inst(Inst::StElemI2),
+            RValType::U32 | RValType::I32 => self.add_inst(Inst::StElemI4),
+            RValType::U64 | RValType::I64 => self.add_inst(Inst::StElemI8),
+            RValType::USize | RValType::ISize => self.add_inst(Inst::StElemI),
+            RValType::F32 => self.add_inst(Inst::StElemR4),
+            RValType::F64 => self.add_inst(Inst::StElemR8),
             RValType::String | RValType::Array(_) | RValType::Class(_) => {
                 self.add_inst(Inst::StElemRef)
             }
@@ -201,14 +262,28 @@ impl MethodBuilder {
                     .add_const_class(ty_ref.modname(), &ty_ref.name);
                 self.add_inst(Inst::StElem(to_tok(idx, tag.to_tok_tag())))
             }
-            RValType::GenericInst(_, _, _) => todo!(),
+            RValType::GenericInst(true, _, _) => self.add_inst(Inst::StElemRef),
+            RValType::GenericInst(false, _, _) | RValType::Var(_) | RValType::MVar(_) => {
+                let idx = builder.borrow_mut().add_const_typespec(ele_ty);
+                self.add_inst(Inst::StElem(to_tok(idx, TokTag::TypeSpec)))
+            }
             _ => unimplemented!(),
         }
     }
 
     pub fn add_ldelem(&mut self, ele_ty: &RValType, builder: &RefCell<Builder>) -> &mut Self {
         match ele_ty {
+            RValType::Bool | RValType::U8 => self.add_inst(Inst::LdElemU1),
+            RValType::I8 => self.add_inst(Inst::LdElemI1),
+            RValType::Char | RValType::U16 => self.add_inst(Inst::LdElemU2),
+            RValType::I16 => self.add_inst(Inst::LdElemI2),
+            RValType::U32 => self.add_inst(Inst::LdElemU4),
             RValType::I32 => self.add_inst(Inst::LdElemI4),
+            // there is no ldelem.u8, ldelem.i8 loads the same bits
+            RValType::U64 | RValType::I64 => self.add_inst(Inst::LdElemI8),
+            RValType::USize | RValType::ISize => self.add_inst(Inst::LdElemI),
+            RValType::F32 => self.add_inst(Inst::LdElemR4),
+            RValType::F64 => self.add_inst(Inst::LdElemR8),
             RValType::String | RValType::Array(_) | RValType::Class(_) => {
                 self.add_inst(Inst::LdElemRef)
             }
@@ -219,16 +294,36 @@ impl MethodBuilder {
                     .add_const_class(ty_ref.modname(), &ty_ref.name);
                 self.add_inst(Inst::LdElem(to_tok(idx, tag.to_tok_tag())))
             }
-            RValType::GenericInst(_, _, _) => todo!(),
+            RValType::GenericInst(true, _, _) => self.add_inst(Inst::LdElemRef),
+            RValType::GenericInst(false, _, _) | RValType::Var(_) | RValType::MVar(_) => {
+                let idx = builder.borrow_mut().add_const_typespec(ele_ty);
+                self.add_inst(Inst::LdElem(to_tok(idx, TokTag::TypeSpec)))
+            }
             _ => unimplemented!(),
         }
     }
 
     pub fn add_ldelema(&mut self, ele_ty: &RValType, builder: &RefCell<Builder>) -> &mut Self {
         match ele_ty {
-            RValType::I32 => {
-                let (idx, tag) = builder.borrow_mut().add_const_class("std", "Int32");
-                self.add_inst(Inst::LdElem(to_tok(idx, tag.to_tok_tag())))
+            RValType::Bool
+            | RValType::Char
+            | RValType::U8
+            | RValType::I8
+            | RValType::U16
+            | RValType::I16
+            | RValType::U32
+            | RValType::I32
+            | RValType::U64
+            | RValType::I64
+            | RValType::USize
+            | RValType::ISize
+            | RValType::F32
+            | RValType::F64 => {
+                // element of a primitive array is the std struct that wraps the primitive
+                let (idx, tag) = builder
+                    .borrow_mut()
+                    .add_const_class("std", ele_ty.prim_struct_name());
+                self.add_inst(Inst::LdElemA(to_tok(idx, tag.to_tok_tag())))
             }
             RValType::String | RValType::Array(_) | RValType::Class(_) => {
                 // ref types don't need ldelema
@@ -241,6 +336,42 @@ impl MethodBuilder {
                     .add_const_class(ty_ref.modname(), &ty_ref.name);
                 self.add_inst(Inst::LdElemA(to_tok(idx, tag.to_tok_tag())))
             }
+            RValType::GenericInst(false, _, _) => {
+                let idx = builder.borrow_mut().add_const_typespec(ele_ty);
+                self.add_inst(Inst::LdElemA(to_tok(idx, TokTag::TypeSpec)))
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    /// ldind.* of primitive type ty
+    pub fn add_ldind(&mut self, ty: &RValType) -> &mut Self {
+        match ty {
+            RValType::Bool | RValType::U8 => self.add_inst(Inst::LdIndU1),
+            RValType::I8 => self.add_inst(Inst::LdIndI1),
+            RValType::Char | RValType::U16 => self.add_inst(Inst::LdIndU2),
+            RValType::I16 => self.add_inst(Inst::LdIndI2),
+            RValType::U32 => self.add_inst(Inst::LdIndU4),
+            RValType::I32 => self.add_inst(Inst::LdIndI4),
+            // there is no ldind.u8, ldind.i8 loads the same bits
+            RValType::U64 | RValType::I64 => self.add_inst(Inst::LdIndI8),
+            RValType::USize | RValType::ISize => self.add_inst(Inst::LdIndI),
+            RValType::F32 => self.add_inst(Inst::LdIndR4),
+            RValType::F64 => self.add_inst(Inst::LdIndR8),
+            _ => unimplemented!(),
+        }
+    }
+
+    /// stind.* of primitive type ty
+    pub fn add_stind(&mut self, ty: &RValType) -> &mut Self {
+        match ty {
+            RValType::Bool | RValType::U8 | RValType::I8 => self.add_inst(Inst::StIndI1),
+            RValType::Char | RValType::U16 | RValType::I16 => self.add_inst(Inst::StIndI2),
+            RValType::U32 | RValType::I32 => self.add_inst(Inst::StIndI4),
+            RValType::U64 | RValType::I64 => self.add_inst(Inst::StIndI8),
+            RValType::USize | RValType::ISize => self.add_inst(Inst::StIndI),
+            RValType::F32 => self.add_inst(Inst::StIndR4),
+            RValType::F64 => self.add_inst(Inst::StIndR8),
             _ => unimplemented!(),
         }
     }
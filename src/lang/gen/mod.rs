@@ -1,16 +1,24 @@
 mod basic_block;
 mod builder;
 mod il_gen;
-// mod interpreter;
+mod interpreter;
 mod method_builder;
+mod optim;
 
-pub use basic_block::{BasicBlock, LLCursor};
+pub use basic_block::{BasicBlock, DebugMarker, LLCursor};
 pub use builder::Builder;
 pub use il_gen::{gen, gen_base_ctor};
-pub use method_builder::MethodBuilder;
+pub use interpreter::{eval, ConstVal, EvalErr};
+pub use method_builder::{ExceptionClauseBB, MethodBuilder};
 
 use super::ast::ASTType;
-use super::mod_mgr::{Crate, Field, Locals, Method, Module, ModuleBuildCtx, Type};
+use super::diagnostic::ErrCode;
+use super::mod_mgr::{
+    tuple_arity, Crate, Field, GenericParam, Locals, Method, Module, ModuleBuildCtx, Type,
+};
+use super::util::Span;
+
+use xir::inst::Inst;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -26,6 +34,15 @@ pub struct LoopCtx {
     pub ty: LoopType,
     pub continue_target: LLCursor<BasicBlock>,
     pub break_target: LLCursor<BasicBlock>,
+    /// number of try blocks and handlers enclosing the loop
+    pub try_depth: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TryBlockType {
+    Try,
+    Catch,
+    Finally,
 }
 
 pub struct CodeGenCtx<'c> {
@@ -38,14 +55,119 @@ pub struct CodeGenCtx<'c> {
     pub locals: RefCell<Locals>,
     pub method_builder: RefCell<MethodBuilder>,
     pub loop_ctx: RefCell<Vec<LoopCtx>>,
+    /// try blocks and handlers enclosing current code, innermost is the last
+    pub try_ctx: RefCell<Vec<TryBlockType>>,
+    /// epilogue that returns inside try blocks and catch handlers leave to,
+    /// and the local that holds the return value
+    pub ret_epilogue: RefCell<Option<(LLCursor<BasicBlock>, Option<u16>)>>,
 }
 
 impl<'mgr> CodeGenCtx<'mgr> {
-    fn get_ty(&self, ast: &ASTType) -> RValType {
-        self.module.get_rval_type(ast, self.mgr, self.class)
+    fn get_ty(&self, ast: &ASTType, span: Span) -> RValType {
+        self.module
+            .get_rval_type(ast, span, self.mgr, self.class, &self.method.generic_params)
+    }
+
+    /// Generic args of current class when it is accessed inside itself
+    pub fn class_generic_args(&self) -> Vec<RValType> {
+        (0..self.class.generic_params.len())
+            .map(|i| RValType::Var(i as u32))
+            .collect()
+    }
+
+    /// Resolve generic args of class and check arity
+    pub fn get_class_generic_args(
+        &self,
+        span: Span,
+        class: &Type,
+        generic_args: &[Box<ASTType>],
+    ) -> Vec<RValType> {
+        if generic_args.len() != class.generic_params.len() {
+            self.error(
+                ErrCode::InvalidGeneric,
+                span,
+                format!(
+                    "{} expects {} generic args but {} are given",
+                    class,
+                    class.generic_params.len(),
+                    generic_args.len()
+                ),
+            );
+        }
+        generic_args
+            .iter()
+            .map(|arg| self.get_ty(arg, span))
+            .collect()
+    }
+
+    /// Report an error if field is not accessible in current class
+    pub fn check_field_access(&self, span: Span, f: &Field) {
+        if !f.is_accessible_in(self.class) {
+            self.error(
+                ErrCode::Inaccessible,
+                span,
+                format!("Field {} is not accessible in {}", f, self.class),
+            );
+        }
+    }
+
+    /// Report an error if method is not accessible in current class
+    pub fn check_method_access(&self, span: Span, m: &Method) {
+        if !m.is_accessible_in(self.class) {
+            self.error(
+                ErrCode::Inaccessible,
+                span,
+                format!("Method {} is not accessible in {}", m, self.class),
+            );
+        }
+    }
+
+    /// Push a symbol table for a new lexical scope
+    pub fn push_scope(&self) {
+        let scope = self.locals.borrow_mut().push();
+        self.method_builder
+            .borrow_mut()
+            .add_marker(DebugMarker::ScopeStart(scope));
+    }
+
+    pub fn pop_scope(&self) {
+        let scope = self.locals.borrow_mut().pop();
+        self.method_builder
+            .borrow_mut()
+            .add_marker(DebugMarker::ScopeEnd(scope));
+    }
+
+    /// Mark the start of a statement for debug info
+    pub fn add_seq_point(&self, span: Span) {
+        self.method_builder
+            .borrow_mut()
+            .add_marker(DebugMarker::SeqPoint(span));
+    }
+
+    /// Name of ty in diagnostics, generic params are shown by their names in current method
+    pub fn ty_name<'a>(&'a self, ty: &'a RValType) -> RValTypeName<'a> {
+        RValTypeName {
+            ty,
+            class_generic_params: &self.class.generic_params,
+            method_generic_params: &self.method.generic_params,
+        }
+    }
+
+    /// Report an error in current method and terminate compilation
+    pub fn error<S: Into<String>>(&self, code: ErrCode, span: Span, msg: S) -> ! {
+        self.module.error(code, span, msg)
     }
 
     pub fn done(&self, optim_level: usize) {
+        if let Some((epilogue, ret_local)) = self.ret_epilogue.borrow_mut().take() {
+            let mut builder = self.method_builder.borrow_mut();
+            builder.set_cur_bb(epilogue);
+            if let Some(ret_local) = ret_local {
+                builder.add_inst_ldloc(ret_local);
+            }
+            builder.add_inst(Inst::Ret);
+        }
+
         let local_mut = self.locals.borrow();
         assert_eq!(
             local_mut.sym_tbl.len(),
@@ -57,6 +179,7 @@ impl<'mgr> CodeGenCtx<'mgr> {
             &mut self.method_builder.borrow_mut(),
             self.method.idx,
             &local_mut.locals,
+            &local_mut.scopes,
             optim_level >= 1,
         );
     }
@@ -88,9 +211,16 @@ pub enum ValExpectation {
 }
 
 pub enum SymType {
-    Method(Vec<NonNull<Method>>),
-    Field(NonNull<Field>),
-    Class(NonNull<Type>),
+    /// .0: generic args of the class through which methods are accessed;
+    /// .1: candidates;
+    /// .2: generic args of method.
+    Method(Vec<RValType>, Vec<NonNull<Method>>, Vec<RValType>),
+    /// .0: generic args of the class through which field is accessed;
+    /// .1: field.
+    Field(Vec<RValType>, NonNull<Field>),
+    /// .0: class;
+    /// .1: generic args, empty if class is not generic.
+    Class(NonNull<Type>, Vec<RValType>),
     Module(NonNull<Module>),
     // index into locals
     Local(usize),
@@ -100,6 +230,9 @@ pub enum SymType {
     Arg(usize),
     /// array element type
     ArrAcc(RValType),
+    /// .0: enum;
+    /// .1: index into variants.
+    Variant(NonNull<Type>, usize),
 }
 
 #[derive(Clone, Eq)]
@@ -107,7 +240,16 @@ pub enum RValType {
     Bool,
     U8,
     Char,
+    I8,
+    U16,
+    I16,
+    U32,
     I32,
+    U64,
+    I64,
+    USize,
+    ISize,
+    F32,
     F64,
     Void,
     Never,
@@ -118,14 +260,158 @@ pub enum RValType {
     /// .1: type;
     /// .3: generic args.
     GenericInst(bool, NonNull<Type>, Vec<RValType>),
+    /// index into generic params of class
+    Var(u32),
+    /// index into generic params of method
+    MVar(u32),
     ByRef(Box<RValType>),
     /// elety
     Array(Box<RValType>),
 }
 
 impl RValType {
+    /// Descriptor of this type used as the key of signatures in builder, e.g. `I`, `Ostd::String;`
     pub fn descriptor(&self) -> String {
-        format!("{}", self)
+        match self {
+            Self::Bool => String::from("Z"),
+            Self::U8 => String::from("B"),
+            Self::Char => String::from("C"),
+            Self::I8 => String::from("b"),
+            Self::U16 => String::from("S"),
+            Self::I16 => String::from("s"),
+            Self::U32 => String::from("u"),
+            Self::I32 => String::from("I"),
+            Self::U64 => String::from("L"),
+            Self::I64 => String::from("l"),
+            Self::USize => String::from("N"),
+            Self::ISize => String::from("n"),
+            Self::F32 => String::from("F"),
+            Self::F64 => String::from("D"),
+            Self::Void => String::from("V"),
+            Self::Never => String::from("!"),
+            Self::String => String::from("Ostd/String;"),
+            Self::Class(ty) => format!("O{};", unsafe { ty.as_ref() }),
+            Self::Value(ty) => format!("o{};", unsafe { ty.as_ref() }),
+            Self::GenericInst(is_class, ty, args) => format!(
+                "{}{}<{}>;",
+                if *is_class { "O" } else { "o" },
+                unsafe { ty.as_ref() },
+                args.iter().map(|arg| arg.descriptor()).collect::<String>()
+            ),
+            Self::Var(i) => format!("!{}", i),
+            Self::MVar(i) => format!("!!{}", i),
+            Self::ByRef(ty) => format!("&{}", ty.descriptor()),
+            Self::Array(ty) => format!("[{}", ty.descriptor()),
+        }
+    }
+
+    /// Type of an instance of class, generic_args is empty if class is not generic
+    pub fn from_class(class: NonNull<Type>, generic_args: Vec<RValType>) -> RValType {
+        let is_value = unsafe { class.as_ref() }.is_value_type();
+        if generic_args.is_empty() {
+            if is_value {
+                RValType::Value(class)
+            } else {
+                RValType::Class(class)
+            }
+        } else {
+            RValType::GenericInst(!is_value, class, generic_args)
+        }
+    }
+
+    /// Type of a literal, ty is the suffix of the literal
+    pub fn from_lit(ty: &ASTType) -> RValType {
+        match ty {
+            ASTType::U8 => RValType::U8,
+            ASTType::I8 => RValType::I8,
+            ASTType::U16 => RValType::U16,
+            ASTType::I16 => RValType::I16,
+            ASTType::U32 => RValType::U32,
+            ASTType::I32 => RValType::I32,
+            ASTType::U64 => RValType::U64,
+            ASTType::I64 => RValType::I64,
+            ASTType::USize => RValType::USize,
+            ASTType::ISize => RValType::ISize,
+            ASTType::F32 => RValType::F32,
+            ASTType::F64 => RValType::F64,
+            _ => unreachable!("{} is not a literal type", ty),
+        }
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(
+            self,
+            Self::U8
+                | Self::I8
+                | Self::U16
+                | Self::I16
+                | Self::U32
+                | Self::I32
+                | Self::U64
+                | Self::I64
+                | Self::USize
+                | Self::ISize
+        )
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            Self::U8 | Self::U16 | Self::U32 | Self::U64 | Self::USize
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// Name of the std struct that wraps a primitive type
+    pub fn prim_struct_name(&self) -> &'static str {
+        match self {
+            Self::U8 => "UInt8",
+            Self::I8 => "Int8",
+            Self::U16 => "UInt16",
+            Self::I16 => "Int16",
+            Self::U32 => "UInt32",
+            Self::I32 => "Int32",
+            Self::U64 => "UInt64",
+            Self::I64 => "Int64",
+            Self::USize => "USize",
+            Self::ISize => "ISize",
+            Self::F32 => "Float32",
+            Self::F64 => "Float64",
+            Self::Bool => "Boolean",
+            Self::Char => "Char",
+            _ => unreachable!(),
+        }
+    }
+
+    /// Replace generic params with generic args.
+    ///
+    /// Generic params are kept if there is no corresponding arg
+    pub fn instantiate(&self, class_args: &[RValType], method_args: &[RValType]) -> RValType {
+        match self {
+            Self::Var(i) => match class_args.get(*i as usize) {
+                Some(arg) => arg.clone(),
+                None => self.clone(),
+            },
+            Self::MVar(i) => match method_args.get(*i as usize) {
+                Some(arg) => arg.clone(),
+                None => self.clone(),
+            },
+            Self::GenericInst(is_class, ty, args) => Self::GenericInst(
+                *is_class,
+                *ty,
+                args.iter()
+                    .map(|arg| arg.instantiate(class_args, method_args))
+                    .collect(),
+            ),
+            Self::ByRef(ty) => Self::ByRef(Box::new(ty.instantiate(class_args, method_args))),
+            Self::Array(ele_ty) => {
+                Self::Array(Box::new(ele_ty.instantiate(class_args, method_args)))
+            }
+            _ => self.clone(),
+        }
     }
 }
 
@@ -135,7 +421,16 @@ impl PartialEq for RValType {
             (Self::Bool, Self::Bool)
             | (Self::U8, Self::U8)
             | (Self::Char, Self::Char)
+            | (Self::I8, Self::I8)
+            | (Self::U16, Self::U16)
+            | (Self::I16, Self::I16)
+            | (Self::U32, Self::U32)
             | (Self::I32, Self::I32)
+            | (Self::U64, Self::U64)
+            | (Self::I64, Self::I64)
+            | (Self::USize, Self::USize)
+            | (Self::ISize, Self::ISize)
+            | (Self::F32, Self::F32)
             | (Self::F64, Self::F64)
             | (Self::String, Self::String)
             | (Self::Void, Self::Void) => true,
@@ -144,6 +439,12 @@ impl PartialEq for RValType {
             },
             (Self::Class(ty1), Self::Class(ty2)) => ty1 == ty2,
             (Self::Value(ty1), Self::Value(ty2)) => ty1 == ty2,
+            (
+                Self::GenericInst(is_class1, ty1, args1),
+                Self::GenericInst(is_class2, ty2, args2),
+            ) => is_class1 == is_class2 && ty1 == ty2 && args1 == args2,
+            (Self::Var(i1), Self::Var(i2)) => i1 == i2,
+            (Self::MVar(i1), Self::MVar(i2)) => i1 == i2,
             (Self::ByRef(ty0), Self::ByRef(ty1)) => ty0 == ty1,
             (Self::Array(ele_ty0), Self::Array(ele_ty1)) => ele_ty0 == ele_ty1,
             _ => false,
@@ -152,21 +453,97 @@ impl PartialEq for RValType {
 }
 
 impl fmt::Display for RValType {
+    /// Name of this type in diagnostics. Generic params are shown by index,
+    /// see `RValTypeName` for their names
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Bool => write!(f, "Z"),
-            Self::U8 => write!(f, "B"),
-            Self::Char => write!(f, "C"),
-            Self::I32 => write!(f, "I"),
-            Self::F64 => write!(f, "D"),
-            Self::Void => write!(f, "V"),
-            Self::Never => write!(f, "!"),
-            Self::String => write!(f, "Ostd/String;"),
-            Self::Class(ty) => write!(f, "O{};", unsafe { ty.as_ref() }),
-            Self::Value(ty) => write!(f, "o{};", unsafe { ty.as_ref() }),
-            RValType::GenericInst(_, _, _) => todo!(),
-            Self::ByRef(ty) => write!(f, "&{}", ty),
-            Self::Array(ty) => write!(f, "[{}", ty),
+        RValTypeName {
+            ty: self,
+            class_generic_params: &[],
+            method_generic_params: &[],
+        }
+        .fmt(f)
+    }
+}
+
+/// Name of a type as it is written in xilang, used in diagnostics,
+/// e.g. `i32`, `string`, `(i32, string)`, `std::Object`, `i32[]`
+pub struct RValTypeName<'a> {
+    pub ty: &'a RValType,
+    /// generic params that `RValType::Var` and `RValType::MVar` refer to
+    pub class_generic_params: &'a [GenericParam],
+    pub method_generic_params: &'a [GenericParam],
+}
+
+impl<'a> RValTypeName<'a> {
+    fn of(&self, ty: &'a RValType) -> RValTypeName<'a> {
+        RValTypeName {
+            ty,
+            class_generic_params: self.class_generic_params,
+            method_generic_params: self.method_generic_params,
+        }
+    }
+
+    fn fmt_list(&self, f: &mut fmt::Formatter, tys: &'a [RValType]) -> fmt::Result {
+        for (i, ty) in tys.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", self.of(ty))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for RValTypeName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.ty {
+            RValType::Bool => write!(f, "bool"),
+            RValType::U8 => write!(f, "u8"),
+            RValType::Char => write!(f, "char"),
+            RValType::I8 => write!(f, "i8"),
+            RValType::U16 => write!(f, "u16"),
+            RValType::I16 => write!(f, "i16"),
+            RValType::U32 => write!(f, "u32"),
+            RValType::I32 => write!(f, "i32"),
+            RValType::U64 => write!(f, "u64"),
+            RValType::I64 => write!(f, "i64"),
+            RValType::USize => write!(f, "usize"),
+            RValType::ISize => write!(f, "isize"),
+            RValType::F32 => write!(f, "f32"),
+            RValType::F64 => write!(f, "f64"),
+            RValType::Void => write!(f, "()"),
+            RValType::Never => write!(f, "!"),
+            RValType::String => write!(f, "string"),
+            RValType::Class(ty) | RValType::Value(ty) => {
+                let ty = unsafe { ty.as_ref() };
+                if ty.modname() == "std" && ty.name == "String" {
+                    write!(f, "string")
+                } else {
+                    write!(f, "{}", ty)
+                }
+            }
+            RValType::GenericInst(_, ty, args) => {
+                let ty = unsafe { ty.as_ref() };
+                if tuple_arity(ty).is_some() {
+                    write!(f, "(")?;
+                    self.fmt_list(f, args)?;
+                    write!(f, ")")
+                } else {
+                    write!(f, "{}<", ty)?;
+                    self.fmt_list(f, args)?;
+                    write!(f, ">")
+                }
+            }
+            RValType::Var(i) => match self.class_generic_params.get(*i as usize) {
+                Some(param) => write!(f, "{}", param.name),
+                None => write!(f, "<class generic param {}>", i),
+            },
+            RValType::MVar(i) => match self.method_generic_params.get(*i as usize) {
+                Some(param) => write!(f, "{}", param.name),
+                None => write!(f, "<method generic param {}>", i),
+            },
+            RValType::ByRef(ty) => write!(f, "&{}", self.of(ty)),
+            RValType::Array(ty) => write!(f, "{}[]", self.of(ty)),
         }
     }
 }
@@ -184,14 +561,18 @@ impl fmt::Display for ValType {
 impl fmt::Display for SymType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Method(method) => write!(f, "(Method){}", unsafe { method[0].as_ref() }),
-            Self::Field(field) => write!(f, "(Field){}", unsafe { field.as_ref() }),
-            Self::Class(class) => write!(f, "(Class){}", unsafe { class.as_ref() }),
+            Self::Method(_, method, _) => write!(f, "(Method){}", unsafe { method[0].as_ref() }),
+            Self::Field(_, field) => write!(f, "(Field){}", unsafe { field.as_ref() }),
+            Self::Class(class, _) => write!(f, "(Class){}", unsafe { class.as_ref() }),
             Self::Module(m) => write!(f, "(Mod){}", unsafe { m.as_ref() }),
             Self::Local(n) => write!(f, "(Local){}", n),
             Self::KwLSelf => write!(f, "(Arg)self"),
             Self::Arg(n) => write!(f, "(Arg){}", n),
             Self::ArrAcc(ele_ty) => write!(f, "(acc){}[]", ele_ty),
+            Self::Variant(ty, idx) => {
+                let ty = unsafe { ty.as_ref() };
+                write!(f, "(Variant){}::{}", ty, ty.variants[*idx].name)
+            }
         }
     }
 }
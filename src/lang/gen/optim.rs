@@ -0,0 +1,423 @@
+use xir::inst::Inst;
+
+use std::ptr;
+
+use super::basic_block::{BasicBlock, LLCursor};
+use super::MethodBuilder;
+
+// -O1 optimizations on basic blocks, must be applied before jump offsets are filled
+impl MethodBuilder {
+    pub fn optimize(&mut self) {
+        self.fold_br();
+        self.thread_jumps();
+        self.remove_unreachable();
+        self.remove_redundant_br();
+        self.remove_redundant_stloc_ldloc();
+        self.shorten_insts();
+    }
+
+    /// index of the bb that cursor points to
+    fn bb_idx(&mut self, cursor: &LLCursor<BasicBlock>) -> usize {
+        let target = cursor.as_ref().unwrap() as *const BasicBlock;
+        self.bb
+            .iter_mut()
+            .position(|bb| ptr::eq(bb, target))
+            .unwrap()
+    }
+
+    /// branch target index of each bb
+    fn target_indices(&mut self) -> Vec<Option<usize>> {
+        let targets: Vec<Option<LLCursor<BasicBlock>>> =
+            self.bb.iter_mut().map(|bb| bb.target.clone()).collect();
+        targets
+            .iter()
+            .map(|t| t.as_ref().map(|t| self.bb_idx(t)))
+            .collect()
+    }
+
+    /// Exception clauses that each bb is in, in the form of (clause index, is handler).
+    ///
+    /// Jumps can only be redirected between bbs in the same regions
+    fn regions(&mut self) -> Vec<Vec<(usize, bool)>> {
+        let bounds: Vec<(usize, usize, usize, usize)> = (0..self.excepts.len())
+            .map(|i| {
+                let e = &self.excepts[i];
+                let (try_start, try_end, handler_start, handler_end) = (
+                    e.try_start.clone(),
+                    e.try_end.clone(),
+                    e.handler_start.clone(),
+                    e.handler_end.clone(),
+                );
+                (
+                    self.bb_idx(&try_start),
+                    self.bb_idx(&try_end),
+                    self.bb_idx(&handler_start),
+                    self.bb_idx(&handler_end),
+                )
+            })
+            .collect();
+        let n_bb = self.bb.iter_mut().count();
+        (0..n_bb)
+            .map(|bb_idx| {
+                let mut regions = Vec::new();
+                for (clause_idx, (try_start, try_end, handler_start, handler_end)) in
+                    bounds.iter().enumerate()
+                {
+                    if bb_idx >= *try_start && bb_idx < *try_end {
+                        regions.push((clause_idx, false));
+                    } else if bb_idx >= *handler_start && bb_idx < *handler_end {
+                        regions.push((clause_idx, true));
+                    }
+                }
+                regions
+            })
+            .collect()
+    }
+
+    /// Fold condition evaluation and conditional branch.
+    ///
+    /// `ldc.i4.0; ceq; brfalse` -> `brtrue`, `clt; brtrue` -> `blt`, `clt; brfalse` -> `bge`, etc.
+    ///
+    /// Negation of a comparison is the inverse comparison only if operands are not floats
+    fn fold_br(&mut self) {
+        for bb in self.bb.iter_mut() {
+            loop {
+                let n = bb.insts.len();
+                let folded = match &bb.insts[n.saturating_sub(3)..] {
+                    [Inst::LdC0, Inst::CEq, Inst::BrFalse(_)] => Some((3, Inst::BrTrue(0))),
+                    [Inst::LdC0, Inst::CEq, Inst::BrTrue(_)] => Some((3, Inst::BrFalse(0))),
+                    [.., Inst::CEq, Inst::BrTrue(_)] => Some((2, Inst::BEq(0))),
+                    [.., Inst::CLt, Inst::BrTrue(_)] => Some((2, Inst::BLt(0))),
+                    [.., Inst::CGt, Inst::BrTrue(_)] => Some((2, Inst::BGt(0))),
+                    [.., Inst::CEq, Inst::BrFalse(_)] => Some((2, Inst::BNe(0))),
+                    [.., Inst::CLt, Inst::BrFalse(_)] if !bb.has_float_cmp => {
+                        Some((2, Inst::BGe(0)))
+                    }
+                    [.., Inst::CGt, Inst::BrFalse(_)] if !bb.has_float_cmp => {
+                        Some((2, Inst::BLe(0)))
+                    }
+                    _ => None,
+                };
+                if let Some((n_removed, br)) = folded {
+                    for _ in 0..n_removed {
+                        bb.size -= bb.insts.pop().unwrap().size();
+                    }
+                    bb.push(br);
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Redirect jumps to a bb that only contains `br` to the final target.
+    ///
+    /// `brtrue L1; br L2; L1:` is also inverted to `brfalse L2; L1:`
+    fn thread_jumps(&mut self) {
+        let mut targets = self.target_indices();
+        let regions = self.regions();
+        let mut bbs: Vec<&mut BasicBlock> = self.bb.iter_mut().collect();
+
+        // bbs that only contain br, (target index, target cursor)
+        let only_br: Vec<Option<(usize, LLCursor<BasicBlock>)>> = bbs
+            .iter()
+            .enumerate()
+            .map(|(i, bb)| match bb.insts.as_slice() {
+                [Inst::Br(_)] if regions[i] == regions[targets[i].unwrap()] => {
+                    Some((targets[i].unwrap(), bb.target.clone().unwrap()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        for i in 0..bbs.len() {
+            if let Some(Inst::Leave(_)) = bbs[i].insts.last() {
+                continue;
+            }
+            if let Some(mut target) = targets[i] {
+                // bound the number of hops in case of infinite loops
+                for _ in 0..bbs.len() {
+                    // empty bbs fall through to the next bb
+                    let non_empty = match (target..bbs.len()).find(|j| !bbs[*j].insts.is_empty()) {
+                        Some(non_empty) => non_empty,
+                        None => break,
+                    };
+                    if let Some((next, cursor)) = &only_br[non_empty] {
+                        if *next == non_empty || regions[*next] != regions[i] {
+                            break;
+                        }
+                        target = *next;
+                        bbs[i].target = Some(cursor.clone());
+                    } else {
+                        break;
+                    }
+                }
+                targets[i] = Some(target);
+            }
+        }
+
+        for i in 0..bbs.len() {
+            let inverted = match bbs[i].insts.last() {
+                Some(Inst::BrTrue(_)) => Inst::BrFalse(0),
+                Some(Inst::BrFalse(_)) => Inst::BrTrue(0),
+                Some(Inst::BEq(_)) => Inst::BNe(0),
+                Some(Inst::BNe(_)) => Inst::BEq(0),
+                _ if bbs[i].has_float_cmp => continue,
+                Some(Inst::BLt(_)) => Inst::BGe(0),
+                Some(Inst::BGe(_)) => Inst::BLt(0),
+                Some(Inst::BGt(_)) => Inst::BLe(0),
+                Some(Inst::BLe(_)) => Inst::BGt(0),
+                _ => continue,
+            };
+            // the bb of `br L2`, which must be the next non-empty bb
+            let br_bb = match (i + 1..bbs.len()).find(|j| !bbs[*j].insts.is_empty()) {
+                Some(j) if only_br[j].is_some() && regions[j] == regions[i] => j,
+                _ => continue,
+            };
+            // L1 must be the next non-empty bb after `br L2`
+            let target = targets[i].unwrap();
+            if target <= br_bb || (br_bb + 1..target).any(|j| !bbs[j].insts.is_empty()) {
+                continue;
+            }
+            // `br L2` will be removed, so it should have no other predecessors
+            if targets
+                .iter()
+                .any(|t| matches!(t, Some(t) if *t > i && *t <= br_bb))
+            {
+                continue;
+            }
+            let (br_target, br_cursor) = only_br[br_bb].clone().unwrap();
+
+            let bb = &mut bbs[i];
+            bb.size -= bb.insts.pop().unwrap().size();
+            bb.push(inverted);
+            bb.target = Some(br_cursor);
+            targets[i] = Some(br_target);
+
+            let bb = &mut bbs[br_bb];
+            bb.insts.clear();
+            bb.size = 0;
+            bb.target = None;
+            targets[br_bb] = None;
+        }
+    }
+
+    /// Clear bbs that are unreachable from the entry or exception handlers
+    fn remove_unreachable(&mut self) {
+        let targets = self.target_indices();
+        let handlers: Vec<usize> = (0..self.excepts.len())
+            .map(|i| {
+                let handler_start = self.excepts[i].handler_start.clone();
+                self.bb_idx(&handler_start)
+            })
+            .collect();
+        let mut bbs: Vec<&mut BasicBlock> = self.bb.iter_mut().collect();
+
+        let mut reachable = vec![false; bbs.len()];
+        let mut worklist = vec![0];
+        worklist.extend(handlers);
+        while let Some(i) = worklist.pop() {
+            if reachable[i] {
+                continue;
+            }
+            reachable[i] = true;
+            if let Some(target) = targets[i] {
+                worklist.push(target);
+            }
+            match bbs[i].insts.last() {
+                Some(Inst::Br(_))
+                | Some(Inst::Leave(_))
+                | Some(Inst::Ret)
+                | Some(Inst::Throw)
+                | Some(Inst::EndFinally) => {}
+                _ => {
+                    if i + 1 < bbs.len() {
+                        worklist.push(i + 1);
+                    }
+                }
+            }
+        }
+
+        for (bb, reachable) in bbs.iter_mut().zip(reachable) {
+            if !reachable {
+                bb.insts.clear();
+                bb.size = 0;
+                bb.target = None;
+            }
+        }
+    }
+
+    /// Remove `br` to the next non-empty bb
+    fn remove_redundant_br(&mut self) {
+        let targets = self.target_indices();
+        let mut bbs: Vec<&mut BasicBlock> = self.bb.iter_mut().collect();
+        for i in 0..bbs.len() {
+            if let Some(Inst::Br(_)) = bbs[i].insts.last() {
+                let target = targets[i].unwrap();
+                if target > i && (i + 1..target).all(|j| bbs[j].insts.is_empty()) {
+                    let bb = &mut bbs[i];
+                    bb.size -= bb.insts.pop().unwrap().size();
+                    bb.target = None;
+                }
+            }
+        }
+    }
+
+    /// `stloc n; ldloc n` -> nothing if local n is not used elsewhere, otherwise `dup; stloc n`.
+    /// `ldloc n; stloc n` -> nothing
+    fn remove_redundant_stloc_ldloc(&mut self) {
+        // number of ldloc and ldloca of each local
+        let mut n_loads: Vec<usize> = Vec::new();
+        for bb in self.bb.iter_mut() {
+            for inst in bb.insts.iter() {
+                if let Some(idx) = ldloc_idx(inst).or_else(|| ldloca_idx(inst)) {
+                    if idx >= n_loads.len() {
+                        n_loads.resize(idx + 1, 0);
+                    }
+                    n_loads[idx] += 1;
+                }
+            }
+        }
+
+        for bb in self.bb.iter_mut() {
+            let mut insts: Vec<Inst> = Vec::with_capacity(bb.insts.len());
+            // new index of each old inst, markers are moved accordingly
+            let mut new_idx: Vec<usize> = Vec::with_capacity(bb.insts.len() + 1);
+            for inst in bb.insts.drain(..) {
+                new_idx.push(insts.len());
+                let folded = match insts.last() {
+                    Some(last) => match (stloc_idx(last), ldloc_idx(&inst)) {
+                        (Some(st), Some(ld)) if st == ld => {
+                            if n_loads[ld] == 1 {
+                                n_loads[ld] -= 1;
+                                insts.pop();
+                            } else {
+                                let stloc = insts.pop().unwrap();
+                                insts.push(Inst::Dup);
+                                insts.push(stloc);
+                            }
+                            true
+                        }
+                        _ => match (ldloc_idx(last), stloc_idx(&inst)) {
+                            (Some(ld), Some(st)) if st == ld => {
+                                n_loads[ld] -= 1;
+                                insts.pop();
+                                true
+                            }
+                            _ => false,
+                        },
+                    },
+                    None => false,
+                };
+                if !folded {
+                    insts.push(inst);
+                } else {
+                    // folded insts are removed together with the previous one
+                    let last = new_idx.last_mut().unwrap();
+                    *last = (*last).min(insts.len());
+                }
+            }
+            new_idx.push(insts.len());
+            for (idx, _) in bb.markers.iter_mut() {
+                *idx = new_idx[(*idx).min(new_idx.len() - 1)].min(insts.len());
+            }
+            bb.size = insts.iter().map(|inst| inst.size()).sum();
+            bb.insts = insts;
+        }
+    }
+
+    /// Use the shortest encodings of ldc, ldloc, stloc and ldarg
+    fn shorten_insts(&mut self) {
+        for bb in self.bb.iter_mut() {
+            for inst in bb.insts.iter_mut() {
+                let short = if let Some(idx) = ldloc_idx(inst) {
+                    match idx {
+                        0 => Inst::LdLoc0,
+                        1 => Inst::LdLoc1,
+                        2 => Inst::LdLoc2,
+                        3 => Inst::LdLoc3,
+                        _ if idx <= u8::MAX as usize => Inst::LdLocS(idx as u8),
+                        _ => continue,
+                    }
+                } else if let Some(idx) = stloc_idx(inst) {
+                    match idx {
+                        0 => Inst::StLoc0,
+                        1 => Inst::StLoc1,
+                        2 => Inst::StLoc2,
+                        3 => Inst::StLoc3,
+                        _ if idx <= u8::MAX as usize => Inst::StLocS(idx as u8),
+                        _ => continue,
+                    }
+                } else {
+                    match inst {
+                        Inst::LdLocA(idx) if *idx <= u8::MAX as u16 => Inst::LdLocAS(*idx as u8),
+                        Inst::LdArgS(0) => Inst::LdArg0,
+                        Inst::LdArgS(1) => Inst::LdArg1,
+                        Inst::LdArgS(2) => Inst::LdArg2,
+                        Inst::LdArgS(3) => Inst::LdArg3,
+                        Inst::LdCI4S(v) => shortest_ldc(*v as i32),
+                        Inst::LdCI4(v) => shortest_ldc(*v),
+                        _ => continue,
+                    }
+                };
+                bb.size -= inst.size();
+                bb.size += short.size();
+                *inst = short;
+            }
+        }
+    }
+}
+
+fn ldloc_idx(inst: &Inst) -> Option<usize> {
+    match inst {
+        Inst::LdLoc0 => Some(0),
+        Inst::LdLoc1 => Some(1),
+        Inst::LdLoc2 => Some(2),
+        Inst::LdLoc3 => Some(3),
+        Inst::LdLocS(idx) => Some(*idx as usize),
+        Inst::LdLoc(idx) => Some(*idx as usize),
+        _ => None,
+    }
+}
+
+fn ldloca_idx(inst: &Inst) -> Option<usize> {
+    match inst {
+        Inst::LdLocAS(idx) => Some(*idx as usize),
+        Inst::LdLocA(idx) => Some(*idx as usize),
+        _ => None,
+    }
+}
+
+fn stloc_idx(inst: &Inst) -> Option<usize> {
+    match inst {
+        Inst::StLoc0 => Some(0),
+        Inst::StLoc1 => Some(1),
+        Inst::StLoc2 => Some(2),
+        Inst::StLoc3 => Some(3),
+        Inst::StLocS(idx) => Some(*idx as usize),
+        Inst::StLoc(idx) => Some(*idx as usize),
+        _ => None,
+    }
+}
+
+fn shortest_ldc(value: i32) -> Inst {
+    match value {
+        -1 => Inst::LdCM1,
+        0 => Inst::LdC0,
+        1 => Inst::LdC1,
+        2 => Inst::LdC2,
+        3 => Inst::LdC3,
+        4 => Inst::LdC4,
+        5 => Inst::LdC5,
+        6 => Inst::LdC6,
+        7 => Inst::LdC7,
+        8 => Inst::LdC8,
+        _ => {
+            if value >= i8::MIN as i32 && value <= i8::MAX as i32 {
+                Inst::LdCI4S(value as i8)
+            } else {
+                Inst::LdCI4(value)
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod ast;
+mod diagnostic;
 mod gen;
 pub mod mod_mgr;
 pub mod parser;
@@ -14,5 +15,9 @@ pub struct XicCfg {
     pub root_path: PathBuf,
     pub out_dir: PathBuf,
     pub optim: usize,
+    /// features enabled for #[cfg(feature = "x")]
+    pub features: Vec<String>,
+    /// generate debug info
+    pub debug_info: bool,
     pub verbose: usize,
 }
@@ -7,13 +7,14 @@ use std::ptr::{self, NonNull};
 
 use xir::attrib::*;
 use xir::file::*;
-use xir::sig::{self, IrSig, TypeSig};
-use xir::tok::{get_tok_tag, TokTag};
-use xir::ty::{ResolutionScope, TypeDefOrRef};
+use xir::generic::TypeOrMethodDef;
+use xir::sig::{self, IrSig, TypeSig, TypeSpecSig};
+use xir::tok::{get_tok_tag, to_tok, TokTag};
+use xir::ty::{get_typedeforref, ResolutionScope, TypeDefOrRef};
 
 use super::super::gen::RValType;
 use super::super::util::{IItemPath, ItemPathBuf};
-use super::{Field, Method, Module, Param, Type};
+use super::{Field, GenericParam, Method, Module, Param, Type};
 
 fn to_param(sig: &sig::ParamType, f: &IrFile, mods: &HashMap<String, Box<Module>>) -> Param {
     Param {
@@ -38,55 +39,125 @@ fn to_rval(sig: &TypeSig, f: &IrFile, mods: &HashMap<String, Box<Module>>) -> RV
     match sig {
         TypeSig::Boolean => RValType::Bool,
         TypeSig::Char => RValType::Char,
-        TypeSig::I1 => unimplemented!(),
+        TypeSig::I1 => RValType::I8,
         TypeSig::U1 => RValType::U8,
+        TypeSig::I2 => RValType::I16,
+        TypeSig::U2 => RValType::U16,
         TypeSig::I4 => RValType::I32,
-        TypeSig::U4 => unimplemented!(),
-        TypeSig::I8 => unimplemented!(),
-        TypeSig::U8 => unimplemented!(),
-        TypeSig::R4 => unimplemented!(),
+        TypeSig::U4 => RValType::U32,
+        TypeSig::I8 => RValType::I64,
+        TypeSig::U8 => RValType::U64,
+        TypeSig::R4 => RValType::F32,
         TypeSig::R8 => RValType::F64,
-        TypeSig::I => unimplemented!(),
-        TypeSig::U => unimplemented!(),
-        TypeSig::SZArray(_) => unimplemented!(),
+        TypeSig::I => RValType::ISize,
+        TypeSig::U => RValType::USize,
+        TypeSig::SZArray(ele_ty) => RValType::Array(Box::new(to_rval(ele_ty, f, mods))),
         TypeSig::String => RValType::String,
-        TypeSig::ValueType(tok) => todo!(),
+        TypeSig::ValueType(tok) => RValType::Value(to_class(*tok, f, mods)),
         TypeSig::Class(tok) => {
             let (tag, idx) = get_tok_tag(*tok);
-            let idx = idx as usize - 1;
-            match tag {
-                TokTag::TypeDef => {
-                    let ty = mods
-                        .get(f.mod_name())
-                        .unwrap()
-                        .classes
-                        .get(f.get_str(f.typedef_tbl[idx].name))
-                        .unwrap();
-                    RValType::Class(NonNull::new(ty.as_ref() as *const Type as *mut Type).unwrap())
+            if let TokTag::TypeSpec = tag {
+                to_typespec_rval(idx, f, mods)
+            } else {
+                RValType::Class(to_class(*tok, f, mods))
+            }
+        }
+        TypeSig::GenericInst(is_class, tok, args) => RValType::GenericInst(
+            *is_class,
+            to_class(*tok, f, mods),
+            args.iter().map(|arg| to_rval(arg, f, mods)).collect(),
+        ),
+        TypeSig::Var(i) => RValType::Var(*i),
+        TypeSig::MVar(i) => RValType::MVar(*i),
+    }
+}
+
+/// tok is typedef or typeref
+fn to_class(tok: u32, f: &IrFile, mods: &HashMap<String, Box<Module>>) -> NonNull<Type> {
+    let (tag, idx) = get_tok_tag(tok);
+    let idx = idx as usize - 1;
+    let ty = match tag {
+        TokTag::TypeDef => mods
+            .get(f.mod_name())
+            .unwrap()
+            .classes
+            .get(f.get_str(f.typedef_tbl[idx].name))
+            .unwrap(),
+        TokTag::TypeRef => {
+            let (parent_tag, parent_idx) = f.typeref_tbl[idx].get_parent();
+            match parent_tag {
+                ResolutionScope::Mod => unreachable!(),
+                ResolutionScope::ModRef => mods
+                    .get(f.get_str(f.modref_tbl[parent_idx].name))
+                    .unwrap()
+                    .classes
+                    .get(f.get_str(f.typeref_tbl[idx].name))
+                    .unwrap(),
+                ResolutionScope::TypeRef => unreachable!(),
+            }
+        }
+        _ => unreachable!(),
+    };
+    NonNull::new(ty.as_ref() as *const Type as *mut Type).unwrap()
+}
+
+/// idx is index into typespec tbl, starts from 1
+fn to_typespec_rval(idx: u32, f: &IrFile, mods: &HashMap<String, Box<Module>>) -> RValType {
+    if let IrSig::TypeSpec(spec) = &f.blob_heap[f.typespec_tbl[idx as usize - 1].sig as usize] {
+        match spec {
+            TypeSpecSig::SZArray(ele_ty) => RValType::Array(Box::new(to_rval(ele_ty, f, mods))),
+            TypeSpecSig::GenericInst(is_class, tok, args) => RValType::GenericInst(
+                *is_class,
+                to_class(*tok, f, mods),
+                args.iter().map(|arg| to_rval(arg, f, mods)).collect(),
+            ),
+            TypeSpecSig::Var(i) => RValType::Var(*i),
+            TypeSpecSig::MVar(i) => RValType::MVar(*i),
+        }
+    } else {
+        panic!();
+    }
+}
+
+/// Generic params of a typedef or methoddef, constraints are not filled
+fn to_generic_params(f: &IrFile, owner: TypeOrMethodDef, owner_idx: u32) -> Vec<GenericParam> {
+    f.generic_param_tbl
+        .iter()
+        .filter(|p| p.get_owner() == (owner, owner_idx))
+        .map(|p| GenericParam::new(f.get_str(p.name)))
+        .collect()
+}
+
+/// Fill constraints of generic params of a typedef or methoddef
+fn fill_generic_constraints(
+    params: &mut [GenericParam],
+    f: &IrFile,
+    owner: TypeOrMethodDef,
+    owner_idx: u32,
+    mods: &HashMap<String, Box<Module>>,
+) {
+    for (param_i, param_entry) in f.generic_param_tbl.iter().enumerate() {
+        if param_entry.get_owner() != (owner, owner_idx) {
+            continue;
+        }
+        for constraint_entry in f.generic_param_constraint_tbl.iter() {
+            if constraint_entry.owner as usize != param_i + 1 {
+                continue;
+            }
+            let (tag, idx) = get_typedeforref(constraint_entry.constraint);
+            let constraint = match tag {
+                TypeDefOrRef::TypeDef => {
+                    RValType::Class(to_class(to_tok(idx, TokTag::TypeDef), f, mods))
                 }
-                TokTag::TypeRef => {
-                    let (parent_tag, parent_idx) = f.typeref_tbl[idx].get_parent();
-                    match parent_tag {
-                        ResolutionScope::Mod => unreachable!(),
-                        ResolutionScope::ModRef => {
-                            let ty = mods
-                                .get(f.get_str(f.modref_tbl[parent_idx].name))
-                                .unwrap()
-                                .classes
-                                .get(f.get_str(f.typeref_tbl[idx].name))
-                                .unwrap();
-                            RValType::Class(
-                                NonNull::new(ty.as_ref() as *const Type as *mut Type).unwrap(),
-                            )
-                        }
-                        ResolutionScope::TypeRef => unreachable!(),
-                    }
+                TypeDefOrRef::TypeRef => {
+                    RValType::Class(to_class(to_tok(idx, TokTag::TypeRef), f, mods))
                 }
-                TokTag::TypeSpec => unimplemented!(),
-                _ => unreachable!(),
-            }
+                TypeDefOrRef::TypeSpec => to_typespec_rval(idx, f, mods),
+            };
+            params[param_entry.number as usize]
+                .constraints
+                .push(constraint);
         }
-        TypeSig::GenericInst(is_ref, tok, args) => todo!(),
     }
 }
 
@@ -104,12 +175,14 @@ pub fn load_external_crate(
     let mut this_mod = Box::new(Module {
         sub_mods: HashSet::new(),
         mod_path: this_mod_path.clone(),
+        // modules are not recorded in ir file, visibility is carried by the flags of their items
+        vis: TypeAttribVisFlag::Pub,
         classes: HashMap::new(),
     });
     let mut this_mod_ptr = NonNull::new(this_mod.as_mut() as *mut Module).unwrap();
 
     // 1. Fill classes
-    for class_entry in file.typedef_tbl.iter() {
+    for (class_i, class_entry) in file.typedef_tbl.iter().enumerate() {
         let flag = TypeAttrib::from(class_entry.flag);
         let name = file.get_str(class_entry.name);
         let class = Box::new(Type {
@@ -117,8 +190,15 @@ pub fn load_external_crate(
             name: name.to_owned(),
             methods: HashMap::new(),
             fields: HashMap::new(),
+            // consts are not exported
+            consts: HashMap::new(),
+            // variants are not exported
+            variants: vec![],
             attrib: flag,
             extends: ptr::null(),
+            // fill later
+            impls: vec![],
+            generic_params: to_generic_params(&file, TypeOrMethodDef::TypeDef, class_i as u32 + 1),
             // idx of external class will not be used
             idx: 0,
         });
@@ -203,50 +283,51 @@ pub fn load_external_crate(
                 }
             }
         }
+    }
 
-        // 3. link extends
-        {
-            let this_mod_mut = unsafe { this_mod_ptr.as_mut() };
-            for class_entry in file.typedef_tbl.iter() {
-                let mut class_mut = this_mod_mut
-                    .classes
-                    .get_mut(file.get_str(class_entry.name))
-                    .unwrap();
-                if let Some((tag, idx)) = class_entry.get_extends() {
-                    class_mut.extends = match tag {
-                        TypeDefOrRef::TypeDef => mod_tbl
-                            .get(file.mod_name())
+    // 3. link extends
+    {
+        let this_mod_mut = unsafe { this_mod_ptr.as_mut() };
+        for class_entry in file.typedef_tbl.iter() {
+            let mut class_mut = this_mod_mut
+                .classes
+                .get_mut(file.get_str(class_entry.name))
+                .unwrap();
+            if let Some((tag, idx)) = class_entry.get_extends() {
+                class_mut.extends = match tag {
+                    TypeDefOrRef::TypeDef => mod_tbl
+                        .get(file.mod_name())
+                        .unwrap()
+                        .classes
+                        .get(file.get_str(file.typedef_tbl[idx].name))
+                        .unwrap()
+                        .as_ref() as *const Type,
+                    TypeDefOrRef::TypeRef => {
+                        let typeref = &file.typeref_tbl[idx];
+                        let (parent_tag, parent_idx) = typeref.get_parent();
+                        mod_tbl
+                            .get(match parent_tag {
+                                ResolutionScope::Mod => file.mod_name(),
+                                ResolutionScope::ModRef => {
+                                    file.get_str(file.modref_tbl[parent_idx].name)
+                                }
+                                ResolutionScope::TypeRef => unreachable!(),
+                            })
                             .unwrap()
                             .classes
-                            .get(file.get_str(file.typedef_tbl[idx].name))
+                            .get(file.get_str(typeref.name))
                             .unwrap()
-                            .as_ref()
-                            as *const Type,
-                        TypeDefOrRef::TypeRef => {
-                            let typeref = &file.typeref_tbl[idx];
-                            let (parent_tag, parent_idx) = typeref.get_parent();
-                            mod_tbl
-                                .get(match parent_tag {
-                                    ResolutionScope::Mod => file.mod_name(),
-                                    ResolutionScope::ModRef => {
-                                        file.get_str(file.modref_tbl[parent_idx].name)
-                                    }
-                                    ResolutionScope::TypeRef => unreachable!(),
-                                })
-                                .unwrap()
-                                .classes
-                                .get(file.get_str(typeref.name))
-                                .unwrap()
-                                .as_ref() as *const Type
-                        }
-                        TypeDefOrRef::TypeSpec => unimplemented!(),
-                    };
-                }
+                            .as_ref() as *const Type
+                    }
+                    TypeDefOrRef::TypeSpec => {
+                        unimplemented!("Generic base class is not supported")
+                    }
+                };
             }
         }
     }
 
-    // 3. fill all method and fields
+    // 4. fill all method and fields
     let (mut field_i, mut method_i) = if let Some(c0) = file.typedef_tbl.first() {
         (c0.fields as usize - 1, c0.methods as usize - 1)
     } else {
@@ -276,6 +357,26 @@ pub fn load_external_crate(
 
         let name = file.get_str(class_entry.name);
         let class = unsafe { this_mod_ptr.as_mut().classes.get_mut(name).unwrap() };
+        fill_generic_constraints(
+            &mut class.generic_params,
+            &file,
+            TypeOrMethodDef::TypeDef,
+            class_i as u32 + 1,
+            mod_tbl,
+        );
+        for interface_impl in file
+            .interfaceimpl_tbl
+            .iter()
+            .filter(|i| i.class as usize == class_i + 1)
+        {
+            let (tag, idx) = interface_impl.get_interface();
+            class.impls.push(match tag {
+                TypeDefOrRef::TypeSpec => unimplemented!("Generic interface is not supported"),
+                _ => {
+                    to_class(to_tok(idx, tag.to_tok_tag()), &file, mod_tbl).as_ptr() as *const Type
+                }
+            });
+        }
 
         while method_i < method_lim {
             let method_entry = &file.method_tbl[method_i];
@@ -301,9 +402,20 @@ pub fn load_external_crate(
                     ps[(p.sequence - 1) as usize].attrib = ParamAttrib::from(p.flag);
                 }
 
+                let mut generic_params =
+                    to_generic_params(&file, TypeOrMethodDef::MethodDef, method_i as u32 + 1);
+                fill_generic_constraints(
+                    &mut generic_params,
+                    &file,
+                    TypeOrMethodDef::MethodDef,
+                    method_i as u32 + 1,
+                    mod_tbl,
+                );
+
                 let method = Box::new(Method {
                     parent: NonNull::new(class.as_ref() as *const Type as *mut Type).unwrap(),
                     name: file.get_str(method_entry.name).to_owned(),
+                    generic_params,
                     ps,
                     ret: to_ret(ret, &file, mod_tbl),
                     attrib: flag,
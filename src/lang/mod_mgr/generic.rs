@@ -0,0 +1,16 @@
+use super::super::gen::RValType;
+
+pub struct GenericParam {
+    pub name: String,
+    /// generic arg must be or derive from every constraint
+    pub constraints: Vec<RValType>,
+}
+
+impl GenericParam {
+    pub fn new(name: &str) -> GenericParam {
+        GenericParam {
+            name: name.to_owned(),
+            constraints: Vec::new(),
+        }
+    }
+}
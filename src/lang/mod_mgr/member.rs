@@ -3,17 +3,30 @@ use xir::attrib::{
 };
 
 use super::super::ast::AST;
-use super::super::gen::RValType;
-use super::Type;
+use super::super::gen::{RValType, RValTypeName};
+use super::{GenericParam, Type};
 
 use std::fmt;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
+
+/// priv members are only accessible in their parent and pub(crate) members in the same crate
+fn is_member_accessible_in(parent: &Type, is_priv: bool, is_crate: bool, class: &Type) -> bool {
+    if is_priv {
+        ptr::eq(parent, class)
+    } else if is_crate {
+        parent.module().crate_name() == class.module().crate_name()
+    } else {
+        true
+    }
+}
 
 pub struct Method {
     pub parent: NonNull<Type>,
 
     pub name: String,
 
+    pub generic_params: Vec<GenericParam>,
+
     pub ret: RValType,
     /// self is not included
     pub ps: Vec<Param>,
@@ -36,6 +49,7 @@ pub struct Method {
 impl Method {
     pub fn sig_match(&self, m1: &Method) -> bool {
         if self.name != m1.name
+            || self.generic_params.len() != m1.generic_params.len()
             || self.ps.len() != m1.ps.len()
             || self.attrib.is(MethodAttribFlag::Static) != m1.attrib.is(MethodAttribFlag::Static)
         {
@@ -50,6 +64,21 @@ impl Method {
 
         true
     }
+
+    /// whether this method can be used in methods of class
+    pub fn is_accessible_in(&self, class: &Type) -> bool {
+        is_member_accessible_in(
+            unsafe { self.parent.as_ref() },
+            self.attrib.is(MethodAttribFlag::Priv),
+            self.attrib.is(MethodAttribFlag::Crate),
+            class,
+        )
+    }
+
+    /// Display with generic params, params and return type, e.g. `std::IO::writeln(v: i32)`
+    pub fn sig_name(&self) -> MethodSigName<'_> {
+        MethodSigName(self)
+    }
 }
 
 impl fmt::Display for Method {
@@ -64,6 +93,47 @@ impl fmt::Display for Method {
     }
 }
 
+pub struct MethodSigName<'a>(&'a Method);
+
+impl<'a> fmt::Display for MethodSigName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = self.0;
+        let parent = unsafe { method.parent.as_ref() };
+        let ty_name = |ty| RValTypeName {
+            ty,
+            class_generic_params: &parent.generic_params,
+            method_generic_params: &method.generic_params,
+        };
+        write!(f, "{}", method)?;
+        if !method.generic_params.is_empty() {
+            write!(
+                f,
+                "<{}>",
+                method
+                    .generic_params
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            )?;
+        }
+        write!(
+            f,
+            "({})",
+            method
+                .ps
+                .iter()
+                .map(|p| format!("{}: {}", p.id, ty_name(&p.ty)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )?;
+        if method.ret != RValType::Void {
+            write!(f, " -> {}", ty_name(&method.ret))?;
+        }
+        Ok(())
+    }
+}
+
 pub struct Param {
     pub id: String,
     pub attrib: ParamAttrib,
@@ -88,14 +158,53 @@ pub struct Field {
     pub idx: u32,
 }
 
+impl Field {
+    /// whether this field can be used in methods of class
+    pub fn is_accessible_in(&self, class: &Type) -> bool {
+        is_member_accessible_in(
+            unsafe { self.parent.as_ref() },
+            self.attrib.is(FieldAttribFlag::Priv),
+            self.attrib.is(FieldAttribFlag::Crate),
+            class,
+        )
+    }
+}
+
 impl fmt::Display for Field {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", unsafe { self.parent.as_ref() })?;
+        let parent = unsafe { self.parent.as_ref() };
+        write!(f, "{}", parent)?;
         if self.attrib.is(FieldAttribFlag::Static) {
             write!(f, "::")?;
         } else {
             write!(f, ".")?;
         }
-        write!(f, "{}: {}", self.name, self.ty)
+        write!(
+            f,
+            "{}: {}",
+            self.name,
+            RValTypeName {
+                ty: &self.ty,
+                class_generic_params: &parent.generic_params,
+                method_generic_params: &[],
+            }
+        )
+    }
+}
+
+/// Field that stores the discriminant of an enum
+pub const ENUM_TAG_FIELD: &str = ".tag";
+
+/// Variant of an enum, its payload is stored in instance fields of the enum
+pub struct Variant {
+    pub name: String,
+    pub discriminant: i32,
+    /// names of fields that store the payload
+    pub fields: Vec<String>,
+}
+
+impl Variant {
+    pub fn field_name(variant: &str, i: usize) -> String {
+        format!(".{}_{}", variant, i)
     }
 }
@@ -1,18 +1,27 @@
 mod external;
+mod generic;
 mod member;
 mod module;
+mod tuple;
 mod ty;
 mod var;
 
-pub use self::member::{Field, Method, Param};
+pub use self::generic::GenericParam;
+pub use self::member::{Field, Method, Param, Variant, ENUM_TAG_FIELD};
 use self::module::new_module;
 pub use self::module::{Module, ModuleBuildCtx};
+pub use self::tuple::{
+    get_tuple_type, tuple_arity, tuple_field_name, TUPLE_MAX_ARITY, TUPLE_MIN_ARITY,
+};
 pub use self::ty::Type;
 pub use self::var::{Locals, Var};
 
 use super::super::XicCfg;
+use super::diagnostic;
 use super::util::ItemPathBuf;
 
+use xir::attrib::TypeAttribVisFlag;
+
 use std::collections::HashMap;
 use std::fs;
 
@@ -48,11 +57,28 @@ impl Crate {
             mod_build_ctx: Vec::new(),
         };
 
-        new_module(mod_path, &mut mgr, &cfg);
+        let mut diags = Vec::new();
+        new_module(
+            mod_path,
+            TypeAttribVisFlag::Pub,
+            None,
+            &mut mgr,
+            &cfg,
+            &mut diags,
+        );
+        // report errors of all modules before going on
+        diagnostic::emit_all(&diags);
         mgr
     }
 
     pub fn build(&mut self, cfg: &XicCfg) {
+        // 0. check uses
+        let mut diags = Vec::new();
+        for ctx in self.mod_build_ctx.iter() {
+            ctx.check_uses(self, &mut diags);
+        }
+        diagnostic::emit_all(&diags);
+
         // 1. class pass
         for ctx in self.mod_build_ctx.iter() {
             ctx.class_pass(self);
@@ -0,0 +1,82 @@
+use std::env::consts::OS;
+use std::path::Path;
+
+use super::super::super::super::XicCfg;
+use super::super::super::ast::{ASTCfgPred, ASTClass, ASTKind, AST};
+use super::super::super::diagnostic::Diagnostic;
+
+/// Remove items whose #[cfg(...)] is false from the AST of a module,
+/// and remove cfg attributes from the remaining items
+pub struct CfgStripper<'c> {
+    cfg: &'c XicCfg,
+    fpath: &'c Path,
+    diags: &'c mut Vec<Diagnostic>,
+}
+
+impl<'c> CfgStripper<'c> {
+    pub fn new(cfg: &'c XicCfg, fpath: &'c Path, diags: &'c mut Vec<Diagnostic>) -> Self {
+        CfgStripper { cfg, fpath, diags }
+    }
+
+    /// Supported options:
+    /// * debug: when compiling with -O0
+    /// * target_os = "linux" | "windows" | "macos" | ...
+    /// * feature = "x": when x is specified in --features
+    ///
+    /// Unknown options are false
+    fn eval(&mut self, span_ast: &AST, pred: &ASTCfgPred) -> bool {
+        match pred {
+            ASTCfgPred::Flag(name) if name == "debug" => self.cfg.optim == 0,
+            ASTCfgPred::KeyValue(name, val) if name == "target_os" => val == OS,
+            ASTCfgPred::KeyValue(name, val) if name == "feature" => self.cfg.features.contains(val),
+            ASTCfgPred::Flag(name) | ASTCfgPred::KeyValue(name, _) => {
+                self.diags.push(
+                    Diagnostic::warning(format!("Unknown cfg option {}", name))
+                        .with_loc(self.fpath, span_ast.span),
+                );
+                false
+            }
+            ASTCfgPred::All(preds) => preds.iter().all(|p| self.eval(span_ast, p)),
+            ASTCfgPred::Any(preds) => preds.iter().any(|p| self.eval(span_ast, p)),
+            ASTCfgPred::Not(pred) => !self.eval(span_ast, pred),
+        }
+    }
+
+    /// Consume cfg attributes in attribs. Return false if any of them is false
+    pub fn strip_attribs(&mut self, attribs: &mut Vec<Box<AST>>) -> bool {
+        let mut enabled = true;
+        let mut i = 0;
+        while i < attribs.len() {
+            if let ASTKind::Cfg(pred) = &attribs[i].kind {
+                // evaluate all of them so that unknown options are all reported
+                enabled &= self.eval(&attribs[i], pred);
+                attribs.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        enabled
+    }
+
+    /// Return false if the class itself is disabled
+    pub fn strip_class(&mut self, class: &mut ASTClass) -> bool {
+        if !self.strip_attribs(&mut class.custom_attribs) {
+            return false;
+        }
+        class.fields.retain_mut(|field| match &mut field.kind {
+            ASTKind::Field(_, _, attr, _) | ASTKind::Const(_, attr, _, _) => {
+                self.strip_attribs(attr)
+            }
+            _ => true,
+        });
+        class.methods.retain_mut(|method| match &mut method.kind {
+            ASTKind::Method(method) => self.strip_attribs(&mut method.custom_attribs),
+            _ => true,
+        });
+        class.ctors.retain_mut(|ctor| match &mut ctor.kind {
+            ASTKind::Ctor(ctor) => self.strip_attribs(&mut ctor.custom_attribs),
+            _ => true,
+        });
+        true
+    }
+}
@@ -1,37 +1,96 @@
-use super::super::super::ast::{ASTMethodAttribFlag, AST};
-use super::super::super::gen::RValType;
-use super::super::{Crate, Field, Method, Param, Type};
+use super::super::super::ast::{ASTGenericParamDecl, ASTKind, ASTMethodAttribFlag, ASTType, AST};
+use super::super::super::diagnostic::{Diagnostic, ErrCode};
+use super::super::super::gen::{eval, ConstVal, EvalErr, RValType};
+use super::super::super::util::{IItemPath, Span};
+use super::super::{Crate, Field, GenericParam, Method, Param, Type, Variant, ENUM_TAG_FIELD};
 use super::ModuleBuildCtx;
 
 use xir::attrib::{
-    FieldAttribFlag, MethodAttrib, MethodAttribFlag, MethodImplAttrib,
+    FieldAttrib, FieldAttribFlag, MethodAttrib, MethodAttribFlag, MethodImplAttrib,
     MethodImplAttribCodeTypeFlag, MethodImplAttribManagedFlag, MethodImplInfoFlag, PInvokeAttrib,
-    PInvokeAttribCallConvFlag, PInvokeAttribCharsetFlag, TypeAttribFlag,
+    PInvokeAttribCallConvFlag, PInvokeAttribCharsetFlag, TypeAttribFlag, TypeAttribVisFlag,
 };
 use xir::{CCTOR_NAME, CTOR_NAME};
 
 use std::ptr::NonNull;
 
 impl ModuleBuildCtx {
-    /// Set extends in class info.
+    /// Set extends and impls in class info.
     /// Extends info must be set before member pass, because we need this to determine value type and reference type
     fn set_extends1(&self, ast: &AST, class: &mut Type, mod_mgr: &Crate) {
-        match ast {
-            AST::Class(class_ast) => {
-                for p in class_ast.extends_or_impls.iter() {
-                    // find base class
-                    let base = self.resolve_user_define_type(p, mod_mgr, None);
-                    let base_ref = unsafe { base.as_ref() };
+        let class_ast = match &ast.kind {
+            ASTKind::Class(class_ast)
+            | ASTKind::Struct(class_ast)
+            | ASTKind::Interface(class_ast)
+            | ASTKind::Enum(class_ast) => class_ast,
+            _ => unreachable!(),
+        };
+
+        for p in class_ast.extends_or_impls.iter() {
+            if p.get_self().unwrap().1.is_some() {
+                self.error(
+                    ErrCode::InvalidGeneric,
+                    ast.span,
+                    format!("{} cannot inherit generic instance {}", class, p),
+                );
+            }
+            // find base class or interface
+            let base = self.resolve_user_define_type(p, ast.span, mod_mgr, None);
+            let base_ref = unsafe { base.as_ref() };
 
+            if base_ref.is_interface() {
+                let interface = base.as_ptr() as *const Type;
+                if class.impls.contains(&interface) {
+                    self.error(
+                        ErrCode::Duplicated,
+                        ast.span,
+                        format!("Duplicated interface {} of {}", base_ref, class),
+                    );
+                }
+                class.impls.push(interface);
+                continue;
+            }
+
+            match &ast.kind {
+                ASTKind::Class(_) => {
                     if base_ref.attrib.is(TypeAttribFlag::Sealed) {
-                        panic!("Class {} cannot inherit sealed class {}", class, base_ref);
+                        self.error(
+                            ErrCode::InvalidInheritance,
+                            ast.span,
+                            format!("Class {} cannot inherit sealed class {}", class, base_ref),
+                        );
                     }
                     if !class.extends.is_null() {
-                        panic!("Multiple inheritance for class {}", class_ast.name);
+                        self.error(
+                            ErrCode::InvalidInheritance,
+                            ast.span,
+                            format!("Multiple inheritance for class {}", class_ast.name),
+                        );
                     }
                     class.extends = base.as_ptr() as *const Type;
                 }
+                ASTKind::Struct(_) => self.error(
+                    ErrCode::InvalidInheritance,
+                    ast.span,
+                    format!(
+                        "Struct {} can only implement interfaces but {} is not",
+                        class, base_ref
+                    ),
+                ),
+                ASTKind::Interface(_) => self.error(
+                    ErrCode::InvalidInheritance,
+                    ast.span,
+                    format!(
+                        "Interface {} can only inherit interfaces but {} is not",
+                        class, base_ref
+                    ),
+                ),
+                _ => unreachable!(),
+            }
+        }
 
+        match &ast.kind {
+            ASTKind::Class(_) => {
                 if class.extends.is_null() {
                     // no explicitly designated base class
                     // implicitly derived from std::Object
@@ -48,7 +107,7 @@ impl ModuleBuildCtx {
                     }
                 }
             }
-            AST::Struct(_) => {
+            ASTKind::Struct(_) | ASTKind::Enum(_) => {
                 class.extends = mod_mgr
                     .mod_tbl
                     .get("std")
@@ -58,17 +117,85 @@ impl ModuleBuildCtx {
                     .unwrap()
                     .as_ref() as *const Type;
             }
+            // interface has no base class
+            ASTKind::Interface(_) => {}
             _ => unreachable!(),
         }
     }
 
+    /// resolve constraints of generic params.
+    /// Generic params are declared before constraints are resolved so that they can refer to each other
+    fn declare_generic_params(
+        &self,
+        mod_mgr: &Crate,
+        span: Span,
+        class: &mut Type,
+        mut method_generic_params: Option<&mut [GenericParam]>,
+        decls: &Vec<ASTGenericParamDecl>,
+    ) {
+        for (i, decl) in decls.iter().enumerate() {
+            let constraints: Vec<RValType> = decl
+                .constraints
+                .iter()
+                .map(|c| {
+                    let ty = ASTType::UsrType(c.clone());
+                    match &method_generic_params {
+                        Some(ps) => self.get_rval_type(&ty, span, mod_mgr, class, ps),
+                        None => self.get_rval_type(&ty, span, mod_mgr, class, &[]),
+                    }
+                })
+                .collect();
+            for constraint in constraints.iter() {
+                match constraint {
+                    RValType::Class(_)
+                    | RValType::Value(_)
+                    | RValType::String
+                    | RValType::GenericInst(_, _, _)
+                    | RValType::Var(_)
+                    | RValType::MVar(_) => {}
+                    _ => self.error(
+                        ErrCode::InvalidGeneric,
+                        span,
+                        format!("{} cannot be a constraint of {}", constraint, decl.id),
+                    ),
+                }
+            }
+            match &method_generic_params {
+                Some(ps) => {
+                    if ps[..i].iter().any(|p| p.name == decl.id)
+                        || class.generic_params.iter().any(|p| p.name == decl.id)
+                    {
+                        self.error(
+                            ErrCode::Duplicated,
+                            span,
+                            format!("Duplicated generic param {}", decl.id),
+                        );
+                    }
+                }
+                None => {
+                    if class.generic_params[..i].iter().any(|p| p.name == decl.id) {
+                        self.error(
+                            ErrCode::Duplicated,
+                            span,
+                            format!("Duplicated generic param {}", decl.id),
+                        );
+                    }
+                }
+            }
+            match method_generic_params {
+                Some(ref mut ps) => ps[i].constraints = constraints,
+                None => class.generic_params[i].constraints = constraints,
+            }
+        }
+    }
+
     /// declare method according to ast
     fn declare_method(&self, mod_mgr: &Crate, class_mut: &mut Type, ast: Option<&Box<AST>>) {
-        let (ast, name, custom_attribs, attrib, ps, ret) = match ast {
+        let (ast, name, custom_attribs, attrib, generic_params, ps, ret) = match ast {
             Some(ast) => {
                 let ast_ptr = NonNull::new(ast.as_ref() as *const AST as *mut AST);
-                match ast.as_ref() {
-                    AST::Block(_) => (
+                match &ast.kind {
+                    ASTKind::Block(_) => (
                         ast_ptr,
                         CCTOR_NAME,
                         None,
@@ -79,22 +206,40 @@ impl ModuleBuildCtx {
                                 | u16::from(MethodAttribFlag::SpecialName),
                         ),
                         None,
-                        RValType::Void,
+                        None,
+                        None,
                     ), // cctor
-                    AST::Ctor(ctor) => (
-                        ast_ptr,
-                        CTOR_NAME,
-                        Some(&ctor.custom_attribs),
-                        ctor.attrib.clone(),
-                        Some(&ctor.ps),
-                        RValType::Void,
-                    ),
-                    AST::Method(method) => {
+                    ASTKind::Ctor(ctor) => {
+                        if !ctor.generic_params.is_empty() {
+                            self.error(
+                                ErrCode::InvalidGeneric,
+                                ast.span,
+                                format!("Ctor of {} cannot be generic", class_mut),
+                            );
+                        }
+                        (
+                            ast_ptr,
+                            CTOR_NAME,
+                            Some(&ctor.custom_attribs),
+                            ctor.attrib.clone(),
+                            None,
+                            Some(&ctor.ps),
+                            None,
+                        )
+                    }
+                    ASTKind::Method(method) => {
                         let mut attrib = method.attrib.clone();
                         if method.ast_attrib.is(ASTMethodAttribFlag::Override) {
                             // override implies virtual
                             if attrib.is(MethodAttribFlag::Virtual) {
-                                panic!("Method {}.{} is marked as override and cannot be marked as virtual", class_mut, method.name);
+                                self.error(
+                                    ErrCode::InvalidModifier,
+                                    ast.span,
+                                    format!(
+                                        "Method {}.{} is marked as override and cannot be marked as virtual",
+                                        class_mut, method.name
+                                    ),
+                                );
                             }
                             attrib.set(MethodAttribFlag::Virtual);
                         } else if attrib.is(MethodAttribFlag::Virtual) {
@@ -102,13 +247,53 @@ impl ModuleBuildCtx {
                             attrib.set(MethodAttribFlag::NewSlot);
                         }
 
+                        if class_mut.is_interface() {
+                            if attrib.is(MethodAttribFlag::Static)
+                                || method.ast_attrib.is(ASTMethodAttribFlag::Override)
+                            {
+                                self.error(
+                                    ErrCode::InvalidInterface,
+                                    ast.span,
+                                    format!(
+                                        "Interface method {}.{} must be a non-override instance method",
+                                        class_mut, method.name
+                                    ),
+                                );
+                            }
+                            if !attrib.is(MethodAttribFlag::Pub) {
+                                self.error(
+                                    ErrCode::InvalidInterface,
+                                    ast.span,
+                                    format!(
+                                        "Interface method {}.{} must be pub",
+                                        class_mut, method.name
+                                    ),
+                                );
+                            }
+                            if !matches!(method.body.kind, ASTKind::None) {
+                                self.error(
+                                    ErrCode::InvalidInterface,
+                                    ast.span,
+                                    format!(
+                                        "Interface method {}.{} cannot have a body",
+                                        class_mut, method.name
+                                    ),
+                                );
+                            }
+                            // interface method is implemented by classes
+                            attrib.set(MethodAttribFlag::Virtual);
+                            attrib.set(MethodAttribFlag::NewSlot);
+                            attrib.set(MethodAttribFlag::Abstract);
+                        }
+
                         (
                             ast_ptr,
                             method.name.as_str(),
                             Some(&method.custom_attribs),
                             attrib,
+                            Some(&method.generic_params),
                             Some(&method.ps),
-                            self.get_rval_type(&method.ret, mod_mgr, class_mut),
+                            Some(method.ret.as_ref()),
                         )
                     }
                     _ => unreachable!(),
@@ -126,18 +311,53 @@ impl ModuleBuildCtx {
                             | u16::from(MethodAttribFlag::RTSpecialName),
                     ),
                     None,
-                    RValType::Void,
+                    None,
+                    None,
                 )
             }
         };
 
+        let span = match ast {
+            Some(ast) => unsafe { ast.as_ref() }.span,
+            None => Span::default(),
+        };
+        let mut method_generic_params = Vec::new();
+        if let Some(decls) = generic_params {
+            if !decls.is_empty() && attrib.is(MethodAttribFlag::Virtual) {
+                self.error(
+                    ErrCode::InvalidGeneric,
+                    span,
+                    format!("Generic method {}.{} cannot be virtual", class_mut, name),
+                );
+            }
+            method_generic_params = decls.iter().map(|p| GenericParam::new(&p.id)).collect();
+            self.declare_generic_params(
+                mod_mgr,
+                span,
+                class_mut,
+                Some(&mut method_generic_params),
+                decls,
+            );
+        }
+
+        let ret = match ret {
+            Some(ret) => self.get_rval_type(ret, span, mod_mgr, class_mut, &method_generic_params),
+            None => RValType::Void,
+        };
+
         let ps = if let Some(ps) = ps {
             ps.iter()
                 .map(|p| {
-                    if let AST::Param(id, attrib, ty) = p.as_ref() {
+                    if let ASTKind::Param(id, attrib, ty) = &p.kind {
                         Param {
                             id: id.to_owned(),
-                            ty: self.get_rval_type(ty, mod_mgr, class_mut),
+                            ty: self.get_rval_type(
+                                ty,
+                                p.span,
+                                mod_mgr,
+                                class_mut,
+                                &method_generic_params,
+                            ),
                             attrib: attrib.clone(),
                         }
                     } else {
@@ -155,23 +375,43 @@ impl ModuleBuildCtx {
         );
         if let Some(custom_attribs) = custom_attribs {
             for attr in custom_attribs.iter() {
-                if let AST::CustomAttrib(id, args) = attr.as_ref() {
+                if let ASTKind::CustomAttrib(id, args) = &attr.kind {
                     if id == "Dllimport" {
                         // TODO: use real attribute object
                         // Currently it's adhoc
-                        assert_eq!(args.len(), 1, "Invalid arg for Dllimport attribute");
-                        if let AST::String(_) = args[0].as_ref() {
+                        if args.len() != 1 {
+                            self.error(
+                                ErrCode::InvalidAttribute,
+                                attr.span,
+                                "Invalid arg for Dllimport attribute",
+                            );
+                        }
+                        if let ASTKind::String(_) = &args[0].kind {
                             impl_flag.set_code_ty(MethodImplAttribCodeTypeFlag::Native);
                             impl_flag.set_managed(MethodImplAttribManagedFlag::Unmanaged);
                         } else {
-                            panic!("Invalid arg for Dllimport attribute");
+                            self.error(
+                                ErrCode::InvalidAttribute,
+                                args[0].span,
+                                "Invalid arg for Dllimport attribute",
+                            );
                         }
                     } else if id == "InternalCall" {
-                        assert_eq!(args.len(), 0, "Invalid arg for InternalCall attribute");
+                        if !args.is_empty() {
+                            self.error(
+                                ErrCode::InvalidAttribute,
+                                attr.span,
+                                "Invalid arg for InternalCall attribute",
+                            );
+                        }
                         impl_flag.set_impl_info(MethodImplInfoFlag::InternalCall);
                         impl_flag.set_code_ty(MethodImplAttribCodeTypeFlag::Runtime);
                     } else {
-                        panic!("Unrecognizable custom attribute {}", id);
+                        self.error(
+                            ErrCode::InvalidAttribute,
+                            attr.span,
+                            format!("Unrecognizable custom attribute {}", id),
+                        );
                     }
                 } else {
                     unreachable!();
@@ -183,10 +423,14 @@ impl ModuleBuildCtx {
             .builder
             .borrow_mut()
             .add_method(name, &ps, &ret, &attrib, &impl_flag);
+        self.builder
+            .borrow_mut()
+            .add_generic_params(Some(method_idx), &method_generic_params);
 
         let method = Box::new(Method {
             parent: NonNull::new(class_mut as *mut Type).unwrap(),
             name: name.to_owned(),
+            generic_params: method_generic_params,
             ret,
             ps,
             attrib: attrib.clone(),
@@ -210,7 +454,13 @@ impl ModuleBuildCtx {
                     }
                 }
                 if is_match {
-                    panic!("Duplicated method {}", method);
+                    let msg = format!("Duplicated method {}", method);
+                    match ast {
+                        Some(ast) => {
+                            self.error(ErrCode::Duplicated, unsafe { ast.as_ref() }.span, msg)
+                        }
+                        None => Diagnostic::error(ErrCode::Duplicated, msg).abort(),
+                    }
                 }
             }
             methods.push(method);
@@ -220,7 +470,7 @@ impl ModuleBuildCtx {
 
         if let Some(custom_attribs) = custom_attribs {
             for (attr_name, args) in custom_attribs.iter().map(|attr| {
-                if let AST::CustomAttrib(id, args) = attr.as_ref() {
+                if let ASTKind::CustomAttrib(id, args) = &attr.kind {
                     (id, args)
                 } else {
                     unreachable!()
@@ -229,7 +479,7 @@ impl ModuleBuildCtx {
                 if attr_name == "Dllimport" {
                     // TODO: use real attribute object
                     // Currently it's adhoc
-                    if let AST::String(v) = args[0].as_ref() {
+                    if let ASTKind::String(v) = &args[0].kind {
                         let pinvoke_attrib = PInvokeAttrib::new(
                             PInvokeAttribCharsetFlag::Ansi,
                             PInvokeAttribCallConvFlag::CDecl,
@@ -251,11 +501,121 @@ impl ModuleBuildCtx {
         }
     }
 
+    fn declare_field(
+        &self,
+        class_mut: &mut Type,
+        span: Span,
+        id: &str,
+        ty: RValType,
+        flag: FieldAttrib,
+    ) {
+        // Build Field in class file
+        let idx = self.builder.borrow_mut().add_field(id, &ty, &flag);
+
+        let f = Box::new(Field {
+            parent: NonNull::new(class_mut as *mut Type).unwrap(),
+            name: id.to_owned(),
+            attrib: flag,
+            ty,
+            idx,
+        });
+
+        if let Some(_) = class_mut.fields.insert(id.to_owned(), f) {
+            self.error(
+                ErrCode::Duplicated,
+                span,
+                format!("Dulicated field {} in class {}", id, class_mut.name),
+            );
+        }
+    }
+
+    /// Enum is lowered to a value type.
+    /// Discriminant is stored in field ENUM_TAG_FIELD and each payload has its own field
+    fn declare_variants(&self, mod_mgr: &Crate, class_mut: &mut Type, variant_asts: &[Box<AST>]) {
+        let flag = FieldAttrib::from(FieldAttribFlag::Pub.into());
+        self.declare_field(
+            class_mut,
+            Span::default(),
+            ENUM_TAG_FIELD,
+            RValType::I32,
+            flag,
+        );
+
+        // implicit discriminant is 1 more than the previous one
+        let mut discriminant = 0;
+        for ast in variant_asts.iter() {
+            if let ASTKind::Variant(id, tys, val) = &ast.kind {
+                if !matches!(val.kind, ASTKind::None) {
+                    discriminant = match eval(val) {
+                        Ok(ConstVal::Int(v, RValType::I32)) => v as i32,
+                        Ok(v) => self.error(
+                            ErrCode::TypeMismatch,
+                            val.span,
+                            format!(
+                                "Discriminant of {}::{} should be i32 but found {}",
+                                class_mut,
+                                id,
+                                v.ty()
+                            ),
+                        ),
+                        Err(EvalErr::NotConst(span)) => self.error(
+                            ErrCode::InvalidExpr,
+                            span,
+                            format!("Discriminant of {}::{} is not a constant", class_mut, id),
+                        ),
+                        Err(EvalErr::Invalid(code, span, msg)) => self.error(code, span, msg),
+                    };
+                }
+
+                if class_mut.query_variant(id).is_some() {
+                    self.error(
+                        ErrCode::Duplicated,
+                        ast.span,
+                        format!("Duplicated variant {} in enum {}", id, class_mut),
+                    );
+                }
+                if let Some(v) = class_mut
+                    .variants
+                    .iter()
+                    .find(|v| v.discriminant == discriminant)
+                {
+                    self.error(
+                        ErrCode::Duplicated,
+                        ast.span,
+                        format!(
+                            "Discriminant {} of {}::{} is already used by {}",
+                            discriminant, class_mut, id, v.name
+                        ),
+                    );
+                }
+
+                let mut fields = vec![];
+                for (i, ty) in tys.iter().enumerate() {
+                    let name = Variant::field_name(id, i);
+                    let ty = self.get_rval_type(ty, ast.span, mod_mgr, class_mut, &[]);
+                    self.declare_field(class_mut, ast.span, &name, ty, flag);
+                    fields.push(name);
+                }
+                class_mut.variants.push(Variant {
+                    name: id.to_owned(),
+                    discriminant,
+                    fields,
+                });
+                discriminant = discriminant.wrapping_add(1);
+            } else {
+                unreachable!();
+            }
+        }
+    }
+
     /// set extends, declare methods and fields
     pub fn class_pass(&self, mod_mgr: &Crate) {
         for class in self.class_asts.iter() {
-            match class.as_ref() {
-                AST::Class(class_ast) | AST::Struct(class_ast) => {
+            match &class.kind {
+                ASTKind::Class(class_ast)
+                | ASTKind::Struct(class_ast)
+                | ASTKind::Interface(class_ast)
+                | ASTKind::Enum(class_ast) => {
                     let mut class_mut = self
                         .get_module_mut()
                         .classes
@@ -264,47 +624,126 @@ impl ModuleBuildCtx {
                         .as_mut();
                     // class should be declared after member declarations of previous class
                     //      and before member declarations of this class
+                    if class_mut.attrib.is_vis(TypeAttribVisFlag::Pub)
+                        && !self.get_module().is_exported(mod_mgr)
+                    {
+                        // other crates cannot see classes in a module that is not pub
+                        class_mut.attrib.set_vis(TypeAttribVisFlag::Crate);
+                    }
                     class_mut.idx = self
                         .builder
                         .borrow_mut()
-                        .add_class(&class_ast.name, &class_ast.attrib);
+                        .add_class(&class_ast.name, &class_mut.attrib);
                     // Set extends
                     self.set_extends1(class, class_mut, mod_mgr);
 
+                    self.declare_generic_params(
+                        mod_mgr,
+                        class.span,
+                        class_mut,
+                        None,
+                        &class_ast.generic_params,
+                    );
+                    self.builder
+                        .borrow_mut()
+                        .add_generic_params(None, &class_mut.generic_params);
+
                     // declare fields
                     for field in class_ast.fields.iter() {
-                        if let AST::Field(id, flag, _, ty) = field.as_ref() {
+                        if class_mut.is_interface() {
+                            self.error(
+                                ErrCode::InvalidInterface,
+                                field.span,
+                                format!("Interface {} cannot have fields", class_mut),
+                            );
+                        }
+                        if let ASTKind::Field(id, flag, _, ty) = &field.kind {
                             // Field will have default initialization
-                            let ty = self.get_rval_type(ty, mod_mgr, &class_mut);
+                            let ty = self.get_rval_type(ty, field.span, mod_mgr, &class_mut, &[]);
+                            self.declare_field(class_mut, field.span, id, ty, *flag);
+                        }
+                    }
 
-                            // Build Field in class file
-                            let idx = self.builder.borrow_mut().add_field(id, &ty, flag);
+                    if class_mut.is_enum() {
+                        self.declare_variants(mod_mgr, class_mut, &class_ast.fields);
+                    }
 
-                            let field = Box::new(Field {
-                                parent: NonNull::new(class_mut as *mut Type).unwrap(),
-                                name: id.clone(),
-                                attrib: *flag,
-                                ty,
-                                idx,
-                            });
+                    // declare consts, they are evaluated at compile time and never emitted
+                    for c in class_ast.fields.iter() {
+                        if let ASTKind::Const(id, _, ty, val) = &c.kind {
+                            let ty = self.get_rval_type(ty, c.span, mod_mgr, class_mut, &[]);
+                            let v = match eval(val) {
+                                Ok(v) => v,
+                                Err(EvalErr::NotConst(span)) => self.error(
+                                    ErrCode::InvalidExpr,
+                                    span,
+                                    format!("Initializer of const {} is not a constant", id),
+                                ),
+                                Err(EvalErr::Invalid(code, span, msg)) => {
+                                    self.error(code, span, msg)
+                                }
+                            };
+                            if v.ty() != ty {
+                                self.error(
+                                    ErrCode::TypeMismatch,
+                                    val.span,
+                                    format!("Cannot assign {} to const {}: {}", v.ty(), id, ty),
+                                );
+                            }
 
-                            if let Some(_) = class_mut.fields.insert(id.to_owned(), field) {
-                                // TODO: use expect_none once it becomes stable
-                                panic!("Dulicated field {} in class {}", id, class_mut.name);
+                            if class_mut.fields.contains_key(id)
+                                || class_mut.consts.insert(id.to_owned(), v).is_some()
+                            {
+                                self.error(
+                                    ErrCode::Duplicated,
+                                    c.span,
+                                    format!("Duplicated const {} in class {}", id, class_mut.name),
+                                );
                             }
                         }
                     }
 
                     // Add static init
-                    match class_ast.cctor.as_ref() {
-                        AST::Block(_) => {
+                    match &class_ast.cctor.kind {
+                        ASTKind::Block(_) => {
+                            if class_mut.is_interface() {
+                                self.error(
+                                    ErrCode::InvalidInterface,
+                                    class_ast.cctor.span,
+                                    format!(
+                                        "Interface {} cannot have static initializer",
+                                        class_mut
+                                    ),
+                                );
+                            }
+                            if !class_mut.generic_params.is_empty() {
+                                self.error(
+                                    ErrCode::InvalidGeneric,
+                                    class_ast.cctor.span,
+                                    format!(
+                                        "Static initializer is not supported in generic class {}",
+                                        class_mut
+                                    ),
+                                );
+                            }
                             self.declare_method(mod_mgr, &mut class_mut, Some(&class_ast.cctor));
                         }
-                        AST::None => (),
+                        ASTKind::None => (),
                         _ => unreachable!("Parser error"),
                     };
 
-                    if class_ast.ctors.is_empty() {
+                    if class_mut.is_enum() {
+                        // enum is created by its variants
+                    } else if class_mut.is_interface() {
+                        // interface has no ctor
+                        if let Some(ctor_ast) = class_ast.ctors.first() {
+                            self.error(
+                                ErrCode::InvalidInterface,
+                                ctor_ast.span,
+                                format!("Interface {} cannot have ctors", class_mut),
+                            );
+                        }
+                    } else if class_ast.ctors.is_empty() {
                         // Add default object creator
                         self.declare_method(mod_mgr, &mut class_mut, None);
                     } else {
@@ -340,8 +779,73 @@ impl ModuleBuildCtx {
         }
     }
 
-    /// set extends in ir file and do inheritance check
+    /// check that all methods of interface and its base interfaces are implemented by class
+    fn check_interface_impl(&self, span: Span, class: &Type, interface: &Type) {
+        for base_interface in interface.impls.iter() {
+            self.check_interface_impl(span, class, unsafe { base_interface.as_ref().unwrap() });
+        }
+
+        for interface_method in interface.methods.values().flatten() {
+            let mut implemented = None;
+            let mut c = class as *const Type;
+            while let Some(c_ref) = unsafe { c.as_ref() } {
+                if let Some(method_grp) = c_ref.methods.get(&interface_method.name) {
+                    implemented = method_grp
+                        .iter()
+                        .find(|m| m.sig_match(interface_method) && m.ret == interface_method.ret);
+                    if implemented.is_some() {
+                        break;
+                    }
+                }
+                c = c_ref.extends;
+            }
+
+            if let Some(m) = implemented {
+                if !m.attrib.is(MethodAttribFlag::Pub) {
+                    self.error(
+                        ErrCode::InvalidInterface,
+                        span,
+                        format!("{} implements {} and must be pub", m, interface_method),
+                    );
+                }
+            } else {
+                self.error(
+                    ErrCode::InvalidInterface,
+                    span,
+                    format!("{} does not implement {}", class, interface_method),
+                );
+            }
+        }
+    }
+
+    /// set extends and impls in ir file and do inheritance check
     pub fn set_extends2(&self) {
+        for class_ast in self.class_asts.iter() {
+            let class_name = match &class_ast.kind {
+                ASTKind::Class(c)
+                | ASTKind::Struct(c)
+                | ASTKind::Interface(c)
+                | ASTKind::Enum(c) => &c.name,
+                _ => unreachable!(),
+            };
+            let class = self.get_module().classes.get(class_name).unwrap();
+
+            // set impls in IrFile
+            for interface in class.impls.iter() {
+                let interface = unsafe { interface.as_ref().unwrap() };
+                {
+                    let mut builder = self.builder.borrow_mut();
+                    let (interface_idx, interface_idx_tag) =
+                        builder.add_const_class(interface.modname(), &interface.name);
+                    builder.add_class_impl(class.idx, interface_idx, interface_idx_tag);
+                }
+
+                if !class.is_interface() {
+                    self.check_interface_impl(class_ast.span, class, interface);
+                }
+            }
+        }
+
         for class in self
             .get_module()
             .classes
@@ -368,7 +872,11 @@ impl ModuleBuildCtx {
                 let mut base = class.extends;
                 while let Some(base_ref) = unsafe { base.as_ref() } {
                     if base_ref.fields.contains_key(field_name) {
-                        println!("Warning: {} has an instance field {} that override field of base type {}", class, field_name, base_ref);
+                        Diagnostic::warning(format!(
+                            "{} has an instance field {} that override field of base type {}",
+                            class, field_name, base_ref
+                        ))
+                        .emit();
                         break;
                     }
 
@@ -385,9 +893,8 @@ impl ModuleBuildCtx {
                     .iter()
                     .filter(|m| !m.attrib.is(MethodAttribFlag::Static))
                 {
-                    let method_ast = method.ast.unwrap();
-                    let method_ast = if let AST::Method(method_ast) = unsafe { method_ast.as_ref() }
-                    {
+                    let ast = unsafe { method.ast.unwrap().as_ref() };
+                    let method_ast = if let ASTKind::Method(method_ast) = &ast.kind {
                         method_ast
                     } else {
                         unreachable!()
@@ -400,7 +907,13 @@ impl ModuleBuildCtx {
                                 if method.sig_match(base_method) {
                                     has_override = true;
                                     if !method_ast.ast_attrib.is(ASTMethodAttribFlag::Override) {
-                                        println!("Warning: {} has a instance method {} that override method of base type {}", class, method_name, base_ref);
+                                        self.warning(
+                                            ast.span,
+                                            format!(
+                                                "{} has a instance method {} that override method of base type {}",
+                                                class, method_name, base_ref
+                                            ),
+                                        );
                                     }
                                     break;
                                 }
@@ -414,7 +927,14 @@ impl ModuleBuildCtx {
                     }
 
                     if method_ast.ast_attrib.is(ASTMethodAttribFlag::Override) && !has_override {
-                        panic!("Method {}.{} is marked override but no suitable method found to override", class, method);
+                        self.error(
+                            ErrCode::InvalidInheritance,
+                            ast.span,
+                            format!(
+                                "Method {}.{} is marked override but no suitable method found to override",
+                                class, method
+                            ),
+                        );
                     }
                 }
             }
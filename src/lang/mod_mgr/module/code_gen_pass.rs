@@ -1,8 +1,10 @@
 use super::super::super::super::XicCfg;
-use super::super::super::ast::AST;
+use super::super::super::ast::ASTKind;
+use super::super::super::diagnostic::ErrCode;
 use super::super::super::gen::{
     gen, gen_base_ctor, CodeGenCtx, MethodBuilder, RValType, ValExpectation, ValType,
 };
+use super::super::super::util::Span;
 use super::super::{Crate, Locals, Method, Type};
 use super::ModuleBuildCtx;
 
@@ -13,7 +15,15 @@ use std::cell::RefCell;
 
 // code gen
 impl ModuleBuildCtx {
-    fn code_gen_method(&self, c: &Crate, class: &Type, m: &Method, optim_level: usize) {
+    /// class_span is used to report errors in default ctor
+    fn code_gen_method(
+        &self,
+        c: &Crate,
+        class: &Type,
+        class_span: Span,
+        m: &Method,
+        optim_level: usize,
+    ) {
         let ctx = CodeGenCtx {
             mgr: c,
             module: self,
@@ -28,33 +38,40 @@ impl ModuleBuildCtx {
                 .collect(),
             method_builder: RefCell::new(MethodBuilder::new()),
             loop_ctx: RefCell::new(vec![]),
+            try_ctx: RefCell::new(vec![]),
+            ret_epilogue: RefCell::new(None),
         };
 
         let ret = match m.ast {
             Some(ast) => {
                 let ast = unsafe { ast.as_ref() };
-                match ast {
-                    AST::Block(_) => gen(&ctx, ast, ValExpectation::RVal), // cctor
-                    AST::Ctor(ctor) => {
+                match &ast.kind {
+                    ASTKind::Block(_) => gen(&ctx, ast, ValExpectation::RVal), // cctor
+                    ASTKind::Ctor(ctor) => {
                         if !class.extends.is_null() {
+                            ctx.add_seq_point(ast.span);
                             // has base class, call base ctor for each ctor
                             if let Some(base_args) = &ctor.base_args {
-                                gen_base_ctor(&ctx, base_args);
+                                gen_base_ctor(&ctx, ast.span, base_args);
                             } else {
                                 // call default ctor
                                 let base_args = Vec::new();
-                                gen_base_ctor(&ctx, &base_args);
+                                gen_base_ctor(&ctx, ast.span, &base_args);
                             }
                         } else if ctor.base_args.is_some() {
                             // has no base class but has base args
-                            panic!(
-                                "{} call base ctor but {} actually has no base class",
-                                ctor, class
+                            self.error(
+                                ErrCode::InvalidInheritance,
+                                ast.span,
+                                format!(
+                                    "{} call base ctor but {} actually has no base class",
+                                    m, class
+                                ),
                             );
                         }
                         gen(&ctx, &ctor.body, ValExpectation::RVal)
                     }
-                    AST::Method(method) => gen(&ctx, &method.body, ValExpectation::RVal),
+                    ASTKind::Method(method) => gen(&ctx, &method.body, ValExpectation::RVal),
                     _ => unreachable!(),
                 }
             }
@@ -62,24 +79,39 @@ impl ModuleBuildCtx {
                 // default ctor
                 if !class.extends.is_null() {
                     let base_args = Vec::new();
-                    gen_base_ctor(&ctx, &base_args);
+                    gen_base_ctor(&ctx, class_span, &base_args);
                 }
                 ValType::RVal(RValType::Void)
             }
         };
 
         // Check type equivalent
+        let ret_mismatch = |ret_ty: &RValType| {
+            let span = match m.ast {
+                Some(ast) => unsafe { ast.as_ref() }.span,
+                None => class_span,
+            };
+            self.error(
+                ErrCode::TypeMismatch,
+                span,
+                format!(
+                    "Expect return {} but return {}",
+                    ctx.ty_name(&m.ret),
+                    ctx.ty_name(ret_ty)
+                ),
+            )
+        };
         match &ret {
             ValType::RVal(rval_ty) => {
                 if rval_ty != &m.ret {
-                    panic!("Expect return {} but return {}", m.ret, rval_ty);
+                    ret_mismatch(rval_ty);
                 }
                 // Add return instruction
                 ctx.method_builder.borrow_mut().add_inst(Inst::Ret);
             }
             ValType::Ret(ret_ty) => {
                 if ret_ty != &m.ret {
-                    panic!("Expect return {} but return {}", m.ret, ret_ty);
+                    ret_mismatch(ret_ty);
                 }
             }
             _ => unreachable!(),
@@ -89,18 +121,21 @@ impl ModuleBuildCtx {
     }
 
     pub fn code_gen(&self, mod_mgr: &Crate, cfg: &XicCfg) {
-        for class in self.class_asts.iter() {
-            match class.as_ref() {
-                AST::Class(class) => {
+        for class_ast in self.class_asts.iter() {
+            let class_span = class_ast.span;
+            match &class_ast.kind {
+                ASTKind::Class(class) => {
                     let class_ref = self.get_module().classes.get(&class.name).unwrap().as_ref();
                     // gen static init
-                    match class.cctor.as_ref() {
-                        AST::Block(_) => {
+                    match &class.cctor.kind {
+                        ASTKind::Block(_) => {
                             let ms = class_ref.methods.get(CCTOR_NAME).unwrap();
                             // only 1 cctor
-                            self.code_gen_method(mod_mgr, &class_ref, &ms[0], cfg.optim);
+                            self.code_gen_method(
+                                mod_mgr, &class_ref, class_span, &ms[0], cfg.optim,
+                            );
                         }
-                        AST::None => (),
+                        ASTKind::None => (),
                         _ => unreachable!("Parser error"),
                     };
 
@@ -108,12 +143,14 @@ impl ModuleBuildCtx {
                     if class.ctors.is_empty() {
                         // gen default ctor
                         assert_eq!(ctors.len(), 1);
-                        self.code_gen_method(mod_mgr, &class_ref, &ctors[0], cfg.optim);
+                        self.code_gen_method(mod_mgr, &class_ref, class_span, &ctors[0], cfg.optim);
                     } else {
                         for ctor in ctors.iter() {
                             if ctor.impl_flag.is_code_ty(MethodImplAttribCodeTypeFlag::IL) {
                                 // only code gen IL method
-                                self.code_gen_method(mod_mgr, &class_ref, ctor, cfg.optim);
+                                self.code_gen_method(
+                                    mod_mgr, &class_ref, class_span, ctor, cfg.optim,
+                                );
                             }
                         }
                     }
@@ -122,22 +159,24 @@ impl ModuleBuildCtx {
                         for m in ms.iter() {
                             if m.impl_flag.is_code_ty(MethodImplAttribCodeTypeFlag::IL) {
                                 // only code gen IL method
-                                self.code_gen_method(mod_mgr, &class_ref, m, cfg.optim);
+                                self.code_gen_method(mod_mgr, &class_ref, class_span, m, cfg.optim);
                             }
                         }
                     }
                 }
-                AST::Struct(class) => {
+                ASTKind::Struct(class) => {
                     // Same as class
                     let class_ref = self.get_module().classes.get(&class.name).unwrap().as_ref();
                     // gen static init
-                    match class.cctor.as_ref() {
-                        AST::Block(_) => {
+                    match &class.cctor.kind {
+                        ASTKind::Block(_) => {
                             let ms = class_ref.methods.get(CCTOR_NAME).unwrap();
                             // only 1 cctor
-                            self.code_gen_method(mod_mgr, &class_ref, &ms[0], cfg.optim);
+                            self.code_gen_method(
+                                mod_mgr, &class_ref, class_span, &ms[0], cfg.optim,
+                            );
                         }
-                        AST::None => (),
+                        ASTKind::None => (),
                         _ => unreachable!("Parser error"),
                     };
 
@@ -145,12 +184,14 @@ impl ModuleBuildCtx {
                     if class.ctors.is_empty() {
                         // gen default ctor
                         assert_eq!(ctors.len(), 1);
-                        self.code_gen_method(mod_mgr, &class_ref, &ctors[0], cfg.optim);
+                        self.code_gen_method(mod_mgr, &class_ref, class_span, &ctors[0], cfg.optim);
                     } else {
                         for ctor in ctors.iter() {
                             if ctor.impl_flag.is_code_ty(MethodImplAttribCodeTypeFlag::IL) {
                                 // only code gen IL method
-                                self.code_gen_method(mod_mgr, &class_ref, ctor, cfg.optim);
+                                self.code_gen_method(
+                                    mod_mgr, &class_ref, class_span, ctor, cfg.optim,
+                                );
                             }
                         }
                     }
@@ -159,11 +200,15 @@ impl ModuleBuildCtx {
                         for m in ms.iter() {
                             if m.impl_flag.is_code_ty(MethodImplAttribCodeTypeFlag::IL) {
                                 // only code gen IL method
-                                self.code_gen_method(mod_mgr, &class_ref, m, cfg.optim);
+                                self.code_gen_method(mod_mgr, &class_ref, class_span, m, cfg.optim);
                             }
                         }
                     }
                 }
+                // methods of interface have no body
+                ASTKind::Interface(_) => {}
+                // enum has no methods
+                ASTKind::Enum(_) => {}
                 _ => unreachable!(),
             }
         }
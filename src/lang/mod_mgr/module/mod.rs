@@ -1,3 +1,4 @@
+mod cfg;
 mod class_pass;
 mod code_gen_pass;
 
@@ -6,21 +7,27 @@ use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::ptr::{self, NonNull};
 
+use xir::attrib::TypeAttribVisFlag;
 use xir::file::IrFile;
 
 use super::super::super::XicCfg;
-use super::super::ast::{ASTType, AST};
+use super::super::ast::{ASTKind, ASTType, AST};
+use super::super::diagnostic::{Diagnostic, ErrCode};
 use super::super::gen::{Builder, RValType};
 use super::super::parser;
-use super::super::util::{IItemPath, ItemPathBuf};
+use super::super::util::{IItemPath, ItemPathBuf, Span};
 use super::external::load_external_crate;
-use super::{Crate, Type};
+use super::tuple::{gen_tuple_asts, get_tuple_type, TUPLE_MAX_ARITY, TUPLE_MIN_ARITY};
+use super::{Crate, GenericParam, Type};
+use cfg::CfgStripper;
 
 pub struct Module {
     pub mod_path: ItemPathBuf,
+    /// visibility declared in the parent module, root modules are pub
+    pub vis: TypeAttribVisFlag,
     pub sub_mods: HashSet<String>,
     /// key: class_name
     pub classes: HashMap<String, Box<Type>>,
@@ -38,6 +45,58 @@ impl Module {
     pub fn is_root(&self) -> bool {
         self.mod_path.len() == 1
     }
+
+    pub fn crate_name(&self) -> &str {
+        self.mod_path.get_root().unwrap().0
+    }
+
+    /// whether this module is ancestor or one of its descendants
+    pub fn is_in(&self, ancestor: &Module) -> bool {
+        ancestor.mod_path.len() <= self.mod_path.len()
+            && ancestor
+                .mod_path
+                .iter()
+                .zip(self.mod_path.iter())
+                .all(|((a, _), (b, _))| a == b)
+    }
+
+    fn parent<'c>(&self, c: &'c Crate) -> Option<&'c Module> {
+        if self.is_root() {
+            None
+        } else {
+            Some(c.mod_tbl.get(self.mod_path.get_super().as_str()).unwrap())
+        }
+    }
+
+    /// whether this module and all its ancestors are pub so that its items can be used by other crates
+    pub fn is_exported(&self, c: &Crate) -> bool {
+        let mut m = self;
+        while let Some(parent) = m.parent(c) {
+            if m.vis != TypeAttribVisFlag::Pub {
+                return false;
+            }
+            m = parent;
+        }
+        true
+    }
+
+    /// whether this module and all its ancestors are visible in module from
+    pub fn is_visible_from(&self, from: &Module, c: &Crate) -> bool {
+        let mut m = self;
+        while let Some(parent) = m.parent(c) {
+            let visible = match m.vis {
+                TypeAttribVisFlag::Pub => true,
+                TypeAttribVisFlag::Crate => m.crate_name() == from.crate_name(),
+                // priv mod is visible in its parent
+                TypeAttribVisFlag::Priv => from.is_in(parent),
+            };
+            if !visible {
+                return false;
+            }
+            m = parent;
+        }
+        true
+    }
 }
 
 impl fmt::Display for Module {
@@ -47,9 +106,12 @@ impl fmt::Display for Module {
 }
 
 pub struct ModuleBuildCtx {
-    /// Vec<Box<AST::Class|AST::Struct>>
+    /// source file of this module
+    pub fpath: PathBuf,
+    /// Vec<Box<ASTKind::Class|ASTKind::Struct|ASTKind::Interface|ASTKind::Enum>>
     class_asts: Vec<Box<AST>>,
-    pub use_map: HashMap<String, ItemPathBuf>,
+    /// alias -> (module path, span of use), checked in Crate::build after all modules are loaded
+    pub use_map: HashMap<String, (ItemPathBuf, Span)>,
 
     pub builder: RefCell<Builder>,
 
@@ -57,7 +119,21 @@ pub struct ModuleBuildCtx {
     module: *mut Module,
 }
 
-pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
+/// Errors are collected in diags so that errors in all modules are reported
+///
+/// decl: file and span of `mod xxx;` that declares this module, None for the root module
+pub fn new_module(
+    mod_path: ItemPathBuf,
+    vis: TypeAttribVisFlag,
+    decl: Option<(&Path, Span)>,
+    mgr: &mut Crate,
+    cfg: &XicCfg,
+    diags: &mut Vec<Diagnostic>,
+) {
+    let at_decl = |diag: Diagnostic| match decl {
+        Some((path, span)) => diag.with_loc(path, span),
+        None => diag,
+    };
     let mut output_dir = cfg.out_dir.clone();
     let mut input_dir = cfg.root_dir.clone();
     let fpath = if mod_path.len() == 1 {
@@ -72,24 +148,34 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
         let mut fpath2 = input_dir.join(mod_path.get_self().unwrap().0);
         fpath2.push("mod.xi");
         if fpath1.is_file() && fpath2.is_file() {
-            panic!(
-                "Ambiguous module {}. {} or {}?",
-                mod_path,
-                fpath1.display(),
-                fpath2.display()
-            );
+            diags.push(at_decl(Diagnostic::error(
+                ErrCode::InvalidModule,
+                format!(
+                    "Ambiguous module {}. {} or {}?",
+                    mod_path,
+                    fpath1.display(),
+                    fpath2.display()
+                ),
+            )));
+            return;
         }
         if fpath1.is_file() {
             fpath1
         } else if fpath2.is_file() {
             fpath2
         } else {
-            panic!(
-                "Cannot find module {} (Consider create {} or {})",
-                mod_path,
-                fpath1.display(),
-                fpath2.display()
+            diags.push(
+                at_decl(Diagnostic::error(
+                    ErrCode::InvalidModule,
+                    format!("Cannot find module {}", mod_path),
+                ))
+                .with_note(format!(
+                    "Consider create {} or {}",
+                    fpath1.display(),
+                    fpath2.display()
+                )),
             );
+            return;
         }
     };
 
@@ -97,12 +183,14 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
 
     let mut this_mod = Box::new(Module {
         mod_path,
+        vis,
         sub_mods: HashSet::new(),
         classes: HashMap::new(),
     });
 
-    // Parse source file
-    let ast = parser::peg_parse(&fpath).unwrap();
+    // Parse source file. Go on with sub modules even if there are syntax errors
+    let (ast, errs) = parser::peg_parse(&fpath);
+    diags.extend(errs);
 
     if cfg.verbose >= 2 {
         // save ast to .json file
@@ -112,13 +200,34 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
     }
 
     let builder = RefCell::new(Builder::new(this_mod.fullname()));
-    if let AST::File(mods, exts, uses, classes) = *ast {
+    if cfg.debug_info {
+        builder.borrow_mut().enable_debug_info(&fpath);
+    }
+    let file_span = ast.span;
+    if let ASTKind::File(mut mods, exts, uses, mut classes) = ast.kind {
+        // evaluate #[cfg(...)] before anything else
+        let mut stripper = CfgStripper::new(cfg, &fpath, diags);
+        mods.retain_mut(|(_, _, attribs, _)| stripper.strip_attribs(attribs));
+        classes.retain_mut(|class| match &mut class.kind {
+            ASTKind::Class(ty)
+            | ASTKind::Struct(ty)
+            | ASTKind::Interface(ty)
+            | ASTKind::Enum(ty) => stripper.strip_class(ty),
+            _ => true,
+        });
+
         if this_mod.is_root() {
             // load external modules specified in root module
             let mut exts_map: HashMap<String, Option<&PathBuf>> = HashMap::new();
             for ext in exts.iter() {
                 if let Some(_) = exts_map.insert(ext.to_owned(), None) {
-                    panic!("Declaring duplicated external module {}", ext);
+                    diags.push(
+                        Diagnostic::error(
+                            ErrCode::Duplicated,
+                            format!("Declaring duplicated external module {}", ext),
+                        )
+                        .with_loc(&fpath, file_span),
+                    );
                 }
             }
             for ext_path in cfg.ext_paths.iter() {
@@ -127,12 +236,15 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
                 // only import declared external modules
                 if let Some(imported) = exts_map.get_mut(ext_mod_name) {
                     if let Some(old_path) = imported {
-                        panic!(
-                            "Ambiguous external module {}: {} or {}?",
-                            ext_mod_name,
-                            old_path.display(),
-                            ext_path.display()
-                        );
+                        diags.push(Diagnostic::error(
+                            ErrCode::InvalidModule,
+                            format!(
+                                "Ambiguous external module {}: {} or {}?",
+                                ext_mod_name,
+                                old_path.display(),
+                                ext_path.display()
+                            ),
+                        ));
                     } else {
                         *imported = Some(ext_path);
                     }
@@ -140,33 +252,67 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
                 load_external_crate(&mut mgr.mod_tbl, ext_path.parent().unwrap(), file);
             }
         } else if exts.len() != 0 {
-            println!("Warning: {} is not root mod. External mod specified in this file won't take effect", this_mod.fullname());
+            diags.push(
+                Diagnostic::warning(format!(
+                    "{} is not root mod. External mod specified in this file won't take effect",
+                    this_mod.fullname()
+                ))
+                .with_loc(&fpath, file_span),
+            );
         }
 
-        for sub_mod_name in mods.into_iter() {
+        for (sub_mod_name, sub_mod_vis, _, sub_mod_span) in mods.into_iter() {
             if !this_mod.sub_mods.insert(sub_mod_name.clone()) {
-                panic!(
-                    "Sub-module {} is defined multiple times in {}",
-                    sub_mod_name,
-                    this_mod.fullname()
+                diags.push(
+                    Diagnostic::error(
+                        ErrCode::Duplicated,
+                        format!(
+                            "Sub-module {} is defined multiple times in {}",
+                            sub_mod_name,
+                            this_mod.fullname()
+                        ),
+                    )
+                    .with_loc(&fpath, sub_mod_span),
                 );
+                continue;
             }
 
             let mut sub_mod_path = this_mod.mod_path.clone();
             sub_mod_path.push(&sub_mod_name);
 
-            new_module(sub_mod_path, mgr, cfg);
+            new_module(
+                sub_mod_path,
+                sub_mod_vis,
+                Some((&fpath, sub_mod_span)),
+                mgr,
+                cfg,
+                diags,
+            );
+        }
+
+        if this_mod.is_root() && this_mod.fullname() == "std" {
+            // value types that tuples are lowered to
+            classes.extend(gen_tuple_asts(file_span));
         }
 
         // generate all classes
         for class in classes.iter() {
-            match class.as_ref() {
-                AST::Class(ty) | AST::Struct(ty) => {
+            match &class.kind {
+                ASTKind::Class(ty)
+                | ASTKind::Struct(ty)
+                | ASTKind::Interface(ty)
+                | ASTKind::Enum(ty) => {
                     if this_mod.sub_mods.contains(&ty.name) {
-                        panic!(
-                            "Ambiguous name {} in module {}. Both a sub-module and a class",
-                            ty.name,
-                            this_mod.fullname()
+                        diags.push(
+                            Diagnostic::error(
+                                ErrCode::Duplicated,
+                                format!(
+                                    "Ambiguous name {} in module {}. Both a sub-module and a class",
+                                    ty.name,
+                                    this_mod.fullname()
+                                ),
+                            )
+                            .with_loc(&fpath, class.span),
                         );
                     }
 
@@ -174,24 +320,35 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
                         ty.name.to_owned(),
                         Box::new(Type {
                             name: ty.name.to_owned(),
+                            // constraints are resolved in class pass
+                            generic_params: ty
+                                .generic_params
+                                .iter()
+                                .map(|p| GenericParam::new(&p.id))
+                                .collect(),
                             fields: HashMap::new(),
+                            consts: HashMap::new(),
+                            variants: vec![],
                             methods: HashMap::new(),
                             parent: NonNull::new(this_mod.as_ref() as *const Module as *mut Module)
                                 .unwrap(),
                             idx: 0,
                             extends: ptr::null(),
+                            impls: vec![],
                             attrib: ty.attrib.clone(),
                         }),
                     );
                 }
+                // syntax error already reported
+                ASTKind::Error => {}
                 _ => unreachable!(),
             }
         }
 
         // process uses
-        let mut use_map: HashMap<String, ItemPathBuf> = HashMap::new();
+        let mut use_map: HashMap<String, (ItemPathBuf, Span)> = HashMap::new();
         for use_ast in uses.iter() {
-            if let AST::Use(raw_path, as_id) = use_ast.as_ref() {
+            if let ASTKind::Use(raw_path, as_id) = &use_ast.kind {
                 let (path_has_crate, path_super_count, can_path) = raw_path.canonicalize();
 
                 let use_path = if path_has_crate {
@@ -224,18 +381,23 @@ pub fn new_module(mod_path: ItemPathBuf, mgr: &mut Crate, cfg: &XicCfg) {
                 };
 
                 if use_map.contains_key(&as_id) {
-                    panic!("Duplicated use as {}", as_id);
+                    diags.push(
+                        Diagnostic::error(
+                            ErrCode::Duplicated,
+                            format!("Duplicated use as {}", as_id),
+                        )
+                        .with_loc(&fpath, use_ast.span),
+                    );
                 } else {
-                    use_map.insert(as_id, use_path);
+                    use_map.insert(as_id, (use_path, use_ast.span));
                 }
-
-                todo!("TODO: Validate use path");
             } else {
                 unreachable!();
             }
         }
 
         let mod_build_ctx = ModuleBuildCtx {
+            fpath,
             class_asts: classes,
             use_map,
 
@@ -259,6 +421,17 @@ impl ModuleBuildCtx {
         unsafe { self.module.as_mut().unwrap() }
     }
 
+    /// Report an error in this module and terminate compilation
+    pub fn error<S: Into<String>>(&self, code: ErrCode, span: Span, msg: S) -> ! {
+        Diagnostic::error(code, msg)
+            .with_loc(&self.fpath, span)
+            .abort()
+    }
+
+    pub fn warning<S: Into<String>>(&self, span: Span, msg: S) {
+        Diagnostic::warning(msg).with_loc(&self.fpath, span).emit();
+    }
+
     pub fn dump(&self, cfg: &XicCfg) {
         let mut p = cfg.out_dir.clone();
         let mod_path = &self.get_module().mod_path;
@@ -285,10 +458,30 @@ impl ModuleBuildCtx {
 }
 
 impl ModuleBuildCtx {
+    /// Paths of uses must be modules. Modules in other files are loaded after this module is created,
+    /// so it is checked after all modules are loaded
+    pub fn check_uses(&self, c: &Crate, diags: &mut Vec<Diagnostic>) {
+        // report in the order of declaration
+        let mut uses: Vec<&(ItemPathBuf, Span)> = self.use_map.values().collect();
+        uses.sort_by_key(|(_, span)| span.start);
+        for (path, span) in uses.into_iter() {
+            if !c.mod_tbl.contains_key(path.as_str()) {
+                diags.push(
+                    Diagnostic::error(
+                        ErrCode::Unresolved,
+                        format!("Cannot find module {}", path.as_str()),
+                    )
+                    .with_loc(&self.fpath, *span),
+                );
+            }
+        }
+    }
+
     /// item must exist
     pub fn resolve_user_define_type(
         &self,
         path: &ItemPathBuf,
+        span: Span,
         c: &Crate,
         class: Option<&Type>,
     ) -> NonNull<Type> {
@@ -303,12 +496,20 @@ impl ModuleBuildCtx {
                 if let Some(class) = class {
                     NonNull::new(class as *const Type as *mut Type).unwrap()
                 } else {
-                    panic!("Invalid Self keyword outside a class");
+                    self.error(
+                        ErrCode::InvalidSelf,
+                        span,
+                        "Invalid Self keyword outside a class",
+                    );
                 }
             } else if let Some(ty) = module.classes.get(class_id) {
                 NonNull::new(ty.as_ref() as *const Type as *mut Type).unwrap()
             } else {
-                panic!("No class {} in mod {}", class_id, module.fullname());
+                self.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("No class {} in mod {}", class_id, module.fullname()),
+                );
             }
         } else {
             let m = if has_crate {
@@ -333,7 +534,7 @@ impl ModuleBuildCtx {
             } else {
                 let mut mod_path_iter = mod_path.iter();
                 let r = mod_path_iter.next().unwrap().0;
-                if let Some(m) = self.use_map.get(r) {
+                if let Some((m, _)) = self.use_map.get(r) {
                     let mut m = m.clone();
                     for (seg_id, generic_ps) in mod_path_iter {
                         m.push_id_with_generic(seg_id, generic_ps.clone());
@@ -346,46 +547,153 @@ impl ModuleBuildCtx {
                         m.push_id_with_generic(seg_id, generic_ps.clone());
                     }
                     m
+                } else if c.mod_tbl.contains_key(r) {
+                    // root module of another crate, e.g. std
+                    let mut m = ItemPathBuf::new();
+                    m.push(r);
+                    for (seg_id, generic_ps) in mod_path_iter {
+                        m.push_id_with_generic(seg_id, generic_ps.clone());
+                    }
+                    m
                 } else {
-                    panic!("Cannot resolve path {}", path);
+                    self.error(
+                        ErrCode::Unresolved,
+                        span,
+                        format!("Cannot resolve path {}", path),
+                    );
                 }
             };
 
             if let Some(m) = c.mod_tbl.get(m.as_str()) {
                 if let Some(ty) = m.classes.get(class_id) {
+                    if !ty.is_visible_from(module, c) {
+                        self.error(
+                            ErrCode::Inaccessible,
+                            span,
+                            format!("{} is not visible in mod {}", ty, module),
+                        );
+                    }
                     NonNull::new(ty.as_ref() as *const Type as *mut Type).unwrap()
                 } else {
-                    panic!("Class {} not found", class_id);
+                    self.error(
+                        ErrCode::Unresolved,
+                        span,
+                        format!("Class {} not found", class_id),
+                    );
                 }
             } else {
-                panic!("Module {} not found", m.as_str());
+                self.error(
+                    ErrCode::Unresolved,
+                    span,
+                    format!("Module {} not found", m.as_str()),
+                );
             }
         }
     }
 
-    pub fn get_rval_type(&self, ast: &ASTType, mod_mgr: &Crate, class: &Type) -> RValType {
+    /// span is the location of the AST node that contains this type
+    ///
+    /// method_generic_params are generic params of the method in which this type is used
+    pub fn get_rval_type(
+        &self,
+        ast: &ASTType,
+        span: Span,
+        mod_mgr: &Crate,
+        class: &Type,
+        method_generic_params: &[GenericParam],
+    ) -> RValType {
         match ast {
+            ASTType::U8 => RValType::U8,
+            ASTType::I8 => RValType::I8,
+            ASTType::U16 => RValType::U16,
+            ASTType::I16 => RValType::I16,
+            ASTType::U32 => RValType::U32,
             ASTType::I32 => RValType::I32,
+            ASTType::U64 => RValType::U64,
+            ASTType::I64 => RValType::I64,
+            ASTType::USize => RValType::USize,
+            ASTType::ISize => RValType::ISize,
+            ASTType::F32 => RValType::F32,
             ASTType::F64 => RValType::F64,
             ASTType::Bool => RValType::Bool,
             ASTType::None => RValType::Void,
             ASTType::Char => RValType::Char,
             ASTType::String => RValType::String,
-            ASTType::Tuple(_) => {
-                unimplemented!();
+            ASTType::Tuple(tys) => {
+                let tuple_ty = if let Some(tuple_ty) = get_tuple_type(mod_mgr, tys.len()) {
+                    tuple_ty
+                } else {
+                    self.error(
+                        ErrCode::TypeMismatch,
+                        span,
+                        format!(
+                            "Tuple should have {} to {} elements but found {}",
+                            TUPLE_MIN_ARITY,
+                            TUPLE_MAX_ARITY,
+                            tys.len()
+                        ),
+                    );
+                };
+                RValType::GenericInst(
+                    false,
+                    NonNull::new(tuple_ty as *const Type as *mut Type).unwrap(),
+                    tys.iter()
+                        .map(|ty| {
+                            self.get_rval_type(ty, span, mod_mgr, class, method_generic_params)
+                        })
+                        .collect(),
+                )
             }
             ASTType::UsrType(class_path) => {
-                let ty = self.resolve_user_define_type(class_path, mod_mgr, Some(class));
+                let (id, generic_args) = class_path.get_self().unwrap();
+                if class_path.len() == 1 && generic_args.is_none() {
+                    // generic params of method shadow generic params of class
+                    if let Some(i) = method_generic_params.iter().position(|p| p.name == id) {
+                        return RValType::MVar(i as u32);
+                    }
+                    if let Some(i) = class.generic_params.iter().position(|p| p.name == id) {
+                        return RValType::Var(i as u32);
+                    }
+                }
+
+                let ty = self.resolve_user_define_type(class_path, span, mod_mgr, Some(class));
                 let ty_ref = unsafe { ty.as_ref() };
-                if ty_ref.is_value_type() {
-                    RValType::Value(ty)
+                let generic_args: Vec<RValType> = if let Some(generic_args) = generic_args {
+                    generic_args
+                        .iter()
+                        .map(|arg| {
+                            self.get_rval_type(arg, span, mod_mgr, class, method_generic_params)
+                        })
+                        .collect()
+                } else if class_path.len() == 1 && id == "Self" {
+                    // Self in generic class is instantiated with its own generic params
+                    (0..ty_ref.generic_params.len())
+                        .map(|i| RValType::Var(i as u32))
+                        .collect()
                 } else {
-                    RValType::Class(ty)
+                    vec![]
+                };
+                if generic_args.len() != ty_ref.generic_params.len() {
+                    self.error(
+                        ErrCode::InvalidGeneric,
+                        span,
+                        format!(
+                            "{} expects {} generic args but {} are given",
+                            ty_ref,
+                            ty_ref.generic_params.len(),
+                            generic_args.len()
+                        ),
+                    );
                 }
+                RValType::from_class(ty, generic_args)
             }
-            ASTType::Arr(dtype) => {
-                RValType::Array(Box::new(self.get_rval_type(dtype, mod_mgr, class)))
-            }
+            ASTType::Arr(dtype) => RValType::Array(Box::new(self.get_rval_type(
+                dtype,
+                span,
+                mod_mgr,
+                class,
+                method_generic_params,
+            ))),
         }
     }
 }
@@ -0,0 +1,95 @@
+use xir::attrib::{FieldAttrib, FieldAttribFlag, TypeAttrib, TypeAttribFlag, TypeAttribVisFlag};
+
+use super::super::ast::{ASTClass, ASTGenericParamDecl, ASTKind, ASTType, AST};
+use super::super::util::{ItemPathBuf, Span};
+use super::{Crate, Type};
+
+// Tuple (T0, T1, ...) is lowered to value type std::TupleN<T0, T1, ...>,
+// which is generated by compiler when compiling std
+
+pub const TUPLE_MIN_ARITY: usize = 2;
+pub const TUPLE_MAX_ARITY: usize = 8;
+
+fn tuple_name(arity: usize) -> String {
+    format!("Tuple{}", arity)
+}
+
+/// t.0 accesses field .0
+pub fn tuple_field_name(i: usize) -> String {
+    format!(".{}", i)
+}
+
+/// Value type that tuples with arity elements are lowered to
+pub fn get_tuple_type(mod_mgr: &Crate, arity: usize) -> Option<&Type> {
+    if (TUPLE_MIN_ARITY..=TUPLE_MAX_ARITY).contains(&arity) {
+        mod_mgr
+            .mod_tbl
+            .get("std")
+            .unwrap()
+            .classes
+            .get(&tuple_name(arity))
+            .map(|ty| ty.as_ref())
+    } else {
+        None
+    }
+}
+
+/// Arity of ty if it is a generated tuple type
+pub fn tuple_arity(ty: &Type) -> Option<usize> {
+    if ty.modname() != "std" {
+        return None;
+    }
+    let arity = ty.name.strip_prefix("Tuple")?.parse::<usize>().ok()?;
+    if (TUPLE_MIN_ARITY..=TUPLE_MAX_ARITY).contains(&arity) && ty.generic_params.len() == arity {
+        Some(arity)
+    } else {
+        None
+    }
+}
+
+/// struct TupleN<T0, T1, ...> { let .0: T0; let .1: T1; ... }
+pub fn gen_tuple_asts(span: Span) -> Vec<Box<AST>> {
+    (TUPLE_MIN_ARITY..=TUPLE_MAX_ARITY)
+        .map(|arity| {
+            let mut attrib = TypeAttrib::new_class(TypeAttribVisFlag::Pub.into());
+            attrib.set(TypeAttribFlag::Sealed);
+            let generic_params: Vec<ASTGenericParamDecl> = (0..arity)
+                .map(|i| ASTGenericParamDecl {
+                    id: format!("T{}", i),
+                    constraints: vec![],
+                })
+                .collect();
+            let fields = generic_params
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let mut ty = ItemPathBuf::new();
+                    ty.push(&p.id);
+                    AST::new(
+                        span,
+                        ASTKind::Field(
+                            tuple_field_name(i),
+                            FieldAttrib::from(FieldAttribFlag::Pub.into()),
+                            vec![],
+                            Box::new(ASTType::UsrType(ty)),
+                        ),
+                    )
+                })
+                .collect();
+            AST::new(
+                span,
+                ASTKind::Struct(ASTClass {
+                    name: tuple_name(arity),
+                    attrib,
+                    custom_attribs: vec![],
+                    generic_params,
+                    extends_or_impls: vec![],
+                    fields,
+                    methods: vec![],
+                    cctor: AST::new(span, ASTKind::None),
+                    ctors: vec![],
+                }),
+            )
+        })
+        .collect()
+}
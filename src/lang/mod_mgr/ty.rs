@@ -2,25 +2,37 @@ use std::collections::HashMap;
 use std::fmt;
 use std::ptr::NonNull;
 
-use xir::attrib::{FieldAttribFlag, MethodAttribFlag, TypeAttrib};
+use xir::attrib::{
+    FieldAttribFlag, MethodAttribFlag, TypeAttrib, TypeAttribFlag, TypeAttribSemFlag,
+    TypeAttribVisFlag,
+};
 
-use super::{Field, Method, Module};
+use super::super::gen::ConstVal;
+use super::{Crate, Field, GenericParam, Method, Module, Variant};
 
 pub struct Type {
     pub parent: NonNull<Module>,
 
     pub name: String,
 
+    pub generic_params: Vec<GenericParam>,
+
     /// key: field_name
     pub fields: HashMap<String, Box<Field>>,
+    /// key: const_name
+    pub consts: HashMap<String, ConstVal>,
     /// Overload is currently not supported
     ///
     /// key: method_name
     pub methods: HashMap<String, Vec<Box<Method>>>,
+    /// in declaration order, empty if this type is not an enum
+    pub variants: Vec<Variant>,
 
     pub attrib: TypeAttrib,
 
     pub extends: *const Type,
+    /// interfaces implemented by this type, or base interfaces if this type is an interface
+    pub impls: Vec<*const Type>,
 
     /// index into typedef tbl
     pub idx: u32,
@@ -37,6 +49,47 @@ impl Type {
         unsafe { self.parent.as_ref().fullname() }
     }
 
+    pub fn module(&self) -> &Module {
+        unsafe { self.parent.as_ref() }
+    }
+
+    /// whether this type can be referred to in module from
+    pub fn is_visible_from(&self, from: &Module, c: &Crate) -> bool {
+        let module = self.module();
+        let visible = match self.attrib.vis() {
+            TypeAttribVisFlag::Pub => true,
+            TypeAttribVisFlag::Crate => module.crate_name() == from.crate_name(),
+            TypeAttribVisFlag::Priv => from.is_in(module),
+        };
+        visible && module.is_visible_from(from, c)
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.attrib.is_sem(TypeAttribSemFlag::Interface)
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.attrib.is(TypeAttribFlag::Enum)
+    }
+
+    pub fn query_variant(&self, name: &str) -> Option<&Variant> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+
+    /// whether this type, its base types or base interfaces implement interface
+    pub fn implements(&self, interface: *const Type) -> bool {
+        let mut c = self as *const Type;
+        while let Some(c_ref) = unsafe { c.as_ref() } {
+            for i in c_ref.impls.iter() {
+                if *i == interface || unsafe { i.as_ref().unwrap() }.implements(interface) {
+                    return true;
+                }
+            }
+            c = c_ref.extends;
+        }
+        false
+    }
+
     pub fn is_value_type(&self) -> bool {
         let mut base = self.extends;
         while let Some(b) = unsafe { base.as_ref() } {
@@ -48,16 +101,17 @@ impl Type {
         false
     }
 
+    /// Accessibility is not checked, see Method::is_accessible_in
     pub fn query_method(&self, name: &str) -> Vec<&Method> {
-        // TODO: check access flag
         let mut ret = Vec::new();
         let mut c = self;
         let mut is_self = true;
         loop {
             if let Some(ms) = c.methods.get(name) {
                 for m in ms.iter() {
-                    if (is_self || !m.attrib.is(MethodAttribFlag::Static))
-                        && !m.attrib.is(MethodAttribFlag::Priv)
+                    if is_self
+                        || (!m.attrib.is(MethodAttribFlag::Static)
+                            && !m.attrib.is(MethodAttribFlag::Priv))
                     {
                         // static method cannot be accessed from derived class
                         // priv cannot be accessed from derived class
@@ -73,11 +127,20 @@ impl Type {
                 break;
             }
         }
+        if ret.is_empty() && self.is_interface() {
+            // methods of base interfaces
+            for i in self.impls.iter() {
+                ret = unsafe { i.as_ref().unwrap() }.query_method(name);
+                if !ret.is_empty() {
+                    break;
+                }
+            }
+        }
         ret
     }
 
+    /// Accessibility is not checked, see Field::is_accessible_in
     pub fn query_field(&self, name: &str) -> Option<&Field> {
-        // TODO: check access flag
         let mut c = self;
         let mut is_self = true;
         loop {
@@ -29,6 +29,10 @@ pub struct Locals {
     pub locals: Vec<Var>,
     /// map from id to index of local
     pub sym_tbl: Vec<HashMap<String, usize>>,
+    /// named locals declared in each scope, in the order scopes are pushed. Used by debug info
+    pub scopes: Vec<Vec<u16>>,
+    /// index into scopes of each frame in sym_tbl
+    scope_stack: Vec<usize>,
 }
 
 impl Locals {
@@ -36,21 +40,30 @@ impl Locals {
         Locals {
             locals: Vec::new(),
             sym_tbl: Vec::new(),
+            scopes: Vec::new(),
+            scope_stack: Vec::new(),
         }
     }
 
-    pub fn push(&mut self) {
+    /// Return index of the new scope
+    pub fn push(&mut self) -> usize {
         self.sym_tbl.push(HashMap::new());
+        self.scopes.push(Vec::new());
+        self.scope_stack.push(self.scopes.len() - 1);
+        self.scopes.len() - 1
     }
 
-    pub fn pop(&mut self) {
+    /// Return index of the popped scope
+    pub fn pop(&mut self) -> usize {
         self.sym_tbl.pop().expect("Cannot pop empty stack");
+        self.scope_stack.pop().unwrap()
     }
 
     pub fn add(&mut self, id: &str, ty: RValType, flag: LocalAttrib, initialized: bool) -> u16 {
         let idx = self.locals.len();
         let var = Var::new(id, flag, ty, idx as u16, initialized);
         self.sym_tbl.last_mut().unwrap().insert(id.to_owned(), idx);
+        self.scopes[*self.scope_stack.last().unwrap()].push(idx as u16);
         self.locals.push(var);
         idx as u16
     }
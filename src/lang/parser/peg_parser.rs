@@ -1,12 +1,13 @@
 use super::super::ast::*;
-use super::super::util::ItemPathBuf;
+use super::super::diagnostic::{Diagnostic, ErrCode};
+use super::super::util::{IItemPath, ItemPathBuf, Span};
 
 use xir::attrib::*;
 
 use std::fs;
 use std::path::Path;
 
-use pest::error::Error;
+use pest::error::{Error, ErrorVariant, InputLocation};
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 
@@ -14,754 +15,1461 @@ use pest::Parser;
 #[grammar = "lang/parser/grammar.pest"]
 struct LRParser;
 
-pub fn parse(path: &Path) -> Result<Box<AST>, Error<Rule>> {
-    let code = fs::read_to_string(path).unwrap();
-
-    let file = LRParser::parse(Rule::File, &code)?.next().unwrap();
-
-    let mut uses: Vec<Box<AST>> = Vec::new();
-    let mut mods: Vec<String> = Vec::new();
-    let mut exts: Vec<String> = Vec::new();
-    let mut classes: Vec<Box<AST>> = Vec::new();
-    for sub in file.into_inner() {
-        match sub.as_rule() {
-            Rule::EOI => break,
-            Rule::Class => classes.push(build_custom_type(sub)),
-            Rule::Modules => mods.push(build_id(sub.into_inner().next().unwrap())),
-            Rule::ExternMod => exts.push(build_id(sub.into_inner().next().unwrap())),
-            Rule::UseDeclarations => {
-                let mut iter = sub.into_inner();
-                let path = build_pathexpr(iter.next().unwrap());
-                let as_clause = iter.next().unwrap();
-                let as_id = match as_clause.as_rule() {
-                    Rule::Id => Some(build_id(as_clause)),
-                    Rule::Semi => None,
-                    _ => unreachable!(),
-                };
+/// Syntax errors are recovered at Semi and "}", so that all of them are reported.
+/// The returned AST contains ASTKind::Error nodes if there are errors
+pub fn parse(path: &Path) -> (Box<AST>, Vec<Diagnostic>) {
+    let code = match fs::read_to_string(path) {
+        Ok(code) => code,
+        Err(e) => {
+            return (
+                empty_file(Span::default()),
+                vec![Diagnostic::error(
+                    ErrCode::InvalidModule,
+                    format!("Cannot read {}: {}", path.display(), e),
+                )],
+            )
+        }
+    };
 
-                uses.push(Box::new(AST::Use(path, as_id)));
-            }
-            _ => unreachable!(),
-        };
-    }
+    let file = match LRParser::parse(Rule::File, &code) {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(e) => {
+            // unrecoverable
+            return (
+                empty_file(Span::new(0, code.len())),
+                vec![syntax_error(path, 0, e)],
+            );
+        }
+    };
 
-    Ok(Box::new(AST::File(mods, exts, uses, classes)))
+    let mut builder = ASTBuilder {
+        path,
+        errs: Vec::new(),
+    };
+    let ast = builder.build_file(file);
+    (ast, builder.errs)
 }
 
-fn build_attributes(iter: &mut Pairs<Rule>) -> Vec<Box<AST>> {
-    let mut ret = Vec::new();
-    while let Rule::AttributeLst = iter.peek().unwrap().as_rule() {
-        for attr in iter.next().unwrap().into_inner() {
-            if let Rule::Attribute = attr.as_rule() {
-                let mut attr_iter = attr.into_inner();
-                let attr_id = build_id(attr_iter.next().unwrap());
-                let attr_args = attr_iter.map(|a| build_literal(a)).collect();
-                ret.push(Box::new(AST::CustomAttrib(attr_id, attr_args)));
-            } else {
-                unreachable!();
+fn empty_file(span: Span) -> Box<AST> {
+    AST::new(
+        span,
+        ASTKind::File(
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            vec![AST::new(span, ASTKind::Error)],
+        ),
+    )
+}
+
+/// offset: offset of the parsed text in the source file
+fn syntax_error(path: &Path, offset: usize, e: Error<Rule>) -> Diagnostic {
+    let span = match e.location {
+        InputLocation::Pos(pos) => Span::new(offset + pos, offset + pos),
+        InputLocation::Span((start, end)) => Span::new(offset + start, offset + end),
+    };
+    let msg = match e.variant {
+        ErrorVariant::ParsingError {
+            positives,
+            negatives,
+        } => {
+            let fmt_rules = |rules: Vec<Rule>| {
+                rules
+                    .iter()
+                    // Error is only for recovery
+                    .filter(|r| **r != Rule::Error)
+                    .map(|r| format!("{:?}", r))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            let positives = fmt_rules(positives);
+            let negatives = fmt_rules(negatives);
+            match (positives.is_empty(), negatives.is_empty()) {
+                (false, false) => format!("unexpected {}, expected {}", negatives, positives),
+                (false, true) => format!("expected {}", positives),
+                (true, false) => format!("unexpected {}", negatives),
+                (true, true) => String::from("unknown parsing error"),
             }
         }
-    }
-    ret
+        ErrorVariant::CustomError { message } => message,
+    };
+    Diagnostic::error(ErrCode::Syntax, msg).with_loc(path, span)
+}
+
+/// Build AST from pest parse tree, user errors are collected in errs
+struct ASTBuilder<'p> {
+    path: &'p Path,
+    errs: Vec<Diagnostic>,
 }
 
-fn build_generic_params_decl(tree: Pair<Rule>, decls: &mut Vec<ASTGenericParamDecl>) {
-    for decl in tree.into_inner() {
-        assert_eq!(Rule::GenericParamDecl, decl.as_rule());
-        let mut decl_iter = decl.into_inner();
-        let mut ast_decl = ASTGenericParamDecl {
-            id: build_id(decl_iter.next().unwrap()),
-            constraints: Vec::new(),
+impl<'p> ASTBuilder<'p> {
+    fn error<S: Into<String>>(&mut self, code: ErrCode, span: Span, msg: S) {
+        self.errs
+            .push(Diagnostic::error(code, msg).with_loc(self.path, span));
+    }
+
+    /// tree: Error, expected: the rule that failed to match there
+    fn build_error(&mut self, tree: Pair<Rule>, expected: Rule) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        // re-parse the skipped text to find out where exactly it goes wrong
+        let err = match LRParser::parse(expected, tree.as_str()) {
+            Ok(_) => Diagnostic::error(ErrCode::Syntax, format!("expected {:?}", expected))
+                .with_loc(self.path, span),
+            Err(e) => syntax_error(self.path, span.start, e),
         };
-        for constraint in decl_iter {
-            ast_decl.constraints.push(build_pathexpr(constraint));
-        }
-        decls.push(ast_decl);
+        self.errs.push(err);
+        AST::new(span, ASTKind::Error)
     }
-}
 
-fn build_custom_type(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let custom_attribs = build_attributes(&mut iter);
-    let sem = iter.next().unwrap().as_rule();
-    let name = build_id(iter.next().unwrap());
-    let mut extends_or_impls: Vec<ItemPathBuf> = Vec::new();
+    fn build_file(&mut self, file: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(file.as_span());
+        let mut uses: Vec<Box<AST>> = Vec::new();
+        let mut mods: Vec<(String, TypeAttribVisFlag, Vec<Box<AST>>, Span)> = Vec::new();
+        let mut exts: Vec<String> = Vec::new();
+        let mut classes: Vec<Box<AST>> = Vec::new();
+        for sub in file.into_inner() {
+            match sub.as_rule() {
+                Rule::EOI => break,
+                Rule::Class => classes.push(self.build_custom_type(sub)),
+                Rule::Enum => classes.push(self.build_enum(sub)),
+                Rule::Error => classes.push(self.build_error(sub, Rule::Item)),
+                Rule::UnmatchedRBrace => {
+                    let span = Span::from(sub.as_span());
+                    self.error(ErrCode::Syntax, span, "unmatched \"}\"");
+                    classes.push(AST::new(span, ASTKind::Error));
+                }
+                Rule::Modules => {
+                    let mut iter = sub.into_inner();
+                    let attribs = self.build_attributes(&mut iter);
+                    let vis = build_vis(&mut iter);
+                    let id = iter.next().unwrap();
+                    let id_span = Span::from(id.as_span());
+                    mods.push((build_id(id), vis, attribs, id_span));
+                }
+                Rule::ExternMod => exts.push(build_id(sub.into_inner().next().unwrap())),
+                Rule::UseDeclarations => {
+                    let use_span = Span::from(sub.as_span());
+                    let mut iter = sub.into_inner();
+                    let path = self.build_pathexpr(iter.next().unwrap());
+                    let as_clause = iter.next().unwrap();
+                    let as_id = match as_clause.as_rule() {
+                        Rule::Id => Some(build_id(as_clause)),
+                        Rule::Semi => None,
+                        _ => unreachable!(),
+                    };
 
-    let mut generic_params = Vec::new();
-    if let Some(try_generic) = iter.peek() {
-        if let Rule::GenericParamsDecl = try_generic.as_rule() {
-            build_generic_params_decl(iter.next().unwrap(), &mut generic_params);
+                    uses.push(AST::new(use_span, ASTKind::Use(path, as_id)));
+                }
+                _ => unreachable!(),
+            };
         }
+
+        AST::new(span, ASTKind::File(mods, exts, uses, classes))
     }
 
-    if let Some(try_extends_or_impls) = iter.peek() {
-        if let Rule::ExtendsOrImpls = try_extends_or_impls.as_rule() {
-            let classes = iter.next().unwrap().into_inner();
-            for class in classes {
-                extends_or_impls.push(build_pathexpr(class));
+    fn build_attributes(&mut self, iter: &mut Pairs<Rule>) -> Vec<Box<AST>> {
+        let mut ret = Vec::new();
+        while let Rule::AttributeLst = iter.peek().unwrap().as_rule() {
+            for attr in iter.next().unwrap().into_inner() {
+                if let Rule::Attribute = attr.as_rule() {
+                    let span = Span::from(attr.as_span());
+                    let mut attr_iter = attr.into_inner();
+                    let first = attr_iter.next().unwrap();
+                    if let Rule::CfgAttribute = first.as_rule() {
+                        let pred = self.build_cfg_pred(first.into_inner().next().unwrap());
+                        ret.push(AST::new(span, ASTKind::Cfg(pred)));
+                        continue;
+                    }
+                    let attr_id = build_id(first);
+                    let attr_args = attr_iter.map(|a| self.build_literal(a, false)).collect();
+                    ret.push(AST::new(span, ASTKind::CustomAttrib(attr_id, attr_args)));
+                } else {
+                    unreachable!();
+                }
             }
         }
+        ret
     }
 
-    let mut fields = Vec::new();
-    let mut methods = Vec::new();
-    let mut cctor: Option<Box<AST>> = None;
-    let mut ctors = Vec::new();
-    while let Some(_) = iter.peek() {
-        let item_attrib = build_attributes(&mut iter);
-        let class_item = iter.next().unwrap();
-        match class_item.as_rule() {
-            Rule::CCtor => {
-                if let Some(_) = cctor {
-                    panic!("Duplicated static init found in class {}", name);
+    fn build_cfg_pred(&mut self, tree: Pair<Rule>) -> ASTCfgPred {
+        match tree.as_rule() {
+            Rule::CfgAll => {
+                ASTCfgPred::All(tree.into_inner().map(|p| self.build_cfg_pred(p)).collect())
+            }
+            Rule::CfgAny => {
+                ASTCfgPred::Any(tree.into_inner().map(|p| self.build_cfg_pred(p)).collect())
+            }
+            Rule::CfgNot => ASTCfgPred::Not(Box::new(
+                self.build_cfg_pred(tree.into_inner().next().unwrap()),
+            )),
+            Rule::CfgOption => {
+                let mut iter = tree.into_inner();
+                let name = build_id(iter.next().unwrap());
+                if let Some(val) = iter.next() {
+                    let val = self.build_literal(val, false);
+                    if let ASTKind::String(val) = val.kind {
+                        ASTCfgPred::KeyValue(name, val)
+                    } else {
+                        self.error(
+                            ErrCode::Syntax,
+                            val.span,
+                            format!("Value of cfg option {} should be a string literal", name),
+                        );
+                        ASTCfgPred::KeyValue(name, String::new())
+                    }
                 } else {
-                    cctor = Some(build_block(class_item.into_inner().next().unwrap()));
+                    ASTCfgPred::Flag(name)
                 }
             }
-            Rule::Ctor => ctors.push(build_ctor(class_item, item_attrib)),
-            Rule::StaticField => fields.push(build_field(class_item, true, item_attrib)),
-            Rule::NonStaticField => fields.push(build_field(class_item, false, item_attrib)),
-            Rule::Method => methods.push(build_method(class_item, item_attrib)),
             _ => unreachable!(),
         }
     }
 
-    let mut ret = ASTClass {
-        name,
-        attrib: TypeAttrib::new_class(TypeAttribVisFlag::Pub.into()),
-        custom_attribs,
-        extends_or_impls,
-        generic_params,
-        methods,
-        fields,
-        cctor: if let Some(v) = cctor {
-            v
-        } else {
-            Box::new(AST::None)
-        },
-        ctors,
-    };
-    Box::new(match sem {
-        Rule::KwClass => AST::Class(ret),
-        Rule::KwStruct => {
-            // struct is default to be sealed
-            ret.attrib.set(TypeAttribFlag::Sealed);
-            AST::Struct(ret)
-        }
-        Rule::KwInterface => {
-            unimplemented!()
-        }
-        _ => unreachable!(),
-    })
-}
-
-fn build_field(tree: Pair<Rule>, is_static: bool, attr: Vec<Box<AST>>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let id = build_id(iter.next().unwrap());
-    let mut flag = FieldAttrib::from(FieldAttribFlag::Pub.into());
-    if is_static {
-        flag.set(FieldAttribFlag::Static);
+    fn build_generic_params_decl(
+        &mut self,
+        tree: Pair<Rule>,
+        decls: &mut Vec<ASTGenericParamDecl>,
+    ) {
+        for decl in tree.into_inner() {
+            assert_eq!(Rule::GenericParamDecl, decl.as_rule());
+            let mut decl_iter = decl.into_inner();
+            let mut ast_decl = ASTGenericParamDecl {
+                id: build_id(decl_iter.next().unwrap()),
+                constraints: Vec::new(),
+            };
+            for constraint in decl_iter {
+                ast_decl.constraints.push(self.build_pathexpr(constraint));
+            }
+            decls.push(ast_decl);
+        }
     }
 
-    Box::new(AST::Field(id, flag, attr, build_type(iter.next().unwrap())))
-}
+    fn build_custom_type(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let custom_attribs = self.build_attributes(&mut iter);
+        let vis = build_vis(&mut iter);
+        let sem = iter.next().unwrap().as_rule();
+        let name = build_id(iter.next().unwrap());
+        let mut extends_or_impls: Vec<ItemPathBuf> = Vec::new();
+
+        let mut generic_params = Vec::new();
+        if let Some(try_generic) = iter.peek() {
+            if let Rule::GenericParamsDecl = try_generic.as_rule() {
+                self.build_generic_params_decl(iter.next().unwrap(), &mut generic_params);
+            }
+        }
+
+        if let Some(try_extends_or_impls) = iter.peek() {
+            if let Rule::ExtendsOrImpls = try_extends_or_impls.as_rule() {
+                let classes = iter.next().unwrap().into_inner();
+                for class in classes {
+                    extends_or_impls.push(self.build_pathexpr(class));
+                }
+            }
+        }
+
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        let mut cctor: Option<Box<AST>> = None;
+        let mut ctors = Vec::new();
+        while let Some(_) = iter.peek() {
+            let item_attrib = self.build_attributes(&mut iter);
+            let class_item = iter.next().unwrap();
+            match class_item.as_rule() {
+                Rule::CCtor => {
+                    if let Some(attr) = item_attrib.first() {
+                        self.error(
+                            ErrCode::Syntax,
+                            attr.span,
+                            format!("Static init of class {} cannot have attributes", name),
+                        );
+                    }
+                    if let Some(_) = cctor {
+                        self.error(
+                            ErrCode::Duplicated,
+                            Span::from(class_item.as_span()),
+                            format!("Duplicated static init found in class {}", name),
+                        );
+                    } else {
+                        cctor = Some(self.build_block(class_item.into_inner().next().unwrap()));
+                    }
+                }
+                Rule::Ctor => ctors.push(self.build_ctor(class_item, item_attrib)),
+                Rule::Const => fields.push(self.build_const(class_item, item_attrib)),
+                Rule::StaticField => fields.push(self.build_field(class_item, true, item_attrib)),
+                Rule::NonStaticField => {
+                    fields.push(self.build_field(class_item, false, item_attrib))
+                }
+                Rule::Method => methods.push(self.build_method(class_item, item_attrib)),
+                Rule::Error => methods.push(self.build_error(class_item, Rule::ClassItem)),
+                _ => unreachable!(),
+            }
+        }
+
+        let mut ret = ASTClass {
+            name,
+            attrib: TypeAttrib::new_class(vis.into()),
+            custom_attribs,
+            extends_or_impls,
+            generic_params,
+            methods,
+            fields,
+            cctor: if let Some(v) = cctor {
+                v
+            } else {
+                AST::new(span, ASTKind::None)
+            },
+            ctors,
+        };
+        AST::new(
+            span,
+            match sem {
+                Rule::KwClass => ASTKind::Class(ret),
+                Rule::KwStruct => {
+                    // struct is default to be sealed
+                    ret.attrib.set(TypeAttribFlag::Sealed);
+                    ASTKind::Struct(ret)
+                }
+                Rule::KwInterface => {
+                    // interface is default to be abstract
+                    ret.attrib.set_sem(TypeAttribSemFlag::Interface);
+                    ret.attrib.set(TypeAttribFlag::Abstract);
+                    ASTKind::Interface(ret)
+                }
+                _ => unreachable!(),
+            },
+        )
+    }
 
-fn build_ctor(tree: Pair<Rule>, custom_attribs: Vec<Box<AST>>) -> Box<AST> {
-    let mut iter = tree.into_inner();
+    fn build_enum(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let custom_attribs = self.build_attributes(&mut iter);
+        let vis = build_vis(&mut iter);
+        let name = build_id(iter.next().unwrap());
+        let variants = iter.map(|v| self.build_variant(v)).collect();
+
+        // enum is a sealed value type
+        let mut attrib = TypeAttrib::new_class(vis.into());
+        attrib.set(TypeAttribFlag::Sealed);
+        attrib.set(TypeAttribFlag::Enum);
+        AST::new(
+            span,
+            ASTKind::Enum(ASTClass {
+                name,
+                attrib,
+                custom_attribs,
+                generic_params: Vec::new(),
+                extends_or_impls: Vec::new(),
+                fields: variants,
+                methods: Vec::new(),
+                cctor: AST::new(span, ASTKind::None),
+                ctors: Vec::new(),
+            }),
+        )
+    }
 
-    let mut generic_params = Vec::new();
-    if let Some(try_generic) = iter.peek() {
-        if let Rule::GenericParamsDecl = try_generic.as_rule() {
-            build_generic_params_decl(iter.next().unwrap(), &mut generic_params);
+    fn build_variant(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let id = build_id(iter.next().unwrap());
+        let mut tys = Vec::new();
+        let mut discriminant = AST::new(Span::new(span.end, span.end), ASTKind::None);
+        while let Some(sub) = iter.next() {
+            match sub.as_rule() {
+                Rule::Type => tys.push(self.build_type(sub)),
+                Rule::Eq => discriminant = self.build_expr(iter.next().unwrap()),
+                _ => unreachable!(),
+            }
         }
+        AST::new(span, ASTKind::Variant(id, tys, discriminant))
     }
 
-    let attrib = MethodAttrib::from(
-        u16::from(MethodAttribFlag::Pub)
-            | u16::from(MethodAttribFlag::SpecialName)
-            | u16::from(MethodAttribFlag::RTSpecialName),
-    );
-    let ps = if let Rule::Params = iter.peek().unwrap().as_rule() {
-        // Build parameters
-        let (ps, has_self) = build_params(iter.next().unwrap());
-        if !has_self {
-            panic!("ctor must have \"self\" as its first param");
+    fn build_field(&mut self, tree: Pair<Rule>, is_static: bool, attr: Vec<Box<AST>>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let mut flag = FieldAttrib::from(0);
+        flag.set(match build_vis(&mut iter) {
+            TypeAttribVisFlag::Pub => FieldAttribFlag::Pub,
+            TypeAttribVisFlag::Crate => FieldAttribFlag::Crate,
+            TypeAttribVisFlag::Priv => FieldAttribFlag::Priv,
+        });
+        let id = build_id(iter.next().unwrap());
+        if is_static {
+            flag.set(FieldAttribFlag::Static);
         }
-        ps
-    } else {
-        // no param
-        Vec::new()
-    };
 
-    let base_args = if let Rule::KwBase = iter.peek().unwrap().as_rule() {
-        iter.next(); // skip base
-        Some(
-            iter.next()
-                .unwrap()
-                .into_inner()
-                .map(|a| build_expr(a))
-                .collect(),
+        AST::new(
+            span,
+            ASTKind::Field(id, flag, attr, self.build_type(iter.next().unwrap())),
         )
-    } else {
-        None
-    };
+    }
 
-    let body = iter.next().unwrap();
-    let body = match body.as_rule() {
-        Rule::BlockExpr => build_block(body),
-        Rule::Semi => Box::new(AST::None),
-        _ => unreachable!(),
-    };
+    fn build_const(&mut self, tree: Pair<Rule>, attr: Vec<Box<AST>>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let id = build_id(iter.next().unwrap());
+        let ty = self.build_type(iter.next().unwrap());
+        let val = self.build_expr(iter.next().unwrap());
+        AST::new(span, ASTKind::Const(id, attr, ty, val))
+    }
 
-    Box::new(AST::Ctor(ASTCtor {
-        attrib,
-        custom_attribs,
-        generic_params,
-        base_args,
-        ps,
-        body,
-    }))
-}
+    fn build_ctor(&mut self, tree: Pair<Rule>, custom_attribs: Vec<Box<AST>>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let mut attrib = MethodAttrib::from(
+            u16::from(MethodAttribFlag::SpecialName) | u16::from(MethodAttribFlag::RTSpecialName),
+        );
+        attrib.set(method_vis(build_vis(&mut iter)));
+
+        let mut generic_params = Vec::new();
+        if let Some(try_generic) = iter.peek() {
+            if let Rule::GenericParamsDecl = try_generic.as_rule() {
+                self.build_generic_params_decl(iter.next().unwrap(), &mut generic_params);
+            }
+        }
 
-fn build_method(tree: Pair<Rule>, custom_attribs: Vec<Box<AST>>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-
-    // built-in attributes
-    let mut attrib = MethodAttrib::from(MethodAttribFlag::Pub.into());
-    let mut ast_attrib = ASTMethodAttrib::default();
-    loop {
-        match iter.peek().unwrap().as_rule() {
-            Rule::KwOverride => {
-                iter.next();
-                if ast_attrib.is(ASTMethodAttribFlag::Override) {
-                    panic!("Duplicated override modifier");
-                } else {
-                    ast_attrib.set(ASTMethodAttribFlag::Override);
-                }
+        let ps = if let Rule::Params = iter.peek().unwrap().as_rule() {
+            // Build parameters
+            let params = iter.next().unwrap();
+            let params_span = Span::from(params.as_span());
+            let (ps, has_self) = self.build_params(params);
+            if !has_self {
+                self.error(
+                    ErrCode::InvalidSelf,
+                    params_span,
+                    "ctor must have \"self\" as its first param",
+                );
             }
-            Rule::KwVirtual => {
-                iter.next();
-                if attrib.is(MethodAttribFlag::Virtual) {
-                    panic!("Duplicated virtual modifier");
-                } else {
-                    attrib.set(MethodAttribFlag::Virtual);
+            ps
+        } else {
+            // no param
+            Vec::new()
+        };
+
+        let base_args = if let Rule::KwBase = iter.peek().unwrap().as_rule() {
+            iter.next(); // skip base
+            Some(
+                iter.next()
+                    .unwrap()
+                    .into_inner()
+                    .map(|a| self.build_expr(a))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        let body = iter.next().unwrap();
+        let body = match body.as_rule() {
+            Rule::BlockExpr => self.build_block(body),
+            Rule::Semi => AST::new(Span::from(body.as_span()), ASTKind::None),
+            _ => unreachable!(),
+        };
+
+        AST::new(
+            span,
+            ASTKind::Ctor(ASTCtor {
+                attrib,
+                custom_attribs,
+                generic_params,
+                base_args,
+                ps,
+                body,
+            }),
+        )
+    }
+
+    fn build_method(&mut self, tree: Pair<Rule>, custom_attribs: Vec<Box<AST>>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+
+        // built-in attributes
+        let mut attrib = MethodAttrib::from(0);
+        attrib.set(method_vis(build_vis(&mut iter)));
+        let mut ast_attrib = ASTMethodAttrib::default();
+        loop {
+            let modifier = iter.peek().unwrap();
+            let modifier_span = Span::from(modifier.as_span());
+            match modifier.as_rule() {
+                Rule::KwOverride => {
+                    iter.next();
+                    if ast_attrib.is(ASTMethodAttribFlag::Override) {
+                        self.error(
+                            ErrCode::InvalidModifier,
+                            modifier_span,
+                            "Duplicated override modifier",
+                        );
+                    } else {
+                        ast_attrib.set(ASTMethodAttribFlag::Override);
+                    }
                 }
+                Rule::KwVirtual => {
+                    iter.next();
+                    if attrib.is(MethodAttribFlag::Virtual) {
+                        self.error(
+                            ErrCode::InvalidModifier,
+                            modifier_span,
+                            "Duplicated virtual modifier",
+                        );
+                    } else {
+                        attrib.set(MethodAttribFlag::Virtual);
+                    }
+                }
+                _ => break,
             }
-            _ => break,
         }
-    }
 
-    let name = build_id(iter.next().unwrap());
+        let name = build_id(iter.next().unwrap());
 
-    let mut generic_params = Vec::new();
-    if let Some(try_generic) = iter.peek() {
-        if let Rule::GenericParamsDecl = try_generic.as_rule() {
-            build_generic_params_decl(iter.next().unwrap(), &mut generic_params);
+        let mut generic_params = Vec::new();
+        if let Some(try_generic) = iter.peek() {
+            if let Rule::GenericParamsDecl = try_generic.as_rule() {
+                self.build_generic_params_decl(iter.next().unwrap(), &mut generic_params);
+            }
         }
-    }
 
-    let (ps, has_self) = build_params(iter.next().unwrap());
-    if !has_self {
-        attrib.set(MethodAttribFlag::Static);
-    }
+        let (ps, has_self) = self.build_params(iter.next().unwrap());
+        if !has_self {
+            attrib.set(MethodAttribFlag::Static);
+        }
 
-    let ty = if let Rule::Type = iter.peek().unwrap().as_rule() {
-        build_type(iter.next().unwrap())
-    } else {
-        Box::new(ASTType::None)
-    };
+        let ty = if let Rule::Type = iter.peek().unwrap().as_rule() {
+            self.build_type(iter.next().unwrap())
+        } else {
+            Box::new(ASTType::None)
+        };
 
-    let body = iter.next().unwrap();
-    let body = match body.as_rule() {
-        Rule::BlockExpr => build_block(body),
-        Rule::Semi => Box::new(AST::None),
-        _ => unreachable!(),
-    };
+        let body = iter.next().unwrap();
+        let body = match body.as_rule() {
+            Rule::BlockExpr => self.build_block(body),
+            Rule::Semi => AST::new(Span::from(body.as_span()), ASTKind::None),
+            _ => unreachable!(),
+        };
 
-    Box::new(AST::Method(ASTMethod {
-        name,
-        attrib,
-        ast_attrib,
-        custom_attribs,
-        generic_params,
-        ret: ty,
-        ps,
-        body,
-    }))
-}
+        AST::new(
+            span,
+            ASTKind::Method(ASTMethod {
+                name,
+                attrib,
+                ast_attrib,
+                custom_attribs,
+                generic_params,
+                ret: ty,
+                ps,
+                body,
+            }),
+        )
+    }
 
-// Build parameters
-fn build_params(tree: Pair<Rule>) -> (Vec<Box<AST>>, bool) {
-    let mut ps = Vec::new();
-    let mut has_self = false;
-    let mut p_iter = tree.into_inner();
-    if let Some(p0) = p_iter.next() {
-        match p0.as_rule() {
-            Rule::KwLSelf => {
-                // non-static method
-                has_self = true;
-            }
-            Rule::Id => {
-                // static method
-                ps.push(Box::new(AST::Param(
-                    build_id(p0),
-                    ParamAttrib::from(0),
-                    build_type(p_iter.next().unwrap()),
-                )));
+    // Build parameters
+    fn build_params(&mut self, tree: Pair<Rule>) -> (Vec<Box<AST>>, bool) {
+        let mut ps = Vec::new();
+        let mut has_self = false;
+        let mut p_iter = tree.into_inner();
+        if let Some(p0) = p_iter.next() {
+            match p0.as_rule() {
+                Rule::KwLSelf => {
+                    // non-static method
+                    has_self = true;
+                }
+                Rule::Id => {
+                    // static method
+                    let ty = p_iter.next().unwrap();
+                    let span = Span::from(p0.as_span()).merge(&Span::from(ty.as_span()));
+                    ps.push(AST::new(
+                        span,
+                        ASTKind::Param(build_id(p0), ParamAttrib::from(0), self.build_type(ty)),
+                    ));
+                }
+                _ => unreachable!(),
             }
-            _ => unreachable!(),
+        } else {
+            // no param
+            return (vec![], false);
         }
-    } else {
-        // no param
-        return (vec![], false);
+
+        loop {
+            if let Some(p_id) = p_iter.next() {
+                let ty = p_iter.next().unwrap();
+                let span = Span::from(p_id.as_span()).merge(&Span::from(ty.as_span()));
+                ps.push(AST::new(
+                    span,
+                    ASTKind::Param(build_id(p_id), ParamAttrib::from(0), self.build_type(ty)),
+                ));
+            } else {
+                break;
+            }
+        }
+        (ps, has_self)
     }
 
-    loop {
-        if let Some(p_id) = p_iter.next() {
-            ps.push(Box::new(AST::Param(
-                build_id(p_id),
-                ParamAttrib::from(0),
-                build_type(p_iter.next().unwrap()),
-            )));
-        } else {
-            break;
+    fn build_pathexpr(&mut self, tree: Pair<Rule>) -> ItemPathBuf {
+        let mut ret = ItemPathBuf::new();
+        for seg in tree.into_inner() {
+            match seg.as_rule() {
+                Rule::IdWithGenericParams => {
+                    let ast_generic_ps = self.build_id_with_generic_params(seg);
+                    ret.push_id_with_generic(
+                        &ast_generic_ps.id,
+                        if ast_generic_ps.generic_params.is_empty() {
+                            None
+                        } else {
+                            Some(ast_generic_ps.generic_params)
+                        },
+                    );
+                }
+                Rule::KwCrate => ret.push("crate"),
+                Rule::KwSuper => ret.push("super"),
+                _ => unreachable!(),
+            };
         }
+        ret
     }
-    (ps, has_self)
-}
 
-fn build_pathexpr(tree: Pair<Rule>) -> ItemPathBuf {
-    let mut ret = ItemPathBuf::new();
-    for seg in tree.into_inner() {
-        match seg.as_rule() {
-            Rule::IdWithGenericParams => {
-                let ast_generic_ps = build_id_with_generic_params(seg);
-                ret.push_id_with_generic(
-                    &ast_generic_ps.id,
-                    if ast_generic_ps.generic_params.is_empty() {
-                        None
-                    } else {
-                        Some(ast_generic_ps.generic_params)
-                    },
-                );
+    fn build_non_arr_type(&mut self, tree: Pair<Rule>) -> Box<ASTType> {
+        Box::new(match tree.as_rule() {
+            Rule::KwBool => ASTType::Bool,
+            Rule::KwChar => ASTType::Char,
+            Rule::KwU8 => ASTType::U8,
+            Rule::KwI8 => ASTType::I8,
+            Rule::KwU16 => ASTType::U16,
+            Rule::KwI16 => ASTType::I16,
+            Rule::KwU32 => ASTType::U32,
+            Rule::KwI32 => ASTType::I32,
+            Rule::KwU64 => ASTType::U64,
+            Rule::KwI64 => ASTType::I64,
+            Rule::KwUSize => ASTType::USize,
+            Rule::KwISize => ASTType::ISize,
+            Rule::KwF32 => ASTType::F32,
+            Rule::KwF64 => ASTType::F64,
+            Rule::KwString => ASTType::String,
+            Rule::KwUSelf => ASTType::UsrType({
+                let mut path = ItemPathBuf::new();
+                path.push("Self");
+                path
+            }),
+            Rule::PathExpr => ASTType::UsrType(self.build_pathexpr(tree)),
+            Rule::TupleType => {
+                ASTType::Tuple(tree.into_inner().map(|ty| self.build_type(ty)).collect())
             }
-            Rule::KwCrate => ret.push("crate"),
-            Rule::KwSuper => ret.push("super"),
-            _ => unreachable!(),
-        };
+            _ => unreachable!(format!("Found {:?}", tree.as_rule())),
+        })
     }
-    ret
-}
 
-fn build_non_arr_type(tree: Pair<Rule>) -> Box<ASTType> {
-    Box::new(match tree.as_rule() {
-        Rule::KwBool => ASTType::Bool,
-        Rule::KwChar => ASTType::Char,
-        Rule::KwI32 => ASTType::I32,
-        Rule::KwF64 => ASTType::F64,
-        Rule::KwString => ASTType::String,
-        Rule::KwUSelf => ASTType::UsrType({
-            let mut path = ItemPathBuf::new();
-            path.push("Self");
-            path
-        }),
-        Rule::PathExpr => ASTType::UsrType(build_pathexpr(tree)),
-        Rule::TupleType => ASTType::Tuple(tree.into_inner().map(|ty| build_type(ty)).collect()),
-        _ => unreachable!(format!("Found {:?}", tree.as_rule())),
-    })
-}
+    /// tree: Type
+    fn build_type(&mut self, tree: Pair<Rule>) -> Box<ASTType> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_non_arr_type(iter.next().unwrap());
 
-/// tree: Type
-fn build_type(tree: Pair<Rule>) -> Box<ASTType> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_non_arr_type(iter.next().unwrap());
+        while let Some(_) = iter.next() {
+            iter.next().unwrap(); // RBracket
+            ret = Box::new(ASTType::Arr(ret));
+        }
+        ret
+    }
 
-    while let Some(_) = iter.next() {
-        iter.next().unwrap(); // RBracket
-        ret = Box::new(ASTType::Arr(ret));
+    fn build_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        match tree.as_rule() {
+            Rule::BlockExpr => self.build_block(tree),
+            Rule::LoopExpr => AST::new(
+                span,
+                ASTKind::Loop(self.build_block(tree.into_inner().next().unwrap())),
+            ),
+            Rule::WhileExpr => {
+                let mut iter = tree.into_inner();
+                let cond = self.build_expr(iter.next().unwrap());
+                let body = self.build_block(iter.next().unwrap());
+                AST::new(span, ASTKind::While(cond, body))
+            }
+            Rule::ForExpr => self.build_for(tree),
+            Rule::IfExpr => self.build_if(tree),
+            Rule::TryExpr => self.build_try(tree),
+            Rule::MatchExpr => self.build_match(tree),
+            Rule::ContinueExpr => AST::new(span, ASTKind::Continue),
+            Rule::BreakExpr => AST::new(
+                span,
+                ASTKind::Break(if let Some(ret_v) = tree.into_inner().next() {
+                    self.build_expr(ret_v)
+                } else {
+                    AST::new(span, ASTKind::None)
+                }),
+            ),
+            Rule::ReturnExpr => AST::new(
+                span,
+                ASTKind::Return(if let Some(ret_v) = tree.into_inner().next() {
+                    self.build_expr(ret_v)
+                } else {
+                    AST::new(span, ASTKind::None)
+                }),
+            ),
+            Rule::ThrowExpr => AST::new(
+                span,
+                ASTKind::Throw(self.build_expr(tree.into_inner().next().unwrap())),
+            ),
+            Rule::AssignExpr => self.build_assign(tree),
+            Rule::OpExpr => {
+                let tree = tree.into_inner().next().unwrap();
+                assert_eq!(tree.as_rule(), Rule::LogOrExpr);
+                self.build_log_or_expr(tree)
+            }
+            _ => unreachable!(format!("Found {:?}", tree.as_rule())),
+        }
     }
-    ret
-}
 
-fn build_expr(tree: Pair<Rule>) -> Box<AST> {
-    match tree.as_rule() {
-        Rule::BlockExpr => build_block(tree),
-        Rule::LoopExpr => Box::new(AST::Loop(build_block(tree.into_inner().next().unwrap()))),
-        Rule::IfExpr => build_if(tree),
-        Rule::ContinueExpr => Box::new(AST::Continue),
-        Rule::BreakExpr => Box::new(AST::Break(if let Some(ret_v) = tree.into_inner().next() {
-            build_expr(ret_v)
-        } else {
-            Box::new(AST::None)
-        })),
-        Rule::ReturnExpr => Box::new(AST::Return(if let Some(ret_v) = tree.into_inner().next() {
-            build_expr(ret_v)
+    fn build_if(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let cond = self.build_expr(iter.next().unwrap());
+        let then = self.build_block(iter.next().unwrap());
+        let els = if let Some(els) = iter.next() {
+            match els.as_rule() {
+                Rule::IfExpr => self.build_if(els),
+                Rule::BlockExpr => self.build_block(els),
+                _ => unreachable!(),
+            }
         } else {
-            Box::new(AST::None)
-        })),
-        Rule::AssignExpr => build_assign(tree),
-        Rule::OpExpr => {
-            let tree = tree.into_inner().next().unwrap();
-            assert_eq!(tree.as_rule(), Rule::LogOrExpr);
-            build_log_or_expr(tree)
+            AST::new(Span::new(span.end, span.end), ASTKind::None)
+        };
+        AST::new(span, ASTKind::If(cond, then, els))
+    }
+
+    fn build_for(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let id = build_id(iter.next().unwrap());
+        let start = self.build_expr(iter.next().unwrap());
+        let next = iter.next().unwrap();
+        let (it, body) = match next.as_rule() {
+            Rule::BlockExpr => (start, next),
+            _ => {
+                // start..end
+                let end = self.build_expr(next);
+                (
+                    AST::new(start.span.merge(&end.span), ASTKind::Range(start, end)),
+                    iter.next().unwrap(),
+                )
+            }
+        };
+        AST::new(span, ASTKind::For(id, it, self.build_block(body)))
+    }
+
+    fn build_try(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let body = self.build_block(iter.next().unwrap());
+        let mut catches = vec![];
+        let mut finally = AST::new(Span::new(span.end, span.end), ASTKind::None);
+        for clause in iter {
+            let clause_span = Span::from(clause.as_span());
+            match clause.as_rule() {
+                Rule::CatchClause => {
+                    let mut iter = clause.into_inner();
+                    let id = build_id(iter.next().unwrap());
+                    let ty = self.build_type(iter.next().unwrap());
+                    let body = self.build_block(iter.next().unwrap());
+                    catches.push(AST::new(clause_span, ASTKind::Catch(id, ty, body)));
+                }
+                Rule::FinallyClause => {
+                    finally = self.build_block(clause.into_inner().next().unwrap());
+                }
+                _ => unreachable!(),
+            }
         }
-        _ => unreachable!(format!("Found {:?}", tree.as_rule())),
+        AST::new(span, ASTKind::Try(body, catches, finally))
     }
-}
 
-fn build_if(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let cond = build_expr(iter.next().unwrap());
-    let then = build_block(iter.next().unwrap());
-    let els = if let Some(els) = iter.next() {
-        match els.as_rule() {
-            Rule::IfExpr => build_if(els),
-            Rule::BlockExpr => build_block(els),
+    fn build_match(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let val = self.build_expr(iter.next().unwrap());
+        let arms = iter
+            .map(|arm| {
+                let arm_span = Span::from(arm.as_span());
+                let mut arm_iter = arm.into_inner();
+                let pattern = self.build_match_pattern(arm_iter.next().unwrap());
+                let body = self.build_expr(arm_iter.next().unwrap());
+                AST::new(arm_span, ASTKind::MatchArm(pattern, body))
+            })
+            .collect();
+        AST::new(span, ASTKind::Match(val, arms))
+    }
+
+    /// tree: MatchPattern
+    fn build_match_pattern(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let tree = tree.into_inner().next().unwrap();
+        let span = Span::from(tree.as_span());
+        match tree.as_rule() {
+            Rule::LiteralPattern => {
+                let mut iter = tree.into_inner();
+                let lit = iter.next().unwrap();
+                if let Rule::Minus = lit.as_rule() {
+                    let lit = self.build_literal(iter.next().unwrap(), true);
+                    if let ASTKind::Int(
+                        _,
+                        ASTType::U8 | ASTType::U16 | ASTType::U32 | ASTType::U64 | ASTType::USize,
+                    ) = lit.kind
+                    {
+                        self.error(
+                            ErrCode::InvalidLiteral,
+                            span,
+                            "Unsigned int literal pattern cannot be negative",
+                        );
+                        lit
+                    } else if let ASTKind::Int(v, ty) = lit.kind {
+                        // already negated by build_literal
+                        AST::new(span, ASTKind::Int(v, ty))
+                    } else {
+                        self.error(
+                            ErrCode::InvalidLiteral,
+                            span,
+                            "Only int literal pattern can be negative",
+                        );
+                        lit
+                    }
+                } else {
+                    self.build_literal(lit, false)
+                }
+            }
+            Rule::VariantPattern => {
+                // empty parens produce no sub pattern
+                let has_parens = tree.as_str().trim_end().ends_with(')');
+                let mut iter = tree.into_inner();
+                let path = self.build_pathexpr(iter.next().unwrap());
+                let sub_patterns: Vec<Box<AST>> =
+                    iter.map(|sub| self.build_match_pattern(sub)).collect();
+                match path.get_self() {
+                    Some((id, None)) if path.len() == 1 && !has_parens => {
+                        // binding or "_"
+                        AST::new(span, ASTKind::Id(id.to_owned()))
+                    }
+                    _ => AST::new(span, ASTKind::VariantPattern(path, sub_patterns)),
+                }
+            }
             _ => unreachable!(),
         }
-    } else {
-        Box::new(AST::None)
-    };
-    Box::new(AST::If(cond, then, els))
-}
+    }
 
-fn build_stmt(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let clause = iter.next().unwrap();
-    match clause.as_rule() {
-        Rule::LetStmt => {
-            let mut iter = clause.into_inner();
-            let pattern = build_pattern(iter.next().unwrap());
-            let clause = iter.next().unwrap();
-            Box::new(match clause.as_rule() {
-                Rule::Type => {
-                    // has type
-                    let ty = build_type(clause);
-                    let clause = iter.next().unwrap();
+    fn build_stmt(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let clause = iter.next().unwrap();
+        match clause.as_rule() {
+            Rule::LetStmt => {
+                let mut iter = clause.into_inner();
+                let pattern = self.build_pattern(iter.next().unwrap());
+                let clause = iter.next().unwrap();
+                let no_init = AST::new(Span::new(span.end, span.end), ASTKind::None);
+                AST::new(
+                    span,
                     match clause.as_rule() {
+                        Rule::Type => {
+                            // has type
+                            let ty = self.build_type(clause);
+                            let clause = iter.next().unwrap();
+                            match clause.as_rule() {
+                                Rule::Semi => {
+                                    // no init
+                                    ASTKind::Let(pattern, LocalAttrib::default(), ty, no_init)
+                                }
+                                Rule::Eq => ASTKind::Let(
+                                    pattern,
+                                    LocalAttrib::default(),
+                                    ty,
+                                    self.build_expr(iter.next().unwrap()),
+                                ),
+                                _ => unreachable!(),
+                            }
+                        }
+                        Rule::Eq => {
+                            // no type but has init
+                            ASTKind::Let(
+                                pattern,
+                                LocalAttrib::default(),
+                                Box::new(ASTType::None),
+                                self.build_expr(iter.next().unwrap()),
+                            )
+                        }
                         Rule::Semi => {
-                            // no init
-                            AST::Let(pattern, LocalAttrib::default(), ty, Box::new(AST::None))
+                            // no type and no init
+                            ASTKind::Let(
+                                pattern,
+                                LocalAttrib::default(),
+                                Box::new(ASTType::None),
+                                no_init,
+                            )
                         }
-                        Rule::Eq => AST::Let(
-                            pattern,
-                            LocalAttrib::default(),
-                            ty,
-                            build_expr(iter.next().unwrap()),
-                        ),
                         _ => unreachable!(),
-                    }
-                }
-                Rule::Eq => {
-                    // no type but has init
-                    AST::Let(
-                        pattern,
-                        LocalAttrib::default(),
-                        Box::new(ASTType::None),
-                        build_expr(iter.next().unwrap()),
-                    )
-                }
-                Rule::Semi => {
-                    // no type and no init
-                    AST::Let(
-                        pattern,
-                        LocalAttrib::default(),
-                        Box::new(ASTType::None),
-                        Box::new(AST::None),
-                    )
+                    },
+                )
+            }
+            _ => {
+                let sub = self.build_expr(clause);
+                if let Some(_) = iter.next() {
+                    // Semi
+                    AST::new(span, ASTKind::ExprStmt(sub))
+                } else {
+                    sub
                 }
-                _ => unreachable!(),
-            })
-        }
-        _ => {
-            let sub = build_expr(clause);
-            if let Some(_) = iter.next() {
-                // Semi
-                Box::new(AST::ExprStmt(sub))
-            } else {
-                sub
             }
         }
     }
-}
 
-fn build_block(tree: Pair<Rule>) -> Box<AST> {
-    Box::new(AST::Block(
-        tree.into_inner()
-            .map(|sub| match sub.as_rule() {
-                Rule::Stmt => build_stmt(sub),
-                _ => build_expr(sub),
-            })
-            .collect(),
-    ))
-}
+    fn build_block(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        AST::new(
+            span,
+            ASTKind::Block(
+                tree.into_inner()
+                    .map(|sub| match sub.as_rule() {
+                        Rule::Stmt => self.build_stmt(sub),
+                        Rule::Error => self.build_error(sub, Rule::Stmt),
+                        _ => self.build_expr(sub),
+                    })
+                    .collect(),
+            ),
+        )
+    }
 
-fn build_assign(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let lhs = build_log_or_expr(iter.next().unwrap());
-    iter.next(); // "="
-    Box::new(AST::OpAssign(lhs, build_log_or_expr(iter.next().unwrap())))
-}
+    fn build_assign(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+        let lhs = self.build_log_or_expr(iter.next().unwrap());
+        let op = iter.next().unwrap();
+        let rhs = self.build_log_or_expr(iter.next().unwrap());
+        let op = match op.as_rule() {
+            Rule::Eq => return AST::new(span, ASTKind::OpAssign(lhs, rhs)),
+            Rule::PlusEq => ASTKind::OpAdd(lhs, rhs),
+            Rule::MinusEq => ASTKind::OpSub(lhs, rhs),
+            Rule::StarEq => ASTKind::OpMul(lhs, rhs),
+            Rule::SlashEq => ASTKind::OpDiv(lhs, rhs),
+            Rule::PercentEq => ASTKind::OpMod(lhs, rhs),
+            Rule::AmpEq => ASTKind::OpBitAnd(lhs, rhs),
+            Rule::PipeEq => ASTKind::OpBitOr(lhs, rhs),
+            Rule::CaretEq => ASTKind::OpBitXor(lhs, rhs),
+            Rule::ShlEq => ASTKind::OpShl(lhs, rhs),
+            Rule::ShrEq => ASTKind::OpShr(lhs, rhs),
+            _ => unreachable!(),
+        };
+        AST::new(span, ASTKind::OpCompoundAssign(AST::new(span, op)))
+    }
 
-fn build_log_or_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_log_and_expr(iter.next().unwrap());
+    fn build_log_or_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_log_and_expr(iter.next().unwrap());
 
-    for rhs in iter {
-        // log or is left associative
-        ret = Box::new(AST::OpLogOr(ret, build_log_and_expr(rhs)));
+        for rhs in iter {
+            // log or is left associative
+            let rhs = self.build_log_and_expr(rhs);
+            ret = AST::new(ret.span.merge(&rhs.span), ASTKind::OpLogOr(ret, rhs));
+        }
+        ret
     }
-    ret
-}
 
-fn build_log_and_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_eq_expr(iter.next().unwrap());
+    fn build_log_and_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_eq_expr(iter.next().unwrap());
 
-    for rhs in iter {
-        ret = Box::new(AST::OpLogAnd(ret, build_eq_expr(rhs)));
+        for rhs in iter {
+            let rhs = self.build_eq_expr(rhs);
+            ret = AST::new(ret.span.merge(&rhs.span), ASTKind::OpLogAnd(ret, rhs));
+        }
+        ret
     }
-    ret
-}
 
-fn build_eq_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_comp_expr(iter.next().unwrap());
+    fn build_eq_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_comp_expr(iter.next().unwrap());
+
+        loop {
+            if let Some(op) = iter.next() {
+                let rhs = self.build_comp_expr(iter.next().unwrap());
+                let span = ret.span.merge(&rhs.span);
+                ret = AST::new(
+                    span,
+                    match op.as_rule() {
+                        Rule::EqEq => ASTKind::OpEq(ret, rhs),
+                        Rule::Ne => ASTKind::OpNe(ret, rhs),
+                        _ => unreachable!(),
+                    },
+                );
+            } else {
+                break;
+            }
+        }
+        ret
+    }
 
-    loop {
-        if let Some(op) = iter.next() {
-            ret = Box::new(match op.as_rule() {
-                Rule::EqEq => AST::OpEq(ret, build_comp_expr(iter.next().unwrap())),
-                Rule::Ne => AST::OpNe(ret, build_comp_expr(iter.next().unwrap())),
-                _ => unreachable!(),
-            });
-        } else {
-            break;
+    fn build_comp_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_bit_or_expr(iter.next().unwrap());
+
+        loop {
+            if let Some(op) = iter.next() {
+                let rhs = self.build_bit_or_expr(iter.next().unwrap());
+                let span = ret.span.merge(&rhs.span);
+                ret = AST::new(
+                    span,
+                    match op.as_rule() {
+                        Rule::Le => ASTKind::OpLe(ret, rhs),
+                        Rule::Lt => ASTKind::OpLt(ret, rhs),
+                        Rule::Ge => ASTKind::OpGe(ret, rhs),
+                        Rule::Gt => ASTKind::OpGt(ret, rhs),
+                        _ => unreachable!(),
+                    },
+                );
+            } else {
+                break;
+            }
         }
+        ret
     }
-    ret
-}
 
-fn build_comp_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_add_expr(iter.next().unwrap());
-
-    loop {
-        if let Some(op) = iter.next() {
-            ret = Box::new(match op.as_rule() {
-                Rule::Le => AST::OpLe(ret, build_add_expr(iter.next().unwrap())),
-                Rule::Lt => AST::OpLt(ret, build_add_expr(iter.next().unwrap())),
-                Rule::Ge => AST::OpGe(ret, build_add_expr(iter.next().unwrap())),
-                Rule::Gt => AST::OpGt(ret, build_add_expr(iter.next().unwrap())),
-                _ => unreachable!(),
-            });
-        } else {
-            break;
+    fn build_bit_or_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_bit_xor_expr(iter.next().unwrap());
+
+        while iter.next().is_some() {
+            // "|"
+            let rhs = self.build_bit_xor_expr(iter.next().unwrap());
+            ret = AST::new(ret.span.merge(&rhs.span), ASTKind::OpBitOr(ret, rhs));
         }
+        ret
     }
-    ret
-}
 
-fn build_add_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_mul_expr(iter.next().unwrap());
+    fn build_bit_xor_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_bit_and_expr(iter.next().unwrap());
 
-    loop {
-        if let Some(op) = iter.next() {
-            ret = Box::new(match op.as_rule() {
-                Rule::Plus => AST::OpAdd(ret, build_mul_expr(iter.next().unwrap())),
-                Rule::Minus => AST::OpSub(ret, build_mul_expr(iter.next().unwrap())),
-                _ => unreachable!(),
-            });
-        } else {
-            break;
+        while iter.next().is_some() {
+            // "^"
+            let rhs = self.build_bit_and_expr(iter.next().unwrap());
+            ret = AST::new(ret.span.merge(&rhs.span), ASTKind::OpBitXor(ret, rhs));
         }
+        ret
     }
-    ret
-}
 
-fn build_mul_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_cast_expr(iter.next().unwrap());
+    fn build_bit_and_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_shift_expr(iter.next().unwrap());
 
-    loop {
-        if let Some(op) = iter.next() {
-            ret = Box::new(match op.as_rule() {
-                Rule::Star => AST::OpMul(ret, build_cast_expr(iter.next().unwrap())),
-                Rule::Slash => AST::OpDiv(ret, build_cast_expr(iter.next().unwrap())),
-                Rule::Percent => AST::OpMod(ret, build_cast_expr(iter.next().unwrap())),
-                _ => unreachable!(),
-            });
-        } else {
-            break;
+        while iter.next().is_some() {
+            // "&"
+            let rhs = self.build_shift_expr(iter.next().unwrap());
+            ret = AST::new(ret.span.merge(&rhs.span), ASTKind::OpBitAnd(ret, rhs));
         }
+        ret
     }
-    ret
-}
 
-fn build_cast_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_unary_expr(iter.next().unwrap());
+    fn build_shift_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_add_expr(iter.next().unwrap());
+
+        while let Some(op) = iter.next() {
+            let rhs = self.build_add_expr(iter.next().unwrap());
+            let span = ret.span.merge(&rhs.span);
+            ret = AST::new(
+                span,
+                match op.as_rule() {
+                    Rule::Shl => ASTKind::OpShl(ret, rhs),
+                    Rule::Shr => ASTKind::OpShr(ret, rhs),
+                    _ => unreachable!(),
+                },
+            );
+        }
+        ret
+    }
 
-    for rhs in iter {
-        ret = Box::new(AST::OpCast(build_type(rhs), ret));
+    fn build_add_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_mul_expr(iter.next().unwrap());
+
+        loop {
+            if let Some(op) = iter.next() {
+                let rhs = self.build_mul_expr(iter.next().unwrap());
+                let span = ret.span.merge(&rhs.span);
+                ret = AST::new(
+                    span,
+                    match op.as_rule() {
+                        Rule::Plus => ASTKind::OpAdd(ret, rhs),
+                        Rule::Minus => ASTKind::OpSub(ret, rhs),
+                        _ => unreachable!(),
+                    },
+                );
+            } else {
+                break;
+            }
+        }
+        ret
     }
-    ret
-}
 
-fn build_unary_expr(tree: Pair<Rule>) -> Box<AST> {
-    // unary is right associative, iterate reversely
-    let mut iter = tree.into_inner().rev();
-    let mut ret = build_new_expr(iter.next().unwrap());
+    fn build_mul_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_cast_expr(iter.next().unwrap());
+
+        loop {
+            if let Some(op) = iter.next() {
+                let rhs = self.build_cast_expr(iter.next().unwrap());
+                let span = ret.span.merge(&rhs.span);
+                ret = AST::new(
+                    span,
+                    match op.as_rule() {
+                        Rule::Star => ASTKind::OpMul(ret, rhs),
+                        Rule::Slash => ASTKind::OpDiv(ret, rhs),
+                        Rule::Percent => ASTKind::OpMod(ret, rhs),
+                        _ => unreachable!(),
+                    },
+                );
+            } else {
+                break;
+            }
+        }
+        ret
+    }
 
-    for op in iter {
-        ret = Box::new(match op.as_rule() {
-            Rule::Plus => AST::OpPos(ret),
-            Rule::Not => AST::OpLogNot(ret),
-            Rule::Minus => AST::OpNeg(ret),
-            _ => unreachable!(),
-        });
+    fn build_cast_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_unary_expr(iter.next().unwrap());
+
+        while let Some(op) = iter.next() {
+            let rhs = iter.next().unwrap();
+            let span = ret.span.merge(&Span::from(rhs.as_span()));
+            let ty = self.build_type(rhs);
+            ret = AST::new(
+                span,
+                match op.as_rule() {
+                    Rule::KwAs => ASTKind::OpCast(ty, ret, false),
+                    Rule::KwAsChecked => ASTKind::OpCast(ty, ret, true),
+                    Rule::KwIs => ASTKind::OpIs(ty, ret),
+                    _ => unreachable!(),
+                },
+            );
+        }
+        ret
     }
-    ret
-}
 
-fn build_new_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-
-    let ret = iter.next().unwrap();
-    match ret.as_rule() {
-        Rule::CallExpr => build_call_expr(ret),
-        Rule::Type => {
-            let ty = build_type(ret);
-            let initializer = iter.next().unwrap();
-            Box::new(match initializer.as_rule() {
-                Rule::Args => AST::OpNew(
-                    ty,
-                    initializer
-                        .into_inner()
-                        .map(|sub| build_expr(sub))
-                        .collect(),
-                ),
-                Rule::ArrAccessExpr => {
-                    let mut elem_ty = ty;
-                    while let Some(_) = iter.next() {
-                        iter.next().unwrap(); // RBracket
-                        elem_ty = Box::new(ASTType::Arr(elem_ty));
-                    }
-                    AST::OpNewArr(
-                        elem_ty,
-                        build_expr(initializer.into_inner().next().unwrap()),
-                    )
+    fn build_unary_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        // unary is right associative, iterate reversely
+        let mut iter = tree.into_inner().rev().peekable();
+        let operand = iter.next().unwrap();
+        let mut ret = match (bare_literal(&operand), iter.peek()) {
+            (Some(lit), Some(op)) if op.as_rule() == Rule::Minus => {
+                let lit = self.build_literal(lit, true);
+                if let ASTKind::Int(
+                    _,
+                    ASTType::I8 | ASTType::I16 | ASTType::I32 | ASTType::I64 | ASTType::ISize,
+                ) = lit.kind
+                {
+                    // folded into the literal
+                    let op = iter.next().unwrap();
+                    AST::new(lit.span.merge(&Span::from(op.as_span())), lit.kind)
+                } else {
+                    lit
                 }
-                _ => unreachable!(),
-            })
+            }
+            _ => self.build_new_expr(operand),
+        };
+
+        for op in iter {
+            let span = ret.span.merge(&Span::from(op.as_span()));
+            ret = AST::new(
+                span,
+                match op.as_rule() {
+                    Rule::Plus => ASTKind::OpPos(ret),
+                    Rule::Not => ASTKind::OpLogNot(ret),
+                    Rule::Minus => ASTKind::OpNeg(ret),
+                    Rule::Tilde => ASTKind::OpBitNot(ret),
+                    _ => unreachable!(),
+                },
+            );
         }
-        _ => unreachable!(),
+        ret
     }
-}
-
-fn build_call_expr(tree: Pair<Rule>) -> Box<AST> {
-    let mut iter = tree.into_inner();
-    let mut ret = build_primary_expr(iter.next().unwrap());
 
-    for rhs in iter {
-        ret = Box::new(match rhs.as_rule() {
-            Rule::Args => AST::OpCall(ret, rhs.into_inner().map(|sub| build_expr(sub)).collect()),
-            Rule::ObjAccessExpr => AST::OpObjAccess(
-                ret,
-                build_id_with_generic_params(rhs.into_inner().next().unwrap()),
-            ),
-            Rule::PathAccessExpr => AST::OpStaticAccess(
-                ret,
-                build_id_with_generic_params(rhs.into_inner().next().unwrap()),
-            ),
-            Rule::ArrAccessExpr => {
-                AST::OpArrayAccess(ret, build_expr(rhs.into_inner().next().unwrap()))
+    fn build_new_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let span = Span::from(tree.as_span());
+        let mut iter = tree.into_inner();
+
+        let ret = iter.next().unwrap();
+        match ret.as_rule() {
+            Rule::CallExpr => self.build_call_expr(ret),
+            Rule::Type => {
+                let ty = self.build_type(ret);
+                let initializer = iter.next().unwrap();
+                AST::new(
+                    span,
+                    match initializer.as_rule() {
+                        Rule::Args => ASTKind::OpNew(
+                            ty,
+                            initializer
+                                .into_inner()
+                                .map(|sub| self.build_expr(sub))
+                                .collect(),
+                        ),
+                        Rule::ArrAccessExpr => {
+                            let mut elem_ty = ty;
+                            while let Some(_) = iter.next() {
+                                iter.next().unwrap(); // RBracket
+                                elem_ty = Box::new(ASTType::Arr(elem_ty));
+                            }
+                            ASTKind::OpNewArr(
+                                elem_ty,
+                                self.build_expr(initializer.into_inner().next().unwrap()),
+                            )
+                        }
+                        _ => unreachable!(),
+                    },
+                )
             }
             _ => unreachable!(),
-        });
+        }
     }
-    ret
-}
 
-fn build_primary_expr(tree: Pair<Rule>) -> Box<AST> {
-    let tree = tree.into_inner().next().unwrap();
-    match tree.as_rule() {
-        Rule::GroupedExpr => build_expr(tree.into_inner().next().unwrap()),
-        Rule::LiteralExpr => build_literal(tree),
-        Rule::KwLSelf => Box::new(AST::Id(String::from("self"))),
-        Rule::IdWithGenericParams => {
-            Box::new(AST::IdWithGenericParams(build_id_with_generic_params(tree)))
+    fn build_call_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let mut iter = tree.into_inner();
+        let mut ret = self.build_primary_expr(iter.next().unwrap());
+
+        for rhs in iter {
+            let span = ret.span.merge(&Span::from(rhs.as_span()));
+            ret = AST::new(
+                span,
+                match rhs.as_rule() {
+                    Rule::Args => ASTKind::OpCall(
+                        ret,
+                        rhs.into_inner().map(|sub| self.build_expr(sub)).collect(),
+                    ),
+                    Rule::ObjAccessExpr => {
+                        let rhs = rhs.into_inner().next().unwrap();
+                        let rhs = if let Rule::TupleIdx = rhs.as_rule() {
+                            // tuple fields are named .0, .1, ...
+                            ASTIdWithGenericParam {
+                                id: format!(".{}", rhs.as_str()),
+                                generic_params: vec![],
+                            }
+                        } else {
+                            self.build_id_with_generic_params(rhs)
+                        };
+                        ASTKind::OpObjAccess(ret, rhs)
+                    }
+                    Rule::PathAccessExpr => ASTKind::OpStaticAccess(
+                        ret,
+                        self.build_id_with_generic_params(rhs.into_inner().next().unwrap()),
+                    ),
+                    Rule::ArrAccessExpr => ASTKind::OpArrayAccess(
+                        ret,
+                        self.build_expr(rhs.into_inner().next().unwrap()),
+                    ),
+                    _ => unreachable!(),
+                },
+            );
+        }
+        ret
+    }
+
+    fn build_primary_expr(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let tree = tree.into_inner().next().unwrap();
+        let span = Span::from(tree.as_span());
+        match tree.as_rule() {
+            Rule::GroupedExpr => self.build_expr(tree.into_inner().next().unwrap()),
+            Rule::TupleExpr => AST::new(
+                span,
+                ASTKind::Tuple(tree.into_inner().map(|e| self.build_expr(e)).collect()),
+            ),
+            Rule::LiteralExpr => self.build_literal(tree, false),
+            Rule::KwLSelf => AST::new(span, ASTKind::Id(String::from("self"))),
+            Rule::IdWithGenericParams => AST::new(
+                span,
+                ASTKind::IdWithGenericParams(self.build_id_with_generic_params(tree)),
+            ),
+            Rule::Type => AST::new(span, ASTKind::Type(self.build_type(tree))),
+            // Actually only expr with block
+            _ => self.build_expr(tree),
         }
-        Rule::Type => Box::new(AST::Type(build_type(tree))),
-        // Actually only expr with block
-        _ => build_expr(tree),
     }
-}
 
-fn build_literal(tree: Pair<Rule>) -> Box<AST> {
-    let tree = tree.into_inner().next().unwrap();
-    Box::new(match tree.as_rule() {
-        Rule::KwTrue => AST::Bool(true),
-        Rule::KwFalse => AST::Bool(false),
-        Rule::KwNull => AST::Null,
-        Rule::EmptyLiteral => AST::None,
-        Rule::IntLiteral => AST::Int(tree.as_span().as_str().trim().parse::<i32>().expect(
-            &format!("Unable to parse \"{}\" as i32", tree.as_span().as_str()),
-        )),
-        Rule::FloatLiteral => AST::Float(tree.as_span().as_str().trim().parse::<f64>().unwrap()),
-        Rule::StringLiteral => {
-            let mut chars = tree.as_span().as_str().trim().chars();
-            chars.next(); // skip first '"'
-            let mut s = String::new();
-            loop {
-                match chars.next() {
-                    Some(ch) => {
-                        match ch {
-                            '\\' => {
-                                // escape
-                                match chars.next().expect("Invalid string literal") {
-                                    'n' => s.push('\n'),
-                                    '"' => s.push('"'),
-                                    _ => unimplemented!("Unsupported escape char"),
+    /// negated: the literal is directly preceded by unary minus.
+    /// A signed int literal is then negated here, so MIN of its type can be written
+    fn build_literal(&mut self, tree: Pair<Rule>, negated: bool) -> Box<AST> {
+        let tree = tree.into_inner().next().unwrap();
+        let span = Span::from(tree.as_span());
+        let kind = match tree.as_rule() {
+            Rule::KwTrue => ASTKind::Bool(true),
+            Rule::KwFalse => ASTKind::Bool(false),
+            Rule::KwNull => ASTKind::Null,
+            Rule::EmptyLiteral => ASTKind::None,
+            Rule::IntLiteral | Rule::FloatLiteral => {
+                let lit = tree.as_str().trim();
+                let suffix_start = lit
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .unwrap_or(lit.len());
+                let (val, suffix) = lit.split_at(suffix_start);
+                let ty = match suffix {
+                    "" if tree.as_rule() == Rule::IntLiteral => ASTType::I32,
+                    "" => ASTType::F64,
+                    "u8" => ASTType::U8,
+                    "i8" => ASTType::I8,
+                    "u16" => ASTType::U16,
+                    "i16" => ASTType::I16,
+                    "u32" => ASTType::U32,
+                    "i32" => ASTType::I32,
+                    "u64" => ASTType::U64,
+                    "i64" => ASTType::I64,
+                    "usize" => ASTType::USize,
+                    "isize" => ASTType::ISize,
+                    "f32" => ASTType::F32,
+                    "f64" => ASTType::F64,
+                    _ => unreachable!(),
+                };
+                // the magnitude of a negated signed literal can be MAX + 1, e.g. -128i8
+                let (max, signed, name) = match ty {
+                    ASTType::U8 => (u8::MAX as u128, false, "u8"),
+                    ASTType::I8 => (i8::MAX as u128, true, "i8"),
+                    ASTType::U16 => (u16::MAX as u128, false, "u16"),
+                    ASTType::I16 => (i16::MAX as u128, true, "i16"),
+                    ASTType::U32 => (u32::MAX as u128, false, "u32"),
+                    ASTType::I32 => (i32::MAX as u128, true, "i32"),
+                    ASTType::U64 => (u64::MAX as u128, false, "u64"),
+                    ASTType::I64 => (i64::MAX as u128, true, "i64"),
+                    ASTType::USize => (usize::MAX as u128, false, "usize"),
+                    ASTType::ISize => (isize::MAX as u128, true, "isize"),
+                    // 1f32 is a float literal
+                    _ => return AST::new(span, ASTKind::Float(val.parse::<f64>().unwrap(), ty)),
+                };
+                let negated = negated && signed;
+                let max = if negated { max + 1 } else { max };
+                match val.parse::<u128>() {
+                    Ok(v) if v <= max && negated => ASTKind::Int(-(v as i128), ty),
+                    Ok(v) if v <= max => ASTKind::Int(v as i128, ty),
+                    _ => {
+                        self.error(
+                            ErrCode::InvalidLiteral,
+                            span,
+                            format!(
+                                "Int literal \"{}{}\" is out of range of {}",
+                                if negated { "-" } else { "" },
+                                lit,
+                                name
+                            ),
+                        );
+                        ASTKind::Int(0, ty)
+                    }
+                }
+            }
+            Rule::StringLiteral => {
+                let mut chars = tree.as_span().as_str().trim().chars();
+                chars.next(); // skip first '"'
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) => {
+                            match ch {
+                                '\\' => {
+                                    // escape
+                                    match chars.next() {
+                                        Some('n') => s.push('\n'),
+                                        Some('"') => s.push('"'),
+                                        Some(c) => self.error(
+                                            ErrCode::InvalidLiteral,
+                                            span,
+                                            format!("Unsupported escape char '\\{}'", c),
+                                        ),
+                                        None => unreachable!(),
+                                    }
                                 }
+                                '"' => break,
+                                _ => s.push(ch),
                             }
-                            '"' => break,
-                            _ => s.push(ch),
                         }
+                        None => unreachable!(),
                     }
-                    None => unreachable!(),
                 }
+                ASTKind::String(s)
             }
-            AST::String(s)
-        }
-        Rule::CharLiteral => {
-            let mut chars = tree.as_span().as_str().trim().chars();
-            chars.next(); // skip first '\''
-            let ch = AST::Char(match chars.next().unwrap() {
-                '\'' => panic!("Empty char literal"),
-                '\\' => chars.next().unwrap().into(),
-                c => c.into(),
-            });
-            match chars.next().expect("Invalid char literal") {
-                '\'' => (),
-                _ => panic!("Too many chars in char literal"),
+            Rule::CharLiteral => {
+                let mut chars = tree.as_span().as_str().trim().chars();
+                chars.next(); // skip first '\''
+                let ch = match chars.next().unwrap() {
+                    '\'' => {
+                        self.error(ErrCode::InvalidLiteral, span, "Empty char literal");
+                        return AST::new(span, ASTKind::Char(0));
+                    }
+                    '\\' => chars.next().unwrap().into(),
+                    c => c.into(),
+                };
+                if chars.next() != Some('\'') {
+                    self.error(
+                        ErrCode::InvalidLiteral,
+                        span,
+                        "Too many chars in char literal",
+                    );
+                }
+                ASTKind::Char(ch)
             }
-            ch
+            _ => unreachable!(),
+        };
+        AST::new(span, kind)
+    }
+
+    fn build_pattern(&mut self, tree: Pair<Rule>) -> Box<AST> {
+        let tree = tree.into_inner().next().unwrap();
+        let span = Span::from(tree.as_span());
+        AST::new(
+            span,
+            match tree.as_rule() {
+                Rule::Id => ASTKind::Id(build_id(tree)),
+                Rule::TuplePattern => ASTKind::TuplePattern(
+                    tree.into_inner()
+                        .map(|sub| self.build_pattern(sub))
+                        .collect(),
+                ),
+                _ => unreachable!(),
+            },
+        )
+    }
+
+    /// Same as GenericParamDecl
+    fn build_id_with_generic_params(&mut self, tree: Pair<Rule>) -> ASTIdWithGenericParam {
+        let mut param_iter = tree.into_inner();
+        let mut ret = ASTIdWithGenericParam {
+            id: build_id(param_iter.next().unwrap()),
+            generic_params: Vec::new(),
+        };
+        for param in param_iter {
+            ret.generic_params.push(self.build_type(param));
         }
-        _ => unreachable!(),
-    })
+        ret
+    }
 }
 
-fn build_pattern(tree: Pair<Rule>) -> Box<AST> {
-    let tree = tree.into_inner().next().unwrap();
-    Box::new(match tree.as_rule() {
-        Rule::Id => AST::Id(build_id(tree)),
-        Rule::TuplePattern => {
-            AST::TuplePattern(tree.into_inner().map(|sub| build_pattern(sub)).collect())
+/// Consume Vis if there is one. Items are pub by default
+fn build_vis(iter: &mut Pairs<Rule>) -> TypeAttribVisFlag {
+    if let Some(Rule::Vis) = iter.peek().map(|p| p.as_rule()) {
+        let mut vis_iter = iter.next().unwrap().into_inner();
+        match vis_iter.next().unwrap().as_rule() {
+            Rule::KwPub => {
+                if vis_iter.next().is_some() {
+                    // pub(crate)
+                    TypeAttribVisFlag::Crate
+                } else {
+                    TypeAttribVisFlag::Pub
+                }
+            }
+            Rule::KwPriv => TypeAttribVisFlag::Priv,
+            _ => unreachable!(),
         }
-        _ => unreachable!(),
-    })
+    } else {
+        TypeAttribVisFlag::Pub
+    }
+}
+
+fn method_vis(vis: TypeAttribVisFlag) -> MethodAttribFlag {
+    match vis {
+        TypeAttribVisFlag::Pub => MethodAttribFlag::Pub,
+        TypeAttribVisFlag::Crate => MethodAttribFlag::Crate,
+        TypeAttribVisFlag::Priv => MethodAttribFlag::Priv,
+    }
 }
 
 fn build_id(tree: Pair<Rule>) -> String {
@@ -769,15 +1477,12 @@ fn build_id(tree: Pair<Rule>) -> String {
     String::from(tree.as_span().as_str().trim())
 }
 
-/// Same as GenericParamDecl
-fn build_id_with_generic_params(tree: Pair<Rule>) -> ASTIdWithGenericParam {
-    let mut param_iter = tree.into_inner();
-    let mut ret = ASTIdWithGenericParam {
-        id: build_id(param_iter.next().unwrap()),
-        generic_params: Vec::new(),
-    };
-    for param in param_iter {
-        ret.generic_params.push(build_type(param));
-    }
-    ret
+/// tree: NewExpr, returns the LiteralExpr if the whole expr is a literal without postfix
+fn bare_literal<'i>(tree: &Pair<'i, Rule>) -> Option<Pair<'i, Rule>> {
+    let text = tree.as_str().trim();
+    tree.clone()
+        .into_inner()
+        .flatten()
+        .find(|sub| sub.as_rule() == Rule::LiteralExpr)
+        .filter(|lit| lit.as_str().trim() == text)
 }
@@ -1,3 +1,5 @@
 mod path;
+mod span;
 
 pub use path::{IItemPath, ItemPath, ItemPathBuf, ItemPathIter};
+pub use span::Span;
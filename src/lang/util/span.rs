@@ -0,0 +1,29 @@
+/// Byte range [start, end) in a source file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// Smallest span that covers both self and other
+    pub fn merge(&self, other: &Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl<'i> From<pest::Span<'i>> for Span {
+    fn from(span: pest::Span<'i>) -> Self {
+        Span {
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+}
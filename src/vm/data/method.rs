@@ -8,6 +8,8 @@ use super::{
     builtin_ty_str_desc, param_sig_str_desc, BuiltinType, ILModule, Module, Type, REF_SIZE,
 };
 
+use std::collections::HashMap;
+
 pub struct Param {
     pub name: usize,
     pub attrib: ParamAttrib,
@@ -47,6 +49,13 @@ pub struct MethodDesc {
     pub ret: Param,
 
     pub method_impl: MethodImpl,
+
+    /// names of generic params
+    pub generic_params: Vec<usize>,
+    /// not empty if this method is an instantiation of generic method
+    pub generic_args: Vec<BuiltinType>,
+    /// instantiations of this generic method, key: str desc of generic args
+    pub generic_insts: HashMap<String, Box<MethodDesc>>,
 }
 
 impl MethodDesc {
@@ -54,6 +63,12 @@ impl MethodDesc {
         self.attrib.is(MethodAttribFlag::Static)
     }
 
+    /// generic params of method or its parent are not determined
+    pub fn is_open(&self) -> bool {
+        (!self.generic_params.is_empty() && self.generic_args.is_empty())
+            || unsafe { self.parent.as_ref() }.is_some_and(|ty| ty.is_generic_def())
+    }
+
     // FIX: instance or not?
     pub fn str_desc_with_fullname(&self, str_pool: &Vec<String>) -> String {
         let mut sig = unsafe { self.ctx.as_ref().expect_il().fullname(str_pool).to_owned() };
@@ -105,6 +120,8 @@ pub enum MethodImpl {
     IL(MethodILImpl),
     Native(MethodNativeImpl),
     Runtime(MethodRuntimeImpl),
+    /// abstract method has no implementation, e.g. method of interface
+    Abstract,
 }
 
 impl MethodImpl {
@@ -113,6 +130,7 @@ impl MethodImpl {
             MethodImpl::IL(method_impl) => method_impl,
             MethodImpl::Native(_) => panic!(),
             MethodImpl::Runtime(_) => panic!(),
+            MethodImpl::Abstract => panic!(),
         }
     }
 }
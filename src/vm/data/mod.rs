@@ -4,7 +4,7 @@ mod module;
 mod ty;
 
 use xir::file::IrFile;
-use xir::sig::{self, TypeSig};
+use xir::sig::{self, TypeSig, TypeSpecSig};
 use xir::tok::{get_tok_tag, TokTag};
 use xir::ty::ResolutionScope;
 
@@ -25,6 +25,8 @@ pub const BOOL_SIZE: usize = size_of::<i8>();
 pub const CHAR_SIZE: usize = size_of::<u16>();
 pub const U1_SIZE: usize = size_of::<u8>();
 pub const I1_SIZE: usize = size_of::<i8>();
+pub const U2_SIZE: usize = size_of::<u16>();
+pub const I2_SIZE: usize = size_of::<i16>();
 pub const U4_SIZE: usize = size_of::<u32>();
 pub const I4_SIZE: usize = size_of::<i32>();
 pub const U8_SIZE: usize = size_of::<u64>();
@@ -40,13 +42,15 @@ pub struct TypedAddr {
 }
 
 /// VM representation of IrSig
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum BuiltinType {
     Void,
     Bool,
     Char,
     U1,
     I1,
+    U2,
+    I2,
     U4,
     I4,
     U8,
@@ -61,7 +65,14 @@ pub enum BuiltinType {
     Value(NonNull<Type>),
     Class(NonNull<Type>),
     /// is_class, type, args
+    ///
+    /// Only used when some generic args are not determined,
+    /// instantiated generic type is represented as Class or Value
     GenericInst(bool, NonNull<Type>, Vec<BuiltinType>),
+    /// Generic param of type
+    Var(u32),
+    /// Generic param of method
+    MVar(u32),
     /// to be filled
     Unk,
 }
@@ -115,10 +126,12 @@ impl BuiltinType {
             TypeSig::Char => BuiltinType::Char,
             TypeSig::I1 => BuiltinType::I1,
             TypeSig::U1 => BuiltinType::U1,
+            TypeSig::I2 => BuiltinType::I2,
+            TypeSig::U2 => BuiltinType::U2,
             TypeSig::I4 => BuiltinType::I4,
             TypeSig::U4 => BuiltinType::U4,
-            TypeSig::I8 => unimplemented!(),
-            TypeSig::U8 => unimplemented!(),
+            TypeSig::I8 => BuiltinType::I8,
+            TypeSig::U8 => BuiltinType::U8,
             TypeSig::R4 => BuiltinType::R4,
             TypeSig::R8 => BuiltinType::R8,
             TypeSig::I => BuiltinType::INative,
@@ -139,6 +152,34 @@ impl BuiltinType {
                         .collect(),
                 )
             }
+            TypeSig::Var(idx) => BuiltinType::Var(*idx),
+            TypeSig::MVar(idx) => BuiltinType::MVar(*idx),
+        }
+    }
+
+    pub fn from_type_spec_sig(ty: &TypeSpecSig, ctx: &ILModule) -> BuiltinType {
+        match ty {
+            TypeSpecSig::SZArray(ele_ty) => {
+                BuiltinType::SZArray(Box::new(Self::from_type_sig(ele_ty, ctx)))
+            }
+            TypeSpecSig::GenericInst(is_class, tok, args) => BuiltinType::GenericInst(
+                *is_class,
+                query_type_from_mod(*tok, ctx),
+                args.iter()
+                    .map(|arg| Self::from_type_sig(arg, ctx))
+                    .collect(),
+            ),
+            TypeSpecSig::Var(idx) => BuiltinType::Var(*idx),
+            TypeSpecSig::MVar(idx) => BuiltinType::MVar(*idx),
+        }
+    }
+
+    /// whether there are undetermined generic params in this type
+    pub fn is_open(&self) -> bool {
+        match self {
+            BuiltinType::ByRef(inner) | BuiltinType::SZArray(inner) => inner.is_open(),
+            BuiltinType::GenericInst(_, _, _) | BuiltinType::Var(_) | BuiltinType::MVar(_) => true,
+            _ => false,
         }
     }
 
@@ -150,6 +191,8 @@ impl BuiltinType {
             BuiltinType::Char => CHAR_SIZE,
             BuiltinType::U1 => U1_SIZE,
             BuiltinType::I1 => I1_SIZE,
+            BuiltinType::U2 => U2_SIZE,
+            BuiltinType::I2 => I2_SIZE,
             BuiltinType::U4 => U4_SIZE,
             BuiltinType::I4 => I4_SIZE,
             BuiltinType::U8 => U8_SIZE,
@@ -163,12 +206,9 @@ impl BuiltinType {
             | BuiltinType::SZArray(_)
             | BuiltinType::Class(_) => REF_SIZE,
             BuiltinType::Value(ty) => unsafe { ty.as_ref() }.basic_instance_size,
-            BuiltinType::GenericInst(is_class, ty, _) => {
-                if *is_class {
-                    REF_SIZE
-                } else {
-                    unsafe { ty.as_ref() }.basic_instance_size
-                }
+            BuiltinType::GenericInst(true, _, _) => REF_SIZE,
+            BuiltinType::GenericInst(false, _, _) | BuiltinType::Var(_) | BuiltinType::MVar(_) => {
+                panic!("Open generic type has no byte size")
             }
             BuiltinType::Unk => unreachable!(),
         }
@@ -182,6 +222,8 @@ pub fn builtin_ty_str_desc(ty: &BuiltinType, str_pool: &Vec<String>) -> String {
         BuiltinType::Char => String::from("C"),
         BuiltinType::U1 => String::from("B"),
         BuiltinType::I1 => String::from("b"),
+        BuiltinType::U2 => String::from("S"),
+        BuiltinType::I2 => String::from("s"),
         BuiltinType::U4 => String::from("I"),
         BuiltinType::I4 => String::from("i"),
         BuiltinType::U8 => String::from("L"),
@@ -194,22 +236,83 @@ pub fn builtin_ty_str_desc(ty: &BuiltinType, str_pool: &Vec<String>) -> String {
         BuiltinType::ByRef(inner) => format!("&{}", builtin_ty_str_desc(inner, str_pool)),
         BuiltinType::SZArray(inner) => format!("[{}", builtin_ty_str_desc(inner, str_pool)),
         BuiltinType::Class(ty) => {
-            format!("O{};", unsafe { ty.as_ref().fullname(str_pool) })
+            let ty = unsafe { ty.as_ref() };
+            format!(
+                "O{}{};",
+                ty.fullname(str_pool),
+                generic_args_str_desc(&ty.generic_args, str_pool)
+            )
         }
         BuiltinType::Value(ty) => {
-            format!("o{};", unsafe { ty.as_ref().fullname(str_pool) })
+            let ty = unsafe { ty.as_ref() };
+            format!(
+                "o{}{};",
+                ty.fullname(str_pool),
+                generic_args_str_desc(&ty.generic_args, str_pool)
+            )
         }
-        BuiltinType::GenericInst(_, _, _) => todo!(),
+        BuiltinType::GenericInst(is_class, ty, args) => format!(
+            "{}{}{};",
+            if *is_class { "O" } else { "o" },
+            unsafe { ty.as_ref().fullname(str_pool) },
+            generic_args_str_desc(args, str_pool)
+        ),
+        BuiltinType::Var(idx) => format!("!{}", idx),
+        BuiltinType::MVar(idx) => format!("!!{}", idx),
         BuiltinType::Unk => unreachable!(),
     }
 }
 
+/// empty if there is no generic args
+pub fn generic_args_str_desc(args: &[BuiltinType], str_pool: &Vec<String>) -> String {
+    if args.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<{}>",
+            args.iter()
+                .map(|arg| builtin_ty_str_desc(arg, str_pool))
+                .collect::<Vec<String>>()
+                .join("")
+        )
+    }
+}
+
+/// tok: TypeDefOrRef, return fullname of type
+fn type_tok_str_desc(tok: u32, ctx: &IrFile) -> String {
+    let (tag, idx) = get_tok_tag(tok);
+    let idx = idx as usize - 1;
+    match tag {
+        TokTag::TypeDef => format!(
+            "{}/{}",
+            ctx.mod_name(),
+            ctx.get_str(ctx.typedef_tbl[idx].name)
+        ),
+        TokTag::TypeRef => {
+            let (scope_tag, parent_idx) = ctx.typeref_tbl[idx].get_parent();
+            format!(
+                "{}/{}",
+                ctx.get_str(match scope_tag {
+                    ResolutionScope::Mod => ctx.mod_tbl[parent_idx].name,
+                    ResolutionScope::ModRef => ctx.modref_tbl[parent_idx].name,
+                    ResolutionScope::TypeRef => unimplemented!(),
+                }),
+                ctx.get_str(ctx.typeref_tbl[idx].name)
+            )
+        }
+        TokTag::TypeSpec => unimplemented!(),
+        _ => unreachable!(),
+    }
+}
+
 fn type_sig_str_desc(ty: &TypeSig, ctx: &IrFile) -> String {
     match ty {
         TypeSig::Boolean => String::from("Z"),
         TypeSig::Char => String::from("C"),
         TypeSig::I1 => String::from("b"),
         TypeSig::U1 => String::from("B"),
+        TypeSig::I2 => String::from("s"),
+        TypeSig::U2 => String::from("S"),
         TypeSig::I4 => String::from("i"),
         TypeSig::U4 => String::from("I"),
         TypeSig::I8 => String::from("l"),
@@ -218,61 +321,21 @@ fn type_sig_str_desc(ty: &TypeSig, ctx: &IrFile) -> String {
         TypeSig::R8 => String::from("D"),
         TypeSig::I => String::from("n"),
         TypeSig::U => String::from("N"),
-        TypeSig::SZArray(_) => unimplemented!(),
+        TypeSig::SZArray(ele_ty) => format!("[{}", type_sig_str_desc(ele_ty, ctx)),
         TypeSig::String => String::from("Ostd/String;"),
-        TypeSig::Class(tok) => {
-            let (tag, idx) = get_tok_tag(*tok);
-            let idx = idx as usize - 1;
-            match tag {
-                TokTag::TypeDef => format!(
-                    "O{}/{};",
-                    ctx.mod_name(),
-                    ctx.get_str(ctx.typedef_tbl[idx].name)
-                ),
-                TokTag::TypeRef => {
-                    let (scope_tag, parent_idx) = ctx.typeref_tbl[idx].get_parent();
-                    format!(
-                        "O{}/{};",
-                        ctx.get_str(match scope_tag {
-                            ResolutionScope::Mod => ctx.mod_tbl[parent_idx].name,
-                            ResolutionScope::ModRef => ctx.modref_tbl[parent_idx].name,
-                            ResolutionScope::TypeRef => unimplemented!(),
-                        }),
-                        ctx.get_str(ctx.typeref_tbl[idx].name)
-                    )
-                }
-                TokTag::TypeSpec => unimplemented!(),
-                _ => unreachable!(),
-            }
-        }
-        TypeSig::ValueType(tok) => {
-            let (tag, idx) = get_tok_tag(*tok);
-            let idx = idx as usize - 1;
-            match tag {
-                TokTag::TypeDef => format!(
-                    "o{}/{};",
-                    ctx.mod_name(),
-                    ctx.get_str(ctx.typedef_tbl[idx].name)
-                ),
-                TokTag::TypeRef => {
-                    let (scope_tag, parent_idx) = ctx.typeref_tbl[idx].get_parent();
-                    format!(
-                        "o{}/{};",
-                        ctx.get_str(match scope_tag {
-                            ResolutionScope::Mod => ctx.mod_tbl[parent_idx].name,
-                            ResolutionScope::ModRef => ctx.modref_tbl[parent_idx].name,
-                            ResolutionScope::TypeRef => unimplemented!(),
-                        }),
-                        ctx.get_str(ctx.typeref_tbl[idx].name)
-                    )
-                }
-                TokTag::TypeSpec => unimplemented!(),
-                _ => unreachable!(),
-            }
-        }
-        TypeSig::GenericInst(_, _, _) => {
-            todo!()
-        }
+        TypeSig::Class(tok) => format!("O{};", type_tok_str_desc(*tok, ctx)),
+        TypeSig::ValueType(tok) => format!("o{};", type_tok_str_desc(*tok, ctx)),
+        TypeSig::GenericInst(is_class, tok, args) => format!(
+            "{}{}<{}>;",
+            if *is_class { "O" } else { "o" },
+            type_tok_str_desc(*tok, ctx),
+            args.iter()
+                .map(|arg| type_sig_str_desc(arg, ctx))
+                .collect::<Vec<String>>()
+                .join("")
+        ),
+        TypeSig::Var(idx) => format!("!{}", idx),
+        TypeSig::MVar(idx) => format!("!!{}", idx),
     }
 }
 
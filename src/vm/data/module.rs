@@ -7,6 +7,9 @@ use super::{Field, MethodDesc, Type};
 pub enum MemberRef {
     Field(NonNull<Field>),
     Method(NonNull<MethodDesc>),
+    /// member of generic instantiation (parent is a typespec),
+    /// resolved at runtime since parent may depend on generic args of caller
+    Inst,
 }
 
 impl MemberRef {
@@ -79,4 +82,16 @@ impl ILModule {
     pub fn fullname<'h>(&self, str_pool: &'h Vec<String>) -> &'h str {
         &str_pool[self.fullname]
     }
+
+    /// (source file, line, col) of IL at offset of the index-th method, available if compiled with -g
+    pub fn source_location(&self, method_index: usize, offset: u32) -> Option<(&str, u32, u32)> {
+        let debug_info = self.ir_file.method_debug_info(method_index as u32 + 1)?;
+        let seq_point = debug_info.seq_point_at(offset)?;
+        let doc = &self.ir_file.document_tbl[debug_info.document as usize - 1];
+        Some((
+            &self.ir_file.str_heap[doc.name as usize],
+            seq_point.line,
+            seq_point.col,
+        ))
+    }
 }
@@ -1,9 +1,9 @@
 pub mod arr;
 
-use xir::attrib::TypeAttrib;
+use xir::attrib::{TypeAttrib, TypeAttribSemFlag};
 
 use super::super::util::ptr::NonNull;
-use super::{Field, ILModule, MethodDesc};
+use super::{BuiltinType, Field, ILModule, MethodDesc};
 
 use std::collections::HashMap;
 use std::ptr;
@@ -40,6 +40,22 @@ pub struct Type {
     pub static_fields: Vec<u8>,
 
     pub vtbl: Vec<NonNull<MethodDesc>>,
+
+    /// interfaces implemented by this type, or base interfaces if this type is an interface
+    pub interfaces: Vec<*mut Type>,
+    /// key: method of interface, value: method that implements it.
+    /// Interfaces of base types are included
+    pub interface_map: HashMap<*const MethodDesc, NonNull<MethodDesc>>,
+
+    /// names of generic params
+    pub generic_params: Vec<usize>,
+    /// not empty if this type is an instantiation of generic type
+    pub generic_args: Vec<BuiltinType>,
+    /// instantiations of this generic type, key: str desc of generic args
+    pub generic_insts: HashMap<String, Box<Type>>,
+    /// members of generic instantiation, members of typedef are owned by module
+    pub inst_fields: Vec<Box<Field>>,
+    pub inst_methods: Vec<Box<MethodDesc>>,
 }
 
 impl Type {
@@ -67,7 +83,45 @@ impl Type {
             static_fields: vec![],
 
             vtbl: vec![],
+
+            // fill in link stage
+            interfaces: vec![],
+            // fill in vtbl stage
+            interface_map: HashMap::new(),
+
+            // fill in load stage
+            generic_params: vec![],
+            generic_args: vec![],
+            generic_insts: HashMap::new(),
+            inst_fields: vec![],
+            inst_methods: vec![],
+        }
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.attrib.is_sem(TypeAttribSemFlag::Interface)
+    }
+
+    /// whether this type is target, derived from target or implements target
+    pub fn is_subtype_of(&self, target: *const Type) -> bool {
+        let mut ty = self as *const Type;
+        while let Some(ty_ref) = unsafe { ty.as_ref() } {
+            if ptr::eq(ty, target)
+                || ty_ref
+                    .interfaces
+                    .iter()
+                    .any(|interface| unsafe { interface.as_ref() }.unwrap().is_subtype_of(target))
+            {
+                return true;
+            }
+            ty = ty_ref.extends;
         }
+        false
+    }
+
+    /// generic type that is not instantiated
+    pub fn is_generic_def(&self) -> bool {
+        !self.generic_params.is_empty() && self.generic_args.is_empty()
     }
 
     pub fn fullname(&self, str_pool: &Vec<String>) -> String {
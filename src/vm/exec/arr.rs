@@ -1,10 +1,9 @@
-use super::super::data::{Type, TypedAddr, I4_SIZE, REF_SIZE};
+use super::super::data::{builtin_ty_str_desc, BuiltinType, Type, TypedAddr, REF_SIZE};
 use super::super::heap::Heap;
+use super::super::loader::resolve_type;
 use super::super::shared_mem::SharedMem;
-use super::super::stack::{ActivationRecord, Slot, SlotTag};
-use super::super::util::ptr::NonNull;
-
-use xir::tok::{get_tok_tag, TokTag};
+use super::super::stack::{load, store_slot, ActivationRecord, Slot, SlotTag};
+use super::Fault;
 
 fn to_arr_size(slot: &Slot) -> isize {
     unsafe {
@@ -19,127 +18,154 @@ fn to_arr_size(slot: &Slot) -> isize {
     }
 }
 
-pub fn exec_newarr(cur_ar: &mut ActivationRecord, mem: &mut SharedMem) {
+/// addr of the idx-th element of arr
+fn get_elem_addr(arr: *mut u8, ele_size: usize, idx: isize) -> Result<*mut u8, Fault> {
+    if arr.is_null() {
+        return Err(Fault::NullReference);
+    }
+    let len = Heap::get_arr_len(arr);
+    if idx < 0 || idx as usize >= len {
+        return Err(Fault::IndexOutOfRange(len, idx));
+    }
+    Ok(Heap::get_arr_offset(arr, ele_size, idx as usize))
+}
+
+/// consume operands of newarr and return (ele_ty, len)
+///
+/// array is allocated by executor since allocation may trigger gc
+pub fn decode_newarr(cur_ar: &mut ActivationRecord, mem: &SharedMem) -> (*const Type, usize) {
     let ty_tok = cur_ar.consume_u32();
-    let (tok_tag, tok_idx) = get_tok_tag(ty_tok);
-    let tok_idx = tok_idx as usize - 1;
-    let ctx = unsafe { cur_ar.method.ctx.as_ref().expect_il() };
-    let ele_ty = match tok_tag {
-        TokTag::TypeDef => ctx.types[tok_idx].as_ref() as *const Type,
-        TokTag::TypeRef => ctx.typerefs[tok_idx].as_ptr() as *const Type,
-        TokTag::TypeSpec => {
-            unimplemented!()
-        }
-        _ => unreachable!(),
+    let ele_ty = match resolve_type(ty_tok, cur_ar.method, &mem.str_pool) {
+        BuiltinType::Class(ty) | BuiltinType::Value(ty) => ty.as_ptr() as *const Type,
+        BuiltinType::I4 => mem.i32_class,
+        BuiltinType::String => mem.str_class,
+        BuiltinType::SZArray(_) => mem.arr_class,
+        ele_ty => unimplemented!(
+            "{} array is not implemented",
+            builtin_ty_str_desc(&ele_ty, &mem.str_pool)
+        ),
     };
 
     let size = to_arr_size(&cur_ar.eval_stack.pop(None));
-
-    let addr = unsafe { mem.new_arr(ele_ty, size as usize) };
-    cur_ar.eval_stack.push_ptr(addr);
+    (ele_ty, size as usize)
 }
 
-pub fn exec_ldlen(cur_ar: &mut ActivationRecord) {
+pub fn exec_ldlen(cur_ar: &mut ActivationRecord) -> Result<(), Fault> {
     let arr = cur_ar.eval_stack.pop(None);
     let arr = unsafe { arr.expect_ref() };
+    if arr.is_null() {
+        return Err(Fault::NullReference);
+    }
 
     let len = Heap::get_arr_len(arr);
     cur_ar.eval_stack.push_usize(len);
+    Ok(())
 }
 
-pub fn exec_ldelem(_: &mut ActivationRecord) {
-    unimplemented!("ldelem is not implemented");
+pub fn exec_ldelem(cur_ar: &mut ActivationRecord, str_pool: &Vec<String>) -> Result<(), Fault> {
+    let ty_tok = cur_ar.consume_u32();
+    match resolve_type(ty_tok, cur_ar.method, str_pool) {
+        BuiltinType::I4 => exec_ldelem_prim(cur_ar, &BuiltinType::I4),
+        BuiltinType::Class(_) | BuiltinType::String | BuiltinType::SZArray(_) => {
+            exec_ldelem_ref(cur_ar)
+        }
+        BuiltinType::Value(ele_ty) => {
+            let ele_ty = unsafe { ele_ty.as_ref() };
+            let idx = to_arr_size(&cur_ar.eval_stack.pop(None));
+            let arr = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
+            let addr = get_elem_addr(arr, ele_ty.basic_instance_size, idx)?;
+            unsafe {
+                cur_ar.eval_stack.alloc_value(ele_ty, addr);
+            }
+            Ok(())
+        }
+        _ => unimplemented!("ldelem is not implemented"),
+    }
 }
 
-pub fn exec_stelem(cur_ar: &mut ActivationRecord) {
+pub fn exec_stelem(
+    cur_ar: &mut ActivationRecord,
+    str_pool: &Vec<String>,
+    heap: &mut Heap,
+) -> Result<(), Fault> {
     let ty_tok = cur_ar.consume_u32();
-    let (tok_tag, tok_idx) = get_tok_tag(ty_tok);
-    let tok_idx = tok_idx as usize - 1;
-    let ctx = unsafe { cur_ar.method.ctx.as_ref().expect_il() };
-    let ele_ty = match tok_tag {
-        TokTag::TypeDef => ctx.types[tok_idx].as_ref(),
-        TokTag::TypeRef => unsafe { ctx.typerefs[tok_idx].as_ref() },
-        TokTag::TypeSpec => {
-            unimplemented!()
+    let ele_ty = match resolve_type(ty_tok, cur_ar.method, str_pool) {
+        BuiltinType::I4 => return exec_stelem_prim(cur_ar, &BuiltinType::I4),
+        BuiltinType::Class(_) | BuiltinType::String | BuiltinType::SZArray(_) => {
+            return exec_stelem_ref(cur_ar, heap)
         }
-        _ => unreachable!(),
-    };
-
-    let ele_size = if ele_ty.ee_class.is_value {
-        ele_ty.basic_instance_size
-    } else {
-        REF_SIZE
+        BuiltinType::Value(ty) => ty,
+        _ => unimplemented!("stelem is not implemented"),
     };
+    let ele_size = unsafe { ele_ty.as_ref() }.basic_instance_size;
 
     let idx = to_arr_size(cur_ar.eval_stack.peek_at(1).unwrap());
     let addr = unsafe { cur_ar.eval_stack.peek_at(2).unwrap().expect_ref() };
-    let addr = Heap::get_arr_offset(addr, ele_size, idx as usize);
-    cur_ar.eval_stack.pop(Some(TypedAddr {
-        ty: NonNull::new(ele_ty as *const Type as *mut Type).unwrap(),
-        addr,
-    }));
+    let addr = get_elem_addr(addr, ele_size, idx)?;
+    cur_ar.eval_stack.pop(Some(TypedAddr { ty: ele_ty, addr }));
+    heap.write_barrier(addr, &BuiltinType::Value(ele_ty));
     cur_ar.eval_stack.pop(None); // pop idx
     cur_ar.eval_stack.pop(None); // pop addr
+    Ok(())
 }
 
-pub fn exec_ldelema(cur_ar: &mut ActivationRecord) {
+pub fn exec_ldelema(cur_ar: &mut ActivationRecord, str_pool: &Vec<String>) -> Result<(), Fault> {
     let ty_tok = cur_ar.consume_u32();
-    let (tok_tag, tok_idx) = get_tok_tag(ty_tok);
-    let tok_idx = tok_idx as usize - 1;
-    let ctx = unsafe { cur_ar.method.ctx.as_ref().expect_il() };
-    let ele_ty = match tok_tag {
-        TokTag::TypeDef => ctx.types[tok_idx].as_ref(),
-        TokTag::TypeRef => unsafe { ctx.typerefs[tok_idx].as_ref() },
-        TokTag::TypeSpec => {
-            unimplemented!()
-        }
-        _ => unreachable!(),
-    };
+    let ele_ty = resolve_type(ty_tok, cur_ar.method, str_pool);
 
     let idx = to_arr_size(&cur_ar.eval_stack.pop(None));
     let arr = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
-    if ele_ty.ee_class.is_value {
-        let addr = Heap::get_arr_offset(arr, ele_ty.basic_instance_size, idx as usize);
+    if let BuiltinType::Value(ele_ty) = ele_ty {
+        let addr = get_elem_addr(arr, unsafe { ele_ty.as_ref() }.basic_instance_size, idx)?;
         cur_ar.eval_stack.push_managed(addr);
+        Ok(())
     } else {
         // TODO: what if ele_ty is a reference type?
         unimplemented!();
     }
 }
 
-pub fn exec_ldelem_i32(cur_ar: &mut ActivationRecord) {
+/// ldelem.<prim>, ele_ty is a primitive type
+pub fn exec_ldelem_prim(cur_ar: &mut ActivationRecord, ele_ty: &BuiltinType) -> Result<(), Fault> {
     let idx = to_arr_size(&cur_ar.eval_stack.pop(None));
     let arr = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
-    let addr = Heap::get_arr_offset(arr, I4_SIZE, idx as usize);
-    cur_ar.eval_stack.push_i32(unsafe { *(addr as *const i32) });
+    let addr = get_elem_addr(arr, ele_ty.byte_size(), idx)?;
+    unsafe {
+        load(ele_ty, addr, &mut cur_ar.eval_stack);
+    }
+    Ok(())
 }
 
-pub fn exec_stelem_i32(cur_ar: &mut ActivationRecord) {
+/// stelem.<prim>, ele_ty is a primitive type
+pub fn exec_stelem_prim(cur_ar: &mut ActivationRecord, ele_ty: &BuiltinType) -> Result<(), Fault> {
     let val = cur_ar.eval_stack.pop(None);
-    val.expect(SlotTag::I32);
     let idx = to_arr_size(&cur_ar.eval_stack.pop(None));
     let addr = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
-    let addr = Heap::get_arr_offset(addr, I4_SIZE, idx as usize);
+    let addr = get_elem_addr(addr, ele_ty.byte_size(), idx)?;
     unsafe {
-        *(addr as *mut i32) = val.data.i32_;
+        store_slot(ele_ty, addr, val);
     }
+    Ok(())
 }
 
-pub fn exec_ldelem_ref(cur_ar: &mut ActivationRecord) {
+pub fn exec_ldelem_ref(cur_ar: &mut ActivationRecord) -> Result<(), Fault> {
     let idx = to_arr_size(&cur_ar.eval_stack.pop(None));
     let arr = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
-    let addr = Heap::get_arr_offset(arr, REF_SIZE, idx as usize);
+    let addr = get_elem_addr(arr, REF_SIZE, idx)?;
     cur_ar
         .eval_stack
         .push_ptr(unsafe { *(addr as *const *mut u8) });
+    Ok(())
 }
 
-pub fn exec_stelem_ref(cur_ar: &mut ActivationRecord) {
+pub fn exec_stelem_ref(cur_ar: &mut ActivationRecord, heap: &mut Heap) -> Result<(), Fault> {
     let val = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
     let idx = to_arr_size(&cur_ar.eval_stack.pop(None));
     let addr = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
-    let addr = Heap::get_arr_offset(addr, REF_SIZE, idx as usize);
+    let addr = get_elem_addr(addr, REF_SIZE, idx)?;
     unsafe {
         *(addr as *mut *mut u8) = val;
     }
+    heap.write_barrier_ref(addr);
+    Ok(())
 }
@@ -0,0 +1,60 @@
+use super::super::data::{builtin_ty_str_desc, BuiltinType, Type};
+use super::super::heap::Heap;
+use super::super::loader::resolve_type;
+use super::super::shared_mem::SharedMem;
+use super::super::stack::ActivationRecord;
+use super::Fault;
+
+use std::ptr;
+
+/// whether obj (not null) is an instance of ty
+fn is_inst(obj: *mut u8, ty: &BuiltinType, mem: &SharedMem) -> bool {
+    let obj_ty = unsafe { Heap::get_vtbl_ptr(obj).as_ref().unwrap() };
+    match ty {
+        BuiltinType::Class(ty) => obj_ty.is_subtype_of(ty.as_ptr()),
+        BuiltinType::String => ptr::eq(obj_ty, mem.str_class),
+        // element type of array is not recorded in obj, any array is accepted
+        BuiltinType::SZArray(_) => ptr::eq(obj_ty, mem.arr_class),
+        // other target types are rejected by verifier
+        _ => unreachable!(
+            "Cast to {} is not allowed",
+            builtin_ty_str_desc(ty, &mem.str_pool)
+        ),
+    }
+}
+
+/// name of ty used in exception message
+fn ty_name(ty: &BuiltinType, mem: &SharedMem) -> String {
+    match ty {
+        BuiltinType::Class(ty) => unsafe { ty.as_ref() }.fullname(&mem.str_pool),
+        _ => builtin_ty_str_desc(ty, &mem.str_pool),
+    }
+}
+
+/// fullname of the type of obj (not null)
+fn obj_ty_name(obj: *mut u8, mem: &SharedMem) -> String {
+    let obj_ty: &Type = unsafe { Heap::get_vtbl_ptr(obj).as_ref().unwrap() };
+    obj_ty.fullname(&mem.str_pool)
+}
+
+pub fn exec_castclass(cur_ar: &mut ActivationRecord, mem: &SharedMem) -> Result<(), Fault> {
+    let tok = cur_ar.consume_u32();
+    let ty = resolve_type(tok, cur_ar.method, &mem.str_pool);
+    let obj = unsafe { cur_ar.eval_stack.peek().unwrap().expect_ref() };
+    if obj.is_null() || is_inst(obj, &ty, mem) {
+        Ok(())
+    } else {
+        Err(Fault::InvalidCast(obj_ty_name(obj, mem), ty_name(&ty, mem)))
+    }
+}
+
+pub fn exec_isinst(cur_ar: &mut ActivationRecord, mem: &SharedMem) {
+    let tok = cur_ar.consume_u32();
+    let ty = resolve_type(tok, cur_ar.method, &mem.str_pool);
+    let obj = unsafe { cur_ar.eval_stack.pop(None).expect_ref() };
+    if obj.is_null() || is_inst(obj, &ty, mem) {
+        cur_ar.eval_stack.push_ptr(obj);
+    } else {
+        cur_ar.eval_stack.push_ptr(ptr::null_mut());
+    }
+}
@@ -0,0 +1,757 @@
+use xir::attrib::FieldAttribFlag;
+use xir::debug::{MethodDebugInfo, SequencePoint};
+
+use super::super::data::{BuiltinType, Field, Type, REF_SIZE};
+use super::super::heap::Heap;
+use super::super::shared_mem::SharedMem;
+use super::super::stack::{load, ActivationRecord, EvalStack, ILocals, Slot, SlotTag};
+use super::super::util::ptr::NonNull;
+use super::{frame_desc, TExecutor};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process;
+use std::ptr;
+
+const HELP: &str = "\
+break, b [<method> | <file>:<line> | <line>]
+                 Set a breakpoint, or list breakpoints if no location is given
+delete, d <id>   Delete a breakpoint
+continue, c      Continue until a breakpoint is hit
+step, s          Step to the next statement, entering calls
+next, n          Step to the next statement, skipping calls
+stepi, si        Step one instruction
+finish           Continue until the selected method returns
+backtrace, bt    Show frames, innermost first
+frame, f <n>     Select frame n for args, locals, stack, print and x
+args             Show args of the selected frame
+locals           Show local vars of the selected frame in scope
+stack            Show the eval stack of the selected frame, top first
+print, p <path>  Show a var, e.g. p node.next.value, p arr[1]
+x <path>         Examine the object or value of a var field by field
+help, h          Show this help
+quit, q          Exit
+An empty line repeats the last command";
+
+/// Max number of array elements shown by x
+const MAX_ELES_SHOWN: usize = 100;
+
+enum Breakpoint {
+    /// method name, optionally qualified by class and module, e.g. main, Program::main, demo/Program::main
+    Method(String),
+    /// (source file, line), any file matches if file is None
+    Line(Option<String>, u32),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Method(name) => write!(f, "method {}", name),
+            Breakpoint::Line(Some(file), line) => write!(f, "line {}:{}", file, line),
+            Breakpoint::Line(None, line) => write!(f, "line {}", line),
+        }
+    }
+}
+
+/// When to stop besides breakpoints
+enum RunMode {
+    /// before the next inst
+    Pause,
+    Continue,
+    /// at the next statement, or the next inst if by_line is false
+    StepInto {
+        by_line: bool,
+    },
+    /// same as StepInto except that frames deeper than depth are skipped
+    StepOver {
+        depth: usize,
+        by_line: bool,
+    },
+    /// when the frame at depth returns
+    StepOut {
+        depth: usize,
+        returns_value: bool,
+    },
+}
+
+enum StopReason {
+    Breakpoint(usize),
+    Step,
+}
+
+/// A place that stores a value of type ty
+struct Place {
+    ty: BuiltinType,
+    addr: *const u8,
+}
+
+enum Accessor {
+    Field(String),
+    Index(usize),
+}
+
+/// Interactive debugger driven by commands on stdin, see HELP
+pub struct Debugger {
+    breakpoints: Vec<(usize, Breakpoint)>,
+    next_bp_id: usize,
+    mode: RunMode,
+    /// frame inspected by args, locals, stack, print and x. 0 is the innermost frame
+    frame: usize,
+    /// repeated when an empty line is entered
+    last_cmd: String,
+    /// commands are echoed if stdin is not a terminal, so that scripted sessions are readable
+    echo: bool,
+    /// file path -> lines
+    sources: HashMap<String, Vec<String>>,
+}
+
+impl Debugger {
+    /// Execution will stop before the first inst
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: Vec::new(),
+            next_bp_id: 1,
+            mode: RunMode::Pause,
+            frame: 0,
+            last_cmd: String::new(),
+            echo: !io::stdin().is_terminal(),
+            sources: HashMap::new(),
+        }
+    }
+
+    fn check_stop(
+        &self,
+        states: &[ActivationRecord],
+        str_pool: &Vec<String>,
+    ) -> Option<StopReason> {
+        let depth = states.len();
+        let top = states.last().unwrap();
+        for (id, bp) in self.breakpoints.iter() {
+            let hit = match bp {
+                Breakpoint::Method(name) => top.ip == 0 && method_matches(top, name, str_pool),
+                Breakpoint::Line(file, line) => match stmt_at(top, top.ip) {
+                    Some((doc, sp)) => {
+                        sp.line == *line
+                            && file.as_ref().is_none_or(|f| Path::new(doc).ends_with(f))
+                    }
+                    None => false,
+                },
+            };
+            if hit {
+                return Some(StopReason::Breakpoint(*id));
+            }
+        }
+
+        let stop = match self.mode {
+            RunMode::Pause => true,
+            RunMode::Continue => false,
+            RunMode::StepInto { by_line } => !by_line || stmt_at(top, top.ip).is_some(),
+            RunMode::StepOver {
+                depth: step_depth,
+                by_line,
+            } => depth <= step_depth && (!by_line || stmt_at(top, top.ip).is_some()),
+            RunMode::StepOut {
+                depth: step_depth, ..
+            } => depth < step_depth,
+        };
+        if stop {
+            Some(StopReason::Step)
+        } else {
+            None
+        }
+    }
+
+    /// Run commands until execution is resumed, return false if stdin is closed
+    fn repl(&mut self, states: &[ActivationRecord], mem: &SharedMem, reason: StopReason) -> bool {
+        let str_pool = &mem.str_pool;
+        let top = states.last().unwrap();
+        match reason {
+            StopReason::Breakpoint(id) => {
+                println!("Breakpoint {}, {}", id, frame_desc(top, str_pool, top.ip))
+            }
+            StopReason::Step => println!("{}", frame_desc(top, str_pool, top.ip)),
+        }
+        self.show_source_line(top, top.ip);
+        if let RunMode::StepOut {
+            returns_value: true,
+            ..
+        } = self.mode
+        {
+            // ret value has been pushed to the eval stack of caller
+            if let Some(ret) = top.eval_stack.peek() {
+                println!("Value returned: {}", fmt_slot(ret));
+            }
+        }
+        self.frame = 0;
+        self.mode = RunMode::Pause;
+
+        loop {
+            print!("(xdb) ");
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).unwrap() == 0 {
+                println!();
+                return false;
+            }
+            if self.echo {
+                println!("{}", line.trim_end());
+            }
+
+            let line = line.trim();
+            if !line.is_empty() {
+                self.last_cmd = line.to_owned();
+            }
+            let line = self.last_cmd.clone();
+            let (cmd, arg) = match line.split_once(char::is_whitespace) {
+                Some((cmd, arg)) => (cmd, arg.trim()),
+                None => (line.as_str(), ""),
+            };
+
+            let (state, offset) = self.selected(states);
+            match cmd {
+                "" => {}
+                "c" | "continue" => {
+                    self.mode = RunMode::Continue;
+                    return true;
+                }
+                "s" | "step" => {
+                    self.mode = RunMode::StepInto {
+                        by_line: debug_info(top).is_some(),
+                    };
+                    return true;
+                }
+                "n" | "next" => {
+                    self.mode = RunMode::StepOver {
+                        depth: states.len(),
+                        by_line: debug_info(top).is_some(),
+                    };
+                    return true;
+                }
+                "si" | "stepi" => {
+                    self.mode = RunMode::StepInto { by_line: false };
+                    return true;
+                }
+                "finish" => {
+                    if self.frame + 1 == states.len() {
+                        println!("\"finish\" is not meaningful in the outermost frame");
+                    } else {
+                        println!("Run till exit from {}", frame_desc(state, str_pool, offset));
+                        self.mode = RunMode::StepOut {
+                            depth: states.len() - self.frame,
+                            returns_value: state.method.ret.ty != BuiltinType::Void,
+                        };
+                        return true;
+                    }
+                }
+                "b" | "break" => self.cmd_break(arg),
+                "d" | "delete" => self.cmd_delete(arg),
+                "bt" | "backtrace" => {
+                    for (i, state) in states.iter().rev().enumerate() {
+                        println!(
+                            "#{} {}",
+                            i,
+                            frame_desc(state, str_pool, frame_offset(state, i))
+                        );
+                    }
+                }
+                "f" | "frame" => match arg.parse::<usize>() {
+                    Ok(frame) if frame < states.len() => {
+                        self.frame = frame;
+                        let (state, offset) = self.selected(states);
+                        println!("#{} {}", frame, frame_desc(state, str_pool, offset));
+                        self.show_source_line(state, offset);
+                    }
+                    _ => println!("Invalid frame {}, there are {} frames", arg, states.len()),
+                },
+                "args" => show_args(state, str_pool),
+                "locals" => show_locals(state, offset, str_pool),
+                "stack" => {
+                    if state.eval_stack.is_empty() {
+                        println!("Eval stack is empty");
+                    }
+                    for i in 0..state.eval_stack.len() {
+                        println!("[{}] {}", i, fmt_slot(state.eval_stack.peek_at(i).unwrap()));
+                    }
+                }
+                "p" | "print" => match resolve_path(state, offset, arg, str_pool) {
+                    Ok(place) => println!("{} = {}", arg, unsafe {
+                        fmt_typed(&place.ty, place.addr, str_pool)
+                    }),
+                    Err(msg) => println!("{}", msg),
+                },
+                "x" => match resolve_path(state, offset, arg, str_pool) {
+                    Ok(place) => println!("{}", unsafe { examine(&place, str_pool) }),
+                    Err(msg) => println!("{}", msg),
+                },
+                "h" | "help" => println!("{}", HELP),
+                "q" | "quit" => {
+                    io::stdout().flush().unwrap();
+                    process::exit(0);
+                }
+                _ => println!("Unknown command {}, try help", cmd),
+            }
+        }
+    }
+
+    /// (selected frame, IL offset to look up source location and local scopes)
+    fn selected<'a, 'm>(
+        &self,
+        states: &'a [ActivationRecord<'m>],
+    ) -> (&'a ActivationRecord<'m>, usize) {
+        let state = &states[states.len() - 1 - self.frame];
+        (state, frame_offset(state, self.frame))
+    }
+
+    fn cmd_break(&mut self, arg: &str) {
+        if arg.is_empty() {
+            if self.breakpoints.is_empty() {
+                println!("No breakpoints");
+            }
+            for (id, bp) in self.breakpoints.iter() {
+                println!("{} {}", id, bp);
+            }
+            return;
+        }
+
+        let bp = if let Some((file, line)) = arg.rsplit_once(':') {
+            match line.parse::<u32>() {
+                Ok(line) => Breakpoint::Line(Some(file.to_owned()), line),
+                // e.g. Program::main
+                Err(_) => Breakpoint::Method(arg.to_owned()),
+            }
+        } else if let Ok(line) = arg.parse::<u32>() {
+            Breakpoint::Line(None, line)
+        } else {
+            Breakpoint::Method(arg.to_owned())
+        };
+        println!("Breakpoint {} at {}", self.next_bp_id, bp);
+        self.breakpoints.push((self.next_bp_id, bp));
+        self.next_bp_id += 1;
+    }
+
+    fn cmd_delete(&mut self, arg: &str) {
+        let len = self.breakpoints.len();
+        if let Ok(id) = arg.parse::<usize>() {
+            self.breakpoints.retain(|(bp_id, _)| *bp_id != id);
+        }
+        if self.breakpoints.len() == len {
+            println!("No breakpoint {}", arg);
+        }
+    }
+
+    fn show_source_line(&mut self, state: &ActivationRecord, offset: usize) {
+        let module = unsafe { state.method.ctx.as_ref().expect_il() };
+        if let Some((file, line, _)) = module.source_location(state.method.index, offset as u32) {
+            let lines = self.sources.entry(file.to_owned()).or_insert_with(|| {
+                fs::read_to_string(file)
+                    .map(|src| src.lines().map(|l| l.to_owned()).collect())
+                    .unwrap_or_default()
+            });
+            if let Some(src) = lines.get(line as usize - 1) {
+                println!("{}\t{}", line, src);
+            }
+        }
+    }
+}
+
+impl<'m> TExecutor<'m> {
+    pub fn attach_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(Box::new(debugger));
+    }
+
+    /// Called before each inst if a debugger is attached
+    pub(super) fn debug_hook(&mut self, mem: &SharedMem) {
+        let mut debugger = self.debugger.take().unwrap();
+        if let Some(reason) = debugger.check_stop(&self.states, &mem.str_pool) {
+            if !debugger.repl(&self.states, mem, reason) {
+                // stdin is closed, detach and run to the end
+                return;
+            }
+        }
+        self.debugger = Some(debugger);
+    }
+}
+
+/// ip of callers has passed the call inst
+fn frame_offset(state: &ActivationRecord, frame: usize) -> usize {
+    if frame == 0 {
+        state.ip
+    } else {
+        state.ip - 1
+    }
+}
+
+fn debug_info<'m>(state: &ActivationRecord<'m>) -> Option<&'m MethodDebugInfo> {
+    let module = unsafe { state.method.ctx.as_ref().expect_il() };
+    module
+        .ir_file
+        .method_debug_info(state.method.index as u32 + 1)
+}
+
+/// (source file, sequence point) of the statement that starts exactly at offset
+fn stmt_at<'m>(
+    state: &ActivationRecord<'m>,
+    offset: usize,
+) -> Option<(&'m str, &'m SequencePoint)> {
+    let info = debug_info(state)?;
+    let sp = info
+        .seq_points
+        .iter()
+        .find(|sp| sp.offset as usize == offset)?;
+    let module = unsafe { state.method.ctx.as_ref().expect_il() };
+    let doc = &module.ir_file.document_tbl[info.document as usize - 1];
+    Some((&module.ir_file.str_heap[doc.name as usize], sp))
+}
+
+/// name matches the fullname of method, or its suffix after a '/' or "::"
+fn method_matches(state: &ActivationRecord, name: &str, str_pool: &Vec<String>) -> bool {
+    let desc = state.method.str_desc_with_fullname(str_pool);
+    let fullname = &desc[..desc.find('(').unwrap()];
+    fullname == name
+        || fullname.ends_with(&format!("/{}", name))
+        || fullname.ends_with(&format!("::{}", name))
+}
+
+fn show_args(state: &ActivationRecord, str_pool: &Vec<String>) {
+    if state.args.get_self().is_some() {
+        let mut stack = EvalStack::new(1);
+        state.args.load(0, &mut stack);
+        println!("self = {}", fmt_slot(&stack.pop(None)));
+    } else if state.method.ps.is_empty() {
+        println!("No args");
+    }
+    for i in 0..state.method.ps.len() {
+        let (ty, addr) = state.args.get(i);
+        println!("{} = {}", arg_name(state, i, str_pool), unsafe {
+            fmt_typed(ty, addr, str_pool)
+        });
+    }
+}
+
+fn arg_name(state: &ActivationRecord, i: usize, str_pool: &Vec<String>) -> String {
+    let name = &str_pool[state.method.ps[i].name];
+    if name.is_empty() {
+        format!("$arg{}", i)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Local vars with names in scope if debug info is available, otherwise all local vars as $locN
+fn show_locals(state: &ActivationRecord, offset: usize, str_pool: &Vec<String>) {
+    let mut shown = false;
+    for i in 0..state.locals.len() {
+        if let Some(name) = local_name(state, i, offset) {
+            let (ty, addr) = state.locals.get(i);
+            println!("{} = {}", name, unsafe { fmt_typed(ty, addr, str_pool) });
+            shown = true;
+        }
+    }
+    if !shown {
+        println!("No locals");
+    }
+}
+
+fn local_name(state: &ActivationRecord, i: usize, offset: usize) -> Option<String> {
+    if let Some(info) = debug_info(state) {
+        let module = unsafe { state.method.ctx.as_ref().expect_il() };
+        info.local_name_at(i as u16, offset as u32)
+            .map(|name| module.ir_file.str_heap[name as usize].clone())
+    } else {
+        Some(format!("$loc{}", i))
+    }
+}
+
+/// path: var ('.' field | '[' index ']')*
+///
+/// var is self, an arg, a local var in scope, $argN or $locN
+fn resolve_path(
+    state: &ActivationRecord,
+    offset: usize,
+    path: &str,
+    str_pool: &Vec<String>,
+) -> Result<Place, String> {
+    if path.is_empty() {
+        return Err(String::from("Expect a var"));
+    }
+    let var_end = path.find(['.', '[']).unwrap_or(path.len());
+    let var = &path[..var_end];
+    let mut place = resolve_var(state, offset, var, str_pool)?;
+
+    let mut rest = &path[var_end..];
+    while !rest.is_empty() {
+        let accessor = if let Some(field) = rest.strip_prefix('.') {
+            let end = field.find(['.', '[']).unwrap_or(field.len());
+            rest = &field[end..];
+            Accessor::Field(field[..end].to_owned())
+        } else if let Some(idx) = rest.strip_prefix('[') {
+            let end = idx
+                .find(']')
+                .ok_or_else(|| format!("Missing ] in {}", path))?;
+            let i = idx[..end]
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid index {}", &idx[..end]))?;
+            rest = &idx[end + 1..];
+            Accessor::Index(i)
+        } else {
+            return Err(format!("Invalid path {}", path));
+        };
+        place = unsafe { access(place, &accessor, str_pool) }?;
+    }
+    Ok(place)
+}
+
+fn resolve_var(
+    state: &ActivationRecord,
+    offset: usize,
+    var: &str,
+    str_pool: &Vec<String>,
+) -> Result<Place, String> {
+    if var == "self" {
+        if state.args.get_self().is_none() {
+            return Err(String::from("No self in static method"));
+        }
+        let parent = NonNull::new(state.method.parent as *mut Type).unwrap();
+        let ty = if unsafe { parent.as_ref() }.ee_class.is_value {
+            BuiltinType::ByRef(Box::new(BuiltinType::Value(parent)))
+        } else {
+            BuiltinType::Class(parent)
+        };
+        return Ok(Place {
+            ty,
+            addr: state.args.as_ptr(),
+        });
+    }
+
+    for i in 0..state.method.ps.len() {
+        if arg_name(state, i, str_pool) == var || format!("$arg{}", i) == var {
+            let (ty, addr) = state.args.get(i);
+            return Ok(Place {
+                ty: ty.clone(),
+                addr,
+            });
+        }
+    }
+    // search backwards since an inner var might shadow an outer one with the same name
+    for i in (0..state.locals.len()).rev() {
+        if local_name(state, i, offset).is_some_and(|name| name == var)
+            || format!("$loc{}", i) == var
+        {
+            let (ty, addr) = state.locals.get(i);
+            return Ok(Place {
+                ty: ty.clone(),
+                addr,
+            });
+        }
+    }
+    Err(format!("No var {} in the selected frame", var))
+}
+
+unsafe fn access(
+    place: Place,
+    accessor: &Accessor,
+    str_pool: &Vec<String>,
+) -> Result<Place, String> {
+    let place = deref(place);
+    match (&place.ty, accessor) {
+        (BuiltinType::Value(ty), Accessor::Field(name)) => {
+            let field = find_field(ty.as_ref(), name, str_pool)?;
+            Ok(Place {
+                ty: field.ty.clone(),
+                addr: place.addr.wrapping_add(field.offset),
+            })
+        }
+        (BuiltinType::Class(_) | BuiltinType::String, Accessor::Field(name)) => {
+            let obj = deref_obj(&place)?;
+            let field = find_field(Heap::get_vtbl_ptr(obj).as_ref().unwrap(), name, str_pool)?;
+            Ok(Place {
+                ty: field.ty.clone(),
+                addr: obj.wrapping_add(field.offset),
+            })
+        }
+        (BuiltinType::SZArray(ele_ty), Accessor::Index(i)) => {
+            let obj = deref_obj(&place)?;
+            let len = Heap::get_arr_len(obj);
+            if *i >= len {
+                return Err(format!("Index {} out of range, length is {}", i, len));
+            }
+            Ok(Place {
+                ty: ele_ty.as_ref().clone(),
+                addr: Heap::get_arr_offset(obj, ele_size(obj), *i),
+            })
+        }
+        (_, Accessor::Field(name)) => Err(format!("No field {}", name)),
+        (_, Accessor::Index(_)) => Err(String::from("Only arrays can be indexed")),
+    }
+}
+
+/// follow managed pointers
+unsafe fn deref(place: Place) -> Place {
+    match place.ty {
+        BuiltinType::ByRef(inner) => deref(Place {
+            ty: *inner,
+            addr: ptr::read_unaligned(place.addr as *const *const u8),
+        }),
+        _ => place,
+    }
+}
+
+unsafe fn deref_obj(place: &Place) -> Result<*mut u8, String> {
+    let obj = ptr::read_unaligned(place.addr as *const *mut u8);
+    if obj.is_null() {
+        Err(String::from("Null reference"))
+    } else {
+        Ok(obj)
+    }
+}
+
+fn ele_size(arr: *mut u8) -> usize {
+    let ele_ty = unsafe { Heap::get_arr_ele_ty(arr).as_ref().unwrap() };
+    if ele_ty.ee_class.is_value {
+        ele_ty.basic_instance_size
+    } else {
+        REF_SIZE
+    }
+}
+
+fn find_field<'t>(ty: &'t Type, name: &str, str_pool: &Vec<String>) -> Result<&'t Field, String> {
+    instance_fields(ty)
+        .into_iter()
+        .find(|f| str_pool[f.name] == name)
+        .ok_or_else(|| format!("No field {} in {}", name, ty.fullname(str_pool)))
+}
+
+/// Instance fields of ty and its base types, base fields first, each in declaration order.
+/// A field redeclared in derived class shares the slot with the base one, only the derived one is kept
+fn instance_fields(ty: &Type) -> Vec<&Field> {
+    let mut fields: Vec<&Field> = Vec::new();
+    let mut ty_ptr = ty as *const Type;
+    while let Some(cur) = unsafe { ty_ptr.as_ref() } {
+        let mut cur_fields: Vec<&Field> = cur
+            .ee_class
+            .fields
+            .values()
+            .map(|f| unsafe { f.as_ref() })
+            .filter(|f| !f.attrib.is(FieldAttribFlag::Static))
+            .filter(|f| fields.iter().all(|derived| derived.offset != f.offset))
+            .collect();
+        cur_fields.sort_by_key(|f| f.index);
+        cur_fields.append(&mut fields);
+        fields = cur_fields;
+        ty_ptr = cur.extends;
+    }
+    fields
+}
+
+/// Strings are shown as their content
+fn fmt_slot(slot: &Slot) -> String {
+    if let SlotTag::Ref = slot.tag {
+        let obj = unsafe { slot.data.ptr_ };
+        if !obj.is_null() && Heap::is_str(obj) {
+            return format!("{:?}", Heap::get_chars(obj).collect::<String>());
+        }
+    }
+    slot.to_string()
+}
+
+/// value of type ty stored at addr
+unsafe fn fmt_typed(ty: &BuiltinType, addr: *const u8, str_pool: &Vec<String>) -> String {
+    match ty {
+        BuiltinType::Value(ty) => fmt_value(ty.as_ref(), addr, str_pool),
+        BuiltinType::Void
+        | BuiltinType::GenericInst(_, _, _)
+        | BuiltinType::Var(_)
+        | BuiltinType::MVar(_)
+        | BuiltinType::Unk => String::from("?"),
+        _ => {
+            let mut stack = EvalStack::new(1);
+            load(ty, addr, &mut stack);
+            fmt_slot(&stack.pop(None))
+        }
+    }
+}
+
+/// Fields of value in one line. Primitives like std/Int32 are shown as their only field
+unsafe fn fmt_value(ty: &Type, addr: *const u8, str_pool: &Vec<String>) -> String {
+    let fields = instance_fields(ty);
+    if fields.len() == 1
+        && str_pool[fields[0].name] == "value"
+        && ty.module.as_ref().fullname(str_pool) == "std"
+    {
+        return fmt_typed(&fields[0].ty, addr.wrapping_add(fields[0].offset), str_pool);
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            format!(
+                "{}: {}",
+                str_pool[f.name],
+                fmt_typed(&f.ty, addr.wrapping_add(f.offset), str_pool)
+            )
+        })
+        .collect();
+    format!("{{ {} }}", fields.join(", "))
+}
+
+/// Fields of an object or value, one per line
+unsafe fn fmt_fields(ty: &Type, addr: *const u8, str_pool: &Vec<String>) -> String {
+    let mut ret = format!("{} {{\n", ty.fullname(str_pool));
+    for f in instance_fields(ty) {
+        ret.push_str(&format!(
+            "    {}: {}\n",
+            str_pool[f.name],
+            fmt_typed(&f.ty, addr.wrapping_add(f.offset), str_pool)
+        ));
+    }
+    ret.push('}');
+    ret
+}
+
+unsafe fn examine(place: &Place, str_pool: &Vec<String>) -> String {
+    let place = deref(Place {
+        ty: place.ty.clone(),
+        addr: place.addr,
+    });
+    match &place.ty {
+        BuiltinType::Value(ty) => fmt_fields(ty.as_ref(), place.addr, str_pool),
+        BuiltinType::Class(_) | BuiltinType::String | BuiltinType::SZArray(_) => {
+            let obj = match deref_obj(&place) {
+                Ok(obj) => obj,
+                Err(_) => return String::from("null"),
+            };
+            let ty = Heap::get_vtbl_ptr(obj).as_ref().unwrap();
+            if Heap::is_str(obj) {
+                format!(
+                    "{} {:?}",
+                    ty.fullname(str_pool),
+                    Heap::get_chars(obj).collect::<String>()
+                )
+            } else if Heap::is_arr(obj) {
+                let ele_ty = Heap::get_arr_ele_ty(obj).as_ref().unwrap();
+                let len = Heap::get_arr_len(obj);
+                let mut ret = format!("{}[{}] {{\n", ele_ty.fullname(str_pool), len);
+                for i in 0..len.min(MAX_ELES_SHOWN) {
+                    let ele = Heap::get_arr_offset(obj, ele_size(obj), i);
+                    let ele = if ele_ty.ee_class.is_value {
+                        fmt_value(ele_ty, ele, str_pool)
+                    } else {
+                        fmt_slot(&Slot::new_ref(*(ele as *const *mut u8)))
+                    };
+                    ret.push_str(&format!("    [{}] {}\n", i, ele));
+                }
+                if len > MAX_ELES_SHOWN {
+                    ret.push_str("    ...\n");
+                }
+                ret.push('}');
+                ret
+            } else {
+                fmt_fields(ty, obj, str_pool)
+            }
+        }
+        ty => fmt_typed(ty, place.addr, str_pool),
+    }
+}
@@ -0,0 +1,235 @@
+use super::super::data::{BuiltinType, Type};
+use super::super::heap::Heap;
+use super::super::loader::resolve_type;
+use super::super::shared_mem::SharedMem;
+use super::super::stack::{EndFinally, FinallyCtx};
+use super::TExecutor;
+
+use xir::code::ExceptionClauseKind;
+
+use std::ptr;
+
+/// Runtime errors that are turned into exceptions
+pub enum Fault {
+    NullReference,
+    /// len, idx
+    IndexOutOfRange(usize, isize),
+    DivideByZero,
+    Overflow,
+    /// fullname of the type of obj, fullname of target type
+    InvalidCast(String, String),
+}
+
+/// exit code when the thread is terminated by an unhandled exception
+pub const UNHANDLED_EXCEPTION_EXIT_CODE: isize = -1;
+
+/// whether ty is class or derived from class
+fn is_subclass_of(ty: *const Type, class: *const Type) -> bool {
+    let mut ty = ty;
+    while let Some(ty_ref) = unsafe { ty.as_ref() } {
+        if ptr::eq(ty, class) {
+            return true;
+        }
+        ty = ty_ref.extends;
+    }
+    false
+}
+
+/// addr of std::Exception.message field of exception object
+fn get_message_addr(exception: *mut u8, mem: &SharedMem) -> *mut u8 {
+    let exception_class = unsafe { mem.exception_class.as_ref().unwrap() };
+    let field = exception_class
+        .ee_class
+        .fields
+        .values()
+        .map(|f| unsafe { f.as_ref() })
+        .find(|f| mem.str_pool[f.name] == "message")
+        .unwrap();
+    exception.wrapping_add(field.offset)
+}
+
+impl<'m> TExecutor<'m> {
+    /// throw exception from the inst just executed in current frame
+    pub fn throw(&mut self, exception: *mut u8, mem: &SharedMem) {
+        let from = self.states.last().unwrap().ip - 1;
+        // frames are popped or truncated by handlers during unwinding
+        let trace = self.stack_trace(mem);
+        self.unwind(exception, trace, from, 0, mem);
+    }
+
+    /// create the exception of fault and throw it
+    pub fn throw_fault(&mut self, fault: Fault, mem: &mut SharedMem) {
+        let (class, msg) = match fault {
+            Fault::NullReference => (
+                mem.null_reference_exception_class,
+                String::from("Object reference not set to an instance of an object"),
+            ),
+            Fault::IndexOutOfRange(len, idx) => (
+                mem.index_out_of_range_exception_class,
+                format!("Accessing array of length {} with index {}", len, idx),
+            ),
+            Fault::DivideByZero => (
+                mem.divide_by_zero_exception_class,
+                String::from("Attempted to divide by zero"),
+            ),
+            Fault::Overflow => (
+                mem.overflow_exception_class,
+                String::from("Arithmetic operation resulted in an overflow"),
+            ),
+            Fault::InvalidCast(from, to) => (
+                mem.invalid_cast_exception_class,
+                format!("Unable to cast object of type {} to type {}", from, to),
+            ),
+        };
+
+        // msg is kept in scratch roots so that it is visible to gc during allocation of exception
+        let msg = self.alloc(mem, |mem| unsafe {
+            mem.heap.new_str_from_str(mem.str_class, &msg)
+        });
+        self.scratch_roots.push(msg);
+        let exception = self.alloc(mem, |mem| unsafe { mem.new_obj(class.as_ref().unwrap()) });
+        let msg = self.scratch_roots.pop().unwrap();
+
+        let msg_addr = get_message_addr(exception, mem);
+        unsafe {
+            ptr::write_unaligned(msg_addr as *mut *mut u8, msg);
+        }
+        mem.heap.write_barrier_ref(msg_addr);
+
+        self.throw(exception, mem);
+    }
+
+    /// Search handlers of exception thrown at offset from, starting at clause start_clause of current frame.
+    ///
+    /// Frames without a matching handler are popped.
+    /// All frames are popped if exception is unhandled, and trace captured when it was thrown is printed
+    fn unwind(
+        &mut self,
+        exception: *mut u8,
+        trace: String,
+        from: usize,
+        start_clause: usize,
+        mem: &SharedMem,
+    ) {
+        let exception_ty = Heap::get_vtbl_ptr(exception);
+        let mut from = from;
+        let mut start_clause = start_clause;
+        for frame in (0..self.states.len()).rev() {
+            let state = &self.states[frame];
+            let ctx = unsafe { state.method.ctx.as_ref() }.expect_il();
+            let excepts = &ctx.ir_file.codes[state.method_impl.index].excepts;
+            let handler = excepts
+                .iter()
+                .enumerate()
+                .skip(start_clause)
+                .find(|(_, clause)| {
+                    clause.try_contains(from as u32)
+                        && match clause.kind {
+                            ExceptionClauseKind::Catch => {
+                                match resolve_type(clause.class_tok, state.method, &mem.str_pool) {
+                                    BuiltinType::Class(class) => {
+                                        is_subclass_of(exception_ty, class.as_ptr())
+                                    }
+                                    _ => unreachable!(),
+                                }
+                            }
+                            ExceptionClauseKind::Finally => true,
+                        }
+                });
+
+            if let Some((i, clause)) = handler {
+                // frames above have no handler
+                self.states.truncate(frame + 1);
+                let cur_state = self.states.last_mut().unwrap();
+                // finally handlers that do not enclose the new handler are abandoned
+                cur_state
+                    .finally_ctx
+                    .retain(|f| excepts[f.clause].handler_contains(clause.handler_offset));
+                cur_state.eval_stack.clear();
+                match clause.kind {
+                    ExceptionClauseKind::Catch => cur_state.eval_stack.push_ptr(exception),
+                    ExceptionClauseKind::Finally => cur_state.finally_ctx.push(FinallyCtx {
+                        clause: i,
+                        from,
+                        then: EndFinally::Throw(exception, trace),
+                    }),
+                }
+                cur_state.ip = clause.handler_offset as usize;
+                return;
+            }
+
+            if frame != 0 {
+                // ip of caller is after the call inst
+                from = self.states[frame - 1].ip - 1;
+                start_clause = 0;
+            }
+        }
+
+        // unhandled
+        let msg =
+            unsafe { ptr::read_unaligned(get_message_addr(exception, mem) as *const *mut u8) };
+        eprint!(
+            "Unhandled exception {}: {}\n{}",
+            unsafe { exception_ty.as_ref().unwrap() }.fullname(&mem.str_pool),
+            if msg.is_null() {
+                String::new()
+            } else {
+                Heap::get_chars(msg).collect::<String>()
+            },
+            trace
+        );
+        self.states.clear();
+    }
+
+    /// Leave protected regions from offset from to target, starting at clause start_clause.
+    ///
+    /// Finally handlers of the regions left are executed
+    fn leave_to(&mut self, from: usize, target: usize, start_clause: usize) {
+        let cur_state = self.states.last_mut().unwrap();
+        let ctx = unsafe { cur_state.method.ctx.as_ref() }.expect_il();
+        let excepts = &ctx.ir_file.codes[cur_state.method_impl.index].excepts;
+        cur_state.eval_stack.clear();
+        for (i, clause) in excepts.iter().enumerate().skip(start_clause) {
+            if let ExceptionClauseKind::Finally = clause.kind {
+                if clause.try_contains(from as u32) && !clause.try_contains(target as u32) {
+                    cur_state.finally_ctx.push(FinallyCtx {
+                        clause: i,
+                        from,
+                        then: EndFinally::Leave(target),
+                    });
+                    cur_state.ip = clause.handler_offset as usize;
+                    return;
+                }
+            }
+        }
+        cur_state.ip = target;
+    }
+
+    /// exec leave inst whose operand has been consumed
+    pub fn leave(&mut self, offset: i32) {
+        let ip = self.states.last().unwrap().ip;
+        self.leave_to(ip - 1, (ip as i32 + offset) as usize, 0);
+    }
+
+    pub fn end_finally(&mut self, mem: &SharedMem) {
+        let finally_ctx = self
+            .states
+            .last_mut()
+            .unwrap()
+            .finally_ctx
+            .pop()
+            .expect("endfinally outside finally handler");
+        match finally_ctx.then {
+            EndFinally::Leave(target) => {
+                self.leave_to(finally_ctx.from, target, finally_ctx.clause + 1)
+            }
+            EndFinally::Throw(exception, trace) => self.unwind(
+                exception,
+                trace,
+                finally_ctx.from,
+                finally_ctx.clause + 1,
+                mem,
+            ),
+        }
+    }
+}
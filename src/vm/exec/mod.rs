@@ -1,25 +1,42 @@
 mod arr;
+mod cast;
+mod debugger;
+mod except;
 mod fld;
 pub mod internal_calls;
 mod op;
 
-use super::data::{BuiltinType, MethodDesc, MethodILImpl, MethodImpl, MethodNativeImpl, REF_SIZE};
+use super::data::{BuiltinType, MethodDesc, MethodILImpl, MethodImpl, MethodNativeImpl, TypedAddr};
 use super::heap::Heap;
+use super::loader::{resolve_method, resolve_type};
 use super::shared_mem::SharedMem;
-use super::stack::{ActivationRecord, Args, EvalStack, ILocals, Locals, Slot, SlotTag};
+use super::stack::{ActivationRecord, Args, EndFinally, EvalStack, ILocals, Locals, Slot, SlotTag};
+use except::Fault;
+
+pub use debugger::Debugger;
+pub use except::UNHANDLED_EXCEPTION_EXIT_CODE;
 
 use xir::attrib::MethodAttribFlag;
-use xir::tok::{get_tok_tag, TokTag};
 
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 
 pub struct TExecutor<'m> {
     states: Vec<ActivationRecord<'m>>,
+    /// attached by xix --debug, consulted before each inst
+    debugger: Option<Box<Debugger>>,
+    /// objects referenced only by the VM itself, e.g. the message of a fault
+    /// while its exception is allocated. Eval stacks cannot be used since they are bounded by max_stack
+    scratch_roots: Vec<*mut u8>,
 }
 
 impl<'m> TExecutor<'m> {
     pub fn new(entry: *const MethodDesc) -> TExecutor<'m> {
-        let mut ret = TExecutor { states: Vec::new() };
+        let mut ret = TExecutor {
+            states: Vec::new(),
+            debugger: None,
+            scratch_roots: Vec::new(),
+        };
         // currently executor entry has no arguments
         let entry_ref = unsafe { entry.as_ref().unwrap() };
         ret.call(
@@ -38,23 +55,95 @@ impl<'m> TExecutor<'m> {
         method: &'m MethodDesc,
         il_impl: &'m MethodILImpl,
     ) {
-        // Currently there is no verification of the arg type
+        // Arg types have been checked by verifier when loading
         let ctx = unsafe { method.ctx.as_ref() }.expect_il();
-        let insts = &ctx.ir_file.codes[il_impl.index].insts;
+        let code = &ctx.ir_file.codes[il_impl.index];
         self.states.push(ActivationRecord {
             method,
             method_impl: il_impl,
-            insts,
+            insts: &code.insts,
             args,
             ret_addr,
-            eval_stack: EvalStack::new(),
+            eval_stack: EvalStack::new(code.max_stack as usize),
             locals: Locals::new(method),
             ip: 0,
+            finally_ctx: Vec::new(),
         });
     }
 
-    pub fn run(&mut self, mem: &'m mut SharedMem) -> isize {
+    /// alloc by f, if heap is exhausted, collect garbage and retry
+    fn alloc<F>(&mut self, mem: &mut SharedMem, f: F) -> *mut u8
+    where
+        F: Fn(&mut SharedMem) -> *mut u8,
+    {
+        let addr = f(mem);
+        if !addr.is_null() {
+            return addr;
+        }
+        // try minor gc first, then major gc which may grow the heap
+        for full in [false, true].iter() {
+            self.collect_garbage(mem, *full);
+            let addr = f(mem);
+            if !addr.is_null() {
+                return addr;
+            }
+        }
+        panic!("Out of memory");
+    }
+
+    fn collect_garbage(&mut self, mem: &mut SharedMem, full: bool) {
+        let states = &mut self.states;
+        let scratch_roots = &mut self.scratch_roots;
+        mem.gc(full, |visitor| {
+            for obj in scratch_roots.iter_mut() {
+                unsafe { visitor.visit_ref(obj as *mut *mut u8) };
+            }
+            for state in states.iter_mut() {
+                state.args.visit_refs(visitor);
+                state.locals.visit_refs(visitor);
+                state.eval_stack.visit_refs(visitor);
+                for f in state.finally_ctx.iter_mut() {
+                    if let EndFinally::Throw(exception, _) = &mut f.then {
+                        unsafe { visitor.visit_ref(exception as *mut *mut u8) };
+                    }
+                }
+            }
+        });
+    }
+
+    /// Managed stack trace of this thread, innermost frame first
+    pub fn stack_trace(&self, mem: &SharedMem) -> String {
+        let mut trace = String::new();
+        for state in self.states.iter().rev() {
+            // ip has passed the opcode of current inst
+            trace.push_str(&format!(
+                "    at {}\n",
+                frame_desc(state, &mem.str_pool, state.ip.saturating_sub(1))
+            ));
+        }
+        trace
+    }
+
+    pub fn run(&mut self, mem: &mut SharedMem) -> isize {
+        // states are only inspected before the panic is propagated
+        match panic::catch_unwind(AssertUnwindSafe(|| self.exec(mem))) {
+            Ok(ret) => ret,
+            Err(e) => {
+                eprint!("Stack trace:\n{}", self.stack_trace(mem));
+                panic::resume_unwind(e);
+            }
+        }
+    }
+
+    fn exec(&mut self, mem: &mut SharedMem) -> isize {
         loop {
+            if self.states.is_empty() {
+                // all frames are popped by an unhandled exception
+                return UNHANDLED_EXCEPTION_EXIT_CODE;
+            }
+            if self.debugger.is_some() {
+                self.debug_hook(mem);
+            }
             let code = self.states.last_mut().unwrap().consume_u8();
             match code {
                 // nop
@@ -205,6 +294,24 @@ impl<'m> TExecutor<'m> {
                     let v = cur_state.consume_i32();
                     cur_state.eval_stack.push_i32(v);
                 }
+                // ldc.i8
+                0x21 => {
+                    let cur_state = self.states.last_mut().unwrap();
+                    let v = cur_state.consume_i64();
+                    cur_state.eval_stack.push_i64(v);
+                }
+                // ldc.r4
+                0x22 => {
+                    let cur_state = self.states.last_mut().unwrap();
+                    let v = cur_state.consume_f32();
+                    cur_state.eval_stack.push_f32(v);
+                }
+                // ldc.r8
+                0x23 => {
+                    let cur_state = self.states.last_mut().unwrap();
+                    let v = cur_state.consume_f64();
+                    cur_state.eval_stack.push_f64(v);
+                }
                 // dup
                 0x25 => self.states.last_mut().unwrap().eval_stack.dup(),
                 // pop
@@ -215,17 +322,8 @@ impl<'m> TExecutor<'m> {
                 0x28 => {
                     let cur_state = self.states.last_mut().unwrap();
                     let tok = cur_state.consume_u32();
-                    let ctx = unsafe { cur_state.method.ctx.as_ref().expect_il() };
-
-                    let (tag, idx) = get_tok_tag(tok);
-
-                    let callee = match tag {
-                        TokTag::MethodDef => ctx.methods[idx as usize - 1].as_ref(),
-                        TokTag::MemberRef => unsafe {
-                            ctx.memberref[idx as usize - 1].expect_method().as_ref()
-                        },
-                        _ => unimplemented!(),
-                    };
+                    let callee =
+                        unsafe { resolve_method(tok, cur_state.method, &mem.str_pool).as_ref() };
 
                     let mut args = Args::new(callee);
                     args.fill_args(&mut cur_state.eval_stack);
@@ -248,6 +346,10 @@ impl<'m> TExecutor<'m> {
                         MethodImpl::Runtime(runtime_impl) => {
                             runtime_impl.func.call(args, ret_addr, mem);
                         }
+                        MethodImpl::Abstract => panic!(
+                            "Cannot call abstract method {}",
+                            callee.str_desc_with_fullname(&mem.str_pool)
+                        ),
                     }
                 }
                 // ret
@@ -264,6 +366,8 @@ impl<'m> TExecutor<'m> {
                         | BuiltinType::Char
                         | BuiltinType::U1
                         | BuiltinType::I1
+                        | BuiltinType::U2
+                        | BuiltinType::I2
                         | BuiltinType::U4
                         | BuiltinType::I4
                         | BuiltinType::U8
@@ -294,9 +398,17 @@ impl<'m> TExecutor<'m> {
                                 *state.ret_addr = ret_v;
                             }
                         }
-                        BuiltinType::Value(_) => unimplemented!(),
-                        BuiltinType::GenericInst(_, _, _) => todo!(),
-                        BuiltinType::Unk => unreachable!(),
+                        BuiltinType::Value(ty) => {
+                            // copy value into the space allocated by caller before ret slot
+                            let addr = (cur_state.ret_addr as *mut u8)
+                                .wrapping_sub(unsafe { ty.as_ref() }.basic_instance_size);
+                            cur_state.eval_stack.pop(Some(TypedAddr { ty, addr }));
+                            self.states.pop();
+                        }
+                        BuiltinType::GenericInst(_, _, _)
+                        | BuiltinType::Var(_)
+                        | BuiltinType::MVar(_)
+                        | BuiltinType::Unk => unreachable!(),
                     }
                 }
                 // br
@@ -336,39 +448,98 @@ impl<'m> TExecutor<'m> {
                 0x3D => op::exec_bgt(self.states.last_mut().unwrap()),
                 0x3E => op::exec_ble(self.states.last_mut().unwrap()),
                 0x3F => op::exec_blt(self.states.last_mut().unwrap()),
+                0x40 => op::exec_bne(self.states.last_mut().unwrap()),
+                0x46 => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::I1)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x47 => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::U1)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x48 => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::I2)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x49 => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::U2)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x4A => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::I4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x4B => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::U4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x4C => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::I8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x4D => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::INative)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x4E => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::R4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x4F => fld::exec_ldind(self.states.last_mut().unwrap(), &BuiltinType::R8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x52 => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::I1)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x53 => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::I2)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x54 => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::I4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x55 => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::I8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x56 => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::R4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x57 => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::R8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
                 0x58 => op::exec_add(self.states.last_mut().unwrap()),
                 0x59 => op::exec_sub(self.states.last_mut().unwrap()),
                 0x5A => op::exec_mul(self.states.last_mut().unwrap()),
-                0x5B => op::exec_div(self.states.last_mut().unwrap()),
-                0x5D => op::exec_rem(self.states.last_mut().unwrap()),
+                0x5B => op::exec_div(self.states.last_mut().unwrap())
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x5C => op::exec_div_un(self.states.last_mut().unwrap())
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x5D => op::exec_rem(self.states.last_mut().unwrap())
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x5E => op::exec_rem_un(self.states.last_mut().unwrap())
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x5F => op::exec_and(self.states.last_mut().unwrap()),
+                0x60 => op::exec_or(self.states.last_mut().unwrap()),
+                0x61 => op::exec_xor(self.states.last_mut().unwrap()),
+                0x62 => op::exec_shift(self.states.last_mut().unwrap(), true, false),
+                0x63 => op::exec_shift(self.states.last_mut().unwrap(), false, false),
+                0x64 => op::exec_shift(self.states.last_mut().unwrap(), false, true),
                 0x65 => op::exec_neg(self.states.last_mut().unwrap()),
+                0x66 => op::exec_not(self.states.last_mut().unwrap()),
+                0x67 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::I1),
+                0x68 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::I2),
+                0x69 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::I4),
+                0x6A => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::I8),
+                0x6B => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::R4),
+                0x6C => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::R8),
+                0x6D => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::U4),
+                0x6E => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::U8),
                 // callvirt
                 0x6F => {
                     let cur_state = self.states.last_mut().unwrap();
                     let tok = cur_state.consume_u32();
-                    let ctx = unsafe { cur_state.method.ctx.as_ref().expect_il() };
-
-                    let (tag, idx) = get_tok_tag(tok);
-
-                    let mut callee = match tag {
-                        TokTag::MethodDef => ctx.methods[idx as usize - 1].as_ref(),
-                        TokTag::MemberRef => unsafe {
-                            ctx.memberref[idx as usize - 1].expect_method().as_ref()
-                        },
-                        _ => unimplemented!(),
-                    };
+                    let mut callee =
+                        unsafe { resolve_method(tok, cur_state.method, &mem.str_pool).as_ref() };
 
                     let mut args = Args::new(callee);
                     args.fill_args(&mut cur_state.eval_stack);
                     if let Some(self_ptr) = args.get_self() {
                         // unlike call, callvirt must make sure self pointer is not NULL
-                        assert!(!self_ptr.is_null());
+                        if self_ptr.is_null() {
+                            self.throw_fault(Fault::NullReference, mem);
+                            continue;
+                        }
 
                         // If calle is virtual, use dynamic dispatching
                         if callee.attrib.is(MethodAttribFlag::Virtual) {
                             let callee_ptr = Heap::get_vtbl_ptr(self_ptr);
-                            callee =
-                                unsafe { callee_ptr.as_ref().unwrap().vtbl[callee.slot].as_ref() };
+                            let self_ty = unsafe { callee_ptr.as_ref().unwrap() };
+                            callee = if unsafe { callee.parent.as_ref().unwrap() }.is_interface() {
+                                // interface method is dispatched through interface map
+                                unsafe {
+                                    self_ty
+                                        .interface_map
+                                        .get(&(callee as *const MethodDesc))
+                                        .unwrap()
+                                        .as_ref()
+                                }
+                            } else {
+                                unsafe { self_ty.vtbl[callee.slot].as_ref() }
+                            };
                         }
                     }
                     let ret_addr = cur_state.eval_stack.alloc_ret(&callee.ret.ty);
@@ -377,7 +548,7 @@ impl<'m> TExecutor<'m> {
                         MethodImpl::IL(il_impl) => {
                             self.call(args, ret_addr, callee, il_impl);
                         }
-                        MethodImpl::Native(_) => panic!(),
+                        MethodImpl::Native(_) | MethodImpl::Abstract => panic!(),
                         MethodImpl::Runtime(runtime_impl) => {
                             runtime_impl.func.call(args, ret_addr, mem);
                         }
@@ -388,25 +559,17 @@ impl<'m> TExecutor<'m> {
                     let cur_state = self.states.last_mut().unwrap();
                     let literal_idx = cur_state.consume_u32() as usize;
                     let ctx = unsafe { cur_state.method.ctx.as_ref().expect_il() };
+                    let s = ctx.usr_str_heap[literal_idx];
 
-                    let str_ptr = unsafe { mem.new_str_from_str(ctx.usr_str_heap[literal_idx]) };
-                    cur_state.eval_stack.push_ptr(str_ptr);
+                    let str_ptr = self.alloc(mem, |mem| unsafe { mem.new_str_from_str(s) });
+                    self.states.last_mut().unwrap().eval_stack.push_ptr(str_ptr);
                 }
                 // newobj
                 0x73 => {
                     let cur_state = self.states.last_mut().unwrap();
                     let tok = cur_state.consume_u32();
-                    let ctx = unsafe { cur_state.method.ctx.as_ref().expect_il() };
-
-                    let (tag, idx) = get_tok_tag(tok);
-
-                    let callee = match tag {
-                        TokTag::MethodDef => ctx.methods[idx as usize - 1].as_ref(),
-                        TokTag::MemberRef => unsafe {
-                            ctx.memberref[idx as usize - 1].expect_method().as_ref()
-                        },
-                        _ => unimplemented!(),
-                    };
+                    let callee =
+                        unsafe { resolve_method(tok, cur_state.method, &mem.str_pool).as_ref() };
                     // TODO: make sure callee is .ctor
 
                     if callee.is_static() {
@@ -419,15 +582,17 @@ impl<'m> TExecutor<'m> {
                     let mut args = Args::new(callee);
                     if ty.ee_class.is_value {
                         // new value type on stack
+                        let cur_state = self.states.last_mut().unwrap();
                         args.fill_args_except_self(&mut cur_state.eval_stack);
                         unsafe {
                             let value_addr = cur_state.eval_stack.alloc_value(ty, ptr::null());
                             args.store_slot(0, Slot::new_managed(value_addr));
                         }
                     } else {
-                        // Alloc space at heap
+                        // Alloc space at heap, ctor args are still on eval stack during gc
+                        let instance_addr = self.alloc(mem, |mem| unsafe { mem.new_obj(ty) });
+                        let cur_state = self.states.last_mut().unwrap();
                         unsafe {
-                            let instance_addr = mem.new_obj(ty);
                             args.store_slot(0, Slot::new_ref(instance_addr));
                             args.fill_args_except_self(&mut cur_state.eval_stack);
                             cur_state.eval_stack.push_slot(Slot::new_ref(instance_addr));
@@ -441,28 +606,172 @@ impl<'m> TExecutor<'m> {
                         callee.method_impl.expect_il(),
                     );
                 }
-                0x7B => fld::exec_ldfld(self.states.last_mut().unwrap()),
-                0x7C => fld::exec_ldflda(self.states.last_mut().unwrap()),
-                0x7D => fld::exec_stfld(self.states.last_mut().unwrap()),
-                0x7E => fld::exec_ldsfld(self.states.last_mut().unwrap()),
-                0x7F => fld::exec_ldsflda(self.states.last_mut().unwrap()),
-                0x80 => fld::exec_stsfld(self.states.last_mut().unwrap()),
-                0x8D => arr::exec_newarr(self.states.last_mut().unwrap(), mem),
-                0x8E => arr::exec_ldlen(self.states.last_mut().unwrap()),
-                0x8F => arr::exec_ldelema(self.states.last_mut().unwrap()),
-                0x94 => arr::exec_ldelem_i32(self.states.last_mut().unwrap()),
-                0x9A => arr::exec_ldelem_ref(self.states.last_mut().unwrap()),
-                0x9E => arr::exec_stelem_i32(self.states.last_mut().unwrap()),
-                0xA2 => arr::exec_stelem_ref(self.states.last_mut().unwrap()),
-                0xA3 => arr::exec_ldelem(self.states.last_mut().unwrap()),
-                0xA4 => arr::exec_stelem(self.states.last_mut().unwrap()),
+                0x74 => cast::exec_castclass(self.states.last_mut().unwrap(), mem)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x75 => cast::exec_isinst(self.states.last_mut().unwrap(), mem),
+                0x76 => op::exec_conv_r_un(self.states.last_mut().unwrap()),
+                // throw
+                0x7A => {
+                    let exception = unsafe {
+                        self.states
+                            .last_mut()
+                            .unwrap()
+                            .eval_stack
+                            .pop(None)
+                            .expect_ref()
+                    };
+                    if exception.is_null() {
+                        self.throw_fault(Fault::NullReference, mem);
+                    } else {
+                        self.throw(exception, mem);
+                    }
+                }
+                0x7B => fld::exec_ldfld(self.states.last_mut().unwrap(), &mem.str_pool)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x7C => fld::exec_ldflda(self.states.last_mut().unwrap(), &mem.str_pool)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x7D => fld::exec_stfld(
+                    self.states.last_mut().unwrap(),
+                    &mem.str_pool,
+                    &mut mem.heap,
+                )
+                .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x7E => fld::exec_ldsfld(self.states.last_mut().unwrap(), &mem.str_pool),
+                0x7F => fld::exec_ldsflda(self.states.last_mut().unwrap(), &mem.str_pool),
+                0x80 => fld::exec_stsfld(self.states.last_mut().unwrap(), &mem.str_pool),
+                0x82 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I1, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x83 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I2, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x84 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I4, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x85 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I8, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x86 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U1, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x87 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U2, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x88 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U4, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x89 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U8, true)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x8A => {
+                    op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::INative, true)
+                        .unwrap_or_else(|fault| self.throw_fault(fault, mem))
+                }
+                0x8B => {
+                    op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::UNative, true)
+                        .unwrap_or_else(|fault| self.throw_fault(fault, mem))
+                }
+                0x8D => {
+                    let (ele_ty, len) = arr::decode_newarr(self.states.last_mut().unwrap(), mem);
+                    let addr = self.alloc(mem, |mem| unsafe { mem.new_arr(ele_ty, len) });
+                    self.states.last_mut().unwrap().eval_stack.push_ptr(addr);
+                }
+                0x8E => arr::exec_ldlen(self.states.last_mut().unwrap())
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x8F => arr::exec_ldelema(self.states.last_mut().unwrap(), &mem.str_pool)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x90 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I1)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x91 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::U1)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x92 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I2)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x93 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::U2)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x94 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x95 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::U4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x96 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x97 => {
+                    arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::INative)
+                        .unwrap_or_else(|fault| self.throw_fault(fault, mem))
+                }
+                0x98 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::R4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x99 => arr::exec_ldelem_prim(self.states.last_mut().unwrap(), &BuiltinType::R8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x9A => arr::exec_ldelem_ref(self.states.last_mut().unwrap())
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x9B => {
+                    arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::INative)
+                        .unwrap_or_else(|fault| self.throw_fault(fault, mem))
+                }
+                0x9C => arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I1)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x9D => arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I2)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x9E => arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0x9F => arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::I8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xA0 => arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::R4)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xA1 => arr::exec_stelem_prim(self.states.last_mut().unwrap(), &BuiltinType::R8)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xA2 => arr::exec_stelem_ref(self.states.last_mut().unwrap(), &mut mem.heap)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xA3 => arr::exec_ldelem(self.states.last_mut().unwrap(), &mem.str_pool)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xA4 => arr::exec_stelem(
+                    self.states.last_mut().unwrap(),
+                    &mem.str_pool,
+                    &mut mem.heap,
+                )
+                .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB3 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I1, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB4 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U1, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB5 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I2, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB6 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U2, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB7 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I4, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB8 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U4, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xB9 => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::I8, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xBA => op::exec_conv_ovf(self.states.last_mut().unwrap(), &BuiltinType::U8, false)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xD1 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::U2),
+                0xD2 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::U1),
+                0xD3 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::INative),
+                0xD4 => op::exec_conv_ovf(
+                    self.states.last_mut().unwrap(),
+                    &BuiltinType::INative,
+                    false,
+                )
+                .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xD5 => op::exec_conv_ovf(
+                    self.states.last_mut().unwrap(),
+                    &BuiltinType::UNative,
+                    false,
+                )
+                .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                // endfinally
+                0xDC => self.end_finally(mem),
+                // leave
+                0xDD => {
+                    let offset = self.states.last_mut().unwrap().consume_i32();
+                    self.leave(offset);
+                }
+                0xDF => fld::exec_stind(self.states.last_mut().unwrap(), &BuiltinType::INative)
+                    .unwrap_or_else(|fault| self.throw_fault(fault, mem)),
+                0xE0 => op::exec_conv(self.states.last_mut().unwrap(), &BuiltinType::UNative),
 
                 0xFE => {
                     let inner_code = self.states.last_mut().unwrap().consume_u8();
                     match inner_code {
                         0x01 => op::exec_ceq(self.states.last_mut().unwrap()),
                         0x02 => op::exec_cgt(self.states.last_mut().unwrap()),
+                        0x03 => op::exec_cgt_un(self.states.last_mut().unwrap()),
                         0x04 => op::exec_clt(self.states.last_mut().unwrap()),
+                        0x05 => op::exec_clt_un(self.states.last_mut().unwrap()),
                         // ldloc
                         0x0C => {
                             let cur_state = self.states.last_mut().unwrap();
@@ -491,30 +800,11 @@ impl<'m> TExecutor<'m> {
                         0x15 => {
                             let cur_state = self.states.last_mut().unwrap();
                             let tok = cur_state.consume_u32();
-                            let ctx = unsafe { cur_state.method.ctx.as_ref().expect_il() };
-
-                            let (tag, idx) = get_tok_tag(tok);
-
-                            let ty = match tag {
-                                TokTag::TypeDef => ctx.types[idx as usize - 1].as_ref(),
-                                TokTag::TypeRef => unsafe {
-                                    ctx.typerefs[idx as usize - 1].as_ref()
-                                },
-                                TokTag::TypeSpec => unimplemented!(),
-                                _ => unimplemented!(),
-                            };
+                            let ty = resolve_type(tok, cur_state.method, &mem.str_pool);
                             let dest = unsafe { cur_state.eval_stack.pop(None).expect_ptr() };
                             unsafe {
                                 // init value with all 0 if ty is value type else ldnull followed by stind.ref
-                                ptr::write_bytes(
-                                    dest,
-                                    0,
-                                    if ty.ee_class.is_value {
-                                        ty.basic_instance_size
-                                    } else {
-                                        REF_SIZE
-                                    },
-                                )
+                                ptr::write_bytes(dest, 0, ty.byte_size())
                             }
                         }
                         _ => panic!("Unknown inst 0xFE{:X}", inner_code),
@@ -526,3 +816,17 @@ impl<'m> TExecutor<'m> {
         }
     }
 }
+
+/// Method and IL offset of a frame, with source location of offset if debug info is available
+fn frame_desc(state: &ActivationRecord, str_pool: &Vec<String>, offset: usize) -> String {
+    let mut desc = format!(
+        "{} IL_{:04x}",
+        state.method.str_desc_with_fullname(str_pool),
+        state.ip
+    );
+    let module = unsafe { state.method.ctx.as_ref().expect_il() };
+    if let Some((file, line, col)) = module.source_location(state.method.index, offset as u32) {
+        desc.push_str(&format!(" ({}:{}:{})", file, line, col));
+    }
+    desc
+}
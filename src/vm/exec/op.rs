@@ -1,34 +1,89 @@
-use super::super::stack::{ActivationRecord, SlotTag};
+use super::super::data::BuiltinType;
+use super::super::stack::{ActivationRecord, EvalStack, Slot, SlotTag};
+use super::Fault;
 
 // III.1.5 Table III.2
+//
+// Integer ops wrap on overflow, f32 is promoted to f64 if the other operand is f64
 macro_rules! exec_numeric_op {
+    ($int_op: ident, $float_op: tt, $lhs: ident, $rhs: ident) => {
+        unsafe {
+            match (&$lhs.tag, &$rhs.tag) {
+                (SlotTag::I32, SlotTag::I32) => {
+                    $lhs.data.i32_ = $lhs.data.i32_.$int_op($rhs.data.i32_);
+                }
+                (SlotTag::I32, SlotTag::INative) => {
+                    $lhs.data.inative_ = ($lhs.data.i32_ as isize).$int_op($rhs.data.inative_);
+                    $lhs.tag = SlotTag::INative;
+                }
+                (SlotTag::I64, SlotTag::I64) => {
+                    $lhs.data.i64_ = $lhs.data.i64_.$int_op($rhs.data.i64_);
+                }
+                (SlotTag::INative, SlotTag::I32) => {
+                    $lhs.data.inative_ = $lhs.data.inative_.$int_op($rhs.data.i32_ as isize);
+                }
+                (SlotTag::INative, SlotTag::INative) => {
+                    $lhs.data.inative_ = $lhs.data.inative_.$int_op($rhs.data.inative_);
+                }
+                (SlotTag::F32, SlotTag::F32) => {
+                    $lhs.data.f32_ = $lhs.expect_f32() $float_op $rhs.data.f32_;
+                }
+                (SlotTag::F32, SlotTag::F64)
+                | (SlotTag::F64, SlotTag::F32)
+                | (SlotTag::F64, SlotTag::F64) => {
+                    $lhs.data.f64_ = $lhs.expect_f64() $float_op $rhs.expect_f64();
+                    $lhs.tag = SlotTag::F64;
+                }
+                (SlotTag::Managed, _) | (_, SlotTag::Managed) => unimplemented!(),
+                (l, r) => panic!("Cannot apply numeric op between {} and {}", l, r),
+            }
+        }
+    };
+}
+
+/// Table III.5, integer ops that treat operands as unsigned
+macro_rules! exec_unsigned_op {
+    ($op: ident, $lhs: ident, $rhs: ident) => {
+        unsafe {
+            match (&$lhs.tag, &$rhs.tag) {
+                (SlotTag::I32, SlotTag::I32) => {
+                    $lhs.data.i32_ = ($lhs.data.i32_ as u32).$op($rhs.data.i32_ as u32) as i32;
+                }
+                (SlotTag::I64, SlotTag::I64) => {
+                    $lhs.data.i64_ = ($lhs.data.i64_ as u64).$op($rhs.data.i64_ as u64) as i64;
+                }
+                (SlotTag::INative, SlotTag::INative) => {
+                    $lhs.data.inative_ =
+                        ($lhs.data.inative_ as usize).$op($rhs.data.inative_ as usize) as isize;
+                }
+                (l, r) => panic!("Cannot apply unsigned op between {} and {}", l, r),
+            }
+        }
+    };
+}
+
+/// Table III.5, bitwise ops on integers
+macro_rules! exec_bit_op {
     ($op: tt, $lhs: ident, $rhs: ident) => {
         unsafe {
-            match $lhs.tag {
-                SlotTag::I32 => match $rhs.tag {
-                    SlotTag::I32 => {
-                        $lhs.data.i32_ = $lhs.data.i32_ $op $rhs.data.i32_;
-                    }
-                    SlotTag::I64 => panic!("Cannot add between i32 and i64"),
-                    SlotTag::INative => {
-                        $lhs.data.inative_ = $lhs.data.i32_ as isize $op $rhs.data.inative_;
-                        $lhs.tag = SlotTag::INative;
-                    }
-                    SlotTag::F32 => panic!("Cannot add between float and int"),
-                    SlotTag::F64 => panic!("Cannot add between float and int"),
-                    SlotTag::Managed => unimplemented!(),
-                    SlotTag::Ref => panic!("Cannot add ref"),
-                    SlotTag::Value => panic!("Cannot cmp value"),
-                    SlotTag::Uninit => unreachable!(),
-                },
-                SlotTag::I64 => unimplemented!(),
-                SlotTag::INative => unimplemented!(),
-                SlotTag::F32 => unimplemented!(),
-                SlotTag::F64 => unimplemented!(),
-                SlotTag::Managed => unimplemented!(),
-                SlotTag::Ref => panic!("Cannot add ref"),
-                SlotTag::Value => panic!("Cannot add value"),
-                SlotTag::Uninit => unreachable!(),
+            match (&$lhs.tag, &$rhs.tag) {
+                (SlotTag::I32, SlotTag::I32) => {
+                    $lhs.data.i32_ = $lhs.expect_i32() $op $rhs.data.i32_;
+                }
+                (SlotTag::I32, SlotTag::INative) => {
+                    $lhs.data.inative_ = ($lhs.data.i32_ as isize) $op $rhs.data.inative_;
+                    $lhs.tag = SlotTag::INative;
+                }
+                (SlotTag::I64, SlotTag::I64) => {
+                    $lhs.data.i64_ = $lhs.expect_i64() $op $rhs.data.i64_;
+                }
+                (SlotTag::INative, SlotTag::I32) => {
+                    $lhs.data.inative_ = $lhs.expect_inative() $op ($rhs.data.i32_ as isize);
+                }
+                (SlotTag::INative, SlotTag::INative) => {
+                    $lhs.data.inative_ = $lhs.expect_inative() $op $rhs.data.inative_;
+                }
+                (l, r) => panic!("Cannot apply bitwise op between {} and {}", l, r),
             }
         }
     };
@@ -38,36 +93,45 @@ macro_rules! exec_numeric_op {
 macro_rules! exec_cmp_op {
     ($op: tt, $lhs: ident, $rhs: ident) => {
         unsafe {
-            match $lhs.tag {
-                SlotTag::I32 => match $rhs.tag {
-                    SlotTag::I32 => $lhs.data.i32_ $op $rhs.data.i32_,
-                    SlotTag::I64 => panic!("Cannot cmp between i32 and i64"),
-                    SlotTag::INative => ($lhs.data.i32_ as isize) $op $rhs.data.inative_,
-                    SlotTag::F32 => panic!("Cannot cmp between i32 and float"),
-                    SlotTag::F64 => panic!("Cannot cmp between i32 and double"),
-                    SlotTag::Managed => panic!("Cannot cmp between i32 and &"),
-                    SlotTag::Ref => panic!("Cannot cmp ref"),
-                    SlotTag::Value => panic!("Cannot cmp value"),
-                    SlotTag::Uninit => unreachable!(),
-                },
-                SlotTag::I64 => unimplemented!(),
-                SlotTag::INative => match $rhs.tag {
-                    SlotTag::I32 => $lhs.data.inative_ $op ($rhs.data.i32_ as isize),
-                    SlotTag::I64 => panic!("Cannot cmp between inative and i64"),
-                    SlotTag::INative => $lhs.data.inative_ $op $rhs.data.inative_,
-                    SlotTag::F32 => panic!("Cannot cmp between inative and float"),
-                    SlotTag::F64 => panic!("Cannot cmp between inative and double"),
-                    SlotTag::Managed => unimplemented!(),
-                    SlotTag::Ref => panic!("Cannot cmp ref"),
-                    SlotTag::Value => panic!("Cannot cmp value"),
-                    SlotTag::Uninit => unreachable!(),
-                }
-                SlotTag::F32 => unimplemented!(),
-                SlotTag::F64 => unimplemented!(),
-                SlotTag::Managed => unimplemented!(),
-                SlotTag::Ref => panic!("Cannot cmp ref"),
-                SlotTag::Value => panic!("Cannot cmp value"),
-                SlotTag::Uninit => unreachable!(),
+            match (&$lhs.tag, &$rhs.tag) {
+                (SlotTag::I32, SlotTag::I32) => $lhs.data.i32_ $op $rhs.data.i32_,
+                (SlotTag::I32, SlotTag::INative) => ($lhs.data.i32_ as isize) $op $rhs.data.inative_,
+                (SlotTag::I64, SlotTag::I64) => $lhs.data.i64_ $op $rhs.data.i64_,
+                (SlotTag::INative, SlotTag::I32) => $lhs.data.inative_ $op ($rhs.data.i32_ as isize),
+                (SlotTag::INative, SlotTag::INative) => $lhs.data.inative_ $op $rhs.data.inative_,
+                (SlotTag::F32, SlotTag::F32) => $lhs.data.f32_ $op $rhs.data.f32_,
+                (SlotTag::F32, SlotTag::F64) => ($lhs.data.f32_ as f64) $op $rhs.data.f64_,
+                (SlotTag::F64, SlotTag::F32) => $lhs.data.f64_ $op ($rhs.data.f32_ as f64),
+                (SlotTag::F64, SlotTag::F64) => $lhs.data.f64_ $op $rhs.data.f64_,
+                (SlotTag::Managed, _) => unimplemented!(),
+                (l, r) => panic!("Cannot cmp between {} and {}", l, r),
+            }
+        }
+    };
+}
+
+/// Table III.4, .un variants.
+///
+/// Integers are compared as unsigned, comparison on floats is true if they are unordered
+macro_rules! exec_cmp_un_op {
+    ($op: tt, $lhs: ident, $rhs: ident) => {
+        unsafe {
+            match (&$lhs.tag, &$rhs.tag) {
+                (SlotTag::I32, SlotTag::I32) => ($lhs.data.i32_ as u32) $op ($rhs.data.i32_ as u32),
+                (SlotTag::I64, SlotTag::I64) => ($lhs.data.i64_ as u64) $op ($rhs.data.i64_ as u64),
+                (SlotTag::INative, SlotTag::INative) => {
+                    ($lhs.data.inative_ as usize) $op ($rhs.data.inative_ as usize)
+                }
+                (SlotTag::Ref, SlotTag::Ref) => ($lhs.data.ptr_ as usize) $op ($rhs.data.ptr_ as usize),
+                (SlotTag::F32, SlotTag::F32) => {
+                    let (l, r) = ($lhs.data.f32_, $rhs.data.f32_);
+                    l.is_nan() || r.is_nan() || l $op r
+                }
+                (SlotTag::F32, SlotTag::F64) | (SlotTag::F64, SlotTag::F32) | (SlotTag::F64, SlotTag::F64) => {
+                    let (l, r) = ($lhs.expect_f64(), $rhs.expect_f64());
+                    l.is_nan() || r.is_nan() || l $op r
+                }
+                (l, r) => panic!("Cannot cmp between {} and {}", l, r),
             }
         }
     };
@@ -123,6 +187,16 @@ pub fn exec_blt(cur_state: &mut ActivationRecord) {
     }
 }
 
+pub fn exec_bne(cur_state: &mut ActivationRecord) {
+    let offset = cur_state.consume_i32();
+    let rhs = cur_state.eval_stack.pop(None);
+    let lhs = cur_state.eval_stack.pop(None);
+    let b = exec_cmp_op!(!=, lhs, rhs);
+    if b {
+        cur_state.ip = (cur_state.ip as i32 + offset) as usize;
+    }
+}
+
 pub fn exec_ceq(cur_state: &mut ActivationRecord) {
     let rhs = cur_state.eval_stack.pop(None);
     let lhs = cur_state.eval_stack.peek_mut().unwrap();
@@ -147,34 +221,90 @@ pub fn exec_clt(cur_state: &mut ActivationRecord) {
     lhs.tag = SlotTag::I32;
 }
 
+pub fn exec_cgt_un(cur_state: &mut ActivationRecord) {
+    let rhs = cur_state.eval_stack.pop(None);
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    let t = exec_cmp_un_op!(>, lhs, rhs);
+    lhs.data.i32_ = if t { 1 } else { 0 };
+    lhs.tag = SlotTag::I32;
+}
+
+pub fn exec_clt_un(cur_state: &mut ActivationRecord) {
+    let rhs = cur_state.eval_stack.pop(None);
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    let t = exec_cmp_un_op!(<, lhs, rhs);
+    lhs.data.i32_ = if t { 1 } else { 0 };
+    lhs.tag = SlotTag::I32;
+}
+
 pub fn exec_add(cur_state: &mut ActivationRecord) {
     let rhs = cur_state.eval_stack.pop(None);
     let lhs = cur_state.eval_stack.peek_mut().unwrap();
-    exec_numeric_op!(+, lhs, rhs);
+    exec_numeric_op!(wrapping_add, +, lhs, rhs);
 }
 
 pub fn exec_sub(cur_state: &mut ActivationRecord) {
     let rhs = cur_state.eval_stack.pop(None);
     let lhs = cur_state.eval_stack.peek_mut().unwrap();
-    exec_numeric_op!(-, lhs, rhs);
+    exec_numeric_op!(wrapping_sub, -, lhs, rhs);
 }
 
 pub fn exec_mul(cur_state: &mut ActivationRecord) {
     let rhs = cur_state.eval_stack.pop(None);
     let lhs = cur_state.eval_stack.peek_mut().unwrap();
-    exec_numeric_op!(*, lhs, rhs);
+    exec_numeric_op!(wrapping_mul, *, lhs, rhs);
+}
+
+/// whether rhs of integer div or rem is zero
+fn is_int_zero(rhs: &Slot) -> bool {
+    unsafe {
+        match rhs.tag {
+            SlotTag::I32 => rhs.data.i32_ == 0,
+            SlotTag::I64 => rhs.data.i64_ == 0,
+            SlotTag::INative => rhs.data.inative_ == 0,
+            _ => false,
+        }
+    }
+}
+
+pub fn exec_div(cur_state: &mut ActivationRecord) -> Result<(), Fault> {
+    let rhs = cur_state.eval_stack.pop(None);
+    if is_int_zero(&rhs) {
+        return Err(Fault::DivideByZero);
+    }
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    exec_numeric_op!(wrapping_div, /, lhs, rhs);
+    Ok(())
+}
+
+pub fn exec_rem(cur_state: &mut ActivationRecord) -> Result<(), Fault> {
+    let rhs = cur_state.eval_stack.pop(None);
+    if is_int_zero(&rhs) {
+        return Err(Fault::DivideByZero);
+    }
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    exec_numeric_op!(wrapping_rem, %, lhs, rhs);
+    Ok(())
 }
 
-pub fn exec_div(cur_state: &mut ActivationRecord) {
+pub fn exec_div_un(cur_state: &mut ActivationRecord) -> Result<(), Fault> {
     let rhs = cur_state.eval_stack.pop(None);
+    if is_int_zero(&rhs) {
+        return Err(Fault::DivideByZero);
+    }
     let lhs = cur_state.eval_stack.peek_mut().unwrap();
-    exec_numeric_op!(/, lhs, rhs);
+    exec_unsigned_op!(wrapping_div, lhs, rhs);
+    Ok(())
 }
 
-pub fn exec_rem(cur_state: &mut ActivationRecord) {
+pub fn exec_rem_un(cur_state: &mut ActivationRecord) -> Result<(), Fault> {
     let rhs = cur_state.eval_stack.pop(None);
+    if is_int_zero(&rhs) {
+        return Err(Fault::DivideByZero);
+    }
     let lhs = cur_state.eval_stack.peek_mut().unwrap();
-    exec_numeric_op!(%, lhs, rhs);
+    exec_unsigned_op!(wrapping_rem, lhs, rhs);
+    Ok(())
 }
 
 pub fn exec_neg(cur_state: &mut ActivationRecord) {
@@ -183,13 +313,13 @@ pub fn exec_neg(cur_state: &mut ActivationRecord) {
     unsafe {
         match lhs.tag {
             SlotTag::I32 => {
-                lhs.data.i32_ = -lhs.data.i32_;
+                lhs.data.i32_ = lhs.data.i32_.wrapping_neg();
             }
             SlotTag::I64 => {
-                lhs.data.i64_ = -lhs.data.i64_;
+                lhs.data.i64_ = lhs.data.i64_.wrapping_neg();
             }
             SlotTag::INative => {
-                lhs.data.inative_ = -lhs.data.inative_;
+                lhs.data.inative_ = lhs.data.inative_.wrapping_neg();
             }
             SlotTag::F32 => {
                 lhs.data.f32_ = -lhs.data.f32_;
@@ -204,3 +334,202 @@ pub fn exec_neg(cur_state: &mut ActivationRecord) {
         }
     }
 }
+
+pub fn exec_not(cur_state: &mut ActivationRecord) {
+    let v = cur_state.eval_stack.peek_mut().unwrap();
+    unsafe {
+        match v.tag {
+            SlotTag::I32 => v.data.i32_ = !v.data.i32_,
+            SlotTag::I64 => v.data.i64_ = !v.data.i64_,
+            SlotTag::INative => v.data.inative_ = !v.data.inative_,
+            _ => panic!("Cannot apply not on {}", v.tag),
+        }
+    }
+}
+
+pub fn exec_and(cur_state: &mut ActivationRecord) {
+    let rhs = cur_state.eval_stack.pop(None);
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    exec_bit_op!(&, lhs, rhs);
+}
+
+pub fn exec_or(cur_state: &mut ActivationRecord) {
+    let rhs = cur_state.eval_stack.pop(None);
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    exec_bit_op!(|, lhs, rhs);
+}
+
+pub fn exec_xor(cur_state: &mut ActivationRecord) {
+    let rhs = cur_state.eval_stack.pop(None);
+    let lhs = cur_state.eval_stack.peek_mut().unwrap();
+    exec_bit_op!(^, lhs, rhs);
+}
+
+/// Shift ops, III.3.58 and III.3.59
+///
+/// amount is masked by the bit width of val
+pub fn exec_shift(cur_state: &mut ActivationRecord, is_left: bool, is_un: bool) {
+    let amount = cur_state.eval_stack.pop(None).expect_inative() as u32;
+    let v = cur_state.eval_stack.peek_mut().unwrap();
+    unsafe {
+        match v.tag {
+            SlotTag::I32 => {
+                v.data.i32_ = if is_left {
+                    v.data.i32_.wrapping_shl(amount)
+                } else if is_un {
+                    (v.data.i32_ as u32).wrapping_shr(amount) as i32
+                } else {
+                    v.data.i32_.wrapping_shr(amount)
+                }
+            }
+            SlotTag::I64 => {
+                v.data.i64_ = if is_left {
+                    v.data.i64_.wrapping_shl(amount)
+                } else if is_un {
+                    (v.data.i64_ as u64).wrapping_shr(amount) as i64
+                } else {
+                    v.data.i64_.wrapping_shr(amount)
+                }
+            }
+            SlotTag::INative => {
+                v.data.inative_ = if is_left {
+                    v.data.inative_.wrapping_shl(amount)
+                } else if is_un {
+                    (v.data.inative_ as usize).wrapping_shr(amount) as isize
+                } else {
+                    v.data.inative_.wrapping_shr(amount)
+                }
+            }
+            _ => panic!("Cannot shift {}", v.tag),
+        }
+    }
+}
+
+/// conv.*, to is the type of the result, III.3.27 Table III.8
+///
+/// Ints are truncated or extended, floats are truncated toward zero when converted to ints
+pub fn exec_conv(cur_state: &mut ActivationRecord, to: &BuiltinType) {
+    let v = cur_state.eval_stack.pop(None);
+    let stack = &mut cur_state.eval_stack;
+    unsafe {
+        match to {
+            BuiltinType::R4 => stack.push_f32(match v.tag {
+                SlotTag::I32 => v.data.i32_ as f32,
+                SlotTag::I64 => v.data.i64_ as f32,
+                SlotTag::INative => v.data.inative_ as f32,
+                SlotTag::F32 | SlotTag::F64 => v.expect_f32(),
+                _ => panic!("Cannot convert {} to float", v.tag),
+            }),
+            BuiltinType::R8 => stack.push_f64(match v.tag {
+                SlotTag::I32 => v.data.i32_ as f64,
+                SlotTag::I64 => v.data.i64_ as f64,
+                SlotTag::INative => v.data.inative_ as f64,
+                SlotTag::F32 | SlotTag::F64 => v.expect_f64(),
+                _ => panic!("Cannot convert {} to float", v.tag),
+            }),
+            _ => {
+                // int32 is zero-extended if result is unsigned
+                let is_unsigned = matches!(
+                    to,
+                    BuiltinType::U1
+                        | BuiltinType::U2
+                        | BuiltinType::U4
+                        | BuiltinType::U8
+                        | BuiltinType::UNative
+                );
+                let i = match v.tag {
+                    SlotTag::I32 if is_unsigned => v.data.i32_ as u32 as i64,
+                    SlotTag::I32 => v.data.i32_ as i64,
+                    SlotTag::I64 => v.data.i64_,
+                    SlotTag::INative => v.data.inative_ as i64,
+                    SlotTag::F32 | SlotTag::F64 if is_unsigned => v.expect_f64() as u64 as i64,
+                    SlotTag::F32 | SlotTag::F64 => v.expect_f64() as i64,
+                    _ => panic!("Cannot convert {} to int", v.tag),
+                };
+                push_int(stack, to, i);
+            }
+        }
+    }
+}
+
+/// Truncate i to int type to and push it
+fn push_int(stack: &mut EvalStack, to: &BuiltinType, i: i64) {
+    match to {
+        BuiltinType::I1 => stack.push_i32(i as i8 as i32),
+        BuiltinType::U1 => stack.push_i32(i as u8 as i32),
+        BuiltinType::I2 => stack.push_i32(i as i16 as i32),
+        BuiltinType::U2 => stack.push_i32(i as u16 as i32),
+        BuiltinType::I4 | BuiltinType::U4 => stack.push_i32(i as i32),
+        BuiltinType::I8 | BuiltinType::U8 => stack.push_i64(i),
+        BuiltinType::INative | BuiltinType::UNative => stack.push_isize(i as isize),
+        _ => unreachable!(),
+    }
+}
+
+/// (min, max) of int type
+fn int_range(ty: &BuiltinType) -> (i128, i128) {
+    match ty {
+        BuiltinType::I1 => (i8::MIN as i128, i8::MAX as i128),
+        BuiltinType::U1 => (0, u8::MAX as i128),
+        BuiltinType::I2 => (i16::MIN as i128, i16::MAX as i128),
+        BuiltinType::U2 => (0, u16::MAX as i128),
+        BuiltinType::I4 => (i32::MIN as i128, i32::MAX as i128),
+        BuiltinType::U4 => (0, u32::MAX as i128),
+        BuiltinType::I8 => (i64::MIN as i128, i64::MAX as i128),
+        BuiltinType::U8 => (0, u64::MAX as i128),
+        BuiltinType::INative => (isize::MIN as i128, isize::MAX as i128),
+        BuiltinType::UNative => (0, usize::MAX as i128),
+        _ => unreachable!(),
+    }
+}
+
+/// conv.ovf.*, to is an int type. Integer operand is treated as unsigned if is_un (conv.ovf.*.un)
+///
+/// Float is truncated toward zero before checking
+pub fn exec_conv_ovf(
+    cur_state: &mut ActivationRecord,
+    to: &BuiltinType,
+    is_un: bool,
+) -> Result<(), Fault> {
+    let v = cur_state.eval_stack.pop(None);
+    let (min, max) = int_range(to);
+    let i = unsafe {
+        match v.tag {
+            SlotTag::I32 if is_un => v.data.i32_ as u32 as i128,
+            SlotTag::I32 => v.data.i32_ as i128,
+            SlotTag::I64 if is_un => v.data.i64_ as u64 as i128,
+            SlotTag::I64 => v.data.i64_ as i128,
+            SlotTag::INative if is_un => v.data.inative_ as usize as i128,
+            SlotTag::INative => v.data.inative_ as i128,
+            SlotTag::F32 | SlotTag::F64 => {
+                let f = v.expect_f64().trunc();
+                // min and max + 1 are powers of 2 so they are exact in f64. NaN fails both
+                if f >= min as f64 && f < (max + 1) as f64 {
+                    f as i128
+                } else {
+                    return Err(Fault::Overflow);
+                }
+            }
+            _ => panic!("Cannot convert {} to int", v.tag),
+        }
+    };
+    if i < min || i > max {
+        return Err(Fault::Overflow);
+    }
+    push_int(&mut cur_state.eval_stack, to, i as i64);
+    Ok(())
+}
+
+/// conv.r.un, convert unsigned int to float
+pub fn exec_conv_r_un(cur_state: &mut ActivationRecord) {
+    let v = cur_state.eval_stack.pop(None);
+    let f = unsafe {
+        match v.tag {
+            SlotTag::I32 => v.data.i32_ as u32 as f64,
+            SlotTag::I64 => v.data.i64_ as u64 as f64,
+            SlotTag::INative => v.data.inative_ as usize as f64,
+            _ => panic!("Cannot convert {} to float as unsigned int", v.tag),
+        }
+    };
+    cur_state.eval_stack.push_f64(f);
+}
@@ -0,0 +1,307 @@
+use super::obj::{ObjHeader, OBJ_FLAG_MARKED};
+use super::region::Region;
+use super::visitor::RefVisitor;
+use super::Heap;
+
+use std::fmt;
+use std::mem::size_of;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct GCStats {
+    pub minor_count: usize,
+    pub major_count: usize,
+    /// bytes moved from young generation to old generation
+    pub promoted_bytes: usize,
+    /// bytes reclaimed by all collections
+    pub freed_bytes: usize,
+    pub peak_heap_size: usize,
+    pub total_time: Duration,
+}
+
+impl fmt::Display for GCStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Minor GC count: {}", self.minor_count)?;
+        writeln!(f, "Major GC count: {}", self.major_count)?;
+        writeln!(f, "Promoted bytes: {}", self.promoted_bytes)?;
+        writeln!(f, "Freed bytes: {}", self.freed_bytes)?;
+        writeln!(f, "Peak heap size: {}", self.peak_heap_size)?;
+        write!(f, "GC time: {}", self.total_time.as_secs_f32())
+    }
+}
+
+/// Mark phase
+///
+/// In minor gc only young objects are marked, old objects that refer to young objects are in remembered set
+struct GCMarker<'h> {
+    heap: &'h Heap,
+    full: bool,
+    /// objects that are marked but whose fields are not visited
+    gray: Vec<*mut u8>,
+}
+
+impl<'h> GCMarker<'h> {
+    fn new(heap: &'h Heap, full: bool) -> GCMarker<'h> {
+        GCMarker {
+            heap,
+            full,
+            gray: Vec::new(),
+        }
+    }
+
+    fn should_trace(&self, addr: usize) -> bool {
+        self.heap.young.contains(addr) || (self.full && self.heap.old.contains(addr))
+    }
+
+    /// trace all objects reachable from marked roots
+    fn finish(mut self) {
+        while let Some(obj) = self.gray.pop() {
+            unsafe {
+                self.visit_obj(obj);
+            }
+        }
+    }
+}
+
+impl<'h> RefVisitor for GCMarker<'h> {
+    unsafe fn visit_ref(&mut self, slot: *mut *mut u8) {
+        let obj = ptr::read_unaligned(slot);
+        if obj.is_null() || !self.should_trace(obj as usize) {
+            return;
+        }
+        let header = (Heap::get_entry::<ObjHeader>(obj) as *mut ObjHeader)
+            .as_mut()
+            .unwrap();
+        if !header.is(OBJ_FLAG_MARKED) {
+            header.flag |= OBJ_FLAG_MARKED;
+            self.gray.push(obj);
+        }
+    }
+
+    unsafe fn visit_managed(&mut self, slot: *mut *mut u8) {
+        let addr = ptr::read_unaligned(slot) as usize;
+        if !self.should_trace(addr) {
+            return;
+        }
+        let mut obj = if self.heap.young.contains(addr) {
+            self.heap.young.find_obj(addr)
+        } else {
+            self.heap.old.find_obj(addr)
+        }
+        .unwrap();
+        self.visit_ref(&mut obj as *mut *mut u8);
+    }
+}
+
+/// New location of a live block
+struct Relocation {
+    /// block start (ObjHeader) before moving
+    from: usize,
+    size: usize,
+    to: usize,
+}
+
+/// Rewrite refs to moved objects, interior pointers are also supported
+struct Relocator {
+    /// sorted by from
+    table: Vec<Relocation>,
+}
+
+impl Relocator {
+    fn relocate(&self, addr: usize) -> usize {
+        let idx = self.table.partition_point(|r| r.from <= addr);
+        if idx > 0 {
+            let r = &self.table[idx - 1];
+            if addr < r.from + r.size {
+                return addr - r.from + r.to;
+            }
+        }
+        addr
+    }
+}
+
+impl RefVisitor for Relocator {
+    unsafe fn visit_ref(&mut self, slot: *mut *mut u8) {
+        let obj = ptr::read_unaligned(slot);
+        if !obj.is_null() {
+            ptr::write_unaligned(slot, self.relocate(obj as usize) as *mut u8);
+        }
+    }
+
+    unsafe fn visit_managed(&mut self, slot: *mut *mut u8) {
+        self.visit_ref(slot);
+    }
+}
+
+/// collect marked blocks in region, mark bits are cleared
+fn collect_live_blocks(region: &Region, live: &mut Vec<Relocation>) {
+    for header in region.blocks() {
+        let header_ref = unsafe { header.as_mut().unwrap() };
+        if header_ref.is(OBJ_FLAG_MARKED) {
+            header_ref.flag &= !OBJ_FLAG_MARKED;
+            live.push(Relocation {
+                from: header as usize,
+                size: header_ref.size,
+                to: 0,
+            });
+        }
+    }
+}
+
+impl Heap {
+    /// Collect garbage
+    ///
+    /// roots must visit all roots of the vm (thread stacks and static fields), it will be called twice,
+    /// once for marking, once for updating refs after objects are moved.
+    ///
+    /// Minor gc promotes all surviving young objects to old generation.
+    /// Major gc (if full is true or old generation cannot hold young objects)
+    /// marks the whole heap and compacts all live objects into old generation, old generation may grow.
+    pub fn collect<F>(&mut self, full: bool, mut roots: F)
+    where
+        F: FnMut(&mut dyn RefVisitor),
+    {
+        let start_time = Instant::now();
+        let full = full || self.old.free() < self.young.used();
+        let used_before = self.young.used() + self.old.used();
+
+        // mark
+        let mut marker = GCMarker::new(self, full);
+        roots(&mut marker);
+        if !full {
+            for slot in self.remembered_set.iter() {
+                unsafe {
+                    marker.visit_ref(*slot as *mut *mut u8);
+                }
+            }
+        }
+        marker.finish();
+
+        if full {
+            self.compact(&mut roots);
+            self.stats.major_count += 1;
+        } else {
+            self.promote(&mut roots);
+            self.stats.minor_count += 1;
+        }
+
+        self.stats.freed_bytes += used_before - self.old.used();
+        self.stats.total_time += start_time.elapsed();
+    }
+
+    /// move surviving young objects to the end of old generation
+    fn promote<F>(&mut self, roots: &mut F)
+    where
+        F: FnMut(&mut dyn RefVisitor),
+    {
+        let mut table = Vec::new();
+        collect_live_blocks(&self.young, &mut table);
+        let mut to = self.old.base() + self.old.used();
+        for r in table.iter_mut() {
+            r.to = to;
+            to += r.size;
+        }
+        let promoted = to - self.old.base() - self.old.used();
+
+        let mut relocator = Relocator { table };
+        self.fix_refs(&mut relocator, roots);
+        for slot in self.remembered_set.iter() {
+            unsafe {
+                relocator.visit_ref(*slot as *mut *mut u8);
+            }
+        }
+
+        // old generation has enough space, see collect
+        self.old.bump(promoted).unwrap();
+        for r in relocator.table.iter() {
+            unsafe {
+                ptr::copy_nonoverlapping(r.from as *const u8, r.to as *mut u8, r.size);
+            }
+        }
+        self.young.set_used(0);
+        self.remembered_set.clear();
+        self.stats.promoted_bytes += promoted;
+    }
+
+    /// slide all live objects to the start of old generation, the old generation will grow if necessary
+    fn compact<F>(&mut self, roots: &mut F)
+    where
+        F: FnMut(&mut dyn RefVisitor),
+    {
+        let mut table = Vec::new();
+        collect_live_blocks(&self.old, &mut table);
+        collect_live_blocks(&self.young, &mut table);
+
+        let live_size: usize = table.iter().map(|r| r.size).sum();
+        let required = live_size + self.pending_size;
+        let mut new_region = if required * 2 > self.old.capacity() {
+            // keep at least half of the old generation free after collection
+            let max_old_size = self.max_size - self.young.capacity();
+            let new_size = (required * 2)
+                .max(self.old.capacity() * 2)
+                .min(max_old_size);
+            if new_size > self.old.capacity() {
+                Some(Region::new(new_size))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        if live_size > new_region.as_ref().unwrap_or(&self.old).capacity() {
+            panic!("Out of memory");
+        }
+
+        let mut to = new_region.as_ref().unwrap_or(&self.old).base();
+        for r in table.iter_mut() {
+            r.to = to;
+            to += r.size;
+        }
+        // old blocks are in ascending order and young blocks are in another region
+        table.sort_by_key(|r| r.from);
+
+        let mut relocator = Relocator { table };
+        self.fix_refs(&mut relocator, roots);
+
+        // move blocks, blocks from old generation are moved first and in ascending order,
+        // so slide in place will not overwrite live blocks
+        let mut moves: Vec<&Relocation> = relocator.table.iter().collect();
+        moves.sort_by_key(|r| r.to);
+        if let Some(new_region) = new_region.as_mut() {
+            new_region.bump(live_size).unwrap();
+        }
+        for r in moves.iter() {
+            unsafe {
+                ptr::copy(r.from as *const u8, r.to as *mut u8, r.size);
+            }
+        }
+        if let Some(new_region) = new_region {
+            self.old = new_region;
+        } else {
+            self.old.set_used(live_size);
+        }
+
+        self.young.set_used(0);
+        self.remembered_set.clear();
+        self.pending_size = 0;
+        self.stats.peak_heap_size = self
+            .stats
+            .peak_heap_size
+            .max(self.old.capacity() + self.young.capacity());
+    }
+
+    /// update refs in roots and in all live objects, objects are not moved yet
+    fn fix_refs<F>(&self, relocator: &mut Relocator, roots: &mut F)
+    where
+        F: FnMut(&mut dyn RefVisitor),
+    {
+        roots(relocator);
+        for i in 0..relocator.table.len() {
+            let obj = (relocator.table[i].from + size_of::<ObjHeader>()) as *mut u8;
+            unsafe {
+                relocator.visit_obj(obj);
+            }
+        }
+    }
+}
@@ -1,21 +1,58 @@
+mod gc;
 mod obj;
+mod region;
+mod visitor;
 
+use std::collections::HashSet;
 use std::mem::size_of;
+use std::ptr;
 
-use obj::{ArrHeader, ObjHeader, StrCharsIter, StrCharsIterMut, StrHeader};
+use obj::{
+    ArrHeader, ObjHeader, StrCharsIter, StrCharsIterMut, StrHeader, OBJ_FLAG_ARR, OBJ_FLAG_STR,
+};
+use region::Region;
 
-use super::data::Type;
+use super::data::{BuiltinType, Type};
 
+pub use gc::GCStats;
+pub use visitor::RefVisitor;
+
+/// all blocks are aligned to this
+const BLOCK_ALIGN: usize = size_of::<usize>();
+/// young generation takes 1/YOUNG_GEN_RATIO of initial heap size
+const YOUNG_GEN_RATIO: usize = 4;
+/// objects larger than 1/LARGE_OBJ_RATIO of young generation are allocated in old generation directly
+const LARGE_OBJ_RATIO: usize = 4;
+
+/// Generational heap
+///
+/// New objects are allocated in young generation. Objects that survive a minor gc are promoted to old generation.
+/// Old generation is compacted in major gc and can grow until max_size is reached.
 pub struct Heap {
-    next_obj_offset: usize,
-    data: Vec<u8>,
+    young: Region,
+    old: Region,
+    /// max size of young and old generations
+    max_size: usize,
+    /// size of the failed allocation, major gc will reserve space for it
+    pending_size: usize,
+    /// slots in old generation that may refer to young objects, see write_barrier
+    remembered_set: HashSet<usize>,
+    pub stats: GCStats,
 }
 
 impl Heap {
-    pub fn new(size: usize) -> Heap {
+    pub fn new(size: usize, max_size: usize) -> Heap {
+        let young_size = size / YOUNG_GEN_RATIO / BLOCK_ALIGN * BLOCK_ALIGN;
         Heap {
-            data: vec![0; size],
-            next_obj_offset: 0,
+            young: Region::new(young_size),
+            old: Region::new(size - young_size),
+            max_size: max_size.max(size),
+            pending_size: 0,
+            remembered_set: HashSet::new(),
+            stats: GCStats {
+                peak_heap_size: size,
+                ..Default::default()
+            },
         }
     }
 
@@ -33,6 +70,33 @@ impl Heap {
         unsafe { Self::get_entry::<ArrHeader>(self_ptr).as_ref().unwrap().len }
     }
 
+    pub fn get_arr_ele_ty(self_ptr: *mut u8) -> *const Type {
+        unsafe {
+            Self::get_entry::<ArrHeader>(self_ptr)
+                .as_ref()
+                .unwrap()
+                .ele_ty
+        }
+    }
+
+    pub fn is_str(self_ptr: *mut u8) -> bool {
+        unsafe {
+            Self::get_entry::<ObjHeader>(self_ptr)
+                .as_ref()
+                .unwrap()
+                .is(OBJ_FLAG_STR)
+        }
+    }
+
+    pub fn is_arr(self_ptr: *mut u8) -> bool {
+        unsafe {
+            Self::get_entry::<ObjHeader>(self_ptr)
+                .as_ref()
+                .unwrap()
+                .is(OBJ_FLAG_ARR)
+        }
+    }
+
     pub fn get_vtbl_ptr(self_ptr: *mut u8) -> *const Type {
         unsafe {
             Self::get_entry::<ObjHeader>(self_ptr)
@@ -51,44 +115,82 @@ impl Heap {
         StrCharsIter::new(self_ptr)
     }
 
+    /// Allocate a zeroed block that can hold at least size bytes, return (start of block, block size)
+    ///
+    /// return None if heap is exhausted
+    fn alloc(&mut self, size: usize) -> Option<(*mut u8, usize)> {
+        // keep headers aligned
+        let size = (size + BLOCK_ALIGN - 1) & !(BLOCK_ALIGN - 1);
+        let ret = if size * LARGE_OBJ_RATIO > self.young.capacity() {
+            self.old.bump(size)
+        } else {
+            self.young.bump(size)
+        };
+        if ret.is_none() {
+            self.pending_size = size;
+        }
+        ret.map(|block| (block, size))
+    }
+
+    /// Must be called after a value of type ty is stored at addr,
+    /// refs from old generation to young generation are recorded
+    pub fn write_barrier(&mut self, addr: *mut u8, ty: &BuiltinType) {
+        if self.old.contains(addr as usize) {
+            unsafe {
+                WriteBarrier { heap: self }.visit_typed(addr, ty);
+            }
+        }
+    }
+
+    /// Same as write_barrier, but slot is known to store a ref
+    pub fn write_barrier_ref(&mut self, slot: *mut u8) {
+        if self.old.contains(slot as usize) {
+            unsafe {
+                WriteBarrier { heap: self }.visit_ref(slot as *mut *mut u8);
+            }
+        }
+    }
+
     /// New obj
     ///
     /// [ObjHeader] [content...]
+    ///
+    /// return null if heap is exhausted
     pub unsafe fn new_obj(&mut self, class: &Type) -> *mut u8 {
-        let offset_after_alloc =
-            class.basic_instance_size + size_of::<ObjHeader>() + self.next_obj_offset;
-        if offset_after_alloc >= self.data.len() {
-            // GC
-            unimplemented!("GC");
-        }
-
-        (&mut self.data[self.next_obj_offset] as *mut u8 as *mut ObjHeader)
+        let (block, size) =
+            if let Some(block) = self.alloc(class.basic_instance_size + size_of::<ObjHeader>()) {
+                block
+            } else {
+                return ptr::null_mut();
+            };
+
+        (block as *mut ObjHeader)
             .as_mut()
             .unwrap()
-            .init(class);
-        let ret = &mut self.data[self.next_obj_offset + size_of::<ObjHeader>()] as *mut u8;
-        self.next_obj_offset = offset_after_alloc;
-        ret
+            .init(class, size);
+        block.wrapping_add(size_of::<ObjHeader>())
     }
 
     /// [StrHeader] [chars...]
     ///
     /// str_class must point to std::String
+    ///
+    /// return null if heap is exhausted
     pub unsafe fn new_str_from_str(&mut self, str_class: *const Type, s: &str) -> *mut u8 {
         let char_count = s.chars().count();
-        let offset_after_alloc =
-            char_count * size_of::<char>() + size_of::<StrHeader>() + self.next_obj_offset;
-        if offset_after_alloc >= self.data.len() {
-            // GC
-            unimplemented!("GC");
-        }
-
-        (&mut self.data[self.next_obj_offset] as *mut u8 as *mut StrHeader)
+        let (block, size) = if let Some(block) =
+            self.alloc(char_count * size_of::<char>() + size_of::<StrHeader>())
+        {
+            block
+        } else {
+            return ptr::null_mut();
+        };
+
+        (block as *mut StrHeader)
             .as_mut()
             .unwrap()
-            .init(str_class, char_count);
-        let ret = &mut self.data[self.next_obj_offset + size_of::<ObjHeader>()] as *mut u8;
-        self.next_obj_offset = offset_after_alloc;
+            .init(str_class, char_count, size);
+        let ret = block.wrapping_add(size_of::<ObjHeader>());
         let chars = StrCharsIterMut::new(ret);
         for (ch_target, ch_src) in chars.zip(s.chars()) {
             *ch_target = ch_src;
@@ -97,26 +199,43 @@ impl Heap {
     }
 
     /// [ArrHeader] [elements]
+    ///
+    /// return null if heap is exhausted
     pub unsafe fn new_arr(
         &mut self,
         arr_class: *const Type,
+        ele_ty: *const Type,
         ele_size: usize,
         len: usize,
     ) -> *mut u8 {
         // TODO: ele_ty can be contained in arr_class
-        // TODO: check value type
-        let offset_after_alloc = len * ele_size + size_of::<ArrHeader>() + self.next_obj_offset;
-        if offset_after_alloc >= self.data.len() {
-            // GC
-            unimplemented!("GC");
-        }
-
-        (&mut self.data[self.next_obj_offset] as *mut u8 as *mut ArrHeader)
+        let (block, size) = if let Some(block) = self.alloc(len * ele_size + size_of::<ArrHeader>())
+        {
+            block
+        } else {
+            return ptr::null_mut();
+        };
+
+        (block as *mut ArrHeader)
             .as_mut()
             .unwrap()
-            .init(arr_class, len);
-        let ret = &mut self.data[self.next_obj_offset + size_of::<ObjHeader>()] as *mut u8;
-        self.next_obj_offset = offset_after_alloc;
-        ret
+            .init(arr_class, ele_ty, len, size);
+        block.wrapping_add(size_of::<ObjHeader>())
+    }
+}
+
+struct WriteBarrier<'h> {
+    heap: &'h mut Heap,
+}
+
+impl<'h> RefVisitor for WriteBarrier<'h> {
+    unsafe fn visit_ref(&mut self, slot: *mut *mut u8) {
+        if self.heap.young.contains(ptr::read_unaligned(slot) as usize) {
+            self.heap.remembered_set.insert(slot as usize);
+        }
+    }
+
+    unsafe fn visit_managed(&mut self, slot: *mut *mut u8) {
+        self.visit_ref(slot);
     }
 }
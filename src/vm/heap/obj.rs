@@ -3,28 +3,46 @@ use super::super::data::Type;
 use std::marker::PhantomData;
 use std::mem::size_of;
 
+/// object is reachable in current gc cycle
+pub const OBJ_FLAG_MARKED: usize = 0x1;
+/// object is a string, chars are not traced
+pub const OBJ_FLAG_STR: usize = 0x2;
+/// object is an array, elements are traced according to ArrHeader.ele_ty
+pub const OBJ_FLAG_ARR: usize = 0x4;
+
 #[repr(C)]
 pub struct ObjHeader {
     pub flag: usize,
+    /// block size including header, used to walk through the heap
+    pub size: usize,
     pub p_method_tbl: *const Type,
 }
 
 impl ObjHeader {
-    pub fn init(&mut self, class: *const Type) {
+    pub fn init(&mut self, class: *const Type, size: usize) {
+        self.flag = 0;
+        self.size = size;
         self.p_method_tbl = class;
     }
+
+    pub fn is(&self, flag: usize) -> bool {
+        self.flag & flag != 0
+    }
 }
 
 #[repr(C)]
 pub struct ArrHeader {
     pub obj_header: ObjHeader,
     pub len: usize,
+    pub ele_ty: *const Type,
 }
 
 impl ArrHeader {
-    pub fn init(&mut self, class: *const Type, len: usize) {
-        self.obj_header.init(class);
+    pub fn init(&mut self, class: *const Type, ele_ty: *const Type, len: usize, size: usize) {
+        self.obj_header.init(class, size);
+        self.obj_header.flag |= OBJ_FLAG_ARR;
         self.len = len;
+        self.ele_ty = ele_ty;
     }
 }
 
@@ -35,8 +53,9 @@ pub struct StrHeader {
 }
 
 impl StrHeader {
-    pub fn init(&mut self, class: *const Type, len: usize) {
-        self.obj_header.init(class);
+    pub fn init(&mut self, class: *const Type, len: usize, size: usize) {
+        self.obj_header.init(class, size);
+        self.obj_header.flag |= OBJ_FLAG_STR;
         self.len = len;
     }
 }
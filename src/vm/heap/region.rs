@@ -0,0 +1,100 @@
+use super::obj::ObjHeader;
+
+use std::mem::size_of;
+
+/// A contiguous bump-allocated memory block
+///
+/// Objects are placed one after another, each starting with an ObjHeader,
+/// so the region can be walked with ObjHeader.size
+pub struct Region {
+    data: Vec<u8>,
+    top: usize,
+}
+
+impl Region {
+    pub fn new(size: usize) -> Region {
+        Region {
+            data: vec![0; size],
+            top: 0,
+        }
+    }
+
+    pub fn base(&self) -> usize {
+        self.data.as_ptr() as usize
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn used(&self) -> usize {
+        self.top
+    }
+
+    pub fn free(&self) -> usize {
+        self.data.len() - self.top
+    }
+
+    pub fn set_used(&mut self, top: usize) {
+        assert!(top <= self.data.len());
+        self.top = top;
+    }
+
+    /// whether addr points into allocated part of this region
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.base() && addr < self.base() + self.top
+    }
+
+    /// alloc a zeroed block, return the start of block
+    pub fn bump(&mut self, size: usize) -> Option<*mut u8> {
+        if self.top + size > self.data.len() {
+            return None;
+        }
+        let offset = self.top;
+        self.top += size;
+        for b in self.data[offset..self.top].iter_mut() {
+            *b = 0;
+        }
+        Some(&mut self.data[offset] as *mut u8)
+    }
+
+    /// iterate over headers of all blocks
+    pub fn blocks(&self) -> RegionIter {
+        RegionIter {
+            cur: self.base(),
+            end: self.base() + self.top,
+        }
+    }
+
+    /// find the object that contains addr, return the object pointer
+    pub fn find_obj(&self, addr: usize) -> Option<*mut u8> {
+        if !self.contains(addr) {
+            return None;
+        }
+        for header in self.blocks() {
+            if addr < header as usize + unsafe { (*header).size } {
+                return Some((header as *mut u8).wrapping_add(size_of::<ObjHeader>()));
+            }
+        }
+        unreachable!()
+    }
+}
+
+pub struct RegionIter {
+    cur: usize,
+    end: usize,
+}
+
+impl Iterator for RegionIter {
+    type Item = *mut ObjHeader;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cur < self.end {
+            let header = self.cur as *mut ObjHeader;
+            self.cur += unsafe { (*header).size };
+            Some(header)
+        } else {
+            None
+        }
+    }
+}
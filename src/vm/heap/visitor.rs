@@ -0,0 +1,93 @@
+use xir::attrib::FieldAttribFlag;
+
+use super::super::data::{BuiltinType, Type, REF_SIZE};
+use super::obj::{ArrHeader, ObjHeader, OBJ_FLAG_ARR, OBJ_FLAG_STR};
+use super::Heap;
+
+use std::mem::size_of;
+use std::ptr;
+
+/// Visit reference slots in roots (stacks, static fields) or in objects
+///
+/// Used by marking, reference fixups after objects are moved and write barriers.
+/// Fields and locals are packed, so slots may be unaligned
+pub trait RefVisitor {
+    /// slot stores null or a pointer to the start of an object
+    unsafe fn visit_ref(&mut self, slot: *mut *mut u8);
+
+    /// slot stores a managed pointer, which may point to stack,
+    /// static fields or the interior of an object
+    unsafe fn visit_managed(&mut self, slot: *mut *mut u8);
+
+    /// visit a value of type ty stored at addr
+    unsafe fn visit_typed(&mut self, addr: *mut u8, ty: &BuiltinType) {
+        match ty {
+            BuiltinType::String | BuiltinType::Class(_) | BuiltinType::SZArray(_) => {
+                self.visit_ref(addr as *mut *mut u8)
+            }
+            BuiltinType::ByRef(_) => self.visit_managed(addr as *mut *mut u8),
+            BuiltinType::Value(t) => self.visit_fields(addr, t.as_ref()),
+            BuiltinType::GenericInst(_, _, _) | BuiltinType::Var(_) | BuiltinType::MVar(_) => {
+                unreachable!()
+            }
+            _ => {}
+        }
+    }
+
+    /// visit instance fields (including base fields) of a value or an object located at addr
+    unsafe fn visit_fields(&mut self, addr: *mut u8, ty: &Type) {
+        let mut ty_ptr = ty as *const Type;
+        while let Some(cur) = ty_ptr.as_ref() {
+            for (name, field) in cur.ee_class.fields.iter() {
+                let field = field.as_ref();
+                if field.attrib.is(FieldAttribFlag::Static)
+                    || is_overridden(ty, cur, *name, &field.ty)
+                {
+                    continue;
+                }
+                self.visit_typed(addr.wrapping_add(field.offset), &field.ty);
+            }
+            ty_ptr = cur.extends;
+        }
+    }
+
+    /// visit all refs in a heap object
+    unsafe fn visit_obj(&mut self, obj: *mut u8) {
+        let header = Heap::get_entry::<ObjHeader>(obj).as_ref().unwrap();
+        if header.is(OBJ_FLAG_STR) {
+            // chars contain no reference
+        } else if header.is(OBJ_FLAG_ARR) {
+            let arr_header = Heap::get_entry::<ArrHeader>(obj).as_ref().unwrap();
+            let ele_ty = arr_header.ele_ty.as_ref().unwrap();
+            let eles = obj.wrapping_add(size_of::<ArrHeader>() - size_of::<ObjHeader>());
+            if ele_ty.ee_class.is_value {
+                for i in 0..arr_header.len {
+                    self.visit_fields(eles.wrapping_add(i * ele_ty.basic_instance_size), ele_ty);
+                }
+            } else {
+                for i in 0..arr_header.len {
+                    self.visit_ref(eles.wrapping_add(i * REF_SIZE) as *mut *mut u8);
+                }
+            }
+        } else {
+            self.visit_fields(obj, header.p_method_tbl.as_ref().unwrap());
+        }
+    }
+}
+
+/// fields that are redeclared in derived class share the same slot (see linker::calc_type_mem_layout),
+/// they should be visited only once
+fn is_overridden(ty: &Type, base: &Type, name: usize, field_ty: &BuiltinType) -> bool {
+    let mut ty_ptr = ty as *const Type;
+    while !ptr::eq(ty_ptr, base) {
+        let cur = unsafe { ty_ptr.as_ref().unwrap() };
+        if let Some(candidate) = cur.ee_class.fields.get(&name) {
+            let candidate = unsafe { candidate.as_ref() };
+            if !candidate.attrib.is(FieldAttribFlag::Static) && &candidate.ty == field_ty {
+                return true;
+            }
+        }
+        ty_ptr = cur.extends;
+    }
+    false
+}
@@ -0,0 +1,386 @@
+use xir::attrib::{FieldAttribFlag, MethodAttribFlag};
+use xir::sig::IrSig;
+use xir::tok::{get_tok_tag, to_tok, TokTag};
+
+use super::super::data::{
+    builtin_ty_str_desc, method_str_desc_from_ir, BuiltinType, Field, MemberRef, MethodDesc,
+    MethodILImpl, MethodImpl, Param, Type,
+};
+use super::super::util::ptr::NonNull;
+use super::linker;
+
+use std::collections::HashMap;
+use std::ptr;
+use std::rc::Rc;
+
+/// Substitute generic params in ty with generic args.
+/// Generic inst whose generic args are all determined is instantiated
+///
+/// Generic params that have no corresponding arg are kept
+pub fn instantiate(
+    ty: &BuiltinType,
+    class_args: &[BuiltinType],
+    method_args: &[BuiltinType],
+    str_pool: &Vec<String>,
+) -> BuiltinType {
+    match ty {
+        BuiltinType::Var(idx) => match class_args.get(*idx as usize) {
+            Some(arg) => arg.clone(),
+            None => BuiltinType::Var(*idx),
+        },
+        BuiltinType::MVar(idx) => match method_args.get(*idx as usize) {
+            Some(arg) => arg.clone(),
+            None => BuiltinType::MVar(*idx),
+        },
+        BuiltinType::ByRef(inner) => BuiltinType::ByRef(Box::new(instantiate(
+            inner,
+            class_args,
+            method_args,
+            str_pool,
+        ))),
+        BuiltinType::SZArray(inner) => BuiltinType::SZArray(Box::new(instantiate(
+            inner,
+            class_args,
+            method_args,
+            str_pool,
+        ))),
+        BuiltinType::GenericInst(is_class, def, args) => {
+            let args: Vec<BuiltinType> = args
+                .iter()
+                .map(|arg| instantiate(arg, class_args, method_args, str_pool))
+                .collect();
+            if args.iter().any(|arg| arg.is_open()) {
+                BuiltinType::GenericInst(*is_class, *def, args)
+            } else {
+                let inst = get_type_inst(unsafe { def.as_mut() }, args, str_pool);
+                if *is_class {
+                    BuiltinType::Class(inst)
+                } else {
+                    BuiltinType::Value(inst)
+                }
+            }
+        }
+        _ => ty.clone(),
+    }
+}
+
+fn generic_args_key(args: &[BuiltinType], str_pool: &Vec<String>) -> String {
+    args.iter()
+        .map(|arg| builtin_ty_str_desc(arg, str_pool))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+/// copy method, param info and impl info are not filled
+fn dup_method(
+    method: &MethodDesc,
+    parent: *const Type,
+    generic_args: Vec<BuiltinType>,
+) -> Box<MethodDesc> {
+    Box::new(MethodDesc {
+        ctx: method.ctx,
+        index: method.index,
+        parent,
+        // fill in fill_type_method_info
+        slot: 0,
+        name: method.name,
+        attrib: method.attrib,
+        impl_attrib: method.impl_attrib.clone(),
+        ps: method
+            .ps
+            .iter()
+            .map(|p| Param::new(p.name, p.attrib))
+            .collect(),
+        ps_size: 0,
+        ret: Param::new(method.ret.name, method.ret.attrib),
+        method_impl: match &method.method_impl {
+            MethodImpl::IL(il_impl) => MethodImpl::IL(MethodILImpl {
+                index: il_impl.index,
+                locals: Vec::new(),
+                locals_size: 0,
+            }),
+            MethodImpl::Native(_) | MethodImpl::Runtime(_) => {
+                unimplemented!("Generic native or runtime method is not supported")
+            }
+            MethodImpl::Abstract => MethodImpl::Abstract,
+        },
+        generic_params: method.generic_params.clone(),
+        generic_args,
+        generic_insts: HashMap::new(),
+    })
+}
+
+/// Get or create instantiation of generic type
+///
+/// memory layout of the instantiation is calculated,
+/// method info is filled lazily, see resolve_type
+pub fn get_type_inst(
+    def: &mut Type,
+    args: Vec<BuiltinType>,
+    str_pool: &Vec<String>,
+) -> NonNull<Type> {
+    if args.len() != def.generic_params.len() {
+        panic!(
+            "{} expects {} generic args but {} are given",
+            def.fullname(str_pool),
+            def.generic_params.len(),
+            args.len()
+        );
+    }
+
+    let key = generic_args_key(&args, str_pool);
+    if let Some(inst) = def.generic_insts.get_mut(&key) {
+        return NonNull::new(inst.as_mut() as *mut Type).unwrap();
+    }
+
+    let mut inst = Box::new(Type::new(
+        def.module,
+        def.name,
+        def.attrib,
+        HashMap::new(),
+        HashMap::new(),
+    ));
+    inst.extends = def.extends;
+    inst.interfaces = def.interfaces.clone();
+    inst.generic_params = def.generic_params.clone();
+    inst.generic_args = args;
+    let inst_ptr = inst.as_ref() as *const Type;
+
+    let mut fields = HashMap::new();
+    for (name, field) in def.ee_class.fields.iter() {
+        let field = unsafe { field.as_ref() };
+        let mut inst_field = Box::new(Field {
+            name: field.name,
+            attrib: field.attrib,
+            // fill in calc_type_mem_layout
+            ty: BuiltinType::Unk,
+            index: field.index,
+            offset: 0,
+            addr: ptr::null_mut(),
+        });
+        fields.insert(
+            *name,
+            NonNull::new(inst_field.as_mut() as *mut Field).unwrap(),
+        );
+        inst.inst_fields.push(inst_field);
+    }
+
+    let mut methods = HashMap::new();
+    for (sig, method) in def.ee_class.methods.iter() {
+        let mut inst_method = dup_method(unsafe { method.as_ref() }, inst_ptr, vec![]);
+        methods.insert(
+            sig.clone(),
+            NonNull::new(inst_method.as_mut() as *mut MethodDesc).unwrap(),
+        );
+        inst.inst_methods.push(inst_method);
+    }
+
+    {
+        let ee_class = Rc::get_mut(&mut inst.ee_class).unwrap();
+        ee_class.fields = fields;
+        ee_class.methods = methods;
+    }
+
+    // insert before layout so that recursive reference of this instantiation can be resolved
+    def.generic_insts.insert(key.clone(), inst);
+    let inst = def.generic_insts.get_mut(&key).unwrap();
+    linker::calc_type_mem_layout(inst, str_pool);
+    NonNull::new(inst.as_mut() as *mut Type).unwrap()
+}
+
+/// Get or create instantiation of generic method
+pub fn get_method_inst(
+    method: &mut MethodDesc,
+    args: Vec<BuiltinType>,
+    str_pool: &Vec<String>,
+) -> NonNull<MethodDesc> {
+    if args.len() != method.generic_params.len() {
+        panic!(
+            "{} expects {} generic args but {} are given",
+            method.str_desc_with_fullname(str_pool),
+            method.generic_params.len(),
+            args.len()
+        );
+    }
+
+    let key = generic_args_key(&args, str_pool);
+    if let Some(inst) = method.generic_insts.get_mut(&key) {
+        return NonNull::new(inst.as_mut() as *mut MethodDesc).unwrap();
+    }
+
+    let mut inst = dup_method(method, method.parent, args);
+    inst.slot = method.slot;
+    linker::fill_method_info(&mut inst, str_pool);
+    let ret = NonNull::new(inst.as_mut() as *mut MethodDesc).unwrap();
+    method.generic_insts.insert(key, inst);
+    ret
+}
+
+fn class_args(method: &MethodDesc) -> &[BuiltinType] {
+    &unsafe { method.parent.as_ref() }
+        .expect("Method that has no class parent is not implemented")
+        .generic_args
+}
+
+/// Resolve TypeDefOrRefOrSpec tok in the generic context of method
+///
+/// method info of the result type is filled
+pub fn resolve_type(tok: u32, method: &MethodDesc, str_pool: &Vec<String>) -> BuiltinType {
+    let ctx = unsafe { method.ctx.as_ref() }.expect_il();
+    let (tag, idx) = get_tok_tag(tok);
+    let idx = idx as usize - 1;
+    let ty = match tag {
+        TokTag::TypeDef | TokTag::TypeRef => {
+            let ty = if let TokTag::TypeDef = tag {
+                NonNull::new(ctx.types[idx].as_ref() as *const Type as *mut Type).unwrap()
+            } else {
+                ctx.typerefs[idx]
+            };
+            if unsafe { ty.as_ref() }.ee_class.is_value {
+                BuiltinType::Value(ty)
+            } else {
+                BuiltinType::Class(ty)
+            }
+        }
+        TokTag::TypeSpec => {
+            if let IrSig::TypeSpec(spec) =
+                &ctx.ir_file.blob_heap[ctx.ir_file.typespec_tbl[idx].sig as usize]
+            {
+                instantiate(
+                    &BuiltinType::from_type_spec_sig(spec, ctx),
+                    class_args(method),
+                    &method.generic_args,
+                    str_pool,
+                )
+            } else {
+                unreachable!();
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    match &ty {
+        BuiltinType::Class(ty) | BuiltinType::Value(ty) => {
+            linker::fill_type_method_info(unsafe { ty.as_mut() }, str_pool)
+        }
+        _ => {}
+    }
+    ty
+}
+
+/// Resolve memberref whose parent is a typespec in the generic context of method
+///
+/// idx: index into memberref tbl, starts from 0
+fn resolve_inst_memberref(idx: usize, method: &MethodDesc, str_pool: &Vec<String>) -> MemberRef {
+    let ctx = unsafe { method.ctx.as_ref() }.expect_il();
+    let memberref = &ctx.ir_file.memberref_tbl[idx];
+    let (_, parent_idx) = memberref.get_parent();
+    let parent = match resolve_type(to_tok(parent_idx, TokTag::TypeSpec), method, str_pool) {
+        BuiltinType::Class(ty) | BuiltinType::Value(ty) => unsafe { ty.as_ref() },
+        _ => panic!("Parent of memberref should be a class or value type"),
+    };
+
+    match &ctx.ir_file.blob_heap[memberref.sig as usize] {
+        IrSig::Method(_, ps, _) => {
+            let sig = method_str_desc_from_ir(&ctx.ir_file, memberref.name, ps);
+            if let Some(m) = parent.ee_class.methods.get(&sig) {
+                let m_ref = unsafe { m.as_ref() };
+                linker::check_memberref_access(
+                    ctx,
+                    parent,
+                    ctx.str_heap[memberref.name as usize],
+                    m_ref.attrib.is(MethodAttribFlag::Priv),
+                    m_ref.attrib.is(MethodAttribFlag::Crate),
+                    str_pool,
+                );
+                MemberRef::Method(*m)
+            } else {
+                panic!("External symbol not found");
+            }
+        }
+        IrSig::Field(_) => {
+            if let Some(f) = parent
+                .ee_class
+                .fields
+                .get(&ctx.str_heap[memberref.name as usize])
+            {
+                let f_ref = unsafe { f.as_ref() };
+                linker::check_memberref_access(
+                    ctx,
+                    parent,
+                    ctx.str_heap[memberref.name as usize],
+                    f_ref.attrib.is(FieldAttribFlag::Priv),
+                    f_ref.attrib.is(FieldAttribFlag::Crate),
+                    str_pool,
+                );
+                MemberRef::Field(*f)
+            } else {
+                panic!("External symbol not found");
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Resolve MethodDef, MemberRef or MethodSpec tok in the generic context of method
+pub fn resolve_method(
+    tok: u32,
+    method: &MethodDesc,
+    str_pool: &Vec<String>,
+) -> NonNull<MethodDesc> {
+    let ctx = unsafe { method.ctx.as_ref() }.expect_il();
+    let (tag, idx) = get_tok_tag(tok);
+    let idx = idx as usize - 1;
+    match tag {
+        TokTag::MethodDef => {
+            NonNull::new(ctx.methods[idx].as_ref() as *const MethodDesc as *mut MethodDesc).unwrap()
+        }
+        TokTag::MemberRef => match &ctx.memberref[idx] {
+            MemberRef::Inst => resolve_inst_memberref(idx, method, str_pool).expect_method(),
+            memberref => memberref.expect_method(),
+        },
+        TokTag::MethodSpec => {
+            let spec = &ctx.ir_file.methodspec_tbl[idx];
+            let (method_tag, method_idx) = spec.get_method();
+            let generic_method = resolve_method(
+                to_tok(method_idx, method_tag.to_tok_tag()),
+                method,
+                str_pool,
+            );
+            if let IrSig::MethodSpec(args) = &ctx.ir_file.blob_heap[spec.instantiation as usize] {
+                let args = args
+                    .iter()
+                    .map(|arg| {
+                        instantiate(
+                            &BuiltinType::from_type_sig(arg, ctx),
+                            class_args(method),
+                            &method.generic_args,
+                            str_pool,
+                        )
+                    })
+                    .collect();
+                get_method_inst(unsafe { generic_method.as_mut() }, args, str_pool)
+            } else {
+                unreachable!();
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Resolve Field or MemberRef tok in the generic context of method
+pub fn resolve_field(tok: u32, method: &MethodDesc, str_pool: &Vec<String>) -> NonNull<Field> {
+    let ctx = unsafe { method.ctx.as_ref() }.expect_il();
+    let (tag, idx) = get_tok_tag(tok);
+    let idx = idx as usize - 1;
+    match tag {
+        TokTag::Field => {
+            NonNull::new(ctx.fields[idx].as_ref() as *const Field as *mut Field).unwrap()
+        }
+        TokTag::MemberRef => match &ctx.memberref[idx] {
+            MemberRef::Inst => resolve_inst_memberref(idx, method, str_pool).expect_field(),
+            memberref => memberref.expect_field(),
+        },
+        _ => unreachable!(),
+    }
+}
@@ -1,15 +1,19 @@
-use xir::attrib::{FieldAttribFlag, MethodAttribFlag};
+use xir::attrib::{FieldAttribFlag, MethodAttribFlag, TypeAttribFlag};
 use xir::member::MemberRefParent;
 use xir::sig::IrSig;
 use xir::ty::{ResolutionScope, TypeDefOrRef};
+use xir::util::path::{IItemPath, ItemPathBuf};
 
 use super::super::data::{
-    method_str_desc, BuiltinType, ILModule, Local, MemberRef, MethodImpl, Module, Type,
+    method_str_desc, BuiltinType, ILModule, Local, MemberRef, MethodDesc, MethodImpl, Module, Type,
     TypeInitState, REF_SIZE,
 };
 use super::super::util::ptr::NonNull;
+use super::generic::instantiate;
 
 use std::collections::HashMap;
+use std::process;
+use std::ptr;
 use std::rc::Rc;
 
 pub fn link_modref(module: NonNull<Module>, mods: &HashMap<usize, Box<Module>>) {
@@ -54,7 +58,7 @@ pub fn link_typeref(this_mod: NonNull<Module>) {
     }
 }
 
-/// fill ty.module and ty.extends
+/// fill ty.module, ty.extends and ty.interfaces
 pub fn link_type_info(this_mod: NonNull<Module>) {
     let this_mod_mut = unsafe { this_mod.as_mut().expect_il_mut() };
     let il_mod_ptr = NonNull::new(this_mod_mut as *mut ILModule).unwrap();
@@ -62,10 +66,11 @@ pub fn link_type_info(this_mod: NonNull<Module>) {
     let this_mod_mut1 = unsafe { this_mod.as_mut().expect_il_mut() };
     let this_mod_ref = unsafe { this_mod.as_ref().expect_il() };
 
-    for (ty, type_entry) in this_mod_mut
+    for (ty_i, (ty, type_entry)) in this_mod_mut
         .types
         .iter_mut()
         .zip(this_mod_ref.ir_file.typedef_tbl.iter())
+        .enumerate()
     {
         ty.module = il_mod_ptr;
         if let Some((parent_tag, parent_idx)) = type_entry.get_extends() {
@@ -75,6 +80,21 @@ pub fn link_type_info(this_mod: NonNull<Module>) {
                 TypeDefOrRef::TypeSpec => unimplemented!(),
             };
         }
+
+        for interface_impl in this_mod_ref
+            .ir_file
+            .interfaceimpl_tbl
+            .iter()
+            .filter(|i| i.class as usize == ty_i + 1)
+        {
+            let (interface_tag, interface_idx) = interface_impl.get_interface();
+            let interface_idx = interface_idx as usize - 1;
+            ty.interfaces.push(match interface_tag {
+                TypeDefOrRef::TypeDef => this_mod_mut1.types[interface_idx].as_mut() as *mut Type,
+                TypeDefOrRef::TypeRef => this_mod_ref.typerefs[interface_idx].as_ptr(),
+                TypeDefOrRef::TypeSpec => unimplemented!("Generic interface is not supported"),
+            });
+        }
     }
 }
 
@@ -86,14 +106,16 @@ pub fn calc_type_mem_layout(ty: &mut Type, str_pool: &Vec<String>) {
             "Cyclic loop detected in type dependency graph (in calc mem layout of {})",
             ty.fullname(str_pool)
         ),
-        TypeInitState::InitializingVtbl => return,
-        TypeInitState::Initialized => unreachable!(),
+        // generic instantiation may be created after its dependencies are initialized
+        TypeInitState::InitializingVtbl | TypeInitState::Initialized => return,
     }
 
     Rc::get_mut(&mut ty.ee_class).unwrap().init_state = TypeInitState::InitializingMemLayout;
 
     // check if type is a value type or enum
-    {
+    if ty.attrib.is(TypeAttribFlag::Enum) {
+        Rc::get_mut(&mut ty.ee_class).unwrap().is_value = true;
+    } else {
         let mut base_ptr = ty.extends;
         while let Some(base) = unsafe { base_ptr.as_ref() } {
             if str_pool[unsafe { base.module.as_ref() }.fullname] == "std"
@@ -105,6 +127,12 @@ pub fn calc_type_mem_layout(ty: &mut Type, str_pool: &Vec<String>) {
         }
     }
 
+    if ty.is_generic_def() {
+        // layout of generic type is determined in its instantiations
+        Rc::get_mut(&mut ty.ee_class).unwrap().init_state = TypeInitState::InitializingVtbl;
+        return;
+    }
+
     let mut instance_field_offset = 0;
     if let Some(base) = unsafe { ty.extends.as_mut() } {
         calc_type_mem_layout(base, str_pool);
@@ -120,7 +148,12 @@ pub fn calc_type_mem_layout(ty: &mut Type, str_pool: &Vec<String>) {
         if let IrSig::Field(field_ty) =
             &module.ir_file.blob_heap[module.ir_file.field_tbl[field.index].sig as usize]
         {
-            field.ty = BuiltinType::from_type_sig(field_ty, module);
+            field.ty = instantiate(
+                &BuiltinType::from_type_sig(field_ty, module),
+                &ty.generic_args,
+                &[],
+                str_pool,
+            );
         } else {
             unreachable!();
         }
@@ -130,13 +163,9 @@ pub fn calc_type_mem_layout(ty: &mut Type, str_pool: &Vec<String>) {
         if field.attrib.is(FieldAttribFlag::Static) {
             continue;
         } else {
-            match field.ty {
-                BuiltinType::Value(t) => {
-                    // ty.byte_size must be called after Type's memory layout is determined when ty is Value
-                    calc_type_mem_layout(unsafe { t.as_mut() }, str_pool);
-                }
-                BuiltinType::GenericInst(_, _, _) => todo!(),
-                _ => {}
+            if let BuiltinType::Value(t) = field.ty {
+                // ty.byte_size must be called after Type's memory layout is determined when ty is Value
+                calc_type_mem_layout(unsafe { t.as_mut() }, str_pool);
             }
             field.offset = instance_field_offset;
 
@@ -172,13 +201,9 @@ pub fn calc_type_mem_layout(ty: &mut Type, str_pool: &Vec<String>) {
         // determine field relative offset
         // no alignment
         if field.attrib.is(FieldAttribFlag::Static) {
-            match field.ty {
-                BuiltinType::Value(t) => {
-                    // ty.byte_size must be called after Type's memory layout is determined when ty is Value
-                    calc_type_mem_layout(unsafe { t.as_mut() }, str_pool);
-                }
-                BuiltinType::GenericInst(_, _, _) => todo!(),
-                _ => {}
+            if let BuiltinType::Value(t) = field.ty {
+                // ty.byte_size must be called after Type's memory layout is determined when ty is Value
+                calc_type_mem_layout(unsafe { t.as_mut() }, str_pool);
             }
             field.offset = static_field_offset;
             static_field_offset += field.ty.byte_size();
@@ -198,7 +223,7 @@ pub fn calc_type_mem_layout(ty: &mut Type, str_pool: &Vec<String>) {
 }
 
 /// alloc vtbl, calc param size and local size, must be called after all types' memory layouts have been determined
-pub fn fill_type_method_info(ty: &mut Type) {
+pub fn fill_type_method_info(ty: &mut Type, str_pool: &Vec<String>) {
     match ty.ee_class.init_state {
         TypeInitState::Uninitialized | TypeInitState::InitializingMemLayout => unreachable!(),
         TypeInitState::InitializingVtbl => {}
@@ -206,7 +231,7 @@ pub fn fill_type_method_info(ty: &mut Type) {
     }
 
     if let Some(base) = unsafe { ty.extends.as_mut() } {
-        fill_type_method_info(base);
+        fill_type_method_info(base, str_pool);
         // base methods
         for method_slot in base.vtbl.iter() {
             ty.vtbl.push(*method_slot);
@@ -215,56 +240,7 @@ pub fn fill_type_method_info(ty: &mut Type) {
 
     for (method_sig, method_ptr) in ty.ee_class.methods.iter() {
         let method = unsafe { method_ptr.as_mut() };
-        let module = unsafe { method.ctx.as_ref() }.expect_il();
-
-        // handle param info and return info
-        if let IrSig::Method(_, ps_ty, ret_ty) =
-            &module.ir_file.blob_heap[module.ir_file.method_tbl[method.index].sig as usize]
-        {
-            // calc method param size
-            assert_eq!(method.ps.len(), ps_ty.len());
-            let mut offset = if method.is_static() { 0 } else { REF_SIZE };
-            for (p, p_ty) in method.ps.iter_mut().zip(ps_ty.iter()) {
-                p.ty = BuiltinType::from_param(p_ty, module);
-                p.offset = offset;
-                // no alignment
-                offset += p.ty.byte_size();
-            }
-            method.ps_size = offset;
-            method.ret.ty = BuiltinType::from_ret(ret_ty, module);
-        } else {
-            unreachable!();
-        }
-
-        // handle method impl info
-        match &mut method.method_impl {
-            MethodImpl::IL(method_impl) => {
-                let body = &module.ir_file.codes[method_impl.index];
-                if body.locals != 0 {
-                    if let IrSig::LocalVar(local_types) = &module.ir_file.blob_heap
-                        [module.ir_file.stand_alone_sig_tbl[body.locals as usize - 1].sig as usize]
-                    {
-                        let mut local_size: usize = 0;
-                        for local_ty in local_types.iter() {
-                            let local_ty = BuiltinType::from_local(local_ty, module);
-                            let size = local_ty.byte_size();
-                            method_impl.locals.push(Local {
-                                ty: local_ty,
-                                offset: local_size,
-                            });
-                            local_size += size;
-                        }
-                        method_impl.locals_size = local_size;
-                    } else {
-                        unreachable!();
-                    }
-                }
-            }
-            MethodImpl::Native(_) => {}
-            MethodImpl::Runtime(_) => {
-                // maybe we can generate internal calls here
-            }
-        }
+        fill_method_info(method, str_pool);
 
         // alloc slot
         method.slot = ty.vtbl.len();
@@ -297,9 +273,181 @@ pub fn fill_type_method_info(ty: &mut Type) {
         }
     }
 
+    if !ty.is_interface() {
+        // interfaces of this type and its base types
+        let mut interfaces: Vec<*mut Type> = Vec::new();
+        let mut base_ptr = ty as *const Type;
+        while let Some(base) = unsafe { base_ptr.as_ref() } {
+            interfaces.extend(base.interfaces.iter());
+            base_ptr = base.extends;
+        }
+        for interface in interfaces.into_iter() {
+            fill_interface_map(ty, unsafe { interface.as_mut().unwrap() }, str_pool);
+        }
+    }
+
     Rc::get_mut(&mut ty.ee_class).unwrap().init_state = TypeInitState::Initialized;
 }
 
+/// map methods of interface and its base interfaces to their implementations in ty or base types of ty
+fn fill_interface_map(ty: &mut Type, interface: &mut Type, str_pool: &Vec<String>) {
+    fill_type_method_info(interface, str_pool);
+    for base_interface in interface.interfaces.iter() {
+        fill_interface_map(ty, unsafe { base_interface.as_mut().unwrap() }, str_pool);
+    }
+
+    for (method_sig, interface_method) in interface.ee_class.methods.iter() {
+        let interface_method_ref = unsafe { interface_method.as_ref() };
+        // search from the most derived type so that overrides are respected
+        let mut implementation = None;
+        let mut base_ptr = ty as *const Type;
+        while let Some(base) = unsafe { base_ptr.as_ref() } {
+            if let Some(candidate) = base.ee_class.methods.get(method_sig) {
+                let candidate_ref = unsafe { candidate.as_ref() };
+                if !candidate_ref.is_static() && candidate_ref.ret.ty == interface_method_ref.ret.ty
+                {
+                    implementation = Some(*candidate);
+                    break;
+                }
+            }
+            base_ptr = base.extends;
+        }
+
+        if let Some(implementation) = implementation {
+            ty.interface_map.insert(
+                interface_method.as_ptr() as *const MethodDesc,
+                implementation,
+            );
+        } else {
+            panic!(
+                "{} does not implement {} of interface {}",
+                ty.fullname(str_pool),
+                method_sig,
+                interface.fullname(str_pool)
+            );
+        }
+    }
+}
+
+/// fill param info, return info and locals of method
+///
+/// generic params are substituted by generic args of method and its parent,
+/// size info is not calculated if method is still open
+pub fn fill_method_info(method: &mut MethodDesc, str_pool: &Vec<String>) {
+    let module = unsafe { method.ctx.as_ref() }.expect_il();
+    let is_open = method.is_open();
+    let class_args = match unsafe { method.parent.as_ref() } {
+        Some(parent) => parent.generic_args.clone(),
+        None => vec![],
+    };
+    let method_args = method.generic_args.clone();
+
+    // handle param info and return info
+    if let IrSig::Method(_, ps_ty, ret_ty) =
+        &module.ir_file.blob_heap[module.ir_file.method_tbl[method.index].sig as usize]
+    {
+        // calc method param size
+        assert_eq!(method.ps.len(), ps_ty.len());
+        let mut offset = if method.is_static() { 0 } else { REF_SIZE };
+        for (p, p_ty) in method.ps.iter_mut().zip(ps_ty.iter()) {
+            p.ty = instantiate(
+                &BuiltinType::from_param(p_ty, module),
+                &class_args,
+                &method_args,
+                str_pool,
+            );
+            if is_open {
+                continue;
+            }
+            p.offset = offset;
+            // no alignment
+            offset += p.ty.byte_size();
+        }
+        method.ps_size = offset;
+        method.ret.ty = instantiate(
+            &BuiltinType::from_ret(ret_ty, module),
+            &class_args,
+            &method_args,
+            str_pool,
+        );
+    } else {
+        unreachable!();
+    }
+
+    if is_open {
+        // open method will never be executed
+        return;
+    }
+
+    // handle method impl info
+    match &mut method.method_impl {
+        MethodImpl::IL(method_impl) => {
+            let body = &module.ir_file.codes[method_impl.index];
+            if body.locals != 0 {
+                if let IrSig::LocalVar(local_types) = &module.ir_file.blob_heap
+                    [module.ir_file.stand_alone_sig_tbl[body.locals as usize - 1].sig as usize]
+                {
+                    let mut local_size: usize = 0;
+                    for local_ty in local_types.iter() {
+                        let local_ty = instantiate(
+                            &BuiltinType::from_local(local_ty, module),
+                            &class_args,
+                            &method_args,
+                            str_pool,
+                        );
+                        let size = local_ty.byte_size();
+                        method_impl.locals.push(Local {
+                            ty: local_ty,
+                            offset: local_size,
+                        });
+                        local_size += size;
+                    }
+                    method_impl.locals_size = local_size;
+                } else {
+                    unreachable!();
+                }
+            }
+        }
+        MethodImpl::Native(_) | MethodImpl::Abstract => {}
+        MethodImpl::Runtime(_) => {
+            // maybe we can generate internal calls here
+        }
+    }
+}
+
+/// Members referred by memberref are usually declared in other modules,
+/// where priv members are never accessible and pub(crate) members are accessible only in the same crate
+pub fn check_memberref_access(
+    module: &ILModule,
+    parent: &Type,
+    name: usize,
+    is_priv: bool,
+    is_crate: bool,
+    str_pool: &Vec<String>,
+) {
+    let parent_mod = unsafe { parent.module.as_ref() };
+    if ptr::eq(parent_mod, module) {
+        // e.g. member of a generic inst declared in this module
+        return;
+    }
+    let parent_mod_path = ItemPathBuf::from_str(parent_mod.fullname(str_pool));
+    let this_mod_path = ItemPathBuf::from_str(module.fullname(str_pool));
+    if is_priv || (is_crate && parent_mod_path.get_root_name() != this_mod_path.get_root_name()) {
+        // rejected like a module that fails verification
+        eprintln!(
+            "error: module {} is rejected by linker",
+            module.fullname(str_pool)
+        );
+        eprintln!(
+            "  {}::{} is not accessible in module {}",
+            parent.fullname(str_pool),
+            str_pool[name],
+            module.fullname(str_pool)
+        );
+        process::exit(1);
+    }
+}
+
 pub fn link_memberref(module: &mut ILModule, str_pool: &Vec<String>) {
     for memberref in module.ir_file.memberref_tbl.iter() {
         let name = module.str_heap[memberref.name as usize];
@@ -309,10 +457,16 @@ pub fn link_memberref(module: &mut ILModule, str_pool: &Vec<String>) {
         let (parent_tag, parent_idx) = memberref.get_parent();
         let parent_idx = parent_idx as usize - 1;
 
+        if let MemberRefParent::TypeSpec = parent_tag {
+            // parent might depend on generic args of caller
+            module.memberref.push(MemberRef::Inst);
+            continue;
+        }
+
         match sig {
             IrSig::Method(_, ps, ret) => {
                 // this member ref is a function
-                let ret_ty = BuiltinType::from_ret(ret, module);
+                let ret_ty = instantiate(&BuiltinType::from_ret(ret, module), &[], &[], str_pool);
 
                 match parent_tag {
                     MemberRefParent::TypeRef => {
@@ -323,7 +477,16 @@ pub fn link_memberref(module: &mut ILModule, str_pool: &Vec<String>) {
                             .collect();
                         let sig = method_str_desc(str_pool, name, &ps_ty);
                         if let Some(m) = parent.ee_class.methods.get(&sig) {
-                            if unsafe { &m.as_ref().ret.ty } == &ret_ty {
+                            let m_ref = unsafe { m.as_ref() };
+                            if m_ref.ret.ty == ret_ty {
+                                check_memberref_access(
+                                    module,
+                                    parent,
+                                    name,
+                                    m_ref.attrib.is(MethodAttribFlag::Priv),
+                                    m_ref.attrib.is(MethodAttribFlag::Crate),
+                                    str_pool,
+                                );
                                 found = true;
                                 module.memberref.push(MemberRef::Method(*m));
                             }
@@ -337,19 +500,28 @@ pub fn link_memberref(module: &mut ILModule, str_pool: &Vec<String>) {
             }
             IrSig::Field(f_sig) => {
                 // this member ref is a field
-                let sig = BuiltinType::from_type_sig(f_sig, module);
+                let sig = instantiate(
+                    &BuiltinType::from_type_sig(f_sig, module),
+                    &[],
+                    &[],
+                    str_pool,
+                );
                 match parent_tag {
                     MemberRefParent::TypeRef => {
                         // check if parent has this field
-                        if let Some(f) = unsafe {
-                            module.typerefs[parent_idx]
-                                .as_ref()
-                                .ee_class
-                                .fields
-                                .get(&name)
-                        } {
-                            if &sig == unsafe { &f.as_ref().ty } {
+                        let parent = unsafe { module.typerefs[parent_idx].as_ref() };
+                        if let Some(f) = parent.ee_class.fields.get(&name) {
+                            let f_ref = unsafe { f.as_ref() };
+                            if sig == f_ref.ty {
                                 // field found
+                                check_memberref_access(
+                                    module,
+                                    parent,
+                                    name,
+                                    f_ref.attrib.is(FieldAttribFlag::Priv),
+                                    f_ref.attrib.is(FieldAttribFlag::Crate),
+                                    str_pool,
+                                );
                                 module.memberref.push(MemberRef::Field(*f));
                                 found = true;
                             }
@@ -1,5 +1,8 @@
+mod generic;
 mod linker;
 
+pub use self::generic::{resolve_field, resolve_method, resolve_type};
+
 use super::data::*;
 use super::exec::internal_calls::register_internal_calls;
 use super::native::VMDll;
@@ -9,15 +12,18 @@ use super::VMCfg;
 
 use xir::attrib::*;
 use xir::file::*;
+use xir::generic::TypeOrMethodDef;
 use xir::member::MemberForwarded;
 use xir::sig::IrSig;
 use xir::util::path::{IItemPath, ItemPathBuf};
+use xir::verifier::verify;
 use xir::CCTOR_NAME;
 
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process;
 use std::ptr;
 
 pub fn load(
@@ -50,7 +56,7 @@ pub fn load(
     for module in loader.mem.mods.values_mut() {
         if let Module::IL(module) = module.as_mut() {
             for ty in module.types.iter_mut() {
-                linker::fill_type_method_info(ty);
+                linker::fill_type_method_info(ty, &loader.mem.str_pool);
             }
         }
     }
@@ -109,6 +115,12 @@ impl<'c> Loader<'c> {
     }
 
     fn load(&mut self, file: IrFile, root_dir: &Path) -> usize {
+        if let Err(e) = verify(&file) {
+            eprintln!("error: module {} is rejected by verifier", file.mod_name());
+            eprintln!("  {}", e);
+            process::exit(1);
+        }
+
         // Some external mods is not loadable modules but dlls
         let mut ext_mods_mask: Vec<bool> = vec![true; file.modref_tbl.len()];
         for implmap in file.implmap_tbl.iter() {
@@ -173,6 +185,12 @@ impl<'c> Loader<'c> {
                 let impl_flag = MethodImplAttrib::from(method_entry.impl_flag);
 
                 let method_impl = match impl_flag.code_ty() {
+                    MethodImplAttribCodeTypeFlag::IL
+                        if method_attrib.is(MethodAttribFlag::Abstract) =>
+                    {
+                        assert_eq!(method_entry.body, 0);
+                        MethodImpl::Abstract
+                    }
                     MethodImplAttribCodeTypeFlag::IL => {
                         assert_ne!(method_entry.body, 0);
                         // Currently virtual method is not implemented
@@ -257,6 +275,14 @@ impl<'c> Loader<'c> {
                     ps,
                     ps_size: 0,
                     method_impl,
+                    generic_params: Self::generic_params_of(
+                        &file,
+                        &str_heap,
+                        TypeOrMethodDef::MethodDef,
+                        method_i as u32 + 1,
+                    ),
+                    generic_args: vec![],
+                    generic_insts: HashMap::new(),
                 });
 
                 if name == self.cctor_name {
@@ -299,13 +325,19 @@ impl<'c> Loader<'c> {
                 field_i += 1;
             }
 
-            let ty = Box::new(Type::new(
+            let mut ty = Box::new(Type::new(
                 unsafe { NonNull::new_null() },
                 type_name,
                 type_attrib,
                 type_fields,
                 type_methods,
             ));
+            ty.generic_params = Self::generic_params_of(
+                &file,
+                &str_heap,
+                TypeOrMethodDef::TypeDef,
+                typedef_i as u32 + 1,
+            );
 
             for method in ty.ee_class.methods.values() {
                 unsafe { method.as_mut().parent = ty.as_ref() as *const Type };
@@ -317,6 +349,20 @@ impl<'c> Loader<'c> {
                     self.mem.str_class = ty.as_ref() as *const Type;
                 } else if self.mem.str_pool[ty.name] == "Array" {
                     self.mem.arr_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "Int32" {
+                    self.mem.i32_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "Exception" {
+                    self.mem.exception_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "NullReferenceException" {
+                    self.mem.null_reference_exception_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "IndexOutOfRangeException" {
+                    self.mem.index_out_of_range_exception_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "DivideByZeroException" {
+                    self.mem.divide_by_zero_exception_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "OverflowException" {
+                    self.mem.overflow_exception_class = ty.as_ref() as *const Type;
+                } else if self.mem.str_pool[ty.name] == "InvalidCastException" {
+                    self.mem.invalid_cast_exception_class = ty.as_ref() as *const Type;
                 }
             }
 
@@ -456,6 +502,22 @@ impl<'c> Loader<'c> {
         this_mod_fullname_addr
     }
 
+    /// names of generic params owned by a typedef or methoddef
+    fn generic_params_of(
+        file: &IrFile,
+        str_heap: &[usize],
+        owner: TypeOrMethodDef,
+        owner_idx: u32,
+    ) -> Vec<usize> {
+        let mut params: Vec<&xir::generic::GenericParam> = file
+            .generic_param_tbl
+            .iter()
+            .filter(|p| p.get_owner() == (owner, owner_idx))
+            .collect();
+        params.sort_by_key(|p| p.number);
+        params.iter().map(|p| str_heap[p.name as usize]).collect()
+    }
+
     /// fname is file name of mod. If mod is named "Foo", then fname is something like "Foo.xibc"
     fn find_mod<S: AsRef<OsStr>>(&self, fname: &S) -> Vec<PathBuf> {
         let os_fname = OsStr::new(fname);
@@ -15,4 +15,10 @@ pub struct VMCfg {
     /// external module root file or dir
     pub ext_paths: Vec<PathBuf>,
     pub diagnose: bool,
+    /// initial heap size in bytes
+    pub heap_size: usize,
+    pub max_heap_size: usize,
+    pub gc_stats: bool,
+    /// run entry with the interactive debugger
+    pub debug: bool,
 }
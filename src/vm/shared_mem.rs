@@ -1,5 +1,7 @@
 use super::data::{Module, Type, REF_SIZE};
-use super::heap::Heap;
+use super::heap::{Heap, RefVisitor};
+
+use xir::attrib::FieldAttribFlag;
 
 use std::collections::HashMap;
 use std::ptr;
@@ -18,15 +20,24 @@ pub struct SharedMem {
     pub empty_str_idx: usize,
     pub str_class: *const Type,
     pub arr_class: *const Type,
+    pub i32_class: *const Type,
+    pub exception_class: *const Type,
+    pub null_reference_exception_class: *const Type,
+    pub index_out_of_range_exception_class: *const Type,
+    pub divide_by_zero_exception_class: *const Type,
+    pub overflow_exception_class: *const Type,
+    pub invalid_cast_exception_class: *const Type,
 }
 
 /// default to be 1MB
-const HEAP_DEFAULT_SIZE: usize = 0x1 << 20;
+pub const HEAP_DEFAULT_SIZE: usize = 0x1 << 20;
+/// default to be 1GB
+pub const HEAP_DEFAULT_MAX_SIZE: usize = 0x1 << 30;
 
 impl SharedMem {
-    pub fn new() -> SharedMem {
+    pub fn new(heap_size: usize, max_heap_size: usize) -> SharedMem {
         SharedMem {
-            heap: Heap::new(HEAP_DEFAULT_SIZE),
+            heap: Heap::new(heap_size, max_heap_size),
             mods: HashMap::new(),
             str_pool: Vec::new(),
 
@@ -34,8 +45,32 @@ impl SharedMem {
             empty_str_idx: 0,
             str_class: ptr::null(),
             arr_class: ptr::null(),
+            i32_class: ptr::null(),
+            exception_class: ptr::null(),
+            null_reference_exception_class: ptr::null(),
+            index_out_of_range_exception_class: ptr::null(),
+            divide_by_zero_exception_class: ptr::null(),
+            overflow_exception_class: ptr::null(),
+            invalid_cast_exception_class: ptr::null(),
+        }
+    }
+}
+
+/// visit static fields of ty and all its generic instantiations
+fn visit_static_fields(ty: &Type, visitor: &mut dyn RefVisitor) {
+    if !ty.is_generic_def() {
+        for field in ty.ee_class.fields.values() {
+            let field = unsafe { field.as_ref() };
+            if field.attrib.is(FieldAttribFlag::Static) {
+                unsafe {
+                    visitor.visit_typed(field.addr, &field.ty);
+                }
+            }
         }
     }
+    for inst in ty.generic_insts.values() {
+        visit_static_fields(inst, visitor);
+    }
 }
 
 impl SharedMem {
@@ -54,6 +89,7 @@ impl SharedMem {
         let ty_ref = ty.as_ref().unwrap();
         self.heap.new_arr(
             self.arr_class,
+            ty,
             if ty_ref.ee_class.is_value {
                 ty_ref.basic_instance_size
             } else {
@@ -62,4 +98,25 @@ impl SharedMem {
             size,
         )
     }
+
+    /// Collect garbage
+    ///
+    /// Static fields of all loaded types are visited here,
+    /// other roots (e.g. thread stacks) must be visited by roots
+    pub fn gc<F>(&mut self, full: bool, mut roots: F)
+    where
+        F: FnMut(&mut dyn RefVisitor),
+    {
+        let mods = &self.mods;
+        self.heap.collect(full, |visitor| {
+            for module in mods.values() {
+                if let Module::IL(module) = module.as_ref() {
+                    for ty in module.types.iter() {
+                        visit_static_fields(ty, visitor);
+                    }
+                }
+            }
+            roots(visitor);
+        });
+    }
 }
@@ -3,6 +3,23 @@ use super::{Args, EvalStack, Locals, Slot};
 
 use std::mem::transmute;
 
+/// What to do when a finally handler ends
+pub enum EndFinally {
+    /// continue to leave to target offset
+    Leave(usize),
+    /// continue to propagate the exception and its stack trace when thrown
+    Throw(*mut u8, String),
+}
+
+/// A finally handler that is being executed
+pub struct FinallyCtx {
+    /// index of the finally clause
+    pub clause: usize,
+    /// offset of the leave or the inst that throws
+    pub from: usize,
+    pub then: EndFinally,
+}
+
 pub struct ActivationRecord<'m> {
     pub ip: usize,
     pub ret_addr: *mut Slot,
@@ -12,6 +29,8 @@ pub struct ActivationRecord<'m> {
     pub eval_stack: EvalStack,
     pub locals: Locals<'m>,
     pub args: Args<'m>,
+    /// finally handlers being executed, innermost is the last
+    pub finally_ctx: Vec<FinallyCtx>,
 }
 
 impl<'m> ActivationRecord<'m> {
@@ -48,4 +67,19 @@ impl<'m> ActivationRecord<'m> {
         ];
         i32::from_be_bytes(bytes)
     }
+
+    pub fn consume_i64(&mut self) -> i64 {
+        self.ip += 8;
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.insts[self.ip - 8..self.ip]);
+        i64::from_be_bytes(bytes)
+    }
+
+    pub fn consume_f32(&mut self) -> f32 {
+        f32::from_bits(self.consume_u32())
+    }
+
+    pub fn consume_f64(&mut self) -> f64 {
+        f64::from_bits(self.consume_i64() as u64)
+    }
 }
@@ -4,6 +4,7 @@ use std::mem::size_of;
 use std::ptr;
 
 use super::super::data::{BuiltinType, Type, TypedAddr};
+use super::super::heap::RefVisitor;
 
 #[derive(Clone)]
 #[repr(C)]
@@ -44,6 +45,30 @@ impl fmt::Display for SlotTag {
     }
 }
 
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        unsafe {
+            match self.tag {
+                SlotTag::I32 => write!(f, "{} {}", self.tag, self.data.i32_),
+                SlotTag::I64 => write!(f, "{} {}", self.tag, self.data.i64_),
+                SlotTag::INative => write!(f, "{} {}", self.tag, self.data.inative_),
+                SlotTag::F32 => write!(f, "{} {}", self.tag, self.data.f32_),
+                SlotTag::F64 => write!(f, "{} {}", self.tag, self.data.f64_),
+                SlotTag::Managed => write!(f, "{} {:p}", self.tag, self.data.ptr_),
+                SlotTag::Ref => {
+                    if self.data.ptr_.is_null() {
+                        write!(f, "{} null", self.tag)
+                    } else {
+                        write!(f, "{} {:p}", self.tag, self.data.ptr_)
+                    }
+                }
+                SlotTag::Value => write!(f, "{} ({} bytes)", self.tag, self.val_size()),
+                SlotTag::Uninit => write!(f, "{}", self.tag),
+            }
+        }
+    }
+}
+
 /// Not CLI standard, see I.12.1
 #[derive(Clone, Copy)]
 #[repr(C)]
@@ -73,8 +98,10 @@ impl Slot {
             BuiltinType::Bool
             | BuiltinType::Char
             | BuiltinType::U1
-            | BuiltinType::U4
             | BuiltinType::I1
+            | BuiltinType::U2
+            | BuiltinType::I2
+            | BuiltinType::U4
             | BuiltinType::I4 => Slot {
                 tag: SlotTag::I32,
                 data: SlotData { i32_: 0 },
@@ -83,9 +110,18 @@ impl Slot {
                 tag: SlotTag::I64,
                 data: SlotData { i64_: 0 },
             },
-            BuiltinType::UNative | BuiltinType::INative => unimplemented!(),
-            BuiltinType::R4 => unimplemented!(),
-            BuiltinType::R8 => unimplemented!(),
+            BuiltinType::UNative | BuiltinType::INative => Slot {
+                tag: SlotTag::INative,
+                data: SlotData { inative_: 0 },
+            },
+            BuiltinType::R4 => Slot {
+                tag: SlotTag::F32,
+                data: SlotData { f32_: 0.0 },
+            },
+            BuiltinType::R8 => Slot {
+                tag: SlotTag::F64,
+                data: SlotData { f64_: 0.0 },
+            },
             BuiltinType::Value(ty) => Slot {
                 tag: SlotTag::Value,
                 data: SlotData {
@@ -101,7 +137,9 @@ impl Slot {
                     ptr_: ptr::null_mut(),
                 },
             },
-            BuiltinType::GenericInst(_, _, _) => todo!(),
+            BuiltinType::GenericInst(_, _, _) | BuiltinType::Var(_) | BuiltinType::MVar(_) => {
+                unreachable!()
+            }
         }
     }
 
@@ -134,6 +172,58 @@ impl Slot {
         }
     }
 
+    /// int32 or native int (truncated), III.1.6 implicit argument coercion
+    pub fn expect_i32(&self) -> i32 {
+        unsafe {
+            match self.tag {
+                SlotTag::I32 => self.data.i32_,
+                SlotTag::INative => self.data.inative_ as i32,
+                _ => panic!("Expect int32 but found {}", self.tag),
+            }
+        }
+    }
+
+    pub fn expect_i64(&self) -> i64 {
+        if let SlotTag::I64 = self.tag {
+            unsafe { self.data.i64_ }
+        } else {
+            panic!("Expect int64 but found {}", self.tag);
+        }
+    }
+
+    /// native int or int32 (sign-extended), III.1.6 implicit argument coercion
+    pub fn expect_inative(&self) -> isize {
+        unsafe {
+            match self.tag {
+                SlotTag::INative => self.data.inative_,
+                SlotTag::I32 => self.data.i32_ as isize,
+                _ => panic!("Expect native int but found {}", self.tag),
+            }
+        }
+    }
+
+    /// float32 or float64 (rounded)
+    pub fn expect_f32(&self) -> f32 {
+        unsafe {
+            match self.tag {
+                SlotTag::F32 => self.data.f32_,
+                SlotTag::F64 => self.data.f64_ as f32,
+                _ => panic!("Expect float but found {}", self.tag),
+            }
+        }
+    }
+
+    /// float64 or float32
+    pub fn expect_f64(&self) -> f64 {
+        unsafe {
+            match self.tag {
+                SlotTag::F64 => self.data.f64_,
+                SlotTag::F32 => self.data.f32_ as f64,
+                _ => panic!("Expect float but found {}", self.tag),
+            }
+        }
+    }
+
     pub unsafe fn expect_ref(&self) -> *mut u8 {
         if let SlotTag::Ref = self.tag {
             self.data.ptr_ as *mut u8
@@ -177,13 +267,17 @@ impl Slot {
 pub struct EvalStack {
     data: Vec<u8>,
     size: usize,
+    /// max number of slots, from max_stack of method body
+    max_size: usize,
 }
 
 impl EvalStack {
-    pub fn new() -> EvalStack {
+    /// Space for max_size slots (excluding appendix of values) is preallocated
+    pub fn new(max_size: usize) -> EvalStack {
         EvalStack {
-            data: Vec::new(),
+            data: Vec::with_capacity(max_size * mem::size_of::<Slot>()),
             size: 0,
+            max_size,
         }
     }
 
@@ -191,6 +285,16 @@ impl EvalStack {
         self.size == 0
     }
 
+    /// number of slots
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.size = 0;
+    }
+
     /// return top slot body addr
     fn top_ptr(&self) -> *const Slot {
         &self.data[self.data.len() - mem::size_of::<Slot>()] as *const u8 as *const Slot
@@ -224,6 +328,31 @@ impl EvalStack {
         }
     }
 
+    /// visit all refs (including refs in values) on the stack
+    pub fn visit_refs(&mut self, visitor: &mut dyn RefVisitor) {
+        if self.is_empty() {
+            return;
+        }
+        let mut addr = self.top_mut_ptr();
+        for _ in 0..self.size {
+            let slot = unsafe { addr.as_mut().unwrap() };
+            let val_size = slot.val_size();
+            unsafe {
+                match slot.tag {
+                    SlotTag::Ref => visitor.visit_ref(&mut slot.data.ptr_ as *mut *mut u8),
+                    SlotTag::Managed => visitor.visit_managed(&mut slot.data.ptr_ as *mut *mut u8),
+                    SlotTag::Value => visitor.visit_fields(
+                        (addr as *mut u8).wrapping_sub(val_size),
+                        (slot.data.ptr_ as *const Type).as_ref().unwrap(),
+                    ),
+                    _ => {}
+                }
+            }
+            addr = addr.wrapping_sub(1);
+            addr = (addr as *mut u8).wrapping_sub(val_size) as *mut Slot;
+        }
+    }
+
     pub fn peek_mut(&mut self) -> Option<&mut Slot> {
         if self.is_empty() {
             None
@@ -339,6 +468,9 @@ impl EvalStack {
 
     /// Note: value space will not be allocated
     pub fn push_slot(&mut self, slot: Slot) {
+        if self.size == self.max_size {
+            panic!("Eval stack overflow. Max stack is {}", self.max_size);
+        }
         (0..mem::size_of::<Slot>())
             .into_iter()
             .for_each(|_| self.data.push(0));
@@ -367,6 +499,34 @@ impl EvalStack {
         });
     }
 
+    pub fn push_i64(&mut self, v: i64) {
+        self.push_slot(Slot {
+            tag: SlotTag::I64,
+            data: SlotData { i64_: v },
+        });
+    }
+
+    pub fn push_isize(&mut self, v: isize) {
+        self.push_slot(Slot {
+            tag: SlotTag::INative,
+            data: SlotData { inative_: v },
+        });
+    }
+
+    pub fn push_f32(&mut self, v: f32) {
+        self.push_slot(Slot {
+            tag: SlotTag::F32,
+            data: SlotData { f32_: v },
+        });
+    }
+
+    pub fn push_f64(&mut self, v: f64) {
+        self.push_slot(Slot {
+            tag: SlotTag::F64,
+            data: SlotData { f64_: v },
+        });
+    }
+
     pub fn push_usize(&mut self, v: usize) {
         self.push_slot(Slot {
             tag: SlotTag::INative,
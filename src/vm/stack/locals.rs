@@ -1,7 +1,8 @@
 use super::super::data::{BuiltinType, Local, MethodDesc, Param, TypedAddr};
-use super::{EvalStack, Slot, SlotTag};
+use super::super::heap::RefVisitor;
+use super::{EvalStack, Slot};
 
-use std::mem;
+use std::ptr;
 
 pub trait ILocals {
     fn load(&self, i: usize, stack: &mut EvalStack);
@@ -10,63 +11,79 @@ pub trait ILocals {
     fn store_slot(&mut self, i: usize, slot: Slot);
 }
 
-// load from addr into stack
-unsafe fn load(ty: &BuiltinType, addr: *const u8, stack: &mut EvalStack) {
+/// load from addr into stack
+///
+/// Offsets of locals, args and fields are packed, so addr may be unaligned
+pub unsafe fn load(ty: &BuiltinType, addr: *const u8, stack: &mut EvalStack) {
     match ty {
         BuiltinType::Void => panic!("Cannot store void"),
-        BuiltinType::U1 | BuiltinType::Bool => {
-            stack.push_i32(mem::transmute::<u32, i32>(*addr as u32));
+        BuiltinType::U1 | BuiltinType::Bool => stack.push_i32(*addr as i32),
+        BuiltinType::I1 => stack.push_i32(*(addr as *const i8) as i32),
+        BuiltinType::U2 | BuiltinType::Char => {
+            stack.push_i32(ptr::read_unaligned(addr as *const u16) as i32)
         }
-        BuiltinType::Char => unimplemented!(),
-        BuiltinType::I1 => unimplemented!(),
-        BuiltinType::U4 => unimplemented!(),
-        BuiltinType::I4 => stack.push_i32(*(addr as *const i32)),
-        BuiltinType::U8 => unimplemented!(),
-        BuiltinType::I8 => unimplemented!(),
-        BuiltinType::UNative => unimplemented!(),
-        BuiltinType::INative => unimplemented!(),
-        BuiltinType::R4 => unimplemented!(),
-        BuiltinType::R8 => unimplemented!(),
+        BuiltinType::I2 => stack.push_i32(ptr::read_unaligned(addr as *const i16) as i32),
+        BuiltinType::U4 | BuiltinType::I4 => {
+            stack.push_i32(ptr::read_unaligned(addr as *const i32))
+        }
+        BuiltinType::U8 | BuiltinType::I8 => {
+            stack.push_i64(ptr::read_unaligned(addr as *const i64))
+        }
+        BuiltinType::UNative | BuiltinType::INative => {
+            stack.push_isize(ptr::read_unaligned(addr as *const isize))
+        }
+        BuiltinType::R4 => stack.push_f32(ptr::read_unaligned(addr as *const f32)),
+        BuiltinType::R8 => stack.push_f64(ptr::read_unaligned(addr as *const f64)),
         BuiltinType::String
         | BuiltinType::Class(_)
         | BuiltinType::ByRef(_)
-        | BuiltinType::SZArray(_) => stack.push_ptr(*(addr as *const *mut u8)),
-        BuiltinType::Value(_) => unimplemented!(),
-        BuiltinType::GenericInst(_, _, _) => todo!(),
-        BuiltinType::Unk => unreachable!(),
+        | BuiltinType::SZArray(_) => stack.push_ptr(ptr::read_unaligned(addr as *const *mut u8)),
+        BuiltinType::Value(ty) => {
+            stack.alloc_value(ty.as_ref(), addr);
+        }
+        BuiltinType::GenericInst(_, _, _)
+        | BuiltinType::Var(_)
+        | BuiltinType::MVar(_)
+        | BuiltinType::Unk => unreachable!(),
     }
 }
 
-// store slot into addr
-unsafe fn store_slot(ty: &BuiltinType, addr: *mut u8, slot: Slot) {
+/// store slot into addr, ints are truncated to the size of ty
+pub unsafe fn store_slot(ty: &BuiltinType, addr: *mut u8, slot: Slot) {
     match ty {
         BuiltinType::Void => panic!("Cannot store void"),
         BuiltinType::Unk => unreachable!(),
-        BuiltinType::Bool | BuiltinType::U1 => {
-            slot.expect(SlotTag::I32);
-            *addr = mem::transmute::<i32, u32>(slot.data.i32_) as u8;
+        BuiltinType::Bool | BuiltinType::U1 | BuiltinType::I1 => {
+            *addr = slot.expect_i32() as u8;
+        }
+        BuiltinType::Char | BuiltinType::U2 | BuiltinType::I2 => {
+            ptr::write_unaligned(addr as *mut u16, slot.expect_i32() as u16);
         }
-        BuiltinType::Char => unimplemented!(),
-        BuiltinType::I1 => unimplemented!(),
-        BuiltinType::U4 => unimplemented!(),
-        BuiltinType::I4 => {
-            slot.expect(SlotTag::I32);
-            *(addr as *mut i32) = slot.data.i32_;
+        BuiltinType::U4 | BuiltinType::I4 => {
+            ptr::write_unaligned(addr as *mut i32, slot.expect_i32());
+        }
+        BuiltinType::U8 | BuiltinType::I8 => {
+            ptr::write_unaligned(addr as *mut i64, slot.expect_i64());
+        }
+        BuiltinType::UNative | BuiltinType::INative => {
+            ptr::write_unaligned(addr as *mut isize, slot.expect_inative());
+        }
+        BuiltinType::R4 => {
+            ptr::write_unaligned(addr as *mut f32, slot.expect_f32());
+        }
+        BuiltinType::R8 => {
+            ptr::write_unaligned(addr as *mut f64, slot.expect_f64());
         }
-        BuiltinType::U8 => unimplemented!(),
-        BuiltinType::I8 => unimplemented!(),
-        BuiltinType::UNative => unimplemented!(),
-        BuiltinType::INative => unimplemented!(),
-        BuiltinType::R4 => unimplemented!(),
-        BuiltinType::R8 => unimplemented!(),
         BuiltinType::String
         | BuiltinType::Class(_)
         | BuiltinType::ByRef(_)
         | BuiltinType::SZArray(_) => {
-            *(addr as *mut *mut u8) = slot.expect_ref();
+            ptr::write_unaligned(addr as *mut *mut u8, slot.expect_ref());
         }
         BuiltinType::Value(_) => unimplemented!(),
-        BuiltinType::GenericInst(_, _, _) => todo!(),
+        BuiltinType::GenericInst(_, _, _) | BuiltinType::Var(_) | BuiltinType::MVar(_) => {
+            unreachable!()
+        }
     }
 }
 
@@ -84,6 +101,24 @@ impl<'m> Locals<'m> {
             map: &method_impl.locals,
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// type and addr of local var i
+    pub fn get(&self, i: usize) -> (&'m BuiltinType, *const u8) {
+        (&self.map[i].ty, &self.data[self.map[i].offset] as *const u8)
+    }
+
+    /// visit all refs in local vars
+    pub fn visit_refs(&mut self, visitor: &mut dyn RefVisitor) {
+        for local in self.map.iter() {
+            unsafe {
+                visitor.visit_typed(self.data.as_mut_ptr().wrapping_add(local.offset), &local.ty);
+            }
+        }
+    }
 }
 
 impl<'m> ILocals for Locals<'m> {
@@ -142,13 +177,31 @@ impl<'m> Args<'m> {
         }
     }
 
+    /// visit self ptr and all refs in args
+    pub fn visit_refs(&mut self, visitor: &mut dyn RefVisitor) {
+        unsafe {
+            if let Some(self_ptr) = self.get_self_mut() {
+                // self may be a ref or a managed ptr to value
+                visitor.visit_managed(self_ptr as *mut *mut u8);
+            }
+            for p in self.map.iter() {
+                visitor.visit_typed(self.data.as_mut_ptr().wrapping_add(p.offset), &p.ty);
+            }
+        }
+    }
+
     pub fn as_ptr(&self) -> *const u8 {
         self.data.as_ptr()
     }
 
+    /// type and addr of param i, self is not included
+    pub fn get(&self, i: usize) -> (&'m BuiltinType, *const u8) {
+        (&self.map[i].ty, &self.data[self.map[i].offset] as *const u8)
+    }
+
     pub fn get_self(&self) -> Option<*mut u8> {
         if self.has_self {
-            Some(unsafe { *(&self.data[0] as *const u8 as *const *mut u8) })
+            Some(unsafe { ptr::read_unaligned(&self.data[0] as *const u8 as *const *mut u8) })
         } else {
             return None;
         }
@@ -209,7 +262,8 @@ impl<'m> ILocals for Args<'m> {
         let i = if self.has_self {
             if i == 0 {
                 // load self
-                stack.push_ptr(unsafe { *(self.data.as_ptr() as *const *mut u8) });
+                stack
+                    .push_ptr(unsafe { ptr::read_unaligned(self.data.as_ptr() as *const *mut u8) });
                 return;
             }
             i - 1
@@ -230,7 +284,8 @@ impl<'m> ILocals for Args<'m> {
         let i = if self.has_self {
             if i == 0 {
                 // load self
-                stack.push_ptr(unsafe { *(self.data.as_ptr() as *const *mut u8) });
+                stack
+                    .push_ptr(unsafe { ptr::read_unaligned(self.data.as_ptr() as *const *mut u8) });
                 return;
             }
             i - 1
@@ -266,7 +321,10 @@ impl<'m> ILocals for Args<'m> {
             ArgType::ArgSelf => {
                 // store self
                 unsafe {
-                    *(&mut self.data[0] as *mut u8 as *mut *mut u8) = slot.expect_ref_or_ptr();
+                    ptr::write_unaligned(
+                        &mut self.data[0] as *mut u8 as *mut *mut u8,
+                        slot.expect_ref_or_ptr(),
+                    );
                 }
             }
             ArgType::MethodArg(i) => {
@@ -2,6 +2,6 @@ mod ar;
 mod eval_stack;
 mod locals;
 
-pub use ar::ActivationRecord;
+pub use ar::{ActivationRecord, EndFinally, FinallyCtx};
 pub use eval_stack::{EvalStack, Slot, SlotData, SlotTag};
-pub use locals::{Args, ILocals, Locals};
+pub use locals::{load, store_slot, Args, ILocals, Locals};
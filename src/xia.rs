@@ -0,0 +1,62 @@
+extern crate clap;
+extern crate xir;
+
+use clap::{App, Arg};
+
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use xir::parser::parse;
+
+fn main() {
+    let matches = App::new("xia")
+        .version("0.4.0")
+        .author("Xi")
+        .about("Hello world! This is xia")
+        .arg(
+            Arg::with_name("input")
+                .help("Input .xir file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("Output file. Default to be <input> with extension .xibc if not specified")
+                .short("o")
+                .long("output")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let input = PathBuf::from(matches.value_of("input").unwrap());
+    let output = if let Some(output) = matches.value_of("output") {
+        PathBuf::from(output)
+    } else {
+        let mut output = input.clone();
+        output.set_extension("xibc");
+        output
+    };
+
+    let file = match parse(&input) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("error: aborting due to previous error");
+            process::exit(1);
+        }
+    };
+
+    if let Some(dir) = output.parent() {
+        if !dir.as_os_str().is_empty() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                eprintln!("error: cannot create {}: {}", dir.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Err(e) = fs::write(&output, file.to_binary()) {
+        eprintln!("error: cannot write {}: {}", output.display(), e);
+        process::exit(1);
+    }
+}
@@ -57,6 +57,18 @@ fn main() {
                     .short("O")
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("features")
+                    .help("Comma separated features enabled for #[cfg(feature = \"x\")]")
+                    .short("f")
+                    .long("features")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("debug")
+                    .help("Generate debug info")
+                    .short("g"),
+            )
             .arg(
                 Arg::with_name("v")
                     .long("verbose")
@@ -83,6 +95,14 @@ fn main() {
         } else {
             0
         };
+        let features = matches
+            .value_of("features")
+            .unwrap_or("")
+            .split(',')
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+            .map(|f| f.to_owned())
+            .collect();
 
         if !NAME_RULE.is_match(&root_fname) {
             panic!("Invalid root file name {}", root_fname);
@@ -108,6 +128,8 @@ fn main() {
                 root_dir
             },
             optim,
+            features,
+            debug_info: matches.is_present("debug"),
             verbose: matches.occurrences_of("v") as usize,
         }
     };
@@ -124,7 +146,8 @@ fn main() {
             .to_owned();
         std_path.push("std/std.xibc");
         // TODO: what if std is already present in the ext_paths?
-        cfg.ext_paths.push(std_path.canonicalize().unwrap());
+        // other external crates depend on std so it is loaded first
+        cfg.ext_paths.insert(0, std_path.canonicalize().unwrap());
     }
 
     println!("External modules: ");
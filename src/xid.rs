@@ -0,0 +1,59 @@
+extern crate clap;
+extern crate xir;
+
+use clap::{App, Arg};
+
+use std::fs;
+use std::path::PathBuf;
+
+use xir::file::IrFile;
+
+fn main() {
+    let matches = App::new("xid")
+        .version("0.4.0")
+        .author("Xi")
+        .about("Hello world! This is xid")
+        .arg(
+            Arg::with_name("input")
+                .help("Input .xibc file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("class")
+                .help("Only display the class with this name or fullname")
+                .short("c")
+                .long("class")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("method")
+                .help("Only display methods with this name")
+                .short("m")
+                .long("method")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tables")
+                .help("Display raw metadata tables and heaps with their indices instead of IR")
+                .short("t")
+                .long("tables"),
+        )
+        .arg(
+            Arg::with_name("show_tok")
+                .help("Annotate tokens in IL with their raw values")
+                .short("k")
+                .long("show-tok"),
+        )
+        .get_matches();
+
+    let input = PathBuf::from(matches.value_of("input").unwrap());
+    let file = IrFile::from_binary(Box::new(fs::File::open(&input).unwrap()));
+
+    let mut display = file.display();
+    display.class = matches.value_of("class").map(String::from);
+    display.method = matches.value_of("method").map(String::from);
+    display.tables = matches.is_present("tables");
+    display.show_tok = matches.is_present("show_tok");
+    println!("{}", display);
+}
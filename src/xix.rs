@@ -9,11 +9,12 @@ use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::process;
 use std::time::SystemTime;
 
-use vm::exec::TExecutor;
+use vm::exec::{Debugger, TExecutor, UNHANDLED_EXCEPTION_EXIT_CODE};
 use vm::loader::load;
-use vm::shared_mem::SharedMem;
+use vm::shared_mem::{SharedMem, HEAP_DEFAULT_MAX_SIZE, HEAP_DEFAULT_SIZE};
 use vm::VMCfg;
 
 fn main() {
@@ -35,6 +36,30 @@ fn main() {
                     .long("import")
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("heap_size")
+                    .help("Initial heap size, K/M/G suffix is supported. Default: 1M")
+                    .long("heap-size")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("max_heap_size")
+                    .help("Max heap size, K/M/G suffix is supported. Default: 1G")
+                    .long("max-heap-size")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("gc_stats")
+                    .long("gc-stats")
+                    .help("Show GC statistics after execution")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("debug")
+                    .long("debug")
+                    .help("Debug the entry method interactively, commands are read from stdin")
+                    .takes_value(false),
+            )
             .arg(
                 Arg::with_name("diagnose")
                     .short("d")
@@ -75,11 +100,19 @@ fn main() {
                 entry_root,
                 ext_paths: ext_paths.into_iter().collect::<Vec<PathBuf>>(),
                 diagnose: matches.is_present("diagnose"),
+                heap_size: matches
+                    .value_of("heap_size")
+                    .map_or(HEAP_DEFAULT_SIZE, parse_size),
+                max_heap_size: matches
+                    .value_of("max_heap_size")
+                    .map_or(HEAP_DEFAULT_MAX_SIZE, parse_size),
+                gc_stats: matches.is_present("gc_stats"),
+                debug: matches.is_present("debug"),
             },
         )
     };
 
-    let mut m = SharedMem::new();
+    let mut m = SharedMem::new(cfg.heap_size, cfg.max_heap_size);
 
     // loading
     let start_time = SystemTime::now();
@@ -93,7 +126,10 @@ fn main() {
     let start_time = SystemTime::now();
     for static_init in static_inits.into_iter() {
         let mut executor = TExecutor::new(static_init);
-        executor.run(&mut m);
+        if executor.run(&mut m) == UNHANDLED_EXCEPTION_EXIT_CODE {
+            eprintln!("error: static initializer is terminated by an unhandled exception");
+            process::exit(1);
+        }
     }
     let static_exec_time = SystemTime::now()
         .duration_since(start_time)
@@ -102,6 +138,9 @@ fn main() {
 
     let start_time = SystemTime::now();
     let mut executor = TExecutor::new(entry);
+    if cfg.debug {
+        executor.attach_debugger(Debugger::new());
+    }
     let ret = executor.run(&mut m);
     let main_exec_time = SystemTime::now()
         .duration_since(start_time)
@@ -115,4 +154,27 @@ fn main() {
         println!("Static init execution time: {}", static_exec_time);
         println!("Main execution time: {}", main_exec_time);
     }
+
+    if cfg.gc_stats {
+        println!("=============== GC Stats =================");
+        println!("{}", m.heap.stats);
+    }
+
+    if ret == UNHANDLED_EXCEPTION_EXIT_CODE {
+        process::exit(1);
+    }
+}
+
+/// parse size like "512K", "16M", "1G"
+fn parse_size(s: &str) -> usize {
+    let (num, unit) = match s.chars().last() {
+        Some('K') | Some('k') => (&s[..s.len() - 1], 1 << 10),
+        Some('M') | Some('m') => (&s[..s.len() - 1], 1 << 20),
+        Some('G') | Some('g') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    match num.parse::<usize>() {
+        Ok(num) => num * unit,
+        Err(_) => panic!("Invalid size {}", s),
+    }
 }
@@ -0,0 +1,21 @@
+# Check that debugger sessions scripted in tests/debug/*/cmds.txt produce expected.out
+# Stdlib should have been compiled to target/std/ (see demo.sh)
+# Paths of source files are made relative to the repo root and addresses of objects are masked
+
+cargo build --release
+
+failed=0
+for dir in ./tests/debug/*/; do
+    name=$(basename $dir)
+    rm -rf ./tests-build/debug/$name
+    ./target/release/xic $dir/main.xi -g -o ./tests-build/debug/$name > /dev/null
+    ./target/release/xix ./tests-build/debug/$name/$name.xibc --debug < $dir/cmds.txt 2>&1 \
+        | sed -e "s|$(pwd)/||g" -e "s/0x[0-9a-f]\+/<addr>/g" > ./tests-build/debug/$name.out
+    if diff $dir/expected.out ./tests-build/debug/$name.out; then
+        echo "$name: ok"
+    else
+        echo "$name: output differs from expected.out"
+        failed=1
+    fi
+done
+exit $failed
@@ -0,0 +1,42 @@
+# Check that programs compiled with -O0 and -O1 produce expected.out
+# -O1 build also has debug info (-g), which must not change the behavior
+# Stdlib should have been compiled to target/std/ (see demo.sh)
+# Programs are run by both release and debug builds of xix. Debug build checks alignment of pointers and overflows
+# Exit code of xix must be the one in expected.code (0 if absent). Stderr is not compared since stack traces differ in -O0 and -O1
+
+cargo build --release
+cargo build
+
+failed=0
+for dir in ./tests/optim/*/; do
+    name=$(basename $dir)
+    rm -rf ./tests-build/optim/$name
+    ./target/release/xic $dir/main.xi -O0 -o ./tests-build/optim/$name/O0 > /dev/null
+    ./target/release/xic $dir/main.xi -O1 -g -o ./tests-build/optim/$name/O1 > /dev/null
+    expected_code=0
+    if [ -f $dir/expected.code ]; then
+        expected_code=$(cat $dir/expected.code)
+    fi
+    ok=1
+    for profile in release debug; do
+        for opt in O0 O1; do
+            out=./tests-build/optim/$name/$opt.$profile.out
+            ./target/$profile/xix ./tests-build/optim/$name/$opt/$name.xibc > $out 2> $out.err
+            code=$?
+            if ! diff $dir/expected.out $out; then
+                echo "$name: -$opt output of $profile xix differs from expected.out"
+                ok=0
+                failed=1
+            fi
+            if [ $code != $expected_code ]; then
+                echo "$name: -$opt $profile xix exits with $code, expected $expected_code"
+                ok=0
+                failed=1
+            fi
+        done
+    done
+    if [ $ok = 1 ]; then
+        echo "$name: ok"
+    fi
+done
+exit $failed
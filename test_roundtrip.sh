@@ -0,0 +1,45 @@
+# Check that disassembling programs in tests/optim with xid and reassembling them with xia
+# does not change their behavior
+# -O1 build also has debug info (-g), which must survive the round trip: disassembling the reassembled
+# module must give the same IR, including .document, .scope and .line
+# Stdlib should have been compiled to target/std/ (see demo.sh)
+
+cargo build --release
+
+failed=0
+for dir in ./tests/optim/*/; do
+    name=$(basename $dir)
+    for opt in O0 O1; do
+        out=./tests-build/roundtrip/$name/$opt
+        rm -rf $out
+        if [ $opt = O1 ]; then
+            flags="-O1 -g"
+        else
+            flags="-O0"
+        fi
+        ./target/release/xic $dir/main.xi $flags -o $out/orig > /dev/null
+        ok=1
+        for bc in $out/orig/*.xibc; do
+            mod=$(basename $bc .xibc)
+            ./target/release/xid $bc > $out/$mod.xir
+            ./target/release/xia $out/$mod.xir -o $out/asm/$mod.xibc
+            ./target/release/xid $out/asm/$mod.xibc > $out/$mod.asm.xir
+            if ! diff $out/$mod.xir $out/$mod.asm.xir; then
+                echo "$name -$opt: IR of $mod differs after xid and xia"
+                ok=0
+            fi
+        done
+        ./target/release/xix $out/orig/$name.xibc > $out/orig.out 2>&1
+        ./target/release/xix $out/asm/$name.xibc > $out/asm.out 2>&1
+        if ! diff $out/orig.out $out/asm.out; then
+            echo "$name -$opt: output differs after xid and xia"
+            ok=0
+        fi
+        if [ $ok = 1 ]; then
+            echo "$name -$opt: ok"
+        else
+            failed=1
+        fi
+    done
+done
+exit $failed